@@ -0,0 +1,349 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+use syn::spanned::Spanned;
+use syn::visit::{self, Visit};
+use syn::{Block, Expr, ExprMacro, FnArg, Item, ItemFn, Lit, Stmt};
+
+use crate::cfg_builder::builder::CfgBuilder;
+use crate::config::AnnotationRole;
+
+// An annotation written where the builder doesn't read it as intended, with the way to fix it
+#[derive(Debug, Clone)]
+pub struct AnnotationWarning {
+    pub location: String, // 'file:line:column' of the macro
+    pub function: String,
+    pub annotation: String,
+    pub message: String,
+    pub suggestion: String,
+}
+
+impl fmt::Display for AnnotationWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {} in {}: {}\n  help: {}", self.location, self.annotation, self.function, self.message, self.suggestion)
+    }
+}
+
+// Blocks directly nested in a statement, with the reason they never run ('if false')
+#[derive(Default)]
+struct NestedBlocks<'a> {
+    blocks: Vec<(&'a Block, Option<String>)>,
+    loop_bodies: Vec<&'a Block>, // where a 'decreases!' is the measure of the loop
+}
+
+impl<'ast> Visit<'ast> for NestedBlocks<'ast> {
+    fn visit_block(&mut self, i: &'ast Block) {
+        self.blocks.push((i, None));
+    }
+
+    fn visit_expr_if(&mut self, i: &'ast syn::ExprIf) {
+        match &*i.cond {
+            Expr::Lit(syn::ExprLit { lit: Lit::Bool(cond), .. }) if !cond.value => {
+                let line = i.if_token.span.start().line;
+                self.blocks.push((&i.then_branch, Some(format!("inside the 'if false' at line {}", line))));
+                if let Some((_, else_branch)) = &i.else_branch {
+                    self.visit_expr(else_branch);
+                }
+            },
+            _ => visit::visit_expr_if(self, i),
+        }
+    }
+
+    fn visit_expr_while(&mut self, i: &'ast syn::ExprWhile) {
+        self.loop_bodies.push(&i.body);
+        visit::visit_expr_while(self, i);
+    }
+
+    fn visit_expr_for_loop(&mut self, i: &'ast syn::ExprForLoop) {
+        self.loop_bodies.push(&i.body);
+        visit::visit_expr_for_loop(self, i);
+    }
+
+    fn visit_expr_loop(&mut self, i: &'ast syn::ExprLoop) {
+        self.loop_bodies.push(&i.body);
+        visit::visit_expr_loop(self, i);
+    }
+
+    // Nested functions are linted on their own
+    fn visit_item(&mut self, _: &'ast Item) {}
+}
+
+// Variables declared in a function body with their line, and the lines of its 'return's
+#[derive(Default)]
+struct BodyBindings {
+    locals: BTreeMap<String, usize>, // first declaration
+    returns: Vec<usize>,
+}
+
+impl<'ast> Visit<'ast> for BodyBindings {
+    fn visit_local(&mut self, i: &'ast syn::Local) {
+        for name in CfgBuilder::pattern_bindings(&i.pat) {
+            self.locals.entry(name).or_insert(i.span().start().line);
+        }
+        visit::visit_local(self, i);
+    }
+
+    fn visit_expr_for_loop(&mut self, i: &'ast syn::ExprForLoop) {
+        for name in CfgBuilder::pattern_bindings(&i.pat) {
+            self.locals.entry(name).or_insert(i.span().start().line);
+        }
+        visit::visit_expr_for_loop(self, i);
+    }
+
+    fn visit_expr_return(&mut self, i: &'ast syn::ExprReturn) {
+        self.returns.push(i.span().start().line);
+        visit::visit_expr_return(self, i);
+    }
+
+    // The returns of closures and nested functions are not the function's
+    fn visit_expr_closure(&mut self, _: &'ast syn::ExprClosure) {}
+
+    fn visit_item(&mut self, _: &'ast Item) {}
+}
+
+// Variables a condition reads, in order: not the called functions, the methods, the arguments of 'old(..)'
+// or the parameters of closures ('forall(|i| ..)')
+#[derive(Default)]
+struct ConditionNames {
+    names: Vec<String>,
+    bound: HashSet<String>,
+}
+
+impl<'ast> Visit<'ast> for ConditionNames {
+    fn visit_expr_path(&mut self, i: &'ast syn::ExprPath) {
+        if let Some(ident) = i.path.get_ident() {
+            let name = ident.to_string();
+            if i.qself.is_none() && !self.bound.contains(&name) && !self.names.contains(&name) {
+                self.names.push(name);
+            }
+        }
+    }
+
+    fn visit_expr_call(&mut self, i: &'ast syn::ExprCall) {
+        if matches!(&*i.func, Expr::Path(path) if path.path.is_ident("old")) {
+            return;
+        }
+        i.args.iter().for_each(|arg| self.visit_expr(arg));
+    }
+
+    fn visit_expr_closure(&mut self, i: &'ast syn::ExprClosure) {
+        let bound: Vec<String> = i.inputs.iter().flat_map(CfgBuilder::pattern_bindings).collect();
+        let added: Vec<String> = bound.into_iter().filter(|name| self.bound.insert(name.clone())).collect();
+        self.visit_expr(&i.body);
+        for name in added {
+            self.bound.remove(&name);
+        }
+    }
+
+    fn visit_expr_macro(&mut self, i: &'ast ExprMacro) {
+        if !i.mac.path.is_ident("old") {
+            if let Ok(expr) = syn::parse2::<Expr>(i.mac.tokens.clone()) {
+                self.visit_expr(&expr);
+            }
+        }
+    }
+}
+
+impl CfgBuilder {
+    // Annotations the builder would not read as written: 'pre!'/'post!' after the first statement of the body or
+    // in a nested block, 'invariant!' not right before a loop, annotations in code that never runs, and
+    // 'post!' naming variables that are not live at the returns
+    pub fn lint_annotations(&self, file: &str, items: &[Item]) -> Vec<AnnotationWarning> {
+        let mut warnings = Vec::new();
+        // Logic functions are definitions for the specs, their 'decreases!' is the measure of the recursion
+        for (name, item_fn) in Self::spec_functions(items, &[]).into_iter().filter(|(_, item_fn)| !Self::is_logic_function(item_fn)) {
+            self.lint_function(file, &name, &item_fn, &mut warnings);
+        }
+        warnings
+    }
+
+    fn lint_function(&self, file: &str, function: &str, item_fn: &ItemFn, warnings: &mut Vec<AnnotationWarning>) {
+        // Line and text of the first statement of the body that is not a contract
+        let first_code = item_fn.block.stmts.iter()
+            .find(|stmt| !matches!(self.statement_annotation(stmt), Some((AnnotationRole::Pre | AnnotationRole::Post, _))))
+            .map(|stmt| (stmt.span().start().line, Self::render_stmt_head(stmt)));
+        self.lint_block(file, function, &item_fn.block, true, false, None, first_code.as_ref(), warnings);
+        self.lint_postcondition_names(file, function, item_fn, warnings);
+    }
+
+    // Postconditions are read at the returns: a variable they name must be a parameter, 'result', or a
+    // local declared at the top level of the body before every 'return'
+    fn lint_postcondition_names(&self, file: &str, function: &str, item_fn: &ItemFn, warnings: &mut Vec<AnnotationWarning>) {
+        let parameters: HashSet<String> = item_fn.sig.inputs.iter()
+            .flat_map(|input| match input {
+                FnArg::Receiver(_) => vec!["self".to_string()],
+                FnArg::Typed(pat_type) => Self::pattern_bindings(&pat_type.pat),
+            })
+            .collect();
+        let top_level: HashSet<String> = item_fn.block.stmts.iter()
+            .flat_map(|stmt| match stmt {
+                Stmt::Local(local) => Self::pattern_bindings(&local.pat),
+                _ => Vec::new(),
+            })
+            .collect();
+        let mut bindings = BodyBindings::default();
+        bindings.visit_block(&item_fn.block);
+
+        for stmt in &item_fn.block.stmts {
+            let Some((AnnotationRole::Post, annotation)) = self.statement_annotation(stmt) else { continue };
+            let Ok(condition) = crate::spec_parser::annotation_condition(&annotation.mac.tokens) else { continue };
+            let mut names = ConditionNames::default();
+            names.visit_expr(&condition);
+            for name in names.names {
+                // Constants and enum variants are capitalized, 'result' is the returned value
+                if name == "result" || parameters.contains(&name) || self.file.constants.contains_key(&name) || name.starts_with(char::is_uppercase) {
+                    continue;
+                }
+                let message = match bindings.locals.get(&name) {
+                    Some(&line) if top_level.contains(&name) => match bindings.returns.iter().find(|&&ret| ret < line) {
+                        Some(ret) => format!("names '{}', declared at line {}, after the return at line {} where it is not live yet", name, line, ret),
+                        None => continue,
+                    },
+                    Some(line) => format!("names '{}', declared at line {} in a nested block, so it is not live at any return", name, line),
+                    None => format!("names '{}', which is neither a parameter nor a variable of {}", name, function),
+                };
+                warnings.push(AnnotationWarning {
+                    location: Self::location(file, annotation.span()),
+                    function: function.to_string(),
+                    annotation: Self::render_expr(&Expr::Macro(annotation.clone())),
+                    message,
+                    suggestion: "state it with the 'result' keyword for the returned value, or with the parameters".to_string(),
+                });
+            }
+        }
+    }
+
+    // 'in_loop' for the blocks in the body of a loop
+    #[allow(clippy::too_many_arguments)]
+    fn lint_block(&self, file: &str, function: &str, block: &Block, top_level: bool, in_loop: bool, dead: Option<String>, first_code: Option<&(usize, String)>, warnings: &mut Vec<AnnotationWarning>) {
+        let mut dead = dead;
+        let mut code_seen = false;
+        for (index, stmt) in block.stmts.iter().enumerate() {
+            if let Some((role, annotation)) = self.statement_annotation(stmt) {
+                let warning = |message: String, suggestion: String| AnnotationWarning {
+                    location: Self::location(file, annotation.span()),
+                    function: function.to_string(),
+                    annotation: Self::render_expr(&Expr::Macro(annotation.clone())),
+                    message,
+                    suggestion,
+                };
+                if let Some(reason) = &dead {
+                    warnings.push(warning(
+                        format!("is in code that never runs ({}), it is never checked", reason),
+                        "remove it, or move it before the code that leaves the block".to_string(),
+                    ));
+                } else if !top_level && (role != AnnotationRole::Decreases || !in_loop) {
+                    let suggestion = match role {
+                        AnnotationRole::Pre | AnnotationRole::Post => format!("move it to the top of the body of {}", function),
+                        AnnotationRole::Invariant => "annotate a loop at the top level of the function body, or move the nested loop to a function with its own contract".to_string(),
+                        AnnotationRole::Assert | AnnotationRole::OnExit | AnnotationRole::Assume => "move it to the top level of the function body".to_string(),
+                        AnnotationRole::Decreases => "move it right before a loop at the top level of the function body, or into the body of the loop it measures".to_string(),
+                    };
+                    warnings.push(warning("is inside a nested block, where annotations are built as plain statements and not verified".to_string(), suggestion));
+                } else if matches!(role, AnnotationRole::Pre | AnnotationRole::Post) && code_seen {
+                    let suggestion = match first_code {
+                        Some((line, code)) => format!("move it before '{}' at line {}, with the other annotations of the contract", code, line),
+                        None => format!("move it to the top of the body of {}", function),
+                    };
+                    let message = match role {
+                        AnnotationRole::Pre => "comes after the first statement of the body, it is assumed there instead of on entry",
+                        _ => "comes after the first statement of the body, where it reads as a condition on that point",
+                    };
+                    warnings.push(warning(message.to_string(), suggestion));
+                } else if role == AnnotationRole::Invariant && !self.after_annotations(&block.stmts[index + 1..], &[AnnotationRole::Decreases]).is_some_and(Self::is_loop_statement) {
+                    let next_loop = block.stmts[index + 1..].iter().find(|stmt| Self::is_loop_statement(stmt));
+                    let suggestion = match next_loop {
+                        Some(next_loop) => format!("move it right before the loop at line {}", next_loop.span().start().line),
+                        None => "remove it, or map a macro to the 'assert' role in secured.toml to check a condition at this point".to_string(),
+                    };
+                    warnings.push(warning("does not immediately precede a loop, so it is not the invariant of any loop".to_string(), suggestion));
+                } else if role == AnnotationRole::Decreases && top_level
+                    && !self.after_annotations(&block.stmts[index + 1..], &[AnnotationRole::Invariant, AnnotationRole::Decreases]).is_some_and(Self::is_loop_statement) {
+                    warnings.push(warning(
+                        "does not precede a loop, so it is not the measure of any loop".to_string(),
+                        "move it right before the loop it measures, or into the body of the loop".to_string(),
+                    ));
+                }
+                continue;
+            }
+            if !matches!(stmt, Stmt::Item(_)) {
+                code_seen = true;
+            }
+
+            // Annotations in the blocks of the statement
+            let mut nested = NestedBlocks::default();
+            match stmt {
+                Stmt::Local(local) => nested.visit_local(local),
+                Stmt::Expr(expr) | Stmt::Semi(expr, _) => nested.visit_expr(expr),
+                Stmt::Item(_) => {},
+            }
+            for (nested_block, never_runs) in nested.blocks {
+                let in_loop = in_loop || nested.loop_bodies.iter().any(|body| std::ptr::eq(*body, nested_block));
+                self.lint_block(file, function, nested_block, false, in_loop, dead.clone().or(never_runs), first_code, warnings);
+            }
+
+            // The rest of the block is dead after a statement that leaves it
+            if dead.is_none() {
+                dead = self.leaving_statement(stmt);
+            }
+        }
+    }
+
+    // Role and macro of an annotation statement, 'pre!(x > 0);' whether it parses as an expression or an item
+    pub(crate) fn statement_annotation(&self, stmt: &Stmt) -> Option<(AnnotationRole, ExprMacro)> {
+        let expr_macro = match stmt {
+            Stmt::Semi(Expr::Macro(expr_macro), _) | Stmt::Expr(Expr::Macro(expr_macro)) => expr_macro.clone(),
+            Stmt::Item(Item::Macro(item_macro)) => ExprMacro { attrs: item_macro.attrs.clone(), mac: item_macro.mac.clone() },
+            _ => return None,
+        };
+        let role = self.options.annotation_names.role_of(&expr_macro.mac.path.get_ident()?.to_string())?;
+        Some((role, expr_macro))
+    }
+
+    // First statement that is not an annotation of one of these roles: the loop the 'invariant!' and
+    // 'decreases!' in front of it annotate
+    pub(crate) fn after_annotations<'b>(&self, stmts: &'b [Stmt], roles: &[AnnotationRole]) -> Option<&'b Stmt> {
+        stmts.iter().find(|stmt| !self.statement_annotation(stmt).is_some_and(|(role, _)| roles.contains(&role)))
+    }
+
+    // 'let r = loop { .. };' and 'r = loop { .. };' start with their loop too
+    pub(crate) fn is_loop_statement(stmt: &Stmt) -> bool {
+        let expr = match stmt {
+            Stmt::Expr(Expr::Assign(assign)) | Stmt::Semi(Expr::Assign(assign), _) => &*assign.right,
+            Stmt::Expr(expr) | Stmt::Semi(expr, _) => expr,
+            Stmt::Local(local) => match &local.init {
+                Some((_, init)) => &**init,
+                None => return false,
+            },
+            Stmt::Item(_) => return false,
+        };
+        matches!(expr, Expr::While(_) | Expr::ForLoop(_) | Expr::Loop(_))
+    }
+
+    // Why the statements after this one never run: 'return', 'break', 'continue', a panic or a call that never returns
+    fn leaving_statement(&self, stmt: &Stmt) -> Option<String> {
+        let (Stmt::Expr(expr) | Stmt::Semi(expr, _)) = stmt else { return None };
+        let line = stmt.span().start().line;
+        let what = match expr {
+            Expr::Return(_) => "return",
+            Expr::Break(_) => "break",
+            Expr::Continue(_) => "continue",
+            Expr::Macro(expr_macro) if ["panic", "unreachable", "todo", "unimplemented"].iter().any(|name| expr_macro.mac.path.is_ident(name)) => "panic",
+            Expr::Call(call) if self.is_diverging_call(call) => "call that never returns",
+            _ => return None,
+        };
+        Some(format!("after the {} at line {}", what, line))
+    }
+
+    // First line of a statement, as shown in a suggestion
+    fn render_stmt_head(stmt: &Stmt) -> String {
+        let rendered = match stmt {
+            Stmt::Local(local) => Self::render_local(local),
+            Stmt::Expr(expr) | Stmt::Semi(expr, _) => Self::render_expr(expr),
+            Stmt::Item(_) => "item".to_string(),
+        };
+        match rendered.split_once('{') {
+            Some((head, _)) => format!("{}{{ ... }}", head),
+            None => rendered,
+        }
+    }
+}
@@ -0,0 +1,60 @@
+use petgraph::graph::NodeIndex;
+
+use crate::cfg_builder::builder::CfgBuilder;
+use crate::cfg_builder::node::CfgNode;
+use crate::report::{AssumptionKind, UncheckedFact};
+
+// Fact in the metadata of the node of an 'assume!'
+pub(crate) const ASSUME_FACT: &str = "assume!: assumed here, never checked";
+
+impl CfgBuilder {
+    // Facts the verification conditions of the graph take without checking them ('--assumptions'), with the
+    // node each one comes from. The functions left out and the approximated syntax have no node.
+    pub fn unchecked_facts(&self) -> Vec<(Option<NodeIndex>, UncheckedFact)> {
+        let mut facts = Vec::new();
+        for node in self.graph.node_indices() {
+            let metadata = self.results.node_metadata.get(&node);
+            let (kind, detail) = match &self.graph[node] {
+                CfgNode::Assumption(fact, _) if metadata.is_some_and(|metadata| metadata.facts.iter().any(|f| f == ASSUME_FACT)) => (AssumptionKind::Assume, fact.clone()),
+                // Only the facts the solver gets: there is no memory model for the valid references
+                CfgNode::Assumption(fact, Some(_)) => {
+                    let origin = metadata.and_then(|metadata| metadata.facts.first()).map(|origin| format!(" ({})", origin)).unwrap_or_default();
+                    (AssumptionKind::TypeFact, format!("{}{}", fact, origin))
+                },
+                _ => {
+                    let Some(call) = metadata.and_then(|metadata| metadata.indirect_call.as_ref()) else { continue };
+                    match call.contract.as_deref() {
+                        None if call.havoc.is_empty() => (AssumptionKind::Havoc, format!("call to {}: its effects are unknown", call.callee)),
+                        None => (AssumptionKind::Havoc, format!("call to {}: havoc {}", call.callee, call.havoc.join(", "))),
+                        // The contract of a function of the file is proved with it, unless it is left out
+                        Some("callee contract") => match self.unverified_callee(&call.callee) {
+                            Some(reason) => (AssumptionKind::TrustedContract, format!("call to {} with its contract, not proved ({})", call.callee, reason)),
+                            None => continue,
+                        },
+                        Some(source) => (AssumptionKind::TrustedContract, format!("call to {} with its contract from the {}", call.callee, source)),
+                    }
+                },
+            };
+            let function = self.function_of_node(node).unwrap_or_default();
+            facts.push((Some(node), UncheckedFact { kind, function, line: self.source_span(node).map(|span| span.line), detail }));
+        }
+
+        for (function, reason) in &self.results.skipped_functions {
+            let line = self.file.local_functions.get(function).map(|item_fn| item_fn.sig.ident.span().start().line).filter(|&line| line > 0);
+            let detail = format!("{}, its contract is assumed by its callers", reason);
+            facts.push((None, UncheckedFact { kind: AssumptionKind::TrustedFunction, function: function.clone(), line, detail }));
+        }
+        for diagnostics in self.results.diagnostics.iter().filter(|diagnostics| !diagnostics.is_empty()) {
+            let detail = diagnostics.to_string();
+            facts.push((None, UncheckedFact { kind: AssumptionKind::UnmodeledSyntax, function: diagnostics.function.clone(), line: None, detail }));
+        }
+        facts
+    }
+
+    // Why the function of the file a call goes to isn't verified, if it isn't ('#[secured::trusted]')
+    fn unverified_callee(&self, callee: &str) -> Option<String> {
+        let (name, item_fn) = self.file.local_functions.get_key_value(callee)
+            .or_else(|| self.file.local_functions.iter().find(|(name, _)| name.rsplit("::").next() == callee.rsplit("::").next()))?;
+        self.skip_reason(name, item_fn)
+    }
+}
@@ -0,0 +1,132 @@
+use petgraph::graph::NodeIndex;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use syn::Attribute;
+
+use crate::cfg_builder::builder::CfgBuilder;
+use crate::cfg_builder::node::{BranchProbability, CfgNode, EdgeKind};
+
+// Weight of a '#[likely]' branch against an unhinted one; '#[unlikely]' weighs its inverse
+const LIKELY_WEIGHT: f64 = 9.0;
+
+// Execution counts of the branches of one condition, from a profile given to '--branch-profile':
+//   [{ "function": "sum_to", "line": 7, "counts": { "true": 90, "false": 10 } }]
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BranchCounts {
+    pub function: String,
+    pub line: usize, // line of the condition ('if', 'while', 'for' or 'match')
+    pub counts: BTreeMap<String, u64>, // by edge label: 'true', 'false' or the pattern of a match arm
+}
+
+impl BranchCounts {
+    pub fn load(path: &Path) -> Result<Vec<BranchCounts>, Box<dyn std::error::Error>> {
+        let content = fs::read_to_string(path).map_err(|e| format!("can't read the branch profile {}: {}", path.display(), e))?;
+        serde_json::from_str(&content).map_err(|e| format!("invalid branch profile {}: {}", path.display(), e).into())
+    }
+}
+
+impl CfgBuilder {
+    // Weight of a branch from its '#[likely]' or '#[unlikely]' attribute
+    pub(crate) fn branch_hint(attrs: &[Attribute]) -> Option<f64> {
+        attrs.iter().find_map(|attr| {
+            if attr.path.is_ident("likely") {
+                Some(LIKELY_WEIGHT)
+            } else if attr.path.is_ident("unlikely") {
+                Some(1.0 / LIKELY_WEIGHT)
+            } else {
+                None
+            }
+        })
+    }
+
+    // Probabilities of the edges out of a condition node from the weights of its branches, an unhinted branch
+    // weighing 1. Nothing is recorded when no branch is hinted.
+    pub(crate) fn set_branch_weights(&mut self, node: NodeIndex, weights: Vec<(String, Option<f64>)>) {
+        if weights.iter().all(|(_, weight)| weight.is_none()) {
+            return;
+        }
+        let total: f64 = weights.iter().map(|(_, weight)| weight.unwrap_or(1.0)).sum();
+        self.metadata_mut(node).branch_probabilities = weights.into_iter()
+            .map(|(edge, weight)| BranchProbability { edge, probability: weight.unwrap_or(1.0) / total })
+            .collect();
+    }
+
+    // Replace the hints of the conditions a profile has counts for, returning the entries that match no condition
+    pub fn apply_branch_profile(&mut self, profile: &[BranchCounts]) -> Vec<String> {
+        let mut unmatched = Vec::new();
+        for entry in profile {
+            let total: u64 = entry.counts.values().sum();
+            let Some(node) = self.profiled_condition(entry).filter(|_| total > 0) else {
+                unmatched.push(format!("{} line {}", entry.function, entry.line));
+                continue;
+            };
+            self.metadata_mut(node).branch_probabilities = entry.counts.iter()
+                .map(|(edge, &count)| BranchProbability { edge: edge.clone(), probability: count as f64 / total as f64 })
+                .collect();
+        }
+        unmatched
+    }
+
+    fn profiled_condition(&self, entry: &BranchCounts) -> Option<NodeIndex> {
+        let func_node = self.function_nodes().into_iter().find(|&node| {
+            let name = self.graph[node].label();
+            name == entry.function || name.rsplit("::").next() == Some(entry.function.as_str())
+        })?;
+        self.function_subgraph_nodes(func_node).into_iter().find(|&node| {
+            matches!(self.graph[node], CfgNode::Condition(_, _)) && self.node_location(node).map(|(line, _)| line) == Some(entry.line)
+        })
+    }
+
+    // Probability of leaving a node through an edge of this kind, when its branches are hinted or profiled
+    pub fn branch_probability(&self, node: NodeIndex, kind: &EdgeKind) -> Option<f64> {
+        let label = kind.label();
+        self.results.node_metadata.get(&node)?.branch_probabilities.iter()
+            .find(|branch| branch.edge == label)
+            .map(|branch| branch.probability)
+    }
+
+    pub fn has_branch_probabilities(&self) -> bool {
+        self.results.node_metadata.values().any(|metadata| !metadata.branch_probabilities.is_empty())
+    }
+
+    // Probability of a path: the product of the probabilities of its branches, the branches of a condition
+    // without hints being equally likely
+    pub fn path_probability(&self, path: &[NodeIndex]) -> f64 {
+        path.windows(2).map(|step| {
+            if !matches!(self.graph[step[0]], CfgNode::Condition(_, _)) {
+                return 1.0;
+            }
+            let out_degree = self.graph.edges(step[0]).count().max(1) as f64;
+            self.graph.edges_connecting(step[0], step[1]).next()
+                .and_then(|edge| self.branch_probability(step[0], edge.weight()))
+                .unwrap_or(1.0 / out_degree)
+        }).product()
+    }
+
+    // Indices of the paths in the order to verify them: the most likely first when branches are hinted or
+    // profiled, else in their order
+    pub fn verification_order(&self, paths: &[Vec<NodeIndex>]) -> Vec<usize> {
+        let mut order: Vec<usize> = (0..paths.len()).collect();
+        if self.has_branch_probabilities() {
+            let probabilities: Vec<f64> = paths.iter().map(|path| self.path_probability(path)).collect();
+            order.sort_by(|&a, &b| probabilities[b].total_cmp(&probabilities[a]));
+        }
+        order
+    }
+
+    // DOT attributes of an edge with a known probability: thicker the more it is taken, red when hot, gray when cold
+    pub(crate) fn branch_style(probability: Option<f64>) -> String {
+        let Some(probability) = probability else { return String::new() };
+        let color = if probability >= 0.7 {
+            ", color=firebrick, fontcolor=firebrick"
+        } else if probability <= 0.3 {
+            ", color=gray60, fontcolor=gray60"
+        } else {
+            ""
+        };
+        format!(", penwidth={:.1}{}", 1.0 + 4.0 * probability, color)
+    }
+}
@@ -13,14 +13,27 @@ use petgraph::graph::{DiGraph, NodeIndex};
 use petgraph::visit::EdgeRef;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs;
+use std::sync::LazyLock;
 use std::fs::File;
 use std::io::Write;
 use quote::quote;
-use syn::{visit::{self, Visit}, Expr, ExprAssign, ExprReturn, Block, File as SynFile, ItemFn, Pat, Stmt};
-
-use crate::cfg_builder::node::{CfgNode, ConditionalExpr};
+use syn::spanned::Spanned;
+use syn::{visit::{self, Visit}, Expr, ExprAssign, ExprMacro, ExprReturn, Block, File as SynFile, ItemFn, ItemImpl, ItemMod, ItemTrait, Pat, Stmt, TraitItem};
+
+use crate::cfg_builder::node::{CfgNode, ConditionalExpr, EdgeKind, NodeMetadata};
+use crate::cfg_builder::constants::ConstValue;
+use crate::cfg_builder::filter::NodeFilter;
+use crate::cfg_builder::diagnostics::SyntaxDiagnostics;
+use crate::cfg_builder::regions::RegionSummary;
+use crate::cfg_builder::phases::PhaseSnapshot;
+use crate::cfg_builder::ffi::ForeignFunction;
+use crate::cfg_builder::contract_bundle::ContractBundle;
+use crate::cfg_builder::call_strategy::{CallStrategyRecord, CallTarget};
+use crate::cfg_builder::assumption_budget::ASSUME_FACT;
+use crate::wp_calculus::{IntervalState, LogicFunction};
+use crate::config::{conditions_path, AnnotationNames, AnnotationRole, CallSettings, EnumerationSettings, NotationTable, SimplifySettings, SolverSettings};
 use crate::cfg_builder::handle_condition::*;
 use crate::cfg_builder::handle_loops::*;
 use crate::cfg_builder::handle_macros::*;
@@ -35,30 +48,103 @@ pub struct ExternalMethod {
     pub postconditions: Vec<String>,
 }
 
+// Contract of the indirect calls (function pointers, closures, trait objects) made through a callee,
+// e.g. 'self.op' or 'shape.area'
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CallSiteContract {
+    pub callee: String,
+    #[serde(default)]
+    pub preconditions: Vec<String>,
+    #[serde(default)]
+    pub postconditions: Vec<String>, // 'result' is the value returned by the call
+}
+
 // List of external methods
 #[derive(Serialize, Deserialize, Debug)]
 pub struct ExternalMethods {
     pub external_methods: Vec<ExternalMethod>,
+    #[serde(default)]
+    pub call_sites: Vec<CallSiteContract>,
+}
+
+// Settings of a run, given before the build: what is analyzed, how calls and the solver are handled, and
+// how the exports look
+#[derive(Default)]
+pub struct BuilderOptions {
+    pub max_label_len: Option<usize>, // labels longer than this are shortened in DOT exports
+    pub node_filter: NodeFilter, // node categories shown in DOT and JSON exports
+    pub analyze_all_functions: bool, // also build CFGs for functions without annotations
+    pub annotation_names: AnnotationNames, // macro names recognized as pre/post/invariant/assert annotations
+    pub skip_list: Vec<String>, // functions of the '[skip]' section
+    pub imported_contracts: Vec<ContractBundle>, // contracts of other crates, for the calls into them
+    pub debug_phases: bool, // keep a DOT snapshot of the graph after each phase
+    pub call_settings: CallSettings, // contract or inlining for the calls of functions of the file
+    pub simplify: SimplifySettings, // no-op nodes dropped after post-processing
+    pub solver_settings: SolverSettings, // timeout, retries and tactics of each solver call
+    pub enumeration: EnumerationSettings, // input budget of the exhaustive checks
+    pub function_filter: Option<String>, // only this function gets a CFG, by qualified name or last segment
+    pub notation: NotationTable, // how calls in specs are displayed in the DOT, HTML and report labels
+}
+
+// What the items of the analyzed file declare, collected before its functions are built
+#[derive(Default)]
+pub struct FileFacts {
+    pub fn_return_types: HashMap<String, String>, // return types of the functions of the file
+    pub diverging_functions: HashSet<String>, // names of the functions and methods of the file declared '-> !'
+    pub local_enums: HashMap<String, Vec<String>>, // enums declared in the file and their variants
+    pub constants: HashMap<String, ConstValue>, // evaluated 'const'/'static' items of the file
+    pub source_lines: Vec<String>, // source of the analyzed file, for the '//@ call' comments
+    pub struct_invariants: HashMap<String, Vec<Expr>>, // 'struct_invariant!' conditions of the file, by type
+    pub struct_invariant_opt_outs: HashSet<String>, // 'Type::method's that don't check the struct invariants
+    pub foreign_functions: HashMap<String, ForeignFunction>, // functions of the 'extern' blocks, by name
+    pub local_functions: HashMap<String, ItemFn>, // functions of the file by qualified name, for inlining and their contracts
+    pub logic_functions: BTreeMap<String, LogicFunction>, // '#[logic]' functions of the file, by name, for the specs calling them
+}
+
+// What the build and the analyses of the graph produce, for the verification and the reports
+#[derive(Default)]
+pub struct BuilderResults {
+    pub node_metadata: HashMap<NodeIndex, NodeMetadata>, // extra per-node information for exports
+    pub node_ids: HashMap<NodeIndex, String>, // IDs of the nodes in the exports, derived from the source
+    pub region_summaries: HashMap<NodeIndex, RegionSummary>, // loop-free regions crossed in one step, by entry
+    pub intervals: HashMap<NodeIndex, IntervalState>, // ranges of the numeric variables on entry to each node
+    pub diagnostics: Vec<SyntaxDiagnostics>, // constructs each analyzed function uses that the CFG approximates
+    pub skipped_functions: Vec<(String, String)>, // functions left out and why, for the report
+    pub rejected_specs: Vec<String>, // annotations that can't be verified, e.g. postconditions of diverging functions
+    pub ffi_warnings: Vec<String>, // calls of extern functions without a contract in the conditions database
+    pub phase_snapshots: Vec<PhaseSnapshot>,
+    pub call_strategies: Vec<CallStrategyRecord>, // strategy used at each call of a function of the file
 }
 
 // Main struct of the CfgBuilder
 pub struct CfgBuilder {
-    pub graph: DiGraph<CfgNode, String>, // Directed graph representing the CFG
+    pub graph: DiGraph<CfgNode, EdgeKind>, // Directed graph representing the CFG
     pub current_node: Option<NodeIndex>, // current node being processed
-    pub next_edge_label: Option<String>,
+    pub next_edge_kind: Option<EdgeKind>, // kind of the edge to the next node added
     pub external_conditions: ExternalMethods,
     pub postconditions: Vec<CfgNode>,
+    pub pending_decreases: Vec<CfgNode>, // 'decreases!' written before the next loop, attached to its head
+    pub return_nodes: Vec<NodeIndex>, // exits of the current function, each one leads to the postconditions
+    pub type_env: HashMap<String, String>, // types of the variables in scope of the current function
+    pub loop_exits: HashSet<NodeIndex>, // merge nodes where loops exit, for 'on_exit!'
+    pub loop_stack: Vec<LoopContext>, // loops being built around the current node, innermost last
+    pub module_path: Vec<String>, // inline modules ('mod geometry { .. }') around the items being visited
+    pub inline_stack: Vec<String>, // functions whose body is being inlined, innermost last
+    pub current_function: String, // qualified name of the function being built
+    pub options: BuilderOptions,
+    pub file: FileFacts,
+    pub results: BuilderResults,
 }
 
 impl CfgBuilder {
     // Create new instance of CfgBuilder
     pub fn new() -> Self {
         // Attempt to load external conditions from the config file
-        let external_conditions = match Self::parse_external_definitions("src/config/conditions.json") {
+        let external_conditions = match Self::parse_external_definitions(&conditions_path()) {
             Ok(conditions) => conditions,
             Err(e) => {
                 eprintln!("Failed to load external conditions: {}", e);
-                ExternalMethods { external_methods: vec![] }
+                ExternalMethods { external_methods: vec![], call_sites: vec![] }
             }
         };
 
@@ -66,26 +152,57 @@ impl CfgBuilder {
         CfgBuilder {
             graph: DiGraph::new(),
             current_node: None,
-            next_edge_label: None,
+            next_edge_kind: None,
             external_conditions,
             postconditions: Vec::new(),
+            pending_decreases: Vec::new(),
+            return_nodes: Vec::new(),
+            type_env: HashMap::new(),
+            loop_exits: HashSet::new(),
+            loop_stack: Vec::new(),
+            module_path: Vec::new(),
+            inline_stack: Vec::new(),
+            current_function: String::new(),
+            options: BuilderOptions::default(),
+            file: FileFacts::default(),
+            results: BuilderResults::default(),
         }
     }
 
+    // Whether the function filter keeps this function: 'geometry::area' is selected by itself and by 'area'
+    pub fn is_selected_function(&self, func_name: &str) -> bool {
+        self.options.function_filter.as_ref().is_none_or(|filter| {
+            func_name == filter || func_name.rsplit("::").next() == Some(filter.as_str())
+        })
+    }
+
     // Method called to build the CFG
     pub fn build_cfg(&mut self, ast: &SynFile) {
         // Visit the AST to build the CFG nodes and edges
         self.visit_file(ast);
+        self.snapshot_phase("visited");
 
         // Post-process the CFG to handle merges and cleanup
         self.post_process();
+        self.snapshot_phase("post_processed");
+
+        // Drop the nodes that don't change any path
+        self.simplify_graph();
+        self.snapshot_phase("simplified");
+
+        // Name the nodes of the final graph after their source
+        self.assign_node_ids();
+        self.snapshot_phase("node_ids");
+
+        // The final graph in SSA form, only built for the snapshot
+        self.snapshot_ssa();
     }
 
     // Parse external conditions if there are any
-    pub fn parse_external_definitions(file_path: &str) -> Result<ExternalMethods, Box<dyn std::error::Error>> {
-        if !std::path::Path::new(file_path).exists() {
+    pub fn parse_external_definitions(file_path: &std::path::Path) -> Result<ExternalMethods, Box<dyn std::error::Error>> {
+        if !file_path.exists() {
             eprintln!("Warning: External conditions file not found. Using empty conditions.");
-            return Ok(ExternalMethods { external_methods: vec![] });
+            return Ok(ExternalMethods { external_methods: vec![], call_sites: vec![] });
         }
     
         let file_content = fs::read_to_string(file_path)?;
@@ -96,24 +213,33 @@ impl CfgBuilder {
 
     // Method used to add postconditions at the end of graph
     pub fn add_postconditions(&mut self) {
-        let postconditions = self.postconditions.clone();
-        for postcondition in postconditions {
-            self.add_node(postcondition);
+        // Every return and the end of the body lead to the postconditions, so each exit gets its own obligation
+        let mut exits = std::mem::take(&mut self.return_nodes);
+        exits.extend(self.current_node);
+        let postconditions = std::mem::take(&mut self.postconditions);
+        for (i, postcondition) in postconditions.into_iter().enumerate() {
+            if i == 0 {
+                let post_node = self.add_node_without_edge(postcondition);
+                for &exit in &exits {
+                    self.graph.add_edge(exit, post_node, EdgeKind::Fallthrough);
+                }
+            } else {
+                self.add_node(postcondition);
+            }
         }
-        self.postconditions.clear();
     }
 
     // Adds a node to the graph and connects it to the current node
     pub fn add_node(&mut self, node: CfgNode) -> NodeIndex {
         let index = self.graph.add_node(node);
         if let Some(current) = self.current_node {
-            // Use the label for the next edge if available
-            let label = self.next_edge_label.clone().unwrap_or_else(|| "".to_string());
-            self.graph.add_edge(current, index, label);
-            // Reset the edge label
-            self.next_edge_label = None;
+            // Use the kind of the next edge if set
+            let kind = self.next_edge_kind.take().unwrap_or(EdgeKind::Fallthrough);
+            self.graph.add_edge(current, index, kind);
         }
         self.current_node = Some(index);
+        self.record_span(index);
+        self.record_casts(index);
         index
     }
 
@@ -121,54 +247,160 @@ impl CfgBuilder {
     pub fn add_node_without_edge(&mut self, node: CfgNode) -> NodeIndex {
         let index = self.graph.add_node(node);
         self.current_node = Some(index);
+        self.record_span(index);
         index
     }
 
-    // Adds an edge between two nodes with a specified label
-    pub fn add_edge_with_label(&mut self, from: NodeIndex, to: NodeIndex, label: String) {
-        self.graph.add_edge(from, to, label);
+    // Keep where the node is written, for the tooltips and the exports
+    fn record_span(&mut self, node: NodeIndex) {
+        if let Some(span) = self.source_span(node) {
+            self.metadata_mut(node).span = Some(span);
+        }
+    }
+
+    // Metadata of a node, created on first access
+    pub fn metadata_mut(&mut self, node: NodeIndex) -> &mut NodeMetadata {
+        self.results.node_metadata.entry(node).or_default()
+    }
+
+    // Removes a node and keeps the maps by node in sync: petgraph moves the last node into the freed index.
+    // A stale index would give one node the facts of another (an interval discharging the wrong obligation).
+    pub fn remove_node(&mut self, node: NodeIndex) {
+        assert!(self.results.region_summaries.values().all(|region| region.paths.iter().all(|path| !path.contains(&node))),
+            "node {:?} removed from a summarized region", node);
+        let last = NodeIndex::new(self.graph.node_count() - 1);
+        self.graph.remove_node(node);
+        let moved = |index: NodeIndex| if index == last { node } else { index };
+        fn rekey<V>(map: &mut HashMap<NodeIndex, V>, node: NodeIndex, moved: impl Fn(NodeIndex) -> NodeIndex) {
+            map.remove(&node);
+            *map = map.drain().map(|(index, value)| (moved(index), value)).collect();
+        }
+        rekey(&mut self.results.node_metadata, node, moved);
+        rekey(&mut self.results.node_ids, node, moved);
+        rekey(&mut self.results.intervals, node, moved);
+        rekey(&mut self.results.region_summaries, node, moved);
+        for region in self.results.region_summaries.values_mut() {
+            region.entry = moved(region.entry);
+            region.exit = moved(region.exit);
+            for path in &mut region.paths {
+                path.iter_mut().for_each(|index| *index = moved(*index));
+            }
+        }
+        self.loop_exits = self.loop_exits.iter().filter(|&&index| index != node).map(|&index| moved(index)).collect();
+        self.return_nodes.retain(|&index| index != node);
+        self.return_nodes.iter_mut().for_each(|index| *index = moved(*index));
+        self.current_node = self.current_node.filter(|&index| index != node).map(moved);
+    }
+
+    // Format a node for DOT with its metadata in the tooltip
+    pub fn format_node_dot(&self, node: NodeIndex) -> String {
+        self.format_node_dot_as(node, &self.node_id(node))
+    }
+
+    // Same as 'format_node_dot' with another ID, for graphs where a node appears more than once
+    pub fn format_node_dot_as(&self, node: NodeIndex, id: &str) -> String {
+        self.format_node_dot_labeled(node, id, &self.graph[node].label())
+    }
+
+    // Same as 'format_node_dot_as' showing another label, e.g. the SSA form of the node
+    pub fn format_node_dot_labeled(&self, node: NodeIndex, id: &str, label: &str) -> String {
+        let tooltip_lines = self.results.node_metadata.get(&node)
+            .map(|metadata| metadata.tooltip_lines())
+            .unwrap_or_default();
+        self.graph[node].format_dot_labeled(id, &self.options.notation.render(label), self.options.max_label_len, &tooltip_lines)
+    }
+
+    // Adds an edge of the given kind between two nodes
+    pub fn add_edge_with_kind(&mut self, from: NodeIndex, to: NodeIndex, kind: EdgeKind) {
+        self.graph.add_edge(from, to, kind);
     }
 
     // Convert CFG to dot format
     pub fn to_dot(&self) -> String {
+        self.to_dot_labeled(|node| self.graph[node].label())
+    }
+
+    // Same as 'to_dot' with the labels given by 'label_of'
+    pub fn to_dot_labeled(&self, label_of: impl Fn(NodeIndex) -> String) -> String {
         let mut dot_string = String::new();
         dot_string.push_str("digraph G {\n");
-        for node in self.graph.node_indices() {
+        // Nodes and edges are listed by ID, so the output only changes when the source does
+        let mut nodes: Vec<NodeIndex> = self.graph.node_indices().collect();
+        nodes.sort_by_key(|&node| self.node_id(node));
+        for node in nodes {
             let cfg_node = &self.graph[node];
-            // Skip floating invariants
-            if let CfgNode::Invariant(_, _) = cfg_node {
+            // Skip floating invariants and measures, the copies ending loop paths
+            if let CfgNode::Invariant(_, _) | CfgNode::Decreases(_, _) = cfg_node {
                 let has_incoming = self.graph.edges_directed(node, petgraph::Direction::Incoming).count() > 0;
                 let has_outgoing = self.graph.edges_directed(node, petgraph::Direction::Outgoing).count() > 0;
 
-                // If invariant is floating (no incoming or outgoing edges), skip it
+                // If the node is floating (no incoming or outgoing edges), skip it
                 if !has_incoming || !has_outgoing {
                     continue;
                 }
             }
-            dot_string.push_str(&cfg_node.format_dot(node.index()));
+            if !self.is_node_visible(node) {
+                continue;
+            }
+            dot_string.push_str(&self.format_node_dot_labeled(node, &self.node_id(node), &label_of(node)));
             dot_string.push('\n');
         }
-        for edge in self.graph.edge_references() {
-            let source = edge.source().index();
-            let target = edge.target().index();
-            let label = edge.weight();
-            dot_string.push_str(&format!("{} -> {} [label=\"{}\"];\n", source, target, label));
+        let mut edges: Vec<(String, String, EdgeKind, Option<f64>)> = self.visible_edges().into_iter()
+            .map(|(source, target, kind)| (self.node_id(source), self.node_id(target), kind.clone(), self.branch_probability(source, &kind)))
+            .collect();
+        edges.sort_by(|a, b| (&a.0, &a.1, a.2.label()).cmp(&(&b.0, &b.1, b.2.label())));
+        for (source, target, kind, probability) in edges {
+            dot_string.push_str(&Self::format_edge_dot(&source, &target, &kind, probability));
         }
+        dot_string.push_str(&self.loop_rank_hints(|node| self.is_node_visible(node)));
+        dot_string.push_str(&self.loop_summaries_dot(|node| self.is_node_visible(node)));
         dot_string.push_str("}\n");
         dot_string
     }
 
-    pub fn clean_up_formatting(input: &str) -> String {
-        let re = Regex::new(r"\s*([\(\)\[\]!\.,;])\s*").unwrap();
-        let cleaned = re.replace_all(input, "$1").to_string();
+    // Back edges don't constrain the ranks, so a loop body stays between its condition and its exit.
+    // Branches with a known probability show it in their label and width.
+    pub fn format_edge_dot(source: &str, target: &str, kind: &EdgeKind, probability: Option<f64>) -> String {
+        let constraint = if *kind == EdgeKind::BackEdge { ", constraint=false" } else { "" };
+        let label = match probability {
+            Some(probability) => format!("{} ({:.0}%)", kind.label(), probability * 100.0),
+            None => kind.label(),
+        };
+        format!("\"{}\" -> \"{}\" [label=\"{}\"{}{}];\n", source, target, label, constraint, Self::branch_style(probability))
+    }
+
+    // Each loop condition on the same rank as the node its loop exits to, for the shown nodes
+    pub fn loop_rank_hints(&self, shown: impl Fn(NodeIndex) -> bool) -> String {
+        let mut hints = Vec::new();
+        for head in self.graph.node_indices().filter(|&node| shown(node)) {
+            if !matches!(self.graph[head], CfgNode::Condition(_, Some(ConditionalExpr::While(_) | ConditionalExpr::ForLoop(_)))) {
+                continue;
+            }
+            let exit = self.graph.edges(head).find(|edge| *edge.weight() == EdgeKind::FalseBranch).map(|edge| edge.target());
+            if let Some(exit) = exit.filter(|&exit| shown(exit)) {
+                hints.push(format!("{{rank=same; \"{}\"; \"{}\";}}\n", self.node_id(head), self.node_id(exit)));
+            }
+        }
+        hints.sort();
+        hints.concat()
+    }
 
-        cleaned.replace("vec! [", "vec![")
-               .replace("+ ", " + ")
+    // Fallback spacing cleanup for token strings that 'render_expr' does not model
+    pub fn clean_up_formatting(input: &str) -> String {
+        // Compiled once, labels are cleaned up for every node
+        static CLOSERS: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\s+([\)\]\.,;?])").unwrap());
+        static OPENERS: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"([\(\[\.])\s+").unwrap());
+        static PATHS: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\s*::\s*").unwrap());
+        static MACROS: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(\w)\s*!\s*([\(\[\{])").unwrap());
+
+        let cleaned = CLOSERS.replace_all(input, "$1");
+        let cleaned = OPENERS.replace_all(&cleaned, "$1");
+        let cleaned = PATHS.replace_all(&cleaned, "::");
+        MACROS.replace_all(&cleaned, "$1!$2").to_string()
     }
 
-    pub fn format_condition(&self, expr: &Box<Expr>) -> String {
-        let raw_string = quote!(#expr).to_string();
-        Self::clean_up_formatting(&raw_string)
+    pub fn format_condition(&self, expr: &Expr) -> String {
+        Self::render_expr(expr)
     }
 
     // Post process and merge CFG 'empty' nodes used for converging edges 
@@ -198,12 +430,6 @@ impl CfgBuilder {
                 }
             }
         }
-        // Clean up formatting in the node labels
-        for node in self.graph.node_indices() {
-            if let CfgNode::Condition(label, _) | CfgNode::Statement(label, _) = &mut self.graph[node] {
-                *label = CfgBuilder::clean_up_formatting(label);
-            }
-        }
     }
 
     // merge converging nodes with other converging nodes
@@ -215,7 +441,7 @@ impl CfgBuilder {
         for (source_of_edge, weight) in incoming_edges {
             self.graph.add_edge(source_of_edge, target, weight);
         }
-        self.graph.remove_node(source);
+        self.remove_node(source);
     }
     
     // used to redirect edges of merged nodes 
@@ -228,27 +454,55 @@ impl CfgBuilder {
             self.graph.add_edge(source_of_edge, new_target, weight);
         }
     
-        self.graph.remove_node(source);
+        self.remove_node(source);
     }
 
-    fn format_macro_args(&self, tokens: &proc_macro2::TokenStream) -> String {
+    pub(crate) fn format_macro_args(&self, tokens: &proc_macro2::TokenStream) -> String {
+        // Render annotations from their AST when they parse, both as 'pre!(n >= 0)' and 'pre!("n >= 0")'
+        if let Ok(condition) = crate::spec_parser::annotation_condition(tokens) {
+            return Self::render_expr(&condition);
+        }
+        if let Ok(Expr::Lit(syn::ExprLit { lit: syn::Lit::Str(lit_str), .. })) = syn::parse2::<Expr>(tokens.clone()) {
+            return lit_str.value();
+        }
         let tokens_str = tokens.to_string();
         tokens_str.trim_start_matches("!(")
                   .trim_end_matches(')')
                   .trim_matches(|c| c == '"' || c == '\'')
                   .to_string()
     }
-}
 
-impl Visit<'_> for CfgBuilder {
-    // Process Rust source file.
-    fn visit_file(&mut self, i: &SynFile) {
-        visit::visit_file(self, i);
+    // The annotation with its macro renamed to the built-in name of its role ('requires!' -> 'pre!'),
+    // and a condition written as a string ('on_exit!("i == n")') or with quantifiers turned into an expression
+    pub(crate) fn canonical_annotation(expr_macro: &ExprMacro, role: AnnotationRole) -> Expr {
+        let mut annotation = expr_macro.clone();
+        if let Ok(condition) = crate::spec_parser::annotation_condition(&expr_macro.mac.tokens) {
+            annotation.mac.tokens = quote!(#condition);
+        }
+        let span = expr_macro.mac.path.segments.last().map(|segment| segment.ident.span()).unwrap_or_else(proc_macro2::Span::call_site);
+        annotation.mac.path = syn::Path::from(syn::Ident::new(role.macro_name(), span));
+        Expr::Macro(annotation)
     }
 
-    // Handle function definitions and statements
-    fn visit_item_fn(&mut self, i: &ItemFn) {
-        let func_name = i.sig.ident.to_string();
+    // Build the CFG of one annotated function, returning its entry node
+    pub fn build_function_cfg(&mut self, func_name: String, i: &ItemFn) -> Option<NodeIndex> {
+        if let Some(reason) = self.skip_reason(&func_name, i) {
+            if self.is_selected_function(&func_name) {
+                self.results.skipped_functions.push((func_name, reason));
+            }
+            return None;
+        }
+
+        // '#[refines(reference)]' functions are verified against the reference instead of a contract of their own
+        let refinement = Self::refined_reference(i).and_then(|_| match self.refinement(&func_name, i) {
+            Ok(refinement) => Some(refinement),
+            Err(e) => {
+                eprintln!("Warning: {}, it is verified with its own annotations only", e);
+                None
+            },
+        });
+        let written = i;
+        let i = refinement.as_ref().map_or(i, |(refined, _)| refined);
 
         // Check if the function contains any relevant macros
         let mut contains_macros = false;
@@ -256,8 +510,7 @@ impl Visit<'_> for CfgBuilder {
             if let Stmt::Semi(expr, _) = stmt {
                 if let Expr::Macro(expr_macro) = expr {
                     if let Some(macro_ident) = expr_macro.mac.path.get_ident() {
-                        let macro_name = macro_ident.to_string();
-                        if ["pre", "post", "invariant"].contains(&macro_name.as_str()) {
+                        if self.options.annotation_names.role_of(&macro_ident.to_string()).is_some() {
                             contains_macros = true;
                             break;
                         }
@@ -267,40 +520,114 @@ impl Visit<'_> for CfgBuilder {
         }
 
         // Skip this function if no relevant macros are found
-        if !contains_macros {
-            return;
+        if !contains_macros && !self.options.analyze_all_functions {
+            return None;
+        }
+        if !self.is_selected_function(&func_name) {
+            return None;
+        }
+
+        // An annotation with a typo ('n >= O') would give a condition over an unknown variable, it is rejected
+        for problem in self.annotation_problems(None, &func_name, written) {
+            self.results.rejected_specs.push(format!("{} in {} at {}: {}", problem.spec, func_name, problem.location, problem.message));
         }
 
+        self.current_function = func_name.clone();
         let func_node = self.add_node(CfgNode::new_function(func_name.clone(), i.clone()));
+        let param_types = self.init_type_env(i);
+        self.metadata_mut(func_node).var_types = param_types;
+
+        // With the parameter types known, matches on them are resolved to their enum
+        let diagnostics = self.syntax_diagnostics(&func_name, written);
+        self.results.diagnostics.push(diagnostics);
+
+        if let Some((_, fact)) = &refinement {
+            self.metadata_mut(func_node).facts.push(fact.clone());
+        }
 
         self.current_node = Some(func_node);
+        self.add_stub_entry(&func_name, i);
+
+        // Type facts of the parameters are assumed right after the leading 'pre!', on every path from the entry
+        let mut entry_assumptions = Some(self.entry_assumptions(i));
 
         // Process each statement in function body
-        for stmt in &i.block.stmts {
+        let returns_value = matches!(i.sig.output, syn::ReturnType::Type(_, _));
+        for (index, stmt) in i.block.stmts.iter().enumerate() {
+            if !self.is_precondition_statement(stmt) {
+                if let Some(assumptions) = entry_assumptions.take() {
+                    self.add_entry_assumptions(assumptions);
+                }
+            }
             match stmt {
+                // A tail call that never returns is not a returned value
+                Stmt::Expr(expr) if returns_value && index + 1 == i.block.stmts.len() && Self::is_value_tail(expr)
+                    && !matches!(expr, Expr::Call(call) if self.is_diverging_call(call)) => {
+                    self.handle_tail_return(expr);
+                },
+                // The values of the 'break's of a tail 'loop' are the returned ones, and those of the branches
+                // of a tail 'if' or 'match'
+                Stmt::Expr(expr @ (Expr::Loop(_) | Expr::If(_) | Expr::Match(_) | Expr::Block(_))) if returns_value && index + 1 == i.block.stmts.len() => {
+                    self.visit_branching_value(expr, LoopValue::Returned);
+                },
                 Stmt::Semi(expr, _) => { // Statement usually ending with semicolumn
                     // Handle macro expressions
                     if let Expr::Macro(expr_macro) = expr {
                         if let Some(macro_ident) = expr_macro.mac.path.get_ident() {
-                            let macro_name = macro_ident.to_string();
-                            let macro_args = self.format_macro_args(&expr_macro.mac.tokens);
+                            let role = self.options.annotation_names.role_of(&macro_ident.to_string());
+                            // Custom annotation names are stored under the built-in name of their role
+                            let annotation = role.map(|role| Self::canonical_annotation(expr_macro, role));
+                            let macro_args = match &annotation {
+                                Some(Expr::Macro(canonical)) => self.format_macro_args(&canonical.mac.tokens),
+                                _ => self.format_macro_args(&expr_macro.mac.tokens),
+                            };
                             // handle annotation macros
-                            let node = match macro_name.as_str() {
-                                "pre" => CfgNode::new_precondition(macro_args.clone(), Expr::Macro(expr_macro.clone())),
-                                "post" => {
-                                    let post_node = CfgNode::new_postcondition(macro_args.clone(), Expr::Macro(expr_macro.clone()));
+                            match (role, annotation) {
+                                (Some(AnnotationRole::Pre), Some(annotation)) => {
+                                    self.add_node(CfgNode::new_precondition(macro_args, annotation));
+                                },
+                                (Some(AnnotationRole::Post), Some(annotation)) => {
                                     // add postconditions to vec to later merge them at the end of the CFG.
-                                    self.postconditions.push(post_node.clone());
-                                    post_node
+                                    self.postconditions.push(CfgNode::new_postcondition(macro_args, annotation));
+                                },
+                                (Some(AnnotationRole::Invariant), Some(annotation)) => {
+                                    self.add_node(CfgNode::new_invariant(macro_args, annotation));
+                                },
+                                // The measure of the loop that follows, past its invariants
+                                (Some(AnnotationRole::Decreases), Some(annotation)) => {
+                                    let decreases = CfgNode::new_decreases(macro_args, annotation);
+                                    let next = self.after_annotations(&i.block.stmts[index + 1..], &[AnnotationRole::Invariant, AnnotationRole::Decreases]);
+                                    if next.is_some_and(Self::is_loop_statement) {
+                                        self.pending_decreases.push(decreases);
+                                    } else {
+                                        self.results.rejected_specs.push(format!("{} in {}: no loop follows it", decreases.label(), func_name));
+                                    }
+                                },
+                                // An assertion is a cut point like an invariant: paths end there and restart from it
+                                (Some(AnnotationRole::Assert), Some(annotation)) => {
+                                    let assert_node = self.add_node(CfgNode::new_invariant(macro_args, annotation));
+                                    self.metadata_mut(assert_node).facts.push("assertion: checked here, then assumed".to_string());
+                                },
+                                // Taken as a fact by the code that follows, like a precondition, and never checked
+                                (Some(AnnotationRole::Assume), Some(annotation)) => {
+                                    let assume_node = self.add_node(CfgNode::new_assumption(macro_args, Some(annotation)));
+                                    self.metadata_mut(assume_node).facts.push(ASSUME_FACT.to_string());
+                                },
+                                // Asserted on the exit edge of the loop right before it, from the invariant and the
+                                // negated condition; not a cut point, so the paths keep what they know past it
+                                (Some(AnnotationRole::OnExit), Some(annotation)) => {
+                                    let after_loop = self.current_node.is_some_and(|node| self.loop_exits.contains(&node));
+                                    if !after_loop {
+                                        eprintln!("Warning: {}!({}) in {} does not follow a loop, it is checked as an assertion", macro_ident, macro_args, func_name);
+                                    }
+                                    let exit_node = self.add_node(CfgNode::new_assertion(macro_args, annotation));
+                                    let fact = if after_loop { "loop exit: checked when the loop ends, then assumed" } else { "assertion: checked here, then assumed" };
+                                    self.metadata_mut(exit_node).facts.push(fact.to_string());
                                 },
-                                "invariant" => CfgNode::new_invariant(macro_args.clone(), Expr::Macro(expr_macro.clone())),
                                 _ => {
-                                    let expr_str = quote!(#i).to_string();
-                                    CfgNode::new_statement(expr_str, Stmt::Expr(Expr::Macro(expr_macro.clone())))
-                                }
-                            };
-                            if macro_name.as_str() != "post" {
-                                self.add_node(node);
+                                    let expr_str = Self::render_expr(expr);
+                                    self.add_node(CfgNode::new_statement(expr_str, Stmt::Expr(Expr::Macro(expr_macro.clone()))));
+                                },
                             }
                         } else {
                             self.visit_expr(expr);
@@ -312,9 +639,84 @@ impl Visit<'_> for CfgBuilder {
                 _ => self.visit_stmt(stmt),
             }
         }
+        if let Some(assumptions) = entry_assumptions.take() {
+            self.add_entry_assumptions(assumptions);
+        }
+        // Postconditions of a function that never returns would hold vacuously
+        if let Some(reason) = self.never_returns_reason(i).filter(|_| !self.postconditions.is_empty()) {
+            for postcondition in std::mem::take(&mut self.postconditions) {
+                self.results.rejected_specs.push(format!("{} in {}: function never returns ({})", postcondition.label(), func_name, reason));
+            }
+        }
         self.add_postconditions();
 
         self.current_node = None;
+        Some(func_node)
+    }
+}
+
+impl Visit<'_> for CfgBuilder {
+    // Process Rust source file.
+    fn visit_file(&mut self, i: &SynFile) {
+        self.collect_fn_return_types(&i.items);
+        self.collect_diverging_functions(&i.items);
+        self.collect_constants(&i.items);
+        self.collect_local_functions(&i.items, &[]);
+        self.collect_logic_functions(&i.items);
+        self.collect_foreign_functions(&i.items);
+        for item in &i.items {
+            if let syn::Item::Enum(item_enum) = item {
+                let variants = item_enum.variants.iter().map(|v| v.ident.to_string()).collect();
+                self.file.local_enums.insert(item_enum.ident.to_string(), variants);
+            }
+            if let syn::Item::Macro(item_macro) = item {
+                self.collect_struct_invariant(item_macro);
+            }
+        }
+        visit::visit_file(self, i);
+    }
+
+    // Handle function definitions and statements
+    fn visit_item_fn(&mut self, i: &ItemFn) {
+        // Logic functions are definitions for the specs, not code to verify
+        if Self::is_logic_function(i) {
+            return;
+        }
+        self.build_function_cfg(self.qualified_name(&i.sig.ident.to_string()), i);
+    }
+
+    // Functions of inline modules are named with their module path ('geometry::area')
+    fn visit_item_mod(&mut self, i: &ItemMod) {
+        self.module_path.push(i.ident.to_string());
+        visit::visit_item_mod(self, i);
+        self.module_path.pop();
+    }
+
+    // Default method bodies of traits are analyzed like free functions, named 'Trait::method'
+    fn visit_item_trait(&mut self, i: &ItemTrait) {
+        let trait_name = i.ident.to_string();
+        for item in &i.items {
+            if let TraitItem::Method(method) = item {
+                if let Some(default_body) = &method.default {
+                    let item_fn = ItemFn {
+                        attrs: method.attrs.clone(),
+                        vis: syn::Visibility::Inherited,
+                        sig: method.sig.clone(),
+                        block: Box::new(default_body.clone()),
+                    };
+                    let func_name = self.qualified_name(&format!("{}::{}", trait_name, method.sig.ident));
+                    if let Some(func_node) = self.build_function_cfg(func_name, &item_fn) {
+                        // Nothing is known about the implementing type: calls on 'self' stay opaque
+                        self.metadata_mut(func_node).facts.push(format!("Self: {} (generic implementor)", trait_name));
+                    }
+                }
+            }
+        }
+    }
+
+    // Methods of 'impl' blocks are analyzed as 'Type::method', with the invariants of their type if it has any
+    fn visit_item_impl(&mut self, i: &ItemImpl) {
+        self.handle_struct_impl(i);
     }
 
     // Processes Rust expressions (loops, conditions, macros, etc.)
@@ -323,11 +725,22 @@ impl Visit<'_> for CfgBuilder {
             Expr::If(expr_if) => self.handle_if_statement(expr_if),
             Expr::While(expr_while) => self.handle_while_loop(expr_while),
             Expr::ForLoop(expr_for) => self.handle_for_loop(expr_for),
+            Expr::Loop(expr_loop) => self.handle_loop(expr_loop, LoopValue::Ignored),
+            Expr::Match(expr_match) => self.handle_match(expr_match),
             Expr::Return(expr_return) => {
                 self.handle_return_statement(expr_return);
             },
+            Expr::Break(expr_break) => self.handle_break(expr_break),
+            Expr::Continue(expr_continue) => self.handle_continue(expr_continue),
+            Expr::Assign(_) | Expr::AssignOp(_) => self.handle_assignment(i),
+            // The statements of an 'unsafe' block run in place, like the calls of extern functions they make
+            Expr::Unsafe(expr_unsafe) => self.visit_block(&expr_unsafe.block),
             Expr::Call(expr_call) => self.handle_call(expr_call),
             Expr::MethodCall(expr_method_call) => self.handle_method_call(expr_method_call),
+            // In the body of a loop, the measure of the innermost one
+            Expr::Macro(expr_macro) if !self.loop_stack.is_empty() && expr_macro.mac.path.get_ident().and_then(|ident| self.options.annotation_names.role_of(&ident.to_string())) == Some(AnnotationRole::Decreases) => {
+                self.handle_decreases(expr_macro);
+            },
             Expr::Macro(expr_macro) => {
                 self.process_macro(expr_macro); // method from the handle_macro module
             },
@@ -349,9 +762,10 @@ impl Visit<'_> for CfgBuilder {
                     }
                 }
                 // else a simple expression.
-                let expr_str = quote!(#i).to_string();
+                let expr_str = Self::render_expr(i);
                 let call_statement = Stmt::Expr(i.clone());
-                self.add_node(CfgNode::new_statement(expr_str, call_statement));
+                let statement_node = self.add_node(CfgNode::new_statement(expr_str, call_statement));
+                self.record_method_call(statement_node, i);
             },
         }
     }
@@ -363,12 +777,39 @@ impl Visit<'_> for CfgBuilder {
     }
     fn visit_stmt(&mut self, i: &Stmt) {
         match i {
+            // Initializers with branches are built as a sub-CFG assigning the variable
+            Stmt::Local(local) if self.handle_branching_let(local) => {},
+            // Calls of functions of the file configured to be inlined
+            Stmt::Local(local) if local.init.as_ref().is_some_and(|(_, init)| {
+                let target = match Self::local_variable(local) {
+                    Some(_) => CallTarget::Local(local.clone()),
+                    None => CallTarget::Place,
+                };
+                self.inline_local_call(Some(target), init, local.span().start().line)
+            }) => {},
             Stmt::Local(local) => {
                 // Handle local variable declarations
-                let local_str = format!("{}", quote!(#local));
-                self.add_node(CfgNode::new_statement(local_str, Stmt::Local(local.clone())));
+                let local_str = Self::render_local(local);
+                let var_types = self.local_types(local);
+                let indirect_call = local.init.as_ref()
+                    .and_then(|(_, init)| self.indirect_call(init, Self::pattern_bindings(&local.pat), local.span().start().line));
+                if let Some(call) = &indirect_call {
+                    self.add_call_preconditions(call);
+                }
+                let local_node = self.add_node(CfgNode::new_statement(local_str, Stmt::Local(local.clone())));
+                self.metadata_mut(local_node).var_types = var_types;
+                if let Some((_, init)) = &local.init {
+                    self.record_method_call(local_node, init);
+                    if let Some(var) = Self::local_variable(local) {
+                        self.record_construction(local_node, &var.to_string(), init);
+                    }
+                }
+                if let Some(call) = indirect_call {
+                    self.record_indirect_call(local_node, call);
+                }
                 
             }
+            Stmt::Expr(call @ Expr::Call(_)) | Stmt::Semi(call @ Expr::Call(_), _) if self.inline_local_call(None, call, call.span().start().line) => {},
             Stmt::Expr(expr) | Stmt::Semi(expr, _) => self.visit_expr(expr),
             _ => visit::visit_stmt(self, i),
         }
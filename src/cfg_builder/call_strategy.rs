@@ -0,0 +1,402 @@
+use proc_macro2::{Group, Ident, TokenStream, TokenTree};
+use quote::quote;
+use serde::Serialize;
+use std::collections::HashSet;
+use syn::visit::{self, Visit};
+use syn::{Block, Expr, ExprCall, ExprClosure, ExprReturn, FnArg, ItemFn, Local, Stmt};
+
+use crate::cfg_builder::builder::CfgBuilder;
+use crate::config::{AnnotationRole, CallStrategy};
+
+// Strategy used for one call of a function of the file, for the report
+#[derive(Debug, Clone, Serialize)]
+pub struct CallStrategyRecord {
+    pub caller: String,
+    pub callee: String,
+    pub line: usize,
+    pub strategy: CallStrategy,
+    pub reason: String, // why this strategy: 'default', 'secured.toml', or why inlining was not possible
+    pub depth: usize, // number of inlined calls the call site is nested in
+}
+
+// Variables bound in a body and its explicit 'return's
+#[derive(Default)]
+struct BodyCollector {
+    bindings: HashSet<String>,
+    returns: Vec<Option<Expr>>,
+}
+
+impl<'ast> Visit<'ast> for BodyCollector {
+    fn visit_local(&mut self, i: &'ast Local) {
+        self.bindings.extend(CfgBuilder::pattern_bindings(&i.pat));
+        visit::visit_local(self, i);
+    }
+
+    fn visit_expr_return(&mut self, i: &'ast ExprReturn) {
+        self.returns.push(i.expr.as_deref().cloned());
+        visit::visit_expr_return(self, i);
+    }
+
+    fn visit_expr_closure(&mut self, _i: &'ast ExprClosure) {}
+}
+
+impl CfgBuilder {
+    // Record every top-level function of the file (and of its inline modules) by qualified name
+    pub fn collect_local_functions(&mut self, items: &[syn::Item], module_path: &[String]) {
+        for item in items {
+            match item {
+                syn::Item::Fn(item_fn) => {
+                    let name = module_path.iter().cloned().chain(std::iter::once(item_fn.sig.ident.to_string())).collect::<Vec<_>>().join("::");
+                    self.file.local_functions.insert(name, item_fn.clone());
+                },
+                syn::Item::Mod(item_mod) => {
+                    if let Some((_, items)) = &item_mod.content {
+                        let mut path = module_path.to_vec();
+                        path.push(item_mod.ident.to_string());
+                        self.collect_local_functions(items, &path);
+                    }
+                },
+                _ => {},
+            }
+        }
+    }
+
+    // Function of the file called by 'f(..)': looked up in the current module, then at the top level
+    pub fn local_callee(&self, call: &ExprCall) -> Option<(String, ItemFn)> {
+        let Expr::Path(expr_path) = &*call.func else { return None };
+        let name = expr_path.path.get_ident()?.to_string();
+        [self.qualified_name(&name), name].into_iter()
+            .find_map(|name| self.file.local_functions.get(&name).map(|item_fn| (name, item_fn.clone())))
+    }
+
+    // Build the body of the called function in place of the call when the inline strategy applies to it.
+    // 'target' is the variable receiving the result ('let y = f(x)' or 'y = f(x)'), if any.
+    // Returns false when the call is verified with the callee's contract instead.
+    pub fn inline_local_call(&mut self, target: Option<CallTarget>, value: &Expr, line: usize) -> bool {
+        let Expr::Call(call) = value else { return false };
+        if self.is_diverging_call(call) {
+            return false;
+        }
+        let Some((callee, item_fn)) = self.local_callee(call) else { return false };
+        let destructured = matches!(target, Some(CallTarget::Place));
+        let (strategy, reason) = self.choose_call_strategy(&callee, &item_fn, line, destructured);
+        self.results.call_strategies.push(CallStrategyRecord {
+            caller: self.current_function.clone(),
+            callee: callee.clone(),
+            line,
+            strategy,
+            reason,
+            depth: self.inline_stack.len(),
+        });
+        if strategy != CallStrategy::Inline {
+            return false;
+        }
+
+        let body = self.inlined_body(&callee, &item_fn, call);
+        self.inline_stack.push(callee.clone());
+        let fact = format!("inlined call of {} (depth {})", callee, self.inline_stack.len());
+        match target {
+            Some(CallTarget::Local(local)) => {
+                let var = Self::local_variable(&local).expect("checked by the caller");
+                let declaration_node = self.declare_local(&local);
+                self.metadata_mut(declaration_node).facts.push(fact);
+                self.visit_block(&self.assign_block_value(&body, &var));
+            },
+            Some(CallTarget::Variable(var)) => {
+                let body = self.assign_block_value(&body, &var);
+                self.visit_block(&body);
+            },
+            Some(CallTarget::Place) => unreachable!("results not bound to a variable are not inlined"),
+            None => self.visit_block(&body),
+        }
+        self.inline_stack.pop();
+        true
+    }
+
+    // The configured strategy of a call site, or the contract when the body can't be inlined
+    fn choose_call_strategy(&self, callee: &str, item_fn: &ItemFn, line: usize, destructured: bool) -> (CallStrategy, String) {
+        let (comment_pre, comment_post) = self.call_comments(line);
+        if !comment_pre.is_empty() || !comment_post.is_empty() {
+            return (CallStrategy::Contract, "contract given at the call site".to_string());
+        }
+        let configured = self.options.call_settings.site.iter().rev().find(|site| {
+            site.callee == callee
+                && site.caller.as_ref().is_none_or(|caller| *caller == self.current_function)
+                && site.line.is_none_or(|site_line| site_line == line)
+        });
+        let (strategy, reason) = match configured {
+            Some(site) => (site.strategy, "secured.toml".to_string()),
+            None => (self.options.call_settings.default, "default".to_string()),
+        };
+        if strategy == CallStrategy::Contract {
+            return (strategy, reason);
+        }
+
+        let fallback = if callee == self.current_function || self.inline_stack.iter().any(|inlined| inlined == callee) {
+            Some("recursive call".to_string())
+        } else if self.inline_stack.len() >= self.options.call_settings.max_inline_depth {
+            Some(format!("inlining depth limit ({})", self.options.call_settings.max_inline_depth))
+        } else if Self::has_early_return(item_fn) {
+            Some("early return in the callee".to_string())
+        } else if destructured {
+            Some("result not bound to a single variable".to_string())
+        } else {
+            None
+        };
+        match fallback {
+            Some(fallback) => (CallStrategy::Contract, format!("{}, not inlined: {}", reason, fallback)),
+            None => (strategy, reason),
+        }
+    }
+
+    // A 'return' anywhere but as the last statement can't be inlined in the flat CFG
+    pub(crate) fn has_early_return(item_fn: &ItemFn) -> bool {
+        let mut collector = BodyCollector::default();
+        collector.visit_block(&item_fn.block);
+        let final_return = matches!(item_fn.block.stmts.last(), Some(Stmt::Semi(Expr::Return(_), _)) | Some(Stmt::Expr(Expr::Return(_))));
+        collector.returns.len() > usize::from(final_return)
+    }
+
+    // Body of the callee for the call site: parameters bound to the arguments, variables renamed
+    // '<callee>_<name>' so they don't clash with the caller's, pre/post annotations left out, and the
+    // returned value as the tail expression
+    pub(crate) fn inlined_body(&self, callee: &str, item_fn: &ItemFn, call: &ExprCall) -> Block {
+        let mut collector = BodyCollector::default();
+        collector.visit_block(&item_fn.block);
+        let mut names = collector.bindings;
+        for input in &item_fn.sig.inputs {
+            if let FnArg::Typed(pat_type) = input {
+                names.extend(Self::pattern_bindings(&pat_type.pat));
+            }
+        }
+        let prefix = format!("{}_", callee.rsplit("::").next().unwrap_or(callee));
+
+        let mut stmts: Vec<Stmt> = Vec::new();
+        for (input, arg) in item_fn.sig.inputs.iter().zip(call.args.iter()) {
+            if let FnArg::Typed(pat_type) = input {
+                let pat = &pat_type.pat;
+                let pat = Self::rename_idents(quote!(#pat), &names, &prefix);
+                stmts.push(syn::parse_quote!(let #pat = #arg;));
+            }
+        }
+        let body_stmts: Vec<&Stmt> = item_fn.block.stmts.iter().filter(|stmt| !self.is_contract_annotation(stmt)).collect();
+        let body = Self::rename_idents(quote!({ #(#body_stmts)* }), &names, &prefix);
+        let mut body: Block = syn::parse2(body).expect("Failed to parse the inlined body");
+        // 'return v;' at the end is the tail 'v'
+        if let Some(Stmt::Semi(Expr::Return(expr_return), _)) | Some(Stmt::Expr(Expr::Return(expr_return))) = body.stmts.last().cloned() {
+            body.stmts.pop();
+            if let Some(value) = expr_return.expr {
+                body.stmts.push(Stmt::Expr(*value));
+            }
+        }
+        stmts.extend(body.stmts);
+        Block { brace_token: body.brace_token, stmts }
+    }
+
+    pub(crate) fn is_contract_annotation(&self, stmt: &Stmt) -> bool {
+        let Stmt::Semi(Expr::Macro(expr_macro), _) = stmt else { return false };
+        let role = expr_macro.mac.path.get_ident().and_then(|ident| self.options.annotation_names.role_of(&ident.to_string()));
+        matches!(role, Some(AnnotationRole::Pre) | Some(AnnotationRole::Post))
+    }
+
+    // Rename the identifiers in 'names', except field names ('x.len') and method names ('x.len()')
+    fn rename_idents(tokens: TokenStream, names: &HashSet<String>, prefix: &str) -> TokenStream {
+        let mut renamed = Vec::new();
+        let mut after_dot = false;
+        let mut previous_dot = false;
+        for token in tokens {
+            let is_dot = matches!(&token, TokenTree::Punct(punct) if punct.as_char() == '.');
+            match token {
+                TokenTree::Group(group) => {
+                    let mut new_group = Group::new(group.delimiter(), Self::rename_idents(group.stream(), names, prefix));
+                    new_group.set_span(group.span());
+                    renamed.push(TokenTree::Group(new_group));
+                },
+                TokenTree::Ident(ident) if !after_dot && names.contains(&ident.to_string()) => {
+                    renamed.push(TokenTree::Ident(Ident::new(&format!("{}{}", prefix, ident), ident.span())));
+                },
+                token => renamed.push(token),
+            }
+            // A single '.' is a field or method access, '..' a range
+            after_dot = is_dot && !previous_dot;
+            previous_dot = is_dot;
+        }
+        renamed.into_iter().collect()
+    }
+
+    // Preconditions and postconditions of a function of the file for one of its calls: the parameters
+    // replaced by the arguments, and the variable it returns by 'result'
+    pub fn local_contract(&self, item_fn: &ItemFn, call: &ExprCall) -> (Vec<String>, Vec<String>) {
+        let params: Vec<String> = item_fn.sig.inputs.iter()
+            .filter_map(|input| match input {
+                FnArg::Typed(pat_type) => Self::pattern_bindings(&pat_type.pat).into_iter().next(),
+                FnArg::Receiver(_) => None,
+            })
+            .collect();
+        let returned = Self::returned_variable(item_fn);
+        let args: Vec<&Expr> = call.args.iter().collect();
+        let instantiate = |condition: &Expr| self.instantiate_contract(condition, &params, &args, returned.as_deref());
+
+        let (preconditions, postconditions) = self.contract_conditions(item_fn);
+        (preconditions.iter().map(instantiate).collect(), postconditions.iter().map(instantiate).collect())
+    }
+
+    // The 'pre!' and 'post!' conditions of a function, as written
+    pub(crate) fn contract_conditions(&self, item_fn: &ItemFn) -> (Vec<Expr>, Vec<Expr>) {
+        let (mut preconditions, mut postconditions) = (Vec::new(), Vec::new());
+        for stmt in &item_fn.block.stmts {
+            let Stmt::Semi(Expr::Macro(expr_macro), _) = stmt else { continue };
+            let Some(role) = expr_macro.mac.path.get_ident().and_then(|ident| self.options.annotation_names.role_of(&ident.to_string())) else { continue };
+            let Expr::Macro(canonical) = Self::canonical_annotation(expr_macro, role) else { continue };
+            let Ok(condition) = syn::parse2::<Expr>(canonical.mac.tokens) else { continue };
+            match role {
+                AnnotationRole::Pre => preconditions.push(condition),
+                AnnotationRole::Post => postconditions.push(condition),
+                _ => {},
+            }
+        }
+        (preconditions, postconditions)
+    }
+
+    // A contract condition with the parameters replaced by the arguments of a call, and the returned variable, if
+    // any, by 'result'
+    pub(crate) fn instantiate_contract(&self, condition: &Expr, params: &[String], args: &[&Expr], returned: Option<&str>) -> String {
+        // Through placeholders, so that an argument naming another parameter is not replaced again
+        let mut condition = condition.clone();
+        for (i, param) in params.iter().enumerate() {
+            let placeholder: Expr = syn::parse_str(&format!("__arg{}", i)).expect("Failed to parse placeholder");
+            condition = self.substitute_in_contract(&condition, param, &placeholder);
+        }
+        if let Some(returned) = returned {
+            condition = self.recursive_substitution(&condition, returned, &syn::parse_quote!(result));
+        }
+        for (i, arg) in args.iter().enumerate().take(params.len()) {
+            condition = self.substitute_in_contract(&condition, &format!("__arg{}", i), arg);
+        }
+        // '*out' with 'out' bound to '&mut x' is 'x'
+        Self::render_expr(&Self::lower_references(&condition))
+    }
+
+    // The variable a function returns on every path ('return r' and a tail 'r'), named 'result' in its contract
+    pub(crate) fn returned_variable(item_fn: &ItemFn) -> Option<String> {
+        let mut collector = BodyCollector::default();
+        collector.visit_block(&item_fn.block);
+        let mut returned: Vec<Option<Expr>> = collector.returns;
+        if let Some(Stmt::Expr(tail)) = item_fn.block.stmts.last() {
+            if !matches!(tail, Expr::Return(_)) {
+                returned.push(Some(tail.clone()));
+            }
+        }
+        let names: HashSet<Option<String>> = returned.iter()
+            .map(|value| match value {
+                Some(Expr::Path(expr_path)) => expr_path.path.get_ident().map(|ident| ident.to_string()),
+                _ => None,
+            })
+            .collect();
+        match names.into_iter().collect::<Vec<_>>().as_slice() {
+            [Some(name)] => Some(name.clone()),
+            _ => None,
+        }
+    }
+
+    // Which strategy was used for each call of a function of the file
+    pub fn call_strategy_report(&self) -> String {
+        let mut report = String::from("Call strategies:\n");
+        for record in &self.results.call_strategies {
+            let strategy = match record.strategy {
+                CallStrategy::Contract => "contract",
+                CallStrategy::Inline => "inline",
+            };
+            let nesting = if record.depth > 0 { format!(", inside {} inlined call(s)", record.depth) } else { String::new() };
+            report.push_str(&format!("  {} -> {} (line {}): {} ({}{})\n", record.caller, record.callee, record.line, strategy, record.reason, nesting));
+        }
+        report
+    }
+}
+
+// Where the result of an inlined call goes
+pub enum CallTarget {
+    Local(Local), // 'let y = f(x);'
+    Variable(syn::Ident), // 'y = f(x);'
+    Place, // 'let (a, b) = f(x);' or 'p.x += f(x);', not inlined
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{self, built_with, discharge};
+    use crate::verifier::VcOutcome;
+
+    const SOURCE: &str = "fn inc(a: i32) -> i32 {
+        post!(r > a);
+        let r = a + 1;
+        return r;
+    }
+    fn next(x: i32) -> i32 {
+        pre!(true);
+        post!(y == x + 1);
+        let y = inc(x);
+        return y;
+    }";
+
+    fn verdicts(default: CallStrategy) -> (Vec<VcOutcome>, String) {
+        let mut builder = built_with(SOURCE, |builder| builder.options.call_settings.default = default);
+        (discharge(&mut builder), builder.call_strategy_report())
+    }
+
+    // The contract only tells 'y > x', the inlined body gives the exact value
+    #[test]
+    fn inlining_is_more_precise_than_the_contract() {
+        let (outcomes, report) = verdicts(CallStrategy::Contract);
+        assert!(matches!(outcomes.as_slice(), [VcOutcome::Counterexample(_)]));
+        assert_eq!(report, "Call strategies:\n  next -> inc (line 9): contract (default)\n");
+        let (outcomes, report) = verdicts(CallStrategy::Inline);
+        assert!(matches!(outcomes.as_slice(), [VcOutcome::Valid]));
+        assert_eq!(report, "Call strategies:\n  next -> inc (line 9): inline (default)\n");
+    }
+
+    #[test]
+    fn recursive_calls_keep_the_contract() {
+        let builder = built_with("fn down(n: i32) -> i32 {
+                pre!(n >= 0);
+                post!(r >= 0);
+                let r = if n == 0 { 0 } else { down(n - 1) };
+                return r;
+            }", |builder| builder.options.call_settings.default = CallStrategy::Inline);
+        assert_eq!(builder.results.call_strategies.len(), 1);
+        assert_eq!(builder.results.call_strategies[0].strategy, CallStrategy::Contract);
+        assert_eq!(builder.results.call_strategies[0].reason, "default, not inlined: recursive call");
+    }
+
+    // The precondition of the callee is checked with what the caller knows at the call, and the caller
+    // still knows it past the call
+    #[test]
+    fn the_precondition_of_a_callee_keeps_the_facts_of_the_caller() {
+        let source = "fn f(a: i32) -> i32 {
+                pre!(a > 0);
+                post!(r > a);
+                let r = a + 1;
+                r
+            }
+            fn g(x: i32) -> i32 {
+                pre!(x > 5);
+                post!(y > 5);
+                let y = f(x);
+                y
+            }";
+        assert!(matches!(test_support::verdicts(source).as_slice(), [VcOutcome::Valid, VcOutcome::Valid]));
+        // Without the caller's precondition, the one of the callee doesn't hold
+        let outcomes = test_support::verdicts(&source.replace("pre!(x > 5)", "pre!(true)"));
+        assert!(matches!(outcomes.as_slice(), [VcOutcome::Valid, VcOutcome::Counterexample(_)]));
+    }
+
+    #[test]
+    fn contracts_only_parenthesize_the_arguments_that_need_it() {
+        let builder = CfgBuilder::new();
+        let item_fn: ItemFn = syn::parse_str("fn f(a: i32, b: i32) -> i32 { pre!(a * 2 > b); post!(r > a); let r = a + b; r }").unwrap();
+        let call: ExprCall = syn::parse_str("f(x + 1, y)").unwrap();
+        let (preconditions, postconditions) = builder.local_contract(&item_fn, &call);
+        assert_eq!(preconditions, ["(x + 1) * 2 > y"]);
+        assert_eq!(postconditions, ["result > x + 1"]);
+    }
+}
@@ -0,0 +1,78 @@
+use crate::cfg_builder::builder::CfgBuilder;
+use crate::config::AnnotationNames;
+
+// Marker of a spec written as a comment: '//@ pre n >= 0'
+const COMMENT_SPEC_MARKER: &str = "//@";
+
+// Words that may come before 'fn' in a function signature
+const SIGNATURE_QUALIFIERS: [&str; 6] = ["const", "async", "unsafe", "extern", "\"C\"", "default"];
+
+impl CfgBuilder {
+    // Turn the '//@ <name> <condition>' comments of a source file into the annotation macros they stand for,
+    // for files that can't depend on the macros. A comment in a body becomes 'name!(condition);' on its own
+    // line; the comments right above a function go at the start of its body. Line numbers don't change.
+    // Returns the source and a warning for each comment that isn't a spec.
+    pub fn expand_comment_specs(content: &str, names: &AnnotationNames) -> (String, Vec<String>) {
+        let lines: Vec<&str> = content.lines().collect();
+        let mut expanded = Vec::with_capacity(lines.len());
+        let mut warnings = Vec::new();
+        let mut pending: Vec<String> = Vec::new(); // specs of the next function body
+        for (i, line) in lines.iter().enumerate() {
+            let trimmed = line.trim_start();
+            // '//@ call' comments are the contracts of indirect calls, read where the call is built
+            if let Some(spec) = trimmed.strip_prefix(COMMENT_SPEC_MARKER).filter(|spec| !spec.starts_with(" call ")) {
+                match Self::comment_spec_macro(spec, names) {
+                    Ok(mac) if Self::precedes_function(&lines[i + 1..]) => {
+                        pending.push(mac);
+                        expanded.push(line.to_string());
+                    },
+                    Ok(mac) => expanded.push(format!("{}{}", &line[..line.len() - trimmed.len()], mac)),
+                    Err(reason) => {
+                        warnings.push(format!("line {}: '{}' is not a spec: {}", i + 1, trimmed, reason));
+                        expanded.push(line.to_string());
+                    },
+                }
+                continue;
+            }
+            // The body of the function the pending specs are for opens at its first '{'
+            match line.find('{').filter(|_| !pending.is_empty()) {
+                Some(brace) => {
+                    expanded.push(format!("{} {} {}", &line[..=brace], pending.join(" "), &line[brace + 1..]));
+                    pending.clear();
+                },
+                None => expanded.push(line.to_string()),
+            }
+        }
+        let mut expanded = expanded.join("\n");
+        if content.ends_with('\n') {
+            expanded.push('\n');
+        }
+        (expanded, warnings)
+    }
+
+    // 'pre n >= 0' -> 'pre!(n >= 0);', for the names of annotations
+    fn comment_spec_macro(spec: &str, names: &AnnotationNames) -> Result<String, String> {
+        let spec = spec.trim();
+        let (name, condition) = spec.split_once(char::is_whitespace).unwrap_or((spec, ""));
+        if names.role_of(name).is_none() {
+            return Err(format!("'{}' is not an annotation name", name));
+        }
+        let condition = condition.trim().trim_end_matches(';');
+        if let Err(e) = crate::spec_parser::parse_condition(condition) {
+            return Err(format!("invalid condition: {}", e));
+        }
+        Ok(format!("{}!({});", name, condition))
+    }
+
+    // Whether the next line that isn't a comment, an attribute or blank starts a function signature
+    fn precedes_function(lines: &[&str]) -> bool {
+        let Some(next) = lines.iter().map(|line| line.trim()).find(|line| !line.is_empty() && !line.starts_with("//") && !line.starts_with("#[")) else {
+            return false;
+        };
+        let words: Vec<&str> = next.split_whitespace().collect();
+        match words.iter().position(|word| *word == "fn") {
+            Some(fn_position) => words[..fn_position].iter().all(|word| word.starts_with("pub") || SIGNATURE_QUALIFIERS.contains(word)),
+            None => false,
+        }
+    }
+}
@@ -0,0 +1,206 @@
+use std::collections::HashMap;
+use std::fmt;
+use syn::{BinOp, Expr, Item, Lit, Type, UnOp};
+
+use crate::cfg_builder::builder::CfgBuilder;
+
+// Value of a 'const' or immutable 'static' item, evaluated when the file is analyzed
+#[derive(Clone, Debug, PartialEq)]
+pub enum ConstValue {
+    Int(i128),
+    Bool(bool),
+}
+
+impl ConstValue {
+    pub fn to_expr(&self) -> Option<Expr> {
+        let value = match self {
+            ConstValue::Int(value) => value.to_string(),
+            ConstValue::Bool(value) => value.to_string(),
+        };
+        syn::parse_str(&value).ok()
+    }
+}
+
+impl fmt::Display for ConstValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConstValue::Int(value) => write!(f, "{}", value),
+            ConstValue::Bool(value) => write!(f, "{}", value),
+        }
+    }
+}
+
+impl CfgBuilder {
+    // Evaluate the 'const'/'static' items of the file so that specs can refer to them ('n <= MAX')
+    pub fn collect_constants(&mut self, items: &[Item]) {
+        let mut pending: Vec<(String, Expr)> = items.iter()
+            .filter_map(|item| match item {
+                Item::Const(item_const) => Some((item_const.ident.to_string(), (*item_const.expr).clone())),
+                // A 'static mut' can change at runtime and stays an unknown symbol
+                Item::Static(item_static) if item_static.mutability.is_none() => {
+                    Some((item_static.ident.to_string(), (*item_static.expr).clone()))
+                },
+                _ => None,
+            })
+            .collect();
+
+        // Constants may refer to constants declared later: evaluate until nothing changes
+        loop {
+            let remaining = pending.len();
+            pending.retain(|(name, expr)| match self.eval_const_expr(expr) {
+                Some(value) => {
+                    self.file.constants.insert(name.clone(), value);
+                    false
+                },
+                None => true,
+            });
+            if pending.is_empty() || pending.len() == remaining {
+                break;
+            }
+        }
+    }
+
+    // Evaluate integer and boolean constant expressions, None when they need more than that
+    pub fn eval_const_expr(&self, expr: &Expr) -> Option<ConstValue> {
+        self.eval_expr_in(expr, &HashMap::new())
+    }
+
+    // Same evaluation, with variables taking their values from 'env' (e.g. a counterexample)
+    pub fn eval_expr_in(&self, expr: &Expr, env: &HashMap<String, ConstValue>) -> Option<ConstValue> {
+        match expr {
+            Expr::Lit(expr_lit) => match &expr_lit.lit {
+                Lit::Int(lit_int) => lit_int.base10_parse::<i128>().ok().map(ConstValue::Int),
+                Lit::Bool(lit_bool) => Some(ConstValue::Bool(lit_bool.value)),
+                _ => None,
+            },
+            Expr::Path(expr_path) => expr_path.path.get_ident()
+                .map(|ident| ident.to_string())
+                .and_then(|name| env.get(&name).or_else(|| self.file.constants.get(&name)).cloned()),
+            Expr::Paren(paren) => self.eval_expr_in(&paren.expr, env),
+            Expr::Group(group) => self.eval_expr_in(&group.expr, env),
+            Expr::Cast(cast) => Self::cast_value(self.eval_expr_in(&cast.expr, env)?, &cast.ty),
+            Expr::Unary(unary) => match (&unary.op, self.eval_expr_in(&unary.expr, env)?) {
+                (UnOp::Neg(_), ConstValue::Int(value)) => value.checked_neg().map(ConstValue::Int),
+                (UnOp::Not(_), ConstValue::Bool(value)) => Some(ConstValue::Bool(!value)),
+                _ => None,
+            },
+            Expr::Binary(bin) => {
+                let left = self.eval_expr_in(&bin.left, env)?;
+                let right = self.eval_expr_in(&bin.right, env)?;
+                match (left, right) {
+                    (ConstValue::Int(l), ConstValue::Int(r)) => match bin.op {
+                        BinOp::Add(_) => l.checked_add(r).map(ConstValue::Int),
+                        BinOp::Sub(_) => l.checked_sub(r).map(ConstValue::Int),
+                        BinOp::Mul(_) => l.checked_mul(r).map(ConstValue::Int),
+                        BinOp::Div(_) => l.checked_div(r).map(ConstValue::Int),
+                        BinOp::Rem(_) => l.checked_rem(r).map(ConstValue::Int),
+                        BinOp::Shl(_) => u32::try_from(r).ok().and_then(|r| l.checked_shl(r)).map(ConstValue::Int),
+                        BinOp::Shr(_) => u32::try_from(r).ok().and_then(|r| l.checked_shr(r)).map(ConstValue::Int),
+                        BinOp::BitAnd(_) => Some(ConstValue::Int(l & r)),
+                        BinOp::BitOr(_) => Some(ConstValue::Int(l | r)),
+                        BinOp::BitXor(_) => Some(ConstValue::Int(l ^ r)),
+                        BinOp::Eq(_) => Some(ConstValue::Bool(l == r)),
+                        BinOp::Ne(_) => Some(ConstValue::Bool(l != r)),
+                        BinOp::Lt(_) => Some(ConstValue::Bool(l < r)),
+                        BinOp::Le(_) => Some(ConstValue::Bool(l <= r)),
+                        BinOp::Gt(_) => Some(ConstValue::Bool(l > r)),
+                        BinOp::Ge(_) => Some(ConstValue::Bool(l >= r)),
+                        _ => None,
+                    },
+                    (ConstValue::Bool(l), ConstValue::Bool(r)) => match bin.op {
+                        BinOp::And(_) => Some(ConstValue::Bool(l && r)),
+                        BinOp::Or(_) => Some(ConstValue::Bool(l || r)),
+                        BinOp::Eq(_) => Some(ConstValue::Bool(l == r)),
+                        BinOp::Ne(_) => Some(ConstValue::Bool(l != r)),
+                        _ => None,
+                    },
+                    _ => None,
+                }
+            },
+            _ => None,
+        }
+    }
+
+    // 'value as ty' as Rust computes it: wrapped to the bit width of the integer type and read with its
+    // signedness ('300 as u8' is 44, '-1 as u32' is 4294967295). 'usize'/'isize' depend on the target, so
+    // only the values both widths keep unchanged are folded. None for the other types, which stay symbolic.
+    fn cast_value(value: ConstValue, ty: &Type) -> Option<ConstValue> {
+        let Type::Path(type_path) = ty else { return None };
+        let value = match value {
+            ConstValue::Int(value) => value,
+            ConstValue::Bool(value) => value as i128,
+        };
+        let (bits, signed) = match type_path.path.get_ident()?.to_string().as_str() {
+            "i8" => (8, true),
+            "i16" => (16, true),
+            "i32" => (32, true),
+            "i64" => (64, true),
+            "i128" => return Some(ConstValue::Int(value)),
+            "u8" => (8, false),
+            "u16" => (16, false),
+            "u32" => (32, false),
+            "u64" => (64, false),
+            "u128" => return (value >= 0).then_some(ConstValue::Int(value)),
+            "isize" => return i32::try_from(value).is_ok().then_some(ConstValue::Int(value)),
+            "usize" => return u32::try_from(value).is_ok().then_some(ConstValue::Int(value)),
+            _ => return None,
+        };
+        let modulus = 1i128 << bits;
+        let wrapped = value.rem_euclid(modulus);
+        Some(ConstValue::Int(if signed && wrapped >= modulus / 2 { wrapped - modulus } else { wrapped }))
+    }
+
+    // Replace the constants of the file by their values in a verification condition
+    pub fn substitute_constants(&self, expr: &Expr) -> Expr {
+        let mut names: Vec<&String> = self.file.constants.keys().collect();
+        names.sort();
+        names.into_iter().fold(expr.clone(), |expr, name| match self.file.constants[name].to_expr() {
+            Some(value) => self.recursive_substitution(&expr, name, &value),
+            None => expr,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use quote::ToTokens;
+    use crate::config::SolverSettings;
+    use crate::verifier::{verify_str_implication, VcOutcome};
+
+    fn constants_of(source: &str) -> CfgBuilder {
+        let file: syn::File = syn::parse_str(source).unwrap();
+        let mut builder = CfgBuilder::new();
+        builder.collect_constants(&file.items);
+        builder
+    }
+
+    #[test]
+    fn casts_wrap_to_the_target_type() {
+        let builder = constants_of("const BIG: i32 = 300; const SMALL: u8 = BIG as u8; const MINUS: u32 = -1i32 as u32; const BYTE: i8 = 200 as i8;");
+        assert_eq!(builder.file.constants["SMALL"], ConstValue::Int(44));
+        assert_eq!(builder.file.constants["MINUS"], ConstValue::Int(4294967295));
+        assert_eq!(builder.file.constants["BYTE"], ConstValue::Int(-56));
+    }
+
+    #[test]
+    fn casts_with_a_target_dependent_width_stay_symbolic() {
+        let builder = constants_of("const BIG: i64 = 5000000000; const WIDE: usize = BIG as usize; const NARROW: usize = 7 as usize;");
+        assert!(!builder.file.constants.contains_key("WIDE"));
+        assert_eq!(builder.file.constants["NARROW"], ConstValue::Int(7));
+    }
+
+    // Folded as the identity, 'SMALL' would be 300 and 'x == SMALL ==> x > 255' would be proved
+    #[test]
+    fn folded_cast_decides_the_verdict() {
+        let builder = constants_of("const BIG: i32 = 300; const SMALL: u8 = BIG as u8;");
+        let condition = builder.substitute_constants(&syn::parse_str("(x == SMALL) >> (x > 255)").unwrap());
+        assert_eq!(condition.to_token_stream().to_string().replace(['(', ')', ' '], ""), "x==44>>x>255");
+        let verdict = |condition: &str| {
+            let condition = builder.substitute_constants(&syn::parse_str(condition).unwrap());
+            verify_str_implication(&condition.to_token_stream().to_string(), &SolverSettings::default()).unwrap()
+        };
+        assert!(matches!(verdict("(x == SMALL) >> (x > 255)"), VcOutcome::Counterexample(_)));
+        assert!(matches!(verdict("(x == SMALL) >> (x == 44)"), VcOutcome::Valid));
+    }
+}
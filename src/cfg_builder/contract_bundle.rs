@@ -0,0 +1,107 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+use syn::{Expr, ExprCall, FnArg, ReturnType};
+
+use crate::cfg_builder::builder::CfgBuilder;
+
+// Contracts of the verified functions of a crate, exported by 'contracts export' and imported by the crates
+// calling into it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContractBundle {
+    #[serde(rename = "crate")]
+    pub crate_name: String, // path prefix of the calls into the crate ('mathlib::clamp')
+    pub source: String, // file the contracts were proved in
+    pub functions: Vec<BundledContract>,
+}
+
+// Contract of one function, written over its parameters, with 'result' for the returned value
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BundledContract {
+    pub name: String, // qualified in the crate, e.g. 'geometry::area'
+    pub params: Vec<(String, String)>, // parameters and their types
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub returns: Option<String>,
+    #[serde(default)]
+    pub preconditions: Vec<String>,
+    #[serde(default)]
+    pub postconditions: Vec<String>,
+}
+
+impl ContractBundle {
+    pub fn load(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let content = fs::read_to_string(path).map_err(|e| format!("can't read the contract bundle {}: {}", path.display(), e))?;
+        serde_json::from_str(&content).map_err(|e| format!("invalid contract bundle {}: {}", path.display(), e).into())
+    }
+}
+
+impl CfgBuilder {
+    // Bundle of the contracts of the functions of the file whose obligations were all proved ('verified'
+    // holds their qualified names). Functions without 'pre!' or 'post!' have nothing to export.
+    pub fn export_contracts(&self, crate_name: &str, source: &str, verified: &HashSet<String>) -> ContractBundle {
+        let mut names: Vec<&String> = self.file.local_functions.keys().filter(|name| verified.contains(*name)).collect();
+        names.sort();
+        let mut functions = Vec::new();
+        for name in names {
+            let item_fn = &self.file.local_functions[name];
+            let (preconditions, postconditions) = self.contract_conditions(item_fn);
+            if preconditions.is_empty() && postconditions.is_empty() {
+                continue;
+            }
+            let params: Vec<(String, String)> = item_fn.sig.inputs.iter()
+                .filter_map(|input| match input {
+                    FnArg::Typed(pat_type) => Self::pattern_bindings(&pat_type.pat).into_iter().next()
+                        .map(|param| (param, Self::render_type(&pat_type.ty))),
+                    FnArg::Receiver(_) => None,
+                })
+                .collect();
+            // The variable the function returns is 'result' for its callers
+            let returned = Self::returned_variable(item_fn);
+            let instantiate = |condition: &Expr| {
+                let condition = match &returned {
+                    Some(returned) => self.recursive_substitution(condition, returned, &syn::parse_quote!(result)),
+                    None => condition.clone(),
+                };
+                Self::render_expr(&Self::without_parens(&condition))
+            };
+            functions.push(BundledContract {
+                name: name.clone(),
+                params,
+                returns: match &item_fn.sig.output {
+                    ReturnType::Type(_, ty) => Some(Self::render_type(ty)),
+                    ReturnType::Default => None,
+                },
+                preconditions: preconditions.iter().map(instantiate).collect(),
+                postconditions: postconditions.iter().map(instantiate).collect(),
+            });
+        }
+        ContractBundle { crate_name: crate_name.to_string(), source: source.to_string(), functions }
+    }
+
+    // Imported contract of a call into another crate: 'mathlib::clamp(x)', or 'clamp(x)' once imported by 'use'
+    pub fn bundled_callee(&self, call: &ExprCall) -> Option<(&ContractBundle, &BundledContract)> {
+        let Expr::Path(func) = &*call.func else { return None };
+        let segments: Vec<String> = func.path.segments.iter().map(|segment| segment.ident.to_string()).collect();
+        self.options.imported_contracts.iter().find_map(|bundle| {
+            let in_crate = match segments.split_first() {
+                Some((first, rest)) if first == &bundle.crate_name && !rest.is_empty() => rest.join("::"),
+                _ => segments.join("::"),
+            };
+            bundle.functions.iter().find(|function| function.name == in_crate).map(|function| (bundle, function))
+        })
+    }
+
+    // Imported contract with the parameters replaced by the arguments of the call
+    pub fn bundled_contract(&self, function: &BundledContract, call: &ExprCall) -> (Vec<String>, Vec<String>) {
+        let params: Vec<String> = function.params.iter().map(|(name, _)| name.clone()).collect();
+        let args: Vec<&Expr> = call.args.iter().collect();
+        let instantiate = |conditions: &[String]| -> Vec<String> {
+            conditions.iter().map(|condition| match syn::parse_str(condition) {
+                Ok(condition) => self.instantiate_contract(&condition, &params, &args, None),
+                Err(_) => condition.clone(),
+            }).collect()
+        };
+        (instantiate(&function.preconditions), instantiate(&function.postconditions))
+    }
+}
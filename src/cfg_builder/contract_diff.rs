@@ -0,0 +1,112 @@
+use syn::{Expr, ImplItem, Item, ItemFn, Visibility};
+
+use crate::cfg_builder::builder::CfgBuilder;
+
+// Contract of a public function in one version of a file ('contracts diff'), with 'result' for the
+// variable it returns
+#[derive(Debug, Clone)]
+pub struct PublicContract {
+    pub name: String, // qualified in the file, e.g. 'geometry::area'
+    pub params: Vec<String>,
+    pub preconditions: Vec<Expr>,
+    pub postconditions: Vec<Expr>,
+}
+
+// Implications a new version of a contract must satisfy not to break the callers of the old one, as the
+// solver gets them. 'None' when that side of the contract didn't change.
+#[derive(Debug, Clone, Default)]
+pub struct ContractComparison {
+    pub precondition: Option<String>, // old pre ==> new pre: the callers meeting the old one meet the new one
+    pub postcondition: Option<String>, // old pre && new post ==> old post: the callers get what they relied on
+}
+
+impl PublicContract {
+    // 'a && b' of the conditions, 'true' without any
+    pub fn conjunction(conditions: &[Expr]) -> String {
+        match conditions {
+            [] => "true".to_string(),
+            [condition] => CfgBuilder::render_expr(condition),
+            conditions => conditions.iter().map(|condition| format!("({})", CfgBuilder::render_expr(condition))).collect::<Vec<_>>().join(" && "),
+        }
+    }
+}
+
+impl CfgBuilder {
+    // Contracts of the public API of a file: the 'pub' functions of its public modules and the 'pub' methods
+    // of their impls (the methods of trait impls are as public as their type). Functions without 'pre!' or
+    // 'post!' promise nothing and are left out.
+    pub fn public_contracts(&self, items: &[Item]) -> Vec<PublicContract> {
+        let mut contracts: Vec<PublicContract> = Self::public_functions(items, &[]).into_iter()
+            .filter_map(|(name, item_fn)| {
+                let (preconditions, postconditions) = self.contract_conditions(&item_fn);
+                if preconditions.is_empty() && postconditions.is_empty() {
+                    return None;
+                }
+                let returned = Self::returned_variable(&item_fn);
+                let with_result = |condition: &Expr| match &returned {
+                    Some(returned) => Self::without_parens(&self.recursive_substitution(condition, returned, &syn::parse_quote!(result))),
+                    None => Self::without_parens(condition),
+                };
+                Some(PublicContract {
+                    name,
+                    params: Self::parameter_names(&item_fn),
+                    preconditions: preconditions.iter().map(with_result).collect(),
+                    postconditions: postconditions.iter().map(with_result).collect(),
+                })
+            })
+            .collect();
+        contracts.sort_by(|a, b| a.name.cmp(&b.name));
+        contracts
+    }
+
+    fn public_functions(items: &[Item], module_path: &[String]) -> Vec<(String, ItemFn)> {
+        let qualified = |name: String| module_path.iter().cloned().chain(std::iter::once(name)).collect::<Vec<_>>().join("::");
+        let is_pub = |vis: &Visibility| matches!(vis, Visibility::Public(_));
+        let mut functions = Vec::new();
+        for item in items {
+            match item {
+                Item::Fn(item_fn) if is_pub(&item_fn.vis) => functions.push((qualified(item_fn.sig.ident.to_string()), item_fn.clone())),
+                Item::Mod(item_mod) if is_pub(&item_mod.vis) => {
+                    if let Some((_, items)) = &item_mod.content {
+                        let mut path = module_path.to_vec();
+                        path.push(item_mod.ident.to_string());
+                        functions.extend(Self::public_functions(items, &path));
+                    }
+                },
+                Item::Impl(item_impl) => {
+                    let syn::Type::Path(self_ty) = &*item_impl.self_ty else { continue };
+                    let Some(type_name) = self_ty.path.segments.last().map(|segment| segment.ident.to_string()) else { continue };
+                    for impl_item in &item_impl.items {
+                        let ImplItem::Method(method) = impl_item else { continue };
+                        if is_pub(&method.vis) || item_impl.trait_.is_some() {
+                            let item_fn = ItemFn { attrs: method.attrs.clone(), vis: method.vis.clone(), sig: method.sig.clone(), block: Box::new(method.block.clone()) };
+                            functions.push((qualified(format!("{}::{}", type_name, method.sig.ident)), item_fn));
+                        }
+                    }
+                },
+                _ => {},
+            }
+        }
+        functions
+    }
+
+    // What the new contract of a function must imply, with the old one written over the parameters of the new
+    // version by position, so that renaming a parameter is not a change
+    pub fn compare_contracts(&self, old: &PublicContract, new: &PublicContract) -> Result<ContractComparison, String> {
+        let args: Vec<Expr> = new.params.iter()
+            .map(|param| syn::parse_str(param).map_err(|e| format!("failed to parse parameter '{}' of {}: {}", param, new.name, e)))
+            .collect::<Result<_, _>>()?;
+        let args: Vec<&Expr> = args.iter().collect();
+        let renamed = |conditions: &[Expr]| -> Vec<Expr> {
+            conditions.iter()
+                .map(|condition| syn::parse_str(&self.instantiate_contract(condition, &old.params, &args, None)).unwrap_or_else(|_| condition.clone()))
+                .collect()
+        };
+        let (old_pre, old_post) = (PublicContract::conjunction(&renamed(&old.preconditions)), PublicContract::conjunction(&renamed(&old.postconditions)));
+        let (new_pre, new_post) = (PublicContract::conjunction(&new.preconditions), PublicContract::conjunction(&new.postconditions));
+        Ok(ContractComparison {
+            precondition: (old_pre != new_pre).then(|| format!("({}) >> ({})", old_pre, new_pre)),
+            postcondition: (old_post != new_post).then(|| format!("({}) >> ({}) >> ({})", old_pre, new_post, old_post)),
+        })
+    }
+}
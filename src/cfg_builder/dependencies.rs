@@ -0,0 +1,114 @@
+use petgraph::graph::NodeIndex;
+use quote::ToTokens;
+use std::collections::BTreeMap;
+use syn::spanned::Spanned;
+use syn::visit_mut::{self, VisitMut};
+use syn::{ImplItemMethod, ItemFn, TraitItemMethod};
+
+use crate::cfg_builder::builder::CfgBuilder;
+use crate::cfg_builder::node::CfgNode;
+use crate::config::CallStrategy;
+use crate::report::{fnv1a, FunctionFingerprint, ObligationDependencies};
+
+// Empties the bodies of the functions, except the logic functions whose bodies define them in the specs
+struct BodyEraser;
+
+impl VisitMut for BodyEraser {
+    fn visit_item_fn_mut(&mut self, item_fn: &mut ItemFn) {
+        if !CfgBuilder::is_logic_function(item_fn) {
+            item_fn.block.stmts.clear();
+        }
+    }
+
+    fn visit_impl_item_method_mut(&mut self, method: &mut ImplItemMethod) {
+        let item_fn = ItemFn { attrs: method.attrs.clone(), vis: method.vis.clone(), sig: method.sig.clone(), block: Box::new(method.block.clone()) };
+        if !CfgBuilder::is_logic_function(&item_fn) {
+            method.block.stmts.clear();
+        }
+    }
+
+    fn visit_trait_item_method_mut(&mut self, method: &mut TraitItemMethod) {
+        if let Some(default) = &mut method.default {
+            default.stmts.clear();
+        }
+        visit_mut::visit_trait_item_method_mut(self, method);
+    }
+}
+
+fn hash(text: &str) -> String {
+    format!("{:016x}", fnv1a(text.as_bytes()))
+}
+
+impl CfgBuilder {
+    // Code and contract fingerprints of every function of the file, by qualified name. The code includes the
+    // '//@ call' comments of the body, which give the contracts of its calls.
+    pub fn function_fingerprints(&self, ast: &syn::File) -> BTreeMap<String, FunctionFingerprint> {
+        Self::spec_functions(&ast.items, &[]).into_iter()
+            .map(|(name, item_fn)| {
+                let (contract, body): (Vec<&syn::Stmt>, Vec<&syn::Stmt>) = item_fn.block.stmts.iter().partition(|stmt| self.is_contract_annotation(stmt));
+                let span = item_fn.span();
+                let comments: Vec<&str> = self.file.source_lines.iter()
+                    .skip(span.start().line.saturating_sub(1))
+                    .take(span.end().line + 1 - span.start().line)
+                    .map(|line| line.trim())
+                    .filter(|line| line.starts_with("//@"))
+                    .collect();
+                let attrs = &item_fn.attrs;
+                let sig = &item_fn.sig;
+                let code = format!("{} {} {} {}", quote::quote!(#(#attrs)*), sig.to_token_stream(), quote::quote!(#(#body)*), comments.join("\n"));
+                let contract = quote::quote!(#(#contract)*).to_string();
+                (name, FunctionFingerprint { code: hash(&code), contract: hash(&contract) })
+            })
+            .collect()
+    }
+
+    // Fingerprint of what every obligation of the file depends on: the file without the bodies of its functions
+    // (types, constants, struct invariants, signatures) and with the logic functions
+    pub fn shared_fingerprint(ast: &syn::File) -> String {
+        let mut erased = ast.clone();
+        BodyEraser.visit_file_mut(&mut erased);
+        hash(&erased.to_token_stream().to_string())
+    }
+
+    // Functions of the file whose code and contracts the verification condition of a basic path is made of:
+    // its function and the callees inlined into it, the contract of its function when the path starts at the
+    // 'pre!' or ends at a 'post!', and the contracts of the callees checked or assumed at its calls
+    pub fn path_dependencies(&self, path: &[NodeIndex], functions: &BTreeMap<String, FunctionFingerprint>) -> ObligationDependencies {
+        let mut dependencies = ObligationDependencies::default();
+        let Some(&start) = path.first() else { return dependencies };
+        let Some(func_node) = self.function_nodes().into_iter().find(|&func_node| self.function_subgraph_nodes(func_node).contains(&start)) else {
+            return dependencies;
+        };
+        // A callee is named as written at the call ('area' for 'geometry::area' called from its module)
+        let local = |callee: &str| -> Vec<String> {
+            functions.keys().filter(|name| *name == callee || name.ends_with(&format!("::{}", callee))).cloned().collect()
+        };
+        let function = self.graph[func_node].label();
+        dependencies.code.insert(function.clone());
+        dependencies.code.extend(self.results.call_strategies.iter()
+            .filter(|record| record.caller == function && record.strategy == CallStrategy::Inline)
+            .map(|record| record.callee.clone()));
+        if let CfgNode::Function(_, Some(item_fn)) = &self.graph[func_node] {
+            if let Some(reference) = Self::refined_reference(item_fn) {
+                dependencies.code.extend(local(&reference));
+                dependencies.contracts.extend(local(&reference));
+            }
+        }
+
+        let own_contract = matches!(self.graph[start], CfgNode::Precondition(_, _))
+            || path.last().is_some_and(|&end| matches!(self.graph[end], CfgNode::Postcondition(_, _)));
+        if own_contract {
+            dependencies.contracts.insert(function);
+        }
+        for node in path {
+            let Some(metadata) = self.results.node_metadata.get(node) else { continue };
+            if let Some(callee) = &metadata.call_precondition {
+                dependencies.contracts.extend(local(callee));
+            }
+            if let Some(call) = metadata.indirect_call.as_ref().filter(|call| call.contract.as_deref() == Some("callee contract")) {
+                dependencies.contracts.extend(local(&call.callee));
+            }
+        }
+        dependencies
+    }
+}
@@ -0,0 +1,230 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use syn::visit::{self, Visit};
+use syn::{Expr, ExprMacro, ItemFn, Stmt};
+
+use crate::cfg_builder::builder::CfgBuilder;
+
+// Syntax that ends up in the graph without being modeled precisely
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum UnmodeledSyntax {
+    Closure,
+    BreakContinue,
+    TryOperator,
+    LetCondition,
+    Block,
+    UnconstrainedMatchArm,
+    OpaqueMacro,
+    NestedItem,
+}
+
+impl UnmodeledSyntax {
+    // (singular, plural, what happens to it)
+    fn describe(&self) -> (&'static str, &'static str, &'static str) {
+        match self {
+            UnmodeledSyntax::Closure => ("closure", "closures", "opaque"),
+            UnmodeledSyntax::BreakContinue => ("break/continue outside a loop", "break/continue outside a loop", "ignored"),
+            UnmodeledSyntax::TryOperator => ("? operator", "? operators", "without early return"),
+            UnmodeledSyntax::LetCondition => ("if let/while let/matches! pattern", "if let/while let/matches! patterns", "without path condition"),
+            UnmodeledSyntax::Block => ("block/unsafe block", "blocks/unsafe blocks", "flattened"),
+            UnmodeledSyntax::UnconstrainedMatchArm => ("match arm", "match arms", "without path condition"),
+            UnmodeledSyntax::OpaqueMacro => ("macro call", "macro calls", "opaque"),
+            UnmodeledSyntax::NestedItem => ("nested item", "nested items", "ignored"),
+        }
+    }
+}
+
+// Per-function counts of the constructs the CFG approximates
+#[derive(Debug, Clone, Default)]
+pub struct SyntaxDiagnostics {
+    pub function: String,
+    pub counts: BTreeMap<UnmodeledSyntax, usize>,
+}
+
+impl SyntaxDiagnostics {
+    pub fn is_empty(&self) -> bool {
+        self.counts.is_empty()
+    }
+
+    fn count(&mut self, kind: UnmodeledSyntax) {
+        *self.counts.entry(kind).or_insert(0) += 1;
+    }
+}
+
+impl fmt::Display for SyntaxDiagnostics {
+    // '2 match arms without path condition, 1 closure opaque'
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let parts: Vec<String> = self.counts.iter()
+            .map(|(kind, &count)| {
+                let (singular, plural, effect) = kind.describe();
+                format!("{} {} {}", count, if count == 1 { singular } else { plural }, effect)
+            })
+            .collect();
+        write!(f, "{}", parts.join(", "))
+    }
+}
+
+struct SyntaxCounter<'a> {
+    builder: &'a CfgBuilder,
+    diagnostics: SyntaxDiagnostics,
+    loops: Vec<Option<String>>, // labels of the loops around the visited expression
+    flattened: usize, // depth of blocks, whose 'break'/'continue' aren't built
+}
+
+impl SyntaxCounter<'_> {
+    // A 'break'/'continue' is built when it leaves a loop the graph has
+    fn jumps_to_built_loop(&self, label: Option<&syn::Lifetime>) -> bool {
+        self.flattened == 0 && match label {
+            Some(label) => self.loops.iter().any(|name| name.as_ref() == Some(&label.ident.to_string())),
+            None => !self.loops.is_empty(),
+        }
+    }
+
+    fn visit_loop(&mut self, label: &Option<syn::Label>, expr: &Expr) {
+        self.loops.push(label.as_ref().map(|label| label.name.ident.to_string()));
+        visit::visit_expr(self, expr);
+        self.loops.pop();
+    }
+
+    fn count_opaque_pattern_tests(&mut self, cond: &Expr) {
+        let (_, structure) = self.builder.lower_condition(cond);
+        for _ in structure.pattern_tests().into_iter().filter(|test| test.opaque) {
+            self.diagnostics.count(UnmodeledSyntax::LetCondition);
+        }
+    }
+
+    // The operands of a condition, without the 'matches!' it tests
+    fn visit_condition(&mut self, cond: &Expr) {
+        match cond {
+            Expr::Binary(binary) if matches!(binary.op, syn::BinOp::And(_) | syn::BinOp::Or(_)) => {
+                self.visit_condition(&binary.left);
+                self.visit_condition(&binary.right);
+            },
+            Expr::Unary(unary) if matches!(unary.op, syn::UnOp::Not(_)) => self.visit_condition(&unary.expr),
+            Expr::Paren(paren) => self.visit_condition(&paren.expr),
+            Expr::Let(expr_let) => self.visit_expr(&expr_let.expr),
+            Expr::Macro(expr_macro) => match CfgBuilder::matches_arguments(&expr_macro.mac) {
+                Some((scrutinee, _, guard)) => {
+                    self.visit_expr(&scrutinee);
+                    if let Some(guard) = &guard {
+                        self.visit_expr(guard);
+                    }
+                },
+                None => self.visit_expr(cond),
+            },
+            _ => self.visit_expr(cond),
+        }
+    }
+
+    fn visit_flattened(&mut self, expr: &Expr) {
+        self.flattened += 1;
+        visit::visit_expr(self, expr);
+        self.flattened -= 1;
+    }
+}
+
+impl<'ast> Visit<'ast> for SyntaxCounter<'_> {
+    fn visit_expr(&mut self, expr: &'ast Expr) {
+        match expr {
+            // A closure body runs at an unknown time: nothing inside it is part of the CFG
+            Expr::Closure(_) => {
+                self.diagnostics.count(UnmodeledSyntax::Closure);
+                return;
+            },
+            Expr::Loop(expr_loop) => return self.visit_loop(&expr_loop.label, expr),
+            // The pattern tests of a condition are modeled like match arms
+            Expr::If(expr_if) => {
+                self.count_opaque_pattern_tests(&expr_if.cond);
+                self.visit_condition(&expr_if.cond);
+                self.visit_block(&expr_if.then_branch);
+                // The 'else' block is a branch of the CFG like the 'then' block, not a flattened block
+                match expr_if.else_branch.as_ref().map(|(_, else_branch)| &**else_branch) {
+                    Some(Expr::Block(else_block)) => self.visit_block(&else_block.block),
+                    Some(else_if) => self.visit_expr(else_if),
+                    None => {},
+                }
+                return;
+            },
+            Expr::While(expr_while) => {
+                self.count_opaque_pattern_tests(&expr_while.cond);
+                self.visit_condition(&expr_while.cond);
+                self.loops.push(expr_while.label.as_ref().map(|label| label.name.ident.to_string()));
+                self.visit_block(&expr_while.body);
+                self.loops.pop();
+                return;
+            },
+            Expr::ForLoop(expr_for) => return self.visit_loop(&expr_for.label, expr),
+            Expr::Break(expr_break) if !self.jumps_to_built_loop(expr_break.label.as_ref()) => self.diagnostics.count(UnmodeledSyntax::BreakContinue),
+            Expr::Continue(expr_continue) if !self.jumps_to_built_loop(expr_continue.label.as_ref()) => self.diagnostics.count(UnmodeledSyntax::BreakContinue),
+            Expr::Try(_) => self.diagnostics.count(UnmodeledSyntax::TryOperator),
+            Expr::Block(_) | Expr::Async(_) => {
+                self.diagnostics.count(UnmodeledSyntax::Block);
+                return self.visit_flattened(expr);
+            },
+            Expr::Unsafe(_) => self.diagnostics.count(UnmodeledSyntax::Block),
+            Expr::Match(expr_match) => {
+                let unconstrained = self.builder.lower_match_condition(expr_match).arms.iter()
+                    .filter(|arm| arm.condition.is_none())
+                    .count();
+                for _ in 0..unconstrained {
+                    self.diagnostics.count(UnmodeledSyntax::UnconstrainedMatchArm);
+                }
+            },
+            _ => {},
+        }
+        visit::visit_expr(self, expr);
+    }
+
+    fn visit_expr_macro(&mut self, expr_macro: &'ast ExprMacro) {
+        let name = expr_macro.mac.path.segments.last().map(|segment| segment.ident.to_string()).unwrap_or_default();
+        if name != "vec" && self.builder.options.annotation_names.role_of(&name).is_none() {
+            self.diagnostics.count(UnmodeledSyntax::OpaqueMacro);
+        }
+    }
+
+    fn visit_stmt(&mut self, stmt: &'ast Stmt) {
+        match stmt {
+            Stmt::Item(_) => self.diagnostics.count(UnmodeledSyntax::NestedItem),
+            _ => visit::visit_stmt(self, stmt),
+        }
+    }
+}
+
+impl CfgBuilder {
+    // Count the constructs of a function that the CFG only approximates
+    pub fn syntax_diagnostics(&self, func_name: &str, item_fn: &ItemFn) -> SyntaxDiagnostics {
+        let mut counter = SyntaxCounter {
+            builder: self,
+            diagnostics: SyntaxDiagnostics { function: func_name.to_string(), counts: BTreeMap::new() },
+            loops: Vec::new(),
+            flattened: 0,
+        };
+        counter.visit_block(&item_fn.block);
+        counter.diagnostics
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_support::built;
+
+    fn diagnostics(src: &str) -> String {
+        built(src).results.diagnostics.iter().map(|diagnostics| diagnostics.to_string()).collect()
+    }
+
+    #[test]
+    fn else_branches_are_not_flattened_blocks() {
+        assert_eq!(diagnostics("fn sign(x: i32) -> i32 {
+                pre!(true);
+                post!(s >= -1);
+                let s = if x > 0 { 1 } else if x < 0 { -1 } else { 0 };
+                return s;
+            }"), "");
+        assert_eq!(diagnostics("fn id(x: i32) -> i32 {
+                pre!(true);
+                post!(y == x);
+                let y = { x };
+                return y;
+            }"), "1 block/unsafe block flattened");
+    }
+}
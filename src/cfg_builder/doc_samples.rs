@@ -0,0 +1,112 @@
+use crate::cfg_builder::builder::CfgBuilder;
+
+// Attributes of a rustdoc code block that don't name its language; 'ignore' and 'compile_fail' samples
+// aren't verified
+const RUSTDOC_ATTRIBUTES: [&str; 7] = ["ignore", "no_run", "should_panic", "compile_fail", "edition2015", "edition2018", "edition2021"];
+const SKIPPED_ATTRIBUTES: [&str; 2] = ["ignore", "compile_fail"];
+
+// A Rust code block of a markdown file or of doc comments, as a source file of its own. Lines outside the
+// block are blank, so the node IDs and the reported lines are those of the file it's read from.
+#[derive(Debug, Clone)]
+pub struct CodeSample {
+    pub line: usize, // line of the opening fence
+    pub source: String,
+}
+
+impl CfgBuilder {
+    // The '```rust' blocks of a markdown file
+    pub fn markdown_samples(content: &str) -> Vec<CodeSample> {
+        let lines: Vec<&str> = content.lines().collect();
+        Self::fenced_samples(&lines, false)
+    }
+
+    // The Rust blocks of the '///' and '//!' comments of a source file; as in rustdoc, a block without a
+    // language is Rust
+    pub fn doc_comment_samples(content: &str) -> Vec<CodeSample> {
+        let lines: Vec<&str> = content.lines()
+            .map(|line| {
+                let trimmed = line.trim_start();
+                match trimmed.strip_prefix("///").or_else(|| trimmed.strip_prefix("//!")) {
+                    Some(doc) => doc.strip_prefix(' ').unwrap_or(doc),
+                    None => "",
+                }
+            })
+            .collect();
+        Self::fenced_samples(&lines, true)
+    }
+
+    fn fenced_samples(lines: &[&str], rust_by_default: bool) -> Vec<CodeSample> {
+        let mut samples = Vec::new();
+        let mut i = 0;
+        while i < lines.len() {
+            let Some((fence, info)) = Self::opening_fence(lines[i]) else {
+                i += 1;
+                continue;
+            };
+            let start = i;
+            let end = (start + 1..lines.len())
+                .find(|&j| lines[j].trim().starts_with(&fence) && lines[j].trim().trim_start_matches(fence.chars().next().unwrap()).is_empty())
+                .unwrap_or(lines.len());
+            if Self::is_rust_block(info, rust_by_default) {
+                let code: Vec<&str> = lines[start + 1..end].iter().map(|line| Self::unhidden_line(line)).collect();
+                samples.push(CodeSample { line: start + 1, source: Self::sample_source(start, &code) });
+            }
+            i = end + 1;
+        }
+        samples
+    }
+
+    // '```rust' gives the fence and the info string 'rust'; at most three spaces of indentation, as in markdown
+    fn opening_fence(line: &str) -> Option<(String, &str)> {
+        let trimmed = line.trim_start();
+        if line.len() - trimmed.len() > 3 {
+            return None;
+        }
+        let marker = trimmed.chars().next().filter(|c| *c == '`' || *c == '~')?;
+        let length = trimmed.chars().take_while(|c| *c == marker).count();
+        if length < 3 {
+            return None;
+        }
+        Some((marker.to_string().repeat(length), trimmed[length..].trim()))
+    }
+
+    // 'rust', 'rust,no_run' and 'should_panic' are Rust; 'text', 'toml' and 'rust,ignore' aren't verified
+    fn is_rust_block(info: &str, rust_by_default: bool) -> bool {
+        let words: Vec<&str> = info.split(|c: char| c == ',' || c.is_whitespace()).filter(|word| !word.is_empty()).collect();
+        if words.iter().any(|word| SKIPPED_ATTRIBUTES.contains(word)) {
+            return false;
+        }
+        let languages: Vec<&str> = words.into_iter().filter(|word| !RUSTDOC_ATTRIBUTES.contains(word)).collect();
+        match languages.as_slice() {
+            [] => rust_by_default,
+            languages => languages.iter().all(|language| *language == "rust"),
+        }
+    }
+
+    // Lines rustdoc hides from the rendered sample ('# use std::io;') still belong to the code
+    fn unhidden_line(line: &str) -> &str {
+        match line.trim_start().strip_prefix('#') {
+            Some("") => "",
+            Some(rest) if rest.starts_with(' ') => &rest[1..],
+            _ => line,
+        }
+    }
+
+    // The code at its lines; statements outside a function are put in a 'main' opening on the fence line, as
+    // rustdoc does
+    fn sample_source(fence_line: usize, code: &[&str]) -> String {
+        let body = code.join("\n");
+        let wrap = syn::parse_file(&body).is_err();
+        let mut source = "\n".repeat(fence_line);
+        if wrap {
+            source.push_str("fn main() {");
+        }
+        source.push('\n');
+        source.push_str(&body);
+        source.push('\n');
+        if wrap {
+            source.push_str("}\n");
+        }
+        source
+    }
+}
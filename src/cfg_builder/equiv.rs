@@ -0,0 +1,192 @@
+use petgraph::graph::NodeIndex;
+use std::fmt;
+use syn::{Expr, FnArg, ItemFn, Stmt};
+
+use crate::cfg_builder::builder::CfgBuilder;
+use crate::cfg_builder::node::CfgNode;
+use crate::config::AnnotationRole;
+
+// One step of the structural alignment of two versions of a function: nodes of the same kind are paired,
+// in graph order, and the others were removed from the old version or added by the new one
+#[derive(Debug, Clone, PartialEq)]
+pub enum AlignmentStep {
+    Same(String, String), // kind, label
+    Changed(String, String, String), // kind, old label, new label
+    Removed(String, String),
+    Added(String, String),
+}
+
+impl fmt::Display for AlignmentStep {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AlignmentStep::Same(kind, label) => write!(f, "  = {}: {}", kind, label),
+            AlignmentStep::Changed(kind, old, new) => write!(f, "  ~ {}: {} -> {}", kind, old, new),
+            AlignmentStep::Removed(kind, label) => write!(f, "  - {}: {}", kind, label),
+            AlignmentStep::Added(kind, label) => write!(f, "  + {}: {}", kind, label),
+        }
+    }
+}
+
+impl CfgBuilder {
+    // Function of the file by qualified name ('geometry::area', 'Counter::push')
+    pub fn find_function(items: &[syn::Item], name: &str) -> Option<ItemFn> {
+        Self::spec_functions(items, &[]).into_iter().find(|(qualified, _)| qualified == name).map(|(_, item_fn)| item_fn)
+    }
+
+    // The new version of a function with the 'pre!'/'post!' of the old version instead of its own, written over
+    // its parameters (matched by position) and the variable it returns
+    pub fn transplant_contract(&self, old_fn: &ItemFn, new_fn: &ItemFn) -> Result<ItemFn, String> {
+        let (preconditions, postconditions) = self.contract_conditions(old_fn);
+        if postconditions.is_empty() {
+            return Err(format!("{} has no post! in the old version: both versions are checked against its contract", old_fn.sig.ident));
+        }
+        let params = |item_fn: &ItemFn| -> Vec<String> {
+            item_fn.sig.inputs.iter()
+                .filter_map(|input| match input {
+                    FnArg::Typed(pat_type) => Self::pattern_bindings(&pat_type.pat).into_iter().next(),
+                    FnArg::Receiver(_) => Some("self".to_string()),
+                })
+                .collect()
+        };
+        let (mut old_names, new_params) = (params(old_fn), params(new_fn));
+        if old_names.len() != new_params.len() {
+            return Err(format!("{} takes {} parameters in the old version and {} in the new one", old_fn.sig.ident, old_names.len(), new_params.len()));
+        }
+        let mut new_names: Vec<Expr> = new_params.iter()
+            .map(|param| syn::parse_str(param).map_err(|e| format!("failed to parse parameter '{}' of {}: {}", param, new_fn.sig.ident, e)))
+            .collect::<Result<_, _>>()?;
+
+        // The returned value is named by the variable each version returns
+        if let Some(old_returned) = Self::returned_variable(old_fn) {
+            let Some(new_returned) = Self::returned_variable(new_fn) else {
+                return Err(format!("the new version of {} doesn't return a single variable: return '{}' on every path to compare the versions", new_fn.sig.ident, old_returned));
+            };
+            old_names.push(old_returned);
+            new_names.push(syn::parse_str(&new_returned)
+                .map_err(|e| format!("failed to parse the variable '{}' returned by {}: {}", new_returned, new_fn.sig.ident, e))?);
+        }
+        let args: Vec<&Expr> = new_names.iter().collect();
+
+        let annotation = |role: &str, condition: &Expr| -> Result<Stmt, String> {
+            let instantiated = self.instantiate_contract(condition, &old_names, &args, None);
+            let condition: Expr = syn::parse_str(&instantiated)
+                .map_err(|e| format!("failed to parse the {} condition '{}' of {}: {}", role, instantiated, new_fn.sig.ident, e))?;
+            let condition = Self::without_parens(&condition);
+            let role = syn::Ident::new(role, proc_macro2::Span::call_site());
+            Ok(syn::parse_quote!(#role!(#condition);))
+        };
+        let mut transplanted = new_fn.clone();
+        let mut stmts: Vec<Stmt> = preconditions.iter().map(|pre| annotation("pre", pre))
+            .chain(postconditions.iter().map(|post| annotation("post", post)))
+            .collect::<Result<_, _>>()?;
+        stmts.extend(new_fn.block.stmts.iter()
+            .filter(|stmt| !matches!(stmt, Stmt::Semi(Expr::Macro(expr_macro), _)
+                if matches!(expr_macro.mac.path.get_ident().and_then(|ident| self.options.annotation_names.role_of(&ident.to_string())), Some(AnnotationRole::Pre | AnnotationRole::Post))))
+            .cloned());
+        transplanted.block.stmts = stmts;
+        Ok(transplanted)
+    }
+
+    // Entry node of an analyzed function
+    pub fn function_node(&self, name: &str) -> Option<NodeIndex> {
+        self.function_nodes().into_iter().find(|&node| self.graph[node].label() == name)
+    }
+
+    // Pair the nodes of two versions of a function by kind, in graph order, preferring nodes with the same
+    // label. The contract, the assumptions from types and the merge points are left out: they are the same in both checks.
+    pub fn align_functions(old: &CfgBuilder, old_fn: NodeIndex, new: &CfgBuilder, new_fn: NodeIndex) -> Vec<AlignmentStep> {
+        let structure = |builder: &CfgBuilder, func_node: NodeIndex| -> Vec<(&'static str, String)> {
+            builder.function_subgraph_nodes(func_node).into_iter()
+                .map(|node| &builder.graph[node])
+                .filter(|node| !matches!(node, CfgNode::Function(_, _) | CfgNode::Precondition(_, _) | CfgNode::Assumption(_, _) | CfgNode::Postcondition(_, _) | CfgNode::MergePoint))
+                .map(|node| (node.kind_name(), node.label()))
+                .collect()
+        };
+        let (old_nodes, new_nodes) = (structure(old, old_fn), structure(new, new_fn));
+
+        // Longest common subsequence of the kinds, nodes with the same label counting twice
+        let (n, m) = (old_nodes.len(), new_nodes.len());
+        let mut score = vec![vec![0usize; m + 1]; n + 1];
+        for i in (0..n).rev() {
+            for j in (0..m).rev() {
+                let paired = match (&old_nodes[i], &new_nodes[j]) {
+                    ((old_kind, old_label), (new_kind, new_label)) if old_kind == new_kind => Some(score[i + 1][j + 1] + if old_label == new_label { 2 } else { 1 }),
+                    _ => None,
+                };
+                score[i][j] = paired.unwrap_or(0).max(score[i + 1][j]).max(score[i][j + 1]);
+            }
+        }
+        let (mut i, mut j, mut steps) = (0, 0, Vec::new());
+        while i < n || j < m {
+            if i < n && j < m && old_nodes[i].0 == new_nodes[j].0 {
+                let gain = if old_nodes[i].1 == new_nodes[j].1 { 2 } else { 1 };
+                if score[i][j] == score[i + 1][j + 1] + gain {
+                    let (kind, old_label) = &old_nodes[i];
+                    let new_label = &new_nodes[j].1;
+                    steps.push(if old_label == new_label {
+                        AlignmentStep::Same(kind.to_string(), old_label.clone())
+                    } else {
+                        AlignmentStep::Changed(kind.to_string(), old_label.clone(), new_label.clone())
+                    });
+                    i += 1;
+                    j += 1;
+                    continue;
+                }
+            }
+            if i < n && (j == m || score[i][j] == score[i + 1][j]) {
+                steps.push(AlignmentStep::Removed(old_nodes[i].0.to_string(), old_nodes[i].1.clone()));
+                i += 1;
+            } else {
+                steps.push(AlignmentStep::Added(new_nodes[j].0.to_string(), new_nodes[j].1.clone()));
+                j += 1;
+            }
+        }
+        steps
+    }
+
+    // Replace a function of the file, found by qualified name as in 'find_function'. Returns whether it was found.
+    pub fn replace_function(items: &mut [syn::Item], module_path: &[String], name: &str, replacement: &ItemFn) -> bool {
+        let qualified = |name: String| module_path.iter().cloned().chain(std::iter::once(name)).collect::<Vec<_>>().join("::");
+        for item in items {
+            match item {
+                syn::Item::Fn(item_fn) if qualified(item_fn.sig.ident.to_string()) == name => {
+                    *item_fn = replacement.clone();
+                    return true;
+                },
+                syn::Item::Mod(item_mod) => {
+                    let mut path = module_path.to_vec();
+                    path.push(item_mod.ident.to_string());
+                    if let Some((_, items)) = &mut item_mod.content {
+                        if Self::replace_function(items, &path, name, replacement) {
+                            return true;
+                        }
+                    }
+                },
+                syn::Item::Impl(item_impl) => {
+                    let syn::Type::Path(self_ty) = &*item_impl.self_ty else { continue };
+                    let Some(type_name) = self_ty.path.segments.last().map(|segment| segment.ident.to_string()) else { continue };
+                    for impl_item in &mut item_impl.items {
+                        if let syn::ImplItem::Method(method) = impl_item {
+                            if qualified(format!("{}::{}", type_name, method.sig.ident)) == name {
+                                method.block = (*replacement.block).clone();
+                                return true;
+                            }
+                        }
+                    }
+                },
+                syn::Item::Trait(item_trait) => {
+                    for trait_item in &mut item_trait.items {
+                        if let syn::TraitItem::Method(method) = trait_item {
+                            if method.default.is_some() && qualified(format!("{}::{}", item_trait.ident, method.sig.ident)) == name {
+                                method.default = Some((*replacement.block).clone());
+                                return true;
+                            }
+                        }
+                    }
+                },
+                _ => {},
+            }
+        }
+        false
+    }
+}
@@ -0,0 +1,43 @@
+use petgraph::graph::NodeIndex;
+use serde_json::{json, Value};
+
+use crate::cfg_builder::builder::CfgBuilder;
+use crate::cfg_builder::node::CfgNode;
+
+impl CfgBuilder {
+    // Convert CFG to JSON, always keeping the full (untruncated) node labels
+    pub fn to_json(&self) -> Value {
+        let mut visible_nodes: Vec<NodeIndex> = self.graph.node_indices().filter(|&node| self.is_node_visible(node)).collect();
+        visible_nodes.sort_by_key(|&node| self.node_id(node));
+        let nodes: Vec<Value> = visible_nodes.into_iter().map(|node| {
+            let cfg_node = &self.graph[node];
+            let mut value = json!({
+                "id": self.node_id(node),
+                "kind": cfg_node.kind_name(),
+                "label": cfg_node.label(),
+            });
+            if let CfgNode::Function(_, _) = cfg_node {
+                value["function_id"] = json!(self.function_id(node));
+            }
+            if let Some(metadata) = self.results.node_metadata.get(&node).filter(|m| !m.is_empty()) {
+                value["metadata"] = json!(metadata);
+            }
+            value
+        }).collect();
+
+        let mut edges: Vec<(String, String, String, &'static str)> = self.visible_edges().into_iter()
+            .map(|(source, target, kind)| (self.node_id(source), self.node_id(target), kind.label(), kind.kind_name()))
+            .collect();
+        edges.sort();
+        let edges: Vec<Value> = edges.into_iter().map(|(source, target, label, kind)| {
+            json!({
+                "source": source,
+                "target": target,
+                "kind": kind,
+                "label": label,
+            })
+        }).collect();
+
+        json!({ "nodes": nodes, "edges": edges })
+    }
+}
@@ -0,0 +1,90 @@
+use serde::Serialize;
+use syn::{ExprCall, FnArg, ForeignItem, Item, ReturnType};
+
+use crate::cfg_builder::builder::{CallSiteContract, CfgBuilder};
+use crate::config::conditions_path;
+
+// Function declared in an 'extern' block: its body is out of reach, so its calls need a contract
+#[derive(Clone, Debug, Serialize)]
+pub struct ForeignFunction {
+    pub name: String,
+    pub abi: String, // e.g. 'C'
+    pub params: Vec<(String, String)>, // declared parameters and their types, the names the contract uses
+    pub returns: Option<String>,
+}
+
+impl CfgBuilder {
+    // Record the functions of the 'extern "C" { fn read(fd: i32, count: usize) -> isize; }' blocks of the file
+    pub fn collect_foreign_functions(&mut self, items: &[Item]) {
+        for item in items {
+            match item {
+                Item::ForeignMod(foreign_mod) => {
+                    let abi = foreign_mod.abi.name.as_ref().map(|name| name.value()).unwrap_or_else(|| "C".to_string());
+                    for foreign_item in &foreign_mod.items {
+                        let ForeignItem::Fn(foreign_fn) = foreign_item else { continue };
+                        let params = foreign_fn.sig.inputs.iter()
+                            .filter_map(|input| match input {
+                                FnArg::Typed(pat_type) => Self::pattern_bindings(&pat_type.pat).into_iter().next()
+                                    .map(|name| (name, Self::render_type(&pat_type.ty))),
+                                FnArg::Receiver(_) => None,
+                            })
+                            .collect();
+                        let returns = match &foreign_fn.sig.output {
+                            ReturnType::Type(_, ty) => Some(Self::render_type(ty)),
+                            ReturnType::Default => None,
+                        };
+                        let name = foreign_fn.sig.ident.to_string();
+                        self.file.foreign_functions.insert(name.clone(), ForeignFunction { name, abi: abi.clone(), params, returns });
+                    }
+                },
+                Item::Mod(item_mod) => {
+                    if let Some((_, items)) = &item_mod.content {
+                        self.collect_foreign_functions(items);
+                    }
+                },
+                _ => {},
+            }
+        }
+    }
+
+    // Extern function a call goes to, unless a function of the file has the same name
+    pub fn foreign_callee(&self, call: &ExprCall) -> Option<&ForeignFunction> {
+        let syn::Expr::Path(func) = &*call.func else { return None };
+        let name = func.path.segments.last()?.ident.to_string();
+        if self.local_callee(call).is_some() {
+            return None;
+        }
+        self.file.foreign_functions.get(&name)
+    }
+
+    // Contract of the conditions database for a call of an extern function, written over its declared
+    // parameters: the parameters are replaced by the arguments of the call
+    pub fn foreign_contract(&self, foreign: &ForeignFunction, site: &CallSiteContract, call: &ExprCall) -> (Vec<String>, Vec<String>) {
+        let params: Vec<String> = foreign.params.iter().map(|(name, _)| name.clone()).collect();
+        let args: Vec<&syn::Expr> = call.args.iter().collect();
+        let instantiate = |conditions: &[String]| -> Vec<String> {
+            conditions.iter().map(|condition| match syn::parse_str(condition) {
+                Ok(condition) => self.instantiate_contract(&condition, &params, &args, None),
+                Err(_) => condition.clone(),
+            }).collect()
+        };
+        (instantiate(&site.preconditions), instantiate(&site.postconditions))
+    }
+
+    // Calls of extern functions must have a contract in the conditions database, whatever contract a comment
+    // gives them: the others are reported once the graph is built. Returns whether the callee is extern.
+    pub fn check_foreign_call(&mut self, callee: &str, line: usize) -> bool {
+        let name = callee.rsplit("::").next().unwrap_or(callee);
+        if self.file.local_functions.contains_key(callee) {
+            return false;
+        }
+        let Some(foreign) = self.file.foreign_functions.get(name) else { return false };
+        if !self.external_conditions.call_sites.iter().any(|site| site.callee == foreign.name) {
+            self.results.ffi_warnings.push(format!(
+                "call to extern \"{}\" function {} at line {} in {} has no contract in {}, its effects are unknown",
+                foreign.abi, foreign.name, line, self.current_function, conditions_path().display(),
+            ));
+        }
+        true
+    }
+}
@@ -0,0 +1,40 @@
+use proc_macro2::Span;
+use quote::ToTokens;
+use syn::spanned::Spanned;
+
+use crate::cfg_builder::builder::CfgBuilder;
+
+impl CfgBuilder {
+    // Inner attributes of the file ('#![allow(unused)]', '#![deny(clippy::all)]'), as written in the source.
+    // '//!' comments are the crate documentation, not attributes.
+    pub fn file_attributes(&self, file: &syn::File) -> Vec<String> {
+        file.attrs.iter()
+            .filter(|attr| !attr.path.is_ident("doc"))
+            .map(|attr| self.source_text(attr.span()).unwrap_or_else(|| Self::clean_up_formatting(&attr.to_token_stream().to_string())))
+            .collect()
+    }
+
+    // Source the span covers, None when it isn't in the analyzed file
+    fn source_text(&self, span: Span) -> Option<String> {
+        let (start, end) = (span.start(), span.end());
+        if start.line == 0 || end.line > self.file.source_lines.len() {
+            return None;
+        }
+        let lines = &self.file.source_lines[start.line - 1..end.line];
+        let text = match lines {
+            [line] => line.chars().skip(start.column).take(end.column.saturating_sub(start.column)).collect(),
+            [first, .., last] => {
+                let mut text: String = first.chars().skip(start.column).collect();
+                for line in &lines[1..lines.len() - 1] {
+                    text.push('\n');
+                    text.push_str(line);
+                }
+                text.push('\n');
+                text.extend(last.chars().take(end.column));
+                text
+            },
+            [] => return None,
+        };
+        Some(text)
+    }
+}
@@ -0,0 +1,104 @@
+use petgraph::graph::NodeIndex;
+use petgraph::visit::EdgeRef;
+use std::collections::HashSet;
+
+use crate::cfg_builder::builder::CfgBuilder;
+use crate::cfg_builder::node::{CfgNode, ConditionalExpr, EdgeKind};
+
+// Node categories accepted by '--hide' and '--only'
+pub const NODE_CATEGORIES: [&str; 8] = [
+    "functions", "annotations", "statements", "conditions", "branches", "loops", "returns", "merges",
+];
+
+// Which nodes appear in the exported graphs; hidden nodes are contracted away
+#[derive(Debug, Clone, Default)]
+pub struct NodeFilter {
+    pub hide: Vec<String>,
+    pub only: Vec<String>, // when not empty, only these categories are kept
+}
+
+impl NodeFilter {
+    // Parse a comma separated list of categories ('annotations,conditions')
+    pub fn parse_categories(list: &str) -> Result<Vec<String>, String> {
+        list.split(',')
+            .map(|category| category.trim().to_lowercase())
+            .filter(|category| !category.is_empty())
+            .map(|category| {
+                if NODE_CATEGORIES.contains(&category.as_str()) {
+                    Ok(category)
+                } else {
+                    Err(format!("unknown node category '{}' (expected one of: {})", category, NODE_CATEGORIES.join(", ")))
+                }
+            })
+            .collect()
+    }
+
+    pub fn is_active(&self) -> bool {
+        !self.hide.is_empty() || !self.only.is_empty()
+    }
+
+    pub fn keeps(&self, node: &CfgNode) -> bool {
+        let categories = Self::categories(node);
+        if !self.only.is_empty() && !categories.iter().any(|c| self.only.iter().any(|o| o == c)) {
+            return false;
+        }
+        !categories.iter().any(|c| self.hide.iter().any(|h| h == c))
+    }
+
+    // A condition is both a 'condition' and either a 'branch' or a 'loop'
+    fn categories(node: &CfgNode) -> Vec<&'static str> {
+        match node {
+            CfgNode::Function(_, _) => vec!["functions"],
+            CfgNode::Precondition(_, _) | CfgNode::Assumption(_, _) | CfgNode::Postcondition(_, _) | CfgNode::Invariant(_, _) | CfgNode::Assertion(_, _) | CfgNode::Decreases(_, _)
+            | CfgNode::Cutoff(_) => vec!["annotations"],
+            CfgNode::Statement(_, _) => vec!["statements"],
+            CfgNode::Condition(_, Some(ConditionalExpr::If(_))) | CfgNode::Condition(_, Some(ConditionalExpr::Match(_))) => vec!["conditions", "branches"],
+            CfgNode::Condition(_, Some(ConditionalExpr::While(_))) | CfgNode::Condition(_, Some(ConditionalExpr::ForLoop(_))) => vec!["conditions", "loops"],
+            CfgNode::Condition(_, None) => vec!["conditions"],
+            CfgNode::Return(_, _) => vec!["returns"],
+            CfgNode::MergePoint => vec!["merges"],
+        }
+    }
+}
+
+impl CfgBuilder {
+    pub fn is_node_visible(&self, node: NodeIndex) -> bool {
+        self.options.node_filter.keeps(&self.graph[node])
+    }
+
+    // Edges of the exported graph: an edge is added between two kept nodes whenever one reaches
+    // the other through hidden nodes only, of the kind of the first edge on the way that isn't a fallthrough
+    pub fn visible_edges(&self) -> Vec<(NodeIndex, NodeIndex, EdgeKind)> {
+        if !self.options.node_filter.is_active() {
+            return self.graph.edge_references()
+                .map(|edge| (edge.source(), edge.target(), edge.weight().clone()))
+                .collect();
+        }
+
+        let mut edges = Vec::new();
+        for source in self.graph.node_indices().filter(|&n| self.is_node_visible(n)) {
+            let mut visited = HashSet::new();
+            let mut stack: Vec<(NodeIndex, EdgeKind)> = self.graph.edges(source)
+                .map(|edge| (edge.target(), edge.weight().clone()))
+                .collect();
+            while let Some((node, kind)) = stack.pop() {
+                if self.is_node_visible(node) {
+                    if !edges.contains(&(source, node, kind.clone())) {
+                        edges.push((source, node, kind));
+                    }
+                    continue;
+                }
+                // Hidden nodes are revisited under another kind, so both branches of a hidden join show up
+                if !visited.insert((node, kind.clone())) {
+                    continue;
+                }
+                for edge in self.graph.edges(node) {
+                    let next_kind = if kind == EdgeKind::Fallthrough { edge.weight().clone() } else { kind.clone() };
+                    stack.push((edge.target(), next_kind));
+                }
+            }
+        }
+        edges.sort();
+        edges
+    }
+}
@@ -3,10 +3,10 @@ use std::collections::HashSet;
 use quote::quote;
 use std::fs::File;
 use std::io::Write;
-use crate::cfg_builder::{builder::CfgBuilder, node::CfgNode, node::ConditionalExpr};
+use crate::cfg_builder::{builder::CfgBuilder, node::CfgNode, node::EdgeKind};
 use crate::cfg_builder::handle_condition::*;
 use petgraph::visit::EdgeRef;
-use std::path::{Path};
+use std::path::PathBuf;
 
 impl CfgBuilder {
     pub fn generate_basic_paths(&mut self) -> Vec<Vec<NodeIndex>> {
@@ -18,13 +18,21 @@ impl CfgBuilder {
         }
 
         // Process paths to check for loops and invariants
-        for path in paths.iter_mut() {
-            if self.is_loop_path(path) {
-                self.process_loop_invariant_path(path);
+        let mut basic_paths = Vec::new();
+        for mut path in paths {
+            // Only a path going around the loop ends where it starts
+            if self.is_loop_path(&path) && path.first() == path.last() {
+                let termination = self.termination_path(&path);
+                self.process_loop_invariant_path(&mut path);
+                basic_paths.push(path);
+                // The same iteration must decrease the measure of the loop, when it has one
+                basic_paths.extend(termination);
+            } else {
+                basic_paths.push(path);
             }
         }
 
-        paths
+        basic_paths
     }
 
     fn get_condition_nodes(&self) -> Vec<NodeIndex> {
@@ -48,9 +56,13 @@ impl CfgBuilder {
         current_path.push(current_node);
 
         // Collect edge information first to avoid borrowing issues
-        let edges_info: Vec<(NodeIndex, String)> = self.graph.edges(current_node)
-        .map(|edge| (edge.target(), edge.weight().clone()))
-        .collect();
+        let targets: Vec<NodeIndex> = match self.results.region_summaries.get(&current_node) {
+            // A summarized region is crossed in one step
+            Some(region) => vec![region.exit],
+            None => self.graph.edges(current_node)
+                .map(|edge| edge.target())
+                .collect(),
+        };
 
         // Check for a terminal condition or another condition node
         if matches!(
@@ -64,7 +76,7 @@ impl CfgBuilder {
             paths.push(current_path.clone());
         } else {
             // Continue exploring adjacent nodes
-            for (target, edge_label) in edges_info {
+            for target in targets {
                 self.find_paths(target, current_path, paths);
             }
         }
@@ -73,10 +85,10 @@ impl CfgBuilder {
     }
 
     fn is_loop_path(&self, path: &Vec<NodeIndex>) -> bool {
-        // Check if there's a "back to loop" edge in the path, indicating a loop structure
+        // Check if there's a back edge in the path, indicating a loop structure
         path.windows(2).any(|pair| {
             if let [from, to] = pair {
-                self.graph.edges_connecting(*from, *to).any(|edge| edge.weight() == "back to loop")
+                self.graph.edges_connecting(*from, *to).any(|edge| *edge.weight() == EdgeKind::BackEdge)
             } else {
                 false
             }
@@ -88,6 +100,8 @@ impl CfgBuilder {
             if let CfgNode::Invariant(cond, expr) = &self.graph[first_node] {
                 // Create a new terminal node with the same invariant condition
                 let new_terminal_node = self.graph.add_node(CfgNode::Invariant(cond.clone(), expr.clone()));
+                let terminal_id = format!("{}/end", self.node_id(first_node));
+                self.results.node_ids.insert(new_terminal_node, terminal_id);
 
                 // Remove the last node in the path
                 path.pop();
@@ -98,41 +112,168 @@ impl CfgBuilder {
         }
     }
 
-    pub fn write_paths_to_dot_files(&self, paths: Vec<Vec<NodeIndex>>, base_path: &Path) {
-        // Create the output directory if it doesn't exist
-        std::fs::create_dir_all(base_path).expect("Unable to create base directory for paths");
+    // The path around a loop with a 'decreases!' right after its head, ending at a copy of that node instead
+    // of the head: its goal is the measure, non-negative at the head and smaller at the end of the iteration
+    fn termination_path(&mut self, path: &[NodeIndex]) -> Option<Vec<NodeIndex>> {
+        let &decreases_node = path.get(1).filter(|&&node| matches!(self.graph[node], CfgNode::Decreases(_, _)))?;
+        let terminal_node = self.graph.add_node(self.graph[decreases_node].clone());
+        let terminal_id = format!("{}/end", self.node_id(decreases_node));
+        self.results.node_ids.insert(terminal_node, terminal_id);
+        let mut termination = path[..path.len() - 1].to_vec();
+        termination.push(terminal_node);
+        Some(termination)
+    }
 
-        for (i, path) in paths.iter().enumerate() {
-            let mut dot_string = String::from("digraph Path {\n");
+    // Label of a path boundary in reports ('post: sum >= 0')
+    pub fn annotation_label(&self, node: NodeIndex) -> String {
+        match &self.graph[node] {
+            CfgNode::Precondition(pre, _) => format!("pre: {}", pre),
+            CfgNode::Postcondition(post, _) => format!("post: {}", post),
+            CfgNode::Invariant(inv, _) => format!("invariant: {}", inv),
+            CfgNode::Cutoff(inv) => format!("cutoff: {}", inv),
+            CfgNode::Decreases(measure, _) => format!("decreases: {}", measure),
+            other => other.label(),
+        }
+    }
 
-            // Add nodes to the DOT string
-            for &node in path {
-                let cfg_node = &self.graph[node];
-                dot_string.push_str(&cfg_node.format_dot(node.index()));
-                dot_string.push('\n');
-            }
+    // Annotation a basic path discharges: the ID of its last node and its label. The copy of the
+    // invariant ending a loop path stands for the invariant itself.
+    pub fn path_target(&self, path: &[NodeIndex]) -> Option<(String, String)> {
+        let last = *path.last()?;
+        let id = self.node_id(last);
+        let id = id.strip_suffix("/end").map(str::to_string).unwrap_or(id);
+        Some((id, self.annotation_label(last)))
+    }
 
-            // Add edges for path
-            for window in path.windows(2) {
-                if let [from, to] = window {
-                    // Find all edges connecting 'from' to 'to'
-                    let edges: Vec<_> = self.graph.edges_connecting(*from, *to).collect();
-
-                    if let Some(edge) = edges.first() {
-                        let label = &self.graph[edge.id()];
-                        dot_string.push_str(&format!("{} -> {} [label=\"{}\"];\n", from.index(), to.index(), label));
-                    } else {
-                        dot_string.push_str(&format!("{} -> {};\n", from.index(), to.index()));
-                    }
-                }
-            }
+    // Write one DOT file per basic path, path i in 'dirs[i]', returning the files written. The nodes in
+    // 'irrelevant[i]' (not needed by the proof of path i) are dimmed.
+    pub fn write_paths_to_dot_files(&self, paths: Vec<Vec<NodeIndex>>, irrelevant: &[HashSet<NodeIndex>], dirs: &[PathBuf]) -> Vec<PathBuf> {
+        let mut written = Vec::new();
 
-            dot_string.push_str("}\n");
+        for (i, (path, base_path)) in paths.iter().zip(dirs).enumerate() {
+            // Create the output directory if it doesn't exist
+            std::fs::create_dir_all(base_path).expect("Unable to create base directory for paths");
+            let dot_string = self.path_to_dot(path, irrelevant.get(i));
 
             // Write the DOT file
             let dot_file_path = base_path.join(format!("basic_path_{}.dot", i));
             let mut dot_file = File::create(&dot_file_path).expect("Unable to create DOT file");
             dot_file.write_all(dot_string.as_bytes()).expect("Unable to write to DOT file");
+            written.push(dot_file_path);
+        }
+        written
+    }
+
+    // DOT graph of one basic path
+    pub fn path_to_dot(&self, path: &[NodeIndex], irrelevant: Option<&HashSet<NodeIndex>>) -> String {
+        format!("digraph Path {{\n{}}}\n", self.path_dot_statements(path, irrelevant, ""))
+    }
+
+    // The basic paths of one function in one DOT file, each in a cluster labeled with its number, the
+    // annotations it goes from and to, and its verdict. The nodes of a cluster are the path's node IDs
+    // prefixed with 'p<number>:', since the same node is on several paths.
+    pub fn paths_to_cluster_dot(&self, paths: &[BundledPath]) -> String {
+        let mut dot_string = String::from("digraph Paths {\n");
+        for path in paths {
+            dot_string.push_str(&format!("subgraph cluster_path_{} {{\n", path.number));
+            dot_string.push_str(&format!("label=\"{}\";\n", self.bundled_path_title(path).replace('"', "\\\"")));
+            dot_string.push_str(&self.path_dot_statements(path.nodes, Some(path.irrelevant), &format!("p{}:", path.number)));
+            dot_string.push_str("}\n");
+        }
+        dot_string.push_str("}\n");
+        dot_string
+    }
+
+    // Index of the basic paths of one function: a link per path, and the graph of each path, rendered when it is
+    // opened
+    pub fn paths_to_html(&self, function: &str, paths: &[BundledPath]) -> String {
+        let mut html = String::from(concat!(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n",
+            "<script src=\"https://unpkg.com/@viz-js/viz@3/lib/viz-standalone.js\"></script>\n",
+        ));
+        html.push_str(&format!("<title>Basic paths of {}</title>\n</head>\n<body>\n", Self::escape_html(function)));
+        html.push_str(&format!("<h1>Basic paths of {} ({})</h1>\n<ul>\n", Self::escape_html(function), paths.len()));
+        for path in paths {
+            html.push_str(&format!("<li><a href=\"#path-{}\">{}</a></li>\n", path.number, Self::escape_html(&self.bundled_path_title(path))));
+        }
+        html.push_str("</ul>\n");
+        for path in paths {
+            html.push_str(&format!("<details id=\"path-{}\">\n<summary>{}</summary>\n<div class=\"graph\" data-dot=\"", path.number, Self::escape_html(&self.bundled_path_title(path))));
+            html.push_str(&Self::escape_html(&self.path_to_dot(path.nodes, Some(path.irrelevant))));
+            html.push_str("\"></div>\n</details>\n");
         }
+
+        // A path is rendered the first time it is opened, or shown as DOT source without Viz.js
+        html.push_str(concat!(
+            "<script>\n",
+            "function render(details) {\n",
+            "  var el = details.querySelector('.graph');\n",
+            "  if (el.childElementCount > 0) return;\n",
+            "  var dot = el.getAttribute('data-dot');\n",
+            "  if (window.Viz) {\n",
+            "    Viz.instance().then(function (viz) { el.appendChild(viz.renderSVGElement(dot)); });\n",
+            "  } else {\n",
+            "    var pre = document.createElement('pre'); pre.textContent = dot; el.appendChild(pre);\n",
+            "  }\n",
+            "}\n",
+            "document.querySelectorAll('details').forEach(function (details) {\n",
+            "  details.addEventListener('toggle', function () { if (details.open) render(details); });\n",
+            "});\n",
+            "var target = location.hash && document.querySelector(location.hash);\n",
+            "if (target) { target.open = true; }\n",
+            "</script>\n</body>\n</html>\n",
+        ));
+        html
+    }
+
+    // 'Path 3: pre: x >= 0 -> post: y >= 0 (proved)'
+    fn bundled_path_title(&self, path: &BundledPath) -> String {
+        let from = path.nodes.first().map(|&node| self.options.notation.render(&self.annotation_label(node))).unwrap_or_default();
+        let to = path.nodes.last().map(|&node| self.options.notation.render(&self.annotation_label(node))).unwrap_or_default();
+        let verdict = path.verdict.map(|verdict| format!(" ({})", verdict)).unwrap_or_default();
+        format!("Path {}: {} -> {}{}", path.number, from, to, verdict)
     }
+
+    // Nodes and edges of a basic path, with their IDs prefixed by 'prefix'. The nodes in 'irrelevant' are dimmed.
+    fn path_dot_statements(&self, path: &[NodeIndex], irrelevant: Option<&HashSet<NodeIndex>>, prefix: &str) -> String {
+        let mut dot_string = String::new();
+        let id = |node: NodeIndex| format!("{}{}", prefix, self.node_id(node));
+
+        // Add nodes to the DOT string
+        for &node in path {
+            dot_string.push_str(&self.format_node_dot_as(node, &id(node)));
+            dot_string.push('\n');
+            if irrelevant.is_some_and(|nodes| nodes.contains(&node)) {
+                dot_string.push_str(&format!("\"{}\" [color=gray70, fontcolor=gray70];\n", id(node)));
+            }
+        }
+
+        // Add edges for path
+        for (position, window) in path.windows(2).enumerate() {
+            if let [from, to] = window {
+                // Find all edges connecting 'from' to 'to'
+                let edges: Vec<_> = self.graph.edges_connecting(*from, *to).collect();
+
+                if let Some(region) = self.region_at(path, position) {
+                    let label = format!("region ({} paths)", region.paths.len());
+                    dot_string.push_str(&format!("\"{}\" -> \"{}\" [label=\"{}\", style=dashed];\n", id(*from), id(*to), label));
+                } else if let Some(edge) = edges.first() {
+                    let label = self.graph[edge.id()].label();
+                    dot_string.push_str(&format!("\"{}\" -> \"{}\" [label=\"{}\"];\n", id(*from), id(*to), label));
+                } else {
+                    dot_string.push_str(&format!("\"{}\" -> \"{}\";\n", id(*from), id(*to)));
+                }
+            }
+        }
+        dot_string
+    }
+}
+
+// One basic path of a bundle: its number in the run output, its nodes, the nodes its proof doesn't need and its
+// verdict ('proved', 'failed', 'undecided'), if it was verified
+pub struct BundledPath<'a> {
+    pub number: usize,
+    pub nodes: &'a [NodeIndex],
+    pub irrelevant: &'a HashSet<NodeIndex>,
+    pub verdict: Option<&'static str>,
 }
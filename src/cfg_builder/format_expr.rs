@@ -0,0 +1,395 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::punctuated::Punctuated;
+use syn::{BinOp, Expr, ExprParen, GenericArgument, Local, MacroDelimiter, Pat, PathArguments, ReturnType, Stmt, Token, Type, UnOp};
+
+use crate::cfg_builder::builder::CfgBuilder;
+
+// Labels rebuilt from the AST. The parentheses of the source are kept, others are only added where a
+// synthesized tree (e.g. after a substitution) needs them to keep its precedence.
+impl CfgBuilder {
+    // Render an expression as a readable label
+    pub fn render_expr(expr: &Expr) -> String {
+        match expr {
+            Expr::Lit(expr_lit) => quote!(#expr_lit).to_string(),
+            Expr::Path(expr_path) => Self::render_path(&expr_path.path),
+            // Parentheses a substitution added are only rendered where the precedence needs them
+            Expr::Paren(paren) if Self::is_synthesized(paren) => Self::render_expr(&paren.expr),
+            Expr::Paren(paren) => format!("({})", Self::render_expr(&paren.expr)),
+            Expr::Group(group) => Self::render_expr(&group.expr),
+            Expr::Unary(unary) => {
+                let op = match unary.op {
+                    UnOp::Deref(_) => "*",
+                    UnOp::Not(_) => "!",
+                    UnOp::Neg(_) => "-",
+                };
+                format!("{}{}", op, Self::render_operand(&unary.expr, Self::UNARY_PRECEDENCE, false))
+            },
+            Expr::Binary(bin) => {
+                let precedence = Self::binop_precedence(&bin.op);
+                // Assignment operators are right associative, every other operator is left associative
+                let right_assoc = precedence == Self::ASSIGN_PRECEDENCE;
+                // 'x as i32 < y' would read '<' as the generic arguments of 'i32'
+                let left = if matches!(bin.op, BinOp::Lt(_) | BinOp::Shl(_)) && Self::ends_with_cast(&bin.left) {
+                    format!("({})", Self::render_expr(&bin.left))
+                } else {
+                    Self::render_operand(&bin.left, precedence, right_assoc)
+                };
+                format!(
+                    "{} {} {}",
+                    left,
+                    Self::render_binop(&bin.op),
+                    Self::render_operand(&bin.right, precedence, !right_assoc),
+                )
+            },
+            Expr::Assign(assign) => format!(
+                "{} = {}",
+                Self::render_expr(&assign.left),
+                Self::render_expr(&assign.right)
+            ),
+            Expr::AssignOp(assign_op) => format!(
+                "{} {} {}",
+                Self::render_expr(&assign_op.left),
+                Self::render_binop(&assign_op.op),
+                Self::render_expr(&assign_op.right)
+            ),
+            Expr::Cast(cast) => format!(
+                "{} as {}",
+                Self::render_operand(&cast.expr, Self::CAST_PRECEDENCE, false),
+                Self::render_type(&cast.ty)
+            ),
+            Expr::Call(call) => format!(
+                "{}({})",
+                Self::render_operand(&call.func, Self::POSTFIX_PRECEDENCE, false),
+                Self::render_expr_list(call.args.iter())
+            ),
+            Expr::MethodCall(method_call) => {
+                let turbofish = method_call.turbofish.as_ref()
+                    .map(|t| format!("::<{}>", t.args.iter().map(|arg| match arg {
+                        syn::GenericMethodArgument::Type(ty) => Self::render_type(ty),
+                        syn::GenericMethodArgument::Const(expr) => Self::render_expr(expr),
+                    }).collect::<Vec<_>>().join(", ")))
+                    .unwrap_or_default();
+                format!(
+                    "{}.{}{}({})",
+                    Self::render_operand(&method_call.receiver, Self::POSTFIX_PRECEDENCE, false),
+                    method_call.method,
+                    turbofish,
+                    Self::render_expr_list(method_call.args.iter())
+                )
+            },
+            Expr::Field(field) => {
+                let member = match &field.member {
+                    syn::Member::Named(ident) => ident.to_string(),
+                    syn::Member::Unnamed(index) => index.index.to_string(),
+                };
+                format!("{}.{}", Self::render_operand(&field.base, Self::POSTFIX_PRECEDENCE, false), member)
+            },
+            Expr::Index(index) => format!(
+                "{}[{}]",
+                Self::render_operand(&index.expr, Self::POSTFIX_PRECEDENCE, false),
+                Self::render_expr(&index.index)
+            ),
+            Expr::Try(expr_try) => format!("{}?", Self::render_operand(&expr_try.expr, Self::POSTFIX_PRECEDENCE, false)),
+            Expr::Await(expr_await) => format!("{}.await", Self::render_operand(&expr_await.base, Self::POSTFIX_PRECEDENCE, false)),
+            Expr::Reference(reference) => {
+                let mutability = if reference.mutability.is_some() { "mut " } else { "" };
+                format!("&{}{}", mutability, Self::render_operand(&reference.expr, Self::UNARY_PRECEDENCE, false))
+            },
+            Expr::Range(range) => {
+                let limits = match range.limits {
+                    syn::RangeLimits::HalfOpen(_) => "..",
+                    syn::RangeLimits::Closed(_) => "..=",
+                };
+                let from = range.from.as_ref().map(|e| Self::render_operand(e, Self::RANGE_PRECEDENCE, true)).unwrap_or_default();
+                let to = range.to.as_ref().map(|e| Self::render_operand(e, Self::RANGE_PRECEDENCE, true)).unwrap_or_default();
+                format!("{}{}{}", from, limits, to)
+            },
+            Expr::Tuple(tuple) => {
+                if tuple.elems.len() == 1 {
+                    format!("({},)", Self::render_expr(&tuple.elems[0]))
+                } else {
+                    format!("({})", Self::render_expr_list(tuple.elems.iter()))
+                }
+            },
+            Expr::Array(array) => format!("[{}]", Self::render_expr_list(array.elems.iter())),
+            Expr::Repeat(repeat) => format!("[{}; {}]", Self::render_expr(&repeat.expr), Self::render_expr(&repeat.len)),
+            Expr::Struct(expr_struct) => {
+                let mut fields: Vec<String> = expr_struct.fields.iter().map(|field| {
+                    let member = match &field.member {
+                        syn::Member::Named(ident) => ident.to_string(),
+                        syn::Member::Unnamed(index) => index.index.to_string(),
+                    };
+                    if field.colon_token.is_some() {
+                        format!("{}: {}", member, Self::render_expr(&field.expr))
+                    } else {
+                        member
+                    }
+                }).collect();
+                if let Some(rest) = &expr_struct.rest {
+                    fields.push(format!("..{}", Self::render_expr(rest)));
+                } else if expr_struct.dot2_token.is_some() {
+                    fields.push("..".to_string());
+                }
+                format!("{} {{ {} }}", Self::render_path(&expr_struct.path), fields.join(", "))
+            },
+            Expr::Closure(closure) => {
+                let movability = if closure.capture.is_some() { "move " } else { "" };
+                let inputs = closure.inputs.iter().map(Self::render_pat).collect::<Vec<_>>().join(", ");
+                let output = match &closure.output {
+                    ReturnType::Default => String::new(),
+                    ReturnType::Type(_, ty) => format!(" -> {}", Self::render_type(ty)),
+                };
+                format!("{}|{}|{} {}", movability, inputs, output, Self::render_expr(&closure.body))
+            },
+            Expr::Macro(expr_macro) => {
+                let name = Self::render_path(&expr_macro.mac.path);
+                let (open, close) = match expr_macro.mac.delimiter {
+                    MacroDelimiter::Paren(_) => ("(", ")"),
+                    MacroDelimiter::Bracket(_) => ("[", "]"),
+                    MacroDelimiter::Brace(_) => ("{ ", " }"),
+                };
+                format!("{}!{}{}{}", name, open, Self::render_macro_tokens(&expr_macro.mac.tokens), close)
+            },
+            Expr::Let(expr_let) => format!("let {} = {}", Self::render_pat(&expr_let.pat), Self::render_expr(&expr_let.expr)),
+            Expr::Return(expr_return) => match &expr_return.expr {
+                Some(value) => format!("return {}", Self::render_expr(value)),
+                None => "return".to_string(),
+            },
+            Expr::Break(expr_break) => {
+                let mut rendered = "break".to_string();
+                if let Some(label) = &expr_break.label {
+                    rendered.push_str(&format!(" {}", label));
+                }
+                if let Some(value) = &expr_break.expr {
+                    rendered.push_str(&format!(" {}", Self::render_expr(value)));
+                }
+                rendered
+            },
+            Expr::Continue(expr_continue) => match &expr_continue.label {
+                Some(label) => format!("continue {}", label),
+                None => "continue".to_string(),
+            },
+            Expr::Block(block) if block.block.stmts.len() == 1 => {
+                format!("{{ {} }}", Self::render_stmt(&block.block.stmts[0]))
+            },
+            _ => Self::clean_up_formatting(&quote!(#expr).to_string()),
+        }
+    }
+
+    // Render a statement as a readable label
+    pub fn render_stmt(stmt: &Stmt) -> String {
+        match stmt {
+            Stmt::Local(local) => Self::render_local(local),
+            Stmt::Expr(expr) => Self::render_expr(expr),
+            Stmt::Semi(expr, _) => format!("{};", Self::render_expr(expr)),
+            Stmt::Item(item) => Self::clean_up_formatting(&quote!(#item).to_string()),
+        }
+    }
+
+    // Render a 'let' binding, keeping the trailing semicolon so the label stays a valid statement
+    pub fn render_local(local: &Local) -> String {
+        match &local.init {
+            Some((_, init)) => format!("let {} = {};", Self::render_pat(&local.pat), Self::render_expr(init)),
+            None => format!("let {};", Self::render_pat(&local.pat)),
+        }
+    }
+
+    pub fn render_pat(pat: &Pat) -> String {
+        match pat {
+            Pat::Ident(pat_ident) => {
+                let mut rendered = String::new();
+                if pat_ident.by_ref.is_some() {
+                    rendered.push_str("ref ");
+                }
+                if pat_ident.mutability.is_some() {
+                    rendered.push_str("mut ");
+                }
+                rendered.push_str(&pat_ident.ident.to_string());
+                if let Some((_, sub_pat)) = &pat_ident.subpat {
+                    rendered.push_str(&format!(" @ {}", Self::render_pat(sub_pat)));
+                }
+                rendered
+            },
+            Pat::Wild(_) => "_".to_string(),
+            Pat::Rest(_) => "..".to_string(),
+            Pat::Lit(pat_lit) => Self::render_expr(&pat_lit.expr),
+            Pat::Path(pat_path) => Self::render_path(&pat_path.path),
+            Pat::Range(pat_range) => {
+                let limits = match pat_range.limits {
+                    syn::RangeLimits::HalfOpen(_) => "..",
+                    syn::RangeLimits::Closed(_) => "..=",
+                };
+                format!("{}{}{}", Self::render_expr(&pat_range.lo), limits, Self::render_expr(&pat_range.hi))
+            },
+            Pat::Reference(pat_ref) => {
+                let mutability = if pat_ref.mutability.is_some() { "mut " } else { "" };
+                format!("&{}{}", mutability, Self::render_pat(&pat_ref.pat))
+            },
+            Pat::Tuple(tuple) => format!("({})", tuple.elems.iter().map(Self::render_pat).collect::<Vec<_>>().join(", ")),
+            Pat::TupleStruct(tuple_struct) => format!(
+                "{}({})",
+                Self::render_path(&tuple_struct.path),
+                tuple_struct.pat.elems.iter().map(Self::render_pat).collect::<Vec<_>>().join(", ")
+            ),
+            Pat::Struct(pat_struct) => {
+                let mut fields: Vec<String> = pat_struct.fields.iter().map(|field| {
+                    let member = match &field.member {
+                        syn::Member::Named(ident) => ident.to_string(),
+                        syn::Member::Unnamed(index) => index.index.to_string(),
+                    };
+                    if field.colon_token.is_some() {
+                        format!("{}: {}", member, Self::render_pat(&field.pat))
+                    } else {
+                        member
+                    }
+                }).collect();
+                if pat_struct.dot2_token.is_some() {
+                    fields.push("..".to_string());
+                }
+                format!("{} {{ {} }}", Self::render_path(&pat_struct.path), fields.join(", "))
+            },
+            Pat::Slice(slice) => format!("[{}]", slice.elems.iter().map(Self::render_pat).collect::<Vec<_>>().join(", ")),
+            Pat::Or(pat_or) => pat_or.cases.iter().map(Self::render_pat).collect::<Vec<_>>().join(" | "),
+            Pat::Type(pat_type) => format!("{}: {}", Self::render_pat(&pat_type.pat), Self::render_type(&pat_type.ty)),
+            _ => Self::clean_up_formatting(&quote!(#pat).to_string()),
+        }
+    }
+
+    pub fn render_type(ty: &Type) -> String {
+        match ty {
+            Type::Path(type_path) => Self::render_path(&type_path.path),
+            Type::Reference(reference) => {
+                let lifetime = reference.lifetime.as_ref().map(|l| format!("{} ", l)).unwrap_or_default();
+                let mutability = if reference.mutability.is_some() { "mut " } else { "" };
+                format!("&{}{}{}", lifetime, mutability, Self::render_type(&reference.elem))
+            },
+            Type::Slice(slice) => format!("[{}]", Self::render_type(&slice.elem)),
+            Type::Array(array) => format!("[{}; {}]", Self::render_type(&array.elem), Self::render_expr(&array.len)),
+            Type::Tuple(tuple) => format!("({})", tuple.elems.iter().map(Self::render_type).collect::<Vec<_>>().join(", ")),
+            Type::Ptr(ptr) => {
+                let mutability = if ptr.mutability.is_some() { "mut" } else { "const" };
+                format!("*{} {}", mutability, Self::render_type(&ptr.elem))
+            },
+            Type::Paren(paren) => format!("({})", Self::render_type(&paren.elem)),
+            Type::Group(group) => Self::render_type(&group.elem),
+            Type::Never(_) => "!".to_string(),
+            Type::Infer(_) => "_".to_string(),
+            _ => Self::clean_up_formatting(&quote!(#ty).to_string()),
+        }
+    }
+
+    pub fn render_path(path: &syn::Path) -> String {
+        let segments: Vec<String> = path.segments.iter().map(|segment| {
+            let args = match &segment.arguments {
+                PathArguments::None => String::new(),
+                PathArguments::AngleBracketed(angle) => {
+                    let args: Vec<String> = angle.args.iter().map(|arg| match arg {
+                        GenericArgument::Type(ty) => Self::render_type(ty),
+                        GenericArgument::Lifetime(lifetime) => lifetime.to_string(),
+                        GenericArgument::Const(expr) => Self::render_expr(expr),
+                        other => Self::clean_up_formatting(&quote!(#other).to_string()),
+                    }).collect();
+                    let colons = if angle.colon2_token.is_some() { "::" } else { "" };
+                    format!("{}<{}>", colons, args.join(", "))
+                },
+                PathArguments::Parenthesized(parenthesized) => {
+                    let inputs = parenthesized.inputs.iter().map(Self::render_type).collect::<Vec<_>>().join(", ");
+                    match &parenthesized.output {
+                        ReturnType::Default => format!("({})", inputs),
+                        ReturnType::Type(_, ty) => format!("({}) -> {}", inputs, Self::render_type(ty)),
+                    }
+                },
+            };
+            format!("{}{}", segment.ident, args)
+        }).collect();
+        let leading = if path.leading_colon.is_some() { "::" } else { "" };
+        format!("{}{}", leading, segments.join("::"))
+    }
+
+    // Macro arguments are rendered as expressions when possible (e.g. 'vec![1, 2]', 'assert!(a > b)')
+    fn render_macro_tokens(tokens: &TokenStream) -> String {
+        let parser = Punctuated::<Expr, Token![,]>::parse_terminated;
+        match syn::parse::Parser::parse2(parser, tokens.clone()) {
+            Ok(args) => Self::render_expr_list(args.iter()),
+            Err(_) => Self::clean_up_formatting(&tokens.to_string()),
+        }
+    }
+
+    fn render_expr_list<'a>(exprs: impl Iterator<Item = &'a Expr>) -> String {
+        exprs.map(Self::render_expr).collect::<Vec<_>>().join(", ")
+    }
+
+    // Render a sub-expression, wrapping it in parentheses when its precedence would otherwise change the meaning
+    fn render_operand(expr: &Expr, parent_precedence: u8, strict: bool) -> String {
+        let rendered = Self::render_expr(expr);
+        let precedence = Self::expr_precedence(expr);
+        if precedence < parent_precedence || (strict && precedence == parent_precedence) {
+            format!("({})", rendered)
+        } else {
+            rendered
+        }
+    }
+
+    // Whether an expression keeps its meaning as the operand of any operator without parentheses, so that a
+    // substituted value only gets them where it needs them
+    pub(crate) fn binds_tightest(expr: &Expr) -> bool {
+        Self::expr_precedence(expr) >= Self::POSTFIX_PRECEDENCE
+            && !matches!(expr, Expr::If(_) | Expr::Match(_) | Expr::Block(_) | Expr::Unsafe(_) | Expr::Struct(_) | Expr::Loop(_) | Expr::While(_) | Expr::ForLoop(_))
+    }
+
+    // Parentheses built by the analysis, which span no source text
+    fn is_synthesized(paren: &ExprParen) -> bool {
+        paren.paren_token.span.byte_range().is_empty()
+    }
+
+    // Whether the rendering ends with the type of a cast, 'a + x as i32'
+    fn ends_with_cast(expr: &Expr) -> bool {
+        match expr {
+            Expr::Cast(_) => true,
+            Expr::Binary(bin) => {
+                let (precedence, right_precedence) = (Self::binop_precedence(&bin.op), Self::expr_precedence(&bin.right));
+                let wrapped = right_precedence < precedence || (precedence != Self::ASSIGN_PRECEDENCE && right_precedence == precedence);
+                !wrapped && Self::ends_with_cast(&bin.right)
+            },
+            _ => false,
+        }
+    }
+
+    const ASSIGN_PRECEDENCE: u8 = 1;
+    const RANGE_PRECEDENCE: u8 = 2;
+    const CAST_PRECEDENCE: u8 = 13;
+    const UNARY_PRECEDENCE: u8 = 14;
+    const POSTFIX_PRECEDENCE: u8 = 15;
+
+    fn expr_precedence(expr: &Expr) -> u8 {
+        match expr {
+            Expr::Paren(paren) if Self::is_synthesized(paren) => Self::expr_precedence(&paren.expr),
+            Expr::Assign(_) | Expr::AssignOp(_) | Expr::Closure(_) | Expr::Return(_) | Expr::Break(_) => Self::ASSIGN_PRECEDENCE,
+            Expr::Range(_) => Self::RANGE_PRECEDENCE,
+            Expr::Binary(bin) => Self::binop_precedence(&bin.op),
+            Expr::Cast(_) => Self::CAST_PRECEDENCE,
+            Expr::Unary(_) | Expr::Reference(_) => Self::UNARY_PRECEDENCE,
+            _ => u8::MAX,
+        }
+    }
+
+    fn binop_precedence(op: &BinOp) -> u8 {
+        match op {
+            BinOp::Mul(_) | BinOp::Div(_) | BinOp::Rem(_) => 12,
+            BinOp::Add(_) | BinOp::Sub(_) => 11,
+            BinOp::Shl(_) | BinOp::Shr(_) => 10,
+            BinOp::BitAnd(_) => 9,
+            BinOp::BitXor(_) => 8,
+            BinOp::BitOr(_) => 7,
+            BinOp::Eq(_) | BinOp::Lt(_) | BinOp::Le(_) | BinOp::Ne(_) | BinOp::Ge(_) | BinOp::Gt(_) => 6,
+            BinOp::And(_) => 5,
+            BinOp::Or(_) => 4,
+            _ => Self::ASSIGN_PRECEDENCE,
+        }
+    }
+
+    pub(crate) fn render_binop(op: &BinOp) -> String {
+        quote!(#op).to_string().replace(' ', "")
+    }
+}
@@ -0,0 +1,64 @@
+use quote::ToTokens;
+use syn::spanned::Spanned;
+use syn::{Expr, Stmt, UnOp};
+
+use crate::cfg_builder::builder::CfgBuilder;
+use crate::cfg_builder::call_strategy::CallTarget;
+use crate::cfg_builder::node::{AssignmentInfo, CfgNode, PlaceKind};
+
+impl CfgBuilder {
+    // 'x = e' and 'x op= e': a statement node recording the written place and the value separately
+    pub fn handle_assignment(&mut self, expr: &Expr) {
+        let (place, operator, value) = match expr {
+            Expr::Assign(assign) => (&*assign.left, "=".to_string(), &*assign.right),
+            Expr::AssignOp(assign_op) => (&*assign_op.left, assign_op.op.to_token_stream().to_string(), &*assign_op.right),
+            _ => return,
+        };
+        if matches!(expr, Expr::Assign(_)) && self.handle_branching_assignment(place, value) {
+            return;
+        }
+        // 'y = f(x)' with 'f' configured to be inlined
+        let target = match (expr, place) {
+            (Expr::Assign(_), Expr::Path(expr_path)) => expr_path.path.get_ident().map(|var| CallTarget::Variable(var.clone())),
+            _ => None,
+        };
+        if self.inline_local_call(Some(target.unwrap_or(CallTarget::Place)), value, value.span().start().line) {
+            return;
+        }
+        // Only 'x = f(..)' takes the value of an indirect call, 'x += f(..)' also depends on the old value
+        let assigned = match (matches!(expr, Expr::Assign(_)), Self::place_kind(place), Self::place_root(place)) {
+            (true, PlaceKind::Variable, Some(root)) => vec![root],
+            _ => Vec::new(),
+        };
+        let indirect_call = self.indirect_call(value, assigned, value.span().start().line);
+        if let Some(call) = &indirect_call {
+            self.add_call_preconditions(call);
+        }
+        let assignment_node = self.add_node(CfgNode::new_statement(Self::render_expr(expr), Stmt::Expr(expr.clone())));
+        self.metadata_mut(assignment_node).assignment = Some(AssignmentInfo {
+            place: Self::render_expr(place),
+            place_kind: Self::place_kind(place),
+            place_root: Self::place_root(place),
+            operator,
+            value: Self::render_expr(value),
+        });
+        if matches!(expr, Expr::Assign(_)) && matches!(Self::place_kind(place), PlaceKind::Variable | PlaceKind::Field) {
+            self.record_construction(assignment_node, &Self::render_expr(place), value);
+        }
+        self.record_method_call(assignment_node, expr);
+        if let Some(call) = indirect_call {
+            self.record_indirect_call(assignment_node, call);
+        }
+    }
+
+    fn place_kind(place: &Expr) -> PlaceKind {
+        match place {
+            Expr::Path(expr_path) if expr_path.path.get_ident().is_some() => PlaceKind::Variable,
+            Expr::Field(_) => PlaceKind::Field,
+            Expr::Index(_) => PlaceKind::Index,
+            Expr::Unary(unary) if matches!(unary.op, UnOp::Deref(_)) => PlaceKind::Deref,
+            Expr::Paren(paren) => Self::place_kind(&paren.expr),
+            _ => PlaceKind::Other,
+        }
+    }
+}
@@ -1,12 +1,31 @@
 use syn::{ExprIf};
 
 use crate::cfg_builder::builder::CfgBuilder;
-use crate::cfg_builder::node::{CfgNode, ConditionalExpr};
-use quote::quote;
+use crate::cfg_builder::node::{CfgNode, EdgeKind, MethodCallInfo};
+use petgraph::graph::NodeIndex;
 use syn::{ visit::{self, Visit}, Expr, Pat, Stmt, ExprCall, ExprMethodCall };
+use syn::spanned::Spanned;
 
 impl CfgBuilder {
     pub fn handle_call(&mut self, expr_call: &ExprCall) {
+        // 'std::process::exit(..)' and functions returning '!' end the path: nothing after them runs
+        if self.is_diverging_call(expr_call) {
+            let call_description = format!("Call: {}", Self::render_expr(&Expr::Call(expr_call.clone())));
+            // A branch into the call keeps its kind: the branch is what the condition selects
+            self.next_edge_kind.get_or_insert(EdgeKind::PanicEdge);
+            let call_node = self.add_node(CfgNode::new_statement(call_description, Stmt::Expr(Expr::Call(expr_call.clone()))));
+            self.metadata_mut(call_node).facts.push("never returns: the path ends here".to_string());
+            self.current_node = None;
+            return;
+        }
+        // A call through a pointer or closure gets a node of its own, for its contract or its effects
+        let call_expr = Expr::Call(expr_call.clone());
+        if let Some(call) = self.indirect_call(&call_expr, Vec::new(), expr_call.span().start().line) {
+            self.add_call_preconditions(&call);
+            let call_description = format!("Call: {}", Self::render_expr(&call_expr));
+            let call_node = self.add_node(CfgNode::new_statement(call_description, Stmt::Expr(call_expr)));
+            self.record_indirect_call(call_node, call);
+        }
         if let Expr::Path(expr_path) = &*expr_call.func {
             if let Some(segment) = expr_path.path.segments.last() {
                 if segment.ident == "vec" {
@@ -34,10 +53,11 @@ impl CfgBuilder {
             }
     
             // Add the full method call expression
-            let call_expression = quote!(#expr_method_call).to_string();
-            let call_description = format!("Call: {}", Self::clean_up_formatting(&call_expression));
+            let call_expression = Self::render_expr(&Expr::MethodCall(expr_method_call.clone()));
+            let call_description = format!("Call: {}", call_expression);
             let call_statement = Stmt::Expr(Expr::MethodCall(expr_method_call.clone()));
-            self.add_node(CfgNode::new_statement(call_description, call_statement));
+            let call_node = self.add_node(CfgNode::new_statement(call_description, call_statement));
+            self.metadata_mut(call_node).method_call = Some(Self::method_call_info(expr_method_call));
     
             // Add postconditions after the method call
             for post in external_method.postconditions {
@@ -45,10 +65,157 @@ impl CfgBuilder {
             }
         } else {
             // If no external conditions match, add the method call as a single node
-            let call_expression = quote!(#expr_method_call).to_string();
-            let call_description = format!("Call: {}", Self::clean_up_formatting(&call_expression));
+            let indirect_call = self.indirect_call(&Expr::MethodCall(expr_method_call.clone()), Vec::new(), expr_method_call.span().start().line);
+            if let Some(call) = &indirect_call {
+                self.add_call_preconditions(call);
+            }
+            let call_expression = Self::render_expr(&Expr::MethodCall(expr_method_call.clone()));
+            let call_description = format!("Call: {}", call_expression);
             let call_statement = Stmt::Expr(Expr::MethodCall(expr_method_call.clone()));
-            self.add_node(CfgNode::new_statement(call_description, call_statement));
+            let call_node = self.add_node(CfgNode::new_statement(call_description, call_statement));
+            self.metadata_mut(call_node).method_call = Some(Self::method_call_info(expr_method_call));
+            if let Some(call) = indirect_call {
+                self.record_indirect_call(call_node, call);
+            }
+        }
+    }
+
+    // Call that never returns: 'process::exit', 'process::abort' or a function or method of the file declared
+    // '-> !', called by any path ('fail()', 'Self::fail()', 'module::die()')
+    pub fn is_diverging_call(&self, expr_call: &ExprCall) -> bool {
+        let Expr::Path(expr_path) = &*expr_call.func else { return false };
+        let segments: Vec<String> = expr_path.path.segments.iter().map(|segment| segment.ident.to_string()).collect();
+        match segments.as_slice() {
+            [.., process, exit] if process == "process" && (exit == "exit" || exit == "abort") => true,
+            [.., name] => self.file.diverging_functions.contains(name),
+            [] => false,
+        }
+    }
+
+    // Record the functions and methods of the file, and of its inline modules, declared '-> !'
+    pub fn collect_diverging_functions(&mut self, items: &[syn::Item]) {
+        for item in items {
+            let signatures: Vec<&syn::Signature> = match item {
+                syn::Item::Fn(item_fn) => vec![&item_fn.sig],
+                syn::Item::Impl(item_impl) => item_impl.items.iter()
+                    .filter_map(|item| match item {
+                        syn::ImplItem::Method(method) => Some(&method.sig),
+                        _ => None,
+                    })
+                    .collect(),
+                syn::Item::Mod(item_mod) => {
+                    if let Some((_, items)) = &item_mod.content {
+                        self.collect_diverging_functions(items);
+                    }
+                    continue;
+                },
+                _ => continue,
+            };
+            for sig in signatures {
+                if matches!(&sig.output, syn::ReturnType::Type(_, ty) if matches!(**ty, syn::Type::Never(_))) {
+                    self.file.diverging_functions.insert(sig.ident.to_string());
+                }
+            }
+        }
+    }
+
+    // Record the method call whose result a statement uses ('let n = v.len();', 'x = self.get()')
+    pub fn record_method_call(&mut self, node: NodeIndex, expr: &Expr) {
+        let method_call = match expr {
+            Expr::Assign(assign) => &*assign.right,
+            Expr::AssignOp(assign_op) => &*assign_op.right,
+            expr => expr,
+        };
+        if let Expr::MethodCall(expr_method_call) = Self::strip_call_wrappers(method_call) {
+            self.metadata_mut(node).method_call = Some(Self::method_call_info(expr_method_call));
+        }
+    }
+
+    pub fn method_call_info(expr_method_call: &ExprMethodCall) -> MethodCallInfo {
+        let receiver_root = Self::place_root(&expr_method_call.receiver);
+        MethodCallInfo {
+            receiver: Self::render_expr(&expr_method_call.receiver),
+            self_receiver: receiver_root.as_deref() == Some("self"),
+            receiver_root,
+            method: expr_method_call.method.to_string(),
+            args: expr_method_call.args.iter().map(Self::render_expr).collect(),
+        }
+    }
+
+    // Variable a place is reached from: 'self' for 'self.items[i]', 'v' for 'v.iter()'
+    pub(crate) fn place_root(receiver: &Expr) -> Option<String> {
+        match receiver {
+            Expr::Path(expr_path) => expr_path.path.get_ident().map(|ident| ident.to_string()),
+            Expr::Field(field) => Self::place_root(&field.base),
+            Expr::Index(index) => Self::place_root(&index.expr),
+            Expr::MethodCall(method_call) => Self::place_root(&method_call.receiver),
+            Expr::Reference(reference) => Self::place_root(&reference.expr),
+            Expr::Unary(unary) => Self::place_root(&unary.expr),
+            Expr::Paren(paren) => Self::place_root(&paren.expr),
+            Expr::Try(expr_try) => Self::place_root(&expr_try.expr),
+            _ => None,
+        }
+    }
+
+    // '&v.len()', 'v.pop()?' and '(v.len())' still use the value of the method call
+    fn strip_call_wrappers(expr: &Expr) -> &Expr {
+        match expr {
+            Expr::Reference(reference) => Self::strip_call_wrappers(&reference.expr),
+            Expr::Try(expr_try) => Self::strip_call_wrappers(&expr_try.expr),
+            Expr::Paren(paren) => Self::strip_call_wrappers(&paren.expr),
+            expr => expr,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_support::{built, discharge};
+    use crate::verifier::VcOutcome;
+
+    // The branch that exits doesn't reach the postcondition, so only the other branch has to satisfy it
+    #[test]
+    fn a_path_ends_at_a_call_that_never_returns() {
+        let mut builder = built("fn checked(x: i32) -> i32 {
+                pre!(true);
+                post!(x > 0);
+                if x <= 0 {
+                    std::process::exit(1);
+                }
+                x
+            }");
+        assert!(builder.results.rejected_specs.is_empty());
+        let outcomes = discharge(&mut builder);
+        assert_eq!(outcomes.len(), 1);
+        assert!(matches!(outcomes[0], VcOutcome::Valid));
+    }
+
+    #[test]
+    fn postconditions_of_a_function_that_never_returns_are_rejected() {
+        let builder = built("fn fail(code: i32) -> ! {
+                pre!(true);
+                post!(code == 0);
+                std::process::exit(code)
+            }
+            fn stop() {
+                pre!(true);
+                post!(false);
+                fail(1);
+            }");
+        assert_eq!(builder.results.rejected_specs.len(), 2);
+        assert!(builder.results.rejected_specs[0].contains("it returns `!`"));
+        assert!(builder.results.rejected_specs[1].contains("every path ends in a call that never returns"));
+    }
+
+    #[test]
+    fn calls_that_never_return_are_found_by_any_path() {
+        let builder = built("mod errors { pub fn die() -> ! { std::process::abort() } }
+            struct Checker;
+            impl Checker { fn fail() -> ! { std::process::exit(1) } }
+            fn run() { Checker::fail(); errors::die(); std::process::abort(); }");
+        for call in ["Self::fail()", "Checker::fail()", "errors::die()", "die()", "std::process::abort()"] {
+            assert!(builder.is_diverging_call(&syn::parse_str(call).unwrap()), "{}", call);
         }
+        assert!(!builder.is_diverging_call(&syn::parse_str("errors::live()").unwrap()));
     }
-}
\ No newline at end of file
+}
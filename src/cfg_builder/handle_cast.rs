@@ -0,0 +1,87 @@
+use petgraph::graph::NodeIndex;
+use std::collections::HashMap;
+use syn::visit::{self, Visit};
+use syn::{ExprCast, ExprClosure};
+
+use crate::cfg_builder::builder::CfgBuilder;
+use crate::cfg_builder::node::{CastInfo, CfgNode};
+
+// Collects the 'as' casts of an expression
+#[derive(Default)]
+struct CastCollector {
+    casts: Vec<ExprCast>,
+}
+
+impl<'ast> Visit<'ast> for CastCollector {
+    fn visit_expr_cast(&mut self, i: &'ast ExprCast) {
+        self.casts.push(i.clone());
+        visit::visit_expr_cast(self, i);
+    }
+
+    // Closure bodies are not part of the CFG
+    fn visit_expr_closure(&mut self, _i: &'ast ExprClosure) {}
+}
+
+impl CfgBuilder {
+    // Record the conversions made by a statement, condition or return
+    pub fn record_casts(&mut self, node: NodeIndex) {
+        let mut collector = CastCollector::default();
+        match &self.graph[node] {
+            CfgNode::Statement(_, Some(stmt)) => collector.visit_stmt(stmt),
+            CfgNode::Condition(_, Some(conditional_expr)) => collector.visit_expr(conditional_expr.to_syn_expr()),
+            CfgNode::Return(_, Some(expr_return)) => collector.visit_expr_return(expr_return),
+            _ => {},
+        }
+        if collector.casts.is_empty() {
+            return;
+        }
+        let casts: Vec<CastInfo> = collector.casts.iter().map(|cast| self.cast_info(cast, &self.type_env)).collect();
+        self.metadata_mut(node).casts = casts;
+    }
+
+    // Source and target types of a cast, with the variable types taken from 'env'
+    pub fn cast_info(&self, cast: &ExprCast, env: &HashMap<String, String>) -> CastInfo {
+        let from = self.infer_expr_type_in(&cast.expr, env);
+        let to = Self::render_type(&cast.ty);
+        let narrowing = from.as_deref().is_some_and(|from| Self::is_narrowing(from, &to));
+        CastInfo { expr: Self::render_expr(&syn::Expr::Cast(cast.clone())), from, to, narrowing }
+    }
+
+    // Whether some values of the integer type 'from' are out of the range of the integer type 'to'
+    pub fn is_narrowing(from: &str, to: &str) -> bool {
+        let (Some((from_min, from_max)), Some((to_min, to_max))) = (Self::integer_bounds(from), Self::integer_bounds(to)) else {
+            return false;
+        };
+        // The bounds are powers of two (minus one): distinct bounds stay distinct as floats
+        let value = |bound: &str| bound.parse::<f64>().unwrap_or(0.0);
+        value(from_min) < value(to_min) || value(from_max) > value(to_max)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::SolverSettings;
+    use crate::test_support::verdicts;
+    use crate::verifier::{implication_to_smtlib, verify_str_implication, VcOutcome};
+
+    // Kept as the identity, 'x as u8' would be 300 and 'y == x' would be proved
+    #[test]
+    fn narrowing_casts_wrap_around_in_the_solver() {
+        let outcomes = verdicts("fn low(x: i32) -> u8 { pre!(x == 300); post!(y == x); let y = x as u8; return y; }");
+        assert!(matches!(outcomes.as_slice(), [VcOutcome::Counterexample(_)]));
+        let outcomes = verdicts("fn low(x: i32) -> u8 { pre!(x == 300); post!(y == 44); let y = x as u8; return y; }");
+        assert!(matches!(outcomes.as_slice(), [VcOutcome::Valid]));
+        let outcomes = verdicts("fn low(x: i32) -> i8 { pre!(x == 200); post!(y == -56); let y = x as i8; return y; }");
+        assert!(matches!(outcomes.as_slice(), [VcOutcome::Valid]));
+    }
+
+    #[test]
+    fn literal_suffixes_give_the_value_its_type() {
+        let verdict = |implication: &str| verify_str_implication(implication, &SolverSettings::default()).unwrap();
+        assert!(matches!(verdict("(x == -128i8) >> (x < 0)"), VcOutcome::Valid));
+        assert!(matches!(verdict("(x == 255u8 as i8) >> (x == 255)"), VcOutcome::Counterexample(_)));
+        assert!(matches!(verdict("(x == 255u8 as i8) >> (x == -1)"), VcOutcome::Valid));
+        let smtlib = implication_to_smtlib("(x == 300 as u8) >> (x == 44)").unwrap();
+        assert!(smtlib.contains("(+ (mod (- 300 0) 256) 0)"), "{}", smtlib);
+    }
+}
@@ -1,24 +1,30 @@
-use syn::{ExprIf};
+use syn::{ExprIf, ExprLet};
 
 use crate::cfg_builder::builder::CfgBuilder;
-use crate::cfg_builder::node::{CfgNode, ConditionalExpr};
-use quote::quote;
+use crate::cfg_builder::node::{CfgNode, ConditionalExpr, EdgeKind};
 use syn::{ visit::{self, Visit}, Expr, Pat, ExprParen, ExprUnary, UnOp, token};
 use proc_macro2::{Span, TokenTree};
 
 impl CfgBuilder {
     pub fn handle_if_statement(&mut self, expr_if: &ExprIf) {
+        if let Expr::Let(expr_let) = &*expr_if.cond {
+            return self.handle_if_let(expr_if, expr_let);
+        }
         let cond_str = self.format_condition(&expr_if.cond);
-        let cond_label = if self.next_edge_label == Some("false".to_string()) {
+        let cond_label = if self.is_else_branch() {
             format!("else if: {}", cond_str)
         } else {
             format!("if: {}", cond_str)
         };
-        let cond_expr = ConditionalExpr::If(expr_if.cond.clone());
+        // 'matches!' and let-chains are given to the solver as tests of their patterns
+        let (cond, structure) = self.lower_condition(&expr_if.cond);
+        let cond_expr = ConditionalExpr::If(Box::new(cond));
         let cond_node = self.add_node(CfgNode::new_condition(cond_label, cond_expr));
+        self.record_condition_structure(cond_node, structure);
+        self.set_branch_weights(cond_node, vec![("true".to_string(), Self::branch_hint(&expr_if.attrs)), ("false".to_string(), None)]);
 
         // Processing the true branch
-        self.next_edge_label = Some("true".to_string());
+        self.next_edge_kind = Some(EdgeKind::TrueBranch);
         self.current_node = Some(cond_node.clone());
         self.visit_block(&expr_if.then_branch);
         let true_branch_end = self.current_node;
@@ -28,13 +34,13 @@ impl CfgBuilder {
 
         // Connect the true branch end to the merge point
         if let Some(true_end) = true_branch_end {
-            self.add_edge_with_label(true_end, merge_node, "".to_string());
+            self.add_edge_with_kind(true_end, merge_node, EdgeKind::Fallthrough);
         }
 
         // Handling the else branch if present
         if let Some((_, else_branch)) = &expr_if.else_branch {
             self.current_node = Some(cond_node.clone());
-            self.next_edge_label = Some("false".to_string());
+            self.next_edge_kind = Some(EdgeKind::FalseBranch);
             match &**else_branch {
                 Expr::If(elseif) => {
                     // Handle else if with recursion
@@ -50,19 +56,64 @@ impl CfgBuilder {
 
             // Connect the end of the else branch to the merge point
             if let Some(false_end) = self.current_node {
-                self.add_edge_with_label(false_end, merge_node, "".to_string());
+                self.add_edge_with_kind(false_end, merge_node, EdgeKind::Fallthrough);
             }
         } else {
-            // If there is no else branch, connect the condition node to the merge point with a 'false' label
-            self.add_edge_with_label(cond_node, merge_node, "false".to_string());
+            // If there is no else branch, connect the condition node to the merge point with a false edge
+            self.add_edge_with_kind(cond_node, merge_node, EdgeKind::FalseBranch);
         }
 
         // Continue from the merge point after if-else
         self.current_node = Some(merge_node);
     }
+    // 'if let P = e' branches like a match with two arms: the edge into the then branch is labeled with the
+    // pattern, the one into the else branch (or past the 'if') with 'else'
+    fn handle_if_let(&mut self, expr_if: &ExprIf, expr_let: &ExprLet) {
+        let keyword = if self.is_else_branch() { "else if let" } else { "if let" };
+        let cond_label = format!("{}: {} = {}", keyword, Self::render_pat(&expr_let.pat), self.format_condition(&expr_let.expr));
+        let match_condition = self.let_match_condition(expr_let);
+        let (pattern_label, else_label) = (match_condition.arms[0].label.clone(), match_condition.arms[1].label.clone());
+        let cond_node = self.add_node(CfgNode::new_condition(cond_label, ConditionalExpr::Match(match_condition)));
+        let structure = self.lower_condition(&expr_if.cond).1;
+        self.record_condition_structure(cond_node, structure);
+        self.set_branch_weights(cond_node, vec![(pattern_label.clone(), Self::branch_hint(&expr_if.attrs)), (else_label.clone(), None)]);
+
+        let mut branch_ends = Vec::new();
+        self.current_node = Some(cond_node);
+        self.next_edge_kind = Some(EdgeKind::MatchArm(pattern_label.clone()));
+        self.visit_block(&expr_if.then_branch);
+        branch_ends.push((self.current_node, pattern_label));
+
+        self.current_node = Some(cond_node);
+        self.next_edge_kind = Some(EdgeKind::MatchArm(else_label.clone()));
+        match expr_if.else_branch.as_ref().map(|(_, else_branch)| &**else_branch) {
+            Some(Expr::If(else_if)) => self.handle_if_statement(else_if),
+            Some(Expr::Block(block)) => self.visit_block(&block.block),
+            Some(else_branch) => self.visit_expr(else_branch),
+            None => {},
+        }
+        branch_ends.push((self.current_node, else_label));
+        self.next_edge_kind = None;
+
+        let merge_node = self.add_node_without_edge(CfgNode::MergePoint);
+        for (end, label) in branch_ends {
+            match end {
+                // Empty branch: its edge goes straight to the merge point
+                Some(end) if end == cond_node => self.add_edge_with_kind(cond_node, merge_node, EdgeKind::MatchArm(label)),
+                Some(end) => self.add_edge_with_kind(end, merge_node, EdgeKind::Fallthrough),
+                None => {},
+            }
+        }
+        self.current_node = Some(merge_node);
+    }
+
+    // Whether the node being built starts the else branch of an 'if' or 'if let'
+    fn is_else_branch(&self) -> bool {
+        matches!(&self.next_edge_kind, Some(EdgeKind::FalseBranch)) || matches!(&self.next_edge_kind, Some(EdgeKind::MatchArm(label)) if label == "else")
+    }
+
     pub fn format_pattern_condition(&self, pat: &Pat) -> String {
-        let raw_string = quote!(#pat).to_string();
-        Self::clean_up_formatting(&raw_string)
+        Self::render_pat(pat)
     }
     pub fn negate_condition(expr: Expr) -> Expr {
         // unary negation expression with '!'
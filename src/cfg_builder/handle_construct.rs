@@ -0,0 +1,97 @@
+use petgraph::graph::NodeIndex;
+use syn::{Expr, Member};
+
+use crate::cfg_builder::builder::CfgBuilder;
+use crate::cfg_builder::node::{ConstructionInfo, FieldBinding};
+
+impl CfgBuilder {
+    // Record what each field of a constructed value holds: 'p = Point { x: 1, y: a }' binds 'p.x' and 'p.y',
+    // 'r = Pair(a, 2)' binds 'r.0' and 'r.1', 'o = Some(v)' binds the payload 'o.0'
+    pub fn record_construction(&mut self, node: NodeIndex, place: &str, value: &Expr) {
+        let Some((constructor, fields)) = Self::constructor_fields(value) else { return };
+        let fields = fields.into_iter().map(|(member, value)| FieldBinding {
+            field: format!("{}.{}", place, Self::member_name(&member)),
+            value: Self::render_expr(&value),
+        }).collect();
+        self.metadata_mut(node).construction = Some(ConstructionInfo { place: place.to_string(), constructor, fields });
+    }
+
+    // Constructor of a struct, tuple-struct or enum-variant expression and the value given to each field.
+    // Calls and paths are constructors when their last segment is capitalized ('Some(v)', 'Shape::Circle(r)',
+    // 'None'), which keeps constants ('MAX', 'u64::MAX') out.
+    pub(crate) fn constructor_fields(expr: &Expr) -> Option<(String, Vec<(Member, Expr)>)> {
+        match expr {
+            Expr::Paren(paren) => Self::constructor_fields(&paren.expr),
+            Expr::Struct(expr_struct) => {
+                let fields = expr_struct.fields.iter().map(|field_value| (field_value.member.clone(), field_value.expr.clone())).collect();
+                Some((Self::render_path(&expr_struct.path), fields))
+            },
+            Expr::Call(call) => {
+                let Expr::Path(func) = &*call.func else { return None };
+                if !Self::is_constructor_path(&func.path) {
+                    return None;
+                }
+                let fields = call.args.iter().enumerate()
+                    .map(|(index, arg)| (Member::Unnamed(syn::Index::from(index)), arg.clone()))
+                    .collect();
+                Some((Self::render_path(&func.path), fields))
+            },
+            // Unit variants: 'None', 'Shape::Empty'
+            Expr::Path(expr_path) if expr_path.path.is_ident("None") || (expr_path.path.segments.len() > 1 && Self::is_constructor_path(&expr_path.path)) => {
+                Some((Self::render_path(&expr_path.path), Vec::new()))
+            },
+            _ => None,
+        }
+    }
+
+    // 'Point', 'Some', 'Shape::Circle', but not 'MAX'
+    fn is_constructor_path(path: &syn::Path) -> bool {
+        let Some(last) = path.segments.last().map(|segment| segment.ident.to_string()) else { return false };
+        last.starts_with(|c: char| c.is_ascii_uppercase()) && last.chars().any(|c| c.is_ascii_lowercase())
+    }
+
+    pub(crate) fn member_name(member: &Member) -> String {
+        match member {
+            Member::Named(ident) => ident.to_string(),
+            Member::Unnamed(index) => index.index.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::verdicts;
+    use crate::verifier::VcOutcome;
+
+    #[test]
+    fn fields_of_a_constructed_value_are_known() {
+        let outcomes = verdicts("fn wrap(v: i32) -> Option<i32> {
+                pre!(v > 0);
+                post!(is_some(result) && result.unwrap() > 0);
+                let result = Some(v);
+                return result;
+            }");
+        assert!(matches!(outcomes.as_slice(), [VcOutcome::Valid]));
+        let outcomes = verdicts("fn origin(a: i32) -> Point {
+                pre!(a > 0);
+                post!(p.x == 0 && p.y > 0 && q.1 == 2 && o.unwrap_or(a) == a);
+                let p = Point { x: 0, y: a };
+                let q = Pair(a, 2);
+                let o = None;
+                return p;
+            }");
+        assert!(matches!(outcomes.as_slice(), [VcOutcome::Valid]));
+    }
+
+    #[test]
+    fn constructors_are_told_apart_from_constants() {
+        let fields = |src: &str| CfgBuilder::constructor_fields(&syn::parse_str(src).unwrap())
+            .map(|(constructor, fields)| (constructor, fields.iter().map(|(member, _)| CfgBuilder::member_name(member)).collect::<Vec<_>>()));
+        assert_eq!(fields("Shape::Circle(r)"), Some(("Shape::Circle".to_string(), vec!["0".to_string()])));
+        assert_eq!(fields("Point { x: 1, y: a }"), Some(("Point".to_string(), vec!["x".to_string(), "y".to_string()])));
+        assert_eq!(fields("None"), Some(("None".to_string(), Vec::new())));
+        assert_eq!(fields("u64::MAX"), None);
+        assert_eq!(fields("max(a, b)"), None);
+    }
+}
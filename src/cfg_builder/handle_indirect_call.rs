@@ -0,0 +1,200 @@
+use petgraph::graph::NodeIndex;
+use syn::spanned::Spanned;
+use syn::{Expr, UnOp};
+
+use crate::cfg_builder::builder::CfgBuilder;
+use crate::cfg_builder::node::{CfgNode, IndirectCallInfo};
+
+impl CfgBuilder {
+    // Indirect call producing the value of a statement ('let y = f(x)', 'y = (self.op)(x)', 'shape.area()'),
+    // with its contract: from '//@ call pre: ...' / '//@ call post: ...' comments right above the
+    // statement at 'line', or else from the 'call_sites' of the conditions database. Direct calls of
+    // functions ('let y = abs(x)') are handled the same way when they have such a contract, or when
+    // the function is in the file and has 'pre!'/'post!' annotations.
+    pub fn indirect_call(&self, value: &Expr, assigned: Vec<String>, line: usize) -> Option<IndirectCallInfo> {
+        let mut direct = false;
+        let (callee, callee_type, args) = match Self::strip_parens(value) {
+            Expr::Call(call) => {
+                let callee_type = match Self::strip_parens(&call.func) {
+                    // Calling a variable (parameter or local) goes through a pointer or closure
+                    Expr::Path(expr_path) => match expr_path.path.get_ident().and_then(|name| self.type_env.get(&name.to_string())) {
+                        Some(ty) => Some(ty.clone()),
+                        None => {
+                            direct = true;
+                            None
+                        },
+                    },
+                    Expr::Field(_) => None,
+                    _ => return None,
+                };
+                (Self::render_expr(Self::strip_parens(&call.func)), callee_type, call.args.iter().collect::<Vec<_>>())
+            },
+            Expr::MethodCall(method_call) => {
+                let receiver_type = self.infer_expr_type(&method_call.receiver).filter(|ty| ty.contains("dyn "))?;
+                let callee = format!("{}.{}", Self::render_expr(&method_call.receiver), method_call.method);
+                (callee, Some(receiver_type), method_call.args.iter().collect())
+            },
+            _ => return None,
+        };
+
+        let local_callee = match Self::strip_parens(value) {
+            Expr::Call(call) if direct => self.local_callee(call).map(|(name, item_fn)| (name, item_fn, call)),
+            _ => None,
+        };
+        let bundled_callee = match Self::strip_parens(value) {
+            Expr::Call(call) if direct && local_callee.is_none() => self.bundled_callee(call).map(|(bundle, function)| (bundle, function, call)),
+            _ => None,
+        };
+        let foreign_callee = match Self::strip_parens(value) {
+            Expr::Call(call) if direct => self.foreign_callee(call).map(|foreign| (foreign, call)),
+            _ => None,
+        };
+        let (contract, preconditions, postconditions) = match self.call_comments(line) {
+            (pre, post) if !pre.is_empty() || !post.is_empty() => (Some("comment".to_string()), pre, post),
+            _ => match self.external_conditions.call_sites.iter().find(|site| site.callee == callee) {
+                // Contracts of extern functions are written over their declared parameters
+                Some(site) => match foreign_callee {
+                    Some((foreign, call)) => {
+                        let (pre, post) = self.foreign_contract(foreign, site, call);
+                        (Some("conditions database".to_string()), pre, post)
+                    },
+                    None => (Some("conditions database".to_string()), site.preconditions.clone(), site.postconditions.clone()),
+                },
+                // A function of the file brings its own 'pre!'/'post!'
+                None => match local_callee {
+                    Some((_, ref item_fn, call)) => {
+                        let (pre, post) = self.local_contract(item_fn, call);
+                        let contract = (!pre.is_empty() || !post.is_empty()).then(|| "callee contract".to_string());
+                        (contract, pre, post)
+                    },
+                    // A function of another crate brings the contract proved there
+                    None => match bundled_callee {
+                        Some((bundle, function, call)) => {
+                            let (pre, post) = self.bundled_contract(function, call);
+                            (Some(format!("contract bundle of {}", bundle.crate_name)), pre, post)
+                        },
+                        None => (None, Vec::new(), Vec::new()),
+                    },
+                },
+            },
+        };
+        // Without a contract, the result of a function of the file is unknown; extern functions are havocked
+        if direct && contract.is_none() && local_callee.is_none() && foreign_callee.is_none() {
+            return None;
+        }
+
+        // The call may write through the '&mut' arguments
+        let result = if assigned.len() == 1 { assigned.first().cloned() } else { None };
+        let mut havoc = assigned;
+        for arg in args {
+            if let Expr::Reference(reference) = arg {
+                if reference.mutability.is_some() {
+                    havoc.extend(Self::place_root(&reference.expr));
+                }
+            }
+        }
+        Some(IndirectCallInfo { callee, callee_type, contract, preconditions, postconditions, result, havoc })
+    }
+
+    // Check the preconditions of an indirect call before it: asserted on the paths through the call, with
+    // what the caller knows there, then assumed
+    pub fn add_call_preconditions(&mut self, call: &IndirectCallInfo) {
+        for pre in &call.preconditions {
+            let Ok(expr) = syn::parse_str::<Expr>(&format!("invariant!({})", pre)) else {
+                eprintln!("Warning: can't parse the precondition '{}' of the call to {}", pre, call.callee);
+                continue;
+            };
+            let pre_node = self.add_node(CfgNode::new_assertion(pre.clone(), expr));
+            self.metadata_mut(pre_node).facts.push(format!("precondition of the call to {}: checked on each path through the call, with what the caller knows there, then assumed", call.callee));
+            if call.contract.as_deref() == Some("callee contract") {
+                self.metadata_mut(pre_node).call_precondition = Some(call.callee.clone());
+            }
+        }
+    }
+
+    // Warn about calls made without contract, which the verification conditions havoc
+    pub fn record_indirect_call(&mut self, node: NodeIndex, call: IndirectCallInfo) {
+        let line = match &self.graph[node] {
+            CfgNode::Statement(_, Some(stmt)) => stmt.span().start().line,
+            _ => 0,
+        };
+        let foreign = self.check_foreign_call(&call.callee, line);
+        if call.contract.is_none() && !foreign {
+            eprintln!("Warning: no contract for the call to {}, its effects are unknown", call.callee);
+        }
+        self.metadata_mut(node).indirect_call = Some(call);
+    }
+
+    // '//@ call pre: ...' and '//@ call post: ...' comment lines right above line 'line' (1-based)
+    pub(crate) fn call_comments(&self, line: usize) -> (Vec<String>, Vec<String>) {
+        let (mut pre, mut post) = (Vec::new(), Vec::new());
+        let above = self.file.source_lines.iter().take(line.saturating_sub(1)).rev();
+        for comment in above.map(|l| l.trim()).take_while(|l| l.starts_with("//@ call ")) {
+            let spec = comment.trim_start_matches("//@ call ").trim();
+            if let Some(condition) = spec.strip_prefix("pre:") {
+                pre.insert(0, condition.trim().to_string());
+            } else if let Some(condition) = spec.strip_prefix("post:") {
+                post.insert(0, condition.trim().to_string());
+            }
+        }
+        (pre, post)
+    }
+
+    fn strip_parens(expr: &Expr) -> &Expr {
+        match expr {
+            Expr::Paren(paren) => Self::strip_parens(&paren.expr),
+            Expr::Unary(unary) if matches!(unary.op, UnOp::Deref(_)) => Self::strip_parens(&unary.expr),
+            // 'unsafe { read(fd, n) }'
+            Expr::Unsafe(expr_unsafe) => match expr_unsafe.block.stmts.as_slice() {
+                [syn::Stmt::Expr(inner)] => Self::strip_parens(inner),
+                _ => expr,
+            },
+            expr => expr,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_support::verdicts;
+    use crate::verifier::VcOutcome;
+
+    fn apply(pre: &str, contract: &str) -> String {
+        format!("fn apply(f: fn(i32) -> i32, x: i32) -> i32 {{
+    pre!({});
+    post!(y > x);
+{}
+    let y = f(x);
+    return y;
+}}", pre, contract)
+    }
+
+    #[test]
+    fn the_contract_of_a_call_site_is_checked_then_assumed() {
+        let contract = "    //@ call pre: x >= 0\n    //@ call post: result > x";
+        let outcomes = verdicts(&apply("x >= 0", contract));
+        assert!(!outcomes.is_empty() && outcomes.iter().all(|outcome| matches!(outcome, VcOutcome::Valid)));
+        // The precondition of the call doesn't follow from the one of the caller
+        let outcomes = verdicts(&apply("true", contract));
+        assert_eq!(outcomes.iter().filter(|outcome| matches!(outcome, VcOutcome::Counterexample(_))).count(), 1);
+        // Without a contract the result is unknown
+        let outcomes = verdicts(&apply("x >= 0", ""));
+        assert!(matches!(outcomes.as_slice(), [VcOutcome::Counterexample(_)]));
+    }
+
+    // The precondition of the call doesn't cut the path: the caller's precondition is still known after it
+    #[test]
+    fn the_caller_keeps_its_facts_past_the_precondition_of_a_call() {
+        let source = "fn apply(f: fn(i32) -> i32, x: i32) -> i32 {
+    pre!(x > 5);
+    post!(y > 5);
+    //@ call pre: x > 0
+    //@ call post: result > x
+    let y = f(x);
+    return y;
+}";
+        let outcomes = verdicts(source);
+        assert_eq!(outcomes.len(), 1);
+        assert!(matches!(outcomes[0], VcOutcome::Valid));
+    }
+}
@@ -0,0 +1,220 @@
+use petgraph::graph::NodeIndex;
+use syn::spanned::Spanned;
+use syn::visit::Visit;
+use syn::{Block, Expr, Local, Pat, Stmt};
+
+use crate::cfg_builder::builder::CfgBuilder;
+use crate::cfg_builder::handle_loops::LoopValue;
+use crate::cfg_builder::node::CfgNode;
+
+impl CfgBuilder {
+    // 'let x = if c { f(a) } else { g(b) };': the variable is declared, then the initializer is built
+    // as a sub-CFG whose branches end with 'x = f(a)' and 'x = g(b)', so each call gets its contract
+    // and the value reaches the binding along its own path. Returns false for other initializers.
+    pub fn handle_branching_let(&mut self, local: &Local) -> bool {
+        let Some((_, init)) = &local.init else { return false };
+        let init = match &**init {
+            Expr::Paren(paren) => &*paren.expr,
+            init => init,
+        };
+        if !matches!(init, Expr::If(_) | Expr::Match(_) | Expr::Block(_) | Expr::Loop(_)) {
+            return false;
+        }
+        let Some(var) = Self::local_variable(local) else { return false };
+        // A 'loop' starts at the invariant written before the 'let', so the declaration goes before it
+        let invariant_node = self.current_node
+            .filter(|&current| matches!(init, Expr::Loop(_)) && matches!(self.graph[current], CfgNode::Invariant(_, _)));
+        let declaration_node = self.declare_local(local);
+        if let Some(invariant_node) = invariant_node {
+            if let Some(edge) = self.graph.find_edge(invariant_node, declaration_node) {
+                self.graph.remove_edge(edge);
+            }
+            self.insert_before(invariant_node, declaration_node);
+            self.current_node = Some(invariant_node);
+        }
+        self.metadata_mut(declaration_node).facts.push(format!("initialized on each branch of: {}", Self::render_expr(init)));
+
+        self.visit_branching_value(init, LoopValue::Assigned(var));
+        true
+    }
+
+    // 'x = match c { .. }' and 'x = if ..': built as the initializer of a 'let' is, without the declaration.
+    // Returns false for other assignments.
+    pub fn handle_branching_assignment(&mut self, place: &Expr, value: &Expr) -> bool {
+        let value = match value {
+            Expr::Paren(paren) => &*paren.expr,
+            value => value,
+        };
+        let Expr::Path(expr_path) = place else { return false };
+        let Some(var) = expr_path.path.get_ident() else { return false };
+        if !matches!(value, Expr::If(_) | Expr::Match(_) | Expr::Block(_) | Expr::Loop(_)) {
+            return false;
+        }
+        self.visit_branching_value(value, LoopValue::Assigned(var.clone()));
+        true
+    }
+
+    // The branches of 'value' as a sub-CFG, each ending with the assignment of its value to a variable or
+    // with its return; a 'loop' does it at its 'break's
+    pub(crate) fn visit_branching_value(&mut self, value: &Expr, target: LoopValue) {
+        if let Expr::Loop(expr_loop) = value {
+            self.handle_loop(expr_loop, target);
+            return;
+        }
+        match self.branch_values(value, &target) {
+            Expr::Block(expr_block) => self.visit_block(&expr_block.block),
+            assigned => self.visit_expr(&assigned),
+        }
+    }
+
+    // Variable bound by 'let x = ..' or 'let x: T = ..'
+    pub(crate) fn local_variable(local: &Local) -> Option<syn::Ident> {
+        match &local.pat {
+            Pat::Ident(pat_ident) => Some(pat_ident.ident.clone()),
+            Pat::Type(pat_type) => match &*pat_type.pat {
+                Pat::Ident(pat_ident) => Some(pat_ident.ident.clone()),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    // Node declaring the variable of a 'let' without its initializer ('let x;'), assigned afterwards
+    pub(crate) fn declare_local(&mut self, local: &Local) -> NodeIndex {
+        let var_types = self.local_types(local);
+        let declaration = Local { init: None, ..local.clone() };
+        let declaration_node = self.add_node(CfgNode::new_statement(Self::render_local(&declaration), Stmt::Local(declaration)));
+        self.metadata_mut(declaration_node).var_types = var_types;
+        declaration_node
+    }
+
+    // The initializer with the value of each branch assigned to the variable, or returned. Branches leaving
+    // the function or the loop ('return', 'break', 'panic!(..)', 'process::exit(..)') don't assign anything.
+    fn branch_values(&self, expr: &Expr, target: &LoopValue) -> Expr {
+        match expr {
+            Expr::Paren(paren) => self.branch_values(&paren.expr, target),
+            Expr::If(expr_if) => {
+                let mut expr_if = expr_if.clone();
+                expr_if.then_branch = self.block_value(&expr_if.then_branch, target);
+                if let Some((else_token, else_branch)) = expr_if.else_branch {
+                    expr_if.else_branch = Some((else_token, Box::new(self.branch_values(&else_branch, target))));
+                }
+                Expr::If(expr_if)
+            },
+            Expr::Match(expr_match) => {
+                let mut expr_match = expr_match.clone();
+                for arm in expr_match.arms.iter_mut() {
+                    *arm.body = self.branch_values(&arm.body, target);
+                }
+                Expr::Match(expr_match)
+            },
+            Expr::Block(expr_block) => {
+                let mut expr_block = expr_block.clone();
+                expr_block.block = self.block_value(&expr_block.block, target);
+                Expr::Block(expr_block)
+            },
+            Expr::Return(_) | Expr::Break(_) | Expr::Continue(_) | Expr::Macro(_) => expr.clone(),
+            Expr::Call(call) if self.is_diverging_call(call) => expr.clone(),
+            // The assignment or return is located at the value it takes
+            value => match target {
+                LoopValue::Assigned(var) => {
+                    let var = syn::Ident::new(&var.to_string(), value.span());
+                    syn::parse_quote!(#var = #value)
+                },
+                LoopValue::Returned => syn::parse_quote_spanned!(value.span()=> return #value),
+                LoopValue::Ignored => value.clone(),
+            },
+        }
+    }
+
+    // The block with its tail expression, if any, assigned to 'var'
+    pub(crate) fn assign_block_value(&self, block: &Block, var: &syn::Ident) -> Block {
+        self.block_value(block, &LoopValue::Assigned(var.clone()))
+    }
+
+    fn block_value(&self, block: &Block, target: &LoopValue) -> Block {
+        let mut block = block.clone();
+        if let Some(Stmt::Expr(tail)) = block.stmts.last_mut() {
+            let assigned = self.branch_values(tail, target);
+            *block.stmts.last_mut().expect("tail checked above") = match assigned {
+                // Nested branches stay expressions, assignments and returns become statements
+                Expr::Assign(_) | Expr::Return(_) => Stmt::Semi(assigned, Default::default()),
+                assigned => Stmt::Expr(assigned),
+            };
+        }
+        block
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_support;
+    use crate::verifier::VcOutcome;
+
+    // Verdicts of the basic paths of 'body', run with 'post' as the postcondition on 'r'
+    fn verdicts(post: &str, body: &str) -> Vec<VcOutcome> {
+        let source = format!("enum Color {{ Red, Green }}
+            fn value(c: Color, n: i32) -> i32 {{
+                pre!(true);
+                post!({});
+                let mut r = 0;
+                {}
+                return r;
+            }}", post, body);
+        test_support::verdicts(&source)
+    }
+
+    fn counts(outcomes: &[VcOutcome]) -> (usize, usize) {
+        let valid = outcomes.iter().filter(|outcome| matches!(outcome, VcOutcome::Valid)).count();
+        let refuted = outcomes.iter().filter(|outcome| matches!(outcome, VcOutcome::Counterexample(_))).count();
+        (valid, refuted)
+    }
+
+    #[test]
+    fn each_arm_of_an_assigned_match_is_a_path() {
+        let body = "r = match c { Color::Red => match n { 0 => 1, _ => 2 }, _ => 3 };";
+        assert_eq!(counts(&verdicts("r >= 1 && r <= 3", body)), (3, 0));
+        assert_eq!(counts(&verdicts("r != 2", body)), (2, 1));
+    }
+
+    #[test]
+    fn each_branch_of_an_assigned_if_is_a_path() {
+        let body = "r = if n > 0 { n } else { 0 - n };";
+        assert_eq!(counts(&verdicts("r >= 0", body)), (2, 0));
+        // 'n == 0' takes the else branch
+        assert_eq!(counts(&verdicts("r > 0", body)), (1, 1));
+    }
+
+    #[test]
+    fn an_assigned_block_runs_its_statements_first() {
+        let body = "r = { let t = n + 1; t * 2 };";
+        assert_eq!(counts(&verdicts("r == 2 * n + 2", body)), (1, 0));
+        assert_eq!(counts(&verdicts("r == 2 * n", body)), (0, 1));
+    }
+
+    fn branch_verdicts(else_contract: &str) -> Vec<VcOutcome> {
+        let source = format!("fn larger(a: i32, b: i32) -> i32 {{
+                pre!(true);
+                post!(x > a && x > b);
+                let x = if a > b {{
+                    //@ call post: result > a
+                    inc(a)
+                }} else {{
+                    //@ call post: {}
+                    inc(b)
+                }};
+                return x;
+            }}", else_contract);
+        test_support::verdicts(&source)
+    }
+
+    // Each branch assigns 'x' through its own call, under the contract written above it
+    #[test]
+    fn each_branch_gets_the_contract_of_its_call() {
+        let outcomes = branch_verdicts("result > b");
+        assert_eq!(outcomes.len(), 2);
+        assert!(outcomes.iter().all(|outcome| matches!(outcome, VcOutcome::Valid)));
+        let outcomes = branch_verdicts("result > a");
+        assert_eq!(outcomes.iter().filter(|outcome| matches!(outcome, VcOutcome::Counterexample(_))).count(), 1);
+    }
+}
@@ -1,7 +1,38 @@
-use syn::{visit::{self, Visit}, ExprForLoop, ExprWhile};
+use petgraph::graph::NodeIndex;
+use petgraph::visit::EdgeRef;
+use syn::{visit::Visit, Expr, ExprBreak, ExprContinue, ExprForLoop, ExprLoop, ExprMacro, ExprReturn, ExprWhile, Label, Lifetime, Pat, Stmt};
 
 use crate::cfg_builder::builder::CfgBuilder;
-use crate::cfg_builder::node::{CfgNode, ConditionalExpr};
+use crate::cfg_builder::node::{CfgNode, ConditionalExpr, EdgeKind};
+use crate::config::AnnotationRole;
+
+// Adapters that neither bound nor reorder the iteration
+const TRANSPARENT_ADAPTERS: [&str; 5] = ["iter", "into_iter", "iter_mut", "copied", "cloned"];
+
+// Iterator adapters of a 'for' loop that give facts about its iterations
+#[derive(Debug, Clone)]
+pub struct LoopAdapters {
+    pub take_bounds: Vec<Expr>, // 'take(n)': at most n iterations
+    pub enumerate: bool, // 'enumerate()': the index equals the iteration count
+}
+
+// A loop being built: where its 'continue's go back to and the 'break's to connect to its exit
+#[derive(Debug, Clone)]
+pub struct LoopContext {
+    pub label: Option<String>, // 'outer' for 'outer: while ..
+    pub head: NodeIndex, // invariant or cutoff node the back edges go to
+    pub counter: Option<String>, // iteration counter of an adapter loop, counted at a 'continue' too
+    pub breaks: Vec<NodeIndex>,
+    pub value: LoopValue, // what a 'break' with a value does with it, for a bare 'loop'
+}
+
+// Where the value of 'break value' goes
+#[derive(Debug, Clone, PartialEq)]
+pub enum LoopValue {
+    Ignored,
+    Assigned(syn::Ident), // 'let x = loop { .. }' and 'x = loop { .. }'
+    Returned, // the loop is the tail of a function returning a value
+}
 
 impl CfgBuilder {
     pub fn handle_for_loop(&mut self, expr_for: &syn::ExprForLoop) {
@@ -18,26 +49,57 @@ impl CfgBuilder {
         } else {
             loop_back_node = invariant_node.unwrap();
         }
+        self.attach_pending_decreases(loop_back_node);
     
+        // Adapter loops count their iterations in a synthetic variable, 0 on entry
+        let adapters = Self::loop_adapters(&expr_for.expr);
+        if adapters.is_some() {
+            let counter = Self::loop_counter(&expr_for.pat);
+            let init_node = self.graph.add_node(Self::counter_statement(&format!("let mut {} = 0;", counter)));
+            self.insert_before(loop_back_node, init_node);
+            self.metadata_mut(init_node).var_types = vec![(counter, "usize".to_string())];
+        }
+
         let loop_var = self.format_pattern_condition(&expr_for.pat);
         let iterator = self.format_condition(&expr_for.expr);
         let cond_label = format!("for {} in {}", loop_var, iterator);
         let cond_expr = ConditionalExpr::ForLoop(expr_for.clone());
         let cond_node = self.add_node(CfgNode::new_condition(cond_label, cond_expr));
+        self.set_branch_weights(cond_node, vec![("true".to_string(), Self::branch_hint(&expr_for.attrs)), ("false".to_string(), None)]);
+
+        // The loop variable takes the item type of the iterated range or vector
+        if let Some(item_type) = self.iterated_item_type(&expr_for.expr) {
+            let var_types: Vec<(String, String)> = Self::pattern_bindings(&expr_for.pat).into_iter()
+                .map(|var| (var, item_type.clone()))
+                .collect();
+            for (var, ty) in &var_types {
+                self.type_env.insert(var.clone(), ty.clone());
+            }
+            self.metadata_mut(cond_node).var_types = var_types;
+        }
+        if let Some(adapters) = &adapters {
+            let facts = Self::describe_loop_facts(adapters, &expr_for.pat);
+            self.metadata_mut(cond_node).facts.extend(facts);
+        }
     
         // Process the loop body
         self.current_node = Some(cond_node);
-        self.next_edge_label = Some("true".to_string());
+        self.next_edge_kind = Some(EdgeKind::TrueBranch);
+        let counter = adapters.as_ref().map(|_| Self::loop_counter(&expr_for.pat));
+        self.loop_stack.push(LoopContext { label: Self::loop_label(&expr_for.label), head: loop_back_node, counter: counter.clone(), breaks: Vec::new(), value: LoopValue::Ignored });
         self.visit_block(&expr_for.body);
     
         // Link back to the loop_back_node after the loop body
-        if let Some(end_node) = self.current_node {
-            self.add_edge_with_label(end_node, loop_back_node, "back to loop".to_string());
-        }
+        self.back_to_loop_head(loop_back_node, counter);
     
-        // Create a merge node for the exit of the loop
+        // Create a merge node for the exit of the loop, also reached by its 'break's
+        let context = self.loop_stack.pop().expect("loop context pushed above");
         let merge_node = self.add_node_without_edge(CfgNode::MergePoint);
-        self.add_edge_with_label(cond_node, merge_node, "false".to_string());
+        self.add_edge_with_kind(cond_node, merge_node, EdgeKind::FalseBranch);
+        for break_node in context.breaks {
+            self.add_edge_with_kind(break_node, merge_node, EdgeKind::Fallthrough);
+        }
+        self.loop_exits.insert(merge_node);
     
         // Continue from the merge point after the loop
         self.current_node = Some(merge_node);
@@ -57,27 +119,397 @@ impl CfgBuilder {
         } else {
             loop_back_node = invariant_node.unwrap();
         }
+        self.attach_pending_decreases(loop_back_node);
 
-        // Add the "while" condition node
-        let cond_str = self.format_condition(&expr_while.cond);
-        let cond_expr = ConditionalExpr::While(expr_while.cond.clone());
-        let cond_node = self.add_node(CfgNode::new_condition(format!("while: {}", cond_str), cond_expr));
+        // Add the "while" condition node; 'while let' loops while the value matches the pattern
+        let cond_label = match &*expr_while.cond {
+            Expr::Let(expr_let) => format!("while let: {} = {}", Self::render_pat(&expr_let.pat), self.format_condition(&expr_let.expr)),
+            cond => format!("while: {}", self.format_condition(cond)),
+        };
+        let (cond, structure) = self.lower_condition(&expr_while.cond);
+        let cond_expr = ConditionalExpr::While(Box::new(cond));
+        let cond_node = self.add_node(CfgNode::new_condition(cond_label, cond_expr));
+        self.record_condition_structure(cond_node, structure);
+        self.set_branch_weights(cond_node, vec![("true".to_string(), Self::branch_hint(&expr_while.attrs)), ("false".to_string(), None)]);
 
         // Process the loop body
         self.current_node = Some(cond_node);
-        self.next_edge_label = Some("true".to_string());
+        self.next_edge_kind = Some(EdgeKind::TrueBranch);
+        self.loop_stack.push(LoopContext { label: Self::loop_label(&expr_while.label), head: loop_back_node, counter: None, breaks: Vec::new(), value: LoopValue::Ignored });
         self.visit_block(&expr_while.body);
 
         // Link back to the loop_back_node after the loop body
-        if let Some(end_node) = self.current_node {
-            self.add_edge_with_label(end_node, loop_back_node, "back to loop".to_string());
-        }
+        self.back_to_loop_head(loop_back_node, None);
 
-        // Create a merge node for the false branch of the condition
+        // Create a merge node for the false branch of the condition and the 'break's
+        let context = self.loop_stack.pop().expect("loop context pushed above");
         let merge_node = self.add_node_without_edge(CfgNode::MergePoint);
-        self.add_edge_with_label(cond_node, merge_node, "false".to_string());
+        self.add_edge_with_kind(cond_node, merge_node, EdgeKind::FalseBranch);
+        for break_node in context.breaks {
+            self.add_edge_with_kind(break_node, merge_node, EdgeKind::Fallthrough);
+        }
+        self.loop_exits.insert(merge_node);
 
         // Continue from the merge point after the loop
         self.current_node = Some(merge_node);
     }
+
+    // 'loop { .. }' has no condition: the body starts at its invariant (or cut point) and goes back to it, and
+    // its 'break's are the only ways out. Without any, the code after it is unreachable.
+    pub fn handle_loop(&mut self, expr_loop: &ExprLoop, value: LoopValue) {
+        let loop_back_node = match self.current_node.filter(|&current| matches!(self.graph[current], CfgNode::Invariant(_, _))) {
+            Some(invariant_node) => invariant_node,
+            None => self.add_node(CfgNode::new_cutoff("".to_string())),
+        };
+        self.attach_pending_decreases(loop_back_node);
+
+        self.loop_stack.push(LoopContext { label: Self::loop_label(&expr_loop.label), head: loop_back_node, counter: None, breaks: Vec::new(), value });
+        self.visit_block(&expr_loop.body);
+        self.back_to_loop_head(loop_back_node, None);
+
+        let context = self.loop_stack.pop().expect("loop context pushed above");
+        if context.breaks.is_empty() {
+            self.current_node = None;
+            return;
+        }
+        let merge_node = self.add_node_without_edge(CfgNode::MergePoint);
+        for break_node in context.breaks {
+            self.add_edge_with_kind(break_node, merge_node, EdgeKind::Fallthrough);
+        }
+        self.loop_exits.insert(merge_node);
+        self.current_node = Some(merge_node);
+    }
+
+    // 'decreases!' in the body of a loop: the measure of the innermost one, whatever comes before it there
+    pub fn handle_decreases(&mut self, expr_macro: &ExprMacro) {
+        let Some(head) = self.loop_stack.last().map(|context| context.head) else { return };
+        let annotation = Self::canonical_annotation(expr_macro, AnnotationRole::Decreases);
+        let Expr::Macro(canonical) = &annotation else { return };
+        let measure = self.format_macro_args(&canonical.mac.tokens);
+        self.attach_decreases(head, CfgNode::new_decreases(measure, annotation));
+    }
+
+    // The 'decreases!' written before the loop whose head this is
+    fn attach_pending_decreases(&mut self, head: NodeIndex) {
+        for decreases in std::mem::take(&mut self.pending_decreases) {
+            self.attach_decreases(head, decreases);
+        }
+    }
+
+    // The measure goes right after the head of its loop, so that every path around the loop starts with it
+    // and ends at the head again, where the measure must have decreased. A loop has one measure.
+    fn attach_decreases(&mut self, head: NodeIndex, decreases: CfgNode) {
+        if let Some(measure) = self.graph.neighbors(head).find_map(|next| match &self.graph[next] {
+            CfgNode::Decreases(measure, _) => Some(measure.clone()),
+            _ => None,
+        }) {
+            self.results.rejected_specs.push(format!("{} in {}: the loop already decreases {}", decreases.label(), self.current_function, measure));
+            return;
+        }
+        let current = self.current_node;
+        let decreases_node = self.add_node_without_edge(decreases);
+        let outgoing: Vec<_> = self.graph.edges(head).map(|edge| (edge.target(), edge.weight().clone())).collect();
+        for (target, kind) in outgoing {
+            if let Some(edge) = self.graph.find_edge(head, target) {
+                self.graph.remove_edge(edge);
+            }
+            self.graph.add_edge(decreases_node, target, kind);
+        }
+        self.graph.add_edge(head, decreases_node, EdgeKind::Fallthrough);
+        self.current_node = if current == Some(head) { Some(decreases_node) } else { current };
+    }
+
+    // 'break' leaves the innermost loop, or the one with its label: it is connected to the exit of that loop
+    // once the loop is built, not to the next statement
+    pub fn handle_break(&mut self, expr_break: &ExprBreak) {
+        let target = self.loop_target(expr_break.label.as_ref());
+        // The value of a 'loop' is assigned on the way out, or returned
+        let value = target.map(|target| self.loop_stack[target].value.clone()).unwrap_or(LoopValue::Ignored);
+        match (&expr_break.expr, value) {
+            (Some(break_value), LoopValue::Assigned(var)) => self.visit_expr(&syn::parse_quote!(#var = #break_value)),
+            (Some(break_value), LoopValue::Returned) => {
+                let expr_return = ExprReturn { attrs: Vec::new(), return_token: Default::default(), expr: Some(break_value.clone()) };
+                self.handle_return_statement(&expr_return);
+                return;
+            },
+            _ => {},
+        }
+        let expr = Expr::Break(expr_break.clone());
+        let break_node = self.add_node(CfgNode::new_statement(Self::render_expr(&expr), Stmt::Expr(expr)));
+        // Without a loop to leave (a labeled block), it stays a statement
+        if let Some(target) = target {
+            self.loop_stack[target].breaks.push(break_node);
+            self.current_node = None;
+        }
+    }
+
+    // 'continue' ends the iteration: back to the head of its loop, where the invariant is checked
+    pub fn handle_continue(&mut self, expr_continue: &ExprContinue) {
+        let expr = Expr::Continue(expr_continue.clone());
+        self.add_node(CfgNode::new_statement(Self::render_expr(&expr), Stmt::Expr(expr)));
+        if let Some(target) = self.loop_target(expr_continue.label.as_ref()) {
+            let LoopContext { head, counter, .. } = self.loop_stack[target].clone();
+            self.back_to_loop_head(head, counter);
+        }
+    }
+
+    // End of an iteration, at the end of the body or at a 'continue': counted in adapter loops, then back to
+    // the head
+    fn back_to_loop_head(&mut self, head: NodeIndex, counter: Option<String>) {
+        if let (Some(counter), Some(_)) = (counter, self.current_node) {
+            self.add_node(Self::counter_statement(&format!("{} += 1", counter)));
+        }
+        if let Some(end_node) = self.current_node.take() {
+            self.add_edge_with_kind(end_node, head, EdgeKind::BackEdge);
+        }
+    }
+
+    // Index in the loop stack of the loop a 'break'/'continue' refers to
+    fn loop_target(&self, label: Option<&Lifetime>) -> Option<usize> {
+        match label {
+            Some(label) => self.loop_stack.iter().rposition(|context| context.label.as_ref() == Some(&label.ident.to_string())),
+            None => self.loop_stack.len().checked_sub(1),
+        }
+    }
+
+    fn loop_label(label: &Option<Label>) -> Option<String> {
+        label.as_ref().map(|label| label.name.ident.to_string())
+    }
+
+    // 'take'/'enumerate' chains made of known adapters; None for plain loops and unknown adapters
+    pub fn loop_adapters(iterator: &Expr) -> Option<LoopAdapters> {
+        let mut adapters = LoopAdapters { take_bounds: Vec::new(), enumerate: false };
+        let mut reordered = false;
+        let mut expr = iterator;
+        // Method calls are walked from the outermost adapter inwards
+        while let Expr::MethodCall(method_call) = expr {
+            match method_call.method.to_string().as_str() {
+                "take" => adapters.take_bounds.push(method_call.args.first()?.clone()),
+                "enumerate" if !reordered => adapters.enumerate = true,
+                // 'rev()' keeps the number of iterations, but not the indices produced before it
+                "rev" => reordered = true,
+                name if TRANSPARENT_ADAPTERS.contains(&name) => {},
+                _ => return None,
+            }
+            expr = &method_call.receiver;
+        }
+        if adapters.take_bounds.is_empty() && !adapters.enumerate {
+            None
+        } else {
+            Some(adapters)
+        }
+    }
+
+    // Number of completed iterations, named after the first variable of the loop pattern ('i_iter')
+    pub fn loop_counter(pat: &Pat) -> String {
+        let first = Self::pattern_bindings(pat).into_iter().next().unwrap_or_else(|| "loop".to_string());
+        format!("{}_iter", first)
+    }
+
+    // Facts assumed at the head of an adapter loop: bounds on the counter, and on the taken branch
+    // the remaining iterations and the value of the 'enumerate' index
+    pub fn loop_bound_facts(expr_for: &ExprForLoop, adapters: &LoopAdapters, taken: bool) -> Vec<Expr> {
+        let counter = Self::loop_counter(&expr_for.pat);
+        let Ok(counter) = syn::parse_str::<Expr>(&counter) else {
+            eprintln!("Warning: can't parse the loop counter '{}', the loop is not bounded", counter);
+            return Vec::new();
+        };
+        let mut facts: Vec<Expr> = vec![syn::parse_quote! { 0 <= #counter }];
+        for bound in &adapters.take_bounds {
+            let bound = Self::wrap_with_parens(bound.clone());
+            facts.push(if taken { syn::parse_quote! { #counter < #bound } } else { syn::parse_quote! { #counter <= #bound } });
+        }
+        if taken && adapters.enumerate {
+            if let Some(index) = Self::enumerate_index(&expr_for.pat) {
+                match syn::parse_str::<Expr>(&index) {
+                    Ok(index) => facts.push(syn::parse_quote! { #index == #counter }),
+                    Err(_) => eprintln!("Warning: can't parse the enumerate index '{}', its value is not assumed", index),
+                }
+            }
+        }
+        facts
+    }
+
+    // Readable version of the facts, shown in the DOT tooltip and the JSON export
+    fn describe_loop_facts(adapters: &LoopAdapters, pat: &Pat) -> Vec<String> {
+        let counter = Self::loop_counter(pat);
+        let mut facts: Vec<String> = adapters.take_bounds.iter()
+            .map(|bound| format!("at most {} iterations: {} <= {}", Self::render_expr(bound), counter, Self::render_expr(bound)))
+            .collect();
+        if adapters.enumerate {
+            if let Some(index) = Self::enumerate_index(pat) {
+                facts.push(format!("enumerate index: {} == {}", index, counter));
+            }
+        }
+        facts
+    }
+
+    // 'i' in 'for (i, x) in v.iter().enumerate()'
+    fn enumerate_index(pat: &Pat) -> Option<String> {
+        match pat {
+            Pat::Tuple(tuple) => tuple.elems.first().and_then(|first| match first {
+                Pat::Ident(pat_ident) => Some(pat_ident.ident.to_string()),
+                _ => None,
+            }),
+            _ => None,
+        }
+    }
+
+    // The counter isn't in the source: its statements carry no syntax, so no panic is checked on them
+    fn counter_statement(code: &str) -> CfgNode {
+        CfgNode::Statement(code.to_string(), None)
+    }
+
+    // Put 'new_node' on all the edges entering 'node'
+    pub(crate) fn insert_before(&mut self, node: NodeIndex, new_node: NodeIndex) {
+        let incoming: Vec<_> = self.graph.edges_directed(node, petgraph::Direction::Incoming)
+            .map(|edge| (edge.source(), edge.weight().clone()))
+            .collect();
+        // Edge indices shift on removal, so edges are looked up again each time
+        for (source, kind) in incoming {
+            if let Some(edge) = self.graph.find_edge(source, node) {
+                self.graph.remove_edge(edge);
+            }
+            self.graph.add_edge(source, new_node, kind);
+        }
+        self.graph.add_edge(new_node, node, EdgeKind::Fallthrough);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support;
+    use crate::verifier::VcOutcome;
+
+    fn verdicts(post: &str) -> Vec<VcOutcome> {
+        let source = format!("fn count(v: Vec<i32>, n: usize) -> usize {{
+                pre!(true);
+                post!({});
+                let mut c = 0;
+                invariant!(c == x_iter);
+                for x in v.iter().take(n) {{
+                    c += 1;
+                }}
+                return c;
+            }}", post);
+        test_support::verdicts(&source)
+    }
+
+    // The counter of 'take(n)' bounds the iterations, so the loop runs at most n times
+    #[test]
+    fn take_bounds_the_number_of_iterations() {
+        let outcomes = verdicts("c <= n");
+        assert!(!outcomes.is_empty() && outcomes.iter().all(|outcome| matches!(outcome, VcOutcome::Valid)));
+        let outcomes = verdicts("c < n");
+        assert_eq!(outcomes.iter().filter(|outcome| matches!(outcome, VcOutcome::Counterexample(_))).count(), 1);
+    }
+
+    fn exit_verdicts(on_exit: &str) -> Vec<VcOutcome> {
+        let source = format!("fn up(n: i32) -> i32 {{
+                pre!(n >= 0);
+                post!(i >= n);
+                let mut i = 0;
+                invariant!(i <= n);
+                while i < n {{
+                    i += 1;
+                }}
+                on_exit!(\"{}\");
+                return i;
+            }}", on_exit);
+        test_support::verdicts(&source)
+    }
+
+    // 'on_exit!' is checked from the invariant and the negated condition, and assumed after the loop
+    #[test]
+    fn on_exit_is_checked_when_the_loop_ends() {
+        let outcomes = exit_verdicts("i == n");
+        assert!(!outcomes.is_empty() && outcomes.iter().all(|outcome| matches!(outcome, VcOutcome::Valid)));
+        let outcomes = exit_verdicts("i == n + 1");
+        assert_eq!(outcomes.iter().filter(|outcome| matches!(outcome, VcOutcome::Counterexample(_))).count(), 1);
+    }
+
+    // The exit condition is checked with the loop's context and the path goes on to the postcondition,
+    // which still knows the invariant and the precondition
+    #[test]
+    fn on_exit_keeps_what_the_loop_exit_knows() {
+        let outcomes = test_support::verdicts("fn sum_to(n: i32) -> i32 {
+                pre!(n >= 0);
+                post!(sum == n * (n + 1) / 2);
+                let mut sum = 0;
+                let mut i = 1;
+                invariant!(i <= n + 1 && sum == (i - 1) * i / 2);
+                while i <= n {
+                    sum += i;
+                    i += 1;
+                }
+                on_exit!(i == n + 1);
+                return sum;
+            }");
+        assert_eq!(outcomes.len(), 3);
+        assert!(outcomes.iter().all(|outcome| matches!(outcome, VcOutcome::Valid)));
+    }
+
+    #[test]
+    fn enumerate_gives_the_index_of_the_iteration() {
+        let expr_for: ExprForLoop = syn::parse_quote!(for (i, x) in v.iter().enumerate().take(k + 1) {});
+        let adapters = CfgBuilder::loop_adapters(&expr_for.expr).unwrap();
+        let facts = |taken| CfgBuilder::loop_bound_facts(&expr_for, &adapters, taken).iter().map(CfgBuilder::render_expr).collect::<Vec<_>>();
+        assert_eq!(facts(true), vec!["0 <= i_iter", "i_iter < k + 1", "i == i_iter"]);
+        assert_eq!(facts(false), vec!["0 <= i_iter", "i_iter <= k + 1"]);
+        // 'rev()' before 'enumerate()' still counts, an unknown adapter doesn't
+        assert!(CfgBuilder::loop_adapters(&syn::parse_quote!(v.iter().rev().enumerate())).is_some_and(|adapters| adapters.enumerate));
+        assert!(CfgBuilder::loop_adapters(&syn::parse_quote!(v.iter().filter(p).take(n))).is_none());
+    }
+
+    fn bare_loop_verdicts(post: &str) -> Vec<VcOutcome> {
+        let source = format!("fn double_last(n: i32) -> i32 {{
+                pre!(n >= 1);
+                post!({});
+                let mut i = 0;
+                invariant!(i >= 0 && i < n);
+                let r = loop {{
+                    if i + 1 == n {{
+                        break i * 2;
+                    }}
+                    i = i + 1;
+                }};
+                return r;
+            }}", post);
+        test_support::verdicts(&source)
+    }
+
+    // The invariant is kept around the loop and the break is the only way to the postcondition
+    #[test]
+    fn a_bare_loop_is_left_by_its_break_only() {
+        let outcomes = bare_loop_verdicts("r >= 0 && r == 2 * (n - 1)");
+        assert!(outcomes.len() == 3 && outcomes.iter().all(|outcome| matches!(outcome, VcOutcome::Valid)));
+        // 'n == 1' breaks at once with 0
+        let outcomes = bare_loop_verdicts("r > 0");
+        assert_eq!(outcomes.iter().filter(|outcome| matches!(outcome, VcOutcome::Counterexample(_))).count(), 1);
+    }
+
+    fn measured(measure: &str) -> Vec<VcOutcome> {
+        let source = format!("fn count(n: i32) -> i32 {{
+                pre!(n >= 0);
+                post!(counter == n);
+                let mut counter = 0;
+                invariant!(counter <= n);
+                decreases!({});
+                while counter < n {{
+                    counter = counter + 1;
+                }}
+                return counter;
+            }}", measure);
+        test_support::verdicts(&source)
+    }
+
+    // Only the termination obligation of the iteration depends on the measure
+    #[test]
+    fn the_measure_decreases_on_every_iteration() {
+        let outcomes = measured("n - counter");
+        assert!(outcomes.len() == 4 && outcomes.iter().all(|outcome| matches!(outcome, VcOutcome::Valid)));
+        let outcomes = measured("counter");
+        assert_eq!(outcomes.iter().filter(|outcome| matches!(outcome, VcOutcome::Counterexample(_))).count(), 1);
+    }
 }
@@ -6,11 +6,12 @@ use crate::cfg_builder::node::CfgNode;
 impl CfgBuilder {
     pub fn process_macro(&mut self, expr_macro: &ExprMacro) {
         let macro_name = format!("{}!", expr_macro.mac.path.segments.last().unwrap().ident);
-        self.process_external_conditions(&macro_name, quote!(#expr_macro).to_string());
+        self.process_external_conditions(&macro_name, Self::render_expr(&Expr::Macro(expr_macro.clone())));
     }
 
     pub fn process_macro_call_as_function(&mut self, args: &Punctuated<Expr, Comma>, macro_name: &str) {
-        let call_expression = format!("{}[{}]", macro_name, quote!(#args));
+        let rendered_args: Vec<String> = args.iter().map(Self::render_expr).collect();
+        let call_expression = format!("{}[{}]", macro_name, rendered_args.join(", "));
         self.process_external_conditions(macro_name, call_expression);
     }
 
@@ -0,0 +1,364 @@
+use syn::visit::Visit;
+use syn::{Expr, ExprLet, ExprMatch, Pat};
+
+use crate::cfg_builder::builder::CfgBuilder;
+use crate::cfg_builder::node::{CfgNode, ConditionalExpr, EdgeKind, MatchArmCondition, MatchCondition};
+
+// Enums known without being declared in the analyzed file, with variants in declaration order
+const PRELUDE_ENUMS: [(&str, &[&str]); 2] = [
+    ("Option", &["None", "Some"]),
+    ("Result", &["Ok", "Err"]),
+];
+
+impl CfgBuilder {
+    pub fn handle_match(&mut self, expr_match: &ExprMatch) {
+        let scrutinee_str = self.format_condition(&expr_match.expr);
+        let match_condition = self.lower_match_condition(expr_match);
+
+        let cond_expr = ConditionalExpr::Match(match_condition.clone());
+        let cond_node = self.add_node(CfgNode::new_condition(format!("match: {}", scrutinee_str), cond_expr));
+        if let Some(enum_name) = &match_condition.enum_name {
+            let variants = self.enum_variants(enum_name).unwrap_or_default();
+            self.metadata_mut(cond_node).facts.push(format!(
+                "match on {} is exhaustive: exactly one of {} holds",
+                enum_name,
+                variants.join(", ")
+            ));
+        }
+
+        let weights = expr_match.arms.iter().zip(&match_condition.arms)
+            .map(|(arm, arm_condition)| (arm_condition.label.clone(), Self::branch_hint(&arm.attrs)))
+            .collect();
+        self.set_branch_weights(cond_node, weights);
+
+        // Each arm is a branch out of the condition node, labeled with its pattern (and guard)
+        let mut arm_ends = Vec::new();
+        for (arm, arm_condition) in expr_match.arms.iter().zip(&match_condition.arms) {
+            self.current_node = Some(cond_node);
+            self.next_edge_kind = Some(EdgeKind::MatchArm(arm_condition.label.clone()));
+            match &*arm.body {
+                Expr::Block(block) => self.visit_block(&block.block),
+                body => self.visit_expr(body),
+            }
+            arm_ends.push((self.current_node, arm_condition.label.clone()));
+            self.next_edge_kind = None;
+        }
+
+        // All arms reconverge at a merge point
+        let merge_node = self.add_node_without_edge(CfgNode::MergePoint);
+        for (arm_end, label) in arm_ends {
+            match arm_end {
+                // Empty arm body: the arm edge goes straight to the merge point
+                Some(end) if end == cond_node => self.add_edge_with_kind(cond_node, merge_node, EdgeKind::MatchArm(label)),
+                Some(end) => self.add_edge_with_kind(end, merge_node, EdgeKind::Fallthrough),
+                None => {}
+            }
+        }
+        self.current_node = Some(merge_node);
+    }
+
+    // Build the per-arm path conditions and, for a known enum, the exhaustiveness fact
+    pub(crate) fn lower_match_condition(&self, expr_match: &ExprMatch) -> MatchCondition {
+        let enum_name = self.match_enum_name(expr_match);
+        let tag_var = Self::tag_variable(&expr_match.expr);
+
+        let mut arms = Vec::new();
+        let mut previous_conditions: Vec<String> = Vec::new();
+        for arm in &expr_match.arms {
+            let mut label = Self::render_pat(&arm.pat);
+            let pattern_condition = self.pattern_condition(&arm.pat, &expr_match.expr, enum_name.as_deref(), &tag_var);
+
+            // An arm is taken when its own test holds and none of the previous arms matched
+            let mut parts: Vec<String> = previous_conditions.iter().map(|c| format!("!({})", c)).collect();
+            let mut own_condition = pattern_condition.clone().filter(|c| c != "true");
+            if let Some((_, guard)) = &arm.guard {
+                let guard_str = Self::render_expr(guard);
+                label = format!("{} if {}", label, guard_str);
+                own_condition = Some(match own_condition {
+                    Some(cond) => format!("({}) && ({})", cond, guard_str),
+                    None => guard_str,
+                });
+            }
+            // Later arms may only assume this one failed when its test is exactly the pattern: 'Some(0)' is
+            // tested as 'o_tag == 1', and its negation would make a following 'Some(n)' unreachable. A guard
+            // on the pattern's bindings reads values the solver doesn't tie to the scrutinee either.
+            let guard_reads_bindings = arm.guard.is_some() && !Self::pattern_bindings(&arm.pat).iter()
+                .all(|name| name.starts_with(char::is_uppercase));
+            let exact = self.pattern_is_exact(&arm.pat) && !guard_reads_bindings;
+            if let Some(own) = &own_condition {
+                parts.push(format!("({})", own));
+                if exact {
+                    previous_conditions.push(own.clone());
+                }
+            }
+
+            let condition = if parts.is_empty() { None } else { syn::parse_str::<Expr>(&parts.join(" && ")).ok() };
+            arms.push(MatchArmCondition { label, condition });
+        }
+
+        let exhaustive_fact = enum_name.as_ref()
+            .and_then(|name| self.enum_variants(name))
+            .map(|variants| {
+                (0..variants.len()).map(|k| format!("{} == {}", tag_var, k)).collect::<Vec<_>>().join(" || ")
+            })
+            .and_then(|fact| syn::parse_str::<Expr>(&fact).ok());
+
+        MatchCondition {
+            scrutinee: expr_match.expr.clone(),
+            enum_name,
+            arms,
+            exhaustive_fact,
+        }
+    }
+
+    // Match of the pattern of an 'if let'/'while let' against its value: the arm of the pattern, then an
+    // 'else' arm for the values it doesn't match
+    pub(crate) fn let_match_condition(&self, expr_let: &ExprLet) -> MatchCondition {
+        let (pat, scrutinee) = (&expr_let.pat, &expr_let.expr);
+        let expr_match: ExprMatch = syn::parse_quote!(match #scrutinee { #pat => {}, _ => {} });
+        let mut match_condition = self.lower_match_condition(&expr_match);
+        if let Some(other) = match_condition.arms.last_mut() {
+            other.label = "else".to_string();
+        }
+        match_condition
+    }
+
+    // Solver variable holding the constructor of an enum value ('o_tag' for 'o')
+    pub(crate) fn tag_variable(scrutinee: &Expr) -> String {
+        format!("{}_tag", Self::sanitize_identifier(&Self::render_expr(scrutinee)))
+    }
+
+    // Condition under which a pattern matches, as Rust expression text
+    pub(crate) fn pattern_condition(&self, pat: &Pat, scrutinee: &Expr, enum_name: Option<&str>, tag_var: &str) -> Option<String> {
+        let scrutinee_str = Self::render_expr(scrutinee);
+        match pat {
+            Pat::Wild(_) => Some("true".to_string()),
+            Pat::Ident(pat_ident) if pat_ident.subpat.is_none() => {
+                // A bare identifier is either a unit variant in scope or a catch-all binding
+                match enum_name.and_then(|name| self.variant_index(name, &pat_ident.ident.to_string())) {
+                    Some(k) => Some(format!("{} == {}", tag_var, k)),
+                    None => Some("true".to_string()),
+                }
+            },
+            Pat::Path(pat_path) => self.variant_tag_condition(&pat_path.path, enum_name, tag_var),
+            Pat::TupleStruct(tuple_struct) => self.variant_tag_condition(&tuple_struct.path, enum_name, tag_var),
+            Pat::Struct(pat_struct) => self.variant_tag_condition(&pat_struct.path, enum_name, tag_var),
+            Pat::Lit(pat_lit) => Some(format!("{} == {}", scrutinee_str, Self::render_expr(&pat_lit.expr))),
+            Pat::Range(pat_range) => {
+                let upper = match pat_range.limits {
+                    syn::RangeLimits::HalfOpen(_) => "<",
+                    syn::RangeLimits::Closed(_) => "<=",
+                };
+                Some(format!(
+                    "{} >= {} && {} {} {}",
+                    scrutinee_str, Self::render_expr(&pat_range.lo), scrutinee_str, upper, Self::render_expr(&pat_range.hi)
+                ))
+            },
+            Pat::Slice(pat_slice) => Self::slice_length_condition(pat_slice, scrutinee),
+            Pat::Or(pat_or) => {
+                let cases: Option<Vec<String>> = pat_or.cases.iter()
+                    .map(|case| self.pattern_condition(case, scrutinee, enum_name, tag_var))
+                    .collect();
+                cases.map(|cases| cases.iter().map(|c| format!("({})", c)).collect::<Vec<_>>().join(" || "))
+            },
+            _ => None,
+        }
+    }
+
+    // Whether the condition of 'pattern_condition' holds exactly for the values the pattern matches, rather
+    // than for a superset of them: constructors are only tested by their tag, so their fields must match
+    // anything
+    pub(crate) fn pattern_is_exact(&self, pat: &Pat) -> bool {
+        match pat {
+            Pat::Wild(_) | Pat::Lit(_) | Pat::Range(_) | Pat::Path(_) => true,
+            Pat::Ident(pat_ident) => pat_ident.subpat.as_ref().is_none_or(|(_, subpat)| self.pattern_is_exact(subpat)),
+            Pat::TupleStruct(tuple_struct) => tuple_struct.pat.elems.iter().all(Self::is_irrefutable),
+            Pat::Struct(pat_struct) => pat_struct.fields.iter().all(|field| Self::is_irrefutable(&field.pat)),
+            // The length condition is only given for slices of bindings
+            Pat::Slice(_) => true,
+            Pat::Or(pat_or) => pat_or.cases.iter().all(|case| self.pattern_is_exact(case)),
+            _ => false,
+        }
+    }
+
+    // Patterns matching every value of their type. Names starting with a capital are unit variants or
+    // constants, not bindings.
+    fn is_irrefutable(pat: &Pat) -> bool {
+        match pat {
+            Pat::Wild(_) | Pat::Rest(_) => true,
+            Pat::Ident(pat_ident) => {
+                !pat_ident.ident.to_string().starts_with(char::is_uppercase)
+                    && pat_ident.subpat.as_ref().is_none_or(|(_, subpat)| Self::is_irrefutable(subpat))
+            },
+            Pat::Tuple(tuple) => tuple.elems.iter().all(Self::is_irrefutable),
+            Pat::Reference(pat_ref) => Self::is_irrefutable(&pat_ref.pat),
+            Pat::Type(pat_type) => Self::is_irrefutable(&pat_type.pat),
+            _ => false,
+        }
+    }
+
+    // Length fact of a slice pattern whose elements are all bindings: '[a, b]' needs 'v.len() == 2' and
+    // '[first, .., last]' 'v.len() >= 2'. Patterns testing element values get no condition.
+    fn slice_length_condition(pat_slice: &syn::PatSlice, scrutinee: &Expr) -> Option<String> {
+        let mut fixed = 0;
+        let mut has_rest = false;
+        for elem in &pat_slice.elems {
+            match elem {
+                Pat::Rest(_) => has_rest = true,
+                Pat::Ident(pat_ident) => match &pat_ident.subpat {
+                    None => fixed += 1,
+                    Some((_, subpat)) if matches!(**subpat, Pat::Rest(_)) => has_rest = true,
+                    Some(_) => return None,
+                },
+                Pat::Wild(_) => fixed += 1,
+                _ => return None,
+            }
+        }
+        let receiver = match scrutinee {
+            Expr::Reference(reference) => &*reference.expr,
+            scrutinee => scrutinee,
+        };
+        let op = if has_rest { ">=" } else { "==" };
+        Some(format!("({}).len() {} {}", Self::render_expr(receiver), op, fixed))
+    }
+
+    fn variant_tag_condition(&self, path: &syn::Path, enum_name: Option<&str>, tag_var: &str) -> Option<String> {
+        let variant = path.segments.last()?.ident.to_string();
+        let index = self.variant_index(enum_name?, &variant)?;
+        Some(format!("{} == {}", tag_var, index))
+    }
+
+    // Enum matched on, from qualified patterns ('Color::Red'), prelude variants or the scrutinee type
+    pub(crate) fn match_enum_name(&self, expr_match: &ExprMatch) -> Option<String> {
+        for arm in &expr_match.arms {
+            let path = match &arm.pat {
+                Pat::Path(pat_path) => &pat_path.path,
+                Pat::TupleStruct(tuple_struct) => &tuple_struct.path,
+                Pat::Struct(pat_struct) => &pat_struct.path,
+                _ => continue,
+            };
+            let segments: Vec<String> = path.segments.iter().map(|s| s.ident.to_string()).collect();
+            if segments.len() >= 2 {
+                let enum_name = &segments[segments.len() - 2];
+                if self.enum_variants(enum_name).is_some() {
+                    return Some(enum_name.clone());
+                }
+            }
+            if let Some(variant) = segments.last() {
+                if let Some((name, _)) = PRELUDE_ENUMS.iter().find(|(_, variants)| variants.contains(&variant.as_str())) {
+                    return Some(name.to_string());
+                }
+            }
+        }
+
+        let scrutinee_type = self.infer_expr_type(&expr_match.expr)?;
+        let base = scrutinee_type.trim_start_matches('&').trim_start_matches("mut ");
+        let base = base.split('<').next().unwrap_or(base);
+        self.enum_variants(base).map(|_| base.to_string())
+    }
+
+    pub fn enum_variants(&self, enum_name: &str) -> Option<Vec<String>> {
+        if let Some(variants) = self.file.local_enums.get(enum_name) {
+            return Some(variants.clone());
+        }
+        PRELUDE_ENUMS.iter()
+            .find(|(name, _)| *name == enum_name)
+            .map(|(_, variants)| variants.iter().map(|v| v.to_string()).collect())
+    }
+
+    fn variant_index(&self, enum_name: &str, variant: &str) -> Option<usize> {
+        self.enum_variants(enum_name)?.iter().position(|v| v == variant)
+    }
+
+    // Turn an expression label into an identifier usable as a solver variable ('self.kind' -> 'self_kind')
+    pub fn sanitize_identifier(input: &str) -> String {
+        let sanitized: String = input.chars()
+            .map(|c| if c.is_alphanumeric() || c == '_' { c } else { '_' })
+            .collect();
+        sanitized.trim_matches('_').to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{built, discharge};
+    use crate::verifier::{verify_str_implication, VcOutcome};
+
+    #[test]
+    fn arms_are_taken_when_no_earlier_arm_matched() {
+        let builder = built("enum Color { Red, Green, Blue }");
+        let expr_match: ExprMatch = syn::parse_quote!(match c { Color::Red => 1, Color::Green if n > 2 => 2, _ => 3 });
+        let lowered = builder.lower_match_condition(&expr_match);
+        let conditions: Vec<String> = lowered.arms.iter()
+            .map(|arm| arm.condition.as_ref().map(CfgBuilder::render_expr).unwrap_or_default())
+            .collect();
+        assert_eq!(conditions, vec![
+            "(c_tag == 0)",
+            "!(c_tag == 0) && ((c_tag == 1) && (n > 2))",
+            "!(c_tag == 0) && !((c_tag == 1) && (n > 2))",
+        ]);
+        let labels: Vec<&str> = lowered.arms.iter().map(|arm| arm.label.as_str()).collect();
+        assert_eq!(labels, vec!["Color::Red", "Color::Green if n > 2", "_"]);
+    }
+
+    #[test]
+    fn matches_on_a_known_enum_assume_one_constructor_holds() {
+        let builder = built("enum Color { Red, Green, Blue }");
+        let expr_match: ExprMatch = syn::parse_quote!(match c { Color::Red => 1, _ => 3 });
+        let fact = builder.lower_match_condition(&expr_match).exhaustive_fact.map(|fact| CfgBuilder::render_expr(&fact));
+        assert_eq!(fact.as_deref(), Some("c_tag == 0 || c_tag == 1 || c_tag == 2"));
+        // An unknown scrutinee type gets no fact
+        let expr_match: ExprMatch = syn::parse_quote!(match n { 0 => 1, _ => 3 });
+        assert!(builder.lower_match_condition(&expr_match).exhaustive_fact.is_none());
+    }
+
+    #[test]
+    fn every_arm_is_a_path_with_its_condition() {
+        let mut builder = built("enum Color { Red, Green, Blue }
+            fn code(c: Color, n: i32) -> i32 {
+                pre!(n >= 0);
+                post!(r >= 1 && r <= 3);
+                let mut r = 0;
+                match c {
+                    Color::Red => { r = 1; }
+                    Color::Green if n > 2 => { r = 2; }
+                    _ => { r = 3; }
+                }
+                return r;
+            }");
+        let paths = builder.generate_basic_paths();
+        let arms = builder.graph.edge_indices().filter(|&edge| matches!(builder.graph[edge], EdgeKind::MatchArm(_))).count();
+        assert_eq!((paths.len(), arms), (3, 3));
+        for implication in builder.apply_wp_calculus(&paths) {
+            assert!(implication.contains("c_tag == 0 || c_tag == 1 || c_tag == 2"), "{}", implication);
+            assert!(matches!(verify_str_implication(&implication, &builder.options.solver_settings), Ok(VcOutcome::Valid)), "{}", implication);
+        }
+    }
+
+    #[test]
+    fn an_arm_testing_fields_does_not_exclude_later_arms() {
+        let expr_match: ExprMatch = syn::parse_quote!(match o { Some(0) => 1, Some(n) => 2, None => 3 });
+        let conditions: Vec<String> = built("").lower_match_condition(&expr_match).arms.iter()
+            .map(|arm| arm.condition.as_ref().map(CfgBuilder::render_expr).unwrap_or_default())
+            .collect();
+        assert_eq!(conditions, vec!["(o_tag == 1)", "(o_tag == 1)", "!(o_tag == 1) && (o_tag == 0)"]);
+
+        // 'Some(n)' is reachable after 'Some(0)', so a postcondition it breaks is refuted there
+        let mut builder = built("fn pick(o: Option<u32>) -> u32 {
+                pre!(true);
+                post!(r != 2);
+                let mut r = 0;
+                match o {
+                    Some(0) => { r = 1; }
+                    Some(n) => { r = 2; }
+                    None => { r = 3; }
+                }
+                return r;
+            }");
+        let outcomes = discharge(&mut builder);
+        assert_eq!(outcomes.len(), 3);
+        assert_eq!(outcomes.iter().filter(|outcome| matches!(outcome, VcOutcome::Counterexample(_))).count(), 1);
+        assert_eq!(outcomes.iter().filter(|outcome| matches!(outcome, VcOutcome::Valid)).count(), 2);
+    }
+}
@@ -1,11 +1,66 @@
-use syn::ExprReturn;
-use quote::quote;
+use petgraph::graph::NodeIndex;
+use syn::{Expr, ExprReturn, ItemFn, ReturnType, Type};
 use crate::cfg_builder::{CfgBuilder, CfgNode};
 
 impl CfgBuilder {
     pub fn handle_return_statement(&mut self, expr_return: &ExprReturn) {
-        let return_expr = expr_return.expr.as_ref().map(|expr| quote!(#expr).to_string()).unwrap_or_default();
+        let return_expr = expr_return.expr.as_ref().map(|expr| Self::render_expr(expr)).unwrap_or_default();
         let return_node = self.add_node(CfgNode::new_return(return_expr, expr_return.clone()));
-        self.current_node = Some(return_node);
+        // A return leaves the function: it is connected to the postconditions, not to the next statement
+        self.return_nodes.push(return_node);
+        self.current_node = None;
+    }
+
+    // The tail expression of a function returning a value is an implicit 'return'
+    pub fn handle_tail_return(&mut self, expr: &Expr) {
+        let expr_return = ExprReturn {
+            attrs: Vec::new(),
+            return_token: Default::default(),
+            expr: Some(Box::new(expr.clone())),
+        };
+        self.handle_return_statement(&expr_return);
+    }
+
+    // Value of 'result' in the postconditions a path reaches through this node: the expression it returns,
+    // explicitly or as the tail of the body
+    pub(crate) fn returned_value(&self, node: NodeIndex) -> Option<Expr> {
+        match &self.graph[node] {
+            CfgNode::Return(_, Some(expr_return)) => expr_return.expr.as_ref().map(|expr| Self::resolve_pure_calls(expr)),
+            _ => None,
+        }
+    }
+
+    // Tail expressions that are not plain values are control flow, handled by the visitor
+    pub fn is_value_tail(expr: &Expr) -> bool {
+        !matches!(
+            expr,
+            Expr::If(_) | Expr::Match(_) | Expr::While(_) | Expr::ForLoop(_) | Expr::Loop(_)
+            | Expr::Block(_) | Expr::Macro(_) | Expr::Return(_)
+        )
+    }
+
+    // Why a function can't satisfy postconditions: it never returns ('-> !'), or every path of its body
+    // ends in a call that never returns or a 'loop' without 'break'. Checked once the body is built.
+    pub fn never_returns_reason(&self, item_fn: &ItemFn) -> Option<&'static str> {
+        if let ReturnType::Type(_, ty) = &item_fn.sig.output {
+            if matches!(**ty, Type::Never(_)) {
+                return Some("it returns `!`");
+            }
+        }
+        (self.return_nodes.is_empty() && self.current_node.is_none()).then_some("every path ends in a call that never returns or a loop that never exits")
+    }
+
+    // How a path leaves its function, for paths ending at a postcondition
+    pub fn exit_of_path(&self, path: &[NodeIndex]) -> Option<String> {
+        let last = *path.last()?;
+        if !matches!(self.graph[last], CfgNode::Postcondition(_, _)) {
+            return None;
+        }
+        let exit = path.iter().rev().find_map(|&node| match &self.graph[node] {
+            CfgNode::Return(ret, _) if ret.is_empty() => Some("return".to_string()),
+            CfgNode::Return(ret, _) => Some(format!("return {}", ret)),
+            _ => None,
+        });
+        Some(exit.unwrap_or_else(|| "end of function".to_string()))
     }
 }
@@ -0,0 +1,112 @@
+use petgraph::algo::has_path_connecting;
+use petgraph::graph::NodeIndex;
+use petgraph::visit::{Dfs, EdgeFiltered, EdgeRef};
+use petgraph::Direction;
+use std::collections::BTreeSet;
+use syn::Stmt;
+
+use crate::cfg_builder::builder::CfgBuilder;
+use crate::cfg_builder::node::{CfgNode, ConditionalExpr, EdgeKind};
+
+// What a reviewer needs to audit one loop: its annotations and the variables its body assigns
+#[derive(Debug, Clone)]
+pub struct LoopSummary {
+    pub head: NodeIndex, // 'while'/'for' condition node
+    pub label: String, // e.g. 'while: i < n'
+    pub invariants: Vec<String>,
+    pub decreases: Vec<String>, // 'decreases!' measure, before the loop or in its body
+    pub modified: Vec<String>, // variables and fields assigned in the body, loop variables included
+}
+
+impl CfgBuilder {
+    // Summaries of the 'while' and 'for' loops of the graph, by node ID of their head
+    pub fn loop_summaries(&self) -> Vec<LoopSummary> {
+        let mut summaries: Vec<LoopSummary> = self.graph.node_indices()
+            .filter_map(|head| self.loop_summary(head))
+            .collect();
+        summaries.sort_by_key(|summary| self.node_id(summary.head));
+        summaries
+    }
+
+    fn loop_summary(&self, head: NodeIndex) -> Option<LoopSummary> {
+        let pattern = match &self.graph[head] {
+            CfgNode::Condition(_, Some(ConditionalExpr::While(_))) => None,
+            CfgNode::Condition(_, Some(ConditionalExpr::ForLoop(expr_for))) => Some(&expr_for.pat),
+            _ => return None,
+        };
+
+        // The back edges go to the invariant (the last one when there are several) or to the cut point before the
+        // head, with the measure of the loop in between
+        let before_head = self.graph.edges_directed(head, Direction::Incoming)
+            .map(|edge| edge.source())
+            .find(|&source| matches!(self.graph[source], CfgNode::Decreases(_, _))
+                || self.graph.edges_directed(source, Direction::Incoming).any(|edge| *edge.weight() == EdgeKind::BackEdge))?;
+        let (back_node, decreases) = match &self.graph[before_head] {
+            CfgNode::Decreases(measure, _) => (self.graph.edges_directed(before_head, Direction::Incoming).next()?.source(), vec![measure.clone()]),
+            _ => (before_head, Vec::new()),
+        };
+        // Invariants written before the loop come right before the back node
+        let mut invariants = Vec::new();
+        let mut node = Some(back_node);
+        while let Some(CfgNode::Invariant(invariant, _)) = node.map(|current| &self.graph[current]) {
+            invariants.push(invariant.clone());
+            node = node.and_then(|current| self.graph.edges_directed(current, Direction::Incoming)
+                .find(|edge| *edge.weight() != EdgeKind::BackEdge)
+                .map(|edge| edge.source()));
+        }
+        invariants.reverse();
+
+        // The body is what the head reaches without going around the loop, and that leads back to it
+        let forward = EdgeFiltered::from_fn(&self.graph, |edge| *edge.weight() != EdgeKind::BackEdge);
+        let mut modified: BTreeSet<String> = pattern.map(Self::pattern_bindings).unwrap_or_default().into_iter().collect();
+        let mut dfs = Dfs::new(&forward, head);
+        while let Some(node) = dfs.next(&forward) {
+            if let CfgNode::Statement(label, stmt) = &self.graph[node] {
+                if matches!(stmt, Some(Stmt::Local(_))) || !has_path_connecting(&self.graph, node, back_node, None) {
+                    continue;
+                }
+                if let Some((var, _)) = self.parse_assignment(label) {
+                    modified.insert(var);
+                }
+            }
+        }
+
+        Some(LoopSummary {
+            head,
+            label: self.graph[head].label(),
+            invariants,
+            decreases,
+            modified: modified.into_iter().collect(),
+        })
+    }
+
+    // A note next to the head of each shown loop, linked to it by a dashed line
+    pub fn loop_summaries_dot(&self, shown: impl Fn(NodeIndex) -> bool) -> String {
+        let mut dot = String::new();
+        for summary in self.loop_summaries().into_iter().filter(|summary| shown(summary.head)) {
+            let head_id = self.node_id(summary.head);
+            let id = format!("{}/loop_summary", head_id);
+            let lines = summary.lines();
+            let label: String = lines.iter().map(|line| format!("{}\\l", self.options.notation.render(line).replace('"', "\\\""))).collect();
+            dot.push_str(&format!("\"{}\" [label=\"{}\", shape=note, style=filled, fillcolor=lightyellow, fontsize=10];\n", id, label));
+            dot.push_str(&format!("\"{}\" -> \"{}\" [style=dashed, arrowhead=none, constraint=false];\n", id, head_id));
+            dot.push_str(&format!("{{rank=same; \"{}\"; \"{}\";}}\n", head_id, id));
+        }
+        dot
+    }
+}
+
+impl LoopSummary {
+    // 'invariant: ..', 'decreases: ..' and 'modifies: ..' lines under the loop label
+    pub fn lines(&self) -> Vec<String> {
+        let list = |items: &[String]| if items.is_empty() { "none".to_string() } else { items.join(", ") };
+        let mut lines = vec![format!("loop {}", self.label)];
+        if self.invariants.is_empty() {
+            lines.push("invariant: none (cut point)".to_string());
+        }
+        lines.extend(self.invariants.iter().map(|invariant| format!("invariant: {}", invariant)));
+        lines.push(format!("decreases: {}", list(&self.decreases)));
+        lines.push(format!("modifies: {}", list(&self.modified)));
+        lines
+    }
+}
@@ -5,9 +5,52 @@ mod handle_loops;
 mod handle_macros;
 mod handle_call;
 mod handle_return;
+mod handle_match;
+mod pattern_tests;
+mod handle_assign;
+mod handle_cast;
+mod handle_indirect_call;
+mod handle_let;
+mod handle_construct;
 mod find_paths; 
+mod format_expr;
+mod export_json;
+mod summary;
+mod type_env;
+mod constants;
+mod filter;
+mod path_sampling;
+mod diagnostics;
+mod node_ids;
+mod regions;
+mod struct_invariants;
+mod skeleton;
+mod call_strategy;
+mod spec_check;
+mod phases;
+mod ssa;
+mod ffi;
+mod contract_bundle;
+mod contract_diff;
+mod annotation_lint;
+mod equiv;
+mod refines;
+mod type_assumptions;
+mod simplify;
+mod loop_summary;
+mod comment_specs;
+mod branch_hints;
+mod query;
+mod project_graph;
+mod taint;
+mod doc_samples;
+mod skip;
+mod file_attributes;
+mod stubs;
+mod dependencies;
+mod assumption_budget;
 
-pub use builder::CfgBuilder;
+pub use builder::{BuilderOptions, BuilderResults, CfgBuilder, FileFacts};
 pub use node::*;
 pub use handle_condition::*;
 pub use handle_loops::*;
@@ -15,5 +58,21 @@ pub use handle_macros::*;
 pub use handle_call::*;
 pub use handle_return::*;
 pub use find_paths::*; 
-
-
+pub use summary::*;
+pub use constants::ConstValue;
+pub use filter::*;
+pub use path_sampling::*;
+pub use diagnostics::*;
+pub use regions::*;
+pub use spec_check::{SpecCheck, SpecProblem};
+pub use phases::PhaseSnapshot;
+pub use ffi::ForeignFunction;
+pub use contract_bundle::{BundledContract, ContractBundle};
+pub use contract_diff::{ContractComparison, PublicContract};
+pub use annotation_lint::AnnotationWarning;
+pub use equiv::AlignmentStep;
+pub use loop_summary::LoopSummary;
+pub use branch_hints::BranchCounts;
+pub use project_graph::{PathTarget, ProjectEdge, ProjectGraph};
+pub use taint::TaintFlow;
+pub use doc_samples::CodeSample;
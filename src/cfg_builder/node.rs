@@ -1,5 +1,6 @@
 use proc_macro2::TokenStream;
 use quote::ToTokens;
+use serde::Serialize;
 use syn::{Expr, ExprForLoop, ExprIf, ExprReturn, ItemFn, Stmt};
 
 #[derive(Clone)]
@@ -8,6 +9,76 @@ pub enum ConditionalExpr {
     If(Box<Expr>),
     ForLoop(ExprForLoop),
     While(Box<Expr>),
+    Match(MatchCondition),
+}
+
+// Path condition of one match arm, selected by the label of the arm edge
+#[derive(Clone)]
+#[derive(Debug)]
+pub struct MatchArmCondition {
+    pub label: String,
+    pub condition: Option<Expr>, // None when the pattern can't be expressed for the solver
+}
+
+#[derive(Clone)]
+#[derive(Debug)]
+pub struct MatchCondition {
+    pub scrutinee: Box<Expr>,
+    pub enum_name: Option<String>, // set when matching over a known enum
+    pub arms: Vec<MatchArmCondition>,
+    pub exhaustive_fact: Option<Expr>, // exactly one constructor of the enum holds
+}
+
+impl MatchCondition {
+    pub fn arm_for_label(&self, label: &str) -> Option<&MatchArmCondition> {
+        self.arms.iter().find(|arm| arm.label == label)
+    }
+
+    // Arm taken through an edge leaving the match
+    pub fn arm_for_edge(&self, kind: &EdgeKind) -> Option<&MatchArmCondition> {
+        match kind {
+            EdgeKind::MatchArm(label) => self.arm_for_label(label),
+            _ => None,
+        }
+    }
+}
+
+// How control flows along an edge of the CFG. Analyses match on the kind; it becomes a label only when the
+// graph is exported.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum EdgeKind {
+    Fallthrough, // to the next statement, or to a merge point
+    TrueBranch, // condition holds: the then branch of an 'if', the body of a loop
+    FalseBranch, // condition fails: the else branch of an 'if', the exit of a loop
+    BackEdge, // from the end of a loop body back to its invariant
+    MatchArm(String), // into the arm with this pattern (and guard) label
+    PanicEdge, // into a call that never returns: the path ends there
+}
+
+impl EdgeKind {
+    // Label of the edge in the DOT and JSON exports
+    pub fn label(&self) -> String {
+        match self {
+            EdgeKind::Fallthrough => String::new(),
+            EdgeKind::TrueBranch => "true".to_string(),
+            EdgeKind::FalseBranch => "false".to_string(),
+            EdgeKind::BackEdge => "back to loop".to_string(),
+            EdgeKind::MatchArm(label) => label.clone(),
+            EdgeKind::PanicEdge => "never returns".to_string(),
+        }
+    }
+
+    // Name of the kind in the JSON export
+    pub fn kind_name(&self) -> &'static str {
+        match self {
+            EdgeKind::Fallthrough => "fallthrough",
+            EdgeKind::TrueBranch => "true_branch",
+            EdgeKind::FalseBranch => "false_branch",
+            EdgeKind::BackEdge => "back_edge",
+            EdgeKind::MatchArm(_) => "match_arm",
+            EdgeKind::PanicEdge => "panic_edge",
+        }
+    }
 }
 
 impl ConditionalExpr {
@@ -15,6 +86,7 @@ impl ConditionalExpr {
         match self {
             ConditionalExpr::If(expr) | ConditionalExpr::While(expr) => expr,
             ConditionalExpr::ForLoop(expr_for) => &expr_for.expr,
+            ConditionalExpr::Match(match_condition) => &match_condition.scrutinee,
         }
     }
 }
@@ -26,7 +98,224 @@ impl ToTokens for ConditionalExpr {
             ConditionalExpr::If(expr) => expr.to_tokens(tokens),
             ConditionalExpr::ForLoop(expr_for) => expr_for.to_tokens(tokens),
             ConditionalExpr::While(expr) => expr.to_tokens(tokens),
+            ConditionalExpr::Match(match_condition) => match_condition.scrutinee.to_tokens(tokens),
+        }
+    }
+}
+
+// Method call made by a node, with the receiver kept apart from the method and its arguments
+#[derive(Clone, Debug, Serialize)]
+pub struct MethodCallInfo {
+    pub receiver: String, // e.g. 'self.items'
+    pub receiver_root: Option<String>, // variable the receiver is reached from ('self', 'v'), if any
+    pub self_receiver: bool, // called on 'self' or on a place inside it
+    pub method: String,
+    pub args: Vec<String>,
+}
+
+// Kind of place written by an assignment
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PlaceKind {
+    Variable, // 'x = ...'
+    Field, // 'p.x = ...'
+    Index, // 'v[i] = ...'
+    Deref, // '*r = ...'
+    Other,
+}
+
+impl PlaceKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            PlaceKind::Variable => "variable",
+            PlaceKind::Field => "field",
+            PlaceKind::Index => "index",
+            PlaceKind::Deref => "deref",
+            PlaceKind::Other => "other",
+        }
+    }
+}
+
+// Assignment made by a node: the written place and the assigned value kept apart
+#[derive(Clone, Debug, Serialize)]
+pub struct AssignmentInfo {
+    pub place: String, // e.g. 'self.items[i]'
+    pub place_kind: PlaceKind,
+    pub place_root: Option<String>, // variable the place belongs to ('self'), if any
+    pub operator: String, // '=' or a compound operator ('+=')
+    pub value: String, // right-hand side as written
+}
+
+// Value built by a node from a struct, tuple-struct or enum-variant constructor, with what each field holds
+#[derive(Clone, Debug, Serialize)]
+pub struct ConstructionInfo {
+    pub place: String, // variable or field place the value is stored in
+    pub constructor: String, // e.g. 'Point', 'Some', 'Shape::Circle'
+    pub fields: Vec<FieldBinding>,
+}
+
+// 'p.x = 1' for 'p = Point { x: 1, .. }', 'r.0 = v' for 'r = Some(v)'
+#[derive(Clone, Debug, Serialize)]
+pub struct FieldBinding {
+    pub field: String,
+    pub value: String,
+}
+
+// 'as' conversion made by a node, with the types on both sides
+#[derive(Clone, Debug, Serialize)]
+pub struct CastInfo {
+    pub expr: String, // e.g. 'n as usize'
+    pub from: Option<String>, // type of the converted value, when known
+    pub to: String,
+    pub narrowing: bool, // some values of 'from' don't fit in 'to'
+}
+
+// Call that can't be resolved statically (function pointer, closure, trait object) and the contract given for it
+#[derive(Clone, Debug, Serialize)]
+pub struct IndirectCallInfo {
+    pub callee: String, // e.g. 'f', 'self.op', 'shape.area'
+    pub callee_type: Option<String>, // e.g. 'fn(i32) -> i32', '&dyn Shape'
+    pub contract: Option<String>, // where the contract comes from: 'comment' or 'conditions database'
+    pub preconditions: Vec<String>,
+    pub postconditions: Vec<String>, // 'result' is the returned value
+    pub result: Option<String>, // variable receiving the returned value
+    pub havoc: Vec<String>, // variables the call gives an unknown value: the result and the '&mut' arguments
+}
+
+// Share of the executions leaving a condition node through one of its edges, from hints or a branch profile
+#[derive(Clone, Debug, Serialize)]
+pub struct BranchProbability {
+    pub edge: String, // label of the edge: 'true', 'false' or the arm of a match
+    pub probability: f64,
+}
+
+// Boolean structure of a branch condition, with its pattern tests ('matches!(e, P)', 'let P = e' of an 'if let'
+// or a let-chain) apart from the plain boolean operands
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ConditionStructure {
+    And { operands: Vec<ConditionStructure> },
+    Or { operands: Vec<ConditionStructure> },
+    Not { operand: Box<ConditionStructure> },
+    PatternTest(PatternTest),
+    Boolean { expr: String },
+}
+
+impl ConditionStructure {
+    // Pattern tests of the condition, left to right
+    pub fn pattern_tests(&self) -> Vec<&PatternTest> {
+        match self {
+            ConditionStructure::And { operands } | ConditionStructure::Or { operands } => operands.iter().flat_map(|operand| operand.pattern_tests()).collect(),
+            ConditionStructure::Not { operand } => operand.pattern_tests(),
+            ConditionStructure::PatternTest(test) => vec![test],
+            ConditionStructure::Boolean { .. } => Vec::new(),
+        }
+    }
+}
+
+// 'matches!(o, Some(_))' or 'let Some(v) = o', and what the solver is given for it
+#[derive(Clone, Debug, Serialize)]
+pub struct PatternTest {
+    pub form: &'static str, // 'matches' or 'let'
+    pub scrutinee: String,
+    pub pattern: String, // with its guard, e.g. 'Some(x) if x > 0'
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub bindings: Vec<String>, // variables a 'let' binds for the rest of the chain and the branch
+    pub test: String, // condition on the scrutinee, e.g. 'o_tag == 1'
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub opaque: bool, // the pattern can't be expressed: 'test' is a value of its own, 'o_matches_... == 1'
+}
+
+// Where a node is written in the analyzed file: lines and columns are 1-based, the end column is the one
+// past the node, and the byte range is that of the file's text
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct SourceSpan {
+    pub line: usize,
+    pub column: usize,
+    pub end_line: usize,
+    pub end_column: usize,
+    pub byte_start: usize,
+    pub byte_end: usize,
+}
+
+// Extra information attached to a node, shown in DOT tooltips and JSON exports
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct NodeMetadata {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub span: Option<SourceSpan>, // set when the node is added, for the nodes written in the source
+    pub var_types: Vec<(String, String)>, // variables defined by the node and their types
+    pub facts: Vec<String>, // facts established by the node (e.g. match exhaustiveness)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub method_call: Option<MethodCallInfo>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub assignment: Option<AssignmentInfo>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub casts: Vec<CastInfo>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub indirect_call: Option<IndirectCallInfo>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub construction: Option<ConstructionInfo>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub branch_probabilities: Vec<BranchProbability>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub intervals: Vec<String>, // ranges of the numeric variables on entry, e.g. 'i in [1, +inf)'
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub call_precondition: Option<String>, // callee of the file whose precondition the node checks
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub condition: Option<ConditionStructure>, // set on conditions with pattern tests or '&&', '||', '!'
+}
+
+impl NodeMetadata {
+    pub fn is_empty(&self) -> bool {
+        self.span.is_none() && self.var_types.is_empty() && self.facts.is_empty() && self.method_call.is_none() && self.assignment.is_none() && self.casts.is_empty()
+            && self.indirect_call.is_none() && self.construction.is_none() && self.branch_probabilities.is_empty() && self.intervals.is_empty()
+            && self.call_precondition.is_none() && self.condition.is_none()
+    }
+
+    // Lines shown in the DOT tooltip below the full label
+    pub fn tooltip_lines(&self) -> Vec<String> {
+        let mut lines: Vec<String> = self.span.iter()
+            .map(|span| format!("source: {}:{}-{}:{} (bytes {}..{})", span.line, span.column, span.end_line, span.end_column, span.byte_start, span.byte_end))
+            .collect();
+        lines.extend(self.var_types.iter().map(|(var, ty)| format!("{}: {}", var, ty)));
+        lines.extend(self.facts.iter().map(|fact| format!("fact: {}", fact)));
+        if let Some(call) = &self.method_call {
+            let on_self = if call.self_receiver { " (self)" } else { "" };
+            lines.push(format!("method: {} on {}{}", call.method, call.receiver, on_self));
         }
+        if let Some(assignment) = &self.assignment {
+            lines.push(format!("writes: {} ({})", assignment.place, assignment.place_kind.name()));
+        }
+        for cast in &self.casts {
+            let from = cast.from.as_deref().unwrap_or("?");
+            let narrowing = if cast.narrowing { ", narrowing" } else { "" };
+            lines.push(format!("cast: {} ({} -> {}{})", cast.expr, from, cast.to, narrowing));
+        }
+        if let Some(call) = &self.indirect_call {
+            let callee_type = call.callee_type.as_ref().map(|ty| format!(" ({})", ty)).unwrap_or_default();
+            let contract = match &call.contract {
+                Some(source) => format!("contract from {}", source),
+                None => format!("no contract, havoc {}", call.havoc.join(", ")),
+            };
+            lines.push(format!("indirect call: {}{}, {}", call.callee, callee_type, contract));
+        }
+        if let Some(construction) = &self.construction {
+            lines.push(format!("constructs: {} in {}", construction.constructor, construction.place));
+            lines.extend(construction.fields.iter().map(|binding| format!("field: {} = {}", binding.field, binding.value)));
+        }
+        for branch in &self.branch_probabilities {
+            lines.push(format!("branch {}: {:.0}%", branch.edge, branch.probability * 100.0));
+        }
+        lines.extend(self.intervals.iter().map(|range| format!("range: {}", range)));
+        for test in self.condition.iter().flat_map(|condition| condition.pattern_tests()) {
+            let binds = if test.bindings.is_empty() { String::new() } else { format!(", binds {}", test.bindings.join(", ")) };
+            let written = match test.form {
+                "let" => format!("let {} = {}", test.pattern, test.scrutinee),
+                _ => format!("matches!({}, {})", test.scrutinee, test.pattern),
+            };
+            lines.push(format!("pattern test: {}{} ({})", written, binds, test.test));
+        }
+        lines
     }
 }
 
@@ -35,8 +324,11 @@ impl ToTokens for ConditionalExpr {
 pub enum CfgNode {
     Function(String, Option<ItemFn>),
     Precondition(String, Option<Expr>),
+    Assumption(String, Option<Expr>), // fact implied by a parameter type, assumed on entry like a 'pre!'
     Postcondition(String, Option<Expr>),
     Invariant(String, Option<Expr>),
+    Assertion(String, Option<Expr>), // checked where the path reaches it, then assumed; unlike an invariant, not a cut point
+    Decreases(String, Option<Expr>), // measure of the loop whose head it follows, 'decreases!(n - i)'
     Statement(String, Option<Stmt>),
     Cutoff(String),
     Condition(String, Option<ConditionalExpr>),
@@ -45,20 +337,103 @@ pub enum CfgNode {
 }
 
 impl CfgNode {
-    pub fn format_dot(&self, index: usize) -> String {
-        let (label, shape) = match self {
+    pub fn format_dot(&self, id: &str) -> String {
+        self.format_dot_truncated(id, None)
+    }
+
+    // Format the node for DOT, shortening long labels and keeping the full text in the tooltip
+    pub fn format_dot_truncated(&self, id: &str, max_label_len: Option<usize>) -> String {
+        self.format_dot_with_tooltip(id, max_label_len, &[])
+    }
+
+    // Same as 'format_dot_truncated', with extra tooltip lines (e.g. from the node metadata)
+    pub fn format_dot_with_tooltip(&self, id: &str, max_label_len: Option<usize>, tooltip_lines: &[String]) -> String {
+        self.format_dot_labeled(id, &self.label(), max_label_len, tooltip_lines)
+    }
+
+    // Same as 'format_dot_with_tooltip', showing 'label' instead of the label of the node (e.g. its SSA form)
+    pub fn format_dot_labeled(&self, id: &str, label: &str, max_label_len: Option<usize>, tooltip_lines: &[String]) -> String {
+        let (label, shape) = (label.to_string(), self.label_and_shape().1);
+        let shown_label = match max_label_len {
+            Some(max_len) => Self::truncate_label(&label, max_len),
+            None => label.clone(),
+        };
+        if shown_label == label && tooltip_lines.is_empty() {
+            return format!("\"{}\" [label=\"{}\", shape={}]", id, self.escape_quotes_for_dot(&label), shape);
+        }
+
+        let mut tooltip = vec![label];
+        tooltip.extend(tooltip_lines.iter().cloned());
+        let tooltip = tooltip.iter()
+            .map(|line| self.escape_quotes_for_dot(line))
+            .collect::<Vec<_>>()
+            .join("\\n");
+        format!(
+            "\"{}\" [label=\"{}\", shape={}, tooltip=\"{}\"]",
+            id,
+            self.escape_quotes_for_dot(&shown_label),
+            shape,
+            tooltip
+        )
+    }
+
+    // Full display label of the node, as shown in exports
+    pub fn label(&self) -> String {
+        self.label_and_shape().0
+    }
+
+    // Node of a Mermaid flowchart, in the shape of its DOT counterpart
+    pub fn format_mermaid(&self, id: &str, label: &str) -> String {
+        let label = label.replace('"', "#quot;").replace('\n', " ");
+        match self.label_and_shape().1 {
+            "box" => format!("{}[\"{}\"]", id, label),
+            "diamond" => format!("{}{{\"{}\"}}", id, label),
+            "Mdiamond" => format!("{}{{{{\"{}\"}}}}", id, label),
+            "circle" => format!("{}((\" \"))", id),
+            _ => format!("{}([\"{}\"])", id, label),
+        }
+    }
+
+    pub fn kind_name(&self) -> &'static str {
+        match self {
+            CfgNode::Function(_, _) => "function",
+            CfgNode::Precondition(_, _) => "precondition",
+            CfgNode::Assumption(_, _) => "assumption",
+            CfgNode::Postcondition(_, _) => "postcondition",
+            CfgNode::Invariant(_, _) => "invariant",
+            CfgNode::Assertion(_, _) => "assertion",
+            CfgNode::Decreases(_, _) => "decreases",
+            CfgNode::Statement(_, _) => "statement",
+            CfgNode::Cutoff(_) => "cutoff",
+            CfgNode::Condition(_, _) => "condition",
+            CfgNode::Return(_, _) => "return",
+            CfgNode::MergePoint => "merge",
+        }
+    }
+
+    pub fn truncate_label(label: &str, max_len: usize) -> String {
+        if label.chars().count() <= max_len {
+            return label.to_string();
+        }
+        let kept: String = label.chars().take(max_len.saturating_sub(1)).collect();
+        format!("{}…", kept.trim_end())
+    }
+
+    fn label_and_shape(&self) -> (String, &'static str) {
+        match self {
             CfgNode::Function(func, _) => (func.clone(), "Mdiamond"),
             CfgNode::Precondition(pre, _) => (format!("Pre: {}", pre), "ellipse"),
+            CfgNode::Assumption(fact, _) => (format!("Assume: {}", fact), "ellipse"),
             CfgNode::Postcondition(post, _) => (format!("Post: {}", post), "ellipse"),
             CfgNode::Invariant(inv, _) => (format!("@Inv: {}", inv), "ellipse"),
+            CfgNode::Assertion(condition, _) => (format!("Assert: {}", condition), "ellipse"),
+            CfgNode::Decreases(measure, _) => (format!("@Decreases: {}", measure), "ellipse"),
             CfgNode::Statement(stmt, _) => (stmt.clone(), "box"),
             CfgNode::Condition(cond, _) => (cond.clone(), "diamond"),
             CfgNode::Cutoff(inv) => (format!("@Cutoff {}", inv), "ellipse"),
             CfgNode::MergePoint => (String::from("Merge"), "circle"),
             CfgNode::Return(ret, _) => (format!("return: {}", ret), "ellipse"),
-        };
-
-        format!("{} [label=\"{}\", shape={}]", index, self.escape_quotes_for_dot(&label), shape)
+        }
     }
 
     pub fn new_function(func_name: String, item_fn: ItemFn) -> Self {
@@ -69,6 +444,10 @@ impl CfgNode {
         CfgNode::Precondition(pre, Some(expr))
     }
 
+    pub fn new_assumption(fact: String, expr: Option<Expr>) -> Self {
+        CfgNode::Assumption(fact, expr)
+    }
+
     pub fn new_postcondition(post: String, expr: Expr) -> Self {
         CfgNode::Postcondition(post, Some(expr))
     }
@@ -77,6 +456,14 @@ impl CfgNode {
         CfgNode::Invariant(inv, Some(expr))
     }
 
+    pub fn new_assertion(condition: String, expr: Expr) -> Self {
+        CfgNode::Assertion(condition, Some(expr))
+    }
+
+    pub fn new_decreases(measure: String, expr: Expr) -> Self {
+        CfgNode::Decreases(measure, Some(expr))
+    }
+
     pub fn new_statement(stmt_str: String, stmt: Stmt) -> Self {
         CfgNode::Statement(stmt_str, Some(stmt))
     }
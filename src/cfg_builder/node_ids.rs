@@ -0,0 +1,185 @@
+use petgraph::graph::NodeIndex;
+use petgraph::visit::EdgeRef;
+use proc_macro2::{Delimiter, Span, TokenTree};
+use quote::ToTokens;
+use std::collections::{HashMap, HashSet, VecDeque};
+use syn::spanned::Spanned;
+
+use crate::cfg_builder::builder::CfgBuilder;
+use crate::cfg_builder::node::{CfgNode, ConditionalExpr, SourceSpan};
+use crate::report::fnv1a;
+
+impl CfgBuilder {
+    // Give every node an ID that only depends on the source: '<function>:<kind>@<line>:<column>'.
+    // Nodes without source (merge points, cut points, loop counters) are named after the node
+    // they follow ('<previous id>/<kind>'), and repeated IDs get a '#2', '#3', ... suffix.
+    pub fn assign_node_ids(&mut self) {
+        let mut ids: HashMap<NodeIndex, String> = HashMap::new();
+        let mut used: HashSet<String> = HashSet::new();
+
+        for func_node in self.function_nodes() {
+            let function = self.graph[func_node].label();
+            let mut queue = VecDeque::from([(func_node, None::<String>)]);
+            while let Some((node, previous)) = queue.pop_front() {
+                if ids.contains_key(&node) {
+                    continue;
+                }
+                let base = match (self.node_location(node), previous) {
+                    (Some((line, column)), _) => format!("{}:{}@{}:{}", function, self.graph[node].kind_name(), line, column),
+                    (None, Some(previous)) => format!("{}/{}", previous, self.graph[node].kind_name()),
+                    (None, None) => format!("{}:{}", function, self.graph[node].kind_name()),
+                };
+                let id = Self::unique_id(base, &mut used);
+
+                // Successors in source order, so that the naming of source-less nodes doesn't depend on insertion order
+                let mut successors: Vec<NodeIndex> = self.graph.edges(node).map(|edge| edge.target()).collect();
+                successors.sort_by_key(|&successor| (self.node_location(successor), self.graph[successor].kind_name()));
+                for successor in successors {
+                    queue.push_back((successor, Some(id.clone())));
+                }
+                ids.insert(node, id);
+            }
+        }
+
+        // Unreachable code (e.g. after a 'return') still gets an ID from its location
+        for node in self.graph.node_indices() {
+            if let std::collections::hash_map::Entry::Vacant(entry) = ids.entry(node) {
+                let base = match self.node_location(node) {
+                    Some((line, column)) => format!("{}@{}:{}", self.graph[node].kind_name(), line, column),
+                    None => self.graph[node].kind_name().to_string(),
+                };
+                entry.insert(Self::unique_id(base, &mut used));
+            }
+        }
+        self.results.node_ids = ids;
+    }
+
+    // Name of an item of the module being visited, with the path of its inline modules
+    pub fn qualified_name(&self, name: &str) -> String {
+        self.module_path.iter().map(String::as_str).chain(std::iter::once(name)).collect::<Vec<_>>().join("::")
+    }
+
+    // Canonical identifier of an analyzed function: its qualified name and a hash of its signature,
+    // 'geometry::Shape::area#1b2c3d4e'. Used wherever functions are keyed (reports, manifests, clusters).
+    pub fn function_id(&self, func_node: NodeIndex) -> String {
+        let signature = match &self.graph[func_node] {
+            CfgNode::Function(_, Some(item_fn)) => item_fn.sig.to_token_stream().to_string(),
+            _ => String::new(),
+        };
+        format!("{}#{:08x}", self.graph[func_node].label(), fnv1a(signature.as_bytes()) as u32)
+    }
+
+    // Canonical identifier of the function whose CFG contains the node
+    pub fn function_id_of_node(&self, node: NodeIndex) -> Option<String> {
+        self.function_nodes().into_iter()
+            .find(|&func_node| self.function_subgraph_nodes(func_node).contains(&node))
+            .map(|func_node| self.function_id(func_node))
+    }
+
+    // ID used in the exports; nodes added after 'assign_node_ids' fall back to their index
+    pub fn node_id(&self, node: NodeIndex) -> String {
+        self.results.node_ids.get(&node).cloned().unwrap_or_else(|| format!("n{}", node.index()))
+    }
+
+    fn unique_id(base: String, used: &mut HashSet<String>) -> String {
+        let mut id = base.clone();
+        let mut suffix = 2;
+        while !used.insert(id.clone()) {
+            id = format!("{}#{}", base, suffix);
+            suffix += 1;
+        }
+        id
+    }
+
+    // Line and column (1-based) where the source of the node starts
+    pub fn node_location(&self, node: NodeIndex) -> Option<(usize, usize)> {
+        let start = self.syntax_span(node)?.start();
+        // Spans created by the analysis itself point nowhere
+        if start.line == 0 {
+            return None;
+        }
+        Some((start.line, start.column + 1))
+    }
+
+    // Span of the syntax the node holds. A condition spans its tokens, but starts where 'node_location' does.
+    fn syntax_span(&self, node: NodeIndex) -> Option<Span> {
+        let span: Span = match &self.graph[node] {
+            CfgNode::Function(_, Some(item_fn)) => item_fn.sig.ident.span(),
+            CfgNode::Precondition(_, Some(expr)) | CfgNode::Postcondition(_, Some(expr)) | CfgNode::Invariant(_, Some(expr)) | CfgNode::Decreases(_, Some(expr))
+            | CfgNode::Assertion(_, Some(expr)) => expr.span(),
+            CfgNode::Statement(_, Some(stmt)) => stmt.span(),
+            CfgNode::Condition(_, Some(ConditionalExpr::Match(match_condition))) => match_condition.scrutinee.span(),
+            CfgNode::Condition(_, Some(conditional_expr)) => {
+                let mut tokens = conditional_expr.to_token_stream().into_iter();
+                let first = tokens.next()?.span();
+                tokens.last().and_then(|last| first.join(last.span())).unwrap_or(first)
+            },
+            // The implicit return of a tail expression has no 'return' keyword in the source
+            CfgNode::Return(_, Some(expr_return)) => match &expr_return.expr {
+                Some(expr) => expr.span(),
+                None => expr_return.span(),
+            },
+            _ => return None,
+        };
+        Some(span)
+    }
+
+    // Where the node is written in the analyzed file. Conditions the analysis writes itself (struct invariants,
+    // inlined contracts, facts of the parameter types) are parsed from strings of their own, so their span
+    // counts only where the source spells out the node's first token.
+    pub fn source_span(&self, node: NodeIndex) -> Option<SourceSpan> {
+        let span = match &self.graph[node] {
+            CfgNode::Assumption(_, Some(expr)) => expr.span(),
+            _ => self.syntax_span(node)?,
+        };
+        let (start, end) = (span.start(), span.end());
+        if start.line == 0 {
+            return None;
+        }
+        let tokens = match &self.graph[node] {
+            CfgNode::Function(_, Some(item_fn)) => item_fn.sig.ident.to_token_stream(),
+            CfgNode::Precondition(_, Some(expr)) | CfgNode::Postcondition(_, Some(expr)) | CfgNode::Invariant(_, Some(expr)) | CfgNode::Decreases(_, Some(expr))
+            | CfgNode::Assumption(_, Some(expr)) | CfgNode::Assertion(_, Some(expr)) => expr.to_token_stream(),
+            CfgNode::Statement(_, Some(stmt)) => stmt.to_token_stream(),
+            CfgNode::Condition(_, Some(ConditionalExpr::Match(match_condition))) => match_condition.scrutinee.to_token_stream(),
+            CfgNode::Condition(_, Some(conditional_expr)) => conditional_expr.to_token_stream(),
+            CfgNode::Return(_, Some(expr_return)) => match &expr_return.expr {
+                Some(expr) => expr.to_token_stream(),
+                None => expr_return.to_token_stream(),
+            },
+            _ => return None,
+        };
+        let source: String = self.file.source_lines.get(start.line - 1)?.chars().skip(start.column).collect();
+        let spelled = match tokens.into_iter().next()? {
+            TokenTree::Group(group) => match group.delimiter() {
+                Delimiter::Parenthesis => source.starts_with('('),
+                Delimiter::Brace => source.starts_with('{'),
+                Delimiter::Bracket => source.starts_with('['),
+                Delimiter::None => true,
+            },
+            // Annotations are stored under the built-in name of their role, the source may use a custom one
+            TokenTree::Ident(ident) if ["pre", "post", "invariant", "decreases"].contains(&ident.to_string().as_str()) => {
+                let name: String = source.chars().take_while(|&c| c.is_alphanumeric() || c == '_').collect();
+                source[name.len()..].starts_with('!') && self.options.annotation_names.role_of(&name).is_some()
+            },
+            token => source.starts_with(&token.to_string()),
+        };
+        if !spelled {
+            return None;
+        }
+        // Offset of a line and column, with '\n' line ends
+        let offset = |line: usize, column: usize| -> Option<usize> {
+            let text = self.file.source_lines.get(line - 1)?;
+            let line_start: usize = self.file.source_lines.iter().take(line - 1).map(|text| text.len() + 1).sum();
+            Some(line_start + text.char_indices().nth(column).map(|(index, _)| index).unwrap_or(text.len()))
+        };
+        Some(SourceSpan {
+            line: start.line,
+            column: start.column + 1,
+            end_line: end.line,
+            end_column: end.column + 1,
+            byte_start: offset(start.line, start.column)?,
+            byte_end: offset(end.line, end.column)?,
+        })
+    }
+}
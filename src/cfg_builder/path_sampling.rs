@@ -0,0 +1,185 @@
+use petgraph::graph::{EdgeIndex, NodeIndex};
+use petgraph::visit::EdgeRef;
+use std::collections::HashSet;
+use std::fmt;
+
+use crate::cfg_builder::builder::CfgBuilder;
+use crate::cfg_builder::node::CfgNode;
+
+// How paths are picked when a file has more basic paths than '--sample-paths' allows
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum SamplingStrategy {
+    #[default]
+    BranchCoverage, // greedily take the path covering the most branch edges not covered yet
+    Random,
+}
+
+impl SamplingStrategy {
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name {
+            "greedy" | "branch-coverage" => Ok(SamplingStrategy::BranchCoverage),
+            "random" => Ok(SamplingStrategy::Random),
+            _ => Err(format!("unknown sampling strategy '{}' (expected 'greedy' or 'random')", name)),
+        }
+    }
+}
+
+// Coverage of the whole-file CFG achieved by a set of paths
+#[derive(Debug, Clone)]
+pub struct PathCoverage {
+    pub sampled_paths: usize,
+    pub total_paths: usize,
+    pub branch_edges_covered: usize,
+    pub branch_edges_total: usize,
+    pub nodes_covered: usize,
+    pub nodes_total: usize,
+}
+
+impl fmt::Display for PathCoverage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let percent = |covered: usize, total: usize| if total == 0 { 100.0 } else { covered as f64 * 100.0 / total as f64 };
+        write!(
+            f,
+            "{} of {} paths, branch edges {}/{} ({:.1}%), nodes {}/{} ({:.1}%)",
+            self.sampled_paths,
+            self.total_paths,
+            self.branch_edges_covered,
+            self.branch_edges_total,
+            percent(self.branch_edges_covered, self.branch_edges_total),
+            self.nodes_covered,
+            self.nodes_total,
+            percent(self.nodes_covered, self.nodes_total),
+        )
+    }
+}
+
+impl CfgBuilder {
+    // Keep at most 'limit' paths, picked with the given strategy, in their original order
+    pub fn sample_paths(&self, paths: &[Vec<NodeIndex>], limit: usize, strategy: SamplingStrategy) -> Vec<Vec<NodeIndex>> {
+        if paths.len() <= limit {
+            return paths.to_vec();
+        }
+        let mut chosen = match strategy {
+            SamplingStrategy::BranchCoverage => self.greedy_branch_sample(paths, limit),
+            SamplingStrategy::Random => Self::random_sample(paths.len(), limit),
+        };
+        chosen.sort();
+        chosen.into_iter().map(|i| paths[i].clone()).collect()
+    }
+
+    pub fn path_coverage(&self, sample: &[Vec<NodeIndex>], total_paths: usize) -> PathCoverage {
+        let branch_edges = self.branch_edges();
+        let covered_edges: HashSet<EdgeIndex> = sample.iter().flat_map(|path| self.path_edges(path)).collect();
+        let covered_nodes: HashSet<NodeIndex> = sample.iter().flatten().copied().collect();
+        PathCoverage {
+            sampled_paths: sample.len(),
+            total_paths,
+            branch_edges_covered: branch_edges.intersection(&covered_edges).count(),
+            branch_edges_total: branch_edges.len(),
+            nodes_covered: covered_nodes.len(),
+            nodes_total: self.graph.node_count(),
+        }
+    }
+
+    fn greedy_branch_sample(&self, paths: &[Vec<NodeIndex>], limit: usize) -> Vec<usize> {
+        let branch_edges = self.branch_edges();
+        let path_edges: Vec<HashSet<EdgeIndex>> = paths.iter()
+            .map(|path| self.path_edges(path).into_iter().filter(|e| branch_edges.contains(e)).collect())
+            .collect();
+
+        let mut covered_edges: HashSet<EdgeIndex> = HashSet::new();
+        let mut covered_nodes: HashSet<NodeIndex> = HashSet::new();
+        let mut chosen = Vec::new();
+        while chosen.len() < limit {
+            // New branch edges first, then new nodes; ties go to the earlier path
+            let best = (0..paths.len())
+                .filter(|i| !chosen.contains(i))
+                .max_by_key(|&i| {
+                    let new_edges = path_edges[i].difference(&covered_edges).count();
+                    let new_nodes = paths[i].iter().filter(|n| !covered_nodes.contains(n)).count();
+                    (new_edges, new_nodes, std::cmp::Reverse(i))
+                });
+            match best {
+                Some(i) => {
+                    covered_edges.extend(path_edges[i].iter().copied());
+                    covered_nodes.extend(paths[i].iter().copied());
+                    chosen.push(i);
+                },
+                None => break,
+            }
+        }
+        chosen
+    }
+
+    // Deterministic pseudo-random choice (xorshift), so runs on the same file pick the same paths
+    fn random_sample(count: usize, limit: usize) -> Vec<usize> {
+        let mut indices: Vec<usize> = (0..count).collect();
+        let mut state: u64 = 0x2545_F491_4F6C_DD1D;
+        for i in (1..indices.len()).rev() {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            indices.swap(i, (state % (i as u64 + 1)) as usize);
+        }
+        indices.truncate(limit);
+        indices
+    }
+
+    // Edges leaving condition nodes: the branches a path can take
+    fn branch_edges(&self) -> HashSet<EdgeIndex> {
+        self.graph.edge_references()
+            .filter(|edge| matches!(self.graph[edge.source()], CfgNode::Condition(_, _)))
+            .map(|edge| edge.id())
+            .collect()
+    }
+
+    fn path_edges(&self, path: &[NodeIndex]) -> Vec<EdgeIndex> {
+        path.windows(2)
+            .filter_map(|pair| self.graph.edges_connecting(pair[0], pair[1]).next().map(|edge| edge.id()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::built;
+
+    // Three 'if's in a row: eight basic paths over six branch edges
+    fn three_branches() -> (CfgBuilder, Vec<Vec<NodeIndex>>) {
+        let mut builder = built("fn f(a: i32, b: i32, c: i32) -> i32 {
+            pre!(true);
+            post!(r >= 0);
+            let mut r = 0;
+            if a > 0 { r = r + 1; } else { r = r + 2; }
+            if b > 0 { r = r + 1; } else { r = r + 2; }
+            if c > 0 { r = r + 1; } else { r = r + 2; }
+            return r;
+        }");
+        let paths = builder.generate_basic_paths();
+        (builder, paths)
+    }
+
+    #[test]
+    fn two_greedy_paths_cover_every_branch() {
+        let (builder, paths) = three_branches();
+        assert_eq!(paths.len(), 8);
+        let sample = builder.sample_paths(&paths, 2, SamplingStrategy::BranchCoverage);
+        let coverage = builder.path_coverage(&sample, paths.len());
+        assert_eq!((coverage.sampled_paths, coverage.total_paths), (2, 8));
+        assert_eq!((coverage.branch_edges_covered, coverage.branch_edges_total), (6, 6));
+    }
+
+    #[test]
+    fn samples_keep_the_order_of_the_paths() {
+        let (builder, paths) = three_branches();
+        for strategy in [SamplingStrategy::BranchCoverage, SamplingStrategy::Random] {
+            let sample = builder.sample_paths(&paths, 3, strategy);
+            let positions: Vec<usize> = sample.iter().map(|path| paths.iter().position(|p| p == path).unwrap()).collect();
+            assert_eq!(positions.len(), 3);
+            assert!(positions.windows(2).all(|pair| pair[0] < pair[1]), "{:?}", positions);
+        }
+        // Under the limit every path is kept
+        assert_eq!(builder.sample_paths(&paths, 8, SamplingStrategy::Random), paths);
+    }
+}
@@ -0,0 +1,140 @@
+use petgraph::graph::NodeIndex;
+use proc_macro2::{TokenStream, TokenTree};
+use quote::quote;
+use syn::{BinOp, Expr, ExprBinary, ExprMatch, ExprParen, ExprUnary, Pat, UnOp};
+
+use crate::cfg_builder::builder::CfgBuilder;
+use crate::cfg_builder::node::{ConditionStructure, PatternTest};
+
+impl CfgBuilder {
+    // Condition of an 'if' or a 'while' as the solver gets it, and its structure for the node metadata. Each
+    // pattern test becomes a condition on its scrutinee and the rest is kept as written:
+    // 'matches!(o, Some(_)) && y > 0' is '(o_tag == 1) && y > 0', 'let Some(v) = o && v > 0' is
+    // '(o_tag == 1) && v > 0' with 'v' left unconstrained.
+    pub(crate) fn lower_condition(&self, cond: &Expr) -> (Expr, ConditionStructure) {
+        match cond {
+            Expr::Binary(binary) if matches!(binary.op, BinOp::And(_) | BinOp::Or(_)) => {
+                let (left, left_structure) = self.lower_condition(&binary.left);
+                let (right, right_structure) = self.lower_condition(&binary.right);
+                let is_and = matches!(binary.op, BinOp::And(_));
+                // 'a && b && c' is one conjunction of three operands
+                let mut operands = Vec::new();
+                for structure in [left_structure, right_structure] {
+                    match structure {
+                        ConditionStructure::And { operands: inner } if is_and => operands.extend(inner),
+                        ConditionStructure::Or { operands: inner } if !is_and => operands.extend(inner),
+                        structure => operands.push(structure),
+                    }
+                }
+                let lowered = Expr::Binary(ExprBinary { left: Box::new(left), right: Box::new(right), ..binary.clone() });
+                let structure = if is_and { ConditionStructure::And { operands } } else { ConditionStructure::Or { operands } };
+                (lowered, structure)
+            },
+            Expr::Unary(unary) if matches!(unary.op, UnOp::Not(_)) => {
+                let (operand, structure) = self.lower_condition(&unary.expr);
+                (Expr::Unary(ExprUnary { expr: Box::new(operand), ..unary.clone() }), ConditionStructure::Not { operand: Box::new(structure) })
+            },
+            Expr::Paren(paren) => {
+                let (inner, structure) = self.lower_condition(&paren.expr);
+                (Expr::Paren(ExprParen { expr: Box::new(inner), ..paren.clone() }), structure)
+            },
+            Expr::Let(expr_let) => self.pattern_test("let", &expr_let.pat, None, &expr_let.expr),
+            Expr::Macro(expr_macro) => match Self::matches_arguments(&expr_macro.mac) {
+                Some((scrutinee, pat, guard)) => self.pattern_test("matches", &pat, guard.as_ref(), &scrutinee),
+                None => (cond.clone(), ConditionStructure::Boolean { expr: Self::render_expr(cond) }),
+            },
+            _ => (cond.clone(), ConditionStructure::Boolean { expr: Self::render_expr(cond) }),
+        }
+    }
+
+    // Test of a pattern (and guard) against a value, in parentheses. A pattern the solver can't express is a
+    // value of its own, as in an unconstrained match arm, so that the test and its negation both stay possible.
+    fn pattern_test(&self, form: &'static str, pat: &Pat, guard: Option<&Expr>, scrutinee: &Expr) -> (Expr, ConditionStructure) {
+        let expr_match: ExprMatch = syn::parse_quote!(match #scrutinee { #pat => {} });
+        let enum_name = self.match_enum_name(&expr_match);
+        let scrutinee_name = Self::sanitize_identifier(&Self::render_expr(scrutinee));
+        let condition = self.pattern_condition(pat, scrutinee, enum_name.as_deref(), &Self::tag_variable(scrutinee));
+        let mut opaque = condition.is_none();
+        let havoc = format!("{}_matches_{} == 1", scrutinee_name, Self::sanitize_identifier(&Self::render_pat(pat)));
+        // A test of the constructor alone holds for more values than the pattern matches ('Some(0)' is
+        // 'o_tag == 1'): the rest of the pattern is a value of its own, so the negation doesn't exclude them
+        let mut test = match condition {
+            Some(condition) if !self.pattern_is_exact(pat) => format!("({}) && ({})", condition, havoc),
+            Some(condition) => condition,
+            None => havoc.clone(),
+        };
+        let mut pattern = Self::render_pat(pat);
+        if let Some(guard) = guard {
+            let guard = Self::render_expr(guard);
+            pattern = format!("{} if {}", pattern, guard);
+            test = if test == "true" { guard } else { format!("({}) && ({})", test, guard) };
+        }
+        // A test that doesn't parse back leaves the arm condition unconstrained, as for an opaque pattern
+        let lowered = match syn::parse_str::<Expr>(&format!("({})", test)) {
+            Ok(lowered) => lowered,
+            Err(_) => {
+                eprintln!("Warning: can't parse the test '{}' of the pattern '{}', the arm condition is havocked", test, pattern);
+                (test, opaque) = (havoc, true);
+                syn::parse_str::<Expr>(&format!("({})", test)).unwrap_or_else(|_| syn::parse_quote!((pattern_matches == 1)))
+            },
+        };
+        // Names starting with a capital are unit variants or constants, not bindings
+        let bindings = match form {
+            "let" => Self::pattern_bindings(pat).into_iter().filter(|name| !name.starts_with(char::is_uppercase)).collect(),
+            _ => Vec::new(),
+        };
+        let structure = ConditionStructure::PatternTest(PatternTest {
+            form,
+            scrutinee: Self::render_expr(scrutinee),
+            pattern,
+            bindings,
+            test,
+            opaque,
+        });
+        (lowered, structure)
+    }
+
+    // Value, pattern and guard of 'matches!(value, pattern if guard)'
+    pub(crate) fn matches_arguments(mac: &syn::Macro) -> Option<(Expr, Pat, Option<Expr>)> {
+        mac.path.segments.last().filter(|segment| segment.ident == "matches")?;
+        let is_comma = |token: &TokenTree| matches!(token, TokenTree::Punct(punct) if punct.as_char() == ',');
+        let tokens: Vec<TokenTree> = mac.tokens.clone().into_iter().collect();
+        let comma = tokens.iter().position(is_comma)?;
+        let scrutinee: TokenStream = tokens[..comma].iter().cloned().collect();
+        let mut pattern = tokens[comma + 1..].to_vec();
+        if pattern.last().is_some_and(is_comma) {
+            pattern.pop();
+        }
+        let pattern: TokenStream = pattern.into_iter().collect();
+        let arm: syn::Arm = syn::parse2(quote!(#pattern => {})).ok()?;
+        Some((syn::parse2(scrutinee).ok()?, arm.pat, arm.guard.map(|(_, guard)| *guard)))
+    }
+
+    // Structure of a condition in the metadata of its node, unless it is a single boolean operand
+    pub(crate) fn record_condition_structure(&mut self, node: NodeIndex, structure: ConditionStructure) {
+        if !matches!(structure, ConditionStructure::Boolean { .. }) {
+            self.metadata_mut(node).condition = Some(structure);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_support::verdicts;
+    use crate::verifier::VcOutcome;
+
+    #[test]
+    fn a_value_failing_a_field_test_can_still_match_the_constructor() {
+        let outcomes = verdicts("fn pick(o: Option<u32>) -> u32 {
+                pre!(true);
+                post!(r != 2);
+                let mut r = 1;
+                if !matches!(o, Some(0)) && matches!(o, Some(_)) {
+                    r = 2;
+                }
+                return r;
+            }");
+        assert_eq!(outcomes.iter().filter(|outcome| matches!(outcome, VcOutcome::Counterexample(_))).count(), 1);
+        assert_eq!(outcomes.iter().filter(|outcome| matches!(outcome, VcOutcome::Valid)).count(), 1);
+    }
+}
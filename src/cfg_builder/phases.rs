@@ -0,0 +1,53 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::cfg_builder::builder::CfgBuilder;
+
+// The graph as a builder phase left it, kept by '--debug-phases'
+#[derive(Debug, Clone)]
+pub struct PhaseSnapshot {
+    pub phase: String, // e.g. 'visited', 'post_processed'
+    pub dot: String,
+}
+
+impl CfgBuilder {
+    // Keep the DOT of the graph after a phase, when the phases are debugged. Nodes are named 'n<index>'
+    // until the node IDs are assigned, and summarized regions are dashed edges from their entry to their exit.
+    pub fn snapshot_phase(&mut self, phase: &str) {
+        if !self.options.debug_phases {
+            return;
+        }
+        let mut dot = self.to_dot();
+        let mut regions: Vec<String> = self.results.region_summaries.values()
+            .map(|region| format!("\"{}\" -> \"{}\" [label=\"region ({} paths)\", style=dashed, constraint=false];\n",
+                self.node_id(region.entry), self.node_id(region.exit), region.paths.len()))
+            .collect();
+        regions.sort();
+        if let Some(closing) = dot.rfind('}') {
+            dot.insert_str(closing, &regions.concat());
+        }
+        self.results.phase_snapshots.push(PhaseSnapshot { phase: phase.to_string(), dot });
+    }
+
+    // Keep the DOT of the graph in SSA form ('ssa_labels'), when the phases are debugged
+    pub fn snapshot_ssa(&mut self) {
+        if !self.options.debug_phases {
+            return;
+        }
+        let labels = self.ssa_labels();
+        let dot = self.to_dot_labeled(|node| labels.get(&node).cloned().unwrap_or_else(|| self.graph[node].label()));
+        self.results.phase_snapshots.push(PhaseSnapshot { phase: "ssa".to_string(), dot });
+    }
+
+    // Write the snapshots as '<dir>/01_visited.dot', '<dir>/02_post_processed.dot', ... in phase order
+    pub fn write_phase_snapshots(&self, dir: &Path) -> std::io::Result<Vec<PathBuf>> {
+        fs::create_dir_all(dir)?;
+        let mut files = Vec::new();
+        for (i, snapshot) in self.results.phase_snapshots.iter().enumerate() {
+            let file = dir.join(format!("{:02}_{}.dot", i + 1, snapshot.phase));
+            fs::write(&file, &snapshot.dot)?;
+            files.push(file);
+        }
+        Ok(files)
+    }
+}
@@ -0,0 +1,254 @@
+use petgraph::graph::{DiGraph, NodeIndex};
+use petgraph::visit::EdgeRef;
+use std::collections::{HashMap, VecDeque};
+use std::path::Path;
+use syn::visit::{self, Visit};
+use syn::{Expr, ExprCall, ExprMacro, ExprMethodCall, ItemMacro};
+
+use crate::cfg_builder::builder::CfgBuilder;
+use crate::cfg_builder::node::{CfgNode, EdgeKind};
+
+// Macros and methods that panic on some input: the panic sites of 'project-graph --to panic'
+const PANIC_MACROS: [&str; 4] = ["panic", "unreachable", "todo", "unimplemented"];
+const PANIC_METHODS: [&str; 2] = ["unwrap", "expect"];
+
+// Edge of the project graph: an edge of a file's CFG, or a call into the function node of the callee
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProjectEdge {
+    Flow(EdgeKind),
+    Call,
+}
+
+// Where a path query of the project graph ends
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PathTarget<'a> {
+    Function(&'a str), // the entry of a function, by qualified name or last segment
+    Panic, // a 'panic!'-like macro, an 'unwrap'/'expect' or a call that never returns
+}
+
+// The CFGs of several files in one graph, each call of a function of another file (or of the same file)
+// linked to the function node of the callee. Nodes are a file (index in 'files') and a node of its CFG.
+pub struct ProjectGraph<'a> {
+    pub files: &'a [(String, CfgBuilder)],
+    pub graph: DiGraph<(usize, NodeIndex), ProjectEdge>,
+    nodes: HashMap<(usize, NodeIndex), NodeIndex>,
+}
+
+// Calls and panic sites in the source of one node
+#[derive(Default)]
+pub(crate) struct CallCollector {
+    pub calls: Vec<(Vec<String>, bool)>, // path segments of the called functions, or method names, and whether it's a method
+    pub panics: bool,
+}
+
+impl<'ast> Visit<'ast> for CallCollector {
+    fn visit_expr_call(&mut self, call: &'ast ExprCall) {
+        if let Expr::Path(path) = &*call.func {
+            self.calls.push((path.path.segments.iter().map(|segment| segment.ident.to_string()).collect(), false));
+        }
+        visit::visit_expr_call(self, call);
+    }
+
+    fn visit_expr_method_call(&mut self, call: &'ast ExprMethodCall) {
+        let method = call.method.to_string();
+        self.panics |= PANIC_METHODS.contains(&method.as_str());
+        self.calls.push((vec![method], true));
+        visit::visit_expr_method_call(self, call);
+    }
+
+    fn visit_expr_macro(&mut self, expr_macro: &'ast ExprMacro) {
+        self.panics |= Self::is_panic_macro(&expr_macro.mac);
+    }
+
+    // 'panic!(..);' as a statement
+    fn visit_item_macro(&mut self, item_macro: &'ast ItemMacro) {
+        self.panics |= Self::is_panic_macro(&item_macro.mac);
+    }
+}
+
+impl CallCollector {
+    fn is_panic_macro(mac: &syn::Macro) -> bool {
+        mac.path.get_ident().is_some_and(|name| PANIC_MACROS.contains(&name.to_string().as_str()))
+    }
+}
+
+impl CfgBuilder {
+    pub(crate) fn node_calls(&self, node: NodeIndex) -> CallCollector {
+        let mut collector = CallCollector::default();
+        match &self.graph[node] {
+            CfgNode::Statement(_, Some(stmt)) => collector.visit_stmt(stmt),
+            CfgNode::Condition(_, Some(conditional_expr)) => collector.visit_expr(conditional_expr.to_syn_expr()),
+            CfgNode::Return(_, Some(expr_return)) => collector.visit_expr_return(expr_return),
+            _ => {},
+        }
+        collector
+    }
+}
+
+impl<'a> ProjectGraph<'a> {
+    // Copy the CFG of every file and add the call edges
+    pub fn build(files: &'a [(String, CfgBuilder)]) -> Self {
+        let mut project = ProjectGraph { files, graph: DiGraph::new(), nodes: HashMap::new() };
+        for (file, (_, builder)) in files.iter().enumerate() {
+            for node in builder.graph.node_indices() {
+                let index = project.graph.add_node((file, node));
+                project.nodes.insert((file, node), index);
+            }
+            for edge in builder.graph.edge_references() {
+                let (source, target) = (project.nodes[&(file, edge.source())], project.nodes[&(file, edge.target())]);
+                project.graph.add_edge(source, target, ProjectEdge::Flow(edge.weight().clone()));
+            }
+        }
+        for (file, (_, builder)) in files.iter().enumerate() {
+            for node in builder.graph.node_indices() {
+                let callees: Vec<NodeIndex> = builder.node_calls(node).calls.iter()
+                    .filter_map(|(segments, method)| project.resolve_call(file, segments, *method))
+                    .collect();
+                for callee in callees {
+                    let source = project.nodes[&(file, node)];
+                    if !project.graph.edges_connecting(source, callee).any(|edge| *edge.weight() == ProjectEdge::Call) {
+                        project.graph.add_edge(source, callee, ProjectEdge::Call);
+                    }
+                }
+            }
+        }
+        project
+    }
+
+    // Function node a call resolves to: the functions named like its last segment, narrowed by its qualifier
+    // ('geometry::area' in 'geometry.rs' or module 'geometry') and preferring the caller's file. Methods only
+    // resolve to functions of an 'impl'. None when the callee isn't in the project or stays ambiguous.
+    fn resolve_call(&self, caller_file: usize, segments: &[String], method: bool) -> Option<NodeIndex> {
+        let name = segments.last()?;
+        let qualifier = segments.len().checked_sub(2).map(|i| &segments[i]);
+        let mut candidates: Vec<(usize, NodeIndex)> = self.functions().into_iter()
+            .filter(|(_, _, function)| function.rsplit("::").next() == Some(name.as_str()))
+            .filter(|(file, node, _)| !method || Self::takes_self(&self.files[*file].1, *node))
+            .filter(|(file, _, function)| match qualifier {
+                Some(qualifier) => function.contains(&format!("{}::{}", qualifier, name)) || Self::file_stem(&self.files[*file].0) == qualifier.as_str(),
+                None => true,
+            })
+            .map(|(file, node, _)| (file, node))
+            .collect();
+        if candidates.len() > 1 {
+            candidates.retain(|(file, _)| *file == caller_file);
+        }
+        match candidates.as_slice() {
+            [(file, node)] => self.nodes.get(&(*file, *node)).copied(),
+            _ => None,
+        }
+    }
+
+    fn takes_self(builder: &CfgBuilder, node: NodeIndex) -> bool {
+        matches!(&builder.graph[node], CfgNode::Function(_, Some(item_fn)) if matches!(item_fn.sig.inputs.first(), Some(syn::FnArg::Receiver(_))))
+    }
+
+    fn file_stem(file: &str) -> &str {
+        Path::new(file).file_stem().and_then(|stem| stem.to_str()).unwrap_or(file)
+    }
+
+    // Function nodes of every file: file index, node and qualified name
+    pub fn functions(&self) -> Vec<(usize, NodeIndex, String)> {
+        self.files.iter().enumerate()
+            .flat_map(|(file, (_, builder))| builder.function_nodes().into_iter().map(move |node| (file, node, builder.graph[node].label())))
+            .collect()
+    }
+
+    pub fn call_edge_count(&self) -> (usize, usize) {
+        let calls: Vec<_> = self.graph.edge_references().filter(|edge| *edge.weight() == ProjectEdge::Call).collect();
+        let cross_file = calls.iter().filter(|edge| self.graph[edge.source()].0 != self.graph[edge.target()].0).count();
+        (calls.len(), cross_file)
+    }
+
+    fn is_panic_site(&self, node: NodeIndex) -> bool {
+        let (file, cfg_node) = self.graph[node];
+        let builder = &self.files[file].1;
+        builder.node_calls(cfg_node).panics
+            || builder.graph.edges_directed(cfg_node, petgraph::Direction::Incoming).any(|edge| *edge.weight() == EdgeKind::PanicEdge)
+    }
+
+    fn is_function(&self, node: NodeIndex, name: &str) -> bool {
+        let (file, cfg_node) = self.graph[node];
+        let builder = &self.files[file].1;
+        let label = builder.graph[cfg_node].label();
+        matches!(builder.graph[cfg_node], CfgNode::Function(_, _)) && (label == name || label.rsplit("::").next() == Some(name))
+    }
+
+    // Entry nodes of the functions named 'from', or of every 'pub' function for "pub"
+    pub fn entries(&self, from: &str) -> Vec<NodeIndex> {
+        self.graph.node_indices().filter(|&node| {
+            let (file, cfg_node) = self.graph[node];
+            match (&self.files[file].1.graph[cfg_node], from) {
+                (CfgNode::Function(_, Some(item_fn)), "pub") => matches!(item_fn.vis, syn::Visibility::Public(_)),
+                _ => self.is_function(node, from),
+            }
+        }).collect()
+    }
+
+    // Shortest path from one of the entries to a node of the target, through the CFG edges and the calls
+    pub fn find_path(&self, entries: &[NodeIndex], target: PathTarget) -> Option<Vec<NodeIndex>> {
+        let mut parents: HashMap<NodeIndex, Option<NodeIndex>> = entries.iter().map(|&entry| (entry, None)).collect();
+        let mut queue: VecDeque<NodeIndex> = entries.iter().copied().collect();
+        while let Some(node) = queue.pop_front() {
+            let reached = match target {
+                PathTarget::Panic => self.is_panic_site(node),
+                PathTarget::Function(name) => !entries.contains(&node) && self.is_function(node, name),
+            };
+            if reached {
+                let mut path = vec![node];
+                while let Some(Some(parent)) = parents.get(path.last()?) {
+                    path.push(*parent);
+                }
+                path.reverse();
+                return Some(path);
+            }
+            for next in self.graph.neighbors(node) {
+                if let std::collections::hash_map::Entry::Vacant(entry) = parents.entry(next) {
+                    entry.insert(Some(node));
+                    queue.push_back(next);
+                }
+            }
+        }
+        None
+    }
+
+    // 'file.rs: label' for the report of a path
+    pub fn describe(&self, node: NodeIndex) -> String {
+        let (file, cfg_node) = self.graph[node];
+        let (name, builder) = &self.files[file];
+        format!("{}: {}", name, builder.annotation_label(cfg_node))
+    }
+
+    // One cluster per file; call edges dashed, panic sites outlined in red
+    pub fn to_dot(&self) -> String {
+        let dot_id = |node: NodeIndex| {
+            let (file, cfg_node) = self.graph[node];
+            format!("{}::{}", self.files[file].0, self.files[file].1.node_id(cfg_node))
+        };
+        let mut dot = String::from("digraph project {\ncompound=true;\n");
+        for (file, (name, builder)) in self.files.iter().enumerate() {
+            dot.push_str(&format!("subgraph cluster_{} {{\nlabel=\"{}\";\n", file, name));
+            let mut nodes: Vec<NodeIndex> = self.graph.node_indices().filter(|&node| self.graph[node].0 == file).collect();
+            nodes.sort_by_key(|&node| dot_id(node));
+            for node in nodes {
+                let cfg_node = self.graph[node].1;
+                let label = builder.options.notation.render(&builder.graph[cfg_node].label());
+                let mut node_dot = builder.graph[cfg_node].format_dot_labeled(&dot_id(node), &label, builder.options.max_label_len, &[]);
+                if self.is_panic_site(node) {
+                    node_dot.insert_str(node_dot.len() - 1, ", color=red, penwidth=2");
+                }
+                dot.push_str(&node_dot);
+                dot.push('\n');
+            }
+            dot.push_str("}\n");
+        }
+        let mut edges: Vec<String> = self.graph.edge_references().map(|edge| match edge.weight() {
+            ProjectEdge::Flow(kind) => CfgBuilder::format_edge_dot(&dot_id(edge.source()), &dot_id(edge.target()), kind, None),
+            ProjectEdge::Call => format!("\"{}\" -> \"{}\" [label=\"calls\", style=dashed, color=blue, constraint=false];\n", dot_id(edge.source()), dot_id(edge.target())),
+        }).collect();
+        edges.sort();
+        dot.push_str(&edges.concat());
+        dot.push_str("}\n");
+        dot
+    }
+}
@@ -0,0 +1,91 @@
+use petgraph::graph::NodeIndex;
+use petgraph::visit::EdgeRef;
+use petgraph::Direction;
+use proc_macro2::LineColumn;
+use syn::spanned::Spanned;
+
+use crate::cfg_builder::builder::CfgBuilder;
+use crate::cfg_builder::node::{CfgNode, EdgeKind};
+
+// Queries for embedders (editors, tests, plugins) that need the graph without going through petgraph.
+// Nodes are 'NodeIndex'es of the built graph; positions are 1-based lines and columns, as in the node IDs.
+impl CfgBuilder {
+    // Nodes the node leads to, with the kind of each edge, by node ID
+    pub fn successors(&self, node: NodeIndex) -> Vec<(NodeIndex, EdgeKind)> {
+        self.neighbors_with_kinds(node, Direction::Outgoing)
+    }
+
+    // Nodes leading to the node, with the kind of each edge, by node ID
+    pub fn predecessors(&self, node: NodeIndex) -> Vec<(NodeIndex, EdgeKind)> {
+        self.neighbors_with_kinds(node, Direction::Incoming)
+    }
+
+    fn neighbors_with_kinds(&self, node: NodeIndex, direction: Direction) -> Vec<(NodeIndex, EdgeKind)> {
+        let mut neighbors: Vec<(NodeIndex, EdgeKind)> = self.graph.edges_directed(node, direction)
+            .map(|edge| {
+                let other = if direction == Direction::Outgoing { edge.target() } else { edge.source() };
+                (other, edge.weight().clone())
+            })
+            .collect();
+        neighbors.sort_by_key(|(other, kind)| (self.node_id(*other), kind.label()));
+        neighbors
+    }
+
+    // Entry node of each analyzed function with its qualified name, in source order
+    pub fn function_entries(&self) -> Vec<(String, NodeIndex)> {
+        let mut entries: Vec<(String, NodeIndex)> = self.function_nodes().into_iter()
+            .map(|node| (self.graph[node].label(), node))
+            .collect();
+        entries.sort_by_key(|(name, node)| (self.node_location(*node), name.clone()));
+        entries
+    }
+
+    // Node with this ID ('sum_to:condition@6:11'), as in the DOT and JSON exports
+    pub fn node_by_id(&self, id: &str) -> Option<NodeIndex> {
+        self.graph.node_indices().find(|&node| self.node_id(node) == id)
+    }
+
+    // Start and end of the source of the node; a function spans its whole item
+    pub fn node_span(&self, node: NodeIndex) -> Option<(LineColumn, LineColumn)> {
+        let span = match &self.graph[node] {
+            CfgNode::Function(_, Some(item_fn)) => item_fn.span(),
+            CfgNode::Precondition(_, Some(expr)) | CfgNode::Postcondition(_, Some(expr)) | CfgNode::Invariant(_, Some(expr)) | CfgNode::Assertion(_, Some(expr)) => expr.span(),
+            CfgNode::Statement(_, Some(stmt)) => stmt.span(),
+            CfgNode::Condition(_, Some(conditional_expr)) => conditional_expr.to_syn_expr().span(),
+            CfgNode::Return(_, Some(expr_return)) => match &expr_return.expr {
+                Some(expr) => expr.span(),
+                None => expr_return.span(),
+            },
+            _ => return None,
+        };
+        // Spans created by the analysis itself point nowhere
+        if span.start().line == 0 {
+            return None;
+        }
+        Some((span.start(), span.end()))
+    }
+
+    // Innermost node whose source contains the position (1-based line and column)
+    pub fn node_at(&self, line: usize, column: usize) -> Option<NodeIndex> {
+        let position = LineColumn { line, column: column.saturating_sub(1) };
+        self.graph.node_indices()
+            .filter_map(|node| self.node_span(node).map(|span| (node, span)))
+            .filter(|(_, (start, end))| *start <= position && position < *end)
+            .min_by_key(|(node, (start, end))| (end.line - start.line, end.column.abs_diff(start.column), self.node_id(*node)))
+            .map(|(node, _)| node)
+    }
+
+    // Innermost node at a byte offset of the analyzed source
+    pub fn node_at_offset(&self, offset: usize) -> Option<NodeIndex> {
+        let mut line_start = 0;
+        for (i, line) in self.file.source_lines.iter().enumerate() {
+            let line_end = line_start + line.len();
+            if offset <= line_end {
+                let column = line.get(..offset - line_start)?.chars().count() + 1;
+                return self.node_at(i + 1, column);
+            }
+            line_start = line_end + 1;
+        }
+        None
+    }
+}
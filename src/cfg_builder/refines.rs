@@ -0,0 +1,91 @@
+use syn::{Expr, FnArg, ItemFn, Stmt};
+
+use crate::cfg_builder::builder::CfgBuilder;
+
+impl CfgBuilder {
+    // Reference implementation named by '#[refines(reference)]' or '#[secured::refines(reference)]'
+    pub fn refined_reference(item_fn: &ItemFn) -> Option<String> {
+        let attr = item_fn.attrs.iter().find(|attr| attr.path.segments.last().is_some_and(|segment| segment.ident == "refines"))?;
+        match attr.parse_args::<syn::Path>() {
+            Ok(path) => Some(path.segments.iter().map(|segment| segment.ident.to_string()).collect::<Vec<_>>().join("::")),
+            Err(e) => {
+                eprintln!("Warning: can't parse the reference of {} in {}: {}", item_fn.sig.ident, quote::quote!(#attr), e);
+                None
+            },
+        }
+    }
+
+    // A function refining a reference implementation, with the contract that makes them equivalent: its own
+    // 'pre!' (the reference's when it has none, over its parameters) and a 'post!' stating that the variable it
+    // returns equals the result of the reference for the same arguments. The reference is inlined before the
+    // body, its variables renamed '<reference>_<name>', or called in the spec when it is a logic function.
+    // Returns the rewritten function and the fact recorded on its node.
+    pub(crate) fn refinement(&self, func_name: &str, item_fn: &ItemFn) -> Result<(ItemFn, String), String> {
+        let reference = Self::refined_reference(item_fn).ok_or_else(|| format!("{} has no reference implementation", func_name))?;
+        let params = Self::parameter_names(item_fn);
+        let args: Vec<Expr> = params.iter().map(|param| syn::parse_str(param).expect("Failed to parse parameter")).collect();
+        let call: syn::ExprCall = {
+            let callee: syn::Path = syn::parse_str(&reference).expect("Failed to parse reference");
+            syn::parse_quote!(#callee(#(#args),*))
+        };
+        let Some(returned) = Self::returned_variable(item_fn) else {
+            return Err(format!("{} doesn't return a single variable: return one variable on every path to compare it with {}", func_name, reference));
+        };
+        let returned: Expr = syn::parse_str(&returned).expect("Failed to parse returned variable");
+
+        let logic = self.file.logic_functions.contains_key(&reference);
+        let (reference_fn, expected, computed): (Option<ItemFn>, Expr, Option<Stmt>) = if logic {
+            (None, Expr::Call(call.clone()), None)
+        } else {
+            let (callee, reference_fn) = self.local_callee(&call).ok_or_else(|| format!("{} refines {}, which is not a function of the file", func_name, reference))?;
+            if callee == func_name {
+                return Err(format!("{} refines itself", func_name));
+            }
+            let inputs = reference_fn.sig.inputs.len();
+            if inputs != params.len() {
+                return Err(format!("{} takes {} parameters and its reference {} takes {}", func_name, params.len(), reference, inputs));
+            }
+            if Self::has_early_return(&reference_fn) {
+                return Err(format!("{} has an early return, so it can't be inlined to compare {} with it", reference, func_name));
+            }
+            let name = callee.rsplit("::").next().unwrap_or(&callee);
+            let result = syn::Ident::new(&format!("{}_result", name), proc_macro2::Span::call_site());
+            let body = self.inlined_body(&callee, &reference_fn, &call);
+            (Some(reference_fn), syn::parse_quote!(#result), Some(syn::parse_quote!(let #result = #body;)))
+        };
+
+        // Preconditions of the implementation, or of the reference written over the parameters
+        let (mut preconditions, _) = self.contract_conditions(item_fn);
+        if preconditions.is_empty() {
+            if let Some(reference_fn) = &reference_fn {
+                let (reference_pre, _) = self.contract_conditions(reference_fn);
+                let reference_params = Self::parameter_names(reference_fn);
+                let args: Vec<&Expr> = args.iter().collect();
+                preconditions = reference_pre.iter()
+                    .map(|pre| Self::without_parens(&syn::parse_str(&self.instantiate_contract(pre, &reference_params, &args, None)).expect("Failed to parse condition")))
+                    .collect();
+            }
+        }
+
+        let mut refined = item_fn.clone();
+        let mut stmts: Vec<Stmt> = preconditions.iter().map(|pre| syn::parse_quote!(pre!(#pre);)).collect();
+        stmts.push(syn::parse_quote!(post!(#returned == #expected);));
+        let (contract, body): (Vec<Stmt>, Vec<Stmt>) = item_fn.block.stmts.iter().cloned().partition(|stmt| self.is_contract_annotation(stmt));
+        stmts.extend(contract.into_iter().filter(|stmt| !self.is_precondition_statement(stmt)));
+        stmts.extend(computed);
+        stmts.extend(body);
+        refined.block.stmts = stmts;
+        let how = if logic { "logic function" } else { "inlined" };
+        Ok((refined, format!("refines {} ({}): returns the same result for every input satisfying the precondition", reference, how)))
+    }
+
+    // Parameter names by position, 'self' for the receiver
+    pub(crate) fn parameter_names(item_fn: &ItemFn) -> Vec<String> {
+        item_fn.sig.inputs.iter()
+            .filter_map(|input| match input {
+                FnArg::Typed(pat_type) => Self::pattern_bindings(&pat_type.pat).into_iter().next(),
+                FnArg::Receiver(_) => Some("self".to_string()),
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,162 @@
+use petgraph::algo::dominators;
+use petgraph::graph::{DiGraph, NodeIndex};
+use petgraph::visit::EdgeRef;
+use petgraph::Direction;
+use std::collections::{HashMap, HashSet};
+
+use crate::cfg_builder::builder::CfgBuilder;
+use crate::cfg_builder::node::{CfgNode, ConditionalExpr, EdgeKind};
+
+// Loop-free single-entry single-exit region starting at a branch. Basic paths go from its entry
+// straight to its exit, and the verification conditions combine all the paths through it.
+#[derive(Debug, Clone)]
+pub struct RegionSummary {
+    pub entry: NodeIndex, // 'if'/'match' condition where the region starts
+    pub exit: NodeIndex, // first node reached by every path leaving the entry
+    pub paths: Vec<Vec<NodeIndex>>, // paths through the region, from the entry to the exit included
+}
+
+impl CfgBuilder {
+    // Find the outermost loop-free regions without annotations, returning how many were found
+    pub fn summarize_regions(&mut self) -> usize {
+        let mut regions: Vec<(RegionSummary, HashSet<NodeIndex>)> = Vec::new();
+        for func_node in self.function_nodes() {
+            let post_dominators = self.immediate_post_dominators(func_node);
+            for node in self.function_subgraph_nodes(func_node) {
+                if !matches!(self.graph[node], CfgNode::Condition(_, Some(ConditionalExpr::If(_))) | CfgNode::Condition(_, Some(ConditionalExpr::Match(_)))) {
+                    continue;
+                }
+                if let Some(region) = post_dominators.get(&node).and_then(|&exit| self.region_between(node, exit)) {
+                    regions.push(region);
+                }
+            }
+        }
+
+        // Regions nested in another one are covered by the paths of the outer region
+        let inner: HashSet<NodeIndex> = regions.iter()
+            .filter(|(region, _)| regions.iter().any(|(_, nodes)| nodes.contains(&region.entry)))
+            .map(|(region, _)| region.entry)
+            .collect();
+        self.results.region_summaries = regions.into_iter()
+            .filter(|(region, _)| !inner.contains(&region.entry))
+            .map(|(region, _)| (region.entry, region))
+            .collect();
+        self.results.region_summaries.len()
+    }
+
+    // Summarized region whose entry is 'path[position]', when the path goes from it straight to its exit
+    pub fn region_at(&self, path: &[NodeIndex], position: usize) -> Option<&RegionSummary> {
+        let region = self.results.region_summaries.get(path.get(position)?)?;
+        (path.get(position + 1) == Some(&region.exit)).then_some(region)
+    }
+
+    // Region from a branch to its immediate post-dominator, with the nodes strictly inside it,
+    // if nothing enters or leaves it on the way and it has no loop, annotation or return
+    fn region_between(&self, entry: NodeIndex, exit: NodeIndex) -> Option<(RegionSummary, HashSet<NodeIndex>)> {
+        let mut inside = HashSet::new();
+        let mut stack: Vec<NodeIndex> = self.graph.neighbors(entry).filter(|&n| n != exit).collect();
+        while let Some(node) = stack.pop() {
+            if node == entry {
+                return None;
+            }
+            if !inside.insert(node) {
+                continue;
+            }
+            let allowed = matches!(
+                self.graph[node],
+                CfgNode::Statement(_, _) | CfgNode::MergePoint
+                | CfgNode::Condition(_, Some(ConditionalExpr::If(_))) | CfgNode::Condition(_, Some(ConditionalExpr::Match(_)))
+            );
+            if !allowed {
+                return None;
+            }
+            stack.extend(self.graph.neighbors(node).filter(|&n| n != exit));
+        }
+
+        let single_entry = inside.iter().all(|&node| {
+            self.graph.neighbors_directed(node, Direction::Incoming).all(|pred| pred == entry || inside.contains(&pred))
+        });
+        let single_exit = inside.iter().chain(std::iter::once(&entry)).all(|&node| {
+            self.graph.edges(node).all(|edge| {
+                inside.contains(&edge.target()) || (edge.target() == exit && *edge.weight() != EdgeKind::BackEdge)
+            })
+        });
+        if !single_entry || !single_exit {
+            return None;
+        }
+
+        let mut paths = Vec::new();
+        self.region_paths(&mut vec![entry], exit, &mut paths);
+        Some((RegionSummary { entry, exit, paths }, inside))
+    }
+
+    fn region_paths(&self, path: &mut Vec<NodeIndex>, exit: NodeIndex, paths: &mut Vec<Vec<NodeIndex>>) {
+        let last = *path.last().expect("Path can't be empty");
+        if last == exit {
+            paths.push(path.clone());
+            return;
+        }
+        let successors: Vec<NodeIndex> = self.graph.neighbors(last).collect();
+        for next in successors {
+            path.push(next);
+            self.region_paths(path, exit, paths);
+            path.pop();
+        }
+    }
+
+    // Immediate post-dominator of each node of a function: dominators of the reversed graph,
+    // rooted at a virtual node that every exit of the function leads to
+    fn immediate_post_dominators(&self, func_node: NodeIndex) -> HashMap<NodeIndex, NodeIndex> {
+        let nodes = self.function_subgraph_nodes(func_node);
+        let mut reversed: DiGraph<Option<NodeIndex>, ()> = DiGraph::new();
+        let root = reversed.add_node(None);
+        let local: HashMap<NodeIndex, NodeIndex> = nodes.iter().map(|&node| (node, reversed.add_node(Some(node)))).collect();
+        for &node in &nodes {
+            let mut is_exit = true;
+            for successor in self.graph.neighbors(node) {
+                if let Some(&target) = local.get(&successor) {
+                    reversed.add_edge(target, local[&node], ());
+                    is_exit = false;
+                }
+            }
+            if is_exit {
+                reversed.add_edge(root, local[&node], ());
+            }
+        }
+
+        let dominators = dominators::simple_fast(&reversed, root);
+        nodes.iter()
+            .filter_map(|&node| {
+                let dominator = dominators.immediate_dominator(local[&node])?;
+                reversed[dominator].map(|dominator| (node, dominator))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_support::{built, discharge};
+    use crate::verifier::VcOutcome;
+
+    fn summarized_verdicts(post: &str) -> Vec<VcOutcome> {
+        let source = format!("fn count(a: i32, b: i32) -> i32 {{
+                pre!(true);
+                post!({});
+                let mut r = 0;
+                if a > 0 {{ r = r + 1; }} else {{ r = r + 2; }}
+                if b > 0 {{ r = r + 1; }}
+                return r;
+            }}", post);
+        let mut builder = built(&source);
+        assert_eq!(builder.summarize_regions(), 2);
+        discharge(&mut builder)
+    }
+
+    // One basic path crosses both regions and its condition covers the four ways through them
+    #[test]
+    fn a_summarized_region_holds_for_every_path_through_it() {
+        assert!(matches!(summarized_verdicts("r >= 1 && r <= 3").as_slice(), [VcOutcome::Valid]));
+        assert!(matches!(summarized_verdicts("r >= 2").as_slice(), [VcOutcome::Counterexample(_)]));
+    }
+}
@@ -0,0 +1,85 @@
+use petgraph::algo::has_path_connecting;
+use petgraph::graph::NodeIndex;
+use petgraph::visit::EdgeRef;
+use petgraph::Direction;
+use syn::{Expr, Stmt};
+
+use crate::cfg_builder::builder::CfgBuilder;
+use crate::cfg_builder::node::{CfgNode, EdgeKind};
+
+impl CfgBuilder {
+    // Drop the nodes that don't change the meaning of any path, returning how many were dropped:
+    // unit statements ('();', '{}'), statements with an empty label, and cut points that no back
+    // edge reaches. Each one is bypassed, its predecessors linked straight to its successor.
+    pub fn simplify_graph(&mut self) -> usize {
+        if !self.options.simplify.enabled {
+            return 0;
+        }
+        let mut dropped = 0;
+        // Removing a node moves the last one to its index, so look for the next one from scratch
+        while let Some((node, target, edges)) = self.graph.node_indices()
+            .filter(|&node| self.is_no_op_node(node))
+            .find_map(|node| self.bypass_edges(node).map(|(target, edges)| (node, target, edges)))
+        {
+            for (source, kind) in edges {
+                let exists = self.graph.edges_connecting(source, target).any(|edge| *edge.weight() == kind);
+                if !exists {
+                    self.add_edge_with_kind(source, target, kind);
+                }
+            }
+            self.remove_node(node);
+            dropped += 1;
+        }
+        dropped
+    }
+
+    fn is_no_op_node(&self, node: NodeIndex) -> bool {
+        match &self.graph[node] {
+            CfgNode::Statement(label, stmt) => {
+                (self.options.simplify.empty_labels && label.trim().trim_matches(';').trim().is_empty())
+                    || (self.options.simplify.unit_statements && stmt.as_ref().is_some_and(Self::is_unit_statement))
+            }
+            CfgNode::Cutoff(label) => {
+                // A cut point on a cycle ends the paths around the loop, it must stay
+                self.options.simplify.unused_cutoffs && label.trim().is_empty()
+                    && !self.graph.edges(node).any(|edge| has_path_connecting(&self.graph, edge.target(), node, None))
+            }
+            _ => false,
+        }
+    }
+
+    fn is_unit_statement(stmt: &Stmt) -> bool {
+        match stmt {
+            Stmt::Expr(expr) | Stmt::Semi(expr, _) => match expr {
+                Expr::Tuple(tuple) => tuple.elems.is_empty(),
+                Expr::Block(block) => block.block.stmts.is_empty() && block.label.is_none(),
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+
+    // The single successor of the node and the edges that replace its incoming ones, or None when
+    // an incoming and the outgoing edge both carry meaning (a branch into a back edge) and can't be merged
+    fn bypass_edges(&self, node: NodeIndex) -> Option<(NodeIndex, Vec<(NodeIndex, EdgeKind)>)> {
+        let outgoing: Vec<_> = self.graph.edges(node).collect();
+        if outgoing.len() != 1 || outgoing[0].target() == node {
+            return None;
+        }
+        let (target, out_kind) = (outgoing[0].target(), outgoing[0].weight().clone());
+        let incoming: Vec<_> = self.graph.edges_directed(node, Direction::Incoming).collect();
+        if incoming.is_empty() {
+            return None;
+        }
+        let mut edges = Vec::new();
+        for edge in incoming {
+            let kind = match (edge.weight(), &out_kind) {
+                (kind, EdgeKind::Fallthrough) => kind.clone(),
+                (EdgeKind::Fallthrough, kind) => kind.clone(),
+                _ => return None,
+            };
+            edges.push((edge.source(), kind));
+        }
+        Some((target, edges))
+    }
+}
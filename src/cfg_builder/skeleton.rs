@@ -0,0 +1,54 @@
+use petgraph::graph::NodeIndex;
+use std::collections::HashSet;
+
+use crate::cfg_builder::builder::CfgBuilder;
+use crate::cfg_builder::node::CfgNode;
+
+impl CfgBuilder {
+    // Graph of the annotations only: one cluster per function, with its entry and its pre/post/invariant/assert
+    // nodes, and an edge wherever some code path goes from one to the other without crossing another annotation
+    pub fn skeleton_to_dot(&self) -> String {
+        let mut dot_string = String::from("digraph Skeleton {\n");
+        for func_node in self.function_nodes() {
+            let nodes: Vec<NodeIndex> = self.function_subgraph_nodes(func_node).into_iter()
+                .filter(|&node| node == func_node || Self::is_skeleton_node(&self.graph[node]))
+                .collect();
+            dot_string.push_str(&format!("subgraph \"cluster_{}\" {{\nlabel=\"{}\";\n", self.function_id(func_node), self.graph[func_node].label().replace('"', "\\\"")));
+            for &node in &nodes {
+                dot_string.push_str(&self.format_node_dot(node));
+                dot_string.push('\n');
+            }
+            dot_string.push_str("}\n");
+            for &node in &nodes {
+                for target in self.next_annotations(node) {
+                    dot_string.push_str(&format!("\"{}\" -> \"{}\";\n", self.node_id(node), self.node_id(target)));
+                }
+            }
+        }
+        dot_string.push_str("}\n");
+        dot_string
+    }
+
+    // Annotations reached from a node by a code path that crosses no other annotation
+    pub fn next_annotations(&self, from: NodeIndex) -> Vec<NodeIndex> {
+        let mut visited = HashSet::new();
+        let mut reached = Vec::new();
+        let mut stack: Vec<NodeIndex> = self.graph.neighbors(from).collect();
+        while let Some(node) = stack.pop() {
+            if !visited.insert(node) {
+                continue;
+            }
+            if Self::is_skeleton_node(&self.graph[node]) {
+                reached.push(node);
+            } else {
+                stack.extend(self.graph.neighbors(node));
+            }
+        }
+        reached.sort();
+        reached
+    }
+
+    fn is_skeleton_node(node: &CfgNode) -> bool {
+        matches!(node, CfgNode::Precondition(_, _) | CfgNode::Postcondition(_, _) | CfgNode::Invariant(_, _) | CfgNode::Cutoff(_))
+    }
+}
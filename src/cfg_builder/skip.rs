@@ -0,0 +1,41 @@
+use proc_macro2::TokenTree;
+use syn::{Attribute, ItemFn};
+
+use crate::cfg_builder::builder::CfgBuilder;
+
+impl CfgBuilder {
+    // Why the function is left out of the graph, if it is: its attribute or the '[skip]' entry naming it.
+    // '#[secured::trusted]' leaves it out the same way, for a body the tool can't prove but whose contract
+    // its callers rely on.
+    pub(crate) fn skip_reason(&self, func_name: &str, item_fn: &ItemFn) -> Option<String> {
+        if item_fn.attrs.iter().any(|attr| Self::is_marker_attribute(attr, "skip")) {
+            return Some("#[secured::skip]".to_string());
+        }
+        if item_fn.attrs.iter().any(|attr| Self::is_marker_attribute(attr, "trusted")) {
+            return Some("#[secured::trusted]".to_string());
+        }
+        self.options.skip_list.iter()
+            .find(|entry| Self::skip_entry_matches(entry, func_name))
+            .map(|entry| format!("'{}' in [skip] of secured.toml", entry))
+    }
+
+    // '#[secured::skip]', '#[skip]', or '#[cfg_attr(.., secured::skip)]' for code that must build without the tool
+    fn is_marker_attribute(attr: &Attribute, name: &str) -> bool {
+        let segments: Vec<String> = attr.path.segments.iter().map(|segment| segment.ident.to_string()).collect();
+        if segments == [name] || segments == ["secured", name] {
+            return true;
+        }
+        let Some(TokenTree::Group(group)) = attr.tokens.clone().into_iter().next() else { return false };
+        attr.path.is_ident("cfg_attr") && group.stream().into_iter()
+            .skip_while(|token| !matches!(token, TokenTree::Punct(punct) if punct.as_char() == ','))
+            .any(|token| matches!(token, TokenTree::Ident(ident) if ident == name))
+    }
+
+    // 'gen::*' matches the functions of module 'gen'; a single name, the functions of any module named so
+    fn skip_entry_matches(entry: &str, func_name: &str) -> bool {
+        match entry.strip_suffix("::*") {
+            Some(module) => func_name.starts_with(&format!("{}::", module)) || func_name.contains(&format!("::{}::", module)),
+            None => func_name == entry || func_name.rsplit("::").next() == Some(entry),
+        }
+    }
+}
@@ -0,0 +1,494 @@
+use std::collections::HashMap;
+use std::fmt;
+use syn::spanned::Spanned;
+use syn::visit::{self, Visit};
+use syn::{BinOp, Expr, ExprMacro, ImplItem, Item, ItemFn, Lit, Pat, ReturnType, TraitItem, Type, UnOp};
+
+use crate::cfg_builder::builder::CfgBuilder;
+use crate::cfg_builder::constants::ConstValue;
+use crate::cfg_builder::struct_invariants::StructInvariant;
+use crate::config::{conditions_path, AnnotationNames, AnnotationRole};
+use crate::spec_parser::Quantifier;
+
+// A specification that can't be verified as written, found without building any path
+#[derive(Debug, Clone)]
+pub struct SpecProblem {
+    pub location: String, // 'file:line:column', or the contract database
+    pub owner: String, // function the annotation belongs to, or the callee of an external contract
+    pub spec: String,
+    pub message: String,
+}
+
+impl fmt::Display for SpecProblem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {} in {}: {}", self.location, self.spec, self.owner, self.message)
+    }
+}
+
+// Outcome of 'check-specs'
+#[derive(Debug, Default)]
+pub struct SpecCheck {
+    pub functions: usize, // functions with at least one annotation
+    pub specs: usize, // annotations, call-site comments, struct invariants and external contracts checked
+    pub problems: Vec<SpecProblem>,
+}
+
+// Rough type of a spec expression: what the solver can reason about
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SpecType {
+    Int,
+    Bool,
+    Unknown,
+}
+
+impl SpecType {
+    fn of(ty: &str) -> SpecType {
+        let ty = ty.trim_start_matches('&').trim_start_matches("mut ").trim();
+        match ty {
+            "bool" => SpecType::Bool,
+            "i8" | "i16" | "i32" | "i64" | "i128" | "isize" | "u8" | "u16" | "u32" | "u64" | "u128" | "usize" => SpecType::Int,
+            _ => SpecType::Unknown,
+        }
+    }
+
+    fn describe(&self) -> &'static str {
+        match self {
+            SpecType::Int => "an integer",
+            SpecType::Bool => "a boolean",
+            SpecType::Unknown => "a value",
+        }
+    }
+}
+
+// Annotations of a function body and the variables it binds, whatever their scope
+struct SpecCollector<'a> {
+    names: &'a AnnotationNames,
+    annotations: Vec<(AnnotationRole, ExprMacro)>,
+    variables: HashMap<String, String>, // name -> type, empty when unknown
+}
+
+impl<'ast> Visit<'ast> for SpecCollector<'_> {
+    fn visit_expr_macro(&mut self, i: &'ast ExprMacro) {
+        if let Some(role) = i.mac.path.get_ident().and_then(|ident| self.names.role_of(&ident.to_string())) {
+            self.annotations.push((role, i.clone()));
+        }
+    }
+
+    fn visit_local(&mut self, i: &'ast syn::Local) {
+        let declared = match &i.pat {
+            Pat::Type(pat_type) => CfgBuilder::render_type(&pat_type.ty),
+            _ => match i.init.as_ref().map(|(_, init)| &**init) {
+                Some(Expr::Lit(expr_lit)) if matches!(expr_lit.lit, Lit::Int(_)) => "i32".to_string(),
+                Some(Expr::Lit(expr_lit)) if matches!(expr_lit.lit, Lit::Bool(_)) => "bool".to_string(),
+                _ => String::new(),
+            },
+        };
+        for var in CfgBuilder::pattern_bindings(&i.pat) {
+            self.variables.insert(var, declared.clone());
+        }
+        visit::visit_local(self, i);
+    }
+
+    // The loop variables, and the iteration counter of 'take'/'enumerate' loops ('x_iter')
+    fn visit_expr_for_loop(&mut self, i: &'ast syn::ExprForLoop) {
+        let bindings = CfgBuilder::pattern_bindings(&i.pat);
+        if let Some(first) = bindings.first() {
+            self.variables.insert(format!("{}_iter", first), "usize".to_string());
+        }
+        for var in bindings {
+            self.variables.entry(var).or_default();
+        }
+        visit::visit_expr_for_loop(self, i);
+    }
+
+    fn visit_arm(&mut self, i: &'ast syn::Arm) {
+        for var in CfgBuilder::pattern_bindings(&i.pat) {
+            self.variables.entry(var).or_default();
+        }
+        visit::visit_arm(self, i);
+    }
+
+    fn visit_expr_closure(&mut self, i: &'ast syn::ExprClosure) {
+        for input in &i.inputs {
+            for var in CfgBuilder::pattern_bindings(input) {
+                self.variables.entry(var).or_default();
+            }
+        }
+        visit::visit_expr_closure(self, i);
+    }
+
+    // Statement macros ('precondition!(..);') parse as items; other nested items are functions of their own
+    fn visit_item(&mut self, i: &'ast Item) {
+        if let Item::Macro(item_macro) = i {
+            self.visit_expr_macro(&ExprMacro { attrs: item_macro.attrs.clone(), mac: item_macro.mac.clone() });
+        }
+    }
+}
+
+impl CfgBuilder {
+    // Parse every annotation, '//@ call' comment, struct invariant and external contract, and check the
+    // identifiers and the types of the spec expressions against the variables of their function
+    pub fn check_specs(&mut self, file: &str, items: &[Item]) -> SpecCheck {
+        self.collect_local_functions(items, &[]);
+        self.collect_constants(items);
+        let mut check = SpecCheck::default();
+        for (name, item_fn) in Self::spec_functions(items, &[]) {
+            self.check_function_specs(file, &name, &item_fn, &mut check);
+        }
+
+        for item in items {
+            let Item::Macro(item_macro) = item else { continue };
+            if !item_macro.mac.path.is_ident("struct_invariant") {
+                continue;
+            }
+            check.specs += 1;
+            let spec = format!("struct_invariant!({})", Self::clean_up_formatting(&item_macro.mac.tokens.to_string()));
+            let location = Self::location(file, item_macro.span());
+            let variables = HashMap::from([("self".to_string(), String::new())]);
+            match syn::parse2::<StructInvariant>(item_macro.mac.tokens.clone()) {
+                Ok(invariant) => self.check_condition(&invariant.condition, &variables, &location, &invariant.type_name.to_string(), &spec, &mut check.problems),
+                Err(e) => check.problems.push(SpecProblem { location, owner: "the file".to_string(), spec, message: format!("expected 'Type: condition': {}", e) }),
+            }
+        }
+
+        // Contracts of extern functions are written over their declared parameters: type-checked against them
+        self.collect_foreign_functions(items);
+        for site in &self.external_conditions.call_sites {
+            let Some(foreign) = self.file.foreign_functions.get(&site.callee) else { continue };
+            let mut variables: HashMap<String, String> = foreign.params.iter().cloned().collect();
+            let location = conditions_path().display().to_string();
+            for (condition, is_post) in site.preconditions.iter().map(|pre| (pre, false)).chain(site.postconditions.iter().map(|post| (post, true))) {
+                check.specs += 1;
+                if is_post {
+                    variables.insert("result".to_string(), foreign.returns.clone().unwrap_or_default());
+                } else {
+                    variables.remove("result");
+                }
+                match syn::parse_str::<Expr>(condition) {
+                    Ok(expr) => self.check_condition(&expr, &variables, &location, &site.callee, condition, &mut check.problems),
+                    Err(e) => check.problems.push(SpecProblem { location: location.clone(), owner: site.callee.clone(), spec: condition.clone(), message: format!("can't parse the condition: {}", e) }),
+                }
+            }
+        }
+
+        // Other contracts of the conditions database are written over the arguments: only their syntax is checked
+        let foreign_sites: Vec<String> = self.file.foreign_functions.keys().cloned().collect();
+        let external = self.external_conditions.external_methods.iter().map(|method| (&method.name, &method.preconditions, &method.postconditions))
+            .chain(self.external_conditions.call_sites.iter()
+                .filter(|site| !foreign_sites.contains(&site.callee))
+                .map(|site| (&site.callee, &site.preconditions, &site.postconditions)));
+        for (callee, preconditions, postconditions) in external {
+            for condition in preconditions.iter().chain(postconditions) {
+                check.specs += 1;
+                if let Err(e) = syn::parse_str::<Expr>(condition) {
+                    check.problems.push(SpecProblem {
+                        location: conditions_path().display().to_string(),
+                        owner: callee.clone(),
+                        spec: condition.clone(),
+                        message: format!("can't parse the condition: {}", e),
+                    });
+                }
+            }
+        }
+        check
+    }
+
+    // Functions whose annotations are verified: free functions, methods and trait default methods
+    pub(crate) fn spec_functions(items: &[Item], module_path: &[String]) -> Vec<(String, ItemFn)> {
+        let qualified = |name: String| module_path.iter().cloned().chain(std::iter::once(name)).collect::<Vec<_>>().join("::");
+        let mut functions = Vec::new();
+        for item in items {
+            match item {
+                Item::Fn(item_fn) => functions.push((qualified(item_fn.sig.ident.to_string()), item_fn.clone())),
+                Item::Mod(item_mod) => {
+                    if let Some((_, items)) = &item_mod.content {
+                        let mut path = module_path.to_vec();
+                        path.push(item_mod.ident.to_string());
+                        functions.extend(Self::spec_functions(items, &path));
+                    }
+                },
+                Item::Impl(item_impl) => {
+                    let Type::Path(self_ty) = &*item_impl.self_ty else { continue };
+                    let Some(type_name) = self_ty.path.segments.last().map(|segment| segment.ident.to_string()) else { continue };
+                    for impl_item in &item_impl.items {
+                        if let ImplItem::Method(method) = impl_item {
+                            let item_fn = ItemFn { attrs: method.attrs.clone(), vis: method.vis.clone(), sig: method.sig.clone(), block: Box::new(method.block.clone()) };
+                            functions.push((qualified(format!("{}::{}", type_name, method.sig.ident)), item_fn));
+                        }
+                    }
+                },
+                Item::Trait(item_trait) => {
+                    for trait_item in &item_trait.items {
+                        if let TraitItem::Method(method) = trait_item {
+                            if let Some(default_body) = &method.default {
+                                let item_fn = ItemFn { attrs: method.attrs.clone(), vis: syn::Visibility::Inherited, sig: method.sig.clone(), block: Box::new(default_body.clone()) };
+                                functions.push((qualified(format!("{}::{}", item_trait.ident, method.sig.ident)), item_fn));
+                            }
+                        }
+                    }
+                },
+                _ => {},
+            }
+        }
+        functions
+    }
+
+    // Annotations of the body of a function, and the variables they can name: its parameters and the
+    // variables it binds
+    fn spec_scope(&mut self, item_fn: &ItemFn) -> (Vec<(AnnotationRole, ExprMacro)>, HashMap<String, String>) {
+        let mut collector = SpecCollector { names: &self.options.annotation_names, annotations: Vec::new(), variables: HashMap::new() };
+        collector.visit_block(&item_fn.block);
+        let (annotations, mut variables) = (collector.annotations, collector.variables);
+        variables.extend(self.init_type_env(item_fn));
+        (annotations, variables)
+    }
+
+    // Problems of the annotations of a function: a condition that doesn't parse (written as an expression or as
+    // a string), names that aren't in scope and operands of the wrong type. 'check-specs' reports them with the
+    // location in 'file'; without a file they are located by line, for the annotations the CFG builder rejects.
+    pub(crate) fn annotation_problems(&mut self, file: Option<&str>, name: &str, item_fn: &ItemFn) -> Vec<SpecProblem> {
+        let (annotations, variables) = self.spec_scope(item_fn);
+        let returned = match &item_fn.sig.output {
+            ReturnType::Type(_, ty) => Self::render_type(ty),
+            ReturnType::Default => "()".to_string(),
+        };
+        let mut problems = Vec::new();
+        for (role, annotation) in annotations {
+            let spec = Self::render_expr(&Expr::Macro(annotation.clone()));
+            let location = match file {
+                Some(file) => Self::location(file, annotation.span()),
+                None => format!("line {}", annotation.span().start().line),
+            };
+            let condition = match crate::spec_parser::annotation_condition(&annotation.mac.tokens) {
+                Ok(condition) => condition,
+                Err(e) => {
+                    problems.push(SpecProblem { location, owner: name.to_string(), spec, message: format!("can't parse the condition: {}", e) });
+                    continue;
+                },
+            };
+            let mut visible = variables.clone();
+            if role == AnnotationRole::Post {
+                visible.entry("result".to_string()).or_insert_with(|| returned.clone());
+            }
+            match role {
+                AnnotationRole::Decreases => self.check_measure(&condition, &visible, &location, name, &spec, &mut problems),
+                _ => self.check_condition(&condition, &visible, &location, name, &spec, &mut problems),
+            }
+        }
+        problems
+    }
+
+
+    fn check_function_specs(&mut self, file: &str, name: &str, item_fn: &ItemFn, check: &mut SpecCheck) {
+        let (annotations, variables) = self.spec_scope(item_fn);
+
+        // '//@ call' comments inside the function, with 'result' for the value of the call
+        let span = item_fn.span();
+        let (first, last) = (span.start().line, span.end().line);
+        let comments: Vec<(usize, String)> = self.file.source_lines.iter().enumerate()
+            .filter(|(index, _)| (first..=last).contains(&(index + 1)))
+            .filter_map(|(index, line)| {
+                let spec = line.trim().strip_prefix("//@ call ")?.trim();
+                let condition = spec.strip_prefix("pre:").or_else(|| spec.strip_prefix("post:"))?;
+                Some((index + 1, condition.trim().to_string()))
+            })
+            .collect();
+        if annotations.is_empty() && comments.is_empty() {
+            return;
+        }
+        check.functions += 1;
+        check.specs += annotations.len();
+        check.problems.extend(self.annotation_problems(Some(file), name, item_fn));
+
+        for (line, condition) in comments {
+            check.specs += 1;
+            let spec = format!("//@ call {}", condition);
+            let location = format!("{}:{}", file, line);
+            match syn::parse_str::<Expr>(&condition) {
+                Ok(condition) => {
+                    let mut visible = variables.clone();
+                    visible.entry("result".to_string()).or_default();
+                    self.check_condition(&condition, &visible, &location, name, &spec, &mut check.problems);
+                },
+                Err(e) => check.problems.push(SpecProblem { location, owner: name.to_string(), spec, message: format!("can't parse the condition: {}", e) }),
+            }
+        }
+    }
+
+    // A condition must be a boolean expression over visible names
+    fn check_condition(&self, condition: &Expr, variables: &HashMap<String, String>, location: &str, owner: &str, spec: &str, problems: &mut Vec<SpecProblem>) {
+        let mut messages = Vec::new();
+        if self.spec_type(condition, variables, &mut messages) == SpecType::Int {
+            messages.push("the condition is an integer, not a boolean".to_string());
+        }
+        problems.extend(messages.into_iter().map(|message| SpecProblem {
+            location: location.to_string(),
+            owner: owner.to_string(),
+            spec: spec.to_string(),
+            message,
+        }));
+    }
+
+    // A measure ('decreases!') must be an integer expression over visible names
+    fn check_measure(&self, measure: &Expr, variables: &HashMap<String, String>, location: &str, owner: &str, spec: &str, problems: &mut Vec<SpecProblem>) {
+        let mut messages = Vec::new();
+        if self.spec_type(measure, variables, &mut messages) == SpecType::Bool {
+            messages.push("the measure is a boolean, not an integer".to_string());
+        }
+        problems.extend(messages.into_iter().map(|message| SpecProblem {
+            location: location.to_string(),
+            owner: owner.to_string(),
+            spec: spec.to_string(),
+            message,
+        }));
+    }
+
+    // Type of a spec expression, with the undefined identifiers and the operands of the wrong type reported
+    fn spec_type(&self, expr: &Expr, variables: &HashMap<String, String>, messages: &mut Vec<String>) -> SpecType {
+        let expect = |operand: &Expr, expected: SpecType, operator: &str, messages: &mut Vec<String>| {
+            let found = self.spec_type(operand, variables, messages);
+            if found != SpecType::Unknown && found != expected {
+                messages.push(format!("'{}' expects {}, '{}' is {}", operator, expected.describe(), Self::render_expr(operand), found.describe()));
+            }
+        };
+        match expr {
+            Expr::Lit(expr_lit) => match expr_lit.lit {
+                Lit::Int(_) => SpecType::Int,
+                Lit::Bool(_) => SpecType::Bool,
+                _ => SpecType::Unknown,
+            },
+            Expr::Path(expr_path) => {
+                let Some(ident) = expr_path.path.get_ident().map(|ident| ident.to_string()) else { return SpecType::Unknown };
+                if let Some(ty) = variables.get(&ident) {
+                    return SpecType::of(ty);
+                }
+                match self.file.constants.get(&ident) {
+                    Some(ConstValue::Int(_)) => SpecType::Int,
+                    Some(ConstValue::Bool(_)) => SpecType::Bool,
+                    None => {
+                        messages.push(format!("undefined identifier '{}'", ident));
+                        SpecType::Unknown
+                    },
+                }
+            },
+            Expr::Paren(paren) => self.spec_type(&paren.expr, variables, messages),
+            Expr::Group(group) => self.spec_type(&group.expr, variables, messages),
+            Expr::Unary(unary) => match unary.op {
+                UnOp::Not(_) => {
+                    expect(&unary.expr, SpecType::Bool, "!", messages);
+                    SpecType::Bool
+                },
+                UnOp::Neg(_) => {
+                    expect(&unary.expr, SpecType::Int, "-", messages);
+                    SpecType::Int
+                },
+                UnOp::Deref(_) => self.spec_type(&unary.expr, variables, messages),
+            },
+            Expr::Binary(bin) => {
+                let operator = Self::render_binop(&bin.op);
+                match bin.op {
+                    BinOp::Add(_) | BinOp::Sub(_) | BinOp::Mul(_) | BinOp::Div(_) | BinOp::Rem(_) => {
+                        expect(&bin.left, SpecType::Int, &operator, messages);
+                        expect(&bin.right, SpecType::Int, &operator, messages);
+                        SpecType::Int
+                    },
+                    BinOp::Lt(_) | BinOp::Le(_) | BinOp::Gt(_) | BinOp::Ge(_) => {
+                        expect(&bin.left, SpecType::Int, &operator, messages);
+                        expect(&bin.right, SpecType::Int, &operator, messages);
+                        SpecType::Bool
+                    },
+                    BinOp::And(_) | BinOp::Or(_) => {
+                        expect(&bin.left, SpecType::Bool, &operator, messages);
+                        expect(&bin.right, SpecType::Bool, &operator, messages);
+                        SpecType::Bool
+                    },
+                    BinOp::Eq(_) | BinOp::Ne(_) => {
+                        let (left, right) = (self.spec_type(&bin.left, variables, messages), self.spec_type(&bin.right, variables, messages));
+                        if left != SpecType::Unknown && right != SpecType::Unknown && left != right {
+                            messages.push(format!("'{}' compares {} with {}", operator, left.describe(), right.describe()));
+                        }
+                        SpecType::Bool
+                    },
+                    _ => {
+                        self.spec_type(&bin.left, variables, messages);
+                        self.spec_type(&bin.right, variables, messages);
+                        SpecType::Unknown
+                    },
+                }
+            },
+            Expr::Cast(cast) => {
+                self.spec_type(&cast.expr, variables, messages);
+                SpecType::of(&Self::render_type(&cast.ty))
+            },
+            Expr::Field(field) => {
+                self.spec_type(&field.base, variables, messages);
+                SpecType::Unknown
+            },
+            Expr::Index(index) => {
+                self.spec_type(&index.expr, variables, messages);
+                expect(&index.index, SpecType::Int, "[]", messages);
+                SpecType::Unknown
+            },
+            Expr::MethodCall(method_call) => {
+                self.spec_type(&method_call.receiver, variables, messages);
+                for arg in &method_call.args {
+                    self.spec_type(arg, variables, messages);
+                }
+                match method_call.method.to_string().as_str() {
+                    "len" => SpecType::Int,
+                    "is_some" | "is_none" | "is_ok" | "is_err" => SpecType::Bool,
+                    _ => SpecType::Unknown,
+                }
+            },
+            // 'forall(a..b, |i| body)': integer bounds, and a boolean body where 'i' is an integer
+            Expr::Call(_) if Quantifier::of(expr).is_some() => {
+                let quantifier = Quantifier::of(expr).expect("checked by the guard");
+                let keyword = quantifier.kind.keyword();
+                for bound in [&quantifier.range.from, &quantifier.range.to].into_iter().flatten() {
+                    expect(bound, SpecType::Int, keyword, messages);
+                }
+                let mut scope = variables.clone();
+                scope.insert(quantifier.variable.to_string(), "i128".to_string());
+                let body = self.spec_type(&quantifier.body, &scope, messages);
+                if body == SpecType::Int {
+                    messages.push(format!("the condition of '{}' is an integer, not a boolean", keyword));
+                }
+                SpecType::Bool
+            },
+            // 'old(x)': the value of 'x' on entry, of its type
+            Expr::Call(_) if Self::old_argument(expr).is_some() => {
+                self.spec_type(Self::old_argument(expr).expect("checked by the guard"), variables, messages)
+            },
+            Expr::Call(call) => {
+                for arg in &call.args {
+                    self.spec_type(arg, variables, messages);
+                }
+                let Expr::Path(func) = &*call.func else { return SpecType::Unknown };
+                let name = Self::render_path(&func.path);
+                // Variant tests of 'Option' and 'Result', resolved on constructed values
+                if ["is_some", "is_none", "is_ok", "is_err"].contains(&name.as_str()) {
+                    return SpecType::Bool;
+                }
+                let function = self.file.local_functions.iter()
+                    .find(|(qualified, _)| *qualified == &name || qualified.ends_with(&format!("::{}", name)))
+                    .map(|(_, item_fn)| item_fn);
+                match function {
+                    Some(item_fn) => match &item_fn.sig.output {
+                        ReturnType::Type(_, ty) => SpecType::of(&Self::render_type(ty)),
+                        ReturnType::Default => SpecType::Unknown,
+                    },
+                    None if variables.contains_key(&name) || func.path.segments.len() > 1 => SpecType::Unknown,
+                    None => {
+                        messages.push(format!("undefined function '{}'", name));
+                        SpecType::Unknown
+                    },
+                }
+            },
+            _ => SpecType::Unknown,
+        }
+    }
+
+    pub(crate) fn location(file: &str, span: proc_macro2::Span) -> String {
+        format!("{}:{}:{}", file, span.start().line, span.start().column + 1)
+    }
+}
@@ -0,0 +1,354 @@
+use petgraph::algo::dominators;
+use petgraph::graph::NodeIndex;
+use petgraph::Direction;
+use quote::ToTokens;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use syn::parse::Parser;
+use syn::punctuated::Punctuated;
+use syn::visit_mut::{self, VisitMut};
+use syn::{Expr, FieldValue, Member, Pat, PatIdent, Stmt, Token};
+
+use crate::cfg_builder::builder::CfgBuilder;
+use crate::cfg_builder::node::{CfgNode, ConditionalExpr};
+
+// Versions of the assigned variables of a function while it is renamed. A variable read before any of its
+// assignments has version 0, its value on entry; the variables never assigned keep their name.
+#[derive(Default)]
+struct Versions {
+    assigned: HashSet<String>,
+    stacks: HashMap<String, Vec<usize>>, // versions visible from the node being renamed, innermost last
+    counters: HashMap<String, usize>,
+    shadowed: Vec<String>, // parameters of the closures being renamed
+    in_old: bool, // in 'old(..)', which reads the values on entry
+}
+
+impl Versions {
+    fn current(&self, var: &str) -> usize {
+        match self.in_old {
+            true => 0,
+            false => self.stacks.get(var).and_then(|stack| stack.last()).copied().unwrap_or(0),
+        }
+    }
+
+    fn define(&mut self, var: &str) -> usize {
+        let counter = self.counters.entry(var.to_string()).or_default();
+        *counter += 1;
+        let version = *counter;
+        self.stacks.entry(var.to_string()).or_default().push(version);
+        version
+    }
+
+    fn is_renamed(&self, var: &str) -> bool {
+        self.assigned.contains(var) && !self.shadowed.iter().any(|name| name == var)
+    }
+
+    // 'r#type' version 1 is 'type_1', which is no keyword
+    fn versioned_name(name: &str, version: usize) -> String {
+        format!("{}_{}", name.trim_start_matches("r#"), version)
+    }
+
+    fn versioned(name: &str, version: usize, span: proc_macro2::Span) -> syn::Ident {
+        syn::Ident::new(&Self::versioned_name(name, version), span)
+    }
+}
+
+// Uses of the assigned variables, renamed to their current version: 'i' is 'i_2', and a field place
+// ('self.len') is renamed on its last member, 'self.len_1'. In 'old(..)' they are all at version 0.
+impl VisitMut for Versions {
+    fn visit_expr_mut(&mut self, expr: &mut Expr) {
+        if CfgBuilder::old_argument(expr).is_some() && !self.in_old {
+            self.in_old = true;
+            visit_mut::visit_expr_mut(self, expr);
+            self.in_old = false;
+            return;
+        }
+        let place = CfgBuilder::field_place_name(expr).filter(|place| self.is_renamed(place));
+        match (expr, place) {
+            (Expr::Path(expr_path), Some(place)) if expr_path.qself.is_none() => {
+                let span = expr_path.path.segments[0].ident.span();
+                expr_path.path = Self::versioned(&place, self.current(&place), span).into();
+            },
+            (Expr::Field(field), Some(place)) => {
+                if let Member::Named(member) = &field.member {
+                    field.member = Member::Named(Self::versioned(&member.to_string(), self.current(&place), member.span()));
+                }
+            },
+            (Expr::Closure(closure), _) => {
+                let params: Vec<String> = closure.inputs.iter().flat_map(CfgBuilder::pattern_bindings).collect();
+                let outer = self.shadowed.len();
+                self.shadowed.extend(params);
+                self.visit_expr_mut(&mut closure.body);
+                self.shadowed.truncate(outer);
+            },
+            (expr, _) => visit_mut::visit_expr_mut(self, expr),
+        }
+    }
+
+    // 'Point { x }' stays 'Point { x: x_1 }'
+    fn visit_field_value_mut(&mut self, field_value: &mut FieldValue) {
+        self.visit_expr_mut(&mut field_value.expr);
+        let shorthand = matches!((&field_value.member, &field_value.expr), (Member::Named(member), Expr::Path(path)) if path.path.is_ident(member));
+        if !shorthand {
+            field_value.colon_token.get_or_insert_with(Default::default);
+        }
+    }
+
+    // Arguments of 'assert!(..)', 'invariant!(..)', ...
+    fn visit_macro_mut(&mut self, mac: &mut syn::Macro) {
+        let Ok(mut args) = Punctuated::<Expr, Token![,]>::parse_terminated.parse2(mac.tokens.clone()) else { return };
+        for arg in args.iter_mut() {
+            self.visit_expr_mut(arg);
+        }
+        mac.tokens = args.into_token_stream();
+    }
+}
+
+// The bindings of a pattern get a new version, e.g. the variable of a 'for' loop at each iteration
+struct Bindings<'a> {
+    versions: &'a mut Versions,
+    defined: &'a mut Vec<String>,
+}
+
+impl VisitMut for Bindings<'_> {
+    fn visit_pat_ident_mut(&mut self, pat_ident: &mut PatIdent) {
+        let name = pat_ident.ident.to_string();
+        if self.versions.assigned.contains(&name) && !name.starts_with(char::is_uppercase) {
+            pat_ident.ident = Versions::versioned(&name, self.versions.define(&name), pat_ident.ident.span());
+            self.defined.push(name);
+        }
+        visit_mut::visit_pat_ident_mut(self, pat_ident);
+    }
+}
+
+// Renaming step of the walk over the dominator tree: a node is renamed on the way down, and the versions
+// it defined are dropped once its subtree is done
+enum RenameStep {
+    Enter(NodeIndex),
+    Exit(Vec<String>),
+}
+
+impl CfgBuilder {
+    // Labels of the graph in SSA form, for the '--debug-phases' snapshot: every assignment defines a new version of
+    // its variable ('s_2 = s_1 + i_2'), and the nodes where versions from several paths meet start with a
+    // 'phi' choosing between them, by predecessor. The verification conditions don't use this form.
+    pub fn ssa_labels(&self) -> HashMap<NodeIndex, String> {
+        self.function_nodes().into_iter()
+            .flat_map(|func_node| self.function_ssa_labels(func_node))
+            .collect()
+    }
+
+    // Minimal SSA of one function: phis are placed on the iterated dominance frontiers of the assignments,
+    // then the variables are renamed along the dominator tree
+    fn function_ssa_labels(&self, func_node: NodeIndex) -> HashMap<NodeIndex, String> {
+        let nodes = self.function_subgraph_nodes(func_node);
+        let dominators = dominators::simple_fast(&self.graph, func_node);
+        let predecessors = |node: NodeIndex| -> BTreeSet<NodeIndex> {
+            self.graph.neighbors_directed(node, Direction::Incoming).filter(|pred| nodes.binary_search(pred).is_ok()).collect()
+        };
+
+        // A node is on the frontier of the nodes that dominate one of its predecessors but not itself
+        let mut frontiers: HashMap<NodeIndex, BTreeSet<NodeIndex>> = HashMap::new();
+        for &node in &nodes {
+            let preds = predecessors(node);
+            let Some(node_idom) = dominators.immediate_dominator(node).filter(|_| preds.len() > 1) else { continue };
+            for mut runner in preds {
+                while runner != node_idom {
+                    frontiers.entry(runner).or_default().insert(node);
+                    let Some(next) = dominators.immediate_dominator(runner) else { break };
+                    runner = next;
+                }
+            }
+        }
+
+        let definitions: HashMap<NodeIndex, Vec<String>> = nodes.iter().map(|&node| (node, self.ssa_definitions(node))).collect();
+        let assigned: BTreeSet<String> = definitions.values().flatten().cloned().collect();
+        let mut phis: HashMap<NodeIndex, BTreeSet<String>> = HashMap::new();
+        for var in &assigned {
+            let mut work: Vec<NodeIndex> = nodes.iter().copied().filter(|node| definitions[node].contains(var)).collect();
+            let mut placed = HashSet::new();
+            while let Some(node) = work.pop() {
+                for &frontier in frontiers.get(&node).into_iter().flatten() {
+                    if placed.insert(frontier) {
+                        phis.entry(frontier).or_default().insert(var.clone());
+                        work.push(frontier);
+                    }
+                }
+            }
+        }
+
+        let mut children: HashMap<NodeIndex, Vec<NodeIndex>> = HashMap::new();
+        for &node in &nodes {
+            if let Some(parent) = dominators.immediate_dominator(node) {
+                children.entry(parent).or_default().push(node);
+            }
+        }
+        let mut versions = Versions { assigned: assigned.into_iter().collect(), ..Default::default() };
+        let mut phi_results: HashMap<NodeIndex, Vec<(String, String)>> = HashMap::new(); // variable, version
+        let mut phi_arguments: HashMap<(NodeIndex, String), Vec<(NodeIndex, String)>> = HashMap::new(); // by predecessor
+        let mut labels = HashMap::new();
+        let mut steps = vec![RenameStep::Enter(func_node)];
+        while let Some(step) = steps.pop() {
+            let node = match step {
+                RenameStep::Enter(node) => node,
+                RenameStep::Exit(defined) => {
+                    for var in defined {
+                        versions.stacks.get_mut(&var).and_then(Vec::pop);
+                    }
+                    continue;
+                },
+            };
+            let mut defined = Vec::new();
+            for var in phis.get(&node).into_iter().flatten() {
+                let version = Versions::versioned_name(var, versions.define(var));
+                phi_results.entry(node).or_default().push((var.clone(), version));
+                defined.push(var.clone());
+            }
+            labels.insert(node, self.ssa_label(node, &mut versions, &mut defined));
+            let successors: BTreeSet<NodeIndex> = self.graph.neighbors(node).collect();
+            for successor in successors {
+                for var in phis.get(&successor).into_iter().flatten() {
+                    let version = Versions::versioned_name(var, versions.current(var));
+                    phi_arguments.entry((successor, var.clone())).or_default().push((node, version));
+                }
+            }
+            steps.push(RenameStep::Exit(defined));
+            for &child in children.get(&node).into_iter().flatten().rev() {
+                steps.push(RenameStep::Enter(child));
+            }
+        }
+
+        for (node, results) in phi_results {
+            let mut lines: Vec<String> = results.into_iter()
+                .map(|(var, version)| {
+                    let mut arguments = phi_arguments.remove(&(node, var)).unwrap_or_default();
+                    arguments.sort();
+                    let arguments: Vec<String> = arguments.into_iter().map(|(_, argument)| argument).collect();
+                    format!("{} = phi({})", version, arguments.join(", "))
+                })
+                .collect();
+            if let Some(label) = labels.remove(&node) {
+                lines.push(label);
+            }
+            labels.insert(node, lines.join("\\n"));
+        }
+        labels
+    }
+
+    // Variables a node assigns: the target of an assignment (as the verification conditions name it), the
+    // bindings of a 'let' and the variables of a 'for' loop
+    fn ssa_definitions(&self, node: NodeIndex) -> Vec<String> {
+        let bindings = |pat: &Pat| -> Vec<String> {
+            Self::pattern_bindings(pat).into_iter().filter(|name| !name.starts_with(char::is_uppercase)).collect()
+        };
+        match &self.graph[node] {
+            CfgNode::Statement(label, stmt) => {
+                if let Some((var, _)) = self.parse_assignment(label) {
+                    return vec![var];
+                }
+                match stmt.clone().or_else(|| syn::parse_str(label).ok()) {
+                    Some(Stmt::Local(local)) => bindings(&local.pat),
+                    _ => Vec::new(),
+                }
+            },
+            CfgNode::Condition(_, Some(ConditionalExpr::ForLoop(expr_for))) => bindings(&expr_for.pat),
+            _ => Vec::new(),
+        }
+    }
+
+    // Label of a node with the uses renamed to their current version, and the variables it assigns to new ones
+    fn ssa_label(&self, node: NodeIndex, versions: &mut Versions, defined: &mut Vec<String>) -> String {
+        let label = self.graph[node].label();
+        let prefix = label.split_once(": ").map(|(prefix, _)| prefix.to_string()).unwrap_or_default();
+        let renamed = |versions: &mut Versions, expr: &Expr| -> String {
+            let mut expr = expr.clone();
+            versions.visit_expr_mut(&mut expr);
+            Self::render_expr(&expr)
+        };
+        match &self.graph[node] {
+            CfgNode::Statement(stmt_str, stmt) => {
+                if let Some((var, value)) = self.parse_assignment(stmt_str) {
+                    let value = renamed(versions, &value);
+                    defined.push(var.clone());
+                    return format!("{} = {}", Versions::versioned_name(&var, versions.define(&var)), value);
+                }
+                let Some(mut stmt) = stmt.clone().or_else(|| syn::parse_str(stmt_str).ok()) else { return label };
+                match &mut stmt {
+                    Stmt::Local(local) => {
+                        if let Some((_, init)) = &mut local.init {
+                            versions.visit_expr_mut(init);
+                        }
+                        Bindings { versions, defined }.visit_pat_mut(&mut local.pat);
+                    },
+                    stmt => versions.visit_stmt_mut(stmt),
+                }
+                Self::render_stmt(&stmt)
+            },
+            CfgNode::Condition(_, Some(ConditionalExpr::ForLoop(expr_for))) => {
+                let iterator = renamed(versions, &expr_for.expr);
+                let mut pat = expr_for.pat.clone();
+                Bindings { versions, defined }.visit_pat_mut(&mut pat);
+                format!("for {} in {}", Self::render_pat(&pat), iterator)
+            },
+            CfgNode::Condition(_, Some(condition)) => format!("{}: {}", prefix, renamed(versions, condition.to_syn_expr())),
+            CfgNode::Precondition(_, Some(expr)) | CfgNode::Assumption(_, Some(expr)) | CfgNode::Postcondition(_, Some(expr))
+            | CfgNode::Invariant(_, Some(expr)) | CfgNode::Assertion(_, Some(expr)) | CfgNode::Decreases(_, Some(expr)) => {
+                // Some annotations keep their macro, 'invariant!(..)'
+                let condition = match expr {
+                    Expr::Macro(expr_macro) => expr_macro.mac.parse_body::<Expr>().unwrap_or_else(|_| expr.clone()),
+                    expr => expr.clone(),
+                };
+                format!("{}: {}", prefix, renamed(versions, &Self::without_parens(&condition)))
+            },
+            CfgNode::Return(_, Some(expr_return)) => match &expr_return.expr {
+                Some(value) => format!("return: {}", renamed(versions, value)),
+                None => label,
+            },
+            _ => label,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_support::built;
+
+    fn ssa_labels_of(source: &str) -> Vec<String> {
+        let mut labels: Vec<String> = built(source).ssa_labels().into_values().collect();
+        labels.sort();
+        labels
+    }
+
+    #[test]
+    fn loop_heads_choose_between_the_entry_and_the_back_edge() {
+        let labels = ssa_labels_of("fn sum_to(n: u32) -> u32 {
+            let mut s = 0;
+            let mut i = 0;
+            invariant!(i <= n);
+            while i < n {
+                s += i;
+                i = i + 1;
+            }
+            return s;
+        }");
+        assert!(labels.contains(&"i_2 = phi(i_1, i_3)\\ns_2 = phi(s_1, s_3)\\n@Inv: i_2 <= n".to_string()), "{:?}", labels);
+        for label in ["s_1 = 0", "i_1 = 0", "while: i_2 < n", "s_3 = s_2 + i_2", "i_3 = i_2 + 1", "return: s_2"] {
+            assert!(labels.contains(&label.to_string()), "{} in {:?}", label, labels);
+        }
+    }
+
+    #[test]
+    fn branches_meet_in_a_phi_and_read_before_assigned_is_version_0() {
+        let labels = ssa_labels_of("fn clamp(mut x: i32, hi: i32) -> i32 {
+            post!(x <= hi);
+            if x > hi {
+                x = hi;
+            } else {
+                x = x + 0;
+            }
+            return x;
+        }");
+        for label in ["if: x_0 > hi", "x_1 = hi", "x_2 = x_0 + 0", "x_3 = phi(x_1, x_2)\\nreturn: x_3"] {
+            assert!(labels.contains(&label.to_string()), "{} in {:?}", label, labels);
+        }
+    }
+}
@@ -0,0 +1,220 @@
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+use syn::visit::{self, Visit};
+use syn::{BinOp, Expr, ExprBinary, ExprClosure, ExprReturn, FnArg, ImplItem, ItemFn, ItemImpl, ItemMacro, ReturnType, Stmt, Token, Type};
+
+use crate::cfg_builder::builder::CfgBuilder;
+use crate::config::AnnotationRole;
+
+// 'struct_invariant!(Counter: self.len <= self.cap)': the type and the condition on 'self'
+pub(crate) struct StructInvariant {
+    pub(crate) type_name: syn::Ident,
+    pub(crate) condition: Expr,
+}
+
+impl syn::parse::Parse for StructInvariant {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        let type_name = input.parse()?;
+        input.parse::<Token![:]>()?;
+        Ok(StructInvariant { type_name, condition: input.parse()? })
+    }
+}
+
+// Values returned by the explicit 'return's of a body
+#[derive(Default)]
+struct ReturnCollector {
+    values: Vec<Option<Expr>>,
+}
+
+impl<'ast> Visit<'ast> for ReturnCollector {
+    fn visit_expr_return(&mut self, i: &'ast ExprReturn) {
+        self.values.push(i.expr.as_deref().cloned());
+        visit::visit_expr_return(self, i);
+    }
+
+    // A 'return' in a closure leaves the closure only
+    fn visit_expr_closure(&mut self, _i: &'ast ExprClosure) {}
+}
+
+impl CfgBuilder {
+    // Record a 'struct_invariant!' item of the file
+    pub fn collect_struct_invariant(&mut self, item_macro: &ItemMacro) {
+        if !item_macro.mac.path.is_ident("struct_invariant") {
+            return;
+        }
+        match syn::parse2::<StructInvariant>(item_macro.mac.tokens.clone()) {
+            Ok(invariant) => self.file.struct_invariants.entry(invariant.type_name.to_string()).or_default().push(invariant.condition),
+            Err(e) => eprintln!("Warning: can't parse struct_invariant!({}): {}", item_macro.mac.tokens, e),
+        }
+    }
+
+    // Methods of an 'impl' block, inherent or of a trait, each analyzed as 'Type::method'. When the type has
+    // invariants, constructors ensure them and '&mut self' methods preserve them: the invariants are conjoined
+    // to the 'pre!'/'post!' annotations of the method, unless it is opted out in 'secured.toml'.
+    pub fn handle_struct_impl(&mut self, item_impl: &ItemImpl) {
+        let Type::Path(self_ty) = &*item_impl.self_ty else { return };
+        let Some(type_name) = self_ty.path.segments.last().map(|segment| segment.ident.to_string()) else { return };
+        let invariants = self.file.struct_invariants.get(&type_name).cloned().unwrap_or_default();
+
+        for item in &item_impl.items {
+            let ImplItem::Method(method) = item else { continue };
+            let func_name = self.qualified_name(&format!("{}::{}", type_name, method.sig.ident));
+            let mut item_fn = ItemFn {
+                attrs: method.attrs.clone(),
+                vis: method.vis.clone(),
+                sig: method.sig.clone(),
+                block: Box::new(method.block.clone()),
+            };
+
+            if Self::is_logic_function(&item_fn) {
+                continue;
+            }
+
+            let opted_out = invariants.is_empty() || self.file.struct_invariant_opt_outs.contains(&func_name);
+            let role = if opted_out { None } else { Self::invariant_role(&item_fn, &type_name) };
+            let fact = match role {
+                Some(InvariantRole::Preserved) => {
+                    self.conjoin_annotation(&mut item_fn.block.stmts, AnnotationRole::Pre, &invariants);
+                    self.conjoin_annotation(&mut item_fn.block.stmts, AnnotationRole::Post, &invariants);
+                    Some(format!("struct invariant of {}: assumed on entry, checked on exit", type_name))
+                },
+                Some(InvariantRole::Established) => match Self::constructed_value(&item_fn) {
+                    Some(value) => {
+                        // The paths checking the postconditions start at a precondition
+                        self.conjoin_annotation(&mut item_fn.block.stmts, AnnotationRole::Pre, &[]);
+                        let established: Vec<Expr> = invariants.iter()
+                            .map(|condition| self.recursive_substitution(condition, "self", &value))
+                            .collect();
+                        self.conjoin_annotation(&mut item_fn.block.stmts, AnnotationRole::Post, &established);
+                        Some(format!("struct invariant of {}: checked on the constructed value", type_name))
+                    },
+                    None => {
+                        eprintln!("Warning: {} doesn't return a single struct literal or variable, its struct invariant is not checked", func_name);
+                        None
+                    },
+                },
+                None => None,
+            };
+
+            if let Some(func_node) = self.build_function_cfg(func_name, &item_fn) {
+                if let Some(fact) = fact {
+                    self.metadata_mut(func_node).facts.push(fact);
+                }
+            }
+        }
+    }
+
+    // Whether a method establishes the invariants (constructor returning the type) or preserves them ('&mut self')
+    fn invariant_role(item_fn: &ItemFn, type_name: &str) -> Option<InvariantRole> {
+        match item_fn.sig.inputs.first() {
+            Some(FnArg::Receiver(receiver)) if receiver.reference.is_some() && receiver.mutability.is_some() => Some(InvariantRole::Preserved),
+            Some(FnArg::Receiver(_)) => None,
+            _ => match &item_fn.sig.output {
+                ReturnType::Type(_, ty) => {
+                    let Type::Path(ty) = &**ty else { return None };
+                    (ty.path.is_ident("Self") || ty.path.is_ident(type_name)).then_some(InvariantRole::Established)
+                },
+                ReturnType::Default => None,
+            },
+        }
+    }
+
+    // Value a constructor returns on every path: a struct literal or a variable, the tail of the body
+    // and of every explicit 'return'
+    fn constructed_value(item_fn: &ItemFn) -> Option<Expr> {
+        let Some(Stmt::Expr(tail)) = item_fn.block.stmts.last() else { return None };
+        if !matches!(tail, Expr::Struct(_) | Expr::Path(_)) {
+            return None;
+        }
+        let mut returns = ReturnCollector::default();
+        returns.visit_block(&item_fn.block);
+        let rendered = Self::render_expr(tail);
+        returns.values.iter()
+            .all(|value| value.as_ref().is_some_and(|value| Self::render_expr(value) == rendered))
+            .then(|| tail.clone())
+    }
+
+    // Conjoin the invariants to the first annotation of the role, so that they share its cut point, or add
+    // one ('pre!(true)' when there are none to conjoin) if the method has none
+    fn conjoin_annotation(&self, stmts: &mut Vec<Stmt>, role: AnnotationRole, conditions: &[Expr]) {
+        let position = stmts.iter().position(|stmt| matches!(stmt, Stmt::Semi(Expr::Macro(expr_macro), _)
+            if expr_macro.mac.path.get_ident().and_then(|ident| self.options.annotation_names.role_of(&ident.to_string())) == Some(role)));
+        let own = position.and_then(|index| match &stmts[index] {
+            Stmt::Semi(Expr::Macro(expr_macro), _) => crate::spec_parser::annotation_condition(&expr_macro.mac.tokens).ok(),
+            _ => None,
+        });
+        let Some(condition) = own.into_iter().chain(conditions.iter().cloned()).reduce(|left, right| Expr::Binary(ExprBinary {
+            attrs: Vec::new(),
+            left: Box::new(left),
+            op: BinOp::And(Default::default()),
+            right: Box::new(right),
+        })) else {
+            if position.is_none() {
+                stmts.insert(0, Self::annotation_stmt(syn::parse_quote!(pre!(true))));
+            }
+            return;
+        };
+        // Rendered, so that the synthesized conjunction keeps its precedence in the tokens
+        let tokens: TokenStream = syn::parse_str(&Self::render_expr(&condition)).unwrap_or_else(|_| quote!(#condition));
+        let macro_name = syn::Ident::new(role.macro_name(), Span::call_site());
+        let annotation = Self::annotation_stmt(syn::parse_quote!(#macro_name!(#tokens)));
+        match position {
+            Some(index) => stmts[index] = annotation,
+            None => {
+                // After the precondition, where the method has one
+                let index = stmts.iter().position(|stmt| self.is_precondition_statement(stmt)).map_or(0, |index| index + 1);
+                stmts.insert(if role == AnnotationRole::Pre { 0 } else { index }, annotation);
+            },
+        }
+    }
+
+    fn annotation_stmt(annotation: Expr) -> Stmt {
+        Stmt::Semi(annotation, Default::default())
+    }
+}
+
+enum InvariantRole {
+    Established, // constructor: the invariants hold for the value it returns
+    Preserved, // '&mut self' method: the invariants hold before and after
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_support;
+    use crate::verifier::VcOutcome;
+
+    fn verdicts(method: &str) -> Vec<VcOutcome> {
+        let source = format!("struct Counter {{ len: u32, cap: u32 }}
+            struct_invariant!(Counter: self.len <= self.cap);
+            impl Counter {{ {} }}", method);
+        test_support::verdicts(&source)
+    }
+
+    #[test]
+    fn mut_self_methods_preserve_the_invariant() {
+        let outcomes = verdicts("fn push(&mut self) { if self.len < self.cap { self.len = self.len + 1; } }");
+        assert!(!outcomes.is_empty() && outcomes.iter().all(|outcome| matches!(outcome, VcOutcome::Valid)));
+        let outcomes = verdicts("fn push(&mut self) { self.len = self.len + 1; }");
+        assert!(matches!(outcomes.as_slice(), [VcOutcome::Counterexample(_)]));
+    }
+
+    #[test]
+    fn constructors_establish_the_invariant() {
+        let outcomes = verdicts("fn new(n: u32) -> Self { Self { len: 0, cap: n } }");
+        assert!(matches!(outcomes.as_slice(), [VcOutcome::Valid]));
+        let outcomes = verdicts("fn new(n: u32) -> Self { Self { len: n + 1, cap: n } }");
+        assert!(matches!(outcomes.as_slice(), [VcOutcome::Counterexample(_)]));
+    }
+
+    #[test]
+    fn a_method_with_its_own_precondition_assumes_the_invariant() {
+        let outcomes = verdicts("fn push(&mut self) { pre!(true); if self.len < self.cap { self.len = self.len + 1; } }");
+        assert!(!outcomes.is_empty() && outcomes.iter().all(|outcome| matches!(outcome, VcOutcome::Valid)));
+    }
+
+    #[test]
+    fn a_constructor_with_its_own_postcondition_keeps_its_precondition() {
+        let outcomes = verdicts("fn new(n: u32) -> Self { pre!(n > 0); post!(n >= 1); Self { len: 0, cap: n } }");
+        assert!(matches!(outcomes.as_slice(), [VcOutcome::Valid]));
+    }
+}
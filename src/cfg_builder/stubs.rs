@@ -0,0 +1,47 @@
+use syn::{Expr, ItemFn, Stmt};
+
+use crate::cfg_builder::builder::CfgBuilder;
+use crate::cfg_builder::node::CfgNode;
+use crate::config::AnnotationRole;
+
+impl CfgBuilder {
+    // Role and condition of each statement of a body made only of annotations ('pre!', 'post!', ..); None when
+    // the body has other statements or none at all
+    pub(crate) fn stub_annotations(&self, item_fn: &ItemFn) -> Option<Vec<(AnnotationRole, String)>> {
+        let annotations: Vec<(AnnotationRole, String)> = item_fn.block.stmts.iter()
+            .map(|stmt| match stmt {
+                Stmt::Semi(Expr::Macro(expr_macro), _) => {
+                    let role = self.options.annotation_names.role_of(&expr_macro.mac.path.get_ident()?.to_string())?;
+                    let canonical = Self::canonical_annotation(expr_macro, role);
+                    let Expr::Macro(canonical) = canonical else { return None };
+                    Some((role, self.format_macro_args(&canonical.mac.tokens)))
+                },
+                _ => None,
+            })
+            .collect::<Option<_>>()?;
+        (!annotations.is_empty()).then_some(annotations)
+    }
+
+    // A stub still gets a path from its entry to its postconditions: without a 'pre!', it starts at an implicit
+    // 'pre!(true)'. Without a body, a postcondition that isn't one of the preconditions is a claim nothing
+    // establishes, so it is reported.
+    pub(crate) fn add_stub_entry(&mut self, func_name: &str, item_fn: &ItemFn) {
+        let Some(annotations) = self.stub_annotations(item_fn) else { return };
+        let preconditions: Vec<&String> = annotations.iter().filter(|(role, _)| *role == AnnotationRole::Pre).map(|(_, pre)| pre).collect();
+        let unsupported: Vec<&String> = annotations.iter()
+            .filter(|(role, post)| *role == AnnotationRole::Post && post != "true" && !preconditions.contains(&post))
+            .map(|(_, post)| post)
+            .collect();
+        if !unsupported.is_empty() {
+            eprintln!("Warning: {} has only annotations, so its postconditions must follow from its preconditions alone: {}", func_name,
+                unsupported.iter().map(|post| post.as_str()).collect::<Vec<_>>().join("; "));
+        }
+        let has_post = annotations.iter().any(|(role, _)| *role == AnnotationRole::Post);
+        if preconditions.is_empty() && has_post {
+            let span = item_fn.sig.ident.span();
+            let annotation: Expr = syn::parse_quote_spanned!(span=> pre!(true));
+            let pre_node = self.add_node(CfgNode::new_precondition("true".to_string(), annotation));
+            self.metadata_mut(pre_node).facts.push(format!("implicit: {} has no statements and no precondition", func_name));
+        }
+    }
+}
@@ -0,0 +1,264 @@
+use petgraph::graph::NodeIndex;
+use petgraph::visit::EdgeRef;
+use std::collections::HashSet;
+use syn::visit::{self, Visit};
+use syn::{ExprCall, ExprMethodCall, ItemFn};
+
+use crate::cfg_builder::builder::CfgBuilder;
+use crate::cfg_builder::node::CfgNode;
+
+// Condensed view of one function of the whole-file graph
+#[derive(Debug, Clone)]
+pub struct FunctionSummary {
+    pub name: String,
+    pub node: NodeIndex,
+    pub preconditions: Vec<String>,
+    pub postconditions: Vec<String>,
+    pub invariants: Vec<String>,
+    pub complexity: usize, // cyclomatic complexity of the function CFG (E - N + 2)
+    pub calls: Vec<String>, // other functions of the file called from this one
+}
+
+// Collects the names of called functions and methods in a function body
+struct CallCollector {
+    calls: Vec<String>,
+}
+
+impl<'ast> Visit<'ast> for CallCollector {
+    fn visit_expr_call(&mut self, i: &'ast ExprCall) {
+        if let syn::Expr::Path(expr_path) = &*i.func {
+            if let Some(segment) = expr_path.path.segments.last() {
+                self.calls.push(segment.ident.to_string());
+            }
+        }
+        visit::visit_expr_call(self, i);
+    }
+
+    fn visit_expr_method_call(&mut self, i: &'ast ExprMethodCall) {
+        self.calls.push(i.method.to_string());
+        visit::visit_expr_method_call(self, i);
+    }
+}
+
+impl CfgBuilder {
+    // Function entry nodes, in graph order
+    pub fn function_nodes(&self) -> Vec<NodeIndex> {
+        self.graph.node_indices()
+            .filter(|&n| matches!(self.graph[n], CfgNode::Function(_, _)))
+            .collect()
+    }
+
+    // All nodes reachable from a function entry node
+    pub fn function_subgraph_nodes(&self, func_node: NodeIndex) -> Vec<NodeIndex> {
+        let mut visited = HashSet::new();
+        let mut stack = vec![func_node];
+        let mut nodes = Vec::new();
+        while let Some(node) = stack.pop() {
+            if !visited.insert(node) {
+                continue;
+            }
+            nodes.push(node);
+            for edge in self.graph.edges(node) {
+                stack.push(edge.target());
+            }
+        }
+        nodes.sort();
+        nodes
+    }
+
+    // Name of the function whose CFG contains the node
+    pub fn function_of_node(&self, node: NodeIndex) -> Option<String> {
+        self.function_nodes().into_iter()
+            .find(|&func_node| self.function_subgraph_nodes(func_node).contains(&node))
+            .map(|func_node| self.graph[func_node].label())
+    }
+
+    pub fn function_summaries(&self) -> Vec<FunctionSummary> {
+        let functions = self.function_nodes();
+        let function_names: HashSet<String> = functions.iter()
+            .filter_map(|&n| match &self.graph[n] {
+                CfgNode::Function(name, _) => Some(name.clone()),
+                _ => None,
+            })
+            .collect();
+
+        functions.iter().filter_map(|&func_node| {
+            let (name, item_fn) = match &self.graph[func_node] {
+                CfgNode::Function(name, item_fn) => (name.clone(), item_fn.clone()),
+                _ => return None,
+            };
+            let nodes = self.function_subgraph_nodes(func_node);
+            let node_set: HashSet<NodeIndex> = nodes.iter().copied().collect();
+
+            let mut summary = FunctionSummary {
+                name,
+                node: func_node,
+                preconditions: Vec::new(),
+                postconditions: Vec::new(),
+                invariants: Vec::new(),
+                complexity: 0,
+                calls: Vec::new(),
+            };
+            for &node in &nodes {
+                match &self.graph[node] {
+                    CfgNode::Precondition(pre, _) => summary.preconditions.push(pre.clone()),
+                    CfgNode::Postcondition(post, _) => summary.postconditions.push(post.clone()),
+                    CfgNode::Invariant(inv, _) => summary.invariants.push(inv.clone()),
+                    _ => {}
+                }
+            }
+
+            let edge_count = self.graph.edge_references()
+                .filter(|e| node_set.contains(&e.source()) && node_set.contains(&e.target()))
+                .count();
+            summary.complexity = (edge_count + 2).saturating_sub(nodes.len());
+
+            if let Some(item_fn) = item_fn {
+                summary.calls = Self::collect_calls(&item_fn).into_iter()
+                    .filter(|call| function_names.contains(call) && *call != summary.name)
+                    .collect();
+            }
+            Some(summary)
+        }).collect()
+    }
+
+    fn collect_calls(item_fn: &ItemFn) -> Vec<String> {
+        let mut collector = CallCollector { calls: Vec::new() };
+        collector.visit_block(&item_fn.block);
+        let mut seen = HashSet::new();
+        collector.calls.retain(|call| seen.insert(call.clone()));
+        collector.calls
+    }
+
+    // Two-level view: one node per function, connected by call edges
+    pub fn summary_to_dot(&self) -> String {
+        let summaries = self.function_summaries();
+        let mut dot_string = String::from("digraph Summary {\n");
+        for summary in &summaries {
+            let mut lines = vec![summary.name.clone()];
+            lines.extend(summary.preconditions.iter().map(|pre| format!("pre: {}", self.options.notation.render(pre))));
+            lines.extend(summary.postconditions.iter().map(|post| format!("post: {}", self.options.notation.render(post))));
+            lines.push(format!("complexity: {}", summary.complexity));
+            let label = lines.iter()
+                .map(|line| line.replace('"', "\\\""))
+                .collect::<Vec<_>>()
+                .join("\\n");
+            dot_string.push_str(&format!("\"{}\" [label=\"{}\", shape=box];\n", self.node_id(summary.node), label));
+        }
+        for summary in &summaries {
+            for call in &summary.calls {
+                if let Some(callee) = summaries.iter().find(|s| &s.name == call) {
+                    dot_string.push_str(&format!("\"{}\" -> \"{}\" [label=\"calls\"];\n", self.node_id(summary.node), self.node_id(callee.node)));
+                }
+            }
+        }
+        dot_string.push_str("}\n");
+        dot_string
+    }
+
+    // Detailed CFG of a single function
+    pub fn function_to_dot(&self, func_node: NodeIndex) -> String {
+        let nodes = self.function_subgraph_nodes(func_node);
+        let node_set: HashSet<NodeIndex> = nodes.iter().copied().collect();
+        let mut dot_string = String::from("digraph G {\n");
+        for &node in &nodes {
+            dot_string.push_str(&self.format_node_dot(node));
+            dot_string.push('\n');
+        }
+        for edge in self.graph.edge_references() {
+            if node_set.contains(&edge.source()) && node_set.contains(&edge.target()) {
+                let probability = self.branch_probability(edge.source(), edge.weight());
+                dot_string.push_str(&Self::format_edge_dot(&self.node_id(edge.source()), &self.node_id(edge.target()), edge.weight(), probability));
+            }
+        }
+        dot_string.push_str(&self.loop_rank_hints(|node| node_set.contains(&node)));
+        dot_string.push_str(&self.loop_summaries_dot(|node| node_set.contains(&node)));
+        dot_string.push_str("}\n");
+        dot_string
+    }
+
+    // CFG of a function as a Mermaid flowchart, for Markdown reports: the nodes of 'highlighted' (e.g. on a
+    // failed path) are outlined in red
+    pub fn function_to_mermaid(&self, func_node: NodeIndex, highlighted: &HashSet<NodeIndex>) -> String {
+        let nodes = self.function_subgraph_nodes(func_node);
+        let node_set: HashSet<NodeIndex> = nodes.iter().copied().collect();
+        let id = |node: NodeIndex| format!("n{}", node.index());
+        let mut chart = String::from("flowchart TD\n");
+        for &node in &nodes {
+            let label = self.options.notation.render(&self.graph[node].label());
+            chart.push_str(&format!("    {}\n", self.graph[node].format_mermaid(&id(node), &label)));
+        }
+        for edge in self.graph.edge_references() {
+            if node_set.contains(&edge.source()) && node_set.contains(&edge.target()) {
+                let label = edge.weight().label().replace('"', "#quot;").replace('|', "#124;");
+                let arrow = if label.is_empty() { "-->".to_string() } else { format!("-->|\"{}\"|", label) };
+                chart.push_str(&format!("    {} {} {}\n", id(edge.source()), arrow, id(edge.target())));
+            }
+        }
+        let mut marked: Vec<String> = nodes.iter().filter(|node| highlighted.contains(node)).map(|&node| id(node)).collect();
+        if !marked.is_empty() {
+            marked.sort();
+            chart.push_str("    classDef failed stroke:#d73a49,stroke-width:3px\n");
+            chart.push_str(&format!("    class {} failed\n", marked.join(",")));
+        }
+        chart
+    }
+
+    // HTML page listing the function summaries; expanding a function renders its detailed CFG
+    pub fn summary_to_html(&self) -> String {
+        let mut html = String::from(concat!(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Secrust CFG summary</title>\n",
+            "<script src=\"https://unpkg.com/@viz-js/viz@3/lib/viz-standalone.js\"></script>\n",
+            "</head>\n<body>\n<h1>Functions</h1>\n<div class=\"graph\" data-dot=\"",
+        ));
+        html.push_str(&Self::escape_html(&self.summary_to_dot()));
+        html.push_str("\"></div>\n");
+
+        for summary in self.function_summaries() {
+            html.push_str(&format!("<details>\n<summary>{} (complexity {})</summary>\n<ul>\n", Self::escape_html(&summary.name), summary.complexity));
+            for pre in &summary.preconditions {
+                html.push_str(&format!("<li>pre: {}</li>\n", Self::escape_html(&self.options.notation.render(pre))));
+            }
+            for post in &summary.postconditions {
+                html.push_str(&format!("<li>post: {}</li>\n", Self::escape_html(&self.options.notation.render(post))));
+            }
+            for inv in &summary.invariants {
+                html.push_str(&format!("<li>invariant: {}</li>\n", Self::escape_html(&self.options.notation.render(inv))));
+            }
+            let nodes: HashSet<NodeIndex> = self.function_subgraph_nodes(summary.node).into_iter().collect();
+            for loop_summary in self.loop_summaries().into_iter().filter(|loop_summary| nodes.contains(&loop_summary.head)) {
+                let lines: Vec<String> = loop_summary.lines().iter().map(|line| self.options.notation.render(line)).collect();
+                html.push_str(&format!("<li>{}<ul>\n", Self::escape_html(&lines[0])));
+                for line in &lines[1..] {
+                    html.push_str(&format!("<li>{}</li>\n", Self::escape_html(line)));
+                }
+                html.push_str("</ul></li>\n");
+            }
+            html.push_str("</ul>\n<div class=\"graph\" data-dot=\"");
+            html.push_str(&Self::escape_html(&self.function_to_dot(summary.node)));
+            html.push_str("\"></div>\n</details>\n");
+        }
+
+        // Render each graph with Viz.js, falling back to the DOT source when it is not available
+        html.push_str(concat!(
+            "<script>\n",
+            "document.querySelectorAll('.graph').forEach(function (el) {\n",
+            "  var dot = el.getAttribute('data-dot');\n",
+            "  if (window.Viz) {\n",
+            "    Viz.instance().then(function (viz) { el.appendChild(viz.renderSVGElement(dot)); });\n",
+            "  } else {\n",
+            "    var pre = document.createElement('pre'); pre.textContent = dot; el.appendChild(pre);\n",
+            "  }\n",
+            "});\n",
+            "</script>\n</body>\n</html>\n",
+        ));
+        html
+    }
+
+    pub fn escape_html(input: &str) -> String {
+        input.replace('&', "&amp;")
+             .replace('<', "&lt;")
+             .replace('>', "&gt;")
+             .replace('"', "&quot;")
+    }
+}
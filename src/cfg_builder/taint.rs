@@ -0,0 +1,186 @@
+use petgraph::graph::NodeIndex;
+use petgraph::visit::EdgeRef;
+use quote::ToTokens;
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+use syn::visit::{self, Visit};
+use syn::{Expr, ExprReference, PatIdent, Stmt};
+
+use crate::cfg_builder::builder::CfgBuilder;
+use crate::cfg_builder::node::CfgNode;
+use crate::config::TaintSettings;
+
+// Untrusted data reaching a sink: the path from the node calling the source to the node calling the sink,
+// through no sanitizer
+#[derive(Debug, Clone)]
+pub struct TaintFlow {
+    pub function: String,
+    pub source: String, // the source and sink names of '[taint]' the calls matched
+    pub sink: String,
+    pub variables: Vec<String>, // tainted variables the sink reads, none when the source call is in the sink's arguments
+    pub path: Vec<NodeIndex>,
+}
+
+// Variables a statement writes: the bindings of a 'let' and the root of an assigned place ('cmd' for
+// 'cmd.arg = ..') are overwritten, the '&mut' arguments ('line' for 'stdin().read_line(&mut line)') changed
+#[derive(Default)]
+struct WrittenVariables {
+    overwritten: Vec<String>,
+    changed: Vec<String>,
+}
+
+impl<'ast> Visit<'ast> for WrittenVariables {
+    fn visit_pat_ident(&mut self, pat_ident: &'ast PatIdent) {
+        self.overwritten.push(pat_ident.ident.to_string());
+        visit::visit_pat_ident(self, pat_ident);
+    }
+
+    fn visit_expr_reference(&mut self, reference: &'ast ExprReference) {
+        if reference.mutability.is_some() {
+            self.changed.extend(CfgBuilder::place_root(&reference.expr));
+        }
+        visit::visit_expr_reference(self, reference);
+    }
+}
+
+impl CfgBuilder {
+    // Flows from a source to a sink in each function. The taint follows the variables: a source taints what its
+    // statement writes, a statement reading a tainted variable taints what it writes, and overwriting a variable
+    // with clean data clears it. A path stops at the first node calling a sanitizer. One path per source and sink.
+    pub fn taint_flows(&self, settings: &TaintSettings) -> Vec<TaintFlow> {
+        let mut flows = Vec::new();
+        for func_node in self.function_nodes() {
+            let function = self.graph[func_node].label();
+            for node in self.function_subgraph_nodes(func_node) {
+                if let Some(source) = self.called_name(node, &settings.sources) {
+                    flows.extend(self.flows_from(node, &source, &function, settings));
+                }
+            }
+        }
+        flows.sort_by_key(|flow| (self.node_location(flow.path[0]), self.node_location(*flow.path.last().unwrap())));
+        flows
+    }
+
+    // Breadth-first over the nodes and the sets of variables tainted when reaching them
+    fn flows_from(&self, source_node: NodeIndex, source: &str, function: &str, settings: &TaintSettings) -> Vec<TaintFlow> {
+        let mut flows = Vec::new();
+        let mut reported: HashSet<(NodeIndex, String)> = HashSet::new();
+        if let Some(sink) = self.called_name(source_node, &settings.sinks) {
+            flows.push(TaintFlow { function: function.to_string(), source: source.to_string(), sink, variables: Vec::new(), path: vec![source_node] });
+            return flows;
+        }
+
+        type State = (NodeIndex, BTreeSet<String>);
+        let written = self.written_variables(source_node);
+        let start: State = (source_node, written.overwritten.into_iter().chain(written.changed).collect());
+        if start.1.is_empty() {
+            return flows;
+        }
+        let mut parents: HashMap<State, Option<State>> = HashMap::from([(start.clone(), None)]);
+        let mut queue = VecDeque::from([start]);
+        while let Some(state) = queue.pop_front() {
+            let (node, tainted) = &state;
+            for next in self.graph.edges(*node).map(|edge| edge.target()) {
+                if matches!(self.graph[next], CfgNode::Function(_, _)) || self.called_name(next, &settings.sanitizers).is_some() {
+                    continue;
+                }
+                let read: Vec<String> = self.read_variables(next).into_iter().filter(|name| tainted.contains(name)).collect();
+                if let Some(sink) = self.called_name(next, &settings.sinks) {
+                    let used: Vec<String> = self.node_identifiers(next).into_iter().filter(|name| tainted.contains(name)).collect();
+                    if !used.is_empty() && reported.insert((next, sink.clone())) {
+                        let mut path = vec![next];
+                        let mut current = Some(state.clone());
+                        while let Some(step) = current {
+                            path.push(step.0);
+                            current = parents[&step].clone();
+                        }
+                        path.reverse();
+                        let mut variables = used;
+                        variables.sort();
+                        flows.push(TaintFlow { function: function.to_string(), source: source.to_string(), sink, variables, path });
+                    }
+                }
+                let mut next_tainted = tainted.clone();
+                let written = self.written_variables(next);
+                if !read.is_empty() {
+                    next_tainted.extend(written.overwritten.into_iter().chain(written.changed));
+                } else if !self.is_compound_assignment(next) {
+                    for name in &written.overwritten {
+                        next_tainted.remove(name);
+                    }
+                }
+                if next_tainted.is_empty() {
+                    continue;
+                }
+                let next_state = (next, next_tainted);
+                if !parents.contains_key(&next_state) {
+                    parents.insert(next_state.clone(), Some(state.clone()));
+                    queue.push_back(next_state);
+                }
+            }
+        }
+        flows
+    }
+
+    // Name of '[taint]' list a call of the node matches, if any
+    fn called_name(&self, node: NodeIndex, names: &[String]) -> Option<String> {
+        let calls = self.node_calls(node).calls;
+        names.iter().find(|name| calls.iter().any(|(segments, method)| Self::call_matches(name, segments, *method))).cloned()
+    }
+
+    // 'env::var' matches the calls whose path ends with 'env::var'; a single name matches methods too
+    fn call_matches(name: &str, segments: &[String], method: bool) -> bool {
+        let wanted: Vec<&str> = name.split("::").collect();
+        if method {
+            return wanted.len() == 1 && segments.last().map(String::as_str) == Some(wanted[0]);
+        }
+        segments.len() >= wanted.len() && segments[segments.len() - wanted.len()..].iter().zip(&wanted).all(|(segment, wanted)| segment == wanted)
+    }
+
+    fn written_variables(&self, node: NodeIndex) -> WrittenVariables {
+        let mut written = WrittenVariables::default();
+        match &self.graph[node] {
+            CfgNode::Statement(_, Some(Stmt::Local(local))) => {
+                written.visit_pat(&local.pat);
+                if let Some((_, init)) = &local.init {
+                    written.visit_expr(init);
+                }
+            },
+            CfgNode::Statement(_, Some(Stmt::Semi(expr, _) | Stmt::Expr(expr))) => {
+                match expr {
+                    Expr::Assign(assign) => written.overwritten.extend(Self::place_root(&assign.left)),
+                    Expr::AssignOp(assign_op) => written.overwritten.extend(Self::place_root(&assign_op.left)),
+                    _ => {},
+                }
+                written.visit_expr(expr);
+            },
+            _ => {},
+        }
+        written
+    }
+
+    // Identifiers the node reads: the right side of a 'let' or an assignment, the whole node otherwise
+    fn read_variables(&self, node: NodeIndex) -> HashSet<String> {
+        match &self.graph[node] {
+            CfgNode::Statement(_, Some(Stmt::Local(local))) => match &local.init {
+                Some((_, init)) => Self::identifiers(&init.to_token_stream()),
+                None => HashSet::new(),
+            },
+            CfgNode::Statement(_, Some(Stmt::Semi(Expr::Assign(assign), _) | Stmt::Expr(Expr::Assign(assign)))) => Self::identifiers(&assign.right.to_token_stream()),
+            _ => self.node_identifiers(node),
+        }
+    }
+
+    fn node_identifiers(&self, node: NodeIndex) -> HashSet<String> {
+        match &self.graph[node] {
+            CfgNode::Statement(_, Some(stmt)) => Self::identifiers(&stmt.to_token_stream()),
+            CfgNode::Condition(_, Some(conditional_expr)) => Self::identifiers(&conditional_expr.to_syn_expr().to_token_stream()),
+            CfgNode::Return(_, Some(expr_return)) => Self::identifiers(&expr_return.to_token_stream()),
+            _ => HashSet::new(),
+        }
+    }
+
+    // 'x += y' keeps what 'x' held
+    fn is_compound_assignment(&self, node: NodeIndex) -> bool {
+        matches!(&self.graph[node], CfgNode::Statement(_, Some(Stmt::Semi(Expr::AssignOp(_), _) | Stmt::Expr(Expr::AssignOp(_)))))
+    }
+}
@@ -0,0 +1,140 @@
+use std::collections::HashSet;
+
+use syn::visit::{self, Visit};
+use syn::{Expr, ExprMatch, FnArg, ItemFn, Stmt, Type};
+
+use crate::cfg_builder::builder::CfgBuilder;
+use crate::cfg_builder::node::CfgNode;
+use crate::config::AnnotationRole;
+
+const UNSIGNED_TYPES: [&str; 6] = ["u8", "u16", "u32", "u64", "u128", "usize"];
+
+// Tag variables of the values the 'match'es of a body are on
+#[derive(Default)]
+struct MatchedTags {
+    tags: HashSet<String>,
+}
+
+impl<'ast> Visit<'ast> for MatchedTags {
+    fn visit_expr_match(&mut self, i: &'ast ExprMatch) {
+        self.tags.insert(CfgBuilder::tag_variable(&i.expr));
+        visit::visit_expr_match(self, i);
+    }
+}
+
+impl CfgBuilder {
+    // Facts every caller guarantees through the types of the parameters, assumed on entry so they don't
+    // have to be restated in 'pre!': an unsigned parameter is non-negative, an enum holds exactly one of its
+    // variants, a reference points to a valid value. The last one is informative, there is no memory model.
+    // Each comes with the parameter type it follows from.
+    pub fn entry_assumptions(&self, item_fn: &ItemFn) -> Vec<(CfgNode, String)> {
+        let mut matched = MatchedTags::default();
+        matched.visit_block(&item_fn.block);
+        let mut assumptions = Vec::new();
+        for input in &item_fn.sig.inputs {
+            let FnArg::Typed(pat_type) = input else { continue };
+            let ty = Self::render_type(&pat_type.ty);
+            for param in Self::pattern_bindings(&pat_type.pat) {
+                // Parenthesized: the assumption is chained in front of the goal with '>>', which binds tighter
+                let mut fact = |text: String, expr: Option<Expr>| {
+                    let node = CfgNode::new_assumption(text, expr.map(Self::wrap_with_parens));
+                    assumptions.push((node, format!("from the type of {}: {}", param, ty)));
+                };
+                if let Type::Reference(_) = &*pat_type.ty {
+                    fact(format!("{}: {} is a valid reference", param, ty), None);
+                }
+                // The numeric value of a reference is its target, which the solver doesn't read through
+                if UNSIGNED_TYPES.contains(&ty.as_str()) {
+                    let condition = format!("{} >= 0", param);
+                    let expr = syn::parse_str(&condition).ok();
+                    fact(condition, expr);
+                }
+                // Tag of the enum as tested by 'if let' and 'matches!'. A 'match param { .. }' states it on
+                // every path through it, so it is not repeated here.
+                let base = ty.trim_start_matches('&').trim_start_matches("mut ");
+                let base = base.split('<').next().unwrap_or(base);
+                let tag_var = format!("{}_tag", Self::sanitize_identifier(&param));
+                if let Some(variants) = self.enum_variants(base).filter(|variants| !variants.is_empty() && !matched.tags.contains(&tag_var)) {
+                    let condition = (0..variants.len()).map(|k| format!("{} == {}", tag_var, k)).collect::<Vec<_>>().join(" || ");
+                    let expr = syn::parse_str(&condition).ok();
+                    fact(condition, expr);
+                }
+            }
+        }
+        assumptions
+    }
+
+    // Chain the assumptions after the current node, each noting the type it comes from
+    pub fn add_entry_assumptions(&mut self, assumptions: Vec<(CfgNode, String)>) {
+        for (assumption, origin) in assumptions {
+            let node = self.add_node(assumption);
+            self.metadata_mut(node).facts.push(origin);
+        }
+    }
+
+    // 'pre!(..);' statements come before the entry assumptions
+    pub fn is_precondition_statement(&self, stmt: &Stmt) -> bool {
+        matches!(stmt, Stmt::Semi(Expr::Macro(expr_macro), _)
+            if expr_macro.mac.path.get_ident().and_then(|ident| self.options.annotation_names.role_of(&ident.to_string())) == Some(AnnotationRole::Pre))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{self, built};
+    use crate::verifier::VcOutcome;
+
+    fn verdicts(ty: &str) -> Vec<VcOutcome> {
+        let source = format!("fn id(n: {}) -> {} {{
+                pre!(true);
+                post!(n >= 0);
+                return n;
+            }}", ty, ty);
+        test_support::verdicts(&source)
+    }
+
+    #[test]
+    fn an_unsigned_parameter_is_non_negative() {
+        assert!(matches!(verdicts("u32").as_slice(), [VcOutcome::Valid]));
+        assert!(matches!(verdicts("i32").as_slice(), [VcOutcome::Counterexample(_)]));
+    }
+
+    #[test]
+    fn each_parameter_type_gives_its_facts() {
+        let file: syn::File = syn::parse_str("enum Dir { Up, Down, Left }
+            fn step(d: &Dir, k: usize) {}").unwrap();
+        let mut builder = CfgBuilder::new();
+        builder.build_cfg(&file);
+        let syn::Item::Fn(item_fn) = &file.items[1] else { panic!("not a function") };
+        let facts: Vec<String> = builder.entry_assumptions(item_fn).into_iter()
+            .map(|(node, origin)| match node {
+                CfgNode::Assumption(fact, _) => format!("{} ({})", fact, origin),
+                _ => panic!("not an assumption"),
+            })
+            .collect();
+        assert_eq!(facts, vec![
+            "d: &Dir is a valid reference (from the type of d: &Dir)",
+            "d_tag == 0 || d_tag == 1 || d_tag == 2 (from the type of d: &Dir)",
+            "k >= 0 (from the type of k: usize)",
+        ]);
+    }
+
+    // The 'match' states that the tag is one of the variants on each path through it, the entry doesn't repeat it
+    #[test]
+    fn a_matched_enum_parameter_gets_its_tag_fact_once() {
+        let mut builder = built("enum Dir { Up, Down }
+            fn flip(d: Dir) -> i32 {
+                pre!(true);
+                post!(r >= 0);
+                let r = match d { Dir::Up => 1, Dir::Down => 0 };
+                return r;
+            }");
+        let paths = builder.generate_basic_paths();
+        let implications = builder.apply_wp_calculus(&paths);
+        assert_eq!(implications.len(), 2);
+        for implication in &implications {
+            assert_eq!(implication.matches("d_tag == 0 || d_tag == 1").count(), 1, "{}", implication);
+        }
+    }
+}
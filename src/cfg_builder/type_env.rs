@@ -0,0 +1,198 @@
+use std::collections::HashMap;
+use syn::{BinOp, Expr, FnArg, ItemFn, Lit, Pat, ReturnType};
+
+use crate::cfg_builder::builder::CfgBuilder;
+
+// Integer types with their bounds ('isize'/'usize' as on 64-bit targets)
+const INTEGER_BOUNDS: [(&str, &str, &str); 12] = [
+    ("i8", "-128", "127"),
+    ("i16", "-32768", "32767"),
+    ("i32", "-2147483648", "2147483647"),
+    ("i64", "-9223372036854775808", "9223372036854775807"),
+    ("i128", "-170141183460469231731687303715884105728", "170141183460469231731687303715884105727"),
+    ("isize", "-9223372036854775808", "9223372036854775807"),
+    ("u8", "0", "255"),
+    ("u16", "0", "65535"),
+    ("u32", "0", "4294967295"),
+    ("u64", "0", "18446744073709551615"),
+    ("u128", "0", "340282366920938463463374607431768211455"),
+    ("usize", "0", "18446744073709551615"),
+];
+
+impl CfgBuilder {
+    // Start the type environment of a function from its parameters
+    pub fn init_type_env(&mut self, item_fn: &ItemFn) -> Vec<(String, String)> {
+        self.type_env.clear();
+        let mut params = Vec::new();
+        for input in &item_fn.sig.inputs {
+            match input {
+                FnArg::Receiver(_) => params.push(("self".to_string(), "Self".to_string())),
+                FnArg::Typed(pat_type) => {
+                    let ty = Self::render_type(&pat_type.ty);
+                    for var in Self::pattern_bindings(&pat_type.pat) {
+                        params.push((var, ty.clone()));
+                    }
+                }
+            }
+        }
+        for (var, ty) in &params {
+            self.type_env.insert(var.clone(), ty.clone());
+        }
+        params
+    }
+
+    // Record the return type of every function of the file, used to type 'let x = f(..)'
+    pub fn collect_fn_return_types(&mut self, items: &[syn::Item]) {
+        for item in items {
+            if let syn::Item::Fn(item_fn) = item {
+                if let ReturnType::Type(_, ty) = &item_fn.sig.output {
+                    self.file.fn_return_types.insert(item_fn.sig.ident.to_string(), Self::render_type(ty));
+                }
+            }
+        }
+    }
+
+    // Types of the variables bound by a 'let', from the annotation or inferred from the initializer
+    pub fn local_types(&mut self, local: &syn::Local) -> Vec<(String, String)> {
+        let (pat, declared) = match &local.pat {
+            Pat::Type(pat_type) => (&*pat_type.pat, Some(Self::render_type(&pat_type.ty))),
+            pat => (pat, None),
+        };
+        let inferred = declared.or_else(|| local.init.as_ref().and_then(|(_, init)| self.infer_expr_type(init)));
+
+        let mut types = Vec::new();
+        if let Some(ty) = inferred {
+            for var in Self::pattern_bindings(pat) {
+                self.type_env.insert(var.clone(), ty.clone());
+                types.push((var, ty.clone()));
+            }
+        }
+        types
+    }
+
+    // Best-effort type of an expression, from literals, known variables and simple operators
+    pub fn infer_expr_type(&self, expr: &Expr) -> Option<String> {
+        self.infer_expr_type_in(expr, &self.type_env)
+    }
+
+    // Same inference, with the variable types taken from 'env' instead of the current function
+    pub fn infer_expr_type_in(&self, expr: &Expr, env: &HashMap<String, String>) -> Option<String> {
+        match expr {
+            Expr::Lit(expr_lit) => match &expr_lit.lit {
+                Lit::Int(lit_int) if !lit_int.suffix().is_empty() => Some(lit_int.suffix().to_string()),
+                Lit::Int(_) => Some("i32".to_string()),
+                Lit::Float(lit_float) if !lit_float.suffix().is_empty() => Some(lit_float.suffix().to_string()),
+                Lit::Float(_) => Some("f64".to_string()),
+                Lit::Bool(_) => Some("bool".to_string()),
+                Lit::Str(_) => Some("&str".to_string()),
+                Lit::Char(_) => Some("char".to_string()),
+                _ => None,
+            },
+            Expr::Path(expr_path) => expr_path.path.get_ident()
+                .and_then(|ident| env.get(&ident.to_string()).cloned()),
+            Expr::Paren(paren) => self.infer_expr_type_in(&paren.expr, env),
+            Expr::Group(group) => self.infer_expr_type_in(&group.expr, env),
+            Expr::Unary(unary) => match unary.op {
+                syn::UnOp::Not(_) | syn::UnOp::Neg(_) => self.infer_expr_type_in(&unary.expr, env),
+                syn::UnOp::Deref(_) => self.infer_expr_type_in(&unary.expr, env)
+                    .map(|ty| ty.trim_start_matches('&').trim_start_matches("mut ").to_string()),
+            },
+            Expr::Binary(bin) => match bin.op {
+                BinOp::Eq(_) | BinOp::Ne(_) | BinOp::Lt(_) | BinOp::Le(_) | BinOp::Gt(_) | BinOp::Ge(_)
+                | BinOp::And(_) | BinOp::Or(_) => Some("bool".to_string()),
+                // Prefer a variable's type over the default type of an unsuffixed literal
+                _ => {
+                    let left = self.infer_expr_type_in(&bin.left, env);
+                    let right = self.infer_expr_type_in(&bin.right, env);
+                    if matches!(&*bin.left, Expr::Lit(_)) { right.or(left) } else { left.or(right) }
+                }
+            },
+            Expr::Cast(cast) => Some(Self::render_type(&cast.ty)),
+            Expr::Reference(reference) => self.infer_expr_type_in(&reference.expr, env).map(|ty| {
+                if reference.mutability.is_some() { format!("&mut {}", ty) } else { format!("&{}", ty) }
+            }),
+            Expr::Array(array) => {
+                let elem = array.elems.first().and_then(|e| self.infer_expr_type_in(e, env)).unwrap_or_else(|| "_".to_string());
+                Some(format!("[{}; {}]", elem, array.elems.len()))
+            },
+            Expr::Macro(expr_macro) if expr_macro.mac.path.is_ident("vec") => {
+                let parser = syn::punctuated::Punctuated::<Expr, syn::Token![,]>::parse_terminated;
+                let elem = syn::parse::Parser::parse2(parser, expr_macro.mac.tokens.clone()).ok()
+                    .and_then(|elems| elems.first().and_then(|e| self.infer_expr_type_in(e, env)))
+                    .unwrap_or_else(|| "_".to_string());
+                Some(format!("Vec<{}>", elem))
+            },
+            Expr::Struct(expr_struct) => Some(Self::render_path(&expr_struct.path)),
+            Expr::Closure(_) => Some("closure".to_string()),
+            Expr::Tuple(tuple) => {
+                let elems: Option<Vec<String>> = tuple.elems.iter().map(|e| self.infer_expr_type_in(e, env)).collect();
+                elems.map(|elems| format!("({})", elems.join(", ")))
+            },
+            Expr::Call(call) => match &*call.func {
+                Expr::Path(expr_path) => expr_path.path.get_ident()
+                    .and_then(|ident| self.file.fn_return_types.get(&ident.to_string()).cloned()),
+                _ => None,
+            },
+            Expr::MethodCall(method_call) => match method_call.method.to_string().as_str() {
+                "len" | "count" => Some("usize".to_string()),
+                "is_empty" | "contains" | "is_some" | "is_none" | "is_ok" | "is_err" => Some("bool".to_string()),
+                "clone" | "abs" | "pow" | "min" | "max" => self.infer_expr_type_in(&method_call.receiver, env),
+                "to_string" => Some("String".to_string()),
+                _ => None,
+            },
+            Expr::Range(range) => {
+                // As for binary operators, a typed bound wins over an unsuffixed literal ('0..n')
+                let mut bounds: Vec<&Expr> = range.from.iter().chain(range.to.iter()).map(|b| &**b).collect();
+                bounds.sort_by_key(|bound| matches!(bound, Expr::Lit(_)));
+                bounds.iter()
+                    .find_map(|bound| self.infer_expr_type_in(bound, env))
+                    .map(|ty| format!("Range<{}>", ty))
+            },
+            _ => None,
+        }
+    }
+
+    // Bounds of an integer type, 'None' for other types
+    pub fn integer_bounds(ty: &str) -> Option<(&'static str, &'static str)> {
+        INTEGER_BOUNDS.iter().find(|(name, _, _)| *name == ty).map(|&(_, min, max)| (min, max))
+    }
+
+    // Wrap-around of the values cast to an integer type, 'x as T' being '(x - min) mod modulus + min': the
+    // minimum of the type and the number of its values, as decimal digits
+    pub fn integer_wrap(ty: &str) -> Option<(&'static str, String)> {
+        let (min, max) = Self::integer_bounds(ty)?;
+        let (min_value, max_value) = (min.parse::<i128>().ok()?, max.parse::<u128>().ok()?);
+        // 128-bit types have 2^128 values, one more than fits
+        let modulus = match max_value.wrapping_sub(min_value as u128).checked_add(1) {
+            Some(modulus) => modulus.to_string(),
+            None => "340282366920938463463374607431768211456".to_string(),
+        };
+        Some((min, modulus))
+    }
+
+    // Element type of a 'for' loop iterator, when it can be read from the iterated expression
+    pub fn iterated_item_type(&self, expr: &Expr) -> Option<String> {
+        let iterated = self.infer_expr_type(expr)?;
+        if let Some(inner) = iterated.strip_prefix("Range<") {
+            return Some(inner.trim_end_matches('>').to_string());
+        }
+        if let Some(inner) = iterated.strip_prefix("Vec<") {
+            return Some(inner.trim_end_matches('>').to_string());
+        }
+        None
+    }
+
+    // Names bound by a pattern ('x', '(a, b)', 'Some(v)', ...)
+    pub fn pattern_bindings(pat: &Pat) -> Vec<String> {
+        match pat {
+            Pat::Ident(pat_ident) => vec![pat_ident.ident.to_string()],
+            Pat::Type(pat_type) => Self::pattern_bindings(&pat_type.pat),
+            Pat::Reference(pat_ref) => Self::pattern_bindings(&pat_ref.pat),
+            Pat::Tuple(tuple) => tuple.elems.iter().flat_map(Self::pattern_bindings).collect(),
+            Pat::TupleStruct(tuple_struct) => tuple_struct.pat.elems.iter().flat_map(Self::pattern_bindings).collect(),
+            Pat::Struct(pat_struct) => pat_struct.fields.iter().flat_map(|f| Self::pattern_bindings(&f.pat)).collect(),
+            Pat::Slice(slice) => slice.elems.iter().flat_map(Self::pattern_bindings).collect(),
+            _ => Vec::new(),
+        }
+    }
+}
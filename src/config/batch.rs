@@ -0,0 +1,49 @@
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Targets of a batch run, read from the file given to '--manifest' ('verify.toml'):
+//   [[target]]
+//   file = "src/lib.rs"
+//   function = "sum_to"           # optional, all the functions of the file by default
+//   goals = ["contracts", "no-panic"]  # optional, 'contracts' by default
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BatchManifest {
+    #[serde(rename = "target")]
+    pub targets: Vec<BatchTarget>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BatchTarget {
+    pub file: PathBuf, // relative to the directory of the manifest
+    #[serde(default)]
+    pub function: Option<String>,
+    #[serde(default = "BatchTarget::default_goals")]
+    pub goals: Vec<String>,
+}
+
+impl BatchTarget {
+    fn default_goals() -> Vec<String> {
+        vec!["contracts".to_string()]
+    }
+}
+
+impl BatchManifest {
+    // The file paths of the targets are resolved against the manifest's directory
+    pub fn load(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let content = fs::read_to_string(path).map_err(|e| format!("can't read {}: {}", path.display(), e))?;
+        let mut manifest: BatchManifest = toml::from_str(&content).map_err(|e| format!("invalid {}: {}", path.display(), e))?;
+        if manifest.targets.is_empty() {
+            return Err(format!("{} lists no [[target]]", path.display()).into());
+        }
+        let base = path.parent().unwrap_or(Path::new(""));
+        for target in &mut manifest.targets {
+            if target.file.is_relative() {
+                target.file = base.join(&target.file);
+            }
+        }
+        Ok(manifest)
+    }
+}
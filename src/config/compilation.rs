@@ -0,0 +1,298 @@
+use std::collections::BTreeSet;
+use std::fmt;
+use syn::visit::{self, Visit};
+use syn::visit_mut::{self, VisitMut};
+use syn::{Attribute, Expr, Fields, ImplItem, Item, Meta, NestedMeta, Stmt, TraitItem};
+
+// Configuration options are 'name' ('test') or 'name = "value"' ('feature = "fast"')
+pub type CfgOption = (String, Option<String>);
+
+// Most options '--all-cfgs' enumerates: 2^6 configurations
+const MAX_ENUMERATED_OPTIONS: usize = 6;
+
+// Compilation configuration selected with '--cfg': the code under a '#[cfg(..)]' that doesn't hold for it
+// is removed from the AST before the CFG is built
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CompilationConfig {
+    pub options: BTreeSet<CfgOption>,
+}
+
+impl CompilationConfig {
+    // Parse one '--cfg' value: 'feature="fast"', 'test', or '' for no option
+    pub fn parse_option(text: &str) -> Result<Option<CfgOption>, String> {
+        let text = text.trim();
+        if text.is_empty() {
+            return Ok(None);
+        }
+        // The shell often eats the quotes: 'feature=fast' is 'feature="fast"'
+        let (name, value) = match text.split_once('=') {
+            Some((name, value)) => {
+                let value = value.trim();
+                let value = value.strip_prefix('"').and_then(|value| value.strip_suffix('"')).unwrap_or(value);
+                (name.trim(), Some(value.to_string()))
+            },
+            None => (text, None),
+        };
+        if syn::parse_str::<syn::Ident>(name).is_err() {
+            return Err(format!("invalid cfg '{}': '{}' is not an option name", text, name));
+        }
+        Ok(Some((name.to_string(), value)))
+    }
+
+    // Whether a 'cfg' predicate ('feature = "x"', 'all(..)', 'any(..)', 'not(..)') holds
+    pub fn holds(&self, predicate: &NestedMeta) -> bool {
+        match predicate {
+            NestedMeta::Meta(Meta::Path(path)) => self.options.contains(&(path_name(path), None)),
+            NestedMeta::Meta(Meta::NameValue(name_value)) => match &name_value.lit {
+                syn::Lit::Str(value) => self.options.contains(&(path_name(&name_value.path), Some(value.value()))),
+                _ => false,
+            },
+            NestedMeta::Meta(Meta::List(list)) => match path_name(&list.path).as_str() {
+                "all" => list.nested.iter().all(|nested| self.holds(nested)),
+                "any" => list.nested.iter().any(|nested| self.holds(nested)),
+                "not" => list.nested.len() == 1 && !self.holds(&list.nested[0]),
+                _ => false,
+            },
+            NestedMeta::Lit(_) => false,
+        }
+    }
+
+    // Whether code with these attributes is compiled: every 'cfg' among them holds
+    pub fn is_enabled(&self, attrs: &[Attribute]) -> bool {
+        attrs.iter().filter_map(cfg_predicate).all(|predicate| self.holds(&predicate))
+    }
+
+    // Remove the code disabled in this configuration, returning how many items, statements, arms and fields went
+    pub fn apply(&self, file: &mut syn::File) -> usize {
+        let mut pruner = CfgPruner { config: self, removed: 0 };
+        pruner.visit_file_mut(file);
+        pruner.removed
+    }
+
+    // Every configuration of the options the 'cfg's of the file test, for '--all-cfgs'
+    pub fn enumerate(file: &syn::File) -> Result<Vec<CompilationConfig>, String> {
+        let mut collector = CfgOptionCollector { options: BTreeSet::new() };
+        collector.visit_file(file);
+        let options: Vec<CfgOption> = collector.options.into_iter().collect();
+        if options.len() > MAX_ENUMERATED_OPTIONS {
+            return Err(format!("{} cfg options in the file, at most {} can be enumerated; select a configuration with --cfg", options.len(), MAX_ENUMERATED_OPTIONS));
+        }
+        Ok((0..1usize << options.len())
+            .map(|mask| CompilationConfig {
+                options: options.iter().enumerate().filter(|(i, _)| mask & (1 << i) != 0).map(|(_, option)| option.clone()).collect(),
+            })
+            .collect())
+    }
+
+    // Directory name of the artifacts of this configuration under '--all-cfgs', e.g. 'cfg-fast+test'
+    pub fn dir_name(&self) -> String {
+        if self.options.is_empty() {
+            return "cfg-none".to_string();
+        }
+        let names: Vec<String> = self.options.iter().map(|(name, value)| match (name.as_str(), value) {
+            ("feature", Some(value)) => value.clone(),
+            (_, Some(value)) => format!("{}_{}", name, value),
+            (_, None) => name.clone(),
+        }).collect();
+        format!("cfg-{}", names.join("+"))
+    }
+}
+
+impl fmt::Display for CompilationConfig {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.options.is_empty() {
+            return write!(f, "no cfg options");
+        }
+        let options: Vec<String> = self.options.iter().map(|(name, value)| match value {
+            Some(value) => format!("{}=\"{}\"", name, value),
+            None => name.clone(),
+        }).collect();
+        write!(f, "{}", options.join(", "))
+    }
+}
+
+fn path_name(path: &syn::Path) -> String {
+    path.segments.iter().map(|segment| segment.ident.to_string()).collect::<Vec<_>>().join("::")
+}
+
+// The predicate of a '#[cfg(..)]' attribute
+fn cfg_predicate(attr: &Attribute) -> Option<NestedMeta> {
+    if !attr.path.is_ident("cfg") {
+        return None;
+    }
+    match attr.parse_meta() {
+        Ok(Meta::List(list)) if list.nested.len() == 1 => list.nested.into_iter().next(),
+        _ => None,
+    }
+}
+
+fn item_attrs(item: &Item) -> &[Attribute] {
+    match item {
+        Item::Const(item) => &item.attrs,
+        Item::Enum(item) => &item.attrs,
+        Item::ExternCrate(item) => &item.attrs,
+        Item::Fn(item) => &item.attrs,
+        Item::ForeignMod(item) => &item.attrs,
+        Item::Impl(item) => &item.attrs,
+        Item::Macro(item) => &item.attrs,
+        Item::Macro2(item) => &item.attrs,
+        Item::Mod(item) => &item.attrs,
+        Item::Static(item) => &item.attrs,
+        Item::Struct(item) => &item.attrs,
+        Item::Trait(item) => &item.attrs,
+        Item::TraitAlias(item) => &item.attrs,
+        Item::Type(item) => &item.attrs,
+        Item::Union(item) => &item.attrs,
+        Item::Use(item) => &item.attrs,
+        _ => &[],
+    }
+}
+
+fn impl_item_attrs(item: &ImplItem) -> &[Attribute] {
+    match item {
+        ImplItem::Const(item) => &item.attrs,
+        ImplItem::Method(item) => &item.attrs,
+        ImplItem::Type(item) => &item.attrs,
+        ImplItem::Macro(item) => &item.attrs,
+        _ => &[],
+    }
+}
+
+fn trait_item_attrs(item: &TraitItem) -> &[Attribute] {
+    match item {
+        TraitItem::Const(item) => &item.attrs,
+        TraitItem::Method(item) => &item.attrs,
+        TraitItem::Type(item) => &item.attrs,
+        TraitItem::Macro(item) => &item.attrs,
+        _ => &[],
+    }
+}
+
+// Attributes of the expression statements the CFG has nodes for
+fn expr_attrs(expr: &Expr) -> &[Attribute] {
+    match expr {
+        Expr::Assign(expr) => &expr.attrs,
+        Expr::AssignOp(expr) => &expr.attrs,
+        Expr::Block(expr) => &expr.attrs,
+        Expr::Call(expr) => &expr.attrs,
+        Expr::ForLoop(expr) => &expr.attrs,
+        Expr::If(expr) => &expr.attrs,
+        Expr::Loop(expr) => &expr.attrs,
+        Expr::Macro(expr) => &expr.attrs,
+        Expr::Match(expr) => &expr.attrs,
+        Expr::MethodCall(expr) => &expr.attrs,
+        Expr::Return(expr) => &expr.attrs,
+        Expr::Unsafe(expr) => &expr.attrs,
+        Expr::While(expr) => &expr.attrs,
+        _ => &[],
+    }
+}
+
+fn stmt_attrs(stmt: &Stmt) -> &[Attribute] {
+    match stmt {
+        Stmt::Local(local) => &local.attrs,
+        Stmt::Item(item) => item_attrs(item),
+        Stmt::Expr(expr) | Stmt::Semi(expr, _) => expr_attrs(expr),
+    }
+}
+
+struct CfgPruner<'a> {
+    config: &'a CompilationConfig,
+    removed: usize,
+}
+
+impl CfgPruner<'_> {
+    fn retain<T>(&mut self, elements: &mut Vec<T>, attrs: impl Fn(&T) -> &[Attribute]) {
+        let before = elements.len();
+        elements.retain(|element| self.config.is_enabled(attrs(element)));
+        self.removed += before - elements.len();
+    }
+}
+
+impl VisitMut for CfgPruner<'_> {
+    fn visit_file_mut(&mut self, file: &mut syn::File) {
+        // '#![cfg(..)]' at the top of the file compiles all of it out
+        if !self.config.is_enabled(&file.attrs) {
+            self.removed += file.items.len();
+            file.items.clear();
+            return;
+        }
+        self.retain(&mut file.items, item_attrs);
+        visit_mut::visit_file_mut(self, file);
+    }
+
+    fn visit_item_mod_mut(&mut self, item: &mut syn::ItemMod) {
+        if let Some((_, items)) = &mut item.content {
+            self.retain(items, item_attrs);
+        }
+        visit_mut::visit_item_mod_mut(self, item);
+    }
+
+    fn visit_item_impl_mut(&mut self, item: &mut syn::ItemImpl) {
+        self.retain(&mut item.items, impl_item_attrs);
+        visit_mut::visit_item_impl_mut(self, item);
+    }
+
+    fn visit_item_trait_mut(&mut self, item: &mut syn::ItemTrait) {
+        self.retain(&mut item.items, trait_item_attrs);
+        visit_mut::visit_item_trait_mut(self, item);
+    }
+
+    fn visit_block_mut(&mut self, block: &mut syn::Block) {
+        self.retain(&mut block.stmts, stmt_attrs);
+        visit_mut::visit_block_mut(self, block);
+    }
+
+    fn visit_expr_match_mut(&mut self, expr: &mut syn::ExprMatch) {
+        self.retain(&mut expr.arms, |arm| &arm.attrs);
+        visit_mut::visit_expr_match_mut(self, expr);
+    }
+
+    fn visit_fields_mut(&mut self, fields: &mut Fields) {
+        let (Fields::Named(syn::FieldsNamed { named: list, .. }) | Fields::Unnamed(syn::FieldsUnnamed { unnamed: list, .. })) = fields else {
+            return;
+        };
+        let before = list.len();
+        *list = std::mem::take(list).into_pairs().filter(|pair| self.config.is_enabled(&pair.value().attrs)).collect();
+        self.removed += before - list.len();
+        visit_mut::visit_fields_mut(self, fields);
+    }
+
+    fn visit_item_enum_mut(&mut self, item: &mut syn::ItemEnum) {
+        let before = item.variants.len();
+        item.variants = std::mem::take(&mut item.variants).into_pairs().filter(|pair| self.config.is_enabled(&pair.value().attrs)).collect();
+        self.removed += before - item.variants.len();
+        visit_mut::visit_item_enum_mut(self, item);
+    }
+}
+
+// Options the 'cfg' predicates of a file test
+struct CfgOptionCollector {
+    options: BTreeSet<CfgOption>,
+}
+
+impl CfgOptionCollector {
+    fn collect(&mut self, predicate: &NestedMeta) {
+        match predicate {
+            NestedMeta::Meta(Meta::Path(path)) => {
+                self.options.insert((path_name(path), None));
+            },
+            NestedMeta::Meta(Meta::NameValue(name_value)) => {
+                if let syn::Lit::Str(value) = &name_value.lit {
+                    self.options.insert((path_name(&name_value.path), Some(value.value())));
+                }
+            },
+            NestedMeta::Meta(Meta::List(list)) => list.nested.iter().for_each(|nested| self.collect(nested)),
+            NestedMeta::Lit(_) => {},
+        }
+    }
+}
+
+impl<'ast> Visit<'ast> for CfgOptionCollector {
+    fn visit_attribute(&mut self, attr: &'ast Attribute) {
+        if let Some(predicate) = cfg_predicate(attr) {
+            self.collect(&predicate);
+        }
+        visit::visit_attribute(self, attr);
+    }
+}
@@ -0,0 +1,110 @@
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use syn::{Item, ItemMod, Lit, Meta};
+
+// Source files of a crate, as 'cargo metadata' describes it: the root file of each library and binary target of
+// the workspace members, and the files of the modules they declare ('mod parser;')
+#[derive(Debug, Clone)]
+pub struct CrateSources {
+    pub workspace_root: PathBuf,
+    pub target_directory: PathBuf,
+    pub files: Vec<PathBuf>,
+}
+
+// Kinds of targets whose sources are verified; tests, benches, examples and build scripts are left out
+const VERIFIED_TARGET_KINDS: [&str; 4] = ["lib", "bin", "rlib", "proc-macro"];
+
+impl CrateSources {
+    // Run 'cargo metadata' on the manifest ('$CARGO' when run as a cargo subcommand) and follow the modules of
+    // the targets' root files
+    pub fn discover(manifest_path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let cargo = std::env::var("CARGO").unwrap_or_else(|_| "cargo".to_string());
+        let output = Command::new(&cargo)
+            .args(["metadata", "--format-version", "1", "--no-deps", "--manifest-path"])
+            .arg(manifest_path)
+            .output()
+            .map_err(|e| format!("can't run {} metadata: {}", cargo, e))?;
+        if !output.status.success() {
+            return Err(format!("cargo metadata failed for {}: {}", manifest_path.display(), String::from_utf8_lossy(&output.stderr).trim()).into());
+        }
+        let metadata: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+        let path = |key: &str| metadata.get(key).and_then(|value| value.as_str()).map(PathBuf::from)
+            .ok_or_else(|| format!("cargo metadata gives no {}", key));
+        let workspace_root = path("workspace_root")?;
+        let target_directory = path("target_directory")?;
+
+        let mut roots = BTreeSet::new();
+        for package in metadata.get("packages").and_then(|packages| packages.as_array()).into_iter().flatten() {
+            for target in package.get("targets").and_then(|targets| targets.as_array()).into_iter().flatten() {
+                let verified = target.get("kind").and_then(|kinds| kinds.as_array()).into_iter().flatten()
+                    .any(|kind| kind.as_str().is_some_and(|kind| VERIFIED_TARGET_KINDS.contains(&kind)));
+                if let (true, Some(src_path)) = (verified, target.get("src_path").and_then(|src_path| src_path.as_str())) {
+                    roots.insert(PathBuf::from(src_path));
+                }
+            }
+        }
+
+        let mut files = BTreeSet::new();
+        for root in roots {
+            Self::collect_module_files(&root, true, &mut files);
+        }
+        Ok(CrateSources { workspace_root, target_directory, files: files.into_iter().collect() })
+    }
+
+    // A file and the files of the modules it declares, recursively. Files that can't be read or parsed are kept,
+    // to be reported by their verification.
+    fn collect_module_files(file: &Path, is_root: bool, files: &mut BTreeSet<PathBuf>) {
+        if !files.insert(file.to_path_buf()) {
+            return;
+        }
+        let Ok(ast) = fs::read_to_string(file).map_err(|e| e.to_string()).and_then(|content| syn::parse_file(&content).map_err(|e| e.to_string())) else {
+            return;
+        };
+        // 'mod x;' in 'src/lib.rs' or 'src/a/mod.rs' is next to it, in 'src/a.rs' it is in 'src/a/'
+        let parent = file.parent().unwrap_or(Path::new(""));
+        let owns_directory = is_root || file.file_name().is_some_and(|name| name == "mod.rs");
+        let dir = match (owns_directory, file.file_stem()) {
+            (false, Some(stem)) => parent.join(stem),
+            _ => parent.to_path_buf(),
+        };
+        Self::collect_declared_modules(&ast.items, parent, &dir, files);
+    }
+
+    fn collect_declared_modules(items: &[Item], parent: &Path, dir: &Path, files: &mut BTreeSet<PathBuf>) {
+        for item in items {
+            let Item::Mod(item_mod) = item else { continue };
+            match &item_mod.content {
+                // 'mod a { mod b; }' looks for 'b' in 'a/'
+                Some((_, items)) => {
+                    let dir = match Self::path_attribute(item_mod) {
+                        Some(path) => dir.join(path),
+                        None => dir.join(item_mod.ident.to_string()),
+                    };
+                    Self::collect_declared_modules(items, parent, &dir, files);
+                },
+                None => {
+                    let candidates = match Self::path_attribute(item_mod) {
+                        Some(path) => vec![parent.join(path)],
+                        None => vec![dir.join(format!("{}.rs", item_mod.ident)), dir.join(item_mod.ident.to_string()).join("mod.rs")],
+                    };
+                    if let Some(module_file) = candidates.into_iter().find(|candidate| candidate.is_file()) {
+                        Self::collect_module_files(&module_file, false, files);
+                    }
+                },
+            }
+        }
+    }
+
+    // '#[path = "other.rs"]'
+    fn path_attribute(item_mod: &ItemMod) -> Option<String> {
+        item_mod.attrs.iter().find_map(|attr| match attr.parse_meta() {
+            Ok(Meta::NameValue(name_value)) if name_value.path.is_ident("path") => match name_value.lit {
+                Lit::Str(path) => Some(path.value()),
+                _ => None,
+            },
+            _ => None,
+        })
+    }
+}
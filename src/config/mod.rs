@@ -0,0 +1,13 @@
+mod settings;
+mod compilation;
+mod batch;
+mod crate_sources;
+mod scaffold;
+mod notation;
+
+pub use settings::*;
+pub use compilation::*;
+pub use batch::*;
+pub use crate_sources::CrateSources;
+pub use scaffold::*;
+pub use notation::NotationTable;
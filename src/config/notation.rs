@@ -0,0 +1,132 @@
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+// '[notation]' section: how calls in specs are displayed, as 'name = "template"' with '{0}', '{1}', .. standing
+// for the arguments ('implies = "{0} ==> {1}"', 'seq_len = "|{0}|"'). Only the displayed text changes: the
+// CFG, the verification conditions and the JSON exports keep the calls as written.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(transparent)]
+pub struct NotationTable {
+    pub rules: BTreeMap<String, String>,
+}
+
+impl NotationTable {
+    // The text with each call of a function of the table replaced by its template, innermost calls first.
+    // A call with fewer arguments than its template uses is left as it is.
+    pub fn render(&self, text: &str) -> String {
+        if self.rules.is_empty() {
+            return text.to_string();
+        }
+        let chars: Vec<char> = text.chars().collect();
+        let mut rendered = String::new();
+        let mut i = 0;
+        while i < chars.len() {
+            let starts_name = (chars[i].is_alphabetic() || chars[i] == '_')
+                && (i == 0 || !(chars[i - 1].is_alphanumeric() || chars[i - 1] == '_' || chars[i - 1] == '.'));
+            if !starts_name {
+                rendered.push(chars[i]);
+                i += 1;
+                continue;
+            }
+            // A name, with its path ('seq::len')
+            let mut end = i;
+            while end < chars.len() && (chars[end].is_alphanumeric() || chars[end] == '_' || (chars[end] == ':' && chars.get(end + 1) == Some(&':'))) {
+                end += if chars[end] == ':' { 2 } else { 1 };
+            }
+            let name: String = chars[i..end].iter().collect();
+            let open = (end..chars.len()).find(|&k| !chars[k].is_whitespace()).filter(|&k| chars[k] == '(');
+            let call = open.and_then(|open| Some((open, Self::closing_paren(&chars, open)?)));
+            match (self.rules.get(&name), call) {
+                (Some(template), Some((open, close))) => {
+                    let args: Vec<String> = Self::split_args(&chars[open + 1..close]).iter()
+                        .map(|arg| Self::operand(&self.render(arg)))
+                        .collect();
+                    match Self::fill(template, &args) {
+                        Some(filled) => rendered.push_str(&filled),
+                        None => rendered.extend(&chars[i..=close]),
+                    }
+                    i = close + 1;
+                },
+                _ => {
+                    rendered.push_str(&name);
+                    i = end;
+                },
+            }
+        }
+        rendered
+    }
+
+    // Index of the ')' closing the '(' at 'open'
+    fn closing_paren(chars: &[char], open: usize) -> Option<usize> {
+        let mut depth = 0;
+        for (k, &c) in chars.iter().enumerate().skip(open) {
+            match c {
+                '(' | '[' | '{' => depth += 1,
+                ')' | ']' | '}' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Some(k);
+                    }
+                },
+                _ => {},
+            }
+        }
+        None
+    }
+
+    // Arguments separated by the commas outside of nested brackets
+    fn split_args(chars: &[char]) -> Vec<String> {
+        let mut args = vec![String::new()];
+        let mut depth = 0;
+        for &c in chars {
+            match c {
+                '(' | '[' | '{' => depth += 1,
+                ')' | ']' | '}' => depth -= 1,
+                ',' if depth == 0 => {
+                    args.push(String::new());
+                    continue;
+                },
+                _ => {},
+            }
+            args.last_mut().expect("at least one argument").push(c);
+        }
+        let args: Vec<String> = args.into_iter().map(|arg| arg.trim().to_string()).collect();
+        if args.len() == 1 && args[0].is_empty() { Vec::new() } else { args }
+    }
+
+    // An argument with an operator is parenthesized, so the template can't change how it groups
+    fn operand(arg: &str) -> String {
+        let mut depth = 0;
+        let compound = arg.chars().any(|c| {
+            match c {
+                '(' | '[' | '{' => depth += 1,
+                ')' | ']' | '}' => depth -= 1,
+                _ => {},
+            }
+            depth == 0 && c.is_whitespace()
+        });
+        if compound { format!("({})", arg) } else { arg.to_string() }
+    }
+
+    // The template with '{k}' replaced by argument k; None when it uses an argument the call doesn't have
+    fn fill(template: &str, args: &[String]) -> Option<String> {
+        let mut filled = String::new();
+        let mut rest = template;
+        while let Some(start) = rest.find('{') {
+            filled.push_str(&rest[..start]);
+            let after = &rest[start + 1..];
+            match after.find('}').and_then(|end| Some((after[..end].parse::<usize>().ok()?, end))) {
+                Some((k, end)) => {
+                    filled.push_str(args.get(k)?);
+                    rest = &after[end + 1..];
+                },
+                None => {
+                    filled.push('{');
+                    rest = after;
+                },
+            }
+        }
+        filled.push_str(rest);
+        Some(filled)
+    }
+}
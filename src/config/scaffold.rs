@@ -0,0 +1,35 @@
+// Files 'init' writes into a crate to start verifying it
+pub const STARTER_SECURED_TOML: &str = include_str!("starter_secured.toml");
+pub const STARTER_EXAMPLE: &str = include_str!("starter_example.rs");
+
+// Contracts of common std and libc functions, the starting point of 'src/config/conditions.json'
+pub const STD_CONDITIONS: &str = include_str!("std_conditions.json");
+
+// Dependency providing the annotation macros, unless 'init' is given a local checkout
+pub const SECRUST_GIT: &str = "https://github.com/vasilevlaicu/secured-rust";
+
+// The manifest with a 'secrust' entry in '[dependencies]' ('path' when given, else the git repository), or
+// None when it already depends on it
+pub fn with_secrust_dependency(manifest: &str, path: Option<&str>) -> Result<Option<String>, String> {
+    let parsed: toml::Value = toml::from_str(manifest).map_err(|e| format!("invalid Cargo.toml: {}", e))?;
+    if parsed.get("dependencies").and_then(|dependencies| dependencies.get("secrust")).is_some() {
+        return Ok(None);
+    }
+    let entry = match path {
+        Some(path) => format!("secrust = {{ path = {:?} }}", path),
+        None => format!("secrust = {{ git = {:?} }}", SECRUST_GIT),
+    };
+    // The entry goes first in an existing section, so the rest of the file keeps its layout
+    let mut lines: Vec<String> = manifest.lines().map(str::to_string).collect();
+    match lines.iter().position(|line| line.trim() == "[dependencies]") {
+        Some(section) => lines.insert(section + 1, entry),
+        None => {
+            if lines.last().is_some_and(|line| !line.trim().is_empty()) {
+                lines.push(String::new());
+            }
+            lines.push("[dependencies]".to_string());
+            lines.push(entry);
+        },
+    }
+    Ok(Some(lines.join("\n") + "\n"))
+}
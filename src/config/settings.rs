@@ -0,0 +1,215 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::config::NotationTable;
+
+// Project settings, read from 'secured.toml' in the directory the verifier runs from
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SecuredConfig {
+    pub annotations: AnnotationNames,
+    pub struct_invariants: StructInvariantSettings,
+    pub calls: CallSettings,
+    pub contracts: ContractSettings,
+    pub simplify: SimplifySettings,
+    pub solver: SolverSettings,
+    pub taint: TaintSettings,
+    pub skip: SkipSettings,
+    pub enumeration: EnumerationSettings,
+    pub notation: NotationTable,
+}
+
+// '[taint]' section: calls whose result is untrusted, calls that must not get it, and calls that clean it. A name
+// matches the calls ending with it: 'env::var' matches 'std::env::var', 'escape' any function or method 'escape'.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TaintSettings {
+    pub sources: Vec<String>,
+    pub sinks: Vec<String>,
+    pub sanitizers: Vec<String>,
+}
+
+impl Default for TaintSettings {
+    fn default() -> Self {
+        TaintSettings {
+            sources: vec!["env::var".to_string(), "env::args".to_string(), "read_line".to_string()],
+            sinks: vec!["Command::new".to_string(), "fs::remove_file".to_string(), "fs::remove_dir_all".to_string()],
+            sanitizers: Vec::new(),
+        }
+    }
+}
+
+// '[solver]' section: the time each verification condition gets, and what is tried before giving up on it
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SolverSettings {
+    pub timeout_ms: u32, // per attempt, 0 for no limit
+    pub seed: u32, // random seed of the first attempt
+    pub retries: u32, // attempts with the next seeds after an undecided one
+    pub tactics: Vec<String>, // Z3 tactics tried in order after the retries, e.g. 'qfnia'
+}
+
+impl Default for SolverSettings {
+    fn default() -> Self {
+        SolverSettings { timeout_ms: 10_000, seed: 0, retries: 2, tactics: vec!["qfnia".to_string()] }
+    }
+}
+
+// '[enumeration]' section: how many inputs '--exhaustive' tries on a basic path, and for how long
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EnumerationSettings {
+    pub max_inputs: usize, // paths with more inputs aren't enumerated
+    pub timeout_ms: u64, // per path, 0 for no limit
+}
+
+impl Default for EnumerationSettings {
+    fn default() -> Self {
+        EnumerationSettings { max_inputs: 4096, timeout_ms: 1_000 }
+    }
+}
+
+// '[simplify]' section: no-op nodes dropped from the graph once it is built, each kind can be kept
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SimplifySettings {
+    pub enabled: bool,
+    pub unit_statements: bool, // '();' and '{}' statements
+    pub empty_labels: bool, // statements with nothing to show, e.g. a lone ';'
+    pub unused_cutoffs: bool, // cut points of loops that never iterate back
+}
+
+impl Default for SimplifySettings {
+    fn default() -> Self {
+        SimplifySettings { enabled: true, unit_statements: true, empty_labels: true, unused_cutoffs: true }
+    }
+}
+
+// '[contracts]' section: bundles written by 'contracts export' in the crates this one calls into
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ContractSettings {
+    pub import: Vec<String>,
+}
+
+// '[struct_invariants]' section: methods ('Type::method') that neither establish nor preserve the invariants
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StructInvariantSettings {
+    pub skip: Vec<String>,
+}
+
+// '[skip]' section: functions left out of the graphs and the verification, like those marked '#[secured::skip]'.
+// A name is qualified ('gen::parse'), a last segment ('parse'), or a module with '::*' ('gen::*').
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SkipSettings {
+    pub functions: Vec<String>,
+}
+
+// Settings file, read from the directory the verifier runs from
+pub const SECURED_TOML: &str = "secured.toml";
+
+// The conditions database, 'src/config/conditions.json' under the directory the verifier runs from
+pub fn conditions_path() -> PathBuf {
+    ["src", "config", "conditions.json"].iter().collect()
+}
+
+impl SecuredConfig {
+    // A missing file means the default settings, a malformed one is an error
+    pub fn load(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        if !path.exists() {
+            return Ok(SecuredConfig::default());
+        }
+        let content = fs::read_to_string(path)?;
+        toml::from_str(&content).map_err(|e| format!("invalid {}: {}", path.display(), e).into())
+    }
+}
+
+// How a call of a function of the file is verified
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CallStrategy {
+    #[default]
+    Contract, // the callee's preconditions are checked and its postconditions assumed
+    Inline, // the callee's body is built in place of the call
+}
+
+// '[calls]' section: the default strategy, how deep inlining goes, and per call site choices
+// ('[[calls.site]]' with a 'callee', optionally a 'caller' and a 'line', and a 'strategy')
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CallSettings {
+    pub default: CallStrategy,
+    pub max_inline_depth: usize,
+    pub site: Vec<CallSiteStrategy>,
+}
+
+impl Default for CallSettings {
+    fn default() -> Self {
+        CallSettings { default: CallStrategy::Contract, max_inline_depth: 3, site: Vec::new() }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CallSiteStrategy {
+    pub callee: String,
+    #[serde(default)]
+    pub caller: Option<String>,
+    #[serde(default)]
+    pub line: Option<usize>,
+    pub strategy: CallStrategy,
+}
+
+// What an annotation macro means for the verifier
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AnnotationRole {
+    Pre,
+    Post,
+    Invariant,
+    Assert, // checked where it appears, then assumed by the code that follows
+    OnExit, // asserted at the exit of the loop it follows
+    Assume, // assumed where it appears without being checked, for the assumption report
+    Decreases, // termination measure of a loop, checked on the paths around it
+}
+
+impl AnnotationRole {
+    // Name of the built-in macro with this role, used in the verification conditions
+    pub fn macro_name(&self) -> &'static str {
+        match self {
+            AnnotationRole::Pre | AnnotationRole::Assume => "pre",
+            AnnotationRole::Post => "post",
+            AnnotationRole::Invariant | AnnotationRole::Assert | AnnotationRole::OnExit => "invariant",
+            AnnotationRole::Decreases => "decreases",
+        }
+    }
+}
+
+// Macro names recognized as annotations: 'pre', 'post', 'invariant', 'on_exit', 'assume' and 'decreases', plus the '[annotations]' section
+// of 'secured.toml' mapping custom names to roles ('requires = "pre"')
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(transparent)]
+pub struct AnnotationNames {
+    custom: HashMap<String, AnnotationRole>,
+}
+
+impl AnnotationNames {
+    pub fn role_of(&self, macro_name: &str) -> Option<AnnotationRole> {
+        if let Some(role) = self.custom.get(macro_name) {
+            return Some(*role);
+        }
+        match macro_name {
+            "pre" => Some(AnnotationRole::Pre),
+            "post" => Some(AnnotationRole::Post),
+            "invariant" => Some(AnnotationRole::Invariant),
+            "on_exit" => Some(AnnotationRole::OnExit),
+            "assume" => Some(AnnotationRole::Assume),
+            "decreases" => Some(AnnotationRole::Decreases),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,19 @@
+// An annotated function to start from: 'cargo secrust-verify examples/secured_example.rs' proves it
+use secrust::{invariant, post, pre};
+
+fn sum_to(n: i32) -> i32 {
+    pre!(n >= 0);
+    post!(sum == n * (n + 1) / 2);
+    let mut sum = 0;
+    let mut i = 1;
+    invariant!(i <= n + 1 && sum == (i - 1) * i / 2);
+    while i <= n {
+        sum += i;
+        i += 1;
+    }
+    return sum;
+}
+
+fn main() {
+    println!("sum_to(10) = {}", sum_to(10));
+}
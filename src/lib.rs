@@ -1,16 +1,34 @@
 pub mod cfg_builder;
 pub mod wp_calculus;
 pub mod verifier;
+pub mod report;
+pub mod config;
+pub mod smt;
+pub mod spec_parser;
+#[cfg(test)]
+mod test_support;
 
 pub use cfg_builder::*;
 pub use wp_calculus::*;
 pub use verifier::*;
+pub use report::*;
+pub use config::*;
+pub use smt::*;
+pub use spec_parser::*;
 
+// The versions of the crates the graph is made of, for the tools reading it
+pub use petgraph;
+pub use syn;
+
+use petgraph::graph::NodeIndex;
+use std::collections::{HashMap, HashSet};
 use std::path::{PathBuf, Path};
 use syn::{visit::Visit};
 
 use std::fs::{self, File};
-use std::io::Write;
+use std::io::{Read, Write};
+use std::sync::OnceLock;
+use std::time::Instant;
 
 
 // Exporting macros for users
@@ -29,51 +47,1562 @@ macro_rules! invariant {
     ($($t:tt)*) => {{}};
 }
 
-pub fn run_verification(file_path: &PathBuf, generate_dot: bool) -> Result<(), Box<dyn std::error::Error>> {
+// Fact taken without proof, listed by '--assumptions'
+#[macro_export]
+macro_rules! assume {
+    ($($t:tt)*) => {{}};
+}
+
+// Termination measure of a loop, or of a recursive '#[logic]' function
+#[macro_export]
+macro_rules! decreases {
+    ($($t:tt)*) => {{}};
+}
+
+#[macro_export]
+macro_rules! struct_invariant {
+    ($($t:tt)*) => {};
+}
+
+// What a verification run proves
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum VerificationGoal {
+    #[default]
+    Contracts, // the pre/post conditions and invariants of the annotated functions
+    NoPanic, // only the absence of panicking operations, in every function
+    NoneSafety, // only the unwraps of Options that may be None, in every function, without a solver
+}
+
+impl VerificationGoal {
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name {
+            "contracts" => Ok(VerificationGoal::Contracts),
+            "no-panic" => Ok(VerificationGoal::NoPanic),
+            "none-safety" => Ok(VerificationGoal::NoneSafety),
+            _ => Err(format!("unknown goal '{}' (expected 'contracts', 'no-panic' or 'none-safety')", name)),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            VerificationGoal::Contracts => "contracts",
+            VerificationGoal::NoPanic => "no-panic",
+            VerificationGoal::NoneSafety => "none-safety",
+        }
+    }
+}
+
+// Artifacts selected with '--emit', on top of the ones requested by '--dot' and '--stats'
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Artifacts {
+    pub graphs: bool, // whole-file CFG in DOT
+    pub paths: bool, // one DOT file per basic path
+    pub smt: bool, // SMT-LIB2 script of each verification condition
+    pub proofs: bool, // solver proof of each proved verification condition
+    pub report: bool, // verified.json statistics and badge.json
+    pub metrics: bool, // metrics.prom, in the Prometheus text format
+    pub bundle: bool, // the basic paths of each function in one DOT file of clusters, with an HTML index
+    pub vcs: bool, // each basic path as a Hoare triple with its verification condition, in text
+}
+
+impl Artifacts {
+    // Parse a comma separated list of artifact kinds ('graphs,report')
+    pub fn parse(list: &str) -> Result<Self, String> {
+        let mut artifacts = Artifacts::default();
+        for kind in list.split(',').map(|kind| kind.trim().to_lowercase()).filter(|kind| !kind.is_empty()) {
+            match kind.as_str() {
+                "graphs" => artifacts.graphs = true,
+                "paths" => artifacts.paths = true,
+                "smt" => artifacts.smt = true,
+                "proofs" => artifacts.proofs = true,
+                "report" => artifacts.report = true,
+                "metrics" => artifacts.metrics = true,
+                "bundle" => artifacts.bundle = true,
+                "vcs" => artifacts.vcs = true,
+                _ => return Err(format!("unknown artifact '{}' (expected one of: graphs, paths, smt, proofs, report, metrics, bundle, vcs)", kind)),
+            }
+        }
+        Ok(artifacts)
+    }
+}
+
+// Options controlling what a verification run produces
+#[derive(Debug, Clone, Default)]
+pub struct VerificationOptions {
+    pub generate_dot: bool,
+    pub generate_json: bool,
+    pub max_label_len: Option<usize>, // shorten DOT labels past this many characters
+    pub generate_summary: bool, // one node per function with call edges, plus an HTML view
+    pub generate_skeleton: bool, // annotations only, connected where a code path leads from one to the other
+    pub node_filter: NodeFilter, // node categories kept in the DOT and JSON exports
+    pub sample_paths: Option<usize>, // verify at most this many basic paths
+    pub sampling_strategy: SamplingStrategy,
+    pub goal: VerificationGoal,
+    pub generate_stats: bool, // verified.json statistics and a shields.io badge endpoint
+    pub summarize_regions: bool, // cross loop-free branching regions in one step of the basic paths
+    pub color: ColorChoice, // colors of the console summary
+    pub emit: Artifacts, // artifacts to write; the exporters of the others don't run
+    pub debug_phases: bool, // DOT snapshots of the graph after each builder phase
+    pub export_contracts: Option<ContractExport>, // write the contracts of the verified functions as a bundle
+    pub cfg: Option<CompilationConfig>, // '--cfg' options; code under '#[cfg(..)]' is kept as is without them
+    pub all_cfgs: bool, // verify every configuration of the file's 'cfg' options, each in its own output directory
+    pub function: Option<String>, // verify only this function, by qualified name or last segment
+    pub branch_profile: Option<PathBuf>, // branch counts replacing the '#[likely]'/'#[unlikely]' hints
+    pub doc_samples: bool, // verify the Rust samples of the file's doc comments instead of its code
+    pub exhaustive: bool, // check the paths of functions with small parameter domains by enumerating their inputs
+    pub solver_timeout_ms: Option<u32>, // '--timeout': replaces 'timeout_ms' of the '[solver]' settings
+    pub solver_seed: Option<u32>, // '--solver-seed': replaces 'seed' of the '[solver]' settings
+    pub invocation: Vec<String>, // command-line arguments of the run, recorded in the artifacts
+    pub output_dir: Option<PathBuf>, // root of the artifacts, 'out' by default
+    pub incremental: bool, // keep the verdicts of the previous run for the obligations whose code and contracts didn't change
+    pub source_root: Option<PathBuf>, // the artifacts of a file under it are named by its path relative to it ('--crate': the workspace root)
+    pub markdown_report: bool, // '--report md': 'report.md' with the verdicts, the CFGs of the failed functions and the counterexamples
+    pub smt_dir: Option<PathBuf>, // '--smt-dir': the SMT-LIB2 script of each path's verification condition, '<function>_<path>.smt2'
+    pub assumptions: bool, // '--assumptions': the unchecked facts the verdicts rely on, in 'assumptions.json'
+}
+
+impl VerificationOptions {
+    pub fn output_root(&self) -> &Path {
+        self.output_dir.as_deref().unwrap_or(Path::new(OUTPUT_ROOT))
+    }
+}
+
+// Where 'contracts export' writes the bundle and the crate name the importers call through
+#[derive(Debug, Clone, Default)]
+pub struct ContractExport {
+    pub output: Option<PathBuf>, // 'contracts.json' in the output directory by default
+    pub crate_name: Option<String>, // the package of './Cargo.toml' by default, else the file name
+}
+
+pub fn run_verification(file_path: &PathBuf, options: &VerificationOptions) -> Result<(), Box<dyn std::error::Error>> {
+    if options.all_cfgs && options.cfg.is_none() {
+        return run_all_configurations(file_path, options);
+    }
+    if options.doc_samples || file_path.extension().is_some_and(|extension| extension == "md") {
+        return verify_doc_samples(file_path, options).map(|_| ());
+    }
+    verify_file(file_path, options).map(|_| ())
+}
+
+// Verify one file and write its artifacts, returning the outcome of its obligations
+pub fn verify_file(file_path: &PathBuf, options: &VerificationOptions) -> Result<VerificationStats, Box<dyn std::error::Error>> {
     println!("file path: {:?}", file_path);
-    let content = std::fs::read_to_string(&file_path)?;
+    let content = read_source(file_path)?;
+    verify_source(file_path, &content, options)
+}
+
+// Verify the '```rust' samples of a markdown file, or of the doc comments of a source file, each on its own.
+// A sample is named after its line ('README.md/sample_12'), its artifacts go in 'out/README.md/sample_12/'.
+pub fn verify_doc_samples(file_path: &PathBuf, options: &VerificationOptions) -> Result<Vec<(CodeSample, VerificationStats)>, Box<dyn std::error::Error>> {
+    let content = read_input(file_path)?;
+    let samples = match file_path.extension().is_some_and(|extension| extension == "md") {
+        true => CfgBuilder::markdown_samples(&content),
+        false => CfgBuilder::doc_comment_samples(&content),
+    };
+    println!("{} Rust samples in {:?}", samples.len(), file_path);
+    let mut results = Vec::new();
+    let mut skipped = Vec::new();
+    for sample in samples {
+        let sample_path = file_path.join(format!("sample_{}", sample.line));
+        println!("=== Sample at line {} ===", sample.line);
+        // A sample that doesn't parse, or has nothing to verify, doesn't stop the others
+        match verify_source(&sample_path, &expand_comment_specs(&sample_path, &sample.source)?, options) {
+            Ok(stats) => results.push((sample, stats)),
+            Err(e) => skipped.push(format!("Sample at line {} skipped: {}", sample.line, e)),
+        }
+    }
+    println!("Doc samples of {:?}:", file_path);
+    for (sample, stats) in &results {
+        println!("  Sample at line {}: {}", sample.line, stats.one_liner());
+    }
+    for reason in skipped {
+        println!("  {}", reason);
+    }
+    Ok(results)
+}
+
+// Verify a file with this content, 'file_path' naming it in the report and the output directory
+fn verify_source(file_path: &Path, content: &str, options: &VerificationOptions) -> Result<VerificationStats, Box<dyn std::error::Error>> {
     println!("File content (first 100 characters):\n{}", &content[..content.len().min(100)]);
 
     // parse file and build ast
-    let ast = syn::parse_file(&content)?;
-    println!("AST successfully parsed for file {:?}", file_path);
+    let (builder, ast, basic_paths) = build_paths(file_path, content, options)?;
+    let mut stats = run_analyses(file_path, &builder, &ast, &basic_paths, options);
+    let mut output = RunOutput::new(file_path, content, &stats, options)?;
+    let verdicts = discharge_paths(file_path, &builder, &ast, &basic_paths, &mut stats, &output, options)?;
+    report_results(file_path, &builder, &basic_paths, &stats, &verdicts, &mut output, options)?;
+    Ok(stats)
+}
 
-    // visit ast
-    let mut builder = CfgBuilder::new();
+// Where the artifacts of a file go, and the manifest and provenance they are recorded with
+struct RunOutput {
+    layout: OutputLayout,
+    output_dir: PathBuf,
+    file_stem: String,
+    function_ids: Vec<(String, String)>,
+    manifest: Manifest,
+    provenance: Provenance,
+}
 
-    builder.build_cfg(&ast);
+impl RunOutput {
+    fn new(file_path: &Path, content: &str, stats: &VerificationStats, options: &VerificationOptions) -> Result<Self, Box<dyn std::error::Error>> {
+        // Artifacts of the whole file go in 'out/<file>/', those of a function in 'out/<file>/<function>/'
+        let file_stem = match file_path == Path::new(STDIN_INPUT) {
+            true => std::ffi::OsStr::new("stdin"),
+            false => file_path.file_stem().unwrap_or(file_path.as_os_str()), // Get the file name without extension
+        };
+        let function_ids: Vec<(String, String)> = stats.functions.iter().map(|function| (function.id.clone(), function.name.clone())).collect();
+        let named = options.source_root.as_ref().and_then(|root| file_path.strip_prefix(root).ok()).unwrap_or(file_path);
+        let layout = match (&options.cfg, options.all_cfgs) {
+            (Some(config), true) => OutputLayout::for_variant(options.output_root(), named, &config.dir_name(), &function_ids),
+            _ => OutputLayout::new(options.output_root(), named, &function_ids),
+        };
+        let output_dir = layout.file_dir().to_path_buf();
 
-    let basic_paths = builder.generate_basic_paths();
+        // Every file written is indexed in 'manifest.json' and stamped with what the run read
+        let mut manifest = Manifest::new(file_path.display().to_string());
+        let config = SecuredConfig::load(Path::new(SECURED_TOML))?;
+        let mut inputs = vec![conditions_path(), PathBuf::from(SECURED_TOML)];
+        inputs.extend(config.contracts.import.iter().map(PathBuf::from));
+        inputs.extend(options.branch_profile.clone());
+        let provenance = Provenance::new(file_path.display().to_string(), content, &inputs, options.invocation.clone());
+        manifest.provenance = Some(provenance.clone());
+        Ok(RunOutput { layout, output_dir, file_stem: file_stem.to_string_lossy().to_string(), function_ids, manifest, provenance })
+    }
+
+    fn function_dir(&self, function: Option<&String>) -> PathBuf {
+        self.layout.function_dir(function.map(String::as_str)).to_path_buf()
+    }
+
+    // Index an artifact written in the output directory in the manifest
+    fn record(&mut self, path: &Path, kind: &str, function: Option<String>) -> Result<(), Box<dyn std::error::Error>> {
+        self.manifest.record(&self.output_dir, path, kind, function)
+    }
+}
+
+// Verdicts of the basic paths of a file, by path, with what the reports show about them
+struct PathVerdicts {
+    implications: Vec<String>,
+    verdicts: Vec<Option<Verdict>>,
+    irrelevant: Vec<HashSet<NodeIndex>>, // nodes the proof of each proved path doesn't need
+    counterexamples: HashMap<usize, String>, // trace and source lines of the failed paths, by path number
+    index: ObligationIndex, // '--incremental': the obligations of this run, for the next one
+}
+
+impl PathVerdicts {
+    fn proved(&self, i: usize) -> bool {
+        self.verdicts.get(i) == Some(&Some(Verdict::Valid))
+    }
+}
+
+// Statistics of the file, and the goals checked on the graph rather than on the basic paths
+fn run_analyses(file_path: &Path, builder: &CfgBuilder, ast: &syn::File, basic_paths: &[Vec<NodeIndex>], options: &VerificationOptions) -> VerificationStats {
+    let analyzed_functions = builder.function_nodes().into_iter().map(|n| (builder.function_id(n), builder.graph[n].label())).collect();
+    let mut stats = VerificationStats::new(file_path.display().to_string(), ast, analyzed_functions);
+    stats.skipped = builder.results.skipped_functions.clone();
+    stats.cfg_size = (builder.graph.node_count(), builder.graph.edge_count());
+    stats.basic_paths = basic_paths.len();
+    stats.attributes = builder.file_attributes(ast);
+    if let Some(shebang) = &stats.shebang {
+        println!("Script: {}", shebang);
+    }
+    if !stats.attributes.is_empty() {
+        println!("File attributes: {}", stats.attributes.join(", "));
+    }
+
+    if options.goal == VerificationGoal::NoPanic {
+        verify_no_panic(builder, &mut stats);
+    }
+    if options.goal == VerificationGoal::NoneSafety {
+        verify_none_safety(builder, &mut stats);
+    }
+    stats
+}
 
-    let final_implication = builder.apply_wp_calculus(&basic_paths);
-    for (i, implication) in final_implication.iter().enumerate() {
+// Decide the verification condition of each basic path: kept from the previous run, by the intervals or by
+// the solver, most likely paths first
+fn discharge_paths(file_path: &Path, builder: &CfgBuilder, ast: &syn::File, basic_paths: &[Vec<NodeIndex>], stats: &mut VerificationStats,
+    output: &RunOutput, options: &VerificationOptions) -> Result<PathVerdicts, Box<dyn std::error::Error>> {
+    // '--incremental': the verdicts of the previous run are kept for the obligations whose path, code and
+    // contracts didn't change
+    let mut index = ObligationIndex::default();
+    let mut previous_index = None;
+    if options.incremental {
+        let settings = format!("{} {} {:?} {:?} {} {}", CfgBuilder::shared_fingerprint(ast), serde_json::to_string(&output.provenance.inputs)?,
+            options.goal, options.cfg, options.summarize_regions, options.exhaustive);
+        index.settings = format!("{:016x}", fnv1a(settings.as_bytes()));
+        index.functions = builder.function_fingerprints(ast);
+        previous_index = ObligationIndex::load(&output.output_dir);
+        match &previous_index {
+            Some(previous) if previous.settings != index.settings => println!("Incremental: the types, logic functions, configuration or options changed, every obligation is verified"),
+            Some(previous) => {
+                let (code, contracts) = index.changes(previous);
+                println!("Incremental: code changed in {}; only the contract changed in {}",
+                    if code.is_empty() { "no function".to_string() } else { code.join(", ") },
+                    if contracts.is_empty() { "no function".to_string() } else { contracts.join(", ") });
+            },
+            None => println!("Incremental: no previous run in {:?}, every obligation is verified", output.output_dir),
+        }
+    }
+    let mut reused = 0;
+    // The no-panic and none-safety goals ignore the functional contracts
+    let final_implication = match options.goal {
+        VerificationGoal::Contracts => builder.apply_wp_calculus(basic_paths),
+        VerificationGoal::NoPanic | VerificationGoal::NoneSafety => Vec::new(),
+    };
+    let mut irrelevant = vec![HashSet::new(); basic_paths.len()];
+    let mut verdicts = vec![None; final_implication.len()];
+    let mut counterexamples: HashMap<usize, String> = HashMap::new();
+    let emit_paths = options.generate_dot || options.emit.paths;
+    // The most likely paths are checked first when branches are hinted or profiled
+    let order: Vec<usize> = builder.verification_order(basic_paths).into_iter().filter(|&i| i < final_implication.len()).collect();
+    let mut discharged = 0;
+    if order.windows(2).any(|pair| pair[0] > pair[1]) {
+        println!("Paths by likelihood: {}", order.iter().map(|i| (i + 1).to_string()).collect::<Vec<_>>().join(", "));
+    }
+    for i in order {
+        let implication = &final_implication[i];
         println!("---------");
-        println!("Final implication for Path {}: {}", i + 1, implication);
-        verifier::verify_str_implication(implication);
+        // Paths reaching a postcondition are reported with the exit they leave the function through
+        match basic_paths.get(i).and_then(|path| builder.exit_of_path(path)) {
+            Some(exit) => println!("Final implication for Path {} (exit: {}): {}", i + 1, exit, implication),
+            None => println!("Final implication for Path {}: {}", i + 1, implication),
+        }
+        let started = Instant::now();
+        // The dependencies of the path, and its verdict in the previous run if none of them changed
+        let dependencies = match (options.incremental, basic_paths.get(i)) {
+            (true, Some(path)) => Some((path.iter().map(|&node| builder.node_id(node)).collect::<Vec<_>>().join(" "), builder.path_dependencies(path, &index.functions))),
+            _ => None,
+        };
+        let previous_verdict = match (&previous_index, &dependencies) {
+            (Some(previous), Some((key, dependencies))) => index.reusable(previous, key, dependencies),
+            _ => None,
+        };
+        // Annotations the ranges of the variables already imply don't need the solver
+        let outcome = match (previous_verdict, basic_paths.get(i).and_then(|path| builder.interval_discharge(path))) {
+            (Some(verdict), _) => {
+                let contracts = dependencies.as_ref().map(|(_, dependencies)| dependencies.contracts.iter().cloned().collect::<Vec<_>>()).unwrap_or_default();
+                println!("Path {} keeps its verdict of the previous run ({}): its code and the contracts it mentions ({}) are unchanged", i + 1, verdict.describe(),
+                    if contracts.is_empty() { "none".to_string() } else { contracts.join(", ") });
+                reused += 1;
+                verdict.outcome()
+            },
+            (None, Some(ranges)) => {
+                println!("Path {} holds by interval analysis ({}), the solver isn't called", i + 1, ranges.join(", "));
+                discharged += 1;
+                VcOutcome::Valid
+            },
+            (None, None) => solver_verdict(implication, &builder.options.solver_settings),
+        };
+        // '--exhaustive' checks the solver's verdict on every input, and decides the paths it couldn't
+        let outcome = match (options.exhaustive && previous_verdict.is_none(), basic_paths.get(i)) {
+            (true, Some(path)) => cross_check(builder, i + 1, path, outcome),
+            _ => outcome,
+        };
+        println!("Path {}: {}", i + 1, outcome.summary());
+        if let Some((key, dependencies)) = dependencies {
+            index.obligations.push(ObligationRecord { key, dependencies, verdict: Verdict::from(&outcome) });
+        }
+        let elapsed = started.elapsed();
+        verdicts[i] = Some(Verdict::from(&outcome));
+        if let Some(path) = basic_paths.get(i) {
+            if let (Some(function), Some((id, annotation))) = (builder.function_id_of_node(path[0]), builder.path_target(path)) {
+                stats.record_time(&function, format!("Path {}", i + 1), elapsed);
+                // The report shows the annotations in the notation of the project
+                let annotation = builder.options.notation.render(&annotation);
+                let result = PathResult {
+                    path: i + 1,
+                    from: builder.options.notation.render(&builder.annotation_label(path[0])),
+                    exit: builder.exit_of_path(path),
+                    verdict: Verdict::from(&outcome),
+                };
+                stats.record_path(&function, &id, &annotation, result);
+            }
+        }
+        // The graphs of proved paths dim what the proof doesn't need
+        if let (VcOutcome::Valid, true, Some(path)) = (&outcome, emit_paths || options.emit.bundle, basic_paths.get(i)) {
+            if let Some(nodes) = builder.irrelevant_nodes(path) {
+                let mut unused: Vec<String> = path.iter()
+                    .filter(|&node| nodes.contains(node) && !matches!(builder.graph[*node], CfgNode::Statement(_, _)))
+                    .map(|&node| builder.options.notation.render(&builder.annotation_label(node)))
+                    .collect();
+                unused.dedup();
+                if !unused.is_empty() {
+                    println!("Path {} is proved without: {}", i + 1, unused.join("; "));
+                }
+                irrelevant[i] = nodes;
+            }
+        }
+        if let VcOutcome::Counterexample(values) = outcome {
+            // Replay the failed path with the counterexample to show where the implication breaks
+            if let Some(path) = basic_paths.get(i).filter(|_| previous_verdict.is_none()) {
+                let trace = builder.path_trace(path, &values);
+                println!("Trace of failed Path {}:", i + 1);
+                println!("{}", trace);
+                let source_lines = builder.source_lines_of_path(path, &values);
+                println!("Source lines of failed Path {} in {}:", i + 1, file_path.display());
+                println!("{}", source_lines);
+                counterexamples.insert(i + 1, format!("{}\nSource lines:\n{}", trace, source_lines));
+            }
+        }
         println!("Verification completed for {:?}", implication);
         println!("---------");
         println!("");
     }
 
-    if generate_dot {
-        // Save the DOT file and basic paths in the directory named after the input file
-        let output_base_path = Path::new("src/graphs");
-        let file_stem = file_path.file_stem().unwrap(); // Get the file name without extension
-        let output_dir = output_base_path.join(file_stem); // Create directory path as "src/graphs/filename"
+    if discharged > 0 {
+        println!("{} of {} paths decided by interval analysis without the solver", discharged, final_implication.len());
+    }
+    if options.incremental {
+        println!("Incremental: {} of {} obligations verified again, {} kept from the previous run", final_implication.len() - reused, final_implication.len(), reused);
+    }
+    Ok(PathVerdicts { implications: final_implication, verdicts, irrelevant, counterexamples, index })
+}
 
-        // Generate the DOT format for the entire CFG
-        let dot_format = builder.to_dot();
+// Print the summary of the run and write the artifacts asked for, then drop the stale ones of earlier runs
+fn report_results(file_path: &Path, builder: &CfgBuilder, basic_paths: &[Vec<NodeIndex>], stats: &VerificationStats, verdicts: &PathVerdicts,
+    output: &mut RunOutput, options: &VerificationOptions) -> Result<(), Box<dyn std::error::Error>> {
+    if options.goal == VerificationGoal::Contracts && !verdicts.implications.is_empty() {
+        print!("{}", stats.obligations_by_annotation());
+    }
+    print!("{}", stats.console_summary(options.color.enabled()));
+    println!("{}", stats.one_liner());
+
+    write_condition_artifacts(builder, basic_paths, verdicts, output, options)?;
+    write_reports(file_path, builder, basic_paths, stats, verdicts, output, options)?;
+    write_graphs(builder, basic_paths, verdicts, output, options)?;
+
+    // Contracts of the verified functions, for the crates calling into this one
+    if let Some(export) = &options.export_contracts {
+        let crate_name = export.crate_name.clone().unwrap_or_else(|| package_name().unwrap_or_else(|| output.file_stem.clone()));
+        let verified: HashSet<String> = stats.functions.iter()
+            .filter(|function| function.status() == Verdict::Valid)
+            .map(|function| function.name.clone())
+            .collect();
+        let bundle = builder.export_contracts(&crate_name, &file_path.display().to_string(), &verified);
+        let bundle_path = export.output.clone().unwrap_or_else(|| output.output_dir.join("contracts.json"));
+        if let Some(parent) = bundle_path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&bundle_path, serde_json::to_string_pretty(&bundle)?)?;
+        if bundle_path.starts_with(&output.output_dir) {
+            output.record(&bundle_path, "contract-bundle", None)?;
+        } else {
+            output.provenance.stamp(&bundle_path)?;
+        }
+        println!("Contracts of {} verified functions exported as: {:?}", bundle.functions.len(), bundle_path);
+    }
+
+    if options.incremental {
+        let index_path = verdicts.index.write(&output.output_dir)?;
+        output.record(&index_path, "obligation-index", None)?;
+    }
+
+    // One DOT file per builder phase, to find the phase that broke the graph
+    if !builder.results.phase_snapshots.is_empty() {
+        let phases_dir = output.output_dir.join("phases");
+        for phase_file in builder.write_phase_snapshots(&phases_dir)? {
+            output.record(&phase_file, "phase-dot", None)?;
+        }
+        println!("Graph after each phase saved in: {:?}", phases_dir);
+    }
+
+    // Artifacts of the previous run that are now stale are deleted
+    let output_dir = &output.output_dir;
+    if let Some(previous) = Manifest::load(output_dir) {
+        // The ones kept were made by an earlier run, from what may since have changed
+        let changes = previous.provenance.as_ref().map(|old| output.provenance.changes_since(old)).unwrap_or_default();
+        let functions: HashSet<String> = output.function_ids.iter().map(|(id, _)| id.clone()).collect();
+        let written = output.manifest.artifacts.len();
+        let removed = output.manifest.remove_stale(previous, output_dir, &functions)?;
+        if removed > 0 {
+            println!("Removed {} stale artifacts from: {:?}", removed, output_dir);
+        }
+        let kept = output.manifest.artifacts.len() - written;
+        if kept > 0 && !changes.is_empty() {
+            println!("Warning: {} artifacts in {:?} are from an earlier run ({})", kept, output_dir, changes.join(", "));
+        }
+    }
+
+    if !output.manifest.is_empty() {
+        output.manifest.write(output_dir)?;
+    }
+    Ok(())
+}
+
+// The verification conditions in the forms other tools read: SMT-LIB2 scripts, Hoare triples and proofs
+fn write_condition_artifacts(builder: &CfgBuilder, basic_paths: &[Vec<NodeIndex>], verdicts: &PathVerdicts, output: &mut RunOutput,
+    options: &VerificationOptions) -> Result<(), Box<dyn std::error::Error>> {
+    let final_implication = &verdicts.implications;
+    if options.emit.smt && !final_implication.is_empty() {
+        for (i, implication) in final_implication.iter().enumerate() {
+            let function = basic_paths.get(i).and_then(|path| builder.function_id_of_node(path[0]));
+            let smt_dir = output.function_dir(function.as_ref()).join("smt");
+            fs::create_dir_all(&smt_dir)?;
+            let smt_path = smt_dir.join(format!("path_{}.smt2", i + 1));
+            fs::write(&smt_path, verifier::implication_to_smtlib(implication)?)?;
+            output.record(&smt_path, "smt", function)?;
+        }
+        println!("SMT-LIB2 verification conditions saved in the smt directory of each function in: {:?}", output.output_dir);
+    }
+
+    // '--emit vcs': the Hoare triple and the verification condition of each path of a function in 'vcs.txt'
+    if options.emit.vcs && options.goal == VerificationGoal::Contracts {
+        let mut functions: Vec<(String, String)> = Vec::new();
+        for (i, path) in basic_paths.iter().enumerate() {
+            let (Some(function), Some(triple)) = (path.first().and_then(|&start| builder.function_id_of_node(start)), builder.hoare_triple(i + 1, path)) else { continue };
+            match functions.iter_mut().find(|(id, _)| *id == function) {
+                Some((_, text)) => text.push_str(&format!("\n{}", triple)),
+                None => functions.push((function, triple)),
+            }
+        }
+        for (function, text) in &functions {
+            let dir = output.function_dir(Some(function));
+            fs::create_dir_all(&dir)?;
+            let vcs_path = dir.join("vcs.txt");
+            fs::write(&vcs_path, text)?;
+            output.record(&vcs_path, "vcs", Some(function.clone()))?;
+        }
+        println!("Hoare triples and verification conditions saved as vcs.txt in the directory of each function in: {:?}", output.output_dir);
+    }
 
+    // '--smt-dir': the scripts of the 'smt' backend, next to the sources ('src/vcs/' by default), to check
+    // the obligations with any SMT-LIB2 solver
+    if let Some(smt_dir) = options.smt_dir.as_ref().filter(|_| options.goal == VerificationGoal::Contracts) {
+        let mut written = 0;
+        for (i, path) in basic_paths.iter().enumerate() {
+            let function = path.first().and_then(|&start| builder.function_of_node(start)).unwrap_or_default();
+            match builder.path_smtlib(i + 1, path) {
+                Ok(script) => {
+                    fs::create_dir_all(smt_dir)?;
+                    let script_path = smt_dir.join(format!("{}_{}.smt2", OutputLayout::dir_name(&function), i + 1));
+                    fs::write(&script_path, script)?;
+                    output.provenance.stamp(&script_path)?;
+                    written += 1;
+                },
+                Err(reason) => println!("Warning: Path {} of {} has no SMT-LIB2 script: {}", i + 1, function, reason),
+            }
+        }
+        println!("SMT-LIB2 scripts of {} of {} paths saved in: {:?}", written, basic_paths.len(), smt_dir);
+    }
+
+    // Proofs are recomputed with proof generation on, which slows the solver down, so only for the proved paths
+    if options.emit.proofs && (0..final_implication.len()).any(|i| verdicts.proved(i)) {
+        for (i, implication) in final_implication.iter().enumerate().filter(|&(i, _)| verdicts.proved(i)) {
+            let function = basic_paths.get(i).and_then(|path| builder.function_id_of_node(path[0]));
+            let Some(proof) = verifier::implication_proof(implication, &builder.options.solver_settings) else {
+                println!("Warning: no proof object for Path {}, the solver didn't prove it again", i + 1);
+                continue;
+            };
+            let proof_dir = output.function_dir(function.as_ref()).join("proofs");
+            fs::create_dir_all(&proof_dir)?;
+            let proof_path = proof_dir.join(format!("path_{}.proof", i + 1));
+            fs::write(&proof_path, proof)?;
+            output.record(&proof_path, "proof", function)?;
+        }
+        println!("Proofs of the proved paths saved in the proofs directory of each function in: {:?}", output.output_dir);
+    }
+    Ok(())
+}
+
+// The reports of the verdicts: statistics, markdown, assumptions and metrics
+fn write_reports(file_path: &Path, builder: &CfgBuilder, basic_paths: &[Vec<NodeIndex>], stats: &VerificationStats, verdicts: &PathVerdicts,
+    output: &mut RunOutput, options: &VerificationOptions) -> Result<(), Box<dyn std::error::Error>> {
+    let output_dir = output.output_dir.clone();
+    // '--stats' stands for the report
+    if options.generate_stats || options.emit.report {
+        fs::create_dir_all(&output_dir)?;
+        let stats_path = output_dir.join("verified.json");
+        fs::write(&stats_path, serde_json::to_string_pretty(&stats.to_json())?)?;
+        output.record(&stats_path, "stats", None)?;
+        let badge_path = output_dir.join("badge.json");
+        fs::write(&badge_path, serde_json::to_string_pretty(&stats.badge_json())?)?;
+        output.record(&badge_path, "badge", None)?;
+        println!("Verification statistics saved as: {:?} and {:?}", stats_path, badge_path);
+        for function in &stats.functions {
+            let Some(report) = stats.function_json(&function.id) else { continue };
+            let dir = output.function_dir(Some(&function.id));
+            fs::create_dir_all(&dir)?;
+            let report_path = dir.join("report.json");
+            fs::write(&report_path, serde_json::to_string_pretty(&report)?)?;
+            output.record(&report_path, "function-report", Some(function.id.clone()))?;
+        }
+        if !builder.results.call_strategies.is_empty() {
+            let calls_path = output_dir.join("calls.json");
+            fs::write(&calls_path, serde_json::to_string_pretty(&builder.results.call_strategies)?)?;
+            output.record(&calls_path, "call-strategies", None)?;
+            println!("Call strategies saved as: {:?}", calls_path);
+        }
+    }
+    // '--report md': the failed functions get their CFG, with the nodes of their failed paths outlined
+    if options.markdown_report {
+        let mut graphs = HashMap::new();
+        for func_node in builder.function_nodes() {
+            let function = builder.function_id(func_node);
+            if !stats.functions.iter().any(|result| result.id == function && result.status() == Verdict::Failed) {
+                continue;
+            }
+            let failed_nodes: HashSet<NodeIndex> = basic_paths.iter().enumerate()
+                .filter(|&(i, path)| verdicts.verdicts.get(i) == Some(&Some(Verdict::Failed)) && builder.function_id_of_node(path[0]).as_ref() == Some(&function))
+                .flat_map(|(_, path)| path.iter().copied())
+                .collect();
+            graphs.insert(function, builder.function_to_mermaid(func_node, &failed_nodes));
+        }
+        fs::create_dir_all(&output_dir)?;
+        let markdown_path = output_dir.join("report.md");
+        fs::write(&markdown_path, stats.to_markdown(&graphs, &verdicts.counterexamples))?;
+        output.record(&markdown_path, "markdown-report", None)?;
+        println!("Markdown report saved as: {:?}", markdown_path);
+    }
+    // '--assumptions': everything the verdicts take for granted, and how many proofs rely on it
+    if options.assumptions {
+        let facts = builder.unchecked_facts();
+        let fact_nodes: HashSet<NodeIndex> = facts.iter().filter_map(|(node, _)| *node).collect();
+        let mut budget = AssumptionBudget { facts: facts.into_iter().map(|(_, fact)| fact).collect(), ..Default::default() };
+        for (i, path) in basic_paths.iter().enumerate() {
+            match verdicts.verdicts.get(i).copied().flatten() {
+                Some(Verdict::Valid) => {
+                    budget.proved_paths += 1;
+                    if path.iter().any(|node| fact_nodes.contains(node)) {
+                        budget.relying_paths += 1;
+                    }
+                },
+                Some(Verdict::Unknown) => {
+                    let target = builder.path_target(path).map(|(_, label)| label).unwrap_or_default();
+                    budget.facts.push(UncheckedFact {
+                        kind: AssumptionKind::Undecided,
+                        function: path.first().and_then(|&start| builder.function_of_node(start)).unwrap_or_default(),
+                        line: path.last().and_then(|&end| builder.source_span(end).map(|span| span.line)),
+                        detail: format!("Path {}: {} -> {}", i + 1, builder.annotation_label(path[0]), target),
+                    });
+                },
+                _ => {},
+            }
+        }
+        print!("{}", budget.console());
+        fs::create_dir_all(&output_dir)?;
+        let assumptions_path = output_dir.join("assumptions.json");
+        fs::write(&assumptions_path, serde_json::to_string_pretty(&budget.to_json(&file_path.display().to_string()))?)?;
+        output.record(&assumptions_path, "assumptions", None)?;
+        println!("Assumption report saved as: {:?}", assumptions_path);
+    }
+    if options.emit.metrics {
+        fs::create_dir_all(&output_dir)?;
+        let metrics_path = output_dir.join("metrics.prom");
+        fs::write(&metrics_path, prometheus_metrics(&[(stats, options.goal.name())]))?;
+        output.record(&metrics_path, "metrics", None)?;
+        println!("Metrics saved as: {:?}", metrics_path);
+    }
+    Ok(())
+}
+
+// The graph of the file in its exports: JSON, summary, skeleton, the basic paths and the DOT graphs
+fn write_graphs(builder: &CfgBuilder, basic_paths: &[Vec<NodeIndex>], verdicts: &PathVerdicts, output: &mut RunOutput,
+    options: &VerificationOptions) -> Result<(), Box<dyn std::error::Error>> {
+    let output_dir = output.output_dir.clone();
+    let file_stem = output.file_stem.clone();
+    if options.generate_json {
+        fs::create_dir_all(&output_dir)?;
+        let json_file_path = output_dir.join(format!("{}.json", file_stem));
+        fs::write(&json_file_path, serde_json::to_string_pretty(&builder.to_json())?)?;
+        output.record(&json_file_path, "cfg-json", None)?;
+        println!("JSON graph saved as: {:?}", json_file_path);
+    }
+
+    if options.generate_summary {
+        fs::create_dir_all(&output_dir)?;
+        let summary_dot_path = output_dir.join(format!("{}_summary.dot", file_stem));
+        fs::write(&summary_dot_path, builder.summary_to_dot())?;
+        output.record(&summary_dot_path, "summary-dot", None)?;
+        let summary_html_path = output_dir.join(format!("{}_summary.html", file_stem));
+        fs::write(&summary_html_path, builder.summary_to_html())?;
+        output.record(&summary_html_path, "summary-html", None)?;
+        println!("Function summary saved as: {:?} and {:?}", summary_dot_path, summary_html_path);
+    }
+
+    if options.generate_skeleton {
+        fs::create_dir_all(&output_dir)?;
+        let skeleton_path = output_dir.join(format!("{}_skeleton.dot", file_stem));
+        fs::write(&skeleton_path, builder.skeleton_to_dot())?;
+        output.record(&skeleton_path, "skeleton-dot", None)?;
+        println!("Annotation skeleton saved as: {:?}", skeleton_path);
+    }
+
+    // '--emit bundle': the paths of each function in 'paths.dot', one cluster per path, and 'paths.html'
+    if options.emit.bundle {
+        let mut functions: Vec<(String, Vec<BundledPath>)> = Vec::new();
+        for (i, path) in basic_paths.iter().enumerate() {
+            let Some(function) = path.first().and_then(|&start| builder.function_id_of_node(start)) else { continue };
+            let bundled = BundledPath { number: i + 1, nodes: path, irrelevant: &verdicts.irrelevant[i], verdict: verdicts.verdicts.get(i).copied().flatten().map(|verdict| verdict.describe()) };
+            match functions.iter_mut().find(|(id, _)| *id == function) {
+                Some((_, paths)) => paths.push(bundled),
+                None => functions.push((function, vec![bundled])),
+            }
+        }
+        for (function, paths) in &functions {
+            let dir = output.function_dir(Some(function));
+            fs::create_dir_all(&dir)?;
+            let dot_path = dir.join("paths.dot");
+            fs::write(&dot_path, builder.paths_to_cluster_dot(paths))?;
+            output.record(&dot_path, "paths-bundle-dot", Some(function.clone()))?;
+            let name = function.split('#').next().unwrap_or(function);
+            let html_path = dir.join("paths.html");
+            fs::write(&html_path, builder.paths_to_html(name, paths))?;
+            output.record(&html_path, "paths-bundle-html", Some(function.clone()))?;
+        }
+        println!("Basic paths of each function saved as paths.dot and paths.html in its directory in: {:?}", output_dir);
+    }
+
+    // '--dot' stands for the graphs and the paths
+    if options.generate_dot || options.emit.paths {
         // Save all basic paths inside the output directory
-        builder.write_paths_to_dot_files(basic_paths, &output_dir);
+        let path_functions: Vec<Option<String>> = basic_paths.iter()
+            .map(|path| path.first().and_then(|&start| builder.function_id_of_node(start)))
+            .collect();
+        let path_dirs: Vec<PathBuf> = path_functions.iter().map(|function| output.function_dir(function.as_ref()).join("paths")).collect();
+        // The conditions of each path next to its DOT file, for test generators and coverage tools
+        let conditions: Vec<Option<serde_json::Value>> = basic_paths.iter().enumerate()
+            .map(|(i, path)| builder.path_conditions(i + 1, path))
+            .collect();
+        let path_files = builder.write_paths_to_dot_files(basic_paths.to_vec(), &verdicts.irrelevant, &path_dirs);
+        for ((path_file, function), conditions) in path_files.iter().zip(path_functions).zip(conditions) {
+            output.record(path_file, "path-dot", function.clone())?;
+            if let Some(conditions) = conditions {
+                let json_path = path_file.with_extension("json");
+                fs::write(&json_path, serde_json::to_string_pretty(&conditions)?)?;
+                output.record(&json_path, "path-conditions", function)?;
+            }
+        }
+        println!("Basic paths saved in the paths directory of each function in: {:?}", output_dir);
+    }
+
+    if options.generate_dot || options.emit.graphs {
+        // Generate the DOT format for the entire CFG
+        let dot_format = builder.to_dot();
+        fs::create_dir_all(&output_dir)?;
 
         // Save the main DOT file in the same directory
-        let dot_file_path = output_dir.join(format!("{}.dot", file_stem.to_string_lossy()));
+        let dot_file_path = output_dir.join(format!("{}.dot", file_stem));
         let mut dot_file = File::create(&dot_file_path).expect("Unable to create DOT file");
         dot_file.write_all(dot_format.as_bytes()).expect("Unable to write to DOT file");
+        output.record(&dot_file_path, "cfg-dot", None)?;
+
+        // And the CFG of each function in its directory
+        for func_node in builder.function_nodes() {
+            let function = builder.function_id(func_node);
+            let dir = output.function_dir(Some(&function));
+            fs::create_dir_all(&dir)?;
+            let cfg_path = dir.join("cfg.dot");
+            fs::write(&cfg_path, builder.function_to_dot(func_node))?;
+            output.record(&cfg_path, "function-cfg-dot", Some(function))?;
+        }
 
         println!("DOT graph saved as: {:?}", dot_file_path);
     }
+    Ok(())
+}
+
+// 'explain-path': the trace of one basic path (numbered as in the report), its verification condition,
+// the SMT-LIB2 script given to the solver and the verdict
+pub fn explain_path(file_path: &PathBuf, path_number: usize, options: &VerificationOptions) -> Result<(), Box<dyn std::error::Error>> {
+    if options.goal != VerificationGoal::Contracts {
+        return Err("explain-path explains the paths of the contracts goal".into());
+    }
+    let content = read_source(file_path)?;
+    let (builder, _, basic_paths) = build_paths(file_path, &content, options)?;
+    let path = path_number.checked_sub(1).and_then(|i| basic_paths.get(i))
+        .ok_or_else(|| format!("no Path {} in {:?} ({} basic paths)", path_number, file_path, basic_paths.len()))?;
+    let Some(implication) = builder.apply_wp_calculus(std::slice::from_ref(path)).pop() else {
+        return Err(format!("Path {} has no verification condition", path_number).into());
+    };
 
+    let function = builder.function_of_node(path[0]).unwrap_or_default();
+    let target = path.last().map(|&node| builder.annotation_label(node)).unwrap_or_default();
+    let exit = builder.exit_of_path(path).map(|exit| format!(" (exit: {})", exit)).unwrap_or_default();
+    println!("Path {} of {}: from {} to {}{}", path_number, function, builder.annotation_label(path[0]), target, exit);
+    println!("Trace:");
+    println!("{}", builder.path_trace(path, &std::collections::HashMap::new()));
+    println!("Verification condition:");
+    print!("{}", CfgBuilder::readable_condition(&implication));
+    let smtlib = verifier::implication_to_smtlib(&implication)?;
+    println!("SMT-LIB2:");
+    print!("{}", smtlib);
+    match solver_verdict(&implication, &builder.options.solver_settings) {
+        VcOutcome::Valid => {
+            println!("Verdict: proved");
+            let unused: Vec<String> = builder.irrelevant_nodes(path).map(|nodes| {
+                path.iter().filter(|node| nodes.contains(node)).map(|&node| builder.annotation_label(node)).collect()
+            }).unwrap_or_default();
+            if !unused.is_empty() {
+                println!("Not needed by the proof:");
+                for label in unused {
+                    println!("  {}", label);
+                }
+            }
+        },
+        VcOutcome::Unknown => println!("Verdict: undecided"),
+        VcOutcome::Counterexample(values) => {
+            println!("Verdict: failed");
+            println!("{}", builder.path_trace(path, &values));
+        },
+    }
     Ok(())
-}
\ No newline at end of file
+}
+
+// 'simulate': run the basic paths of a function (numbered as in the report) with concrete inputs, 'n=5',
+// and check its annotations on the way, without the solver. Without a path number, every path of the
+// function is run and the ones the inputs don't take are listed. Returns whether no annotation fails.
+pub fn simulate(file_path: &PathBuf, function: &str, inputs: &[String], path_number: Option<usize>, options: &VerificationOptions) -> Result<bool, Box<dyn std::error::Error>> {
+    if options.goal != VerificationGoal::Contracts {
+        return Err("simulate runs the paths of the contracts goal".into());
+    }
+    let mut values = HashMap::new();
+    for input in inputs {
+        let (name, value) = input.split_once('=').ok_or_else(|| format!("input '{}' is not NAME=VALUE", input))?;
+        let value = match value.trim() {
+            "true" => ConstValue::Bool(true),
+            "false" => ConstValue::Bool(false),
+            value => ConstValue::Int(value.parse().map_err(|_| format!("value of '{}' is not an integer or a boolean", input))?),
+        };
+        values.insert(name.trim().to_string(), value);
+    }
+    let content = read_source(file_path)?;
+    let (builder, _, basic_paths) = build_paths(file_path, &content, options)?;
+    let selected: Vec<(usize, &Vec<NodeIndex>)> = basic_paths.iter().enumerate()
+        .map(|(i, path)| (i + 1, path))
+        .filter(|(_, path)| builder.function_of_node(path[0]).is_some_and(|name| name == function || name.rsplit("::").next() == Some(function)))
+        .filter(|(number, _)| path_number.is_none_or(|path_number| path_number == *number))
+        .collect();
+    if selected.is_empty() {
+        return Err(match path_number {
+            Some(number) => format!("Path {} is not a path of {} in {:?}", number, function, file_path),
+            None => format!("no annotated function {} in {:?}", function, file_path),
+        }.into());
+    }
+
+    let mut inputs: Vec<String> = values.iter().map(|(name, value)| format!("{} = {}", name, value)).collect();
+    inputs.sort();
+    println!("Simulating {} with {}", function, if inputs.is_empty() { "no inputs".to_string() } else { inputs.join(", ") });
+    let (mut taken, mut failed) = (0, 0);
+    let count = selected.len();
+    for (number, path) in selected {
+        let simulation = builder.simulate_path(path, &values);
+        let target = builder.path_target(path).map(|(_, label)| label).unwrap_or_default();
+        let exit = builder.exit_of_path(path).map(|exit| format!(" (exit: {})", exit)).unwrap_or_default();
+        // The paths the inputs don't take are only listed, unless one was asked for
+        if let (SimulationOutcome::NotTaken(reason), None) = (&simulation.outcome, path_number) {
+            println!("Path {}: not taken, {}", number, reason);
+            continue;
+        }
+        println!("Path {}{}: {} -> {}", number, exit, builder.annotation_label(path[0]), target);
+        print!("{}", simulation.trace);
+        match &simulation.outcome {
+            SimulationOutcome::Holds => println!("Path {}: every annotation holds", number),
+            SimulationOutcome::Fails(annotation) => println!("Path {}: {} fails", number, annotation),
+            SimulationOutcome::NotTaken(reason) => println!("Path {}: not taken, {}", number, reason),
+            SimulationOutcome::Unknown(annotation) => println!("Path {}: {} can't be evaluated with these inputs", number, annotation),
+        }
+        taken += usize::from(!matches!(simulation.outcome, SimulationOutcome::NotTaken(_)));
+        failed += usize::from(matches!(simulation.outcome, SimulationOutcome::Fails(_)));
+    }
+    println!("Simulation: {} of {} paths taken by the inputs, {} with a failing annotation", taken, count, failed);
+    Ok(failed == 0)
+}
+
+// 'contracts diff': compare the contracts of the public functions of each file with the ones at the revision
+// 'base', and warn about the changes that break callers written against the old ones: a precondition the old
+// one doesn't imply (strengthened) or a postcondition that doesn't imply the old one (weakened). Returns the
+// number of breaking changes.
+pub fn contracts_diff(files: &[PathBuf], base: &str) -> Result<usize, Box<dyn std::error::Error>> {
+    let mut breaking = 0;
+    let mut compared = 0;
+    for file_path in files {
+        let content = read_source(file_path)?;
+        let Some(old_content) = source_at_revision(file_path, base)? else {
+            println!("{}: not in {}, every contract is new", file_path.display(), base);
+            continue;
+        };
+        let builder = configured_builder(&content, &VerificationOptions::default())?;
+        let new_contracts = builder.public_contracts(&syn::parse_file(&content)?.items);
+        let old_contracts = builder.public_contracts(&syn::parse_file(&old_content)?.items);
+
+        for old in &old_contracts {
+            let Some(new) = new_contracts.iter().find(|new| new.name == old.name) else {
+                println!("{}: {} has no public contract anymore", file_path.display(), old.name);
+                continue;
+            };
+            compared += 1;
+            let comparison = builder.compare_contracts(old, new).map_err(|e| format!("{}: {}", file_path.display(), e))?;
+            let checks = [
+                ("precondition", "strengthened", comparison.precondition, &old.preconditions, &new.preconditions, "the old precondition, not the new one"),
+                ("postcondition", "weakened", comparison.postcondition, &old.postconditions, &new.postconditions, "the old precondition and the new postcondition, not the old postcondition"),
+            ];
+            for (side, breaking_change, implication, old_conditions, new_conditions, witness) in checks {
+                let Some(implication) = implication else { continue };
+                // Conditions the solver can't translate are reported instead of aborting the comparison
+                let outcome = verifier::verify_str_implication(&implication, &builder.options.solver_settings);
+                let change = format!("{} of {}\n  before: {}\n  after:  {}", side, old.name, PublicContract::conjunction(old_conditions), PublicContract::conjunction(new_conditions));
+                match outcome {
+                    Ok(VcOutcome::Valid) => println!("{}: compatible change of the {}", file_path.display(), change),
+                    Ok(VcOutcome::Counterexample(values)) => {
+                        breaking += 1;
+                        let mut values: Vec<String> = values.iter().map(|(name, value)| format!("{} = {}", name, value)).collect();
+                        values.sort();
+                        println!("warning: {}: breaking change, {} {}", file_path.display(), breaking_change, change);
+                        println!("  {} satisfies {}", values.join(", "), witness);
+                    },
+                    Ok(VcOutcome::Unknown) => println!("warning: {}: the solver can't compare the {}", file_path.display(), change),
+                    Err(reason) => println!("warning: {}: the solver can't compare the {} ({})", file_path.display(), change, reason),
+                }
+            }
+        }
+    }
+    println!("Contracts diff against {}: {} public contracts compared, {} breaking changes", base, compared, breaking);
+    Ok(breaking)
+}
+
+// Content of a file at a git revision, or 'None' when the file isn't in it
+fn source_at_revision(file_path: &Path, revision: &str) -> Result<Option<String>, Box<dyn std::error::Error>> {
+    // Run in the directory of the file, for './name' to be relative to it
+    let dir = file_path.parent().filter(|parent| !parent.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    let name = file_path.file_name().ok_or_else(|| format!("{} is not a file", file_path.display()))?;
+    let git = |args: &[&str]| std::process::Command::new("git").arg("-C").arg(dir).args(args).output()
+        .map_err(|e| format!("can't run git: {}", e));
+    let commit = git(&["rev-parse", "--verify", "--quiet", &format!("{}^{{commit}}", revision)])?;
+    if !commit.status.success() {
+        return Err(format!("{} is not a revision of the repository of {}", revision, file_path.display()).into());
+    }
+    let output = git(&["show", &format!("{}:./{}", revision, name.to_string_lossy())])?;
+    Ok(output.status.success().then(|| String::from_utf8_lossy(&output.stdout).into_owned()))
+}
+
+// 'check-specs': parse the annotations and external contracts and check their identifiers and types,
+// without building paths or calling the solver
+pub fn check_specs(file_path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let content = read_source(file_path)?;
+    let ast = syn::parse_file(&content)?;
+    let mut builder = CfgBuilder::new();
+    let config = SecuredConfig::load(Path::new(SECURED_TOML))?;
+    builder.options.annotation_names = config.annotations;
+    builder.file.source_lines = content.lines().map(str::to_string).collect();
+
+    let check = builder.check_specs(&file_path.display().to_string(), &ast.items);
+    for warning in builder.lint_annotations(&file_path.display().to_string(), &ast.items) {
+        println!("warning: {}", warning);
+    }
+    for problem in &check.problems {
+        println!("error: {}", problem);
+    }
+    println!("Checked {} specs in {} annotated functions: {} problem(s)", check.specs, check.functions, check.problems.len());
+    if !check.problems.is_empty() {
+        return Err(format!("{} problem(s) in the specs", check.problems.len()).into());
+    }
+    Ok(())
+}
+
+// 'equiv': check a refactoring of a function. Both versions are built and their graphs aligned, then both are
+// verified against the contract of the old version: under its precondition, the new version must return a value
+// satisfying its postcondition too.
+pub fn check_equivalence(old_path: &PathBuf, new_path: &PathBuf, function: &str, options: &VerificationOptions) -> Result<(), Box<dyn std::error::Error>> {
+    let old_content = read_source(old_path)?;
+    let new_content = read_source(new_path)?;
+    let old_ast = syn::parse_file(&old_content)?;
+    let mut new_ast = syn::parse_file(&new_content)?;
+    let old_fn = CfgBuilder::find_function(&old_ast.items, function).ok_or_else(|| format!("no function {} in {:?}", function, old_path))?;
+    let new_fn = CfgBuilder::find_function(&new_ast.items, function).ok_or_else(|| format!("no function {} in {:?}", function, new_path))?;
+
+    // The new version gets the contract of the old one instead of its own
+    let mut contract_builder = CfgBuilder::new();
+    contract_builder.options.annotation_names = SecuredConfig::load(Path::new(SECURED_TOML))?.annotations;
+    let transplanted = contract_builder.transplant_contract(&old_fn, &new_fn)?;
+    CfgBuilder::replace_function(&mut new_ast.items, &[], function, &transplanted);
+
+    let (old_builder, _, old_paths) = build_paths_of_ast(old_path, &old_content, old_ast, options)?;
+    let (new_builder, _, new_paths) = build_paths_of_ast(new_path, &new_content, new_ast, options)?;
+    let old_node = old_builder.function_node(function).ok_or_else(|| format!("{} is not analyzed in {:?}", function, old_path))?;
+    let new_node = new_builder.function_node(function).ok_or_else(|| format!("{} is not analyzed in {:?}", function, new_path))?;
+
+    let alignment = CfgBuilder::align_functions(&old_builder, old_node, &new_builder, new_node);
+    println!("Alignment of {} ({:?} -> {:?}):", function, old_path, new_path);
+    for step in &alignment {
+        println!("{}", step);
+    }
+    let count = |matches: fn(&AlignmentStep) -> bool| alignment.iter().filter(|step| matches(step)).count();
+    println!("{} nodes paired ({} changed), {} removed, {} added",
+        count(|step| matches!(step, AlignmentStep::Same(..) | AlignmentStep::Changed(..))),
+        count(|step| matches!(step, AlignmentStep::Changed(..))),
+        count(|step| matches!(step, AlignmentStep::Removed(..))),
+        count(|step| matches!(step, AlignmentStep::Added(..))));
+
+    let (old_proved, old_total) = verify_function_paths(&old_builder, &old_paths, old_node, "old");
+    let (new_proved, new_total) = verify_function_paths(&new_builder, &new_paths, new_node, "new");
+    println!("Old version: {}/{} paths proved; new version under the contract of the old one: {}/{} paths proved", old_proved, old_total, new_proved, new_total);
+    if old_proved < old_total {
+        return Err(format!("the old version of {} doesn't meet its own contract: the versions can't be compared", function).into());
+    }
+    if new_proved < new_total {
+        return Err(format!("regression: the new version of {} doesn't meet the contract of the old one", function).into());
+    }
+    println!("Equivalent under the contract: both versions of {} return values satisfying the same postconditions from the same preconditions", function);
+    Ok(())
+}
+
+// Verify the basic paths of one function, with the trace of the failed ones. Returns the proved and total paths.
+fn verify_function_paths(builder: &CfgBuilder, basic_paths: &[Vec<NodeIndex>], func_node: NodeIndex, version: &str) -> (usize, usize) {
+    let nodes = builder.function_subgraph_nodes(func_node);
+    let paths: Vec<Vec<NodeIndex>> = basic_paths.iter().filter(|path| path.first().is_some_and(|start| nodes.contains(start))).cloned().collect();
+    let mut proved = 0;
+    for (i, (path, implication)) in paths.iter().zip(builder.apply_wp_calculus(&paths)).enumerate() {
+        println!("Path {} of the {} version: {}", i + 1, version, implication);
+        match solver_verdict(&implication, &builder.options.solver_settings) {
+            VcOutcome::Valid => proved += 1,
+            VcOutcome::Counterexample(values) => {
+                println!("Trace of failed Path {} of the {} version:", i + 1, version);
+                println!("{}", builder.path_trace(path, &values));
+            },
+            VcOutcome::Unknown => {},
+        }
+    }
+    (proved, paths.len())
+}
+
+// Name of the package of './Cargo.toml', as written in paths ('my-lib' is 'my_lib')
+fn package_name() -> Option<String> {
+    let manifest: toml::Value = toml::from_str(&fs::read_to_string("Cargo.toml").ok()?).ok()?;
+    Some(manifest.get("package")?.get("name")?.as_str()?.replace('-', "_"))
+}
+
+// CFG of a file, its AST and its basic paths
+type BuiltPaths = (CfgBuilder, syn::File, Vec<Vec<NodeIndex>>);
+
+// CFG of a file and its basic paths, as the library gives them
+pub type FileCfg = (CfgBuilder, Vec<Vec<NodeIndex>>);
+
+// '--manifest verify.toml': verify each target of the manifest for each of its goals, then print one line per
+// run and write them to 'out/batch.json'. Returns whether every run proved all its obligations.
+pub fn run_batch(manifest_path: &Path, options: &VerificationOptions) -> Result<bool, Box<dyn std::error::Error>> {
+    let manifest = BatchManifest::load(manifest_path)?;
+    let mut runs = Vec::new();
+    let mut measured = Vec::new(); // stats of the runs that completed, for the metrics
+    for target in &manifest.targets {
+        let name = match &target.function {
+            Some(function) => format!("{} ({})", target.file.display(), function),
+            None => target.file.display().to_string(),
+        };
+        for goal in &target.goals {
+            println!("=== Batch target: {}, goal {} ===", name, goal);
+            let outcome = VerificationGoal::parse(goal)
+                .map_err(|e| e.into())
+                .and_then(|parsed| verify_file(&target.file, &VerificationOptions { goal: parsed, function: target.function.clone(), ..options.clone() }));
+            let (status, counts, error) = match outcome {
+                Ok(stats) => {
+                    let (valid, failed, undecided) = stats.obligation_totals();
+                    let status = if failed > 0 { "failed" } else if undecided > 0 { "undecided" } else { "passed" };
+                    measured.push((stats, goal.clone()));
+                    (status, Some((valid, failed, undecided)), None)
+                },
+                Err(e) => ("error", None, Some(e.to_string())),
+            };
+            runs.push((name.clone(), target, goal.clone(), status, counts, error));
+        }
+    }
+
+    println!("Batch summary ({}):", manifest_path.display());
+    let mut report = Vec::new();
+    for (name, target, goal, status, counts, error) in &runs {
+        let detail = match (counts, error) {
+            (Some((valid, failed, undecided)), _) => format!("{} valid, {} failed, {} undecided", valid, failed, undecided),
+            (_, Some(error)) => error.clone(),
+            _ => String::new(),
+        };
+        println!("  {:<9} {} [{}]: {}", status, name, goal, detail);
+        report.push(serde_json::json!({
+            "file": target.file.display().to_string(),
+            "function": target.function,
+            "goal": goal,
+            "status": status,
+            "valid": counts.map(|(valid, _, _)| valid),
+            "failed": counts.map(|(_, failed, _)| failed),
+            "undecided": counts.map(|(_, _, undecided)| undecided),
+            "error": error,
+        }));
+    }
+    let passed = runs.iter().filter(|run| run.3 == "passed").count();
+    println!("Batch: {} targets, {} runs, {} passed, {} not passed", manifest.targets.len(), runs.len(), passed, runs.len() - passed);
+
+    fs::create_dir_all(options.output_root())?;
+    let report_path = options.output_root().join("batch.json");
+    fs::write(&report_path, serde_json::to_string_pretty(&serde_json::json!({ "manifest": manifest_path.display().to_string(), "runs": report }))?)?;
+    println!("Batch report saved as: {:?}", report_path);
+    // All the runs in one file, the samples of each labeled with its file and goal
+    if options.emit.metrics {
+        let metrics_path = options.output_root().join("batch.prom");
+        let runs: Vec<(&VerificationStats, &str)> = measured.iter().map(|(stats, goal)| (stats, goal.as_str())).collect();
+        fs::write(&metrics_path, prometheus_metrics(&runs))?;
+        println!("Batch metrics saved as: {:?}", metrics_path);
+    }
+    Ok(passed == runs.len())
+}
+
+// '--crate Cargo.toml': verify every source file of the crate that 'cargo metadata' and the 'mod' declarations
+// lead to, with the CFG and the report of each function, in 'target/secured/' of the workspace (or the
+// '--output-dir'). Returns whether no obligation failed or is undecided.
+pub fn verify_crate(manifest_path: &Path, options: &VerificationOptions) -> Result<bool, Box<dyn std::error::Error>> {
+    let sources = CrateSources::discover(manifest_path)?;
+    let output_root = options.output_dir.clone().unwrap_or_else(|| sources.target_directory.join("secured"));
+    println!("Crate {}: {} source files, artifacts in {:?}", sources.workspace_root.display(), sources.files.len(), output_root);
+    let options = VerificationOptions {
+        output_dir: Some(output_root.clone()),
+        source_root: Some(sources.workspace_root.clone()),
+        emit: Artifacts { graphs: true, report: true, ..options.emit },
+        ..options.clone()
+    };
+
+    let mut files = Vec::new();
+    for file in &sources.files {
+        let name = file.strip_prefix(&sources.workspace_root).unwrap_or(file).display().to_string();
+        println!("=== Crate file: {} ===", name);
+        // A file that doesn't parse, or has nothing to verify, doesn't stop the others
+        let (status, counts, error) = match verify_file(file, &options) {
+            Ok(stats) if stats.functions.is_empty() => ("skipped", None, Some("no annotated functions".to_string())),
+            Ok(stats) => {
+                let (valid, failed, undecided) = stats.obligation_totals();
+                let status = if failed > 0 { "failed" } else if undecided > 0 { "undecided" } else { "passed" };
+                (status, Some((valid, failed, undecided, stats.functions.len())), None)
+            },
+            Err(e) => ("error", None, Some(e.to_string())),
+        };
+        files.push((name, status, counts, error));
+    }
+
+    println!("Crate summary ({}):", manifest_path.display());
+    let mut report = Vec::new();
+    for (name, status, counts, error) in &files {
+        let detail = match (counts, error) {
+            (Some((valid, failed, undecided, functions)), _) => format!("{} functions, {} valid, {} failed, {} undecided", functions, valid, failed, undecided),
+            (_, Some(error)) => error.clone(),
+            _ => String::new(),
+        };
+        println!("  {:<9} {}: {}", status, name, detail);
+        report.push(serde_json::json!({
+            "file": name,
+            "status": status,
+            "functions": counts.map(|(_, _, _, functions)| functions),
+            "valid": counts.map(|(valid, _, _, _)| valid),
+            "failed": counts.map(|(_, failed, _, _)| failed),
+            "undecided": counts.map(|(_, _, undecided, _)| undecided),
+            "error": error,
+        }));
+    }
+    let verified: Vec<_> = files.iter().filter(|file| file.2.is_some()).collect();
+    let passed = verified.iter().filter(|file| file.1 == "passed").count();
+    let errors = files.iter().filter(|file| file.1 == "error").count();
+    println!("Crate: {} files, {} with annotated functions, {} passed, {} errors", files.len(), verified.len(), passed, errors);
+
+    fs::create_dir_all(&output_root)?;
+    let report_path = output_root.join("crate.json");
+    fs::write(&report_path, serde_json::to_string_pretty(&serde_json::json!({ "manifest": manifest_path.display().to_string(), "files": report }))?)?;
+    println!("Crate report saved as: {:?}", report_path);
+    Ok(passed == verified.len() && errors == 0)
+}
+
+// 'project-graph a.rs b.rs': the CFGs of all the files in one graph, calls linked to the function nodes of
+// their callees, written to 'project.dot' in 'output_root'. With 'from', prints a path from that function
+// ('pub' for every public one) to the function 'to', or to a panic site for "panic".
+pub fn project_graph(files: &[PathBuf], from: Option<&str>, to: Option<&str>, output_root: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let mut builders = Vec::new();
+    for file_path in files {
+        let content = read_source(file_path)?;
+        let ast = syn::parse_file(&content)?;
+        let mut builder = configured_builder(&content, &VerificationOptions::default())?;
+        builder.options.analyze_all_functions = true;
+        builder.build_cfg(&ast);
+        builders.push((file_path.display().to_string(), builder));
+    }
+    let project = ProjectGraph::build(&builders);
+    let (calls, cross_file) = project.call_edge_count();
+    println!("Project graph: {} files, {} functions, {} call edges ({} across files)", files.len(), project.functions().len(), calls, cross_file);
+
+    fs::create_dir_all(output_root)?;
+    let dot_path = output_root.join("project.dot");
+    fs::write(&dot_path, project.to_dot())?;
+    println!("Project graph saved as: {:?}", dot_path);
+
+    if let Some(from) = from {
+        let entries = project.entries(from);
+        if entries.is_empty() {
+            return Err(format!("no function '{}' in the project", from).into());
+        }
+        let target = match to {
+            None | Some("panic") => PathTarget::Panic,
+            Some(function) => PathTarget::Function(function),
+        };
+        match project.find_path(&entries, target) {
+            Some(path) => {
+                println!("Path from {} to {}:", from, to.unwrap_or("panic"));
+                for node in path {
+                    println!("  {}", project.describe(node));
+                }
+            },
+            None => println!("No path from {} to {}", from, to.unwrap_or("panic")),
+        }
+    }
+    Ok(())
+}
+
+// 'taint file.rs': the paths of each function carrying the result of a '[taint]' source to a sink without a
+// sanitizer on the way, written to '<output_root>/<file>/taint.json'. Returns whether there is none.
+pub fn taint_report(file_path: &Path, output_root: &Path) -> Result<bool, Box<dyn std::error::Error>> {
+    let content = read_source(file_path)?;
+    let ast = syn::parse_file(&content)?;
+    let mut builder = configured_builder(&content, &VerificationOptions::default())?;
+    builder.options.analyze_all_functions = true;
+    builder.build_cfg(&ast);
+    let settings = SecuredConfig::load(Path::new(SECURED_TOML))?.taint;
+    let flows = builder.taint_flows(&settings);
+
+    let mut report = Vec::new();
+    for (i, flow) in flows.iter().enumerate() {
+        let (source, sink) = (flow.path[0], *flow.path.last().unwrap());
+        let line = |node: NodeIndex| builder.node_location(node).map(|(line, _)| line.to_string()).unwrap_or_else(|| "?".to_string());
+        let via = if flow.variables.is_empty() { String::new() } else { format!(" via {}", flow.variables.join(", ")) };
+        println!("Flow {} in {}: {} (line {}) reaches {} (line {}){}", i + 1, flow.function, flow.source, line(source), flow.sink, line(sink), via);
+        for &node in &flow.path {
+            println!("  {}: {}", builder.node_id(node), builder.annotation_label(node));
+        }
+        report.push(serde_json::json!({
+            "function": flow.function,
+            "source": flow.source,
+            "sink": flow.sink,
+            "variables": flow.variables,
+            "path": flow.path.iter().map(|&node| builder.node_id(node)).collect::<Vec<_>>(),
+        }));
+    }
+    println!("Taint: {} unsanitized flow(s) from {} source(s) to {} sink(s)", flows.len(), settings.sources.len(), settings.sinks.len());
+
+    let output_dir = OutputLayout::new(output_root, file_path, &[]).file_dir().to_path_buf();
+    fs::create_dir_all(&output_dir)?;
+    let report_path = output_dir.join("taint.json");
+    fs::write(&report_path, serde_json::to_string_pretty(&serde_json::json!({ "file": file_path.display().to_string(), "flows": report }))?)?;
+    println!("Taint report saved as: {:?}", report_path);
+    Ok(flows.is_empty())
+}
+
+// 'init': set up a crate for verification. Writes 'secured.toml', 'src/config/conditions.json' with the
+// contracts of common std functions, an annotated example in 'examples/' and the 'secrust' dependency of
+// 'Cargo.toml'. Existing files are kept unless 'force'.
+pub fn init_project(dir: &Path, secrust_path: Option<&str>, force: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let files = [
+        (PathBuf::from(SECURED_TOML), STARTER_SECURED_TOML),
+        (conditions_path(), STD_CONDITIONS),
+        (Path::new("examples").join("secured_example.rs"), STARTER_EXAMPLE),
+    ];
+    for (name, content) in files {
+        let path = dir.join(name);
+        if path.exists() && !force {
+            println!("Kept existing {}", path.display());
+            continue;
+        }
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, content)?;
+        println!("Created {}", path.display());
+    }
+
+    let manifest_path = dir.join("Cargo.toml");
+    match fs::read_to_string(&manifest_path) {
+        Ok(manifest) => match with_secrust_dependency(&manifest, secrust_path)? {
+            Some(updated) => {
+                fs::write(&manifest_path, updated)?;
+                println!("Added the secrust dependency to {}", manifest_path.display());
+            },
+            None => println!("{} already depends on secrust", manifest_path.display()),
+        },
+        Err(_) => println!("No Cargo.toml in {}: add the secrust dependency to use the annotation macros", dir.display()),
+    }
+    println!("Verify the example with: cargo secrust-verify examples/secured_example.rs");
+    Ok(())
+}
+
+// The CFG of a parsed file and its basic paths, for tools using this crate as a library instead of scraping
+// the DOT files. 'source' is the text 'ast' was parsed from, for the node IDs and the '//@ call' comments;
+// 'secured.toml' and the options apply as in a verification run. The graph is 'builder.graph', and the queries
+// of 'CfgBuilder' ('function_entries', 'successors', 'node_by_id', 'node_at') read it by node.
+pub fn cfg_of_file(ast: &syn::File, source: &str, options: &VerificationOptions) -> Result<FileCfg, Box<dyn std::error::Error>> {
+    let mut ast = ast.clone();
+    if let Some(config) = &options.cfg {
+        config.apply(&mut ast);
+    }
+    let mut builder = configured_builder(source, options)?;
+    builder.build_cfg(&ast);
+    builder.annotate_intervals();
+    let basic_paths = builder.generate_basic_paths();
+    Ok((builder, basic_paths))
+}
+
+// '--all-cfgs': verify the file once per configuration of the options its 'cfg's test
+fn run_all_configurations(file_path: &PathBuf, options: &VerificationOptions) -> Result<(), Box<dyn std::error::Error>> {
+    let ast = syn::parse_file(&read_source(file_path)?)?;
+    let configurations = CompilationConfig::enumerate(&ast)?;
+    println!("Verifying {} configurations of {:?}", configurations.len(), file_path);
+    for config in configurations {
+        println!("=== Configuration: {} ===", config);
+        run_verification(file_path, &VerificationOptions { cfg: Some(config), ..options.clone() })?;
+    }
+    Ok(())
+}
+
+// Read a source file, its '//@' comment specs turned into the annotation macros they stand for
+fn read_source(file_path: &Path) -> Result<String, Box<dyn std::error::Error>> {
+    let content = read_input(file_path)?;
+    expand_comment_specs(file_path, &content)
+}
+
+// Content of an input file, or of the standard input for '-'. The standard input is read once and kept,
+// since a run may read its input again ('--all-cfgs').
+fn read_input(file_path: &Path) -> Result<String, Box<dyn std::error::Error>> {
+    static STDIN: OnceLock<Result<String, String>> = OnceLock::new();
+    if file_path == Path::new(STDIN_INPUT) {
+        let content = STDIN.get_or_init(|| {
+            let mut content = String::new();
+            std::io::stdin().read_to_string(&mut content).map(|_| content).map_err(|e| format!("can't read the standard input: {}", e))
+        });
+        return content.clone().map_err(|e| e.into());
+    }
+    Ok(fs::read_to_string(file_path).map_err(|e| format!("can't read {}: {}", file_path.display(), e))?)
+}
+
+fn expand_comment_specs(file_path: &Path, content: &str) -> Result<String, Box<dyn std::error::Error>> {
+    if !content.contains("//@") {
+        return Ok(content.to_string());
+    }
+    let names = SecuredConfig::load(Path::new(SECURED_TOML))?.annotations;
+    let (expanded, warnings) = CfgBuilder::expand_comment_specs(content, &names);
+    for warning in warnings {
+        println!("Warning: {}: {}", file_path.display(), warning);
+    }
+    Ok(expanded)
+}
+
+// Parse the file and build its CFG and basic paths, numbered as in the verification report
+fn build_paths(file_path: &Path, content: &str, options: &VerificationOptions) -> Result<BuiltPaths, Box<dyn std::error::Error>> {
+    let ast = syn::parse_file(content)?;
+    println!("AST successfully parsed for file {:?}", file_path);
+    build_paths_of_ast(file_path, content, ast, options)
+}
+
+// Builder set up from the options and 'secured.toml' for a file with this content, before its CFG is built
+fn configured_builder(content: &str, options: &VerificationOptions) -> Result<CfgBuilder, Box<dyn std::error::Error>> {
+    let mut builder = CfgBuilder::new();
+    builder.options.max_label_len = options.max_label_len;
+    builder.options.node_filter = options.node_filter.clone();
+    builder.options.analyze_all_functions = options.goal != VerificationGoal::Contracts;
+    let mut config = SecuredConfig::load(Path::new(SECURED_TOML))?;
+    config.solver.timeout_ms = options.solver_timeout_ms.unwrap_or(config.solver.timeout_ms);
+    config.solver.seed = options.solver_seed.unwrap_or(config.solver.seed);
+    builder.options.annotation_names = config.annotations;
+    builder.file.struct_invariant_opt_outs = config.struct_invariants.skip.into_iter().collect();
+    builder.options.skip_list = config.skip.functions;
+    builder.options.call_settings = config.calls;
+    builder.options.simplify = config.simplify;
+    for tactic in verifier::unknown_tactics(&config.solver) {
+        eprintln!("Warning: unknown solver tactic '{}' in secured.toml skipped", tactic);
+    }
+    builder.options.solver_settings = config.solver;
+    builder.options.enumeration = config.enumeration;
+    builder.options.notation = config.notation;
+    builder.options.function_filter = options.function.clone();
+    builder.file.source_lines = content.lines().map(str::to_string).collect();
+    builder.options.debug_phases = options.debug_phases;
+    for bundle in &config.contracts.import {
+        builder.options.imported_contracts.push(ContractBundle::load(Path::new(bundle))?);
+    }
+    Ok(builder)
+}
+
+// Same as 'build_paths' for an AST that was parsed from 'content' and possibly modified since
+fn build_paths_of_ast(file_path: &Path, content: &str, mut ast: syn::File, options: &VerificationOptions) -> Result<BuiltPaths, Box<dyn std::error::Error>> {
+    // The CFG follows one compilation configuration when one is selected
+    if let Some(config) = &options.cfg {
+        let removed = config.apply(&mut ast);
+        println!("Configuration {}: {} disabled items, statements, arms and fields removed", config, removed);
+    }
+    // visit ast
+    let mut builder = configured_builder(content, options)?;
+    builder.build_cfg(&ast);
+    if let Some(function) = options.function.as_ref().filter(|_| builder.function_nodes().is_empty()) {
+        return Err(format!("no function '{}' to verify in {}", function, file_path.display()).into());
+    }
+
+    // Measured branch counts weigh the edges of the conditions they were taken at
+    if let Some(profile) = &options.branch_profile {
+        for unmatched in builder.apply_branch_profile(&BranchCounts::load(profile)?) {
+            eprintln!("Warning: branch profile: no condition at {}", unmatched);
+        }
+    }
+
+    // Ranges of the numeric variables, for the tooltips and the obligations they decide without a solver
+    builder.annotate_intervals();
+
+    // Annotations out of place would silently give a graph that doesn't say what they mean
+    for warning in builder.lint_annotations(&file_path.display().to_string(), &ast.items) {
+        eprintln!("Warning: {}", warning);
+    }
+
+    // Specs that would be proved vacuously are errors, not successes
+    if !builder.results.rejected_specs.is_empty() {
+        for rejected in &builder.results.rejected_specs {
+            eprintln!("Error: {}", rejected);
+        }
+        return Err(format!("{} annotation(s) rejected", builder.results.rejected_specs.len()).into());
+    }
+
+    // Calls of functions of the file are assumed to meet their contract, or inlined
+    if !builder.results.call_strategies.is_empty() {
+        print!("{}", builder.call_strategy_report());
+    }
+
+    // Logic functions whose body can't define them are only known to give equal results for equal arguments
+    for (name, status) in builder.check_logic_functions() {
+        match status {
+            LogicStatus::Defined => println!("Logic function {}: defined by its body", name),
+            LogicStatus::Uninterpreted(reason) => eprintln!("Warning: logic function {} left uninterpreted: {}", name, reason),
+        }
+    }
+
+    // Extern functions have no body to analyze: their contract is required
+    for warning in &builder.results.ffi_warnings {
+        eprintln!("Warning: {}", warning);
+    }
+
+    // Tell how far the graph can be trusted: constructs that were approximated, per function
+    for diagnostics in builder.results.diagnostics.iter().filter(|d| !d.is_empty()) {
+        println!("Warning: unmodeled syntax in {}: {}", diagnostics.function, diagnostics);
+    }
+
+    if options.summarize_regions {
+        let regions = builder.summarize_regions();
+        println!("Region summaries: {} loop-free regions crossed in one step", regions);
+        builder.snapshot_phase("regions_summarized");
+    }
+
+    let mut basic_paths = builder.generate_basic_paths();
+
+    // Too many paths: verify a sample and report how much of the CFG it covers
+    if let Some(limit) = options.sample_paths {
+        let total_paths = basic_paths.len();
+        if total_paths > limit {
+            basic_paths = builder.sample_paths(&basic_paths, limit, options.sampling_strategy);
+            println!("Path sampling ({:?}): {}", options.sampling_strategy, builder.path_coverage(&basic_paths, total_paths));
+        }
+    }
+
+    Ok((builder, ast, basic_paths))
+}
+
+// Verdict of the solver on a condition; one it can't translate is undecided, with the reason
+fn solver_verdict(implication: &str, settings: &SolverSettings) -> VcOutcome {
+    verifier::verify_str_implication(implication, settings).unwrap_or_else(|reason| {
+        println!("Condition not supported by the solver ({}), left undecided.", reason);
+        VcOutcome::Unknown
+    })
+}
+
+// Verdict of a path once its inputs are enumerated: a proof by enumeration decides an undecided path, and so
+// does a failing input; when the two checks disagree the path is undecided
+fn cross_check(builder: &CfgBuilder, number: usize, path: &[NodeIndex], outcome: VcOutcome) -> VcOutcome {
+    let Some(enumerated) = builder.enumerate_path(path) else { return outcome };
+    match (enumerated, outcome) {
+        (EnumerationOutcome::Proved(inputs), VcOutcome::Valid) => {
+            println!("Path {} cross-checked on all {} inputs", number, inputs);
+            VcOutcome::Valid
+        },
+        (EnumerationOutcome::Proved(inputs), VcOutcome::Unknown) => {
+            println!("Path {} proved by enumerating all {} inputs (the solver was undecided)", number, inputs);
+            VcOutcome::Valid
+        },
+        (EnumerationOutcome::Proved(inputs), VcOutcome::Counterexample(_)) => {
+            println!("Warning: Path {} holds on all {} inputs, but the solver found a counterexample; counted as undecided", number, inputs);
+            VcOutcome::Unknown
+        },
+        (EnumerationOutcome::Counterexample(_), VcOutcome::Counterexample(values)) => {
+            println!("Path {} fails by enumeration too", number);
+            VcOutcome::Counterexample(values)
+        },
+        (EnumerationOutcome::Counterexample(values), VcOutcome::Unknown) => {
+            println!("Path {} fails by enumeration (the solver was undecided)", number);
+            VcOutcome::Counterexample(values)
+        },
+        (EnumerationOutcome::Counterexample(values), VcOutcome::Valid) => {
+            let mut inputs: Vec<String> = values.iter().map(|(var, value)| format!("{} = {}", var, value)).collect();
+            inputs.sort();
+            println!("Warning: Path {} was proved by the solver, but fails for {}; counted as undecided", number, inputs.join(", "));
+            VcOutcome::Unknown
+        },
+        (EnumerationOutcome::Inconclusive(reason), VcOutcome::Unknown) => {
+            println!("Path {} not decided by enumeration: {}", number, reason);
+            VcOutcome::Unknown
+        },
+        (_, outcome) => outcome,
+    }
+}
+
+// Prove that no operation of the file can panic, one condition per operation and path
+fn verify_no_panic(builder: &CfgBuilder, stats: &mut VerificationStats) {
+    let checks = builder.no_panic_checks();
+    let (mut proved, mut failed, mut undecided) = (0, 0, 0);
+    for (i, check) in checks.iter().enumerate() {
+        println!("---------");
+        println!("No-panic obligation {} in {}: {} in `{}`", i + 1, check.function, check.obligation.kind, check.obligation.operation);
+        println!("Condition: {}", check.condition);
+
+        // Conditions the solver can't translate (method calls, floats, ...) are reported instead of aborting the run
+        let started = Instant::now();
+        let outcome = verifier::verify_str_implication(&check.condition, &builder.options.solver_settings);
+        let elapsed = started.elapsed();
+        stats.record_time(&check.function_id, format!("obligation {}", i + 1), elapsed);
+
+        let verdict = match &outcome {
+            Ok(outcome) => Verdict::from(outcome),
+            Err(_) => Verdict::Unknown,
+        };
+        stats.record(&check.function_id, verdict);
+        match outcome {
+            Ok(VcOutcome::Valid) => proved += 1,
+            Ok(VcOutcome::Counterexample(_)) => {
+                failed += 1;
+                println!("Possible panic: {} in `{}` ({})", check.obligation.kind, check.obligation.operation, check.function);
+            },
+            Ok(VcOutcome::Unknown) => undecided += 1,
+            Err(reason) => {
+                undecided += 1;
+                println!("Condition not supported by the solver ({}), obligation left undecided.", reason);
+            },
+        }
+    }
+    println!("---------");
+    println!("No-panic goal: {} obligations, {} proved, {} possible panics, {} undecided", checks.len(), proved, failed, undecided);
+}
+
+// Report the unwraps and map indexes that may find no value, with a path where they do. Works on code
+// without any annotation: Options are followed through the CFG, not through contracts.
+fn verify_none_safety(builder: &CfgBuilder, stats: &mut VerificationStats) {
+    let checks = builder.none_safety_checks();
+    let mut unsafe_checks = 0;
+    for (i, check) in checks.iter().enumerate() {
+        println!("---------");
+        let location = builder.node_location(check.node).map(|(line, _)| format!(" at line {}", line)).unwrap_or_default();
+        println!("None check {} in {}: `{}`{}", i + 1, check.function, check.operation, location);
+        if check.state == OptionState::Some {
+            stats.record(&check.function_id, Verdict::Valid);
+            println!("{} is Some on every path", check.value);
+        } else {
+            unsafe_checks += 1;
+            stats.record(&check.function_id, Verdict::Failed);
+            println!("Possible None: {} is {} along: {}", check.value, check.state, builder.describe_path(&check.path));
+        }
+    }
+    println!("---------");
+    println!("None-safety goal: {} checks, {} safe, {} possibly None", checks.len(), checks.len() - unsafe_checks, unsafe_checks);
+}
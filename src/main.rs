@@ -1,18 +1,19 @@
-use std::path::PathBuf;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
 use std::process::exit;
 use clap::{Arg, Command};
-use secrust::run_verification;
+use secrust::{check_equivalence, init_project, project_graph, taint_report, check_specs, explain_path, simulate, contracts_diff, CfgOption, CompilationConfig, ContractExport, run_batch, run_verification, verify_crate, Artifacts, ColorChoice, NodeFilter, SamplingStrategy, VerificationGoal, VerificationOptions, OUTPUT_ROOT};
 
 fn main() {
-    // print args
-    let raw_args: Vec<String> = std::env::args().collect();
+    // print args, as the OS gives them so that paths that aren't UTF-8 reach the file arguments intact
+    let raw_args: Vec<OsString> = std::env::args_os().collect();
     println!("Raw arguments: {:?}", raw_args);
 
-    let adjusted_args: Vec<String> = raw_args
+    let adjusted_args: Vec<OsString> = raw_args
         .iter()
-        .skip_while(|arg| !arg.contains("secrust-verify")) 
+        .skip_while(|arg| !arg.to_string_lossy().contains("secrust-verify")) 
         .skip(1) 
-        .map(|arg| arg.clone())
+        .cloned()
         .collect();
     
     // parsing args using clap
@@ -22,34 +23,623 @@ fn main() {
         .about("Verifies Rust code using Secrust analysis and optionally generates a DOT graph")
         .arg(
             Arg::new("file")
-                .help("The input file to verify")
-                .required(true)
+                .help("The input file to verify ('-' for the standard input)")
+                .value_parser(clap::value_parser!(PathBuf))
+                .required_unless_present_any(["manifest", "input", "crate"])
                 .index(1),  // positional file arg
         )
+        .arg(
+            Arg::new("input")
+                .long("input")
+                .value_name("PATH")
+                .help("The input file to verify, same as the positional argument ('-' for the standard input)")
+                .value_parser(clap::value_parser!(PathBuf))
+                .conflicts_with("file"),
+        )
+        .arg(
+            Arg::new("output-dir")
+                .long("output-dir")
+                .value_name("DIR")
+                .help("Directory the artifacts are written to, in '<DIR>/<file>/' (default: 'out')")
+                .value_parser(clap::value_parser!(PathBuf))
+                .global(true),
+        )
+        .arg(
+            Arg::new("format")
+                .long("format")
+                .help("Formats of the exported CFG, e.g. 'dot,json': 'dot' is the same as --dot and 'json' as --json")
+                .value_parser(["dot", "json"])
+                .value_delimiter(',')
+                .action(clap::ArgAction::Append),
+        )
+        .arg(
+            Arg::new("report")
+                .long("report")
+                .value_name("FORMAT")
+                .help("Write a report of the run: 'md' for 'report.md', with the verdicts of the functions and their paths, the CFGs of the failed functions in Mermaid and the counterexamples, to paste into a pull request or a review")
+                .value_parser(["md"]),
+        )
+        .arg(
+            Arg::new("smt-dir")
+                .long("smt-dir")
+                .value_name("DIR")
+                .help("Write the verification condition of each basic path as an SMT-LIB2 script, '<function>_<path>.smt2', with its assumptions named after their nodes, to check it with any solver (default directory: 'src/vcs')")
+                .value_parser(clap::value_parser!(PathBuf))
+                .num_args(0..=1)
+                .default_missing_value("src/vcs"),
+        )
+        .arg(
+            Arg::new("assumptions")
+                .long("assumptions")
+                .help("Report every fact the verdicts take without checking it: 'assume!', trusted functions and call contracts, calls without contract, facts of parameter types, unmodeled syntax and undecided paths, with how many proved paths rely on them, in 'assumptions.json'")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("function")
+                .long("function")
+                .help("Verify only this function, by qualified name ('geometry::area') or last segment"),
+        )
         .arg(
             Arg::new("dot")
                 .long("dot")
                 .help("Generate a DOT graph representation of the CFG")
                 .action(clap::ArgAction::SetTrue),  // check the flag is here
         )
+        .arg(
+            Arg::new("json")
+                .long("json")
+                .help("Export the CFG as JSON with full node labels")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("summary")
+                .long("summary")
+                .help("Generate a function-level summary graph (DOT and HTML) with call edges")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("skeleton")
+                .long("skeleton")
+                .help("Generate a DOT graph of the annotations only, with an edge wherever a code path connects two of them")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("max-label-len")
+                .long("max-label-len")
+                .help("Truncate DOT labels longer than this many characters (full text kept in tooltips)")
+                .value_parser(clap::value_parser!(usize)),
+        )
+        .arg(
+            Arg::new("hide")
+                .long("hide")
+                .help("Hide these node categories from the exported graphs, e.g. 'statements,merges'")
+                .value_parser(NodeFilter::parse_categories),
+        )
+        .arg(
+            Arg::new("only")
+                .long("only")
+                .help("Only keep these node categories in the exported graphs, e.g. 'annotations,conditions'")
+                .value_parser(NodeFilter::parse_categories),
+        )
+        .arg(
+            Arg::new("sample-paths")
+                .long("sample-paths")
+                .help("Verify at most this many basic paths, chosen with --sample-strategy, and report their coverage")
+                .value_parser(clap::value_parser!(usize))
+                .global(true),
+        )
+        .arg(
+            Arg::new("sample-strategy")
+                .long("sample-strategy")
+                .help("How sampled paths are chosen: 'greedy' (branch coverage, default) or 'random'")
+                .value_parser(SamplingStrategy::parse)
+                .global(true),
+        )
+        .arg(
+            Arg::new("goal")
+                .long("goal")
+                .help("What to prove: 'contracts' (default), 'no-panic' (absence of unwrap on None, out-of-bounds indexing, overflow and panics) or 'none-safety' (unwraps and map indexes of values that may be None, with the path that leads there)")
+                .value_parser(VerificationGoal::parse),
+        )
+        .arg(
+            Arg::new("stats")
+                .long("stats")
+                .help("Write verified.json (functions annotated/verified/failed) and a shields.io badge endpoint badge.json")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("summarize-regions")
+                .long("summarize-regions")
+                .help("Cross loop-free branching regions without annotations in one step, with one condition for all their paths")
+                .action(clap::ArgAction::SetTrue)
+                .global(true),
+        )
+        .arg(
+            Arg::new("emit")
+                .long("emit")
+                .help("Artifacts to write, e.g. 'graphs,paths,smt,proofs,report,metrics,bundle,vcs' (whole CFG, basic paths, SMT-LIB2 conditions, solver proofs of the proved ones, statistics, Prometheus metrics, the paths of each function in one DOT file with an HTML index, the paths as Hoare triples with their verification conditions); none by default")
+                .value_parser(Artifacts::parse),
+        )
+        .arg(
+            Arg::new("color")
+                .long("color")
+                .help("Colors of the per-function summary: 'auto' (default, when printing to a terminal), 'always' or 'never'")
+                .value_parser(ColorChoice::parse),
+        )
+        .arg(
+            Arg::new("debug-phases")
+                .long("debug-phases")
+                .help("Write the graph after each builder phase (visit, post-processing, simplification, node IDs, SSA form, region summaries) to numbered DOT files in 'phases/'")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("cfg")
+                .long("cfg")
+                .help("Enable a configuration option, e.g. 'feature=\"fast\"' or 'test' (repeatable; '' for none): code under a '#[cfg(..)]' that doesn't hold is left out")
+                .value_parser(CompilationConfig::parse_option)
+                .action(clap::ArgAction::Append),
+        )
+        .arg(
+            Arg::new("all-cfgs")
+                .long("all-cfgs")
+                .help("Verify every configuration of the options the file's '#[cfg(..)]' attributes test, each in 'out/<file>/cfg-<options>/'")
+                .action(clap::ArgAction::SetTrue)
+                .conflicts_with("cfg"),
+        )
+        .arg(
+            Arg::new("manifest")
+                .long("manifest")
+                .value_name("PATH")
+                .help("Verify every target of a batch manifest (verify.toml) and exit with 1 unless all of them pass; the report is written to 'out/batch.json'")
+                .value_parser(clap::value_parser!(PathBuf))
+                .conflicts_with_all(["file", "input"]),
+        )
+        .arg(
+            Arg::new("crate")
+                .long("crate")
+                .value_name("CARGO_TOML")
+                .help("Verify every source file of a crate, found with 'cargo metadata' and the 'mod' declarations, writing the CFG and report of each function to 'target/secured/' (default manifest: 'Cargo.toml')")
+                .value_parser(clap::value_parser!(PathBuf))
+                .num_args(0..=1)
+                .default_missing_value("Cargo.toml")
+                .conflicts_with_all(["file", "input", "manifest"]),
+        )
+        .arg(
+            Arg::new("branch-profile")
+                .long("branch-profile")
+                .value_name("PATH")
+                .help("JSON branch counts per condition ('function', 'line', 'counts' by edge label): shown on the DOT edges, the most likely paths are verified first")
+                .value_parser(clap::value_parser!(PathBuf)),
+        )
+        .arg(
+            Arg::new("doc-samples")
+                .long("doc-samples")
+                .help("Verify the Rust code blocks of the file's doc comments, each on its own (always done for a .md file)")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("incremental")
+                .long("incremental")
+                .help("Keep the verdicts of the previous run (obligations.json in the output directory) for the obligations whose path, code and contracts didn't change")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("exhaustive")
+                .long("exhaustive")
+                .help("Also check each path by running every input of functions with small parameter domains through it")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("timeout")
+                .long("timeout")
+                .value_name("MS")
+                .help("Time the solver gets for each attempt at a verification condition, in milliseconds, 0 for no limit (default: 'timeout_ms' of the '[solver]' section of secured.toml, 10000)")
+                .value_parser(clap::value_parser!(u32)),
+        )
+        .arg(
+            Arg::new("solver-seed")
+                .long("solver-seed")
+                .value_name("SEED")
+                .help("Random seed of the solver's first attempt at each verification condition, the retries taking the next ones (default: 'seed' of the '[solver]' section of secured.toml, 0)")
+                .value_parser(clap::value_parser!(u32)),
+        )
+        // 'explain-path 4 file.rs': everything about Path 4, numbered as in the verification report
+        .subcommand(
+            Command::new("explain-path")
+                .about("Print the trace, verification condition, SMT-LIB2 script and verdict of one basic path")
+                .arg(
+                    Arg::new("path-id")
+                        .help("Number of the path, as in 'Path 4' of the verification report")
+                        .required(true)
+                        .value_parser(clap::value_parser!(usize))
+                        .index(1),
+                )
+                .arg(
+                    Arg::new("file")
+                        .help("The input file")
+                        .value_parser(clap::value_parser!(PathBuf))
+                        .required(true)
+                        .index(2),
+                ),
+        )
+        // 'simulate file.rs --function factorial --input n=5': run the paths of a function with concrete values
+        .subcommand(
+            Command::new("simulate")
+                .about("Run the basic paths of a function with concrete inputs and check its annotations, without the solver")
+                .arg(
+                    Arg::new("file")
+                        .help("The input file")
+                        .value_parser(clap::value_parser!(PathBuf))
+                        .required(true)
+                        .index(1),
+                )
+                .arg(
+                    Arg::new("function")
+                        .long("function")
+                        .value_name("NAME")
+                        .help("The function to run, by qualified name or last segment")
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("input")
+                        .long("input")
+                        .value_name("NAME=VALUE")
+                        .help("Value of a parameter, or of a variable of the invariant a path starts from: an integer, 'true' or 'false' (repeated, or separated by commas)")
+                        .action(clap::ArgAction::Append)
+                        .value_delimiter(','),
+                )
+                .arg(
+                    Arg::new("path")
+                        .long("path")
+                        .value_name("N")
+                        .help("Run only this path, as in 'Path 4' of the verification report (default: every path of the function, listing the ones the inputs don't take)")
+                        .value_parser(clap::value_parser!(usize)),
+                ),
+        )
+        // 'init': the configuration, std contracts, dependency and an example to start verifying a crate
+        .subcommand(
+            Command::new("init")
+                .about("Set up a crate for verification: secured.toml, src/config/conditions.json with std contracts, the secrust dependency and an annotated example")
+                .arg(
+                    Arg::new("dir")
+                        .help("Root of the crate (default: the current directory)")
+                        .value_parser(clap::value_parser!(PathBuf))
+                        .index(1),
+                )
+                .arg(
+                    Arg::new("secrust-path")
+                        .long("secrust-path")
+                        .value_name("PATH")
+                        .help("Depend on a local checkout of secrust instead of its git repository"),
+                )
+                .arg(
+                    Arg::new("force")
+                        .long("force")
+                        .help("Overwrite the files that already exist")
+                        .action(clap::ArgAction::SetTrue),
+                ),
+        )
+        // 'check-specs file.rs': undefined identifiers and type mismatches in the specs, without verifying
+        .subcommand(
+            Command::new("check-specs")
+                .about("Parse and type-check the annotations and external contracts, without building paths or calling the solver")
+                .arg(
+                    Arg::new("file")
+                        .help("The input file")
+                        .value_parser(clap::value_parser!(PathBuf))
+                        .required(true)
+                        .index(1),
+                ),
+        )
+        // 'contracts export file.rs': the contracts of the verified functions, for the crates calling into this one
+        .subcommand(
+            Command::new("contracts")
+                .about("Share the proved contracts of a crate with the crates that depend on it")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("export")
+                        .about("Verify the file and write the contracts of its verified functions as a bundle to import in '[contracts]' of secured.toml")
+                        .arg(
+                            Arg::new("file")
+                                .help("The input file")
+                                .value_parser(clap::value_parser!(PathBuf))
+                                .required(true)
+                                .index(1),
+                        )
+                        .arg(
+                            Arg::new("output")
+                                .long("output")
+                                .help("Path of the bundle (default: 'contracts.json' in the output directory)")
+                                .value_parser(clap::value_parser!(PathBuf)),
+                        )
+                        .arg(
+                            Arg::new("crate-name")
+                                .long("crate-name")
+                                .help("Crate the importers call the functions through (default: the package of './Cargo.toml')"),
+                        ),
+                )
+                .subcommand(
+                    Command::new("diff")
+                        .about("Compare the contracts of the public functions with a git revision and warn about strengthened preconditions and weakened postconditions")
+                        .arg(
+                            Arg::new("files")
+                                .help("The input files")
+                                .value_parser(clap::value_parser!(PathBuf))
+                                .required(true)
+                                .num_args(1..)
+                                .index(1),
+                        )
+                        .arg(
+                            Arg::new("base")
+                                .long("base")
+                                .value_name("REV")
+                                .help("Git revision of the old contracts, e.g. a release tag (default: 'HEAD')")
+                                .default_value("HEAD"),
+                        ),
+                ),
+        )
+        // 'project-graph a.rs b.rs --from api --to panic': one graph for several files, with the calls between them
+        .subcommand(
+            Command::new("project-graph")
+                .about("Merge the CFGs of several files into one graph linking each call to its callee, and find paths across them")
+                .arg(
+                    Arg::new("files")
+                        .help("The input files")
+                        .value_parser(clap::value_parser!(PathBuf))
+                        .required(true)
+                        .num_args(1..)
+                        .index(1),
+                )
+                .arg(
+                    Arg::new("from")
+                        .long("from")
+                        .help("Print a path starting at this function ('pub' for any public function)"),
+                )
+                .arg(
+                    Arg::new("to")
+                        .long("to")
+                        .requires("from")
+                        .help("Function the path ends at, or 'panic' for any panic site (the default)"),
+                ),
+        )
+        // 'taint file.rs': untrusted input reaching a command or a file operation unchecked
+        .subcommand(
+            Command::new("taint")
+                .about("Report the paths carrying the result of a '[taint]' source (e.g. 'env::var') to a sink (e.g. 'Command::new') through no sanitizer, and exit with 1 if there is one")
+                .arg(
+                    Arg::new("file")
+                        .help("The input file")
+                        .value_parser(clap::value_parser!(PathBuf))
+                        .required(true)
+                        .index(1),
+                ),
+        )
+        // 'equiv old.rs new.rs --function f': a refactored function still meets the contract of the old version
+        .subcommand(
+            Command::new("equiv")
+                .about("Align two versions of a function and verify both against the contract of the old one")
+                .arg(
+                    Arg::new("old")
+                        .help("The file with the old version")
+                        .value_parser(clap::value_parser!(PathBuf))
+                        .required(true)
+                        .index(1),
+                )
+                .arg(
+                    Arg::new("new")
+                        .help("The file with the new version")
+                        .value_parser(clap::value_parser!(PathBuf))
+                        .required(true)
+                        .index(2),
+                )
+                .arg(
+                    Arg::new("function")
+                        .long("function")
+                        .help("Qualified name of the function in both files ('geometry::area', 'Counter::push')")
+                        .required(true),
+                ),
+        )
+        .subcommand_negates_reqs(true)
         .try_get_matches_from(&adjusted_args)
         .unwrap_or_else(|err| {
             eprintln!("{}", err);
             exit(1);
         });
 
-    // handle file argument
-    let file = matches.get_one::<String>("file").unwrap();
-    let file_path = PathBuf::from(file);
+    if let Some(explain) = matches.subcommand_matches("explain-path") {
+        let file_path = explain.get_one::<PathBuf>("file").unwrap().clone();
+        let options = VerificationOptions {
+            sample_paths: explain.get_one::<usize>("sample-paths").copied(),
+            sampling_strategy: explain.get_one::<SamplingStrategy>("sample-strategy").copied().unwrap_or_default(),
+            summarize_regions: explain.get_flag("summarize-regions"),
+            ..Default::default()
+        };
+        if let Err(e) = explain_path(&file_path, *explain.get_one::<usize>("path-id").unwrap(), &options) {
+            eprintln!("Explain path failed: {}", e);
+            exit(1);
+        }
+        return;
+    }
+
+    if let Some(simulation) = matches.subcommand_matches("simulate") {
+        let file_path = simulation.get_one::<PathBuf>("file").unwrap().clone();
+        let inputs: Vec<String> = simulation.get_many::<String>("input").map(|inputs| inputs.cloned().collect()).unwrap_or_default();
+        let options = VerificationOptions {
+            summarize_regions: simulation.get_flag("summarize-regions"),
+            ..Default::default()
+        };
+        match simulate(&file_path, simulation.get_one::<String>("function").unwrap(), &inputs, simulation.get_one::<usize>("path").copied(), &options) {
+            Ok(true) => {},
+            Ok(false) => exit(1),
+            Err(e) => {
+                eprintln!("Simulation failed: {}", e);
+                exit(1);
+            },
+        }
+        return;
+    }
+
+    if let Some(init) = matches.subcommand_matches("init") {
+        let dir = init.get_one::<PathBuf>("dir").cloned().unwrap_or_else(|| PathBuf::from("."));
+        if let Err(e) = init_project(&dir, init.get_one::<String>("secrust-path").map(String::as_str), init.get_flag("force")) {
+            eprintln!("Init failed: {}", e);
+            exit(1);
+        }
+        return;
+    }
+
+    if let Some(check) = matches.subcommand_matches("check-specs") {
+        let file_path = check.get_one::<PathBuf>("file").unwrap().clone();
+        if let Err(e) = check_specs(&file_path) {
+            eprintln!("Spec check failed: {}", e);
+            exit(1);
+        }
+        return;
+    }
+
+    if let Some(project) = matches.subcommand_matches("project-graph") {
+        let files: Vec<PathBuf> = project.get_many::<PathBuf>("files").unwrap().cloned().collect();
+        let from = project.get_one::<String>("from").map(String::as_str);
+        let output_root = project.get_one::<PathBuf>("output-dir").map(PathBuf::as_path).unwrap_or(Path::new(OUTPUT_ROOT));
+        if let Err(e) = project_graph(&files, from, project.get_one::<String>("to").map(String::as_str), output_root) {
+            eprintln!("Project graph failed: {}", e);
+            exit(1);
+        }
+        return;
+    }
+
+    if let Some(taint) = matches.subcommand_matches("taint") {
+        let file_path = taint.get_one::<PathBuf>("file").unwrap().clone();
+        let output_root = taint.get_one::<PathBuf>("output-dir").map(PathBuf::as_path).unwrap_or(Path::new(OUTPUT_ROOT));
+        match taint_report(&file_path, output_root) {
+            Ok(true) => {},
+            Ok(false) => exit(1),
+            Err(e) => {
+                eprintln!("Taint analysis failed: {}", e);
+                exit(1);
+            },
+        }
+        return;
+    }
+
+    if let Some(equiv) = matches.subcommand_matches("equiv") {
+        let old_path = equiv.get_one::<PathBuf>("old").unwrap().clone();
+        let new_path = equiv.get_one::<PathBuf>("new").unwrap().clone();
+        if let Err(e) = check_equivalence(&old_path, &new_path, equiv.get_one::<String>("function").unwrap(), &VerificationOptions::default()) {
+            eprintln!("Equivalence check failed: {}", e);
+            exit(1);
+        }
+        return;
+    }
+
+    if let Some(diff) = matches.subcommand_matches("contracts").and_then(|contracts| contracts.subcommand_matches("diff")) {
+        let files: Vec<PathBuf> = diff.get_many::<PathBuf>("files").unwrap().cloned().collect();
+        if let Err(e) = contracts_diff(&files, diff.get_one::<String>("base").unwrap()) {
+            eprintln!("Contracts diff failed: {}", e);
+            exit(1);
+        }
+        return;
+    }
+
+    if let Some(export) = matches.subcommand_matches("contracts").and_then(|contracts| contracts.subcommand_matches("export")) {
+        let file_path = export.get_one::<PathBuf>("file").unwrap().clone();
+        let options = VerificationOptions {
+            export_contracts: Some(ContractExport {
+                output: export.get_one::<PathBuf>("output").cloned(),
+                crate_name: export.get_one::<String>("crate-name").cloned(),
+            }),
+            output_dir: export.get_one::<PathBuf>("output-dir").cloned(),
+            ..Default::default()
+        };
+        if let Err(e) = run_verification(&file_path, &options) {
+            eprintln!("Contract export failed: {}", e);
+            exit(1);
+        }
+        return;
+    }
+
+    // check if the dot flag was provided, or 'dot' among the formats
+    let formats: Vec<&String> = matches.get_many::<String>("format").map(|formats| formats.collect()).unwrap_or_default();
+    let generate_dot = matches.get_flag("dot") || formats.iter().any(|format| *format == "dot");
+
+    let options = VerificationOptions {
+        generate_dot,
+        generate_json: matches.get_flag("json") || formats.iter().any(|format| *format == "json"),
+        max_label_len: matches.get_one::<usize>("max-label-len").copied(),
+        generate_summary: matches.get_flag("summary"),
+        generate_skeleton: matches.get_flag("skeleton"),
+        node_filter: NodeFilter {
+            hide: matches.get_one::<Vec<String>>("hide").cloned().unwrap_or_default(),
+            only: matches.get_one::<Vec<String>>("only").cloned().unwrap_or_default(),
+        },
+        sample_paths: matches.get_one::<usize>("sample-paths").copied(),
+        sampling_strategy: matches.get_one::<SamplingStrategy>("sample-strategy").copied().unwrap_or_default(),
+        goal: matches.get_one::<VerificationGoal>("goal").copied().unwrap_or_default(),
+        generate_stats: matches.get_flag("stats"),
+        summarize_regions: matches.get_flag("summarize-regions"),
+        emit: matches.get_one::<Artifacts>("emit").copied().unwrap_or_default(),
+        color: matches.get_one::<ColorChoice>("color").copied().unwrap_or_default(),
+        debug_phases: matches.get_flag("debug-phases"),
+        export_contracts: None,
+        cfg: matches.get_many::<Option<CfgOption>>("cfg").map(|options| CompilationConfig {
+            options: options.flatten().cloned().collect(),
+        }),
+        all_cfgs: matches.get_flag("all-cfgs"),
+        function: matches.get_one::<String>("function").cloned(),
+        branch_profile: matches.get_one::<PathBuf>("branch-profile").cloned(),
+        doc_samples: matches.get_flag("doc-samples"),
+        exhaustive: matches.get_flag("exhaustive"),
+        solver_timeout_ms: matches.get_one::<u32>("timeout").copied(),
+        solver_seed: matches.get_one::<u32>("solver-seed").copied(),
+        invocation: adjusted_args.iter().skip(1).map(|arg| arg.to_string_lossy().into_owned()).collect(),
+        output_dir: matches.get_one::<PathBuf>("output-dir").cloned(),
+        incremental: matches.get_flag("incremental"),
+        source_root: None,
+        markdown_report: matches.get_one::<String>("report").is_some_and(|format| format == "md"),
+        smt_dir: matches.get_one::<PathBuf>("smt-dir").cloned(),
+        assumptions: matches.get_flag("assumptions"),
+    };
+
+    // '--crate': all the files of a crate, for 'cargo secrust-verify --crate' in its directory
+    if let Some(manifest) = matches.get_one::<PathBuf>("crate") {
+        match verify_crate(manifest, &options) {
+            Ok(true) => println!("Crate verification passed."),
+            Ok(false) => {
+                eprintln!("Crate verification failed.");
+                exit(1);
+            },
+            Err(e) => {
+                eprintln!("Crate verification failed: {}", e);
+                exit(1);
+            },
+        }
+        return;
+    }
+
+    // '--manifest verify.toml': one aggregated verdict for all the targets, for CI
+    if let Some(manifest) = matches.get_one::<PathBuf>("manifest") {
+        match run_batch(manifest, &options) {
+            Ok(true) => println!("Batch verification passed."),
+            Ok(false) => {
+                eprintln!("Batch verification failed.");
+                exit(1);
+            },
+            Err(e) => {
+                eprintln!("Batch verification failed: {}", e);
+                exit(1);
+            },
+        }
+        return;
+    }
 
-    // check if the dot flag was provided
-    let generate_dot = *matches.get_one::<bool>("dot").unwrap_or(&false);
+    // handle file argument, positional or '--input'
+    let file_path = matches.get_one::<PathBuf>("file").or_else(|| matches.get_one::<PathBuf>("input")).unwrap().clone();
 
     println!("Running Secrust verification on file: {:?}", file_path);
     println!("Generate DOT graph: {}", generate_dot);
 
-    // run verification function with the provided file and generate_dot flag
-    if let Err(e) = run_verification(&file_path, generate_dot) {
+    // run verification function with the provided file and options
+    if let Err(e) = run_verification(&file_path, &options) {
         eprintln!("Verification failed: {}", e);
         exit(1);
     } else {
@@ -0,0 +1,108 @@
+use serde::Serialize;
+use serde_json::{json, Value};
+
+// What a verdict of the run takes for granted without checking it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AssumptionKind {
+    Assume, // 'assume!' in a body
+    TrustedFunction, // '#[secured::trusted]' or skipped: its body isn't verified, its contract is still used by its callers
+    TrustedContract, // contract of a call from a comment, the conditions database or a contract bundle, not proved here
+    Havoc, // call without contract: the variables it writes get any value
+    TypeFact, // fact of a parameter type assumed on entry
+    UnmodeledSyntax, // construct the graph approximates
+    Undecided, // path the solver gave no verdict for
+}
+
+impl AssumptionKind {
+    // (singular, plural)
+    fn describe(&self) -> (&'static str, &'static str) {
+        match self {
+            AssumptionKind::Assume => ("assume!", "assume!"),
+            AssumptionKind::TrustedFunction => ("trusted function", "trusted functions"),
+            AssumptionKind::TrustedContract => ("trusted call contract", "trusted call contracts"),
+            AssumptionKind::Havoc => ("call without contract", "calls without contract"),
+            AssumptionKind::TypeFact => ("fact of a parameter type", "facts of parameter types"),
+            AssumptionKind::UnmodeledSyntax => ("function with unmodeled syntax", "functions with unmodeled syntax"),
+            AssumptionKind::Undecided => ("undecided path", "undecided paths"),
+        }
+    }
+}
+
+// One unchecked fact and where it is
+#[derive(Debug, Clone, Serialize)]
+pub struct UncheckedFact {
+    pub kind: AssumptionKind,
+    pub function: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line: Option<usize>,
+    pub detail: String, // e.g. 'n < 100', 'call to read: havoc count'
+}
+
+// Every unchecked fact of a run ('--assumptions'), and how many proved paths rely on one of them: a proof of
+// such a path holds only as far as the facts do
+#[derive(Debug, Clone, Default)]
+pub struct AssumptionBudget {
+    pub facts: Vec<UncheckedFact>,
+    pub proved_paths: usize,
+    pub relying_paths: usize, // proved paths that cross an 'assume!', a trusted contract, a havoc or a type fact
+}
+
+impl AssumptionBudget {
+    fn count(&self, kind: AssumptionKind) -> usize {
+        self.facts.iter().filter(|fact| fact.kind == kind).count()
+    }
+
+    fn kinds(&self) -> Vec<AssumptionKind> {
+        let mut kinds: Vec<AssumptionKind> = self.facts.iter().map(|fact| fact.kind).collect();
+        kinds.sort();
+        kinds.dedup();
+        kinds
+    }
+
+    // Counts by kind, then each fact with its location, in the order of the kinds
+    pub fn console(&self) -> String {
+        let plural = if self.facts.len() == 1 { "" } else { "s" };
+        let mut report = format!("Assumption budget: {} unchecked fact{}, {} of {} proved paths rely on them\n",
+            self.facts.len(), plural, self.relying_paths, self.proved_paths);
+        for kind in self.kinds() {
+            let count = self.count(kind);
+            let (singular, plural) = kind.describe();
+            report.push_str(&format!("  {:>4} {}\n", count, if count == 1 { singular } else { plural }));
+        }
+        let mut facts: Vec<&UncheckedFact> = self.facts.iter().collect();
+        facts.sort_by_key(|fact| (fact.kind, fact.line.unwrap_or(0)));
+        for fact in facts {
+            let line = fact.line.map(|line| format!(" (line {})", line)).unwrap_or_default();
+            report.push_str(&format!("  - {}{}: {}: {}\n", fact.function, line, kind_name(fact.kind), fact.detail));
+        }
+        report
+    }
+
+    // 'assumptions.json'
+    pub fn to_json(&self, file: &str) -> Value {
+        let counts: serde_json::Map<String, Value> = self.kinds().into_iter()
+            .map(|kind| (kind_name(kind).to_string(), json!(self.count(kind))))
+            .collect();
+        json!({
+            "file": file,
+            "proved_paths": self.proved_paths,
+            "relying_paths": self.relying_paths,
+            "counts": counts,
+            "facts": self.facts,
+        })
+    }
+}
+
+// Name of the kind in the JSON and the listing, 'trusted_contract'
+fn kind_name(kind: AssumptionKind) -> &'static str {
+    match kind {
+        AssumptionKind::Assume => "assume",
+        AssumptionKind::TrustedFunction => "trusted_function",
+        AssumptionKind::TrustedContract => "trusted_contract",
+        AssumptionKind::Havoc => "havoc",
+        AssumptionKind::TypeFact => "type_fact",
+        AssumptionKind::UnmodeledSyntax => "unmodeled_syntax",
+        AssumptionKind::Undecided => "undecided",
+    }
+}
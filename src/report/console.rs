@@ -0,0 +1,79 @@
+use std::io::IsTerminal;
+
+use crate::report::stats::{FunctionResult, Verdict, VerificationStats};
+
+// When the console summary uses colors
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ColorChoice {
+    #[default]
+    Auto, // when the output is a terminal and NO_COLOR is not set
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name {
+            "auto" => Ok(ColorChoice::Auto),
+            "always" => Ok(ColorChoice::Always),
+            "never" => Ok(ColorChoice::Never),
+            _ => Err(format!("unknown color mode '{}' (expected 'auto', 'always' or 'never')", name)),
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        match self {
+            ColorChoice::Auto => std::io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none(),
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+        }
+    }
+}
+
+impl Verdict {
+    // Status mark and its ANSI color
+    fn mark(&self) -> (&'static str, &'static str) {
+        match self {
+            Verdict::Valid => ("✓", "\x1b[32m"),
+            Verdict::Failed => ("✗", "\x1b[31m"),
+            Verdict::Unknown => ("?", "\x1b[33m"),
+        }
+    }
+}
+
+impl VerificationStats {
+    // One line per analyzed function: status mark, obligation counts and the slowest obligation
+    pub fn console_summary(&self, color: bool) -> String {
+        let width = self.functions.iter().map(|f| f.name.chars().count()).max().unwrap_or(0);
+        let mut summary = String::from("Results:\n");
+        for function in &self.functions {
+            let (mark, ansi) = function.status().mark();
+            let mark = if color { format!("{}{}\x1b[0m", ansi, mark) } else { mark.to_string() };
+            let name = format!("{:width$}", function.name, width = width);
+            let name = if color { format!("\x1b[1m{}\x1b[0m", name) } else { name };
+            let slowest = function.slowest.as_ref()
+                .map(|(obligation, elapsed)| format!(" — slowest: {} ({:.1} ms)", obligation, elapsed.as_secs_f64() * 1000.0))
+                .unwrap_or_default();
+            summary.push_str(&format!("  {} {}  {}{}\n", mark, name, Self::obligation_counts(function), slowest));
+        }
+        // Left out on purpose, listed so the exclusion stays visible
+        for (function, reason) in &self.skipped {
+            summary.push_str(&format!("  - {}  skipped: {}\n", function, reason));
+        }
+        summary
+    }
+
+    // '5 obligations: 4 proved, 1 failed'
+    fn obligation_counts(function: &FunctionResult) -> String {
+        let total = function.valid + function.failed + function.unknown;
+        let counts: Vec<String> = [(function.valid, "proved"), (function.failed, "failed"), (function.unknown, "undecided")].iter()
+            .filter(|&&(count, _)| count > 0)
+            .map(|(count, verdict)| format!("{} {}", count, verdict))
+            .collect();
+        let plural = if total == 1 { "" } else { "s" };
+        match counts.is_empty() {
+            true => format!("{} obligation{}", total, plural),
+            false => format!("{} obligation{}: {}", total, plural, counts.join(", ")),
+        }
+    }
+}
@@ -0,0 +1,85 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::report::stats::Verdict;
+
+// Fingerprints of a function, telling a change of its code from a change of its contract
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FunctionFingerprint {
+    pub code: String, // FNV-1a 64 of the signature, the body without 'pre!'/'post!' and the '//@' comments in it
+    pub contract: String, // FNV-1a 64 of the 'pre!'/'post!' annotations
+}
+
+// What the verification condition of a basic path is made of, besides the file-wide settings
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ObligationDependencies {
+    pub code: BTreeSet<String>, // the function of the path, the callees inlined into it and the reference it refines
+    pub contracts: BTreeSet<String>, // functions whose contract the path starts from, ends at, or checks or assumes at a call
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ObligationRecord {
+    pub key: String, // identifier of the function and node IDs of the path
+    pub dependencies: ObligationDependencies,
+    pub verdict: Verdict,
+}
+
+// Contract-to-obligation dependency index of a run, written to 'obligations.json'. An '--incremental' run keeps
+// the verdict of an obligation of the previous run when its path is the same and none of the code and
+// contracts it depends on changed, so editing a contract only re-verifies the proof of its function and the
+// callers assuming it.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ObligationIndex {
+    pub settings: String, // hash of what all the obligations depend on: types, constants, logic functions, configuration, options
+    pub functions: BTreeMap<String, FunctionFingerprint>, // by qualified name
+    pub obligations: Vec<ObligationRecord>,
+}
+
+impl ObligationIndex {
+    // Index of the previous run in the output directory, if any
+    pub fn load(output_dir: &Path) -> Option<Self> {
+        let content = fs::read_to_string(output_dir.join("obligations.json")).ok()?;
+        serde_json::from_str(&content).ok()
+    }
+
+    pub fn write(&self, output_dir: &Path) -> Result<PathBuf, Box<dyn std::error::Error>> {
+        fs::create_dir_all(output_dir)?;
+        let path = output_dir.join("obligations.json");
+        fs::write(&path, serde_json::to_string_pretty(self)?)?;
+        Ok(path)
+    }
+
+    // Functions added, removed or changed since 'previous': (code changed, only the contract changed)
+    pub fn changes(&self, previous: &ObligationIndex) -> (Vec<String>, Vec<String>) {
+        let names: BTreeSet<&String> = self.functions.keys().chain(previous.functions.keys()).collect();
+        let (mut code, mut contracts) = (Vec::new(), Vec::new());
+        for name in names {
+            match (self.functions.get(name), previous.functions.get(name)) {
+                (Some(current), Some(old)) if current == old => {},
+                (Some(current), Some(old)) if current.code == old.code => contracts.push(name.clone()),
+                _ => code.push(name.clone()),
+            }
+        }
+        (code, contracts)
+    }
+
+    // Verdict of the previous run for an obligation of this run, when nothing it depends on changed
+    pub fn reusable(&self, previous: &ObligationIndex, key: &str, dependencies: &ObligationDependencies) -> Option<Verdict> {
+        if self.settings != previous.settings {
+            return None;
+        }
+        let record = previous.obligations.iter().find(|record| record.key == key && record.dependencies == *dependencies)?;
+        let unchanged = |name: &String, part: fn(&FunctionFingerprint) -> &String| {
+            match (self.functions.get(name), previous.functions.get(name)) {
+                (Some(current), Some(old)) => part(current) == part(old),
+                (None, None) => true,
+                _ => false,
+            }
+        };
+        let code_unchanged = dependencies.code.iter().all(|name| unchanged(name, |fingerprint| &fingerprint.code));
+        let contracts_unchanged = dependencies.contracts.iter().all(|name| unchanged(name, |fingerprint| &fingerprint.contract));
+        (code_unchanged && contracts_unchanged).then_some(record.verdict)
+    }
+}
@@ -0,0 +1,174 @@
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::path::{Component, Path, PathBuf, Prefix};
+
+// Root of the artifacts of all the verified files, unless '--output-dir' gives another
+pub const OUTPUT_ROOT: &str = "out";
+
+// Input path standing for the standard input; its artifacts go in '<root>/stdin.rs/'
+pub const STDIN_INPUT: &str = "-";
+
+// Directories the artifacts of one input file are written to: 'out/<file>/' for the whole file
+// (CFG, statistics, manifest) and 'out/<file>/<function>/' for each analyzed function
+// ('cfg.dot', 'paths/', 'smt/', 'report.json')
+#[derive(Debug, Clone)]
+pub struct OutputLayout {
+    file_dir: PathBuf,
+    function_dirs: HashMap<String, PathBuf>, // by function identifier
+}
+
+// Room left under a file's directory for the function directories and artifact names ('paths/basic_path_12.dot')
+const ARTIFACT_NAME_ROOM: usize = 100;
+const MAX_PATH: usize = 260;
+
+// Directories of the file that are not functions
+const FILE_DIRS: [&str; 2] = ["phases", "smt"];
+
+impl OutputLayout {
+    // 'functions' are the identifiers ('geometry::area#1b2c3d4e') and names of the analyzed functions
+    pub fn new(root: &Path, source: &Path, functions: &[(String, String)]) -> Self {
+        Self::in_dir(Self::file_dir_of(root, source), functions)
+    }
+
+    // Same as 'new' with the file's artifacts in a subdirectory, e.g. one per '--all-cfgs' configuration
+    pub fn for_variant(root: &Path, source: &Path, variant: &str, functions: &[(String, String)]) -> Self {
+        Self::in_dir(Self::file_dir_of(root, source).join(variant), functions)
+    }
+
+    fn in_dir(file_dir: PathBuf, functions: &[(String, String)]) -> Self {
+        let names: Vec<String> = functions.iter().map(|(_, name)| Self::dir_name(name)).collect();
+        let function_dirs = functions.iter().zip(&names)
+            .map(|((id, _), name)| {
+                // Functions whose names give the same directory are told apart by their signature hash
+                let taken = names.iter().filter(|other| *other == name).count() > 1 || FILE_DIRS.contains(&name.as_str());
+                let name = match id.split_once('#') {
+                    Some((_, hash)) if taken => format!("{}-{}", name, hash),
+                    _ => name.clone(),
+                };
+                (id.clone(), file_dir.join(name))
+            })
+            .collect();
+        OutputLayout { file_dir, function_dirs }
+    }
+
+    pub fn file_dir(&self) -> &Path {
+        &self.file_dir
+    }
+
+    // Directory of an analyzed function; artifacts of no known function go to the file directory
+    pub fn function_dir(&self, function: Option<&str>) -> &Path {
+        function.and_then(|function| self.function_dirs.get(function)).unwrap_or(&self.file_dir)
+    }
+
+    // 'src/main.rs' gives 'out/src/main.rs': the extension is kept, so a file and a directory of the same
+    // name don't share a directory
+    fn file_dir_of(root: &Path, source: &Path) -> PathBuf {
+        let mut dir = root.to_path_buf();
+        let source = Self::windows_path_elsewhere(source).unwrap_or_else(|| source.to_path_buf());
+        // The samples of the standard input are 'stdin.rs/sample_12'
+        let source = match source.strip_prefix(STDIN_INPUT) {
+            Ok(rest) => {
+                dir.push("stdin.rs");
+                rest
+            },
+            Err(_) => source.as_path(),
+        };
+        for component in source.components() {
+            match component {
+                Component::Normal(part) => dir.push(part),
+                Component::ParentDir => dir.push("_parent"),
+                Component::RootDir | Component::Prefix(_) | Component::CurDir => {},
+            }
+        }
+        Self::long_path(dir)
+    }
+
+    // Off Windows, 'C:\proj\src\main.rs' is a single file name: its separators and drive are read as Windows
+    // reads them, for its artifacts to get the same directories on every system
+    fn windows_path_elsewhere(source: &Path) -> Option<PathBuf> {
+        let text = source.to_str().filter(|_| !cfg!(windows))?;
+        let without_drive = match text.as_bytes() {
+            [drive, b':', ..] if drive.is_ascii_alphabetic() => &text[2..],
+            _ if text.contains('\\') => text,
+            _ => return None,
+        };
+        Some(PathBuf::from(without_drive.replace('\\', "/")))
+    }
+
+    // Windows refuses paths past MAX_PATH characters unless they are absolute and verbatim ('\\?\C:\repo\out\..'),
+    // so the directories of deeply nested files get that form there. Other systems keep the relative path.
+    fn long_path(dir: PathBuf) -> PathBuf {
+        if !cfg!(windows) {
+            return dir;
+        }
+        let Ok(absolute) = std::path::absolute(&dir) else { return dir };
+        if absolute.as_os_str().len() + ARTIFACT_NAME_ROOM < MAX_PATH {
+            return dir;
+        }
+        let mut components = absolute.components();
+        let mut verbatim = OsString::from(r"\\?\");
+        match components.next() {
+            Some(Component::Prefix(prefix)) => match prefix.kind() {
+                Prefix::Disk(_) => verbatim.push(prefix.as_os_str()),
+                Prefix::UNC(server, share) => {
+                    verbatim.push(r"UNC\");
+                    verbatim.push(server);
+                    verbatim.push(r"\");
+                    verbatim.push(share);
+                },
+                // Already verbatim, or a device path
+                _ => return absolute,
+            },
+            _ => return dir,
+        }
+        // 'absolute' resolved the '.' and '..', which verbatim paths don't
+        for component in components {
+            if let Component::Normal(part) = component {
+                verbatim.push(r"\");
+                verbatim.push(part);
+            }
+        }
+        PathBuf::from(verbatim)
+    }
+
+    // 'geometry::area' gives 'geometry-area': '-' can't appear in a Rust path, and file names have a '.'
+    pub(crate) fn dir_name(function: &str) -> String {
+        function.replace("::", "-").chars()
+            .map(|c| if c.is_alphanumeric() || c == '_' || c == '-' { c } else { '_' })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn functions() -> Vec<(String, String)> {
+        vec![
+            ("geometry::area#1b2c3d4e".to_string(), "geometry::area".to_string()),
+            ("smt#5f6a7b8c".to_string(), "smt".to_string()),
+        ]
+    }
+
+    #[test]
+    fn windows_separators_and_drives_give_the_same_directories() {
+        for source in [r"C:\proj\src\main.rs", r"\proj\src\main.rs", "C:/proj/src/main.rs", "/proj/src/main.rs"] {
+            let layout = OutputLayout::new(Path::new(OUTPUT_ROOT), Path::new(source), &functions());
+            assert_eq!(layout.file_dir(), Path::new("out/proj/src/main.rs"), "{}", source);
+            assert_eq!(layout.function_dir(Some("geometry::area#1b2c3d4e")), Path::new("out/proj/src/main.rs/geometry-area"), "{}", source);
+            // 'smt' is a directory of the file, so the function is told apart by its hash
+            assert_eq!(layout.function_dir(Some("smt#5f6a7b8c")), Path::new("out/proj/src/main.rs/smt-5f6a7b8c"), "{}", source);
+            assert_eq!(layout.function_dir(Some("unknown")), layout.file_dir(), "{}", source);
+        }
+    }
+
+    #[test]
+    fn relative_windows_paths_keep_their_directories() {
+        let layout = OutputLayout::new(Path::new(OUTPUT_ROOT), Path::new(r"src\geometry\mod.rs"), &functions());
+        assert_eq!(layout.file_dir(), Path::new("out/src/geometry/mod.rs"));
+        let layout = OutputLayout::new(Path::new(OUTPUT_ROOT), Path::new(r"..\shared\.\lib.rs"), &functions());
+        assert_eq!(layout.file_dir(), Path::new("out/_parent/shared/lib.rs"));
+        let layout = OutputLayout::for_variant(Path::new(OUTPUT_ROOT), Path::new(r"D:\src\main.rs"), "feature-x", &functions());
+        assert_eq!(layout.function_dir(Some("geometry::area#1b2c3d4e")), Path::new("out/src/main.rs/feature-x/geometry-area"));
+    }
+}
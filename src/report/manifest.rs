@@ -0,0 +1,137 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+use crate::report::provenance::Provenance;
+
+// One file written by a run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArtifactEntry {
+    pub path: String, // relative to the output directory
+    pub kind: String, // 'cfg-dot', 'path-dot', 'cfg-json', 'summary-html', ...
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub function: Option<String>, // set for artifacts about a single function
+    pub hash: String, // FNV-1a 64 of the content, stable across runs and toolchains
+}
+
+// Index of the artifacts of an output directory, written to 'manifest.json'
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Manifest {
+    pub source: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provenance: Option<Provenance>, // stamped on each artifact recorded
+    pub artifacts: Vec<ArtifactEntry>,
+}
+
+impl Manifest {
+    pub fn new(source: String) -> Self {
+        Manifest { source, provenance: None, artifacts: Vec::new() }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.artifacts.is_empty()
+    }
+
+    // Add a file that has been written in 'output_dir', with the provenance of the run. The badge keeps the
+    // schema shields.io reads.
+    pub fn record(&mut self, output_dir: &Path, file: &Path, kind: &str, function: Option<String>) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(provenance) = self.provenance.as_ref().filter(|_| kind != "badge") {
+            provenance.stamp(file)?;
+        }
+        let content = fs::read(file)?;
+        let path = file.strip_prefix(output_dir).unwrap_or(file);
+        self.artifacts.push(ArtifactEntry {
+            path: path.to_string_lossy().replace('\\', "/"),
+            kind: kind.to_string(),
+            function,
+            hash: format!("{:016x}", fnv1a(&content)),
+        });
+        Ok(())
+    }
+
+    // Manifest of the previous run in the output directory, if any
+    pub fn load(output_dir: &Path) -> Option<Self> {
+        let content = fs::read_to_string(output_dir.join("manifest.json")).ok()?;
+        serde_json::from_str(&content).ok()
+    }
+
+    // Delete the artifacts of the previous run that this run didn't write again, when their function is no longer
+    // analyzed ('functions' holds the identifiers of the analyzed ones) or when this run rewrote the artifacts of
+    // their kind for their function, e.g. the paths of a function that now has fewer. The other artifacts are
+    // still current and stay in the manifest. Returns the number of files deleted.
+    pub fn remove_stale(&mut self, previous: Manifest, output_dir: &Path, functions: &HashSet<String>) -> std::io::Result<usize> {
+        let written: HashSet<String> = self.artifacts.iter().map(|artifact| artifact.path.clone()).collect();
+        let rewritten: HashSet<(String, Option<String>)> = self.artifacts.iter()
+            .map(|artifact| (artifact.kind.clone(), artifact.function.clone()))
+            .collect();
+        let mut removed = 0;
+        for artifact in previous.artifacts.into_iter().filter(|artifact| !written.contains(&artifact.path)) {
+            let file = output_dir.join(&artifact.path);
+            let function_removed = artifact.function.as_ref().is_some_and(|function| !functions.contains(function));
+            if !function_removed && !rewritten.contains(&(artifact.kind.clone(), artifact.function.clone())) {
+                if file.exists() {
+                    self.artifacts.push(artifact);
+                }
+                continue;
+            }
+            if file.exists() {
+                fs::remove_file(&file)?;
+                removed += 1;
+            }
+            // Directories left empty go too, up to the output directory
+            let mut dir = file.parent();
+            while let Some(current) = dir.filter(|current| current.starts_with(output_dir) && *current != output_dir) {
+                if fs::remove_dir(current).is_err() {
+                    break;
+                }
+                dir = current.parent();
+            }
+        }
+        Ok(removed)
+    }
+
+    // Write 'manifest.json' in the output directory, artifacts sorted by path
+    pub fn write(&mut self, output_dir: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        self.artifacts.sort_by(|a, b| a.path.cmp(&b.path));
+        let manifest_path = output_dir.join("manifest.json");
+        fs::write(&manifest_path, serde_json::to_string_pretty(self)?)?;
+        println!("Artifact manifest saved as: {:?}", manifest_path);
+        Ok(())
+    }
+}
+
+pub(crate) fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01b3))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::OutputLayout;
+
+    #[test]
+    fn artifacts_of_a_windows_path_are_recorded_relative_with_slashes() {
+        let root = std::env::temp_dir().join(format!("secrust-manifest-{}", std::process::id()));
+        let source = r"C:\proj\src\main.rs";
+        let functions = vec![("geometry::area#1b2c3d4e".to_string(), "geometry::area".to_string())];
+        let layout = OutputLayout::new(&root, Path::new(source), &functions);
+        let paths_dir = layout.function_dir(Some("geometry::area#1b2c3d4e")).join("paths");
+        fs::create_dir_all(&paths_dir).unwrap();
+        let (report, path_dot) = (layout.function_dir(Some("geometry::area#1b2c3d4e")).join("report.json"), paths_dir.join("basic_path_1.dot"));
+        fs::write(&report, "{}").unwrap();
+        fs::write(&path_dot, "digraph {}").unwrap();
+
+        let mut manifest = Manifest::new(source.to_string());
+        manifest.record(layout.file_dir(), &path_dot, "path-dot", Some("geometry::area#1b2c3d4e".to_string())).unwrap();
+        manifest.record(layout.file_dir(), &report, "function-report", Some("geometry::area#1b2c3d4e".to_string())).unwrap();
+        manifest.write(layout.file_dir()).unwrap();
+        let written = Manifest::load(layout.file_dir()).unwrap();
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(layout.file_dir(), root.join("proj").join("src").join("main.rs"));
+        assert_eq!(written.source, source);
+        let paths: Vec<&str> = written.artifacts.iter().map(|artifact| artifact.path.as_str()).collect();
+        assert_eq!(paths, ["geometry-area/paths/basic_path_1.dot", "geometry-area/report.json"]);
+    }
+}
@@ -0,0 +1,87 @@
+use std::collections::HashMap;
+
+use crate::report::stats::{FunctionResult, Verdict, VerificationStats};
+
+impl Verdict {
+    // Status mark in Markdown, rendered as an emoji by GitHub and GitLab
+    fn markdown_mark(&self) -> &'static str {
+        match self {
+            Verdict::Valid => "✅",
+            Verdict::Failed => "❌",
+            Verdict::Unknown => "❔",
+        }
+    }
+}
+
+// '`a | b`' breaks a table cell unless the pipe is escaped
+fn code_cell(text: &str) -> String {
+    format!("`{}`", text.replace('|', "\\|"))
+}
+
+impl VerificationStats {
+    // Markdown report of the run, for a PR description or a review comment ('--report md'): the verdicts of
+    // the functions, then a table of the paths of each function. 'graphs' are the Mermaid CFGs of the failed
+    // functions, by function ID, and 'counterexamples' the traces of the failed paths, by path number.
+    pub fn to_markdown(&self, graphs: &HashMap<String, String>, counterexamples: &HashMap<usize, String>) -> String {
+        let mut report = format!("# Verification report: `{}`\n\n", self.file);
+        let (valid, failed, undecided) = self.obligation_totals();
+        let verified = self.functions.iter().filter(|f| f.status() == Verdict::Valid).count();
+        let status = match (failed, undecided) {
+            (0, 0) => Verdict::Valid,
+            (0, _) => Verdict::Unknown,
+            _ => Verdict::Failed,
+        };
+        report.push_str(&format!("{} **{}/{} annotated functions verified** ({} functions in file); obligations: {} proved, {} failed, {} undecided\n\n",
+            status.markdown_mark(), verified, self.functions.len(), self.functions_in_file, valid, failed, undecided));
+
+        if !self.functions.is_empty() {
+            report.push_str("| | Function | Proved | Failed | Undecided | Solver time |\n|---|---|---:|---:|---:|---:|\n");
+            for function in &self.functions {
+                report.push_str(&format!("| {} | {} | {} | {} | {} | {:.1} ms |\n", function.status().markdown_mark(), code_cell(&function.name),
+                    function.valid, function.failed, function.unknown, function.solver_time.as_secs_f64() * 1000.0));
+            }
+            report.push('\n');
+        }
+        if !self.skipped.is_empty() {
+            report.push_str("Skipped:\n");
+            for (function, reason) in &self.skipped {
+                report.push_str(&format!("- `{}`: {}\n", function, reason));
+            }
+            report.push('\n');
+        }
+
+        for function in self.functions.iter().filter(|f| !f.annotations.is_empty()) {
+            report.push_str(&Self::function_markdown(function, graphs.get(&function.id), counterexamples));
+        }
+        report
+    }
+
+    fn function_markdown(function: &FunctionResult, graph: Option<&String>, counterexamples: &HashMap<usize, String>) -> String {
+        let mut section = format!("## {} `{}`\n\n", function.status().markdown_mark(), function.name);
+        section.push_str("| Annotation | Path | From | Exit | Verdict |\n|---|---:|---|---|---|\n");
+        let mut failed_paths = Vec::new();
+        for group in &function.annotations {
+            for path in &group.paths {
+                let exit = path.exit.as_deref().map(code_cell).unwrap_or_default();
+                section.push_str(&format!("| {} | {} | {} | {} | {} {} |\n", code_cell(&group.annotation), path.path, code_cell(&path.from), exit,
+                    path.verdict.markdown_mark(), path.verdict.describe()));
+                if path.verdict == Verdict::Failed {
+                    failed_paths.push(path.path);
+                }
+            }
+        }
+        section.push('\n');
+
+        if let Some(graph) = graph {
+            section.push_str("Control flow graph, with the nodes of the failed paths outlined:\n\n");
+            section.push_str(&format!("```mermaid\n{}```\n\n", graph));
+        }
+        failed_paths.sort();
+        for number in failed_paths {
+            let trace = counterexamples.get(&number).map(String::as_str)
+                .unwrap_or("  (no counterexample: the verdict was kept from the previous run)\n");
+            section.push_str(&format!("<details>\n<summary>Counterexample of Path {}</summary>\n\n```\n{}\n```\n\n</details>\n\n", number, trace.trim_end()));
+        }
+        section
+    }
+}
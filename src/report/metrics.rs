@@ -0,0 +1,80 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::report::stats::{Verdict, VerificationStats};
+
+// Metrics of verification runs in the Prometheus text format, for the textfile collector of node_exporter or
+// any scraper of OpenMetrics. A run is the stats of a file and the goal it was verified for ('contracts'); the
+// samples of a run are labeled with both, so the runs of a batch go in one file.
+pub fn prometheus_metrics(runs: &[(&VerificationStats, &str)]) -> String {
+    let mut metrics = String::new();
+    let file_labels = |stats: &VerificationStats, goal: &str| format!("file=\"{}\",goal=\"{}\"", escape_label(&stats.file), escape_label(goal));
+
+    let mut family = |name: &str, kind: &str, help: &str, samples: Vec<(String, String)>| {
+        metrics.push_str(&format!("# HELP secrust_{} {}\n# TYPE secrust_{} {}\n", name, help, name, kind));
+        for (labels, value) in samples {
+            match labels.is_empty() {
+                true => metrics.push_str(&format!("secrust_{} {}\n", name, value)),
+                false => metrics.push_str(&format!("secrust_{}{{{}}} {}\n", name, labels, value)),
+            }
+        }
+    };
+
+    family("functions_in_file", "gauge", "Functions with a body in the file.", runs.iter()
+        .map(|(stats, goal)| (file_labels(stats, goal), stats.functions_in_file.to_string()))
+        .collect());
+    family("functions_analyzed", "gauge", "Functions whose obligations were checked, by status.", runs.iter()
+        .flat_map(|(stats, goal)| [(Verdict::Valid, "verified"), (Verdict::Failed, "failed"), (Verdict::Unknown, "undecided")].map(|(verdict, status)| {
+            let count = stats.functions.iter().filter(|function| function.status() == verdict).count();
+            (format!("{},status=\"{}\"", file_labels(stats, goal), status), count.to_string())
+        }))
+        .collect());
+    family("functions_skipped", "gauge", "Functions left out by #[secured::skip] or [skip].", runs.iter()
+        .map(|(stats, goal)| (file_labels(stats, goal), stats.skipped.len().to_string()))
+        .collect());
+    family("obligations", "gauge", "Verification obligations, by status.", runs.iter()
+        .flat_map(|(stats, goal)| {
+            let (valid, failed, undecided) = stats.obligation_totals();
+            [("valid", valid), ("failed", failed), ("undecided", undecided)]
+                .map(|(status, count)| (format!("{},status=\"{}\"", file_labels(stats, goal), status), count.to_string()))
+        })
+        .collect());
+    family("basic_paths", "gauge", "Basic paths of the file's CFG.", runs.iter()
+        .map(|(stats, goal)| (file_labels(stats, goal), stats.basic_paths.to_string()))
+        .collect());
+    family("cfg_nodes", "gauge", "Nodes of the file's CFG.", runs.iter()
+        .map(|(stats, goal)| (file_labels(stats, goal), stats.cfg_size.0.to_string()))
+        .collect());
+    family("cfg_edges", "gauge", "Edges of the file's CFG.", runs.iter()
+        .map(|(stats, goal)| (file_labels(stats, goal), stats.cfg_size.1.to_string()))
+        .collect());
+    family("solver_seconds", "gauge", "Time spent deciding the obligations of the file.", runs.iter()
+        .map(|(stats, goal)| {
+            let total: f64 = stats.functions.iter().map(|function| function.solver_time.as_secs_f64()).sum();
+            (file_labels(stats, goal), format!("{:.6}", total))
+        })
+        .collect());
+
+    // Per function, for the functions whose proofs get slower or start failing
+    family("function_obligations", "gauge", "Verification obligations of a function, by status.", runs.iter()
+        .flat_map(|(stats, goal)| stats.functions.iter().flat_map(move |function| {
+            [("valid", function.valid), ("failed", function.failed), ("undecided", function.unknown)].map(|(status, count)| {
+                (format!("{},function=\"{}\",status=\"{}\"", file_labels(stats, goal), escape_label(&function.name), status), count.to_string())
+            })
+        }))
+        .collect());
+    family("function_solver_seconds", "gauge", "Time spent deciding the obligations of a function.", runs.iter()
+        .flat_map(|(stats, goal)| stats.functions.iter().map(move |function| {
+            (format!("{},function=\"{}\"", file_labels(stats, goal), escape_label(&function.name)), format!("{:.6}", function.solver_time.as_secs_f64()))
+        }))
+        .collect());
+
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|elapsed| elapsed.as_secs()).unwrap_or_default();
+    family("last_run_timestamp_seconds", "gauge", "When the metrics were written, in seconds since the Unix epoch.", vec![(String::new(), now.to_string())]);
+    metrics.push_str("# EOF\n");
+    metrics
+}
+
+// Backslashes, quotes and newlines are escaped in label values
+fn escape_label(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
@@ -0,0 +1,18 @@
+mod stats;
+mod manifest;
+mod console;
+mod layout;
+mod metrics;
+mod provenance;
+mod incremental;
+mod markdown;
+mod assumptions;
+
+pub use stats::*;
+pub use manifest::*;
+pub use console::*;
+pub use layout::*;
+pub use metrics::prometheus_metrics;
+pub use provenance::*;
+pub use incremental::*;
+pub use assumptions::*;
@@ -0,0 +1,118 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::report::manifest::fnv1a;
+
+// What a run's artifacts were built from, so they can be reproduced and a stale one told apart: written as a
+// comment header in DOT, SMT-LIB2 and HTML files, and as a 'provenance' field in the JSON ones
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Provenance {
+    pub tool: String, // 'secrust 0.1.0'
+    pub source: String,
+    pub source_hash: String, // FNV-1a 64 of the verified source, as the manifest hashes the artifacts
+    pub inputs: Vec<InputFile>, // conditions and settings files read by the run, those that exist
+    pub flags: Vec<String>, // command-line arguments of the run
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InputFile {
+    pub path: String,
+    pub hash: String,
+}
+
+impl Provenance {
+    pub fn new(source: String, content: &str, inputs: &[PathBuf], flags: Vec<String>) -> Self {
+        let inputs = inputs.iter()
+            .filter_map(|path| fs::read(path).ok().map(|content| InputFile {
+                path: path.display().to_string().replace('\\', "/"),
+                hash: format!("{:016x}", fnv1a(&content)),
+            }))
+            .collect();
+        Provenance {
+            tool: format!("secrust {}", env!("CARGO_PKG_VERSION")),
+            source,
+            source_hash: format!("{:016x}", fnv1a(content.as_bytes())),
+            inputs,
+            flags,
+        }
+    }
+
+    // The header lines, without comment markers
+    pub fn lines(&self) -> Vec<String> {
+        let mut lines = vec![
+            format!("Generated by {}", self.tool),
+            format!("source: {} ({})", self.source, self.source_hash),
+        ];
+        if !self.inputs.is_empty() {
+            let inputs: Vec<String> = self.inputs.iter().map(|input| format!("{} ({})", input.path, input.hash)).collect();
+            lines.push(format!("inputs: {}", inputs.join(", ")));
+        }
+        if !self.flags.is_empty() {
+            lines.push(format!("flags: {}", self.flags.join(" ")));
+        }
+        lines
+    }
+
+    // Add the provenance to a written artifact, in the comment syntax of its format. JSON files get the field
+    // when they hold an object; other formats are left as they are.
+    pub fn stamp(&self, file: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        let extension = file.extension().and_then(|extension| extension.to_str()).unwrap_or_default();
+        let content = fs::read_to_string(file)?;
+        let stamped = match extension {
+            // Added as the last field of the text, so the others keep their order
+            "json" => {
+                if !serde_json::from_str::<serde_json::Value>(&content)?.is_object() {
+                    return Ok(());
+                }
+                let field = serde_json::to_string_pretty(self)?.replace('\n', "\n  ");
+                let body = content.trim_end().strip_suffix('}').unwrap_or_default().trim_end();
+                match body {
+                    "{" => format!("{{\n  \"provenance\": {}\n}}", field),
+                    _ => format!("{},\n  \"provenance\": {}\n}}", body, field),
+                }
+            },
+            "dot" => self.comment_header("// ", "") + &content,
+            "smt2" | "proof" => self.comment_header("; ", "") + &content,
+            // After the doctype, which must come first
+            "html" => match content.split_once('\n').filter(|(first, _)| first.starts_with("<!DOCTYPE")) {
+                Some((doctype, rest)) => format!("{}\n{}{}", doctype, self.comment_header("<!-- ", " -->"), rest),
+                None => self.comment_header("<!-- ", " -->") + &content,
+            },
+            _ => return Ok(()),
+        };
+        fs::write(file, stamped)?;
+        Ok(())
+    }
+
+    fn comment_header(&self, open: &str, close: &str) -> String {
+        // An HTML comment ends at the first '-->'
+        self.lines().iter()
+            .map(|line| if close.is_empty() { line.clone() } else { line.replace("-->", "- ->") })
+            .map(|line| format!("{}{}{}\n", open, line, close))
+            .collect()
+    }
+
+    // What changed since the run that made 'previous', for the artifacts it wrote that this run didn't. Other
+    // flags don't make them stale: they only select other artifacts.
+    pub fn changes_since(&self, previous: &Provenance) -> Vec<String> {
+        let mut changes = Vec::new();
+        if self.tool != previous.tool {
+            changes.push(format!("built by {}", previous.tool));
+        }
+        if self.source_hash != previous.source_hash {
+            changes.push(format!("{} changed", self.source));
+        }
+        for input in &self.inputs {
+            match previous.inputs.iter().find(|old| old.path == input.path) {
+                Some(old) if old.hash == input.hash => {},
+                Some(_) => changes.push(format!("{} changed", input.path)),
+                None => changes.push(format!("{} added", input.path)),
+            }
+        }
+        for old in previous.inputs.iter().filter(|old| !self.inputs.iter().any(|input| input.path == old.path)) {
+            changes.push(format!("{} removed", old.path));
+        }
+        changes
+    }
+}
@@ -0,0 +1,323 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::time::Duration;
+use syn::visit::{self, Visit};
+
+use crate::verifier::VcOutcome;
+
+// Verdict of one obligation, or of a whole function
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Verdict {
+    Valid,
+    Failed,
+    Unknown,
+}
+
+impl From<&VcOutcome> for Verdict {
+    fn from(outcome: &VcOutcome) -> Self {
+        match outcome {
+            VcOutcome::Valid => Verdict::Valid,
+            VcOutcome::Counterexample(_) => Verdict::Failed,
+            VcOutcome::Unknown => Verdict::Unknown,
+        }
+    }
+}
+
+impl Verdict {
+    pub fn describe(&self) -> &'static str {
+        match self {
+            Verdict::Valid => "proved",
+            Verdict::Failed => "failed",
+            Verdict::Unknown => "undecided",
+        }
+    }
+
+    // Outcome standing for the verdict of an earlier run, which doesn't keep the model of a failure
+    pub fn outcome(&self) -> VcOutcome {
+        match self {
+            Verdict::Valid => VcOutcome::Valid,
+            Verdict::Failed => VcOutcome::Counterexample(Default::default()),
+            Verdict::Unknown => VcOutcome::Unknown,
+        }
+    }
+}
+
+// Outcome of one basic path, reported under the annotation it ends at
+#[derive(Debug, Clone)]
+pub struct PathResult {
+    pub path: usize, // number of the path in the run output
+    pub from: String, // annotation the path starts at
+    pub exit: Option<String>, // how the path leaves the function, for postconditions
+    pub verdict: Verdict,
+}
+
+// Basic paths discharging one annotation
+#[derive(Debug, Clone)]
+pub struct AnnotationResult {
+    pub id: String, // node ID of the annotation
+    pub annotation: String, // e.g. 'post: sum >= 0'
+    pub paths: Vec<PathResult>,
+}
+
+impl AnnotationResult {
+    fn count(&self, verdict: Verdict) -> usize {
+        self.paths.iter().filter(|p| p.verdict == verdict).count()
+    }
+
+    // '4 paths: 3 proved, 1 failed'
+    pub fn summary(&self) -> String {
+        let counts: Vec<String> = [Verdict::Valid, Verdict::Failed, Verdict::Unknown].iter()
+            .map(|&verdict| (self.count(verdict), verdict))
+            .filter(|&(count, _)| count > 0)
+            .map(|(count, verdict)| format!("{} {}", count, verdict.describe()))
+            .collect();
+        let plural = if self.paths.len() == 1 { "" } else { "s" };
+        format!("{} path{}: {}", self.paths.len(), plural, counts.join(", "))
+    }
+}
+
+// Obligation counts of one analyzed function
+#[derive(Debug, Clone)]
+pub struct FunctionResult {
+    pub id: String, // canonical identifier, 'module::Type::name#<signature hash>'
+    pub name: String,
+    pub valid: usize,
+    pub failed: usize,
+    pub unknown: usize,
+    pub annotations: Vec<AnnotationResult>, // basic paths grouped by the annotation they discharge
+    pub slowest: Option<(String, Duration)>, // obligation that took the solver the longest, e.g. 'Path 3'
+    pub solver_time: Duration, // time of all its obligations
+}
+
+impl FunctionResult {
+    fn new(id: String, name: String) -> Self {
+        FunctionResult { id, name, valid: 0, failed: 0, unknown: 0, annotations: Vec::new(), slowest: None, solver_time: Duration::ZERO }
+    }
+
+    // A function is verified when all its obligations are valid
+    pub fn status(&self) -> Verdict {
+        if self.failed > 0 {
+            Verdict::Failed
+        } else if self.unknown > 0 {
+            Verdict::Unknown
+        } else {
+            Verdict::Valid
+        }
+    }
+}
+
+// Statistics of a verification run, written to 'verified.json'
+#[derive(Debug, Clone)]
+pub struct VerificationStats {
+    pub file: String,
+    pub functions_in_file: usize,
+    pub functions: Vec<FunctionResult>, // the analyzed (annotated) functions
+    pub skipped: Vec<(String, String)>, // functions left out by '#[secured::skip]' or '[skip]', and why
+    pub cfg_size: (usize, usize), // nodes and edges of the file's CFG
+    pub basic_paths: usize,
+    pub shebang: Option<String>, // '#!/usr/bin/env rust-script' of a script
+    pub attributes: Vec<String>, // inner attributes of the file, '#![allow(unused)]'
+}
+
+// Counts the functions with a body: free functions, methods and trait default methods
+struct FunctionCounter {
+    count: usize,
+}
+
+impl<'ast> Visit<'ast> for FunctionCounter {
+    fn visit_item_fn(&mut self, i: &'ast syn::ItemFn) {
+        self.count += 1;
+        visit::visit_item_fn(self, i);
+    }
+
+    fn visit_impl_item_method(&mut self, i: &'ast syn::ImplItemMethod) {
+        self.count += 1;
+        visit::visit_impl_item_method(self, i);
+    }
+
+    fn visit_trait_item_method(&mut self, i: &'ast syn::TraitItemMethod) {
+        if i.default.is_some() {
+            self.count += 1;
+        }
+        visit::visit_trait_item_method(self, i);
+    }
+}
+
+impl VerificationStats {
+    // 'analyzed_functions' are the identifiers and names of the analyzed functions
+    pub fn new(file: String, ast: &syn::File, analyzed_functions: Vec<(String, String)>) -> Self {
+        let mut counter = FunctionCounter { count: 0 };
+        counter.visit_file(ast);
+        VerificationStats {
+            file,
+            functions_in_file: counter.count,
+            functions: analyzed_functions.into_iter()
+                .map(|(id, name)| FunctionResult::new(id, name))
+                .collect(),
+            skipped: Vec::new(),
+            cfg_size: (0, 0),
+            basic_paths: 0,
+            shebang: ast.shebang.clone(),
+            attributes: Vec::new(),
+        }
+    }
+
+    pub fn record(&mut self, function: &str, verdict: Verdict) {
+        let result = self.function_result(function);
+        match verdict {
+            Verdict::Valid => result.valid += 1,
+            Verdict::Failed => result.failed += 1,
+            Verdict::Unknown => result.unknown += 1,
+        }
+    }
+
+    // Record how long the solver took for an obligation, keeping the slowest one of the function
+    pub fn record_time(&mut self, function: &str, obligation: String, elapsed: Duration) {
+        let result = self.function_result(function);
+        result.solver_time += elapsed;
+        if result.slowest.as_ref().is_none_or(|(_, slowest)| elapsed > *slowest) {
+            result.slowest = Some((obligation, elapsed));
+        }
+    }
+
+    fn function_result(&mut self, function: &str) -> &mut FunctionResult {
+        if let Some(index) = self.functions.iter().position(|f| f.id == function) {
+            return &mut self.functions[index];
+        }
+        let name = function.split('#').next().unwrap_or(function).to_string();
+        self.functions.push(FunctionResult::new(function.to_string(), name));
+        self.functions.last_mut().unwrap()
+    }
+
+    // Record the outcome of a basic path under the annotation it ends at ('id' and its label)
+    pub fn record_path(&mut self, function: &str, id: &str, annotation: &str, result: PathResult) {
+        self.record(function, result.verdict);
+        let function = self.functions.iter_mut().find(|f| f.id == function).expect("function recorded above");
+        match function.annotations.iter_mut().find(|a| a.id == id) {
+            Some(group) => group.paths.push(result),
+            None => function.annotations.push(AnnotationResult {
+                id: id.to_string(),
+                annotation: annotation.to_string(),
+                paths: vec![result],
+            }),
+        }
+    }
+
+    // Outcomes grouped by function and annotation, with the paths of each annotation below it
+    pub fn obligations_by_annotation(&self) -> String {
+        let mut report = String::from("Obligations by annotation:\n");
+        for function in self.functions.iter().filter(|f| !f.annotations.is_empty()) {
+            report.push_str(&format!("  {}\n", function.name));
+            for group in &function.annotations {
+                report.push_str(&format!("    {} — {}\n", group.annotation, group.summary()));
+                for path in &group.paths {
+                    let exit = path.exit.as_ref().map(|exit| format!(" (exit: {})", exit)).unwrap_or_default();
+                    report.push_str(&format!("      Path {} from {}{}: {}\n", path.path, path.from, exit, path.verdict.describe()));
+                }
+            }
+        }
+        report
+    }
+
+    fn count_functions(&self, verdict: Verdict) -> usize {
+        self.functions.iter().filter(|f| f.status() == verdict).count()
+    }
+
+    fn count_obligations(&self, count: impl Fn(&FunctionResult) -> usize) -> usize {
+        self.functions.iter().map(count).sum()
+    }
+
+    // Valid, failed and undecided obligations of all the functions
+    pub fn obligation_totals(&self) -> (usize, usize, usize) {
+        (self.count_obligations(|f| f.valid), self.count_obligations(|f| f.failed), self.count_obligations(|f| f.unknown))
+    }
+
+    pub fn one_liner(&self) -> String {
+        format!(
+            "Verification summary: {}/{} annotated functions verified, {} failed, {} undecided ({} functions in file); obligations: {} valid, {} failed, {} undecided",
+            self.count_functions(Verdict::Valid),
+            self.functions.len(),
+            self.count_functions(Verdict::Failed),
+            self.count_functions(Verdict::Unknown),
+            self.functions_in_file,
+            self.count_obligations(|f| f.valid),
+            self.count_obligations(|f| f.failed),
+            self.count_obligations(|f| f.unknown),
+        )
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "file": self.file,
+            "shebang": self.shebang,
+            "attributes": self.attributes,
+            "functions": {
+                "in_file": self.functions_in_file,
+                "annotated": self.functions.len(),
+                "verified": self.count_functions(Verdict::Valid),
+                "failed": self.count_functions(Verdict::Failed),
+                "undecided": self.count_functions(Verdict::Unknown),
+            },
+            "obligations": {
+                "valid": self.count_obligations(|f| f.valid),
+                "failed": self.count_obligations(|f| f.failed),
+                "undecided": self.count_obligations(|f| f.unknown),
+            },
+            "results": self.functions.iter().map(Self::result_json).collect::<Vec<_>>(),
+            "skipped": self.skipped.iter().map(|(function, reason)| json!({
+                "function": function,
+                "reason": reason,
+            })).collect::<Vec<_>>(),
+        })
+    }
+
+    // Report of one function, written to 'report.json' in its output directory
+    pub fn function_json(&self, function: &str) -> Option<Value> {
+        let result = self.functions.iter().find(|f| f.id == function)?;
+        Some(json!({
+            "file": self.file,
+            "function": Self::result_json(result),
+        }))
+    }
+
+    fn result_json(f: &FunctionResult) -> Value {
+        json!({
+            "id": f.id,
+            "name": f.name,
+            "status": f.status(),
+            "valid": f.valid,
+            "failed": f.failed,
+            "undecided": f.unknown,
+            "annotations": f.annotations.iter().map(|a| json!({
+                "id": a.id,
+                "annotation": a.annotation,
+                "summary": a.summary(),
+                "paths": a.paths.iter().map(|p| json!({
+                    "path": p.path,
+                    "from": p.from,
+                    "exit": p.exit,
+                    "status": p.verdict,
+                })).collect::<Vec<_>>(),
+            })).collect::<Vec<_>>(),
+        })
+    }
+
+    // shields.io endpoint badge ('https://img.shields.io/endpoint?url=<url of badge.json>')
+    pub fn badge_json(&self) -> Value {
+        let verified = self.count_functions(Verdict::Valid);
+        let color = if self.count_functions(Verdict::Failed) > 0 {
+            "red"
+        } else if verified < self.functions.len() {
+            "yellow"
+        } else {
+            "brightgreen"
+        };
+        json!({
+            "schemaVersion": 1,
+            "label": "verified",
+            "message": format!("{}/{} functions", verified, self.functions.len()),
+            "color": color,
+        })
+    }
+}
@@ -0,0 +1,4 @@
+mod smtlib;
+mod path_scripts;
+
+pub use smtlib::*;
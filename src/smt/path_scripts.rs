@@ -0,0 +1,56 @@
+use std::collections::HashSet;
+
+use petgraph::graph::NodeIndex;
+
+use crate::cfg_builder::builder::CfgBuilder;
+use crate::smt::SmtTranslation;
+use crate::wp_calculus::ASSUMPTION_TAG;
+
+impl CfgBuilder {
+    // SMT-LIB2 script of the verification condition of a basic path, for '--smt-dir': each assumption of the
+    // path asserted under the ID of the node it comes from, then the negation of the annotation it ends at,
+    // so that 'unsat' means the path is proved and the unsat core names the assumptions the proof needs.
+    // 'number' is the one of the run output ('Path 3'). Conditions outside the integer and boolean theories
+    // (logic functions, '%', method calls) give an error naming the construct.
+    pub fn path_smtlib(&self, number: usize, path: &[NodeIndex]) -> Result<String, String> {
+        let condition = self.tagged_wp(path).ok_or("the path has no verification condition")?;
+        let mut parts = Vec::new();
+        Self::implication_parts(&condition, &mut parts);
+        let goal = parts.pop().ok_or("the path has no verification condition")?;
+
+        let mut translation = SmtTranslation::default();
+        let mut names = HashSet::new();
+        let mut assertions = String::new();
+        for (position, part) in parts.into_iter().enumerate() {
+            let (tag, assumption) = Self::untag_assumption(part);
+            let node = tag.as_deref()
+                .and_then(|tag| tag.strip_prefix(ASSUMPTION_TAG))
+                .and_then(|index| index.parse().ok())
+                .map(NodeIndex::new);
+            let base = node.map(|node| self.node_id(node)).unwrap_or_else(|| format!("hypothesis_{}", position + 1));
+            // A node crossed twice gives two assertions
+            let mut name = base.clone();
+            let mut suffix = 2;
+            while !names.insert(name.clone()) {
+                name = format!("{}#{}", base, suffix);
+                suffix += 1;
+            }
+            assertions.push_str(&format!("; {}\n", self.render_condition(&assumption)));
+            assertions.push_str(&format!("(assert (! {} :named {}))\n", translation.formula(&assumption)?, SmtTranslation::symbol(&name)));
+        }
+        let negated_goal = format!("(assert (not {}))\n", translation.formula(&goal)?);
+
+        let function = path.first().and_then(|&start| self.function_of_node(start)).unwrap_or_default();
+        let target = self.path_target(path).map(|(_, label)| label).unwrap_or_default();
+        let mut script = format!("; Path {} of {}: {} -> {}\n", number, function, self.annotation_label(path[0]), target);
+        script.push_str("; unsat: the path is proved; sat: the model is a counterexample\n");
+        script.push_str("(set-option :produce-unsat-cores true)\n");
+        script.push_str(&format!("(set-logic {})\n", translation.logic()));
+        script.push_str(&translation.declarations());
+        script.push_str(&assertions);
+        script.push_str(&format!("; goal: {}\n", self.render_condition(&goal)));
+        script.push_str(&negated_goal);
+        script.push_str("(check-sat)\n(exit)\n");
+        Ok(script)
+    }
+}
@@ -0,0 +1,220 @@
+use std::collections::BTreeSet;
+
+use syn::{BinOp, Expr, ExprBinary, ExprCall, ExprLit, ExprPath, ExprUnary, Lit, UnOp};
+
+use crate::cfg_builder::builder::CfgBuilder;
+use crate::spec_parser::{Quantifier, QuantifierKind};
+use crate::wp_calculus::{ARRAY_STORE, ASSUMPTION_TAG};
+
+// Sort of an SMT-LIB2 term. The variables of the conditions are integers, as for the solver.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SmtSort {
+    Int,
+    Bool,
+}
+
+impl SmtSort {
+    fn name(&self) -> &'static str {
+        match self {
+            SmtSort::Int => "Int",
+            SmtSort::Bool => "Bool",
+        }
+    }
+}
+
+// Symbols of SMT-LIB2 that are valid Rust identifiers; a variable of that name is written '|exists|'
+const RESERVED_SYMBOLS: [&str; 10] = ["par", "exists", "forall", "NUMERAL", "DECIMAL", "STRING", "BINARY", "HEXADECIMAL", "Int", "Bool"];
+
+// Translation of conditions to SMT-LIB2 terms, in the integer and boolean theories and the arrays from
+// integers to integers, without a solver. It collects the constants the terms use and whether they need
+// nonlinear arithmetic or quantifiers, to declare them and pick the logic of the script.
+#[derive(Debug, Default)]
+pub struct SmtTranslation {
+    pub constants: BTreeSet<String>,
+    pub arrays: BTreeSet<String>,
+    pub nonlinear: bool, // a product or quotient of two non-constant terms
+    pub quantified: bool,
+    bound: Vec<String>, // variables of the quantifiers the term is in, which aren't constants
+}
+
+impl SmtTranslation {
+    // 'x + 1 > y' gives '(> (+ x 1) y)'
+    pub fn formula(&mut self, expr: &Expr) -> Result<String, String> {
+        self.term(expr, SmtSort::Bool)
+    }
+
+    // 'QF_LIA', 'QF_NIA' once a term multiplies two variables, without 'QF_' with a quantifier and with
+    // 'A' with an array: 'ALIA'
+    pub fn logic(&self) -> String {
+        format!("{}{}{}", if self.quantified { "" } else { "QF_" }, if self.arrays.is_empty() { "" } else { "A" }, if self.nonlinear { "NIA" } else { "LIA" })
+    }
+
+    pub fn declarations(&self) -> String {
+        let arrays = self.arrays.iter().map(|name| format!("(declare-const {} (Array Int Int))\n", Self::symbol(name)));
+        self.constants.iter().map(|name| format!("(declare-const {} Int)\n", Self::symbol(name))).chain(arrays).collect()
+    }
+
+    // A name as an SMT-LIB2 symbol, quoted unless it is a simple symbol ('self.len' is one)
+    pub fn symbol(name: &str) -> String {
+        let simple = name.chars().all(|c| c.is_ascii_alphanumeric() || "~!@$%^&*_-+=<>.?/".contains(c))
+            && !name.starts_with(|c: char| c.is_ascii_digit())
+            && !RESERVED_SYMBOLS.contains(&name);
+        if simple && !name.is_empty() { name.to_string() } else { format!("|{}|", name.replace(['|', '\\'], "_")) }
+    }
+
+    fn term(&mut self, expr: &Expr, expected: SmtSort) -> Result<String, String> {
+        let (term, sort) = self.translate(expr)?;
+        if sort != expected {
+            return Err(format!("'{}' is {}, expected {}", CfgBuilder::render_expr(expr), sort.name(), expected.name()));
+        }
+        Ok(term)
+    }
+
+    fn translate(&mut self, expr: &Expr) -> Result<(String, SmtSort), String> {
+        match expr {
+            Expr::Macro(expr_macro) => {
+                let name = expr_macro.mac.path.segments.last().map(|segment| segment.ident.to_string()).unwrap_or_default();
+                // Assumptions nested in the condition keep the tag of their node, which only the solver uses
+                if !["pre", "post", "invariant"].contains(&name.as_str()) && !name.starts_with(ASSUMPTION_TAG) {
+                    return Err(format!("'{}!' is outside the integer and boolean theories", name));
+                }
+                let inner = syn::parse2::<Expr>(expr_macro.mac.tokens.clone()).map_err(|e| format!("'{}!': {}", name, e))?;
+                self.translate(&inner)
+            },
+            Expr::Lit(ExprLit { lit: Lit::Int(lit_int), .. }) => match lit_int.suffix() {
+                "" => Ok((lit_int.base10_digits().to_string(), SmtSort::Int)),
+                suffix => Ok((Self::wrap(lit_int.base10_digits(), suffix)?, SmtSort::Int)),
+            },
+            Expr::Unary(ExprUnary { op: UnOp::Neg(_), expr, .. }) if crate::verifier::suffixed_integer_literal(expr).is_some() => {
+                let (digits, suffix) = crate::verifier::suffixed_integer_literal(expr).expect("checked by the guard");
+                Ok((Self::wrap(&format!("(- {})", digits), &suffix)?, SmtSort::Int))
+            },
+            Expr::Lit(ExprLit { lit: Lit::Bool(lit_bool), .. }) => Ok((lit_bool.value.to_string(), SmtSort::Bool)),
+            Expr::Paren(paren) => self.translate(&paren.expr),
+            // Integers are unbounded here, as for the solver: a cast wraps the value around into its type
+            Expr::Cast(cast) => {
+                let term = match self.translate(&cast.expr)? {
+                    (term, SmtSort::Int) => term,
+                    (term, SmtSort::Bool) => format!("(ite {} 1 0)", term),
+                };
+                Ok((Self::wrap(&term, &CfgBuilder::render_type(&cast.ty))?, SmtSort::Int))
+            },
+            Expr::Path(_) | Expr::Field(_) => match crate::verifier::field_place_name(expr) {
+                Some(name) => {
+                    let symbol = Self::symbol(&name);
+                    if !self.bound.contains(&name) {
+                        self.constants.insert(name);
+                    }
+                    Ok((symbol, SmtSort::Int))
+                },
+                None => Err(format!("unsupported place '{}'", CfgBuilder::render_expr(expr))),
+            },
+            Expr::Unary(ExprUnary { op: UnOp::Not(_), expr, .. }) => Ok((format!("(not {})", self.term(expr, SmtSort::Bool)?), SmtSort::Bool)),
+            Expr::Unary(ExprUnary { op: UnOp::Neg(_), expr, .. }) => Ok((format!("(- {})", self.term(expr, SmtSort::Int)?), SmtSort::Int)),
+            Expr::Binary(binary) if matches!(binary.op, BinOp::Shr(_)) => {
+                // 'a >> b >> c' is the chain 'a => (b => c)'
+                let mut parts = Vec::new();
+                CfgBuilder::implication_parts(expr, &mut parts);
+                let mut parts = parts.iter().map(|part| self.term(part, SmtSort::Bool)).collect::<Result<Vec<_>, _>>()?;
+                let goal = parts.pop().unwrap_or_else(|| "true".to_string());
+                Ok((parts.into_iter().rev().fold(goal, |goal, hypothesis| format!("(=> {} {})", hypothesis, goal)), SmtSort::Bool))
+            },
+            Expr::Binary(binary) => self.binary(binary),
+            Expr::Index(index) => Ok((format!("(select {} {})", self.array(&index.expr)?, self.term(&index.index, SmtSort::Int)?), SmtSort::Int)),
+            Expr::Call(call) => match Quantifier::of(expr) {
+                Some(quantifier) => self.quantifier(&quantifier),
+                None => Err(format!("call of '{}' is outside the integer and boolean theories", CfgBuilder::render_expr(&call.func))),
+            },
+            other => Err(format!("'{}' is outside the integer and boolean theories", CfgBuilder::render_expr(other))),
+        }
+    }
+
+    // '(forall ((i Int)) (=> (and (>= i a) (< i b)) body))', '(exists ((i Int)) (and .. body))'
+    fn quantifier(&mut self, quantifier: &Quantifier) -> Result<(String, SmtSort), String> {
+        self.quantified = true;
+        let variable = quantifier.variable.to_string();
+        self.bound.push(variable.clone());
+        let guard = self.term(&quantifier.guard(), SmtSort::Bool);
+        let body = self.term(&quantifier.body, SmtSort::Bool);
+        self.bound.pop();
+        let (guard, body) = (guard?, body?);
+        let (keyword, connective) = match quantifier.kind {
+            QuantifierKind::Forall => ("forall", "=>"),
+            QuantifierKind::Exists => ("exists", "and"),
+        };
+        Ok((format!("({} (({} Int)) ({} {} {}))", keyword, Self::symbol(&variable), connective, guard, body), SmtSort::Bool))
+    }
+
+    // An array term, as for the solver: a variable, 'array_store(v, i, x)' or 'vec![x; n]'
+    fn array(&mut self, expr: &Expr) -> Result<String, String> {
+        match expr {
+            Expr::Paren(paren) => self.array(&paren.expr),
+            Expr::Call(ExprCall { func, args, .. }) if matches!(&**func, Expr::Path(ExprPath { path, .. }) if path.is_ident(ARRAY_STORE)) && args.len() == 3 => {
+                Ok(format!("(store {} {} {})", self.array(&args[0])?, self.term(&args[1], SmtSort::Int)?, self.term(&args[2], SmtSort::Int)?))
+            },
+            Expr::Macro(expr_macro) if expr_macro.mac.path.is_ident("vec") => {
+                let tokens = &expr_macro.mac.tokens;
+                let repeat = syn::parse2::<syn::ExprRepeat>(quote::quote!([#tokens]))
+                    .map_err(|_| format!("'{}' is not an array of one repeated value", CfgBuilder::render_expr(expr)))?;
+                Ok(format!("((as const (Array Int Int)) {})", self.term(&repeat.expr, SmtSort::Int)?))
+            },
+            _ => match crate::verifier::field_place_name(expr) {
+                Some(name) => {
+                    let symbol = Self::symbol(&name);
+                    self.arrays.insert(name);
+                    Ok(symbol)
+                },
+                None => Err(format!("unsupported array '{}'", CfgBuilder::render_expr(expr))),
+            },
+        }
+    }
+
+    fn binary(&mut self, binary: &ExprBinary) -> Result<(String, SmtSort), String> {
+        let (operator, operands, sort) = match binary.op {
+            BinOp::And(_) => ("and", SmtSort::Bool, SmtSort::Bool),
+            BinOp::Or(_) => ("or", SmtSort::Bool, SmtSort::Bool),
+            BinOp::Lt(_) => ("<", SmtSort::Int, SmtSort::Bool),
+            BinOp::Le(_) => ("<=", SmtSort::Int, SmtSort::Bool),
+            BinOp::Gt(_) => (">", SmtSort::Int, SmtSort::Bool),
+            BinOp::Ge(_) => (">=", SmtSort::Int, SmtSort::Bool),
+            BinOp::Add(_) => ("+", SmtSort::Int, SmtSort::Int),
+            BinOp::Sub(_) => ("-", SmtSort::Int, SmtSort::Int),
+            BinOp::Mul(_) => ("*", SmtSort::Int, SmtSort::Int),
+            // Same division as the solver's, 'div' of the integer theory
+            BinOp::Div(_) => ("div", SmtSort::Int, SmtSort::Int),
+            // Both sides of an equality have the sort of the left one
+            BinOp::Eq(_) | BinOp::Ne(_) => {
+                let (left, left_sort) = self.translate(&binary.left)?;
+                let right = self.term(&binary.right, left_sort)?;
+                let operator = if matches!(binary.op, BinOp::Eq(_)) { "=" } else { "distinct" };
+                return Ok((format!("({} {} {})", operator, left, right), SmtSort::Bool));
+            },
+            op => return Err(format!("operator '{}' is outside the integer and boolean theories", quote::quote!(#op))),
+        };
+        if matches!(binary.op, BinOp::Mul(_) | BinOp::Div(_)) && !Self::is_numeral(&binary.right) && (!Self::is_numeral(&binary.left) || matches!(binary.op, BinOp::Div(_))) {
+            self.nonlinear = true;
+        }
+        let left = self.term(&binary.left, operands)?;
+        let right = self.term(&binary.right, operands)?;
+        Ok((format!("({} {} {})", operator, left, right), sort))
+    }
+
+    // 'x as u8' gives '(+ (mod (- x 0) 256) 0)'
+    fn wrap(term: &str, ty: &str) -> Result<String, String> {
+        let (min, modulus) = CfgBuilder::integer_wrap(ty).ok_or_else(|| format!("cast to '{}' is outside the integer and boolean theories", ty))?;
+        let min = match min.strip_prefix('-') {
+            Some(digits) => format!("(- {})", digits),
+            None => min.to_string(),
+        };
+        Ok(format!("(+ (mod (- {} {}) {}) {})", term, min, modulus, min))
+    }
+
+    // '3', '-3' or '(3)': a factor that keeps a product linear
+    fn is_numeral(expr: &Expr) -> bool {
+        match expr {
+            Expr::Lit(ExprLit { lit: Lit::Int(_), .. }) => true,
+            Expr::Unary(ExprUnary { op: UnOp::Neg(_), expr, .. }) | Expr::Paren(syn::ExprParen { expr, .. }) | Expr::Cast(syn::ExprCast { expr, .. }) => Self::is_numeral(expr),
+            _ => false,
+        }
+    }
+}
@@ -0,0 +1,71 @@
+use proc_macro2::{Group, Spacing, TokenStream, TokenTree};
+use quote::quote;
+use syn::{Expr, Lit};
+
+use crate::spec_parser::QuantifierKind;
+
+// Annotation conditions are Rust expressions, with bounded quantifiers over the integers of a range:
+// 'forall i in a..b: condition' and 'exists i in a..b: condition'. The condition of a quantifier extends
+// to the end of the parentheses it is in, as the body of a closure does. A quantifier is lowered to the
+// call 'forall(a..b, |i| condition)', which can be written directly too.
+
+// Condition of an annotation macro, written as an expression or as a string ('pre!("n >= 0")')
+pub fn annotation_condition(tokens: &TokenStream) -> syn::Result<Expr> {
+    if let Ok(Expr::Lit(syn::ExprLit { lit: Lit::Str(lit_str), .. })) = syn::parse2::<Expr>(tokens.clone()) {
+        // Parsed from the literal, so that the tokens keep its location
+        return lit_str.parse_with(|input: syn::parse::ParseStream| parse_condition_tokens(input.parse()?));
+    }
+    parse_condition_tokens(tokens.clone())
+}
+
+// 'forall i in 0..n: v[i] >= 0' gives 'forall(0..n, |i| v[i] >= 0)'
+pub fn parse_condition(text: &str) -> syn::Result<Expr> {
+    parse_condition_tokens(syn::parse_str(text)?)
+}
+
+pub fn parse_condition_tokens(tokens: TokenStream) -> syn::Result<Expr> {
+    syn::parse2(lower_quantifiers(tokens)?)
+}
+
+fn lower_quantifiers(tokens: TokenStream) -> syn::Result<TokenStream> {
+    let trees: Vec<TokenTree> = tokens.into_iter().collect();
+    let mut lowered = TokenStream::new();
+    for (i, tree) in trees.iter().enumerate() {
+        match tree {
+            // 'forall' followed by a name starts a quantifier; 'forall(..)' is one already
+            TokenTree::Ident(keyword) if QuantifierKind::from_keyword(&keyword.to_string()).is_some() && matches!(trees.get(i + 1), Some(TokenTree::Ident(_))) => {
+                let variable = &trees[i + 1];
+                if !matches!(trees.get(i + 2), Some(TokenTree::Ident(in_token)) if in_token == "in") {
+                    return Err(syn::Error::new(variable.span(), format!("expected '{} {} in a..b: condition'", keyword, variable)));
+                }
+                let Some(colon) = (i + 3..trees.len()).find(|&j| is_single_colon(&trees, j)) else {
+                    return Err(syn::Error::new(keyword.span(), format!("'{} {} in ..' needs ':' before its condition", keyword, variable)));
+                };
+                let range: TokenStream = trees[i + 3..colon].iter().cloned().collect();
+                match syn::parse2::<Expr>(range.clone())? {
+                    Expr::Range(syn::ExprRange { from: Some(_), to: Some(_), .. }) => {},
+                    _ => return Err(syn::Error::new_spanned(range, format!("the range of '{} {}' needs both bounds, as in 'a..b' or 'a..=b'", keyword, variable))),
+                }
+                let body = lower_quantifiers(trees[colon + 1..].iter().cloned().collect())?;
+                if body.is_empty() {
+                    return Err(syn::Error::new(trees[colon].span(), format!("'{} {} in ..' needs a condition after ':'", keyword, variable)));
+                }
+                lowered.extend(quote!(#keyword(#range, |#variable| #body)));
+                return Ok(lowered);
+            },
+            TokenTree::Group(group) => {
+                let mut inner = Group::new(group.delimiter(), lower_quantifiers(group.stream())?);
+                inner.set_span(group.span());
+                lowered.extend([TokenTree::Group(inner)]);
+            },
+            tree => lowered.extend([tree.clone()]),
+        }
+    }
+    Ok(lowered)
+}
+
+// ':' on its own, not a part of '::'
+fn is_single_colon(trees: &[TokenTree], j: usize) -> bool {
+    let is_colon = |tree: &TokenTree, spacing: Spacing| matches!(tree, TokenTree::Punct(punct) if punct.as_char() == ':' && punct.spacing() == spacing);
+    is_colon(&trees[j], Spacing::Alone) && !(j > 0 && is_colon(&trees[j - 1], Spacing::Joint))
+}
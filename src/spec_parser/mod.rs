@@ -0,0 +1,5 @@
+mod grammar;
+mod quantifier;
+
+pub use grammar::*;
+pub use quantifier::*;
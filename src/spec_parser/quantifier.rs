@@ -0,0 +1,102 @@
+use proc_macro2::{TokenStream, TokenTree};
+use syn::{Expr, ExprRange, Pat};
+
+use crate::cfg_builder::builder::CfgBuilder;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum QuantifierKind {
+    Forall, // the condition holds for every integer of the range
+    Exists, // for at least one
+}
+
+impl QuantifierKind {
+    pub fn from_keyword(keyword: &str) -> Option<Self> {
+        match keyword {
+            "forall" => Some(QuantifierKind::Forall),
+            "exists" => Some(QuantifierKind::Exists),
+            _ => None,
+        }
+    }
+
+    pub fn keyword(&self) -> &'static str {
+        match self {
+            QuantifierKind::Forall => "forall",
+            QuantifierKind::Exists => "exists",
+        }
+    }
+}
+
+// Bounded quantifier of a condition, held as the call 'forall(a..b, |i| condition)'
+#[derive(Debug, Clone)]
+pub struct Quantifier {
+    pub kind: QuantifierKind,
+    pub variable: syn::Ident,
+    pub range: ExprRange, // with both bounds
+    pub body: Expr,
+}
+
+impl Quantifier {
+    pub fn of(expr: &Expr) -> Option<Quantifier> {
+        let Expr::Call(call) = expr else { return None };
+        let Expr::Path(func) = &*call.func else { return None };
+        let kind = QuantifierKind::from_keyword(&func.path.get_ident()?.to_string())?;
+        let (Some(Expr::Range(range)), Some(Expr::Closure(closure)), 2) = (call.args.first(), call.args.iter().nth(1), call.args.len()) else { return None };
+        let (Some(_), Some(_)) = (&range.from, &range.to) else { return None };
+        let [Pat::Ident(pat_ident)] = closure.inputs.iter().collect::<Vec<_>>()[..] else { return None };
+        Some(Quantifier { kind, variable: pat_ident.ident.clone(), range: range.clone(), body: (*closure.body).clone() })
+    }
+
+    // 'i >= a && i < b': the values of the variable the quantifier ranges over
+    pub fn guard(&self) -> Expr {
+        let variable = &self.variable;
+        CfgBuilder::range_bound_facts(&self.range, &syn::parse_quote!(#variable))
+    }
+}
+
+// Whether the tokens of a condition hold a quantifier, whose variable a substitution of the tokens would
+// replace too
+pub fn mentions_quantifier(tokens: &TokenStream) -> bool {
+    let trees: Vec<TokenTree> = tokens.clone().into_iter().collect();
+    trees.iter().enumerate().any(|(i, tree)| match tree {
+        TokenTree::Ident(ident) => QuantifierKind::from_keyword(&ident.to_string()).is_some() && matches!(trees.get(i + 1), Some(TokenTree::Group(_))),
+        TokenTree::Group(group) => mentions_quantifier(&group.stream()),
+        _ => false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::SolverSettings;
+    use crate::test_support::verdicts;
+    use crate::verifier::{verify_str_implication, VcOutcome};
+
+    fn fill(value: &str) -> Vec<VcOutcome> {
+        verdicts(&format!("fn fill(n: usize) {{
+                pre!(n >= 1);
+                post!(forall k in 0..n: v[k] == 7);
+                let mut v = vec![0; n];
+                let mut i = 0;
+                invariant!(\"i >= 0 && i <= n && forall k in 0..i: v[k] == 7\");
+                while i < n {{
+                    v[i] = {};
+                    i += 1;
+                }}
+            }}", value))
+    }
+
+    #[test]
+    fn quantified_invariants_hold_over_array_stores() {
+        let outcomes = fill("7");
+        assert!(!outcomes.is_empty() && outcomes.iter().all(|outcome| matches!(outcome, VcOutcome::Valid)));
+        // The store of another value breaks the invariant around the loop
+        let outcomes = fill("6");
+        assert_eq!(outcomes.iter().filter(|outcome| matches!(outcome, VcOutcome::Counterexample(_))).count(), 1);
+    }
+
+    #[test]
+    fn exists_needs_a_witness_in_the_range() {
+        let verdict = |implication: &str| verify_str_implication(implication, &SolverSettings::default()).unwrap();
+        assert!(matches!(verdict("(a[2] == 5 && n == 3) >> exists(0..n, |i| a[i] == 5)"), VcOutcome::Valid));
+        assert!(matches!(verdict("(a[3] == 5 && n == 3) >> exists(0..n, |i| a[i] == 5)"), VcOutcome::Counterexample(_)));
+    }
+}
@@ -0,0 +1,39 @@
+// Fixtures of the test modules: a source file through the whole pipeline, from the graph to the solver
+// verdict of each basic path
+use petgraph::graph::NodeIndex;
+
+use crate::cfg_builder::builder::CfgBuilder;
+use crate::verifier::{verify_str_implication, VcOutcome};
+
+// The graph of a source file, with its lines for the '//@ call' comments
+pub(crate) fn built(source: &str) -> CfgBuilder {
+    built_with(source, |_| {})
+}
+
+// The graph of a source file, with options set before the build
+pub(crate) fn built_with(source: &str, configure: impl FnOnce(&mut CfgBuilder)) -> CfgBuilder {
+    let file: syn::File = syn::parse_str(source).unwrap();
+    let mut builder = CfgBuilder::new();
+    builder.file.source_lines = source.lines().map(str::to_string).collect();
+    configure(&mut builder);
+    builder.build_cfg(&file);
+    builder
+}
+
+// Verdict of each of the given paths
+pub(crate) fn discharge_paths(builder: &CfgBuilder, paths: &[Vec<NodeIndex>]) -> Vec<VcOutcome> {
+    builder.apply_wp_calculus(paths).iter()
+        .map(|implication| verify_str_implication(implication, &builder.options.solver_settings).unwrap())
+        .collect()
+}
+
+// Verdict of each basic path of a built graph
+pub(crate) fn discharge(builder: &mut CfgBuilder) -> Vec<VcOutcome> {
+    let paths = builder.generate_basic_paths();
+    discharge_paths(builder, &paths)
+}
+
+// Verdict of each basic path of a source file
+pub(crate) fn verdicts(source: &str) -> Vec<VcOutcome> {
+    discharge(&mut built(source))
+}
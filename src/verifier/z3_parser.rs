@@ -1,6 +1,10 @@
-use z3::{ast, Context};
+use z3::{ast, Context, FuncDecl, RecFuncDecl, Sort};
 use z3::ast::Ast;   
-use syn::{Expr, UnOp, ExprPath, ExprLit, ExprMacro, ExprBinary, ExprParen, ExprUnary, BinOp};
+use syn::{Expr, ExprPath, ExprLit, ExprMacro, ExprBinary, ExprParen, ExprUnary, ExprCast, BinOp, ExprBlock, ExprCall, ExprIf, ExprIndex, Stmt};
+use crate::cfg_builder::builder::CfgBuilder;
+use crate::spec_parser::{Quantifier, QuantifierKind};
+use crate::wp_calculus::ARRAY_STORE;
+use syn::visit::{self, Visit};
 use std::collections::HashMap;
 use std::ops::{Add, Sub, Mul, Div};
 use std::fmt;
@@ -45,19 +49,21 @@ impl<'a> ImplicationPlaceholder<'a> {
 }
 
 
-// Main function to generate Z3 condition and variables HashMap
+// Main function to generate Z3 condition and variables HashMap. Conditions the solver can't express
+// (method calls, floats, ...) are an error naming the unsupported part.
 pub fn generate_condition_and_vars<'a>(
     ctx: &'a Context,
     expr: &Expr,
-) -> (ast::Bool<'a>, HashMap<String, Z3Var<'a>>) {
+) -> Result<(ast::Bool<'a>, HashMap<String, Z3Var<'a>>), String> {
     let mut vars = HashMap::new();
     //println!("Whole SYN AST: {:?}", expr);
-    let z3_condition_var = generate_z3_ast(ctx, expr, &mut vars);
+    let functions = declare_logic_functions(ctx, &[expr])?;
+    let z3_condition_var = generate_z3_ast(ctx, expr, &mut vars, &functions)?;
 
     // Ensure the condition is returned as a Bool, converting if necessary
     let z3_condition = match z3_condition_var {
         Z3Var::Bool(b) => b,
-        _ => panic!("Expected Bool condition, found different type"),
+        _ => return Err("Expected Bool condition, found different type".to_string()),
     };
 
     // Post-process the AST to handle implication placeholders
@@ -80,144 +86,253 @@ pub fn generate_condition_and_vars<'a>(
     }*/
     println!();
     println!("Generated Z3 Condition:\n{}\n", z3_condition.to_string());
-    (z3_condition, vars)
+    Ok((z3_condition, vars))
+}
+
+// Z3 formula of one condition, for solver queries that don't print it. 'functions' are the logic
+// functions declared by the conditions of the query, see 'declare_logic_functions'.
+pub fn condition_to_z3<'a>(ctx: &'a Context, expr: &Expr, functions: &LogicFunctions<'a>) -> Result<ast::Bool<'a>, String> {
+    let mut vars = HashMap::new();
+    match generate_z3_ast(ctx, expr, &mut vars, functions)? {
+        Z3Var::Bool(condition) => Ok(condition),
+        _ => Err("Expected Bool condition, found different type".to_string()),
+    }
 }
 
 fn generate_z3_ast<'a>(
     ctx: &'a Context,
     expr: &Expr,
     vars: &mut HashMap<String, Z3Var<'a>>,
-) -> Z3Var<'a> {
+    functions: &LogicFunctions<'a>,
+) -> Result<Z3Var<'a>, String> {
     match expr {
         Expr::Macro(ExprMacro { mac, .. }) => {
-            let macro_name = mac.path.segments.last().expect("Expected macro name").ident.to_string();
+            let macro_name = mac.path.segments.last().ok_or("Expected macro name")?.ident.to_string();
+            // Definitions are part of the declarations of the logic functions, made beforehand
+            if macro_name == "logic" {
+                return Ok(Z3Var::Bool(ast::Bool::from_bool(ctx, true)));
+            }
             if ["invariant", "pre", "post"].contains(&macro_name.as_str()) {
                 if let Ok(arg_expr) = syn::parse2::<Expr>(mac.tokens.clone()) {
-                    return generate_z3_ast(ctx, &arg_expr, vars);
+                    generate_z3_ast(ctx, &arg_expr, vars, functions)
                 } else {
-                    panic!("Failed to parse macro argument expression");
+                    Err("Failed to parse macro argument expression".to_string())
                 }
             } else {
-                panic!("Unsupported macro: {}", macro_name);
+                Err(format!("Unsupported macro: {}", macro_name))
             }
         }
         Expr::Lit(ExprLit { lit, .. }) => match lit {
             syn::Lit::Int(lit_int) => {
-                let int_value = lit_int.base10_parse::<i64>().expect("Expected integer literal");
-                Z3Var::Int(ast::Int::from_i64(ctx, int_value))
+                // Parsed from the digits so that bounds like 'u64::MAX' fit
+                let value = ast::Int::from_str(ctx, lit_int.base10_digits()).ok_or_else(|| "Expected integer literal".to_string())?;
+                match lit_int.suffix() {
+                    "" => Ok(Z3Var::Int(value)),
+                    suffix => wrap_integer(ctx, &value, suffix).map(Z3Var::Int),
+                }
             }
             syn::Lit::Bool(lit_bool) => {
-                Z3Var::Bool(ast::Bool::from_bool(ctx, lit_bool.value))
+                Ok(Z3Var::Bool(ast::Bool::from_bool(ctx, lit_bool.value)))
             }
-            _ => panic!("Unsupported literal type"),
+            _ => Err("Unsupported literal type".to_string()),
         },
         Expr::Paren(ExprParen { expr, .. }) => {
-            generate_z3_ast(ctx, expr, vars)
+            generate_z3_ast(ctx, expr, vars, functions)
+        }
+        // Bodies of logic functions: '{ e }' and 'if c { a } else { b }'
+        Expr::Block(ExprBlock { block, .. }) => match block.stmts.as_slice() {
+            [Stmt::Expr(tail)] => generate_z3_ast(ctx, tail, vars, functions),
+            _ => Err("Unsupported block expression".to_string()),
+        }
+        Expr::If(ExprIf { cond, then_branch, else_branch: Some((_, else_branch)), .. }) => {
+            let Z3Var::Bool(cond) = generate_z3_ast(ctx, cond, vars, functions)? else {
+                return Err("Expected Bool type for if condition".to_string());
+            };
+            let then_value = match then_branch.stmts.as_slice() {
+                [Stmt::Expr(tail)] => generate_z3_ast(ctx, tail, vars, functions)?,
+                _ => return Err("Unsupported block expression".to_string()),
+            };
+            match (then_value, generate_z3_ast(ctx, else_branch, vars, functions)?) {
+                (Z3Var::Int(then_int), Z3Var::Int(else_int)) => Ok(Z3Var::Int(cond.ite(&then_int, &else_int))),
+                (Z3Var::Bool(then_bool), Z3Var::Bool(else_bool)) => Ok(Z3Var::Bool(cond.ite(&then_bool, &else_bool))),
+                _ => Err("Expected branches of the same type in if expression".to_string()),
+            }
+        }
+        // 'forall(a..b, |i| body)': the variable is bound in the body, over the range
+        Expr::Call(_) if Quantifier::of(expr).is_some() => {
+            let quantifier = Quantifier::of(expr).expect("checked by the guard");
+            let name = quantifier.variable.to_string();
+            let bound = ast::Int::new_const(ctx, name.as_str());
+            let outer = vars.insert(name.clone(), Z3Var::Int(bound.clone()));
+            let guard = generate_z3_ast(ctx, &quantifier.guard(), vars, functions);
+            let body = generate_z3_ast(ctx, &quantifier.body, vars, functions);
+            match outer {
+                Some(outer) => vars.insert(name, outer),
+                None => vars.remove(&name),
+            };
+            let (guard, body) = (guard?, body?);
+            let (guard, body) = (guard.as_bool()?, body.as_bool()?);
+            Ok(Z3Var::Bool(match quantifier.kind {
+                QuantifierKind::Forall => ast::forall_const(ctx, &[&bound], &[], &guard.implies(body)),
+                QuantifierKind::Exists => ast::exists_const(ctx, &[&bound], &[], &ast::Bool::and(ctx, &[guard, body])),
+            }))
+        }
+        // Element of an array of integers
+        Expr::Index(ExprIndex { expr: array, index, .. }) => {
+            let array = generate_z3_array(ctx, array, vars, functions)?;
+            let index = generate_z3_ast(ctx, index, vars, functions)?;
+            array.select(index.as_int()?).as_int().map(Z3Var::Int).ok_or_else(|| "Expected an array of integers".to_string())
+        }
+        // Call of a logic function, declared by a 'logic!(fn ..)' hypothesis
+        Expr::Call(ExprCall { func, args, .. }) => {
+            let name = match &**func {
+                Expr::Path(ExprPath { path, .. }) => path.segments.last().map(|segment| segment.ident.to_string()),
+                _ => None,
+            };
+            let Some(decl) = name.as_ref().and_then(|name| functions.get(name)) else {
+                return Err(format!("Unsupported call: no logic function {:?}", name));
+            };
+            let args = args.iter().map(|arg| generate_z3_ast(ctx, arg, vars, functions)?.to_dynamic()).collect::<Result<Vec<ast::Dynamic<'a>>, String>>()?;
+            let args: Vec<&dyn Ast<'a>> = args.iter().map(|arg| arg as &dyn Ast<'a>).collect();
+            Z3Var::from_dynamic(decl.apply(&args))
+        }
+        // A field place ('self.len') is a variable of its own
+        Expr::Field(_) => match field_place_name(expr) {
+            Some(var_name) => Ok(get_or_create_var(ctx, &var_name, vars)),
+            None => Err("Unsupported field expression".to_string()),
+        }
+        // Integers are unbounded here: a cast to an integer type wraps the value around into the type, as Rust
+        // does, and a boolean is 0 or 1
+        Expr::Cast(ExprCast { expr, ty, .. }) => {
+            let value = match generate_z3_ast(ctx, expr, vars, functions)? {
+                Z3Var::Int(value) => value,
+                Z3Var::Bool(value) => value.ite(&ast::Int::from_i64(ctx, 1), &ast::Int::from_i64(ctx, 0)),
+                _ => return Err("Expected Int or Bool type for a cast".to_string()),
+            };
+            wrap_integer(ctx, &value, &CfgBuilder::render_type(ty)).map(Z3Var::Int)
         }
         Expr::Path(ExprPath { path, .. }) => {
             if let Some(ident) = path.get_ident() {
                 let var_name = ident.to_string();
-                get_or_create_var(ctx, &var_name, vars)
+                Ok(get_or_create_var(ctx, &var_name, vars))
             } else {
-                panic!("Unsupported path expression");
+                Err("Unsupported path expression".to_string())
             }
         }
         Expr::Unary(ExprUnary { op, expr, .. }) => match op {
             syn::UnOp::Not(_) => {
-                let inner_ast = generate_z3_ast(ctx, expr, vars);
+                let inner_ast = generate_z3_ast(ctx, expr, vars, functions)?;
+                match inner_ast {
+                    Z3Var::Bool(inner_bool) => Ok(Z3Var::Bool(inner_bool.not())),
+                    _ => Err("Expected Bool type for Not operation".to_string()),
+                }
+            }
+            // '-128i8' is in range although '128i8' isn't: the suffix applies to the negated value
+            syn::UnOp::Neg(_) if suffixed_integer_literal(expr).is_some() => {
+                let (digits, suffix) = suffixed_integer_literal(expr).expect("checked by the guard");
+                let value = ast::Int::from_str(ctx, &format!("-{}", digits)).ok_or_else(|| "Expected integer literal".to_string())?;
+                wrap_integer(ctx, &value, &suffix).map(Z3Var::Int)
+            }
+            syn::UnOp::Neg(_) => {
+                let inner_ast = generate_z3_ast(ctx, expr, vars, functions)?;
                 match inner_ast {
-                    Z3Var::Bool(inner_bool) => Z3Var::Bool(inner_bool.not()),
-                    _ => panic!("Expected Bool type for Not operation"),
+                    Z3Var::Int(inner_int) => Ok(Z3Var::Int(inner_int.unary_minus())),
+                    _ => Err("Expected Int type for Neg operation".to_string()),
                 }
             }
-            _ => panic!("Unsupported unary operator: {:?}", op),
+            _ => Err(format!("Unsupported unary operator: {:?}", op)),
         },
         Expr::Binary(ExprBinary { left, op, right, .. }) => {
-            let left_ast = generate_z3_ast(ctx, left, vars);
-            let right_ast = generate_z3_ast(ctx, right, vars);
+            let left_ast = generate_z3_ast(ctx, left, vars, functions)?;
+            let right_ast = generate_z3_ast(ctx, right, vars, functions)?;
 
             match op {
                 BinOp::And(_) => {
                     if let (Z3Var::Bool(left_bool), Z3Var::Bool(right_bool)) = (left_ast, right_ast) {
-                        Z3Var::Bool(ast::Bool::and(ctx, &[&left_bool, &right_bool]))
+                        Ok(Z3Var::Bool(ast::Bool::and(ctx, &[&left_bool, &right_bool])))
                     } else {
-                        panic!("Expected Bool types for And operation");
+                        Err("Expected Bool types for And operation".to_string())
                     }
                 },
                 BinOp::Or(_) => {
                     if let (Z3Var::Bool(left_bool), Z3Var::Bool(right_bool)) = (left_ast, right_ast) {
-                        Z3Var::Bool(ast::Bool::or(ctx, &[&left_bool, &right_bool]))
+                        Ok(Z3Var::Bool(ast::Bool::or(ctx, &[&left_bool, &right_bool])))
                     } else {
-                        panic!("Expected Bool types for Or operation");
+                        Err("Expected Bool types for Or operation".to_string())
                     }
                 }
                 BinOp::Eq(_) => match (left_ast, right_ast) {
-                    (Z3Var::Int(left_int), Z3Var::Int(right_int)) => Z3Var::Bool(left_int._eq(&right_int)),
-                    (Z3Var::Bool(left_bool), Z3Var::Bool(right_bool)) => Z3Var::Bool(left_bool._eq(&right_bool)),
-                    _ => panic!("Unsupported types for Eq operation"),
+                    (Z3Var::Int(left_int), Z3Var::Int(right_int)) => Ok(Z3Var::Bool(left_int._eq(&right_int))),
+                    (Z3Var::Bool(left_bool), Z3Var::Bool(right_bool)) => Ok(Z3Var::Bool(left_bool._eq(&right_bool))),
+                    _ => Err("Unsupported types for Eq operation".to_string()),
+                },
+                BinOp::Ne(_) => match (left_ast, right_ast) {
+                    (Z3Var::Int(left_int), Z3Var::Int(right_int)) => Ok(Z3Var::Bool(left_int._eq(&right_int).not())),
+                    (Z3Var::Bool(left_bool), Z3Var::Bool(right_bool)) => Ok(Z3Var::Bool(left_bool._eq(&right_bool).not())),
+                    _ => Err("Unsupported types for Ne operation".to_string()),
                 },
                 BinOp::Le(_) => {
                     match (left_ast, right_ast) {
                         (Z3Var::Int(left_int), Z3Var::Int(right_int)) => {
                             // println!("Attempting Le operation: left = {:?}, right = {:?}", left_int, right_int);
-                            Z3Var::Bool(left_int.le(&right_int))
+                            Ok(Z3Var::Bool(left_int.le(&right_int)))
                         }
                         (left, right) => {
                             println!(
                                 "Expected Int types for Le operation, found incompatible types: left = {:?}, right = {:?}",
                                 left, right
                             );
-                            panic!("Comparison operations require Int types only.");
+                            Err("Comparison operations require Int types only.".to_string())
                         }
                     }
                 },                                                                  
                 BinOp::Ge(_) => {
                     if let (Z3Var::Int(left_int), Z3Var::Int(right_int)) = (left_ast, right_ast) {
-                        Z3Var::Bool(left_int.ge(&right_int))
+                        Ok(Z3Var::Bool(left_int.ge(&right_int)))
                     } else {
-                        panic!("Expected Int types for Ge operation");
+                        Err("Expected Int types for Ge operation".to_string())
                     }
                 }
                 BinOp::Lt(_) => {
                     if let (Z3Var::Int(left_int), Z3Var::Int(right_int)) = (left_ast, right_ast) {
-                        Z3Var::Bool(left_int.lt(&right_int))
+                        Ok(Z3Var::Bool(left_int.lt(&right_int)))
                     } else {
-                        panic!("Expected Int types for Lt operation");
+                        Err("Expected Int types for Lt operation".to_string())
                     }
                 }
                 BinOp::Gt(_) => {
                     if let (Z3Var::Int(left_int), Z3Var::Int(right_int)) = (left_ast, right_ast) {
-                        Z3Var::Bool(left_int.gt(&right_int))
+                        Ok(Z3Var::Bool(left_int.gt(&right_int)))
                     } else {
-                        panic!("Expected Int types for Gt operation");
+                        Err("Expected Int types for Gt operation".to_string())
                     }
                 }
                 BinOp::Add(_) => {
                     if let (Z3Var::Int(left_int), Z3Var::Int(right_int)) = (left_ast, right_ast) {
-                        Z3Var::Int(left_int.add(&right_int))
+                        Ok(Z3Var::Int(left_int.add(&right_int)))
                     } else {
-                        panic!("Expected Int types for Add operation");
+                        Err("Expected Int types for Add operation".to_string())
                     }
                 }
                 BinOp::Sub(_) => {
                     if let (Z3Var::Int(left_int), Z3Var::Int(right_int)) = (left_ast, right_ast) {
-                        Z3Var::Int(left_int.sub(&right_int))
+                        Ok(Z3Var::Int(left_int.sub(&right_int)))
                     } else {
-                        panic!("Expected Int types for Sub operation");
+                        Err("Expected Int types for Sub operation".to_string())
                     }
                 }
                 BinOp::Mul(_) => {
                     if let (Z3Var::Int(left_int), Z3Var::Int(right_int)) = (left_ast, right_ast) {
-                        Z3Var::Int(left_int.mul(&right_int))
+                        Ok(Z3Var::Int(left_int.mul(&right_int)))
                     } else {
-                        panic!("Expected Int types for Mul operation");
+                        Err("Expected Int types for Mul operation".to_string())
                     }
                 }
                 BinOp::Div(_) => {
                     if let (Z3Var::Int(left_int), Z3Var::Int(right_int)) = (left_ast, right_ast) {
-                        Z3Var::Int(left_int.div(&right_int))
+                        Ok(Z3Var::Int(left_int.div(&right_int)))
                     } else {
-                        panic!("Expected Int types for Div operation");
+                        Err("Expected Int types for Div operation".to_string())
                     }
                 }
                 BinOp::Shr(_) => {
@@ -232,52 +347,112 @@ fn generate_z3_ast<'a>(
                         ctx: &'a Context,
                         expr: &Expr,
                         vars: &mut HashMap<String, Z3Var<'a>>,
+                        functions: &LogicFunctions<'a>,
                         placeholder: &mut ImplicationPlaceholder<'a>,
-                    ) {
+                    ) -> Result<(), String> {
                         if let Expr::Binary(ExprBinary { left, op, right, .. }) = expr {
                             if matches!(op, BinOp::Shr(_)) {
                                 // If the left side is also a '>>', traverse it recursively
-                                extract_chain(ctx, left, vars, placeholder);
+                                extract_chain(ctx, left, vars, functions, placeholder)?;
                 
                                 // Process the right side and add it to the placeholder
-                                if let Z3Var::Bool(right_bool) = generate_z3_ast(ctx, right, vars) {
+                                if let Z3Var::Bool(right_bool) = generate_z3_ast(ctx, right, vars, functions)? {
                                     placeholder.add_argument(right_bool);
                                 } else {
-                                    panic!("Expected Bool type for right operand of '>>'");
+                                    return Err("Expected Bool type for right operand of '>>'".to_string());
                                 }
-                                return;
+                                return Ok(());
                             }
                         }
                 
                         // If it's not a chain, process it as a standalone expression
-                        if let Z3Var::Bool(expr_bool) = generate_z3_ast(ctx, expr, vars) {
+                        if let Z3Var::Bool(expr_bool) = generate_z3_ast(ctx, expr, vars, functions)? {
                             placeholder.add_argument(expr_bool);
+                            Ok(())
                         } else {
-                            panic!("Expected Bool type for chain element");
+                            Err("Expected Bool type for chain element".to_string())
                         }
                     }
                 
                     // Extract the left side chain
-                    extract_chain(ctx, left, vars, &mut placeholder);
+                    extract_chain(ctx, left, vars, functions, &mut placeholder)?;
                 
                     // Process the right side of the current '>>' operation
-                    if let Z3Var::Bool(right_bool) = generate_z3_ast(ctx, right, vars) {
+                    if let Z3Var::Bool(right_bool) = generate_z3_ast(ctx, right, vars, functions)? {
                         placeholder.add_argument(right_bool);
                     } else {
                         println!("Left operand: {:?}", left);
-                        panic!("Expected Bool type for right operand of top-level '>>': {:?}", right);
+                        return Err(format!("Expected Bool type for right operand of top-level '>>': {:?}", right));
                     }
                 
                     // Return the placeholder as a 'Z3Var::Bool'
-                    Z3Var::Bool(placeholder.to_z3_implies(ctx))
+                    Ok(Z3Var::Bool(placeholder.to_z3_implies(ctx)))
                 }                                                                                                                                                                               
-                _ => panic!("Unsupported binary operator: {:?}", op),
+                _ => Err(format!("Unsupported binary operator: {:?}", op)),
             }
         }
         other => {
             println!("Encountered unsupported logical expression type: {:?}", other);
-            panic!("Unsupported logical expression");
+            Err("Unsupported logical expression".to_string())
+        }
+    }
+}
+
+// Arrays are from integers to integers: a variable ('v', 'self.data'), the array after an element is stored
+// ('array_store(v, i, x)') or 'vec![x; n]'. Other arrays are unknown, one for each expression.
+// Value of an integer cast to the type 'ty', or of a literal with the suffix 'ty'
+fn wrap_integer<'a>(ctx: &'a Context, value: &ast::Int<'a>, ty: &str) -> Result<ast::Int<'a>, String> {
+    let (min, modulus) = CfgBuilder::integer_wrap(ty).ok_or_else(|| format!("Unsupported cast to '{}'", ty))?;
+    let min = ast::Int::from_str(ctx, min).ok_or("Expected integer bound")?;
+    let modulus = ast::Int::from_str(ctx, &modulus).ok_or("Expected integer bound")?;
+    Ok(value.sub(&min).modulo(&modulus).add(&min))
+}
+
+// Digits and suffix of an integer literal written with the type it has ('300u16'), possibly in parentheses
+pub(crate) fn suffixed_integer_literal(expr: &Expr) -> Option<(String, String)> {
+    match expr {
+        Expr::Lit(ExprLit { lit: syn::Lit::Int(lit_int), .. }) if !lit_int.suffix().is_empty() => {
+            Some((lit_int.base10_digits().to_string(), lit_int.suffix().to_string()))
         }
+        Expr::Paren(ExprParen { expr, .. }) => suffixed_integer_literal(expr),
+        _ => None,
+    }
+}
+
+fn generate_z3_array<'a>(
+    ctx: &'a Context,
+    expr: &Expr,
+    vars: &mut HashMap<String, Z3Var<'a>>,
+    functions: &LogicFunctions<'a>,
+) -> Result<ast::Array<'a>, String> {
+    match expr {
+        Expr::Paren(ExprParen { expr, .. }) => generate_z3_array(ctx, expr, vars, functions),
+        Expr::Call(ExprCall { func, args, .. }) if matches!(&**func, Expr::Path(ExprPath { path, .. }) if path.is_ident(ARRAY_STORE)) && args.len() == 3 => {
+            let array = generate_z3_array(ctx, &args[0], vars, functions)?;
+            let index = generate_z3_ast(ctx, &args[1], vars, functions)?;
+            let value = generate_z3_ast(ctx, &args[2], vars, functions)?;
+            Ok(array.store(index.as_int()?, value.as_int()?))
+        }
+        Expr::Macro(ExprMacro { mac, .. }) if mac.path.is_ident("vec") => {
+            let tokens = &mac.tokens;
+            match syn::parse2::<syn::ExprRepeat>(quote::quote!([#tokens])) {
+                Ok(repeat) => {
+                    let value = generate_z3_ast(ctx, &repeat.expr, vars, functions)?;
+                    Ok(ast::Array::const_array(ctx, &Sort::int(ctx), value.as_int()?))
+                }
+                Err(_) => named_array(ctx, &quote::quote!(#expr).to_string(), vars),
+            }
+        }
+        _ => named_array(ctx, &field_place_name(expr).unwrap_or_else(|| quote::quote!(#expr).to_string()), vars),
+    }
+}
+
+fn named_array<'a>(ctx: &'a Context, name: &str, vars: &mut HashMap<String, Z3Var<'a>>) -> Result<ast::Array<'a>, String> {
+    let array = vars.entry(name.to_string())
+        .or_insert_with(|| Z3Var::Array(ast::Array::new_const(ctx, name, &Sort::int(ctx), &Sort::int(ctx))));
+    match array {
+        Z3Var::Array(array) => Ok(array.clone()),
+        _ => Err(format!("Expected '{}' to be an array", name)),
     }
 }
 
@@ -353,6 +528,22 @@ fn extract_implication_placeholder<'a>(
     None
 }
 
+// 'self.len' for a chain of named or numbered fields from a variable
+pub(crate) fn field_place_name(expr: &Expr) -> Option<String> {
+    match expr {
+        Expr::Path(ExprPath { path, .. }) => path.get_ident().map(|ident| ident.to_string()),
+        Expr::Field(field) => {
+            let member = match &field.member {
+                syn::Member::Named(ident) => ident.to_string(),
+                syn::Member::Unnamed(index) => index.index.to_string(),
+            };
+            Some(format!("{}.{}", field_place_name(&field.base)?, member))
+        }
+        Expr::Paren(ExprParen { expr, .. }) => field_place_name(expr),
+        _ => None,
+    }
+}
+
 // Helper function to create or retrieve Z3 variables
 fn get_or_create_var<'a>(
     ctx: &'a Context,
@@ -366,19 +557,146 @@ fn get_or_create_var<'a>(
 
 // Helper methods for 'Z3Var' to return specific types
 impl<'ctx> Z3Var<'ctx> {
-    fn as_bool(&self) -> &ast::Bool<'ctx> {
+    fn to_dynamic(&self) -> Result<ast::Dynamic<'ctx>, String> {
+        match self {
+            Z3Var::Int(int_var) => Ok(ast::Dynamic::from_ast(int_var)),
+            Z3Var::Bool(bool_var) => Ok(ast::Dynamic::from_ast(bool_var)),
+            _ => Err("Expected Int or Bool argument of a logic function".to_string()),
+        }
+    }
+
+    fn from_dynamic(value: ast::Dynamic<'ctx>) -> Result<Self, String> {
+        match (value.as_int(), value.as_bool()) {
+            (Some(int_var), _) => Ok(Z3Var::Int(int_var)),
+            (_, Some(bool_var)) => Ok(Z3Var::Bool(bool_var)),
+            _ => Err("Expected Int or Bool result of a logic function".to_string()),
+        }
+    }
+
+    fn as_bool(&self) -> Result<&ast::Bool<'ctx>, String> {
         if let Z3Var::Bool(bool_var) = self {
-            bool_var
+            Ok(bool_var)
         } else {
-            panic!("Expected Bool type, but found a different type.")
+            Err("Expected Bool type, but found a different type.".to_string())
         }
     }
 
-    fn as_int(&self) -> &ast::Int<'ctx> {
+    fn as_int(&self) -> Result<&ast::Int<'ctx>, String> {
         if let Z3Var::Int(int_var) = self {
-            int_var
+            Ok(int_var)
         } else {
-            panic!("Expected Int type, but found a different type.")
+            Err("Expected Int type, but found a different type.".to_string())
         }
     }
-}
\ No newline at end of file
+}
+// Logic functions of a condition by name: defined by their body, or only declared
+pub type LogicFunctions<'a> = HashMap<String, LogicDecl<'a>>;
+
+pub enum LogicDecl<'a> {
+    Declared(FuncDecl<'a>),
+    Defined(RecFuncDecl<'a>), // unfolded by the solver as needed
+}
+
+impl<'a> LogicDecl<'a> {
+    fn apply(&self, args: &[&dyn Ast<'a>]) -> ast::Dynamic<'a> {
+        match self {
+            LogicDecl::Declared(decl) => decl.apply(args),
+            LogicDecl::Defined(decl) => decl.apply(args),
+        }
+    }
+}
+
+// 'logic!(fn f(n: i64) -> i64 { .. })' defines 'f', 'logic!(fn f(n: i64) -> i64;)' only declares it
+fn parse_logic_function(mac: &syn::Macro) -> Option<(syn::Signature, Option<Expr>)> {
+    match syn::parse2::<syn::ItemFn>(mac.tokens.clone()) {
+        Ok(item_fn) => {
+            let body = Expr::Block(ExprBlock { attrs: Vec::new(), label: None, block: *item_fn.block });
+            Some((item_fn.sig, Some(body)))
+        },
+        Err(_) => syn::parse2::<syn::TraitItemMethod>(mac.tokens.clone()).ok().map(|method| (method.sig, None)),
+    }
+}
+
+// Logic functions are over integers and booleans
+fn logic_sort<'a>(ctx: &'a Context, ty: &syn::Type) -> Sort<'a> {
+    match ty {
+        syn::Type::Path(type_path) if type_path.path.is_ident("bool") => Sort::bool(ctx),
+        _ => Sort::int(ctx),
+    }
+}
+
+#[derive(Default)]
+struct LogicCollector {
+    functions: HashMap<String, (syn::Signature, Option<Expr>)>,
+}
+
+impl<'ast> Visit<'ast> for LogicCollector {
+    fn visit_expr_macro(&mut self, i: &'ast ExprMacro) {
+        if i.mac.path.is_ident("logic") {
+            if let Some((sig, body)) = parse_logic_function(&i.mac) {
+                let name = sig.ident.to_string();
+                if body.is_some() || !self.functions.contains_key(&name) {
+                    self.functions.insert(name, (sig, body));
+                }
+            }
+        }
+        visit::visit_expr_macro(self, i);
+    }
+}
+
+// Declare the logic functions of the 'logic!' hypotheses of some conditions, all before any definition so
+// they can call each other. A defined function is a recursive function of the solver, which unfolds its
+// body for the calls of the condition; the hypothesis itself is then 'true'.
+pub fn declare_logic_functions<'a>(ctx: &'a Context, exprs: &[&Expr]) -> Result<LogicFunctions<'a>, String> {
+    let mut collector = LogicCollector::default();
+    for expr in exprs {
+        collector.visit_expr(expr);
+    }
+    let params = |sig: &syn::Signature| -> Vec<(String, Sort<'a>)> {
+        sig.inputs.iter()
+            .filter_map(|input| match input {
+                syn::FnArg::Typed(pat_type) => match &*pat_type.pat {
+                    syn::Pat::Ident(pat_ident) => Some((pat_ident.ident.to_string(), logic_sort(ctx, &pat_type.ty))),
+                    _ => None,
+                },
+                syn::FnArg::Receiver(_) => None,
+            })
+            .collect()
+    };
+    let mut functions = LogicFunctions::new();
+    for (name, (sig, body)) in &collector.functions {
+        let domain: Vec<Sort<'a>> = params(sig).into_iter().map(|(_, sort)| sort).collect();
+        let domain: Vec<&Sort<'a>> = domain.iter().collect();
+        let range = match &sig.output {
+            syn::ReturnType::Type(_, ty) => logic_sort(ctx, ty),
+            syn::ReturnType::Default => Sort::bool(ctx),
+        };
+        let decl = match body {
+            Some(_) => LogicDecl::Defined(RecFuncDecl::new(ctx, name.as_str(), &domain, &range)),
+            None => LogicDecl::Declared(FuncDecl::new(ctx, name.as_str(), &domain, &range)),
+        };
+        functions.insert(name.clone(), decl);
+    }
+
+    for (name, (sig, body)) in &collector.functions {
+        let (Some(body), Some(LogicDecl::Defined(decl))) = (body, functions.get(name)) else { continue };
+        // The parameters are bound by the definition, apart from the variables of the condition
+        let mut bound_vars = HashMap::new();
+        let bounds = params(sig).into_iter()
+            .map(|(name, sort)| {
+                let var = if sort == Sort::bool(ctx) {
+                    Z3Var::Bool(ast::Bool::new_const(ctx, name.as_str()))
+                } else {
+                    Z3Var::Int(ast::Int::new_const(ctx, name.as_str()))
+                };
+                let bound = var.to_dynamic();
+                bound_vars.insert(name, var);
+                bound
+            })
+            .collect::<Result<Vec<ast::Dynamic<'a>>, String>>()?;
+        let bound_refs: Vec<&dyn Ast<'a>> = bounds.iter().map(|bound| bound as &dyn Ast<'a>).collect();
+        let value = generate_z3_ast(ctx, body, &mut bound_vars, &functions)?.to_dynamic()?;
+        decl.add_def(&bound_refs, &value);
+    }
+    Ok(functions)
+}
@@ -1,7 +1,9 @@
-use z3::{ast, Config, Context, Solver, SatResult};
+use z3::{ast, Config, Context, Params, Solver, SatResult, Tactic};
 use std::collections::HashMap;
 use crate::verifier::z3_parser;
 use crate::Z3Var;
+use crate::cfg_builder::ConstValue;
+use crate::config::SolverSettings;
 use z3::ast::Ast;
 // Verify Z3 condition and print the model if valid
 pub fn old_verify_condition(
@@ -135,21 +137,55 @@ pub fn old_verify_conditions_for_paths() {
     old_verify_condition(&mut solver, &condition_path_3, &vars);
 }
 
+// Outcome of checking one verification condition
+#[derive(Debug, Clone)]
+pub enum VcOutcome {
+    Valid,
+    Counterexample(HashMap<String, ConstValue>), // values of the integer and boolean variables in the model
+    Unknown,
+}
+
+impl VcOutcome {
+    // Verdict of a path in the run output: 'verified', 'falsified by n = 2, x = -1' or 'unknown'
+    pub fn summary(&self) -> String {
+        match self {
+            VcOutcome::Valid => "verified".to_string(),
+            VcOutcome::Counterexample(values) if values.is_empty() => "falsified".to_string(),
+            VcOutcome::Counterexample(values) => {
+                let mut values: Vec<String> = values.iter().map(|(name, value)| format!("{} = {}", name, value)).collect();
+                values.sort();
+                format!("falsified by {}", values.join(", "))
+            },
+            VcOutcome::Unknown => "unknown".to_string(),
+        }
+    }
+}
+
 // Verify Z3 condition and print the model if satisfiable
 pub fn verify_condition(
     solver: &mut Solver,
     condition: &ast::Bool,
     vars: &HashMap<String, Z3Var>,
 ) -> bool {
+    matches!(check_condition(solver, condition, vars), VcOutcome::Valid)
+}
+
+// Same as 'verify_condition', keeping the counterexample values for later reporting
+pub fn check_condition(
+    solver: &mut Solver,
+    condition: &ast::Bool,
+    vars: &HashMap<String, Z3Var>,
+) -> VcOutcome {
     solver.push();
     solver.assert(&condition.not()); // assert the negation for proof by contradiction
     let result = match solver.check() {
         SatResult::Unsat => {
             println!("Condition is valid (unsatisfiable when negated).\n");
-            true
+            VcOutcome::Valid
         }
         SatResult::Sat => {
             println!("Condition is not valid (counterexample found).\n");
+            let mut values = HashMap::new();
             if let Some(model) = solver.get_model() {
                 println!("Counterexample model assignments:");
                 for (name, var) in vars {
@@ -169,32 +205,174 @@ pub fn verify_condition(
                     if let Some(value) = value {
                         println!("{} = {}", name, value);
                     }
+
+                    // Model completion gives a value to variables the solver left unconstrained
+                    let concrete = match var {
+                        Z3Var::Int(ref int_var) => model.eval(int_var, true)
+                            .and_then(|v| v.as_i64())
+                            .map(|v| ConstValue::Int(v as i128)),
+                        Z3Var::Bool(ref bool_var) => model.eval(bool_var, true)
+                            .and_then(|v| v.as_bool())
+                            .map(ConstValue::Bool),
+                        _ => None,
+                    };
+                    if let Some(concrete) = concrete {
+                        values.insert(name.clone(), concrete);
+                    }
                 }
                 
                 println!();
             }
-            false
+            VcOutcome::Counterexample(values)
         }
         SatResult::Unknown => {
             println!("Solver could not determine validity.\n");
-            false
+            VcOutcome::Unknown
         }
     };
     solver.pop(1);
     result
 }
 
-// Main verification function that uses the parser module
-pub fn verify_str_implication(expr_str: &str) {
+// SMT-LIB2 script of a verification condition: its negation is asserted, so 'unsat' means valid
+pub fn implication_to_smtlib(expr_str: &str) -> Result<String, String> {
+    let cfg = Config::new();
+    let ctx = Context::new(&cfg);
+    let solver = Solver::new(&ctx);
+    let parsed_expr = syn::parse_str::<syn::Expr>(expr_str).map_err(|e| format!("Failed to parse expression: {}", e))?;
+    let (z3_condition, _vars) = z3_parser::generate_condition_and_vars(&ctx, &parsed_expr)?;
+    solver.assert(&z3_condition.not());
+    Ok(format!("; {}\n{}(check-sat)\n", expr_str, solver))
+}
+
+// Main verification function that uses the parser module. Each attempt gets the '[solver]' timeout; an
+// undecided one is retried with the next random seeds, then with the alternate tactics, before giving up.
+// A condition the solver can't express (method calls, floats, ...) is an error with the reason.
+pub fn verify_str_implication(expr_str: &str, settings: &SolverSettings) -> Result<VcOutcome, String> {
     // Z3 context and solver
     let cfg = Config::new();
     let ctx = Context::new(&cfg);
-    let mut solver = Solver::new(&ctx);
-    
-    //old_verify_str_implication();
+
     // Parse and process logical proposition
-    let parsed_expr = syn::parse_str::<syn::Expr>(expr_str).expect("Failed to parse expression");
-    let (z3_condition, vars) = z3_parser::generate_condition_and_vars(&ctx, &parsed_expr);
+    let parsed_expr = syn::parse_str::<syn::Expr>(expr_str).map_err(|e| format!("Failed to parse expression: {}", e))?;
+    let (z3_condition, vars) = z3_parser::generate_condition_and_vars(&ctx, &parsed_expr)?;
     // Verify the condition
-    verify_condition(&mut solver, &z3_condition, &vars);
+    let attempts = solver_attempts(&ctx, settings);
+    let count = attempts.len();
+    for (i, (strategy, mut solver)) in attempts.into_iter().enumerate() {
+        match check_condition(&mut solver, &z3_condition, &vars) {
+            VcOutcome::Unknown => {
+                let reason = solver.get_reason_unknown().unwrap_or_else(|| "unknown".to_string());
+                println!("Attempt {}/{} ({}) undecided: {}", i + 1, count, strategy, reason);
+            },
+            outcome => return Ok(outcome),
+        }
+    }
+    Ok(VcOutcome::Unknown)
+}
+
+// Solvers of the successive attempts with what sets them apart: the seeds first, then the tactics.
+// Tactics Z3 doesn't know are skipped (see 'unknown_tactics').
+fn solver_attempts<'ctx>(ctx: &'ctx Context, settings: &SolverSettings) -> Vec<(String, Solver<'ctx>)> {
+    let mut attempts = Vec::new();
+    for retry in 0..=settings.retries {
+        let seed = settings.seed.wrapping_add(retry);
+        let solver = Solver::new(ctx);
+        let mut params = solver_params(ctx, settings);
+        params.set_u32("random_seed", seed);
+        solver.set_params(&params);
+        attempts.push((format!("seed {}", seed), solver));
+    }
+    let known: Vec<&str> = Tactic::list_all(ctx).filter_map(Result::ok).collect();
+    for tactic in &settings.tactics {
+        if !known.contains(&tactic.as_str()) {
+            continue;
+        }
+        let solver = Tactic::new(ctx, tactic).solver();
+        solver.set_params(&solver_params(ctx, settings));
+        attempts.push((format!("tactic {}", tactic), solver));
+    }
+    attempts
+}
+
+// Proof object of a valid verification condition, for checking it without this tool: the SMT-LIB2 script
+// asserting its negation, followed by Z3's proof that the script is unsatisfiable. Tactics don't produce
+// proofs, so only the seeds of the '[solver]' strategy are tried. None when no attempt proves it, or when
+// the solver can't express the condition.
+pub fn implication_proof(expr_str: &str, settings: &SolverSettings) -> Option<String> {
+    let mut cfg = Config::new();
+    cfg.set_proof_generation(true);
+    let ctx = Context::new(&cfg);
+    let parsed_expr = syn::parse_str::<syn::Expr>(expr_str).ok()?;
+    let (z3_condition, _vars) = z3_parser::generate_condition_and_vars(&ctx, &parsed_expr).ok()?;
+    for retry in 0..=settings.retries {
+        let solver = Solver::new(&ctx);
+        let mut params = solver_params(&ctx, settings);
+        params.set_u32("random_seed", settings.seed.wrapping_add(retry));
+        solver.set_params(&params);
+        solver.assert(&z3_condition.not());
+        if solver.check() == SatResult::Unsat {
+            let proof = solver.get_proof()?;
+            return Some(format!("; {}\n{}(check-sat)\n; proof of unsat (Z3 proof terms)\n{:?}\n", expr_str, solver, proof));
+        }
+    }
+    None
+}
+
+// Tactics of the '[solver]' section that Z3 doesn't have
+pub fn unknown_tactics(settings: &SolverSettings) -> Vec<String> {
+    let cfg = Config::new();
+    let ctx = Context::new(&cfg);
+    let known: Vec<&str> = Tactic::list_all(&ctx).filter_map(Result::ok).collect();
+    settings.tactics.iter().filter(|tactic| !known.contains(&tactic.as_str())).cloned().collect()
+}
+
+fn solver_params<'ctx>(ctx: &'ctx Context, settings: &SolverSettings) -> Params<'ctx> {
+    let mut params = Params::new(ctx);
+    if settings.timeout_ms > 0 {
+        params.set_u32("timeout", settings.timeout_ms);
+    }
+    params
+}
+
+// Tracked hypotheses a proof of 'goal' needs, by name: the unsat core of the hypotheses and the negated
+// goal, or None when they don't imply the goal or the solver can't express them. Hypotheses without a name
+// are always assumed.
+pub fn unsat_core(hypotheses: &[(Option<String>, syn::Expr)], goal: &syn::Expr, settings: &SolverSettings) -> Option<Vec<String>> {
+    let cfg = Config::new();
+    let ctx = Context::new(&cfg);
+    let solver = Solver::new(&ctx);
+    // A smaller core marks fewer assumptions as needed
+    let mut params = solver_params(&ctx, settings);
+    params.set_bool("core.minimize", true);
+    solver.set_params(&params);
+    let conditions: Vec<&syn::Expr> = hypotheses.iter().map(|(_, hypothesis)| hypothesis).chain(std::iter::once(goal)).collect();
+    let functions = z3_parser::declare_logic_functions(&ctx, &conditions).ok()?;
+    for (name, hypothesis) in hypotheses {
+        let condition = z3_parser::condition_to_z3(&ctx, hypothesis, &functions).ok()?;
+        match name {
+            Some(name) => solver.assert_and_track(&condition, &ast::Bool::new_const(&ctx, name.as_str())),
+            None => solver.assert(&condition),
+        }
+    }
+    solver.assert(&z3_parser::condition_to_z3(&ctx, goal, &functions).ok()?.not());
+    match solver.check() {
+        SatResult::Unsat => Some(solver.get_unsat_core().iter().map(|tracked| tracked.to_string()).collect()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unsupported_conditions_are_an_error_not_a_panic() {
+        let settings = SolverSettings::default();
+        let reason = verify_str_implication("(v.len() > 0) >> (x > 0)", &settings).unwrap_err();
+        assert!(reason.contains("Unsupported logical expression"), "{}", reason);
+        assert!(verify_str_implication("(x == 1.5) >> (x > 0)", &settings).unwrap_err().contains("Unsupported literal type"));
+        assert!(implication_to_smtlib("(x == 1.5) >> true").is_err());
+        assert!(matches!(verify_str_implication("(x > 1) >> (x > 0)", &settings), Ok(VcOutcome::Valid)));
+    }
 }
@@ -0,0 +1,157 @@
+use petgraph::graph::NodeIndex;
+use quote::ToTokens;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use syn::{BinOp, Expr, FnArg, UnOp};
+
+use crate::cfg_builder::builder::CfgBuilder;
+use crate::cfg_builder::node::CfgNode;
+use crate::cfg_builder::ConstValue;
+use crate::wp_calculus::Interval;
+
+// What running every input of a function through the condition of a basic path found
+#[derive(Debug, Clone, PartialEq)]
+pub enum EnumerationOutcome {
+    Proved(usize), // the condition holds for each of this many inputs
+    Counterexample(HashMap<String, ConstValue>), // inputs meeting the assumptions of the path and breaking its annotation
+    Inconclusive(String), // why not every input was decided
+}
+
+impl CfgBuilder {
+    // Check the verification condition of a basic path on every input of its function, as a cross-check of the
+    // solver and a fallback when it is undecided. The parameters need small finite domains: booleans, enums by
+    // their tag, and integers bounded by the preconditions or their type. Only paths from a precondition are
+    // enumerated: the others start in a loop state the parameters don't determine, and get None.
+    pub fn enumerate_path(&self, path: &[NodeIndex]) -> Option<EnumerationOutcome> {
+        let start = *path.first()?;
+        if !matches!(self.graph[start], CfgNode::Precondition(_, _)) {
+            return None;
+        }
+        let item_fn = self.function_nodes().into_iter()
+            .find(|&func_node| self.function_subgraph_nodes(func_node).contains(&start))
+            .and_then(|func_node| match &self.graph[func_node] {
+                CfgNode::Function(_, Some(item_fn)) => Some(item_fn),
+                _ => None,
+            })?;
+        let condition = self.substitute_constants(&self.wp_along_path(path, path.len(), None)?);
+        let domains = match self.input_domains(path, item_fn, &condition) {
+            Ok(domains) => domains,
+            Err(reason) => return Some(EnumerationOutcome::Inconclusive(reason)),
+        };
+        let max_inputs = self.options.enumeration.max_inputs;
+        let Some(inputs) = domains.iter().try_fold(1usize, |count, (_, values)| count.checked_mul(values.len())).filter(|&count| count <= max_inputs) else {
+            return Some(EnumerationOutcome::Inconclusive(format!("more than {} inputs", max_inputs)));
+        };
+
+        let started = Instant::now();
+        let timeout = Duration::from_millis(self.options.enumeration.timeout_ms);
+        let mut undecided = None;
+        let mut position = vec![0; domains.len()];
+        for tried in 0..inputs {
+            if !timeout.is_zero() && started.elapsed() > timeout {
+                return Some(EnumerationOutcome::Inconclusive(format!("timed out after {} of {} inputs", tried, inputs)));
+            }
+            let env: HashMap<String, ConstValue> = domains.iter().zip(&position)
+                .map(|((name, values), &k)| (name.clone(), values[k].clone()))
+                .collect();
+            match self.eval_condition(&condition, &env) {
+                Some(true) => {},
+                Some(false) => return Some(EnumerationOutcome::Counterexample(env)),
+                None => undecided = undecided.or(Some(env)),
+            }
+            // Next input, the last parameter changing fastest
+            for k in (0..position.len()).rev() {
+                position[k] += 1;
+                if position[k] < domains[k].1.len() {
+                    break;
+                }
+                position[k] = 0;
+            }
+        }
+
+        match undecided {
+            Some(env) => {
+                let mut values: Vec<String> = env.iter().map(|(name, value)| format!("{} = {}", name, value)).collect();
+                values.sort();
+                Some(EnumerationOutcome::Inconclusive(format!("the condition can't be evaluated for {}", values.join(", "))))
+            },
+            None => Some(EnumerationOutcome::Proved(inputs)),
+        }
+    }
+
+    // Values of each parameter the condition reads, in the order of the signature; an error names the first one
+    // without a small finite domain
+    fn input_domains(&self, path: &[NodeIndex], item_fn: &syn::ItemFn, condition: &Expr) -> Result<Vec<(String, Vec<ConstValue>)>, String> {
+        let names = Self::identifiers(&condition.to_token_stream());
+        // Ranges once the preconditions and the facts of the parameter types are assumed
+        let entry = path.iter()
+            .find(|&&node| !matches!(self.graph[node], CfgNode::Function(_, _) | CfgNode::Precondition(_, _) | CfgNode::Assumption(_, _)))
+            .and_then(|node| self.results.intervals.get(node));
+        let mut domains = Vec::new();
+        for input in &item_fn.sig.inputs {
+            let FnArg::Typed(pat_type) = input else { continue };
+            let ty = Self::render_type(&pat_type.ty);
+            let base = ty.trim_start_matches('&').trim_start_matches("mut ");
+            for param in Self::pattern_bindings(&pat_type.pat) {
+                let tag = format!("{}_tag", Self::sanitize_identifier(&param));
+                if let Some(variants) = self.enum_variants(base).filter(|_| names.contains(&tag)) {
+                    domains.push((tag, (0..variants.len() as i128).map(ConstValue::Int).collect()));
+                }
+                if !names.contains(&param) {
+                    continue;
+                }
+                if base == "bool" {
+                    domains.push((param, vec![ConstValue::Bool(false), ConstValue::Bool(true)]));
+                    continue;
+                }
+                // The range of the type bounds what the preconditions leave open ('x: u8')
+                let Some((min, max)) = Self::integer_bounds(base) else {
+                    return Err(format!("{}: {} has no finite domain", param, ty));
+                };
+                let interval = entry.and_then(|state| state.get(&param)).copied().unwrap_or(Interval { low: None, high: None });
+                let (Some(low), Some(high)) = (interval.low.or(min.parse().ok()), interval.high.or(max.parse().ok())) else {
+                    return Err(format!("{} isn't bounded", param));
+                };
+                if high.saturating_sub(low) >= self.options.enumeration.max_inputs as i128 {
+                    return Err(format!("{} in [{}, {}] has more than {} values", param, low, high, self.options.enumeration.max_inputs));
+                }
+                let values = (low..=high).map(ConstValue::Int).collect();
+                domains.push((param, values));
+            }
+        }
+        Ok(domains)
+    }
+
+    // Truth value of a verification condition under concrete inputs: '>>' is the implication, annotations stand
+    // for their condition. None when some part can't be computed (calls, unknown variables, overflows) and the
+    // rest doesn't decide it.
+    fn eval_condition(&self, expr: &Expr, env: &HashMap<String, ConstValue>) -> Option<bool> {
+        match expr {
+            Expr::Paren(paren) => self.eval_condition(&paren.expr, env),
+            Expr::Group(group) => self.eval_condition(&group.expr, env),
+            Expr::Macro(expr_macro) => self.eval_condition(&syn::parse2::<Expr>(expr_macro.mac.tokens.clone()).ok()?, env),
+            Expr::Unary(unary) if matches!(unary.op, UnOp::Not(_)) => self.eval_condition(&unary.expr, env).map(|holds| !holds),
+            Expr::Binary(bin) if matches!(bin.op, BinOp::Shr(_) | BinOp::And(_) | BinOp::Or(_)) => {
+                let left = self.eval_condition(&bin.left, env);
+                let decided = match bin.op {
+                    BinOp::Shr(_) | BinOp::And(_) => left == Some(false),
+                    _ => left == Some(true),
+                };
+                if decided {
+                    return Some(!matches!(bin.op, BinOp::And(_)));
+                }
+                let right = self.eval_condition(&bin.right, env);
+                match (bin.op, left, right) {
+                    (BinOp::Shr(_), _, Some(true)) | (BinOp::Or(_), _, Some(true)) => Some(true),
+                    (BinOp::And(_), _, Some(false)) => Some(false),
+                    (_, Some(_), right) => right,
+                    _ => None,
+                }
+            },
+            _ => match self.eval_expr_in(expr, env)? {
+                ConstValue::Bool(value) => Some(value),
+                ConstValue::Int(_) => None,
+            },
+        }
+    }
+}
@@ -0,0 +1,86 @@
+use syn::punctuated::Punctuated;
+use syn::{BinOp, Expr, Token};
+
+use crate::cfg_builder::builder::CfgBuilder;
+
+impl CfgBuilder {
+    // Verification condition 'a >> b >> c' as one hypothesis per line and the goal, without the
+    // parentheses the substitutions added
+    pub fn readable_condition(implication: &str) -> String {
+        let Ok(condition) = syn::parse_str::<Expr>(implication) else {
+            return format!("  {}\n", implication);
+        };
+        let mut parts = Vec::new();
+        Self::split_implication(&Self::without_parens(&condition), &mut parts);
+        let goal = parts.pop().unwrap_or_default();
+        let mut readable: String = parts.iter().map(|part| format!("  assume  {}\n", part)).collect();
+        readable.push_str(&format!("  prove   {}\n", goal));
+        readable
+    }
+
+    // '>>' is right associative in the conditions: 'a >> (b >> c)'
+    fn split_implication(condition: &Expr, parts: &mut Vec<String>) {
+        match condition {
+            Expr::Binary(bin) if matches!(bin.op, BinOp::Shr(_)) => {
+                Self::split_implication(&bin.left, parts);
+                Self::split_implication(&bin.right, parts);
+            },
+            Expr::Macro(expr_macro) => {
+                let name = Self::render_path(&expr_macro.mac.path);
+                let parser = Punctuated::<Expr, Token![,]>::parse_terminated;
+                let args = match syn::parse::Parser::parse2(parser, expr_macro.mac.tokens.clone()) {
+                    Ok(args) => args.iter().map(|arg| Self::render_expr(&Self::without_parens(arg))).collect::<Vec<_>>().join(", "),
+                    Err(_) => Self::clean_up_formatting(&expr_macro.mac.tokens.to_string()),
+                };
+                parts.push(format!("{}: {}", name, args));
+            },
+            condition => parts.push(Self::render_expr(condition)),
+        }
+    }
+
+    // The expression without any parentheses: 'render_expr' puts back the ones the precedence requires
+    pub(crate) fn without_parens(expr: &Expr) -> Expr {
+        match expr {
+            Expr::Paren(paren) => Self::without_parens(&paren.expr),
+            Expr::Binary(bin) => {
+                let mut bin = bin.clone();
+                bin.left = Box::new(Self::without_parens(&bin.left));
+                bin.right = Box::new(Self::without_parens(&bin.right));
+                Expr::Binary(bin)
+            },
+            Expr::Unary(unary) => {
+                let mut unary = unary.clone();
+                unary.expr = Box::new(Self::without_parens(&unary.expr));
+                Expr::Unary(unary)
+            },
+            Expr::Call(call) => {
+                let mut call = call.clone();
+                call.args = call.args.iter().map(Self::without_parens).collect();
+                Expr::Call(call)
+            },
+            Expr::MethodCall(method_call) => {
+                let mut method_call = method_call.clone();
+                method_call.receiver = Box::new(Self::without_parens(&method_call.receiver));
+                method_call.args = method_call.args.iter().map(Self::without_parens).collect();
+                Expr::MethodCall(method_call)
+            },
+            Expr::Field(field) => {
+                let mut field = field.clone();
+                field.base = Box::new(Self::without_parens(&field.base));
+                Expr::Field(field)
+            },
+            Expr::Index(index) => {
+                let mut index = index.clone();
+                index.expr = Box::new(Self::without_parens(&index.expr));
+                index.index = Box::new(Self::without_parens(&index.index));
+                Expr::Index(index)
+            },
+            Expr::Cast(cast) => {
+                let mut cast = cast.clone();
+                cast.expr = Box::new(Self::without_parens(&cast.expr));
+                Expr::Cast(cast)
+            },
+            expr => expr.clone(),
+        }
+    }
+}
@@ -0,0 +1,70 @@
+use petgraph::graph::NodeIndex;
+use syn::Expr;
+
+use crate::cfg_builder::builder::CfgBuilder;
+use crate::cfg_builder::node::{CfgNode, ConditionalExpr, EdgeKind};
+use crate::wp_calculus::MEASURE_AT_HEAD;
+
+impl CfgBuilder {
+    // Basic path as a Hoare triple '{P} S {Q}' followed by its verification condition, for '--emit vcs':
+    // the annotations the path starts from, its statements with an 'assume' for each branch it takes, the
+    // annotation it ends at, then the weakest precondition of that annotation along the path, as the solver
+    // gets it. 'number' is the one of the run output ('Path 3').
+    pub fn hoare_triple(&self, number: usize, path: &[NodeIndex]) -> Option<String> {
+        let &last = path.last()?;
+        let mut pre = Vec::new();
+        let mut statements = Vec::new();
+        for (position, &node) in path.iter().enumerate() {
+            let next_edge = path.get(position + 1).and_then(|&next| self.graph.find_edge(node, next)).map(|edge| self.graph[edge].clone());
+            match &self.graph[node] {
+                CfgNode::Precondition(_, Some(expr)) | CfgNode::Assumption(_, Some(expr)) => pre.push(self.render_condition(expr)),
+                CfgNode::Invariant(_, Some(expr)) if position == 0 => pre.push(self.render_condition(expr)),
+                CfgNode::Statement(label, _) => statements.push(label.clone()),
+                CfgNode::Assertion(_, Some(expr)) => statements.push(format!("assert {}", self.render_condition(expr))),
+                CfgNode::Return(label, _) => statements.push(format!("return {}", label).trim_end().to_string()),
+                CfgNode::Decreases(measure, _) if position + 1 < path.len() => statements.push(format!("{} = {}", MEASURE_AT_HEAD, measure)),
+                CfgNode::Condition(label, Some(condition)) => statements.push(Self::assumed_branch(label, condition, next_edge.as_ref())),
+                _ => {},
+            }
+        }
+        let post = match &self.graph[last] {
+            CfgNode::Postcondition(_, Some(expr)) | CfgNode::Invariant(_, Some(expr)) if path.len() > 1 => self.render_condition(expr),
+            CfgNode::Decreases(_, Some(expr)) => Self::termination_goal(expr).map(|goal| self.render_condition(&goal)).unwrap_or_else(|| "true".to_string()),
+            _ => "true".to_string(),
+        };
+
+        let condition = self.substitute_constants(&self.wp_along_path(path, path.len(), None)?);
+        let mut parts = Vec::new();
+        Self::implication_parts(&condition, &mut parts);
+        let parts: Vec<String> = parts.iter().map(|part| self.render_condition(part)).collect();
+
+        let target = self.path_target(path).map(|(_, label)| label).unwrap_or_default();
+        let exit = self.exit_of_path(path).map(|exit| format!(" (exit: {})", exit)).unwrap_or_default();
+        let mut text = format!("Path {}{}: {} -> {}\n", number, exit, self.annotation_label(path[0]), target);
+        text.push_str(&format!("  {{ {} }}\n", if pre.is_empty() { "true".to_string() } else { pre.join(" && ") }));
+        for statement in statements {
+            text.push_str(&format!("      {}\n", statement));
+        }
+        text.push_str(&format!("  {{ {} }}\n", post));
+        text.push_str(&format!("  VC: {}\n", parts.join("\n      ==> ")));
+        Some(text)
+    }
+
+    // 'assume x > 0' for the true branch of 'if: x > 0', 'assume !(x > 0)' for the false one, and the label of
+    // the edge for the arms of a match and the 'for' loops
+    fn assumed_branch(label: &str, condition: &ConditionalExpr, edge: Option<&EdgeKind>) -> String {
+        let condition = match condition {
+            ConditionalExpr::If(expr) | ConditionalExpr::While(expr) => match &**expr {
+                Expr::Paren(paren) => ConditionalExpr::If(paren.expr.clone()),
+                _ => condition.clone(),
+            },
+            _ => condition.clone(),
+        };
+        match (&condition, edge) {
+            (ConditionalExpr::If(expr) | ConditionalExpr::While(expr), Some(EdgeKind::TrueBranch)) => format!("assume {}", Self::render_expr(expr)),
+            (ConditionalExpr::If(expr) | ConditionalExpr::While(expr), Some(EdgeKind::FalseBranch)) => format!("assume !({})", Self::render_expr(expr)),
+            (_, Some(edge)) if !edge.label().is_empty() => format!("assume {} [{}]", label, edge.label()),
+            _ => format!("assume {}", label),
+        }
+    }
+}
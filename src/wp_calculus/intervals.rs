@@ -0,0 +1,569 @@
+use petgraph::graph::NodeIndex;
+use petgraph::visit::{depth_first_search, DfsEvent, EdgeRef};
+use quote::ToTokens;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::fmt;
+use syn::visit::{self, Visit};
+use syn::{BinOp, Expr, ExprMethodCall, ExprReference, Lit, RangeLimits, Stmt, UnOp};
+
+use crate::cfg_builder::builder::CfgBuilder;
+use crate::cfg_builder::ConstValue;
+use crate::cfg_builder::node::{CfgNode, ConditionalExpr, EdgeKind};
+
+// Changes of the state of a loop head joined exactly; after them, the bounds still moving are widened to infinity
+const WIDENING_DELAY: usize = 3;
+
+// Range of the values of a numeric variable; a missing bound is infinite
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Interval {
+    pub low: Option<i128>,
+    pub high: Option<i128>,
+}
+
+impl Interval {
+    const TOP: Interval = Interval { low: None, high: None };
+
+    fn constant(value: i128) -> Self {
+        Interval { low: Some(value), high: Some(value) }
+    }
+
+    fn is_top(&self) -> bool {
+        self.low.is_none() && self.high.is_none()
+    }
+
+    fn is_empty(&self) -> bool {
+        matches!((self.low, self.high), (Some(low), Some(high)) if low > high)
+    }
+
+    fn join(self, other: Interval) -> Interval {
+        Interval {
+            low: self.low.zip(other.low).map(|(a, b)| a.min(b)),
+            high: self.high.zip(other.high).map(|(a, b)| a.max(b)),
+        }
+    }
+
+    fn meet(self, other: Interval) -> Interval {
+        let low = match (self.low, other.low) {
+            (Some(a), Some(b)) => Some(a.max(b)),
+            (a, b) => a.or(b),
+        };
+        let high = match (self.high, other.high) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+        Interval { low, high }
+    }
+
+    // The bounds 'next' moved past are dropped
+    fn widen(self, next: Interval) -> Interval {
+        Interval {
+            low: self.low.filter(|&low| next.low.is_some_and(|next| next >= low)),
+            high: self.high.filter(|&high| next.high.is_some_and(|next| next <= high)),
+        }
+    }
+
+    fn neg(self) -> Interval {
+        Interval { low: self.high.and_then(i128::checked_neg), high: self.low.and_then(i128::checked_neg) }
+    }
+
+    fn add(self, other: Interval) -> Interval {
+        Interval {
+            low: self.low.zip(other.low).and_then(|(a, b)| a.checked_add(b)),
+            high: self.high.zip(other.high).and_then(|(a, b)| a.checked_add(b)),
+        }
+    }
+
+    fn mul(self, other: Interval) -> Interval {
+        if let (Some(a), Some(b), Some(c), Some(d)) = (self.low, self.high, other.low, other.high) {
+            let products = [a.checked_mul(c), a.checked_mul(d), b.checked_mul(c), b.checked_mul(d)];
+            if products.iter().all(Option::is_some) {
+                let products = products.map(Option::unwrap);
+                return Interval { low: products.iter().min().copied(), high: products.iter().max().copied() };
+            }
+        }
+        // Products of non-negative values stay non-negative
+        match (self.low, other.low) {
+            (Some(a), Some(c)) if a >= 0 && c >= 0 => Interval { low: a.checked_mul(c), high: None },
+            _ => Interval::TOP,
+        }
+    }
+
+    // Rust and the solver only agree on the division of non-negative values, by a positive constant here
+    fn div(self, other: Interval) -> Interval {
+        match (self.low, other.low, other.high) {
+            (Some(low), Some(d), Some(d_high)) if low >= 0 && d > 0 && d == d_high => Interval { low: Some(low / d), high: self.high.map(|high| high / d) },
+            _ => Interval::TOP,
+        }
+    }
+
+    fn rem(self, other: Interval) -> Interval {
+        match (self.low, other.low, other.high) {
+            (Some(low), Some(d), Some(d_high)) if low >= 0 && d > 0 && d == d_high => Interval { low: Some(0), high: Some(d - 1) },
+            _ => Interval::TOP,
+        }
+    }
+}
+
+impl fmt::Display for Interval {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.low, self.high) {
+            (Some(low), Some(high)) if low == high => write!(f, "{}", low),
+            (Some(low), Some(high)) => write!(f, "[{}, {}]", low, high),
+            (Some(low), None) => write!(f, "[{}, +inf)", low),
+            (None, Some(high)) => write!(f, "(-inf, {}]", high),
+            (None, None) => write!(f, "(-inf, +inf)"),
+        }
+    }
+}
+
+// Ranges of the numeric variables (and fields, 'self.len') on entry to a node; a variable left out is unbounded
+pub type IntervalState = BTreeMap<String, Interval>;
+
+// Places whose value a statement may change without assigning them: the roots of '&mut' borrows and, with
+// 'receivers', of method receivers ('self' for 'self.bump()')
+#[derive(Default)]
+struct ClobberedPlaces {
+    roots: HashSet<String>,
+    receivers: bool,
+}
+
+impl<'ast> Visit<'ast> for ClobberedPlaces {
+    fn visit_expr_reference(&mut self, reference: &'ast ExprReference) {
+        if reference.mutability.is_some() {
+            self.roots.extend(CfgBuilder::place_root(&reference.expr));
+        }
+        visit::visit_expr_reference(self, reference);
+    }
+
+    fn visit_expr_method_call(&mut self, call: &'ast ExprMethodCall) {
+        if self.receivers {
+            self.roots.extend(CfgBuilder::place_root(&call.receiver));
+        }
+        visit::visit_expr_method_call(self, call);
+    }
+}
+
+impl CfgBuilder {
+    // Forward interval analysis of every function, from the entry with nothing known: assignments evaluate
+    // their value, conditions narrow the ranges of the branches they lead to, and preconditions and the
+    // assumptions of the graph narrow them on entry. Loop heads are widened after a few rounds. Invariants
+    // aren't assumed, so the ranges hold in every run that meets the preconditions.
+    pub fn infer_intervals(&self) -> HashMap<NodeIndex, IntervalState> {
+        let mut states: HashMap<NodeIndex, IntervalState> = HashMap::new();
+        let mut arrivals: HashMap<NodeIndex, usize> = HashMap::new();
+        for func_node in self.function_nodes() {
+            // Variables borrowed mutably can change behind the analysis's back
+            let mut borrowed = ClobberedPlaces::default();
+            for node in self.function_subgraph_nodes(func_node) {
+                if let CfgNode::Statement(_, Some(stmt)) = &self.graph[node] {
+                    borrowed.visit_stmt(stmt);
+                }
+            }
+            // Heads of the cycles seen from the entry: the back edges of the builder don't close every loop
+            // (a body ending in a branch returns to a cut point), and a cycle left unwidened doesn't converge
+            let mut loop_heads = HashSet::new();
+            depth_first_search(&self.graph, Some(func_node), |event| {
+                if let DfsEvent::BackEdge(_, head) = event {
+                    loop_heads.insert(head);
+                }
+            });
+            states.insert(func_node, IntervalState::new());
+            let mut queue = VecDeque::from([func_node]);
+            while let Some(node) = queue.pop_front() {
+                let entry = states[&node].clone();
+                for edge in self.graph.edges(node) {
+                    let Some(mut out) = self.interval_transfer(node, edge.weight(), &entry) else { continue };
+                    out.retain(|name, _| !borrowed.roots.contains(Self::root_of_place(name)));
+                    let target = edge.target();
+                    let next = match states.get(&target) {
+                        None => out,
+                        Some(previous) => {
+                            let joined = Self::join_states(previous, &out);
+                            if loop_heads.contains(&target) && arrivals.get(&target).copied().unwrap_or(0) >= WIDENING_DELAY {
+                                Self::widen_states(previous, &joined)
+                            } else {
+                                joined
+                            }
+                        },
+                    };
+                    if states.get(&target) != Some(&next) {
+                        states.insert(target, next);
+                        *arrivals.entry(target).or_default() += 1;
+                        queue.push_back(target);
+                    }
+                }
+            }
+        }
+        states
+    }
+
+    // Infer the ranges, kept for the obligations and shown in the tooltips of the nodes
+    pub fn annotate_intervals(&mut self) {
+        self.results.intervals = self.infer_intervals();
+        let nodes: Vec<NodeIndex> = self.results.intervals.keys().copied().collect();
+        for node in nodes {
+            let ranges: Vec<String> = self.results.intervals[&node].iter().map(|(name, interval)| format!("{} in {}", name, interval)).collect();
+            if !ranges.is_empty() {
+                self.metadata_mut(node).intervals = ranges;
+            }
+        }
+    }
+
+    // Whether the annotation a basic path ends at holds by the ranges on entry to it, so the path needs no
+    // solver. Returns the ranges it follows from. Only conjunctions and disjunctions of comparisons of
+    // linear expressions over the ranges are decided.
+    pub fn interval_discharge(&self, path: &[NodeIndex]) -> Option<Vec<String>> {
+        let last = *path.last()?;
+        let condition = match &self.graph[last] {
+            CfgNode::Postcondition(_, Some(expr)) | CfgNode::Invariant(_, Some(expr)) => expr,
+            _ => return None,
+        };
+        // The copy of an invariant ending a loop path stands for the invariant itself
+        let target = match self.node_id(last).strip_suffix("/end") {
+            Some(id) => self.node_by_id(id)?,
+            None => last,
+        };
+        let state = self.results.intervals.get(&target)?;
+        if !Self::holds_by_intervals(condition, state, &self.file.constants) {
+            return None;
+        }
+        // The assertions crossed on the way are checked by the path too
+        for &node in &path[..path.len() - 1] {
+            if let CfgNode::Assertion(_, Some(assertion)) = &self.graph[node] {
+                if !self.results.intervals.get(&node).is_some_and(|state| Self::holds_by_intervals(assertion, state, &self.file.constants)) {
+                    return None;
+                }
+            }
+        }
+        let names = Self::identifiers(&condition.to_token_stream());
+        Some(state.iter().filter(|(name, _)| names.contains(*name)).map(|(name, interval)| format!("{} in {}", name, interval)).collect())
+    }
+
+    fn holds_by_intervals(condition: &Expr, state: &IntervalState, constants: &HashMap<String, ConstValue>) -> bool {
+        match condition {
+            Expr::Paren(paren) => Self::holds_by_intervals(&paren.expr, state, constants),
+            // The annotation macro around the condition, 'post!(..)'
+            Expr::Macro(expr_macro) => syn::parse2::<Expr>(expr_macro.mac.tokens.clone()).is_ok_and(|inner| Self::holds_by_intervals(&inner, state, constants)),
+            Expr::Lit(lit) => matches!(&lit.lit, Lit::Bool(value) if value.value),
+            Expr::Binary(binary) => {
+                let (left, right) = (Self::eval_interval(&binary.left, state, constants), Self::eval_interval(&binary.right, state, constants));
+                match binary.op {
+                    BinOp::And(_) => Self::holds_by_intervals(&binary.left, state, constants) && Self::holds_by_intervals(&binary.right, state, constants),
+                    BinOp::Or(_) => Self::holds_by_intervals(&binary.left, state, constants) || Self::holds_by_intervals(&binary.right, state, constants),
+                    BinOp::Lt(_) => matches!((left.high, right.low), (Some(a), Some(b)) if a < b),
+                    BinOp::Le(_) => matches!((left.high, right.low), (Some(a), Some(b)) if a <= b),
+                    BinOp::Gt(_) => matches!((left.low, right.high), (Some(a), Some(b)) if a > b),
+                    BinOp::Ge(_) => matches!((left.low, right.high), (Some(a), Some(b)) if a >= b),
+                    BinOp::Eq(_) => left.low.is_some() && left.low == left.high && left == right,
+                    BinOp::Ne(_) => left.meet(right).is_empty(),
+                    _ => false,
+                }
+            },
+            _ => false,
+        }
+    }
+
+    // Range of the values of an integer expression
+    fn eval_interval(expr: &Expr, state: &IntervalState, constants: &HashMap<String, ConstValue>) -> Interval {
+        match expr {
+            Expr::Lit(lit) => match &lit.lit {
+                Lit::Int(int) => int.base10_parse::<i128>().map(Interval::constant).unwrap_or(Interval::TOP),
+                _ => Interval::TOP,
+            },
+            Expr::Path(_) | Expr::Field(_) => {
+                let Some(name) = Self::field_place_name(expr) else { return Interval::TOP };
+                match (state.get(&name), constants.get(&name)) {
+                    (Some(interval), _) => *interval,
+                    (None, Some(ConstValue::Int(value))) => Interval::constant(*value),
+                    _ => Interval::TOP,
+                }
+            },
+            Expr::Paren(paren) => Self::eval_interval(&paren.expr, state, constants),
+            Expr::Group(group) => Self::eval_interval(&group.expr, state, constants),
+            Expr::Unary(unary) if matches!(unary.op, UnOp::Neg(_)) => Self::eval_interval(&unary.expr, state, constants).neg(),
+            Expr::MethodCall(call) if call.method == "len" && call.args.is_empty() => Interval { low: Some(0), high: None },
+            Expr::Binary(binary) => {
+                let (left, right) = (Self::eval_interval(&binary.left, state, constants), Self::eval_interval(&binary.right, state, constants));
+                match binary.op {
+                    BinOp::Add(_) => left.add(right),
+                    BinOp::Sub(_) => left.add(right.neg()),
+                    BinOp::Mul(_) => left.mul(right),
+                    BinOp::Div(_) => left.div(right),
+                    BinOp::Rem(_) => left.rem(right),
+                    _ => Interval::TOP,
+                }
+            },
+            _ => Interval::TOP,
+        }
+    }
+
+    // State leaving a node through an edge, None when the edge can't be taken
+    fn interval_transfer(&self, node: NodeIndex, kind: &EdgeKind, entry: &IntervalState) -> Option<IntervalState> {
+        let mut state = entry.clone();
+        match &self.graph[node] {
+            CfgNode::Statement(stmt_str, Some(stmt)) => {
+                let mut clobbered = ClobberedPlaces { receivers: true, ..Default::default() };
+                clobbered.visit_stmt(stmt);
+                let assigned = match stmt {
+                    Stmt::Local(local) => {
+                        let bindings = Self::pattern_bindings(&local.pat);
+                        for binding in &bindings {
+                            state.remove(binding);
+                        }
+                        match (bindings.as_slice(), &local.init) {
+                            ([binding], Some((_, init))) => Some((binding.clone(), (**init).clone())),
+                            _ => None,
+                        }
+                    },
+                    _ => self.parse_assignment(stmt_str),
+                };
+                let value = assigned.as_ref().map(|(_, expr)| Self::eval_interval(expr, entry, &self.file.constants));
+                state.retain(|name, _| !clobbered.roots.contains(Self::root_of_place(name)));
+                if let Some((var, _)) = &assigned {
+                    // The fields of an overwritten value are unknown
+                    state.retain(|name, _| !name.starts_with(&format!("{}.", var)));
+                    match value.filter(|value| !value.is_top()) {
+                        Some(value) => state.insert(var.clone(), value),
+                        None => state.remove(var),
+                    };
+                }
+            },
+            CfgNode::Condition(_, Some(ConditionalExpr::If(expr) | ConditionalExpr::While(expr))) => match kind {
+                EdgeKind::TrueBranch => return self.refine_intervals(expr, true, state),
+                EdgeKind::FalseBranch => return self.refine_intervals(expr, false, state),
+                _ => {},
+            },
+            CfgNode::Condition(_, Some(ConditionalExpr::ForLoop(expr_for))) => {
+                let bindings = Self::pattern_bindings(&expr_for.pat);
+                for binding in &bindings {
+                    state.remove(binding);
+                }
+                // 'for i in a..b' runs its body with 'i' in [a, b - 1]
+                if let (EdgeKind::TrueBranch, [binding], Expr::Range(range)) = (kind, bindings.as_slice(), &*expr_for.expr) {
+                    let start = range.from.as_ref().map(|from| Self::eval_interval(from, entry, &self.file.constants)).unwrap_or(Interval::TOP);
+                    let end = range.to.as_ref().map(|to| Self::eval_interval(to, entry, &self.file.constants)).unwrap_or(Interval::TOP);
+                    let high = match range.limits {
+                        RangeLimits::HalfOpen(_) => end.high.and_then(|high| high.checked_sub(1)),
+                        RangeLimits::Closed(_) => end.high,
+                    };
+                    let interval = Interval { low: start.low, high };
+                    if interval.is_empty() {
+                        return None;
+                    }
+                    if !interval.is_top() {
+                        state.insert(binding.clone(), interval);
+                    }
+                }
+            },
+            CfgNode::Condition(_, Some(ConditionalExpr::Match(match_condition))) => {
+                // The names an arm binds shadow the variables
+                if let EdgeKind::MatchArm(label) = kind {
+                    if let Ok(tokens) = label.parse::<proc_macro2::TokenStream>() {
+                        let bound = Self::identifiers(&tokens);
+                        state.retain(|name, _| !bound.contains(Self::root_of_place(name)));
+                    }
+                }
+                if let Some(condition) = match_condition.arm_for_edge(kind).and_then(|arm| arm.condition.as_ref()) {
+                    return self.refine_intervals(condition, true, state);
+                }
+            },
+            // An assertion is checked by the path's condition, and holds after it
+            CfgNode::Precondition(_, Some(expr)) | CfgNode::Assumption(_, Some(expr)) | CfgNode::Assertion(_, Some(expr)) => return self.refine_intervals(expr, true, state),
+            _ => {},
+        }
+        Some(state)
+    }
+
+    // Narrow the state by a condition that holds ('truth') or fails; None when it can't
+    fn refine_intervals(&self, condition: &Expr, truth: bool, mut state: IntervalState) -> Option<IntervalState> {
+        match condition {
+            Expr::Paren(paren) => self.refine_intervals(&paren.expr, truth, state),
+            Expr::Macro(expr_macro) => match syn::parse2::<Expr>(expr_macro.mac.tokens.clone()) {
+                Ok(inner) => self.refine_intervals(&inner, truth, state),
+                Err(_) => Some(state),
+            },
+            Expr::Unary(unary) if matches!(unary.op, UnOp::Not(_)) => self.refine_intervals(&unary.expr, !truth, state),
+            Expr::Lit(lit) => match &lit.lit {
+                Lit::Bool(value) if value.value != truth => None,
+                _ => Some(state),
+            },
+            Expr::Let(expr_let) => {
+                for binding in Self::pattern_bindings(&expr_let.pat) {
+                    state.remove(&binding);
+                }
+                Some(state)
+            },
+            Expr::Binary(binary) => match (&binary.op, truth) {
+                (BinOp::And(_), true) | (BinOp::Or(_), false) => {
+                    let state = self.refine_intervals(&binary.left, truth, state)?;
+                    self.refine_intervals(&binary.right, truth, state)
+                },
+                (BinOp::And(_), false) | (BinOp::Or(_), true) => {
+                    match (self.refine_intervals(&binary.left, truth, state.clone()), self.refine_intervals(&binary.right, truth, state)) {
+                        (Some(left), Some(right)) => Some(Self::join_states(&left, &right)),
+                        (left, right) => left.or(right),
+                    }
+                },
+                (op, _) => {
+                    let Some(op) = Self::comparison(op, truth) else { return Some(state) };
+                    let left = Self::eval_interval(&binary.left, &state, &self.file.constants);
+                    let right = Self::eval_interval(&binary.right, &state, &self.file.constants);
+                    for (place, bound, op) in [(&binary.left, right, op), (&binary.right, left, Self::flip(op))] {
+                        let Some(name) = Self::field_place_name(place) else { continue };
+                        let current = state.get(&name).copied().unwrap_or(Interval::TOP);
+                        let narrowed = current.meet(Self::satisfying(op, bound, current));
+                        if narrowed.is_empty() {
+                            return None;
+                        }
+                        if !narrowed.is_top() {
+                            state.insert(name, narrowed);
+                        }
+                    }
+                    Some(state)
+                },
+            },
+            _ => Some(state),
+        }
+    }
+
+    // Comparison that holds when 'op' evaluates to 'truth': '<' failing is '>='
+    fn comparison(op: &BinOp, truth: bool) -> Option<&'static str> {
+        let (holds, fails) = match op {
+            BinOp::Lt(_) => ("<", ">="),
+            BinOp::Le(_) => ("<=", ">"),
+            BinOp::Gt(_) => (">", "<="),
+            BinOp::Ge(_) => (">=", "<"),
+            BinOp::Eq(_) => ("==", "!="),
+            BinOp::Ne(_) => ("!=", "=="),
+            _ => return None,
+        };
+        Some(if truth { holds } else { fails })
+    }
+
+    // 'a < b' is 'b > a'
+    fn flip(op: &str) -> &'static str {
+        match op {
+            "<" => ">",
+            "<=" => ">=",
+            ">" => "<",
+            ">=" => "<=",
+            "==" => "==",
+            _ => "!=",
+        }
+    }
+
+    // Values 'x' with 'x op y' for some 'y' in 'bound'; 'current' is the range of 'x', for '!='
+    fn satisfying(op: &str, bound: Interval, current: Interval) -> Interval {
+        match op {
+            "<" => Interval { low: None, high: bound.high.and_then(|high| high.checked_sub(1)) },
+            "<=" => Interval { low: None, high: bound.high },
+            ">" => Interval { low: bound.low.and_then(|low| low.checked_add(1)), high: None },
+            ">=" => Interval { low: bound.low, high: None },
+            "==" => bound,
+            // Only a bound of 'x' equal to the one excluded value moves
+            _ => match (bound.low, bound.high) {
+                (Some(value), Some(high)) if value == high => Interval {
+                    low: current.low.map(|low| if low == value { low + 1 } else { low }),
+                    high: current.high.map(|high| if high == value { high - 1 } else { high }),
+                },
+                _ => Interval::TOP,
+            },
+        }
+    }
+
+    fn join_states(a: &IntervalState, b: &IntervalState) -> IntervalState {
+        a.iter()
+            .filter_map(|(name, interval)| b.get(name).map(|other| (name.clone(), interval.join(*other))))
+            .filter(|(_, interval)| !interval.is_top())
+            .collect()
+    }
+
+    fn widen_states(previous: &IntervalState, next: &IntervalState) -> IntervalState {
+        previous.iter()
+            .filter_map(|(name, interval)| next.get(name).map(|other| (name.clone(), interval.widen(*other))))
+            .filter(|(_, interval)| !interval.is_top())
+            .collect()
+    }
+
+    // 'self' for 'self.len'
+    fn root_of_place(name: &str) -> &str {
+        name.split('.').next().unwrap_or(name)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{built, discharge_paths};
+    use crate::verifier::VcOutcome;
+
+    fn annotated(source: &str) -> (CfgBuilder, Vec<Vec<NodeIndex>>) {
+        let mut builder = built(source);
+        builder.annotate_intervals();
+        let paths = builder.generate_basic_paths();
+        (builder, paths)
+    }
+
+    #[test]
+    fn postconditions_implied_by_the_ranges_need_no_solver() {
+        let (builder, paths) = annotated("fn clamp(x: u32) -> u32 {
+            pre!(x < 10);
+            post!(y < 20 && y >= 1);
+            let y = x + 1;
+            return y;
+        }");
+        let ranges: Vec<Vec<String>> = paths.iter().filter_map(|path| builder.interval_discharge(path)).collect();
+        assert_eq!(ranges, vec![vec!["y in [1, 10]".to_string()]]);
+
+        let (builder, paths) = annotated("fn clamp(x: u32) -> u32 {
+            pre!(x < 10);
+            post!(y < 10);
+            let y = x + 1;
+            return y;
+        }");
+        assert!(paths.iter().all(|path| builder.interval_discharge(path).is_none()));
+    }
+
+    // A discharged path is one the solver proves; the others are still decided by the solver
+    #[test]
+    fn the_solver_agrees_with_the_discharged_paths() {
+        let verdicts = |post: &str| {
+            let (builder, paths) = annotated(&format!("fn clamp(x: u32) -> u32 {{
+                pre!(x < 10);
+                post!({});
+                let y = x + 1;
+                return y;
+            }}", post));
+            let discharged: Vec<bool> = paths.iter().map(|path| builder.interval_discharge(path).is_some()).collect();
+            (discharged, discharge_paths(&builder, &paths))
+        };
+        let (discharged, outcomes) = verdicts("y < 20 && y >= 1");
+        assert_eq!(discharged, [true]);
+        assert!(matches!(outcomes.as_slice(), [VcOutcome::Valid]));
+        let (discharged, outcomes) = verdicts("y < 10");
+        assert_eq!(discharged, [false]);
+        assert!(matches!(outcomes.as_slice(), [VcOutcome::Counterexample(_)]));
+        let (discharged, outcomes) = verdicts("y == x + 1");
+        assert_eq!(discharged, [false]);
+        assert!(matches!(outcomes.as_slice(), [VcOutcome::Valid]));
+    }
+
+    #[test]
+    fn removing_a_node_moves_the_ranges_of_the_last_one() {
+        let mut builder = CfgBuilder::new();
+        let removed = builder.add_node_without_edge(CfgNode::MergePoint);
+        let kept = builder.add_node_without_edge(CfgNode::MergePoint);
+        let last = builder.add_node_without_edge(CfgNode::MergePoint);
+        let ranges = |low| IntervalState::from([("x".to_string(), Interval { low: Some(low), high: None })]);
+        builder.results.intervals = HashMap::from([(removed, ranges(0)), (kept, ranges(1)), (last, ranges(2))]);
+        builder.results.node_ids = HashMap::from([(kept, "kept".to_string()), (last, "last".to_string())]);
+        builder.loop_exits = HashSet::from([last]);
+
+        builder.remove_node(removed);
+        // petgraph moved the last node into the freed index
+        assert_eq!(builder.results.intervals, HashMap::from([(removed, ranges(2)), (kept, ranges(1))]));
+        assert_eq!(builder.results.node_ids[&removed], "last");
+        assert_eq!(builder.loop_exits, HashSet::from([removed]));
+    }
+}
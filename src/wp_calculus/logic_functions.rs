@@ -0,0 +1,400 @@
+use proc_macro2::{TokenStream, TokenTree};
+use quote::quote;
+use std::collections::{BTreeSet, HashMap};
+use syn::visit::{self, Visit};
+use syn::{BinOp, Block, Expr, ExprCall, ExprMacro, FnArg, ItemFn, Pat, ReturnType, Stmt, Type, UnOp};
+
+use crate::cfg_builder::builder::CfgBuilder;
+use crate::verifier::{self, VcOutcome};
+
+// A '#[logic]' function: a pure function of integers and booleans that specs can call, translated to a
+// function of the solver defined by its body
+#[derive(Debug, Clone)]
+pub struct LogicFunction {
+    pub item_fn: ItemFn,
+    pub body: Option<Expr>, // the body with its 'let's substituted, when it can be translated
+    pub decreases: Option<Expr>, // measure of a recursive function, from 'decreases!(..)'
+    pub status: LogicStatus,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LogicStatus {
+    Defined,
+    Uninterpreted(String), // why the body isn't used: calls are then only equal for equal arguments
+}
+
+// Logic functions called by a condition, in annotations too
+struct LogicCalls<'a> {
+    builder: &'a CfgBuilder,
+    names: BTreeSet<String>,
+}
+
+impl<'ast> Visit<'ast> for LogicCalls<'_> {
+    fn visit_expr_call(&mut self, i: &'ast ExprCall) {
+        if let Some(name) = CfgBuilder::logic_callee(i).filter(|name| self.builder.file.logic_functions.contains_key(name)) {
+            self.names.insert(name);
+        }
+        visit::visit_expr_call(self, i);
+    }
+
+    fn visit_expr_macro(&mut self, i: &'ast ExprMacro) {
+        if let Ok(inner) = syn::parse2::<Expr>(i.mac.tokens.clone()) {
+            self.visit_expr(&inner);
+        }
+    }
+}
+
+impl CfgBuilder {
+    // '#[logic]', '#[secured::logic]', or '#[cfg_attr(.., logic)]' for code that must build without the tool
+    pub fn is_logic_function(item_fn: &ItemFn) -> bool {
+        item_fn.attrs.iter().any(|attr| {
+            if attr.path.segments.last().is_some_and(|segment| segment.ident == "logic") {
+                return true;
+            }
+            let Some(TokenTree::Group(group)) = attr.tokens.clone().into_iter().next() else { return false };
+            attr.path.is_ident("cfg_attr") && group.stream().into_iter()
+                .skip_while(|token| !matches!(token, TokenTree::Punct(punct) if punct.as_char() == ','))
+                .any(|token| matches!(token, TokenTree::Ident(ident) if ident == "logic"))
+        })
+    }
+
+    // Record the logic functions of the file (and of its inline modules) by name
+    pub fn collect_logic_functions(&mut self, items: &[syn::Item]) {
+        for item in items {
+            match item {
+                syn::Item::Fn(item_fn) if Self::is_logic_function(item_fn) => {
+                    let (body, decreases, status) = match self.logic_body(&item_fn.block) {
+                        Ok((body, decreases)) => (Some(body), decreases, LogicStatus::Defined),
+                        Err(reason) => (None, None, LogicStatus::Uninterpreted(reason)),
+                    };
+                    let function = LogicFunction { item_fn: item_fn.clone(), body, decreases, status };
+                    self.file.logic_functions.insert(item_fn.sig.ident.to_string(), function);
+                },
+                syn::Item::Mod(item_mod) => {
+                    if let Some((_, items)) = &item_mod.content {
+                        self.collect_logic_functions(items);
+                    }
+                },
+                _ => {},
+            }
+        }
+    }
+
+    // Check that each logic function can be defined by its body: integer and boolean values, supported
+    // expressions, and for a recursive function a measure that stays non-negative and decreases at every
+    // recursive call. Functions that fail are left uninterpreted. Returns the status of each function.
+    pub fn check_logic_functions(&mut self) -> Vec<(String, LogicStatus)> {
+        let names: Vec<String> = self.file.logic_functions.keys().cloned().collect();
+        for name in &names {
+            let function = &self.file.logic_functions[name];
+            if function.status != LogicStatus::Defined {
+                continue;
+            }
+            let status = match self.logic_definition_problem(name, function) {
+                Some(reason) => LogicStatus::Uninterpreted(reason),
+                None => LogicStatus::Defined,
+            };
+            if let Some(function) = self.file.logic_functions.get_mut(name) {
+                function.status = status;
+            }
+        }
+        names.into_iter().map(|name| {
+            let status = self.file.logic_functions[&name].status.clone();
+            (name, status)
+        }).collect()
+    }
+
+    // Prefix a condition with the 'logic!(fn ..)' definitions of the logic functions it calls, and of the
+    // ones those call. An uninterpreted function is declared without its body.
+    pub fn with_logic_definitions(&self, condition: &Expr) -> Expr {
+        let mut names = self.called_logic_functions(condition);
+        let mut pending: Vec<String> = names.iter().cloned().collect();
+        while let Some(name) = pending.pop() {
+            let Some(body) = self.file.logic_functions.get(&name).and_then(|function| function.body.as_ref()) else { continue };
+            for callee in self.called_logic_functions(body) {
+                if names.insert(callee.clone()) {
+                    pending.push(callee);
+                }
+            }
+        }
+        names.iter().rev().fold(condition.clone(), |condition, name| {
+            let definition = self.logic_definition(name, self.file.logic_functions[name].status == LogicStatus::Defined);
+            let condition = Self::wrap_with_parens(condition);
+            syn::parse_quote! { #definition >> #condition }
+        })
+    }
+
+    fn called_logic_functions(&self, expr: &Expr) -> BTreeSet<String> {
+        if self.file.logic_functions.is_empty() {
+            return BTreeSet::new();
+        }
+        let mut calls = LogicCalls { builder: self, names: BTreeSet::new() };
+        calls.visit_expr(expr);
+        calls.names
+    }
+
+    // 'logic!(fn f(n: i64) -> i64 { body })', or 'logic!(fn f(n: i64) -> i64;)' without its body
+    fn logic_definition(&self, name: &str, with_body: bool) -> Expr {
+        let function = &self.file.logic_functions[name];
+        let sig = &function.item_fn.sig;
+        let tokens: TokenStream = match (&function.body, with_body) {
+            (Some(body), true) => quote! { #sig { #body } },
+            _ => quote! { #sig; },
+        };
+        syn::parse_quote! { logic!(#tokens) }
+    }
+
+    fn logic_callee(call: &ExprCall) -> Option<String> {
+        match &*call.func {
+            Expr::Path(func) => func.path.segments.last().map(|segment| segment.ident.to_string()),
+            _ => None,
+        }
+    }
+
+    // Body of a logic function as one expression: an optional leading 'decreases!(measure);', 'let's
+    // (substituted in what follows them) and the returned expression
+    fn logic_body(&self, block: &Block) -> Result<(Expr, Option<Expr>), String> {
+        let mut stmts = block.stmts.as_slice();
+        let mut decreases = None;
+        if let Some(Stmt::Semi(Expr::Macro(expr_macro), _)) = stmts.first() {
+            if expr_macro.mac.path.is_ident("decreases") {
+                decreases = Some(syn::parse2::<Expr>(expr_macro.mac.tokens.clone()).map_err(|_| "measure of 'decreases!' is not an expression".to_string())?);
+                stmts = &stmts[1..];
+            }
+        }
+        let Some((Stmt::Expr(tail), lets)) = stmts.split_last() else {
+            return Err("body doesn't end with the returned expression".to_string());
+        };
+        let mut bindings = Vec::new();
+        for stmt in lets {
+            match stmt {
+                Stmt::Local(local) => match (&local.pat, &local.init) {
+                    (Pat::Ident(pat_ident), Some((_, init))) => bindings.push((pat_ident.ident.to_string(), (**init).clone())),
+                    (Pat::Type(pat_type), Some((_, init))) if matches!(&*pat_type.pat, Pat::Ident(_)) => {
+                        let Pat::Ident(pat_ident) = &*pat_type.pat else { unreachable!() };
+                        bindings.push((pat_ident.ident.to_string(), (**init).clone()));
+                    },
+                    _ => return Err(format!("unsupported statement '{}'", quote!(#stmt))),
+                },
+                stmt => return Err(format!("unsupported statement '{}'", quote!(#stmt))),
+            }
+        }
+        let body = bindings.iter().rev().fold(tail.clone(), |body, (var, value)| self.recursive_substitution(&body, var, value));
+        Ok((body, decreases))
+    }
+
+    // Why the body of a logic function can't define it, if it can't
+    fn logic_definition_problem(&self, name: &str, function: &LogicFunction) -> Option<String> {
+        let sig = &function.item_fn.sig;
+        for input in &sig.inputs {
+            let FnArg::Typed(pat_type) = input else { return Some("methods can't be logic functions".to_string()) };
+            if !matches!(&*pat_type.pat, Pat::Ident(_)) || !Self::is_logic_type(&pat_type.ty) {
+                return Some(format!("parameter '{}' is not an integer or a boolean", quote!(#pat_type)));
+            }
+        }
+        match &sig.output {
+            ReturnType::Type(_, ty) if Self::is_logic_type(ty) => {},
+            _ => return Some("doesn't return an integer or a boolean".to_string()),
+        }
+        let body = function.body.as_ref()?;
+        let env: HashMap<String, String> = sig.inputs.iter()
+            .filter_map(|input| match input {
+                FnArg::Typed(pat_type) => match &*pat_type.pat {
+                    Pat::Ident(pat_ident) => Some((pat_ident.ident.to_string(), Self::render_type(&pat_type.ty))),
+                    _ => None,
+                },
+                FnArg::Receiver(_) => None,
+            })
+            .collect();
+        if let Some(construct) = self.unsupported_logic_construct(body, &env) {
+            return Some(format!("'{}' can't be translated", construct));
+        }
+
+        // Calls back to the function through other logic functions have no measure to check
+        let mut reached = BTreeSet::new();
+        let mut pending: Vec<String> = self.called_logic_functions(body).into_iter().filter(|callee| callee != name).collect();
+        while let Some(callee) = pending.pop() {
+            if callee == name {
+                return Some("mutually recursive logic functions are not supported".to_string());
+            }
+            if reached.insert(callee.clone()) {
+                if let Some(callee_body) = self.file.logic_functions.get(&callee).and_then(|function| function.body.as_ref()) {
+                    pending.extend(self.called_logic_functions(callee_body));
+                }
+            }
+        }
+
+        let mut recursive_calls = Vec::new();
+        self.recursive_calls(name, body, &mut Vec::new(), &mut recursive_calls);
+        if recursive_calls.is_empty() {
+            return None;
+        }
+        let Some(measure) = &function.decreases else {
+            return Some("recursive without a 'decreases!' measure".to_string());
+        };
+        for (conditions, call) in recursive_calls {
+            let obligation = self.termination_obligation(sig, measure, &conditions, &call);
+            let call_text = Self::render_expr(&Expr::Call(call));
+            if !matches!(verifier::verify_str_implication(&quote!(#obligation).to_string(), &self.options.solver_settings), Ok(VcOutcome::Valid)) {
+                return Some(format!("measure '{}' doesn't decrease at {}", quote!(#measure), call_text));
+            }
+        }
+        None
+    }
+
+    fn is_logic_type(ty: &Type) -> bool {
+        let ty = Self::render_type(ty);
+        ty == "bool" || Self::integer_bounds(&ty).is_some()
+    }
+
+    // First sub-expression the solver can't translate, if any, with the parameter types in 'env'
+    fn unsupported_logic_construct(&self, expr: &Expr, env: &HashMap<String, String>) -> Option<String> {
+        let tail = |block: &Block| match block.stmts.as_slice() {
+            [Stmt::Expr(tail)] => Ok(tail.clone()),
+            _ => Err(quote!(#block).to_string()),
+        };
+        match expr {
+            Expr::Lit(lit) if matches!(lit.lit, syn::Lit::Int(_) | syn::Lit::Bool(_)) => None,
+            Expr::Path(path) if path.path.get_ident().is_some() => None,
+            Expr::Paren(paren) => self.unsupported_logic_construct(&paren.expr, env),
+            // A narrowing cast changes the values that don't fit, which the function would have to spell out
+            Expr::Cast(cast) => match self.cast_info(cast, env) {
+                info if info.narrowing || info.from.is_none() => Some(Self::render_expr(expr)),
+                _ => self.unsupported_logic_construct(&cast.expr, env),
+            },
+            Expr::Unary(unary) if matches!(unary.op, UnOp::Not(_) | UnOp::Neg(_)) => self.unsupported_logic_construct(&unary.expr, env),
+            Expr::Binary(bin) if matches!(bin.op, BinOp::And(_) | BinOp::Or(_) | BinOp::Eq(_) | BinOp::Ne(_) | BinOp::Le(_) | BinOp::Ge(_)
+                | BinOp::Lt(_) | BinOp::Gt(_) | BinOp::Add(_) | BinOp::Sub(_) | BinOp::Mul(_) | BinOp::Div(_)) => {
+                self.unsupported_logic_construct(&bin.left, env).or_else(|| self.unsupported_logic_construct(&bin.right, env))
+            },
+            Expr::Block(block) => match tail(&block.block) {
+                Ok(tail) => self.unsupported_logic_construct(&tail, env),
+                Err(block) => Some(block),
+            },
+            Expr::If(expr_if) => {
+                let Some((_, else_branch)) = &expr_if.else_branch else { return Some(Self::render_expr(expr)) };
+                let then_branch = match tail(&expr_if.then_branch) {
+                    Ok(tail) => tail,
+                    Err(block) => return Some(block),
+                };
+                self.unsupported_logic_construct(&expr_if.cond, env)
+                    .or_else(|| self.unsupported_logic_construct(&then_branch, env))
+                    .or_else(|| self.unsupported_logic_construct(else_branch, env))
+            },
+            Expr::Call(call) if Self::logic_callee(call).is_some_and(|name| self.file.logic_functions.contains_key(&name)) => {
+                call.args.iter().find_map(|arg| self.unsupported_logic_construct(arg, env))
+            },
+            _ => Some(Self::render_expr(expr)),
+        }
+    }
+
+    // Calls of 'name' in 'expr', each with the conditions under which it's evaluated
+    fn recursive_calls(&self, name: &str, expr: &Expr, conditions: &mut Vec<Expr>, calls: &mut Vec<(Vec<Expr>, ExprCall)>) {
+        let negated = |cond: &Expr| -> Expr {
+            let cond = Self::wrap_with_parens(cond.clone());
+            syn::parse_quote! { !#cond }
+        };
+        match expr {
+            Expr::If(expr_if) => {
+                self.recursive_calls(name, &expr_if.cond, conditions, calls);
+                conditions.push((*expr_if.cond).clone());
+                for stmt in &expr_if.then_branch.stmts {
+                    if let Stmt::Expr(tail) = stmt {
+                        self.recursive_calls(name, tail, conditions, calls);
+                    }
+                }
+                conditions.pop();
+                if let Some((_, else_branch)) = &expr_if.else_branch {
+                    conditions.push(negated(&expr_if.cond));
+                    self.recursive_calls(name, else_branch, conditions, calls);
+                    conditions.pop();
+                }
+            },
+            // The right side of '&&' is evaluated when the left one holds, of '||' when it doesn't
+            Expr::Binary(bin) if matches!(bin.op, BinOp::And(_) | BinOp::Or(_)) => {
+                self.recursive_calls(name, &bin.left, conditions, calls);
+                conditions.push(if matches!(bin.op, BinOp::And(_)) { (*bin.left).clone() } else { negated(&bin.left) });
+                self.recursive_calls(name, &bin.right, conditions, calls);
+                conditions.pop();
+            },
+            Expr::Binary(bin) => {
+                self.recursive_calls(name, &bin.left, conditions, calls);
+                self.recursive_calls(name, &bin.right, conditions, calls);
+            },
+            Expr::Paren(paren) => self.recursive_calls(name, &paren.expr, conditions, calls),
+            Expr::Cast(cast) => self.recursive_calls(name, &cast.expr, conditions, calls),
+            Expr::Unary(unary) => self.recursive_calls(name, &unary.expr, conditions, calls),
+            Expr::Block(block) => {
+                for stmt in &block.block.stmts {
+                    if let Stmt::Expr(tail) = stmt {
+                        self.recursive_calls(name, tail, conditions, calls);
+                    }
+                }
+            },
+            Expr::Call(call) => {
+                for arg in &call.args {
+                    self.recursive_calls(name, arg, conditions, calls);
+                }
+                if Self::logic_callee(call).as_deref() == Some(name) {
+                    calls.push((conditions.clone(), call.clone()));
+                }
+            },
+            _ => {},
+        }
+    }
+
+    // 'c1 >> (c2 >> (0 <= m' && m' < m))' where m' is the measure for the arguments of the call. Logic
+    // functions in the conditions are only declared: the proof can't rely on the definition it checks.
+    fn termination_obligation(&self, sig: &syn::Signature, measure: &Expr, conditions: &[Expr], call: &ExprCall) -> Expr {
+        let params: Vec<String> = sig.inputs.iter()
+            .filter_map(|input| match input {
+                FnArg::Typed(pat_type) => Some(Self::pattern_bindings(&pat_type.pat)),
+                FnArg::Receiver(_) => None,
+            })
+            .flatten()
+            .collect();
+        // Parameters are replaced all at once, through fresh names, so 'f(b, a)' swaps them
+        let renamed = params.iter().enumerate().fold(measure.clone(), |measure, (index, param)| {
+            self.recursive_substitution(&measure, param, &syn::parse_str(&format!("secrust_arg_{}", index)).expect("fresh name"))
+        });
+        let next = call.args.iter().enumerate().fold(renamed, |measure, (index, arg)| {
+            self.recursive_substitution(&measure, &format!("secrust_arg_{}", index), arg)
+        });
+        let (measure, next) = (Self::wrap_with_parens(measure.clone()), Self::wrap_with_parens(next));
+        let goal: Expr = syn::parse_quote! { (0 <= #next && #next < #measure) };
+        let obligation = conditions.iter().rev().fold(goal, |goal, condition| {
+            let condition = Self::wrap_with_parens(condition.clone());
+            syn::parse_quote! { #condition >> (#goal) }
+        });
+        self.called_logic_functions(&obligation).iter().rev().fold(obligation, |obligation, name| {
+            let declaration = self.logic_definition(name, false);
+            syn::parse_quote! { #declaration >> (#obligation) }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{built, discharge};
+
+    fn verdicts(source: &str) -> (Vec<(String, LogicStatus)>, Vec<VcOutcome>) {
+        let mut builder = built(source);
+        let statuses = builder.check_logic_functions();
+        (statuses, discharge(&mut builder))
+    }
+
+    #[test]
+    fn only_casts_keeping_the_value_are_defined() {
+        let (statuses, outcomes) = verdicts("#[logic] fn widen(x: u8) -> i64 { x as i64 }
+            fn f(x: u8) -> i64 { pre!(x == 200); post!(r == widen(x)); let r = 200; return r; }");
+        assert_eq!(statuses, vec![("widen".to_string(), LogicStatus::Defined)]);
+        assert!(matches!(outcomes.as_slice(), [VcOutcome::Valid]));
+
+        // Left uninterpreted, 'low(x)' is not known to be 44
+        let (statuses, outcomes) = verdicts("#[logic] fn low(x: i32) -> u8 { x as u8 }
+            fn f(x: i32) -> u8 { pre!(x == 300); post!(r == low(x)); let r = 44; return r; }");
+        assert_eq!(statuses, vec![("low".to_string(), LogicStatus::Uninterpreted("'x as u8' can't be translated".to_string()))]);
+        assert!(matches!(outcomes.as_slice(), [VcOutcome::Counterexample(_)]));
+    }
+}
@@ -1,3 +1,25 @@
 mod wp_calculus;
+mod path_trace;
+mod simulation;
+mod panic_freedom;
+mod explain;
+mod relevance;
+mod pure_calls;
+mod range_facts;
+mod none_safety;
+mod logic_functions;
+mod intervals;
+mod enumeration;
+mod path_conditions;
+mod hoare;
+mod references;
+mod termination;
 
-pub use wp_calculus::*;
\ No newline at end of file
+pub use wp_calculus::*;
+pub use termination::MEASURE_AT_HEAD;
+pub use panic_freedom::*;
+pub use none_safety::*;
+pub use logic_functions::{LogicFunction, LogicStatus};
+pub use intervals::{Interval, IntervalState};
+pub use enumeration::EnumerationOutcome;
+pub use simulation::{PathSimulation, SimulationOutcome};
\ No newline at end of file
@@ -0,0 +1,471 @@
+use petgraph::graph::NodeIndex;
+use petgraph::visit::EdgeRef;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use syn::visit::{self, Visit};
+use syn::{BinOp, Expr, ExprClosure, ExprIndex, ExprMethodCall, Pat, ReturnType, Stmt, UnOp};
+
+use crate::cfg_builder::builder::CfgBuilder;
+use crate::cfg_builder::node::{CfgNode, ConditionalExpr, EdgeKind};
+
+// What is known of an Option value at a point of a path
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum OptionState {
+    Some,
+    None,
+    Maybe, // either, e.g. a parameter or the result of 'v.get(i)'
+}
+
+impl OptionState {
+    fn join(self, other: OptionState) -> OptionState {
+        if self == other { self } else { OptionState::Maybe }
+    }
+}
+
+impl fmt::Display for OptionState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            OptionState::Some => "Some",
+            OptionState::None => "None",
+            OptionState::Maybe => "possibly None",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+// An 'unwrap'/'expect' of an Option, or an index into a map, with what is known of the value on every
+// path reaching it. A check fails when one path reaches it with a value that may be None; that path is kept.
+#[derive(Debug, Clone)]
+pub struct NoneCheck {
+    pub function: String,
+    pub function_id: String,
+    pub node: NodeIndex,
+    pub operation: String, // e.g. 'x.unwrap()', 'scores[&name]'
+    pub value: String, // the Option ('x') or the map entry ('scores[name]')
+    pub state: OptionState, // Some when every path gives a value
+    pub path: Vec<NodeIndex>, // from the function entry to the operation, on a path where it fails
+}
+
+// Values of the Option variables (and map entries 'm[k]') along a path
+type OptionStates = BTreeMap<String, OptionState>;
+
+// Operation, value read and its state, for each unwrap or map index of a node
+type NoneUse = (String, String, OptionState);
+
+// Receives the uses of each node reached, with the path that reached it
+type NoneReport<'a> = dyn FnMut(NodeIndex, Vec<NoneUse>, &[NodeIndex]) + 'a;
+
+// Methods returning an Option that may be None whatever their receiver
+const MAYBE_NONE_METHODS: [&str; 17] = [
+    "get", "get_mut", "pop", "pop_front", "pop_back", "first", "last", "next", "find", "position", "max", "min", "ok", "err",
+    "strip_prefix", "strip_suffix", "remove",
+];
+
+// Methods of an Option returning an Option in the same state
+const SAME_STATE_METHODS: [&str; 11] = ["clone", "as_ref", "as_mut", "as_deref", "copied", "cloned", "map", "inspect", "take", "replace", "to_owned"];
+
+// What the analysis of one function knows besides the states
+struct NoneContext<'a> {
+    options: HashSet<String>, // variables of an Option type
+    maps: HashSet<String>, // variables of a map type, indexed by key
+    summaries: &'a HashMap<String, OptionState>, // returned value of the functions returning an Option
+    module: String, // module path of the function, to resolve the calls of its siblings
+}
+
+// The unwraps and map indexes of a node, with the value they read
+struct NoneUses<'a, 'b> {
+    builder: &'a CfgBuilder,
+    context: &'a NoneContext<'b>,
+    states: &'a OptionStates,
+    uses: Vec<NoneUse>,
+}
+
+impl<'ast> Visit<'ast> for NoneUses<'_, '_> {
+    fn visit_expr_method_call(&mut self, i: &'ast ExprMethodCall) {
+        if matches!(i.method.to_string().as_str(), "unwrap" | "expect") {
+            if let Some(state) = self.builder.option_state(&i.receiver, self.states, self.context) {
+                let operation = CfgBuilder::render_expr(&Expr::MethodCall(i.clone()));
+                self.uses.push((operation, CfgBuilder::render_expr(&i.receiver), state));
+            }
+        }
+        visit::visit_expr_method_call(self, i);
+    }
+
+    fn visit_expr_index(&mut self, i: &'ast ExprIndex) {
+        if let Some(entry) = CfgBuilder::map_entry(&i.expr, &i.index, self.context) {
+            let state = self.states.get(&entry).copied().unwrap_or(OptionState::Maybe);
+            self.uses.push((CfgBuilder::render_expr(&Expr::Index(i.clone())), entry, state));
+        }
+        visit::visit_expr_index(self, i);
+    }
+
+    // Closure bodies run when the closure is called, not where it is defined
+    fn visit_expr_closure(&mut self, _i: &'ast ExprClosure) {}
+}
+
+// The updates of the states made by the method calls of a node: 'x.take()' leaves None, 'x.insert(v)'
+// leaves Some, an 'x.unwrap()' that didn't panic tells that x is Some
+struct NoneEffects<'a, 'b> {
+    context: &'a NoneContext<'b>,
+    states: &'a mut OptionStates,
+}
+
+impl<'ast> Visit<'ast> for NoneEffects<'_, '_> {
+    fn visit_expr_method_call(&mut self, i: &'ast ExprMethodCall) {
+        visit::visit_expr_method_call(self, i);
+        let method = i.method.to_string();
+        if let Some(var) = CfgBuilder::option_variable(&i.receiver).filter(|var| self.context.options.contains(var)) {
+            match method.as_str() {
+                "take" => { self.states.insert(var, OptionState::None); },
+                "insert" | "get_or_insert" | "get_or_insert_with" | "replace" | "unwrap" | "expect" => { self.states.insert(var, OptionState::Some); },
+                _ => {},
+            }
+        }
+        if let Some(key) = i.args.first() {
+            if let Some(entry) = CfgBuilder::map_entry(&i.receiver, key, self.context) {
+                match method.as_str() {
+                    "insert" => { self.states.insert(entry, OptionState::Some); },
+                    "remove" => { self.states.insert(entry, OptionState::None); },
+                    _ => {},
+                }
+            }
+        }
+    }
+
+    fn visit_expr_closure(&mut self, _i: &'ast ExprClosure) {}
+}
+
+impl CfgBuilder {
+    // Checks of the none-safety goal, in every analyzed function. The values returned by the functions of the
+    // file are summarized first, so that a call of a function that may return None gives a value that may be None.
+    pub fn none_safety_checks(&self) -> Vec<NoneCheck> {
+        let option_functions: Vec<(String, NodeIndex)> = self.function_nodes().into_iter()
+            .filter_map(|func_node| match &self.graph[func_node] {
+                CfgNode::Function(name, Some(item_fn)) if Self::returns_option(&item_fn.sig.output) => Some((name.clone(), func_node)),
+                _ => None,
+            })
+            .collect();
+
+        // Summaries start at Some and only grow to None or Maybe; each round may propagate one more call level
+        let mut summaries: HashMap<String, OptionState> = option_functions.iter().map(|(name, _)| (name.clone(), OptionState::Some)).collect();
+        for _ in 0..=option_functions.len() {
+            let mut changed = false;
+            for (name, func_node) in &option_functions {
+                let (_, returned) = self.analyze_none_safety(*func_node, &summaries);
+                let returned = returned.unwrap_or(OptionState::Some);
+                if summaries.get(name) != Some(&returned) {
+                    summaries.insert(name.clone(), returned);
+                    changed = true;
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+
+        self.function_nodes().into_iter()
+            .flat_map(|func_node| self.analyze_none_safety(func_node, &summaries).0)
+            .collect()
+    }
+
+    // Checks of one function and the state of the value it returns (None if it never returns)
+    fn analyze_none_safety(&self, func_node: NodeIndex, summaries: &HashMap<String, OptionState>) -> (Vec<NoneCheck>, Option<OptionState>) {
+        let function = self.graph[func_node].label();
+        let type_env = self.function_type_env(func_node);
+        let type_of = |prefixes: &[&str]| -> HashSet<String> {
+            type_env.iter()
+                .filter(|(_, ty)| prefixes.iter().any(|prefix| ty.trim_start_matches('&').trim_start_matches("mut ").starts_with(prefix)))
+                .map(|(var, _)| var.clone())
+                .collect()
+        };
+        let context = NoneContext {
+            options: type_of(&["Option"]),
+            maps: type_of(&["HashMap", "BTreeMap"]),
+            summaries,
+            module: function.rsplit_once("::").map(|(module, _)| module.to_string()).unwrap_or_default(),
+        };
+        // Parameters may be None: nothing is known of the callers
+        let params: OptionStates = match &self.graph[func_node] {
+            CfgNode::Function(_, Some(item_fn)) => item_fn.sig.inputs.iter()
+                .filter_map(|input| match input {
+                    syn::FnArg::Typed(pat_type) => Some(Self::pattern_bindings(&pat_type.pat)),
+                    syn::FnArg::Receiver(_) => None,
+                })
+                .flatten()
+                .filter(|param| context.options.contains(param))
+                .map(|param| (param, OptionState::Maybe))
+                .collect(),
+            _ => OptionStates::new(),
+        };
+
+        let mut checks: HashMap<(NodeIndex, String), NoneCheck> = HashMap::new();
+        let mut returned = None;
+        let mut visited = HashSet::new();
+        self.explore_none_states(func_node, params, &mut Vec::new(), &mut visited, &context, &mut |node, uses, path| {
+            for (operation, value, state) in uses {
+                let check = checks.entry((node, operation.clone())).or_insert_with(|| NoneCheck {
+                    function: function.clone(),
+                    function_id: self.function_id(func_node),
+                    node,
+                    operation,
+                    value,
+                    state: OptionState::Some,
+                    path: Vec::new(),
+                });
+                // The first path where the value may be None is the one reported, unless a later one
+                // always gives None
+                let worse = match check.state {
+                    OptionState::Some => state != OptionState::Some,
+                    OptionState::Maybe => state == OptionState::None,
+                    OptionState::None => false,
+                };
+                if worse {
+                    check.state = state;
+                    check.path = path.to_vec();
+                }
+            }
+        }, &mut returned);
+
+        let mut checks: Vec<NoneCheck> = checks.into_values().collect();
+        checks.sort_by_key(|check| (self.node_location(check.node), check.node, check.operation.clone()));
+        (checks, returned)
+    }
+
+    // Walk the paths from 'node' with the states at its entry. A node reached again with the same states
+    // gives nothing new, so loops are walked until their states stop changing.
+    #[allow(clippy::too_many_arguments)]
+    fn explore_none_states(
+        &self,
+        node: NodeIndex,
+        states: OptionStates,
+        path: &mut Vec<NodeIndex>,
+        visited: &mut HashSet<(NodeIndex, OptionStates)>,
+        context: &NoneContext,
+        report: &mut NoneReport,
+        returned: &mut Option<OptionState>,
+    ) {
+        if !visited.insert((node, states.clone())) {
+            return;
+        }
+        path.push(node);
+        let mut states = states;
+
+        let mut uses = NoneUses { builder: self, context, states: &states, uses: Vec::new() };
+        match &self.graph[node] {
+            CfgNode::Statement(_, Some(stmt)) => uses.visit_stmt(stmt),
+            CfgNode::Condition(_, Some(conditional)) => uses.visit_expr(conditional.to_syn_expr()),
+            CfgNode::Return(_, Some(expr_return)) => uses.visit_expr_return(expr_return),
+            _ => {},
+        }
+        if !uses.uses.is_empty() {
+            report(node, uses.uses, path);
+        }
+        self.apply_none_effects(node, &mut states, context);
+
+        if let CfgNode::Return(_, Some(expr_return)) = &self.graph[node] {
+            let state = expr_return.expr.as_ref()
+                .and_then(|expr| self.option_state(expr, &states, context))
+                .unwrap_or(OptionState::Maybe);
+            *returned = Some(returned.map_or(state, |returned| returned.join(state)));
+        } else {
+            for edge in self.graph.edges(node) {
+                let mut next = states.clone();
+                if let CfgNode::Condition(_, Some(conditional)) = &self.graph[node] {
+                    Self::refine_none_states(conditional, edge.weight(), &mut next, context);
+                }
+                self.explore_none_states(edge.target(), next, path, visited, context, report, returned);
+            }
+        }
+        path.pop();
+    }
+
+    // Assignments and method calls of a node
+    fn apply_none_effects(&self, node: NodeIndex, states: &mut OptionStates, context: &NoneContext) {
+        let CfgNode::Statement(_, Some(stmt)) = &self.graph[node] else { return };
+        let assigned = match stmt {
+            Stmt::Local(local) => {
+                let pat = match &local.pat {
+                    Pat::Type(pat_type) => &*pat_type.pat,
+                    pat => pat,
+                };
+                match (pat, &local.init) {
+                    (Pat::Ident(pat_ident), Some((_, init))) => Some((pat_ident.ident.to_string(), (**init).clone())),
+                    _ => None,
+                }
+            },
+            Stmt::Expr(Expr::Assign(assign)) | Stmt::Semi(Expr::Assign(assign), _) => {
+                Self::option_variable(&assign.left).map(|var| (var, (*assign.right).clone()))
+            },
+            _ => None,
+        };
+        // The value is read before the calls of the statement update the states
+        let value = assigned.as_ref().map(|(var, value)| (var.clone(), self.option_state(value, states, context)));
+        NoneEffects { context, states }.visit_stmt(stmt);
+        match value {
+            Some((var, Some(state))) => { states.insert(var, state); },
+            Some((var, None)) if context.options.contains(&var) => { states.insert(var, OptionState::Maybe); },
+            _ => {},
+        }
+    }
+
+    // What is known of an expression of an Option type; None when it is not an Option the analysis follows
+    fn option_state(&self, expr: &Expr, states: &OptionStates, context: &NoneContext) -> Option<OptionState> {
+        match expr {
+            Expr::Paren(paren) => self.option_state(&paren.expr, states, context),
+            Expr::Reference(reference) => self.option_state(&reference.expr, states, context),
+            Expr::Unary(unary) if matches!(unary.op, UnOp::Deref(_)) => self.option_state(&unary.expr, states, context),
+            Expr::Path(expr_path) if expr_path.path.is_ident("None") => Some(OptionState::None),
+            Expr::Path(_) => {
+                let var = Self::option_variable(expr)?;
+                states.get(&var).copied().or_else(|| context.options.contains(&var).then_some(OptionState::Maybe))
+            },
+            Expr::Call(call) => {
+                let Expr::Path(func) = &*call.func else { return None };
+                if func.path.is_ident("Some") {
+                    return Some(OptionState::Some);
+                }
+                let name = func.path.segments.iter().map(|segment| segment.ident.to_string()).collect::<Vec<_>>().join("::");
+                let qualified = if context.module.is_empty() { name.clone() } else { format!("{}::{}", context.module, name) };
+                context.summaries.get(&qualified).or_else(|| context.summaries.get(&name)).copied()
+            },
+            Expr::MethodCall(method_call) => {
+                let method = method_call.method.to_string();
+                let receiver = self.option_state(&method_call.receiver, states, context);
+                // 'm.get(&k)' is the entry of the map
+                if method == "get" {
+                    if let Some(entry) = method_call.args.first().and_then(|key| Self::map_entry(&method_call.receiver, key, context)) {
+                        return Some(states.get(&entry).copied().unwrap_or(OptionState::Maybe));
+                    }
+                }
+                match (method.as_str(), receiver) {
+                    (method, Some(state)) if SAME_STATE_METHODS.contains(&method) => Some(state),
+                    ("or" | "or_else", Some(OptionState::Some)) => Some(OptionState::Some),
+                    ("or", Some(_)) => method_call.args.first().and_then(|alternative| self.option_state(alternative, states, context)).or(Some(OptionState::Maybe)),
+                    ("and" | "and_then" | "filter" | "zip", Some(OptionState::None)) => Some(OptionState::None),
+                    ("or_else" | "and" | "and_then" | "filter" | "zip" | "xor", Some(_)) => Some(OptionState::Maybe),
+                    (method, _) if MAYBE_NONE_METHODS.contains(&method) || method.starts_with("checked_") => Some(OptionState::Maybe),
+                    _ => None,
+                }
+            },
+            _ => None,
+        }
+    }
+
+    // The states on the branch of a condition taken through 'edge'
+    fn refine_none_states(conditional: &ConditionalExpr, edge: &EdgeKind, states: &mut OptionStates, context: &NoneContext) {
+        match (conditional, edge) {
+            (ConditionalExpr::If(cond) | ConditionalExpr::While(cond), EdgeKind::TrueBranch | EdgeKind::FalseBranch) => {
+                Self::refine_by_condition(cond, *edge == EdgeKind::TrueBranch, states, context);
+            },
+            (ConditionalExpr::Match(match_condition), EdgeKind::MatchArm(label)) => {
+                if let Some(var) = Self::option_variable(&match_condition.scrutinee).filter(|var| context.options.contains(var) || states.contains_key(var)) {
+                    if label.starts_with("Some") {
+                        states.insert(var, OptionState::Some);
+                    } else if label.starts_with("None") {
+                        states.insert(var, OptionState::None);
+                    }
+                }
+            },
+            _ => {},
+        }
+    }
+
+    fn refine_by_condition(cond: &Expr, holds: bool, states: &mut OptionStates, context: &NoneContext) {
+        let followed = |var: &String, states: &OptionStates| context.options.contains(var) || states.contains_key(var);
+        let known = |is_some: bool| if is_some { OptionState::Some } else { OptionState::None };
+        match cond {
+            Expr::Paren(paren) => Self::refine_by_condition(&paren.expr, holds, states, context),
+            Expr::Unary(unary) if matches!(unary.op, UnOp::Not(_)) => Self::refine_by_condition(&unary.expr, !holds, states, context),
+            // Both sides of a '&&' hold on the true branch, both sides of a '||' fail on the false branch
+            Expr::Binary(bin) if matches!(bin.op, BinOp::And(_)) && holds || matches!(bin.op, BinOp::Or(_)) && !holds => {
+                Self::refine_by_condition(&bin.left, holds, states, context);
+                Self::refine_by_condition(&bin.right, holds, states, context);
+            },
+            Expr::Binary(bin) if matches!(bin.op, BinOp::Eq(_) | BinOp::Ne(_)) => {
+                let compared = match (&*bin.left, &*bin.right) {
+                    (value, Expr::Path(none)) | (Expr::Path(none), value) if none.path.is_ident("None") => Self::option_variable(value),
+                    _ => None,
+                };
+                if let Some(var) = compared.filter(|var| followed(var, states)) {
+                    let is_none = matches!(bin.op, BinOp::Eq(_)) == holds;
+                    states.insert(var, known(!is_none));
+                }
+            },
+            Expr::MethodCall(method_call) => {
+                let method = method_call.method.to_string();
+                if let Some(var) = Self::option_variable(&method_call.receiver).filter(|var| followed(var, states)) {
+                    match method.as_str() {
+                        "is_some" => { states.insert(var, known(holds)); },
+                        "is_none" => { states.insert(var, known(!holds)); },
+                        _ => {},
+                    }
+                }
+                // 'm.contains_key(&k)' tells whether 'm[&k]' has a value
+                if let (true, "contains_key", Some(key)) = (holds, method.as_str(), method_call.args.first()) {
+                    if let Some(entry) = Self::map_entry(&method_call.receiver, key, context) {
+                        states.insert(entry, OptionState::Some);
+                    }
+                }
+            },
+            // 'if let Some(v) = x', 'while let None = x'
+            Expr::Let(expr_let) => {
+                if let Some(var) = Self::option_variable(&expr_let.expr).filter(|var| followed(var, states)) {
+                    let pattern = Self::render_pat(&expr_let.pat);
+                    if pattern.starts_with("Some") {
+                        states.insert(var, known(holds));
+                    } else if pattern == "None" {
+                        states.insert(var, known(!holds));
+                    }
+                }
+            },
+            _ => {},
+        }
+    }
+
+    // Variable holding an Option: 'x', '&x', 'self.x'
+    fn option_variable(expr: &Expr) -> Option<String> {
+        match expr {
+            Expr::Paren(paren) => Self::option_variable(&paren.expr),
+            Expr::Reference(reference) => Self::option_variable(&reference.expr),
+            Expr::Path(_) | Expr::Field(_) => Self::field_place_name(expr),
+            _ => None,
+        }
+    }
+
+    // Entry 'm[k]' of a map variable for the key 'k' (or '&k')
+    fn map_entry(map: &Expr, key: &Expr, context: &NoneContext) -> Option<String> {
+        let map = Self::option_variable(map).filter(|map| context.maps.contains(map))?;
+        let key = match key {
+            Expr::Reference(reference) => &*reference.expr,
+            key => key,
+        };
+        Some(format!("{}[{}]", map, Self::render_expr(key)))
+    }
+
+    fn returns_option(output: &ReturnType) -> bool {
+        matches!(output, ReturnType::Type(_, ty) if Self::render_type(ty).starts_with("Option"))
+    }
+
+    // 'fn f -> if: x.is_some() (false) -> x.unwrap()', the nodes of a path with the branches it takes
+    pub fn describe_path(&self, path: &[NodeIndex]) -> String {
+        let mut steps = Vec::new();
+        for (position, &node) in path.iter().enumerate() {
+            if matches!(self.graph[node], CfgNode::MergePoint) {
+                continue;
+            }
+            let label = match &self.graph[node] {
+                CfgNode::Function(name, _) => format!("fn {}", name),
+                other => other.label(),
+            };
+            let branch = path.get(position + 1)
+                .and_then(|&next| self.graph.edges_connecting(node, next).next())
+                .map(|edge| edge.weight().label())
+                .filter(|label| !label.is_empty());
+            steps.push(match branch {
+                Some(branch) => format!("{} ({})", label, branch),
+                None => label,
+            });
+        }
+        steps.join(" -> ")
+    }
+}
@@ -0,0 +1,333 @@
+use petgraph::graph::NodeIndex;
+use quote::quote;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use syn::punctuated::Punctuated;
+use syn::visit::{self, Visit};
+use syn::{BinOp, Expr, ExprAssignOp, ExprBinary, ExprCast, ExprClosure, ExprIndex, ExprMacro, ExprMethodCall, Token};
+
+use crate::cfg_builder::builder::CfgBuilder;
+use crate::cfg_builder::node::{CfgNode, ConditionalExpr};
+
+// Operations that can panic at runtime
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PanicKind {
+    Unwrap,
+    IndexOutOfBounds,
+    Overflow,
+    DivisionByZero,
+    ExplicitPanic,
+    Assertion,
+    NarrowingCast, // not a panic: an 'as' cast that would change the value
+}
+
+impl fmt::Display for PanicKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            PanicKind::Unwrap => "unwrap",
+            PanicKind::IndexOutOfBounds => "index out of bounds",
+            PanicKind::Overflow => "arithmetic overflow",
+            PanicKind::DivisionByZero => "division by zero",
+            PanicKind::ExplicitPanic => "explicit panic",
+            PanicKind::Assertion => "assertion",
+            PanicKind::NarrowingCast => "narrowing cast",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+// A panicking operation of a node, with the condition under which it does not panic
+#[derive(Debug, Clone)]
+pub struct PanicObligation {
+    pub kind: PanicKind,
+    pub operation: String,
+    pub safe_condition: Expr,
+}
+
+// Verification condition proving that one operation can't panic when reached along one path
+#[derive(Debug, Clone)]
+pub struct PanicCheck {
+    pub function: String,
+    pub function_id: String, // canonical identifier of the function, see 'function_id'
+    pub node: NodeIndex,
+    pub obligation: PanicObligation,
+    pub path: Vec<NodeIndex>, // from the function entry (or a loop invariant) to the operation
+    pub condition: String,
+}
+
+// Collects the panicking operations of an expression
+struct PanicCollector<'a> {
+    builder: &'a CfgBuilder,
+    type_env: &'a HashMap<String, String>,
+    obligations: Vec<PanicObligation>,
+}
+
+impl PanicCollector<'_> {
+    fn push(&mut self, kind: PanicKind, operation: String, safe_condition: String) {
+        if let Ok(safe_condition) = syn::parse_str::<Expr>(&safe_condition) {
+            self.obligations.push(PanicObligation { kind, operation, safe_condition });
+        }
+    }
+
+    fn check_overflow(&mut self, result: &str, ty: Option<String>, operation: String) {
+        if let Some((min, max)) = ty.and_then(|ty| CfgBuilder::integer_bounds(&ty)) {
+            self.push(PanicKind::Overflow, operation, format!("{} <= ({}) && ({}) <= {}", min, result, result, max));
+        }
+    }
+
+    fn check_arithmetic(&mut self, left: &Expr, op: &BinOp, right: &Expr, ty: Option<String>, operation: String) {
+        let (left_str, right_str) = (CfgBuilder::render_expr(left), CfgBuilder::render_expr(right));
+        match op {
+            BinOp::Add(_) | BinOp::AddEq(_) => self.check_overflow(&format!("({}) + ({})", left_str, right_str), ty, operation),
+            BinOp::Sub(_) | BinOp::SubEq(_) => self.check_overflow(&format!("({}) - ({})", left_str, right_str), ty, operation),
+            BinOp::Mul(_) | BinOp::MulEq(_) => self.check_overflow(&format!("({}) * ({})", left_str, right_str), ty, operation),
+            BinOp::Div(_) | BinOp::DivEq(_) | BinOp::Rem(_) | BinOp::RemEq(_) => {
+                self.push(PanicKind::DivisionByZero, operation, format!("({}) != 0", right_str));
+            },
+            _ => {},
+        }
+    }
+}
+
+impl<'ast> Visit<'ast> for PanicCollector<'_> {
+    fn visit_expr_binary(&mut self, i: &'ast ExprBinary) {
+        let ty = self.builder.infer_expr_type_in(&Expr::Binary(i.clone()), self.type_env);
+        let operation = CfgBuilder::render_expr(&Expr::Binary(i.clone()));
+        self.check_arithmetic(&i.left, &i.op, &i.right, ty, operation);
+        visit::visit_expr_binary(self, i);
+    }
+
+    fn visit_expr_assign_op(&mut self, i: &'ast ExprAssignOp) {
+        let ty = self.builder.infer_expr_type_in(&i.left, self.type_env);
+        let operation = CfgBuilder::render_expr(&Expr::AssignOp(i.clone()));
+        self.check_arithmetic(&i.left, &i.op, &i.right, ty, operation);
+        visit::visit_expr_assign_op(self, i);
+    }
+
+    fn visit_expr_index(&mut self, i: &'ast ExprIndex) {
+        if !matches!(&*i.index, Expr::Range(_)) {
+            let collection = CfgBuilder::render_expr(&i.expr);
+            let index = CfgBuilder::render_expr(&i.index);
+            // Arrays have a known length, other collections get a length variable '<name>_len'
+            let array_len = self.builder.infer_expr_type_in(&i.expr, self.type_env)
+                .and_then(|ty| ty.trim_start_matches('&').trim_start_matches("mut ").strip_suffix(']').map(str::to_string))
+                .and_then(|ty| ty.rsplit_once("; ").map(|(_, len)| len.to_string()));
+            let len = array_len.unwrap_or_else(|| format!("{}_len", CfgBuilder::sanitize_identifier(&collection)));
+            let operation = CfgBuilder::render_expr(&Expr::Index(i.clone()));
+            // Indices are 'usize': only an index of unknown or signed type needs the lower bound
+            let unsigned = self.builder.infer_expr_type_in(&i.index, self.type_env).is_some_and(|ty| ty.starts_with('u'));
+            let condition = if unsigned {
+                format!("({}) < {}", index, len)
+            } else {
+                format!("0 <= ({}) && ({}) < {}", index, index, len)
+            };
+            self.push(PanicKind::IndexOutOfBounds, operation, condition);
+        }
+        visit::visit_expr_index(self, i);
+    }
+
+    // A narrowing cast must not lose the converted value: it stays in the range of the target type
+    fn visit_expr_cast(&mut self, i: &'ast ExprCast) {
+        let cast = self.builder.cast_info(i, self.type_env);
+        if let (true, Some((min, max))) = (cast.narrowing, CfgBuilder::integer_bounds(&cast.to)) {
+            let value = CfgBuilder::render_expr(&i.expr);
+            self.push(PanicKind::NarrowingCast, cast.expr, format!("{} <= ({}) && ({}) <= {}", min, value, value, max));
+        }
+        visit::visit_expr_cast(self, i);
+    }
+
+    fn visit_expr_method_call(&mut self, i: &'ast ExprMethodCall) {
+        let method = i.method.to_string();
+        if method == "unwrap" || method == "expect" {
+            // Same tag encoding as 'match': None = 0, Some = 1 and Ok = 0, Err = 1
+            let receiver_type = self.builder.infer_expr_type_in(&i.receiver, self.type_env).unwrap_or_default();
+            let tag = format!("{}_tag", CfgBuilder::sanitize_identifier(&CfgBuilder::render_expr(&i.receiver)));
+            let expected = if receiver_type.starts_with("Result") { 0 } else { 1 };
+            let operation = CfgBuilder::render_expr(&Expr::MethodCall(i.clone()));
+            self.push(PanicKind::Unwrap, operation, format!("{} == {}", tag, expected));
+        }
+        visit::visit_expr_method_call(self, i);
+    }
+
+    fn visit_expr_macro(&mut self, i: &'ast ExprMacro) {
+        let name = i.mac.path.segments.last().map(|s| s.ident.to_string()).unwrap_or_default();
+        let operation = CfgBuilder::render_expr(&Expr::Macro(i.clone()));
+        let parser = Punctuated::<Expr, Token![,]>::parse_terminated;
+        let args: Vec<Expr> = syn::parse::Parser::parse2(parser, i.mac.tokens.clone())
+            .map(|args| args.into_iter().collect())
+            .unwrap_or_default();
+        match name.trim_start_matches("debug_") {
+            "panic" | "unreachable" | "todo" | "unimplemented" => self.push(PanicKind::ExplicitPanic, operation, "false".to_string()),
+            "assert" if !args.is_empty() => {
+                self.push(PanicKind::Assertion, operation, CfgBuilder::render_expr(&args[0]));
+            },
+            "assert_eq" | "assert_ne" if args.len() >= 2 => {
+                let op = if name.ends_with("assert_eq") { "==" } else { "!=" };
+                let condition = format!("({}) {} ({})", CfgBuilder::render_expr(&args[0]), op, CfgBuilder::render_expr(&args[1]));
+                self.push(PanicKind::Assertion, operation, condition);
+            },
+            _ => {},
+        }
+        for arg in &args {
+            self.visit_expr(arg);
+        }
+    }
+
+    // Closure bodies run when the closure is called, not where it is defined
+    fn visit_expr_closure(&mut self, _i: &'ast ExprClosure) {}
+}
+
+impl CfgBuilder {
+    // Verification conditions of the no-panic goal: every panicking operation, on every path reaching it
+    pub fn no_panic_checks(&self) -> Vec<PanicCheck> {
+        let mut checks = Vec::new();
+        for func_node in self.function_nodes() {
+            let function = self.graph[func_node].label();
+            let function_id = self.function_id(func_node);
+            let type_env = self.function_type_env(func_node);
+            let mut obligations: HashMap<NodeIndex, Vec<PanicObligation>> = HashMap::new();
+            let mut checked_prefixes: HashSet<Vec<NodeIndex>> = HashSet::new();
+
+            for path in self.panic_paths(func_node) {
+                for (position, &node) in path.iter().enumerate() {
+                    // Paths sharing a prefix give the same conditions for the operations on it
+                    if !checked_prefixes.insert(path[..=position].to_vec()) {
+                        continue;
+                    }
+                    let node_obligations = obligations.entry(node)
+                        .or_insert_with(|| self.panic_obligations(node, &type_env))
+                        .clone();
+                    for obligation in node_obligations {
+                        // The operation runs before the node takes effect: only the nodes before it are assumed
+                        let goal = Self::wrap_with_parens(obligation.safe_condition.clone());
+                        let Some(condition) = self.wp_along_path(&path, position, Some(goal)) else {
+                            continue;
+                        };
+                        let condition = self.with_logic_definitions(&Self::solver_abstraction(&self.substitute_constants(&condition)));
+                        checks.push(PanicCheck {
+                            function: function.clone(),
+                            function_id: function_id.clone(),
+                            node,
+                            obligation,
+                            path: path[..=position].to_vec(),
+                            condition: quote! { #condition }.to_string(),
+                        });
+                    }
+                }
+            }
+        }
+        checks
+    }
+
+    pub fn panic_obligations(&self, node: NodeIndex, type_env: &HashMap<String, String>) -> Vec<PanicObligation> {
+        let mut collector = PanicCollector { builder: self, type_env, obligations: Vec::new() };
+        match &self.graph[node] {
+            CfgNode::Statement(_, Some(stmt)) => collector.visit_stmt(stmt),
+            CfgNode::Condition(_, Some(ConditionalExpr::If(expr))) | CfgNode::Condition(_, Some(ConditionalExpr::While(expr))) => {
+                collector.visit_expr(expr)
+            },
+            CfgNode::Condition(_, Some(ConditionalExpr::Match(match_condition))) => collector.visit_expr(&match_condition.scrutinee),
+            CfgNode::Condition(_, Some(ConditionalExpr::ForLoop(expr_for))) => collector.visit_expr(&expr_for.expr),
+            CfgNode::Return(_, Some(expr_return)) => collector.visit_expr_return(expr_return),
+            _ => {},
+        }
+        collector.obligations
+    }
+
+    // Paths checked for panics: from the function entry and from each cut point (loop invariant, or
+    // loop head when there is no invariant) up to the next cut point or the end of the function.
+    // Nothing is assumed at a loop head without invariant, so any iteration is covered.
+    pub fn panic_paths(&self, func_node: NodeIndex) -> Vec<Vec<NodeIndex>> {
+        let starts: Vec<NodeIndex> = std::iter::once(func_node)
+            .chain(self.function_subgraph_nodes(func_node).into_iter().filter(|&n| self.is_panic_cut_point(n)))
+            .collect();
+        let mut paths = Vec::new();
+        for start in starts {
+            self.extend_panic_path(&mut vec![start], &mut paths);
+        }
+        paths
+    }
+
+    fn extend_panic_path(&self, path: &mut Vec<NodeIndex>, paths: &mut Vec<Vec<NodeIndex>>) {
+        let last = *path.last().expect("Path can't be empty");
+        let successors: Vec<NodeIndex> = self.graph.neighbors(last).collect();
+        let mut extended = false;
+        // Control leaves the function at a return
+        let successors = if matches!(self.graph[last], CfgNode::Return(_, _)) { Vec::new() } else { successors };
+        for next in successors {
+            if self.is_panic_cut_point(next) {
+                path.push(next);
+                paths.push(path.clone());
+                path.pop();
+                extended = true;
+            } else if !path.contains(&next) {
+                path.push(next);
+                self.extend_panic_path(path, paths);
+                path.pop();
+                extended = true;
+            }
+        }
+        if !extended {
+            paths.push(path.clone());
+        }
+    }
+
+    fn is_panic_cut_point(&self, node: NodeIndex) -> bool {
+        match &self.graph[node] {
+            CfgNode::Invariant(_, _) => true,
+            CfgNode::Condition(_, Some(ConditionalExpr::While(_))) | CfgNode::Condition(_, Some(ConditionalExpr::ForLoop(_))) => {
+                // The measure of the loop comes between its invariant and its condition
+                !self.graph.neighbors_directed(node, petgraph::Direction::Incoming)
+                    .any(|pred| match self.graph[pred] {
+                        CfgNode::Invariant(_, _) => true,
+                        CfgNode::Decreases(_, _) => self.graph.neighbors_directed(pred, petgraph::Direction::Incoming)
+                            .any(|head| matches!(self.graph[head], CfgNode::Invariant(_, _))),
+                        _ => false,
+                    })
+            },
+            _ => false,
+        }
+    }
+
+    // Types of all the variables of a function, from the metadata of its nodes
+    pub(crate) fn function_type_env(&self, func_node: NodeIndex) -> HashMap<String, String> {
+        self.function_subgraph_nodes(func_node).iter()
+            .filter_map(|node| self.results.node_metadata.get(node))
+            .flat_map(|metadata| metadata.var_types.iter().cloned())
+            .collect()
+    }
+
+    // Replace the calls the solver can't handle by integer variables: 'x.is_some()' becomes a test
+    // on the tag 'x_tag' used for 'match' and 'unwrap', 'v.len()' becomes 'v_len'
+    pub fn solver_abstraction(expr: &Expr) -> Expr {
+        match expr {
+            Expr::MethodCall(method_call) if method_call.args.is_empty() => {
+                let receiver = Self::sanitize_identifier(&Self::render_expr(&method_call.receiver));
+                let replacement = match method_call.method.to_string().as_str() {
+                    "is_some" | "is_err" => Some(format!("({}_tag == 1)", receiver)),
+                    "is_none" | "is_ok" => Some(format!("({}_tag == 0)", receiver)),
+                    "len" => Some(format!("{}_len", receiver)),
+                    _ => None,
+                };
+                replacement.and_then(|r| syn::parse_str(&r).ok()).unwrap_or_else(|| expr.clone())
+            },
+            Expr::Binary(bin) => Expr::Binary(ExprBinary {
+                left: Box::new(Self::solver_abstraction(&bin.left)),
+                right: Box::new(Self::solver_abstraction(&bin.right)),
+                ..bin.clone()
+            }),
+            Expr::Unary(unary) => Expr::Unary(syn::ExprUnary { expr: Box::new(Self::solver_abstraction(&unary.expr)), ..unary.clone() }),
+            Expr::Paren(paren) => Expr::Paren(syn::ExprParen { expr: Box::new(Self::solver_abstraction(&paren.expr)), ..paren.clone() }),
+            Expr::Macro(expr_macro) => match syn::parse2::<Expr>(expr_macro.mac.tokens.clone()) {
+                Ok(inner) => {
+                    let inner = Self::solver_abstraction(&inner);
+                    let mut expr_macro = expr_macro.clone();
+                    expr_macro.mac.tokens = quote! { #inner };
+                    Expr::Macro(expr_macro)
+                },
+                Err(_) => expr.clone(),
+            },
+            _ => expr.clone(),
+        }
+    }
+}
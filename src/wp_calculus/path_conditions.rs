@@ -0,0 +1,89 @@
+use petgraph::graph::NodeIndex;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+use crate::cfg_builder::builder::CfgBuilder;
+use crate::cfg_builder::node::CfgNode;
+use crate::wp_calculus::ASSUMPTION_TAG;
+
+impl CfgBuilder {
+    // What a basic path assumes, in the order of the path: the annotations it starts from, the facts of the
+    // parameter types, the branches it takes and the contracts of the calls it crosses. Each condition is
+    // written over the values at the start of the path, as the solver gets it, so a test generator can solve
+    // them for inputs. 'number' is the one of the run output ('Path 3').
+    pub fn path_conditions(&self, number: usize, path: &[NodeIndex]) -> Option<Value> {
+        let condition = self.tagged_wp(path)?;
+        let mut parts = Vec::new();
+        Self::implication_parts(&condition, &mut parts);
+        let goal = parts.pop()?;
+
+        // The tag of each assumption names the node it comes from
+        let mut by_node: HashMap<usize, Vec<String>> = HashMap::new();
+        let mut untagged = Vec::new();
+        for part in parts {
+            match Self::untag_assumption(part) {
+                (Some(tag), expr) => {
+                    let index = tag.trim_start_matches(ASSUMPTION_TAG).parse().unwrap_or(usize::MAX);
+                    by_node.entry(index).or_default().push(self.render_condition(&expr));
+                },
+                (None, expr) => untagged.push(self.render_condition(&expr)),
+            }
+        }
+
+        let mut conditions = Vec::new();
+        for (position, &node) in path.iter().enumerate() {
+            let Some(assumed) = by_node.remove(&node.index()) else { continue };
+            let edge = path.get(position + 1)
+                .filter(|_| matches!(self.graph[node], CfgNode::Condition(_, _)))
+                .and_then(|&next| self.graph.find_edge(node, next))
+                .map(|edge| self.graph[edge].label());
+            let mut value = json!({
+                "node": self.node_id(node),
+                "kind": self.graph[node].kind_name(),
+                "source": self.annotation_label(node),
+                "conditions": assumed,
+            });
+            if let Some(edge) = edge {
+                value["taken"] = json!(edge);
+            }
+            if let Some(span) = self.results.node_metadata.get(&node).and_then(|metadata| metadata.span.as_ref()) {
+                value["span"] = json!(span);
+            }
+            if let Some(call) = self.results.node_metadata.get(&node).and_then(|metadata| metadata.indirect_call.as_ref()) {
+                value["kind"] = json!("call");
+                value["callee"] = json!(call.callee);
+            }
+            conditions.push(value);
+        }
+
+        let (target_id, target_label) = self.path_target(path)?;
+        let mut value = json!({
+            "path": number,
+            "function": path.first().and_then(|&start| self.function_id_of_node(start)),
+            "start": { "id": self.node_id(path[0]), "label": self.annotation_label(path[0]) },
+            "target": { "id": target_id, "label": target_label },
+            "nodes": path.iter().map(|&node| self.node_id(node)).collect::<Vec<_>>(),
+            "conditions": conditions,
+            "goal": self.render_condition(&goal),
+        });
+        if let Some(exit) = self.exit_of_path(path) {
+            value["exit"] = json!(exit);
+        }
+        // Regions crossed in one step assume the conjunction of their paths, not tied to one node
+        if !untagged.is_empty() {
+            value["region_conditions"] = json!(untagged);
+        }
+        Some(value)
+    }
+
+    // Annotations ('pre!', 'invariant!') as their condition
+    pub(crate) fn render_condition(&self, expr: &syn::Expr) -> String {
+        if let syn::Expr::Macro(expr_macro) = expr {
+            let is_annotation = expr_macro.mac.path.get_ident().is_some_and(|name| self.options.annotation_names.role_of(&name.to_string()).is_some());
+            if let Some(inner) = syn::parse2::<syn::Expr>(expr_macro.mac.tokens.clone()).ok().filter(|_| is_annotation) {
+                return Self::render_expr(&inner);
+            }
+        }
+        Self::render_expr(expr)
+    }
+}
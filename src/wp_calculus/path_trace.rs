@@ -0,0 +1,291 @@
+use petgraph::graph::NodeIndex;
+use std::collections::HashMap;
+use syn::Expr;
+
+use crate::cfg_builder::builder::CfgBuilder;
+use crate::cfg_builder::node::{CfgNode, ConditionalExpr, EdgeKind};
+use crate::cfg_builder::ConstValue;
+use crate::wp_calculus::MEASURE_AT_HEAD;
+
+impl CfgBuilder {
+    // Debugger-like trace of a failed path, replayed forward with the values of the counterexample.
+    // Without values, the trace only shows the symbolic state along the path.
+    pub fn path_trace(&self, path: &[NodeIndex], counterexample: &HashMap<String, ConstValue>) -> String {
+        let path = &self.expand_regions(path, counterexample);
+        let mut inputs: Vec<String> = counterexample.iter().map(|(var, value)| format!("{} = {}", var, value)).collect();
+        inputs.sort();
+        let symbolic_only = counterexample.is_empty();
+        let mut trace = match symbolic_only {
+            true => String::new(),
+            false => format!("  inputs: {}\n", inputs.join(", ")),
+        };
+
+        // Values of the variables as expressions over the inputs, and as concrete values
+        let mut symbolic_state: HashMap<String, Expr> = HashMap::new();
+        let mut concrete_state = counterexample.clone();
+        let mut broken = false;
+
+        for (step, &node) in path.iter().enumerate() {
+            let mut state_change = String::new();
+            let (kind, text, check) = match &self.graph[node] {
+                CfgNode::Function(name, _) => ("enter", format!("fn {}", name), None),
+                CfgNode::Precondition(pre, expr) => ("assume", format!("pre: {}", pre), self.trace_check(expr.as_ref(), &concrete_state)),
+                CfgNode::Assumption(fact, expr) => ("assume", fact.clone(), self.trace_check(expr.as_ref(), &concrete_state)),
+                // A path starting at an invariant assumes it, a path ending at one must re-establish it
+                CfgNode::Invariant(inv, expr) if step == 0 => ("assume", format!("invariant: {}", inv), self.trace_check(expr.as_ref(), &concrete_state)),
+                CfgNode::Invariant(inv, expr) => ("assert", format!("invariant: {}", inv), self.trace_check(expr.as_ref(), &concrete_state)),
+                CfgNode::Postcondition(post, expr) => ("assert", format!("post: {}", post), self.trace_check(expr.as_ref(), &concrete_state)),
+                CfgNode::Assertion(condition, expr) => ("assert", format!("assert: {}", condition), self.trace_check(expr.as_ref(), &concrete_state)),
+                // The measure when the iteration starts, then the termination check of the copy ending the path
+                CfgNode::Decreases(measure, _) if step + 1 < path.len() => {
+                    self.advance_concrete_state(node, &mut concrete_state);
+                    if let Some(value) = concrete_state.get(MEASURE_AT_HEAD) {
+                        state_change = format!("{} = {}", MEASURE_AT_HEAD, value);
+                    }
+                    ("measure", format!("decreases: {}", measure), None)
+                },
+                CfgNode::Decreases(measure, expr) => ("assert", format!("decreases: {}", measure), self.trace_check(expr.as_ref().and_then(Self::termination_goal).as_ref(), &concrete_state)),
+                CfgNode::Cutoff(inv) => ("cut", inv.clone(), None),
+                CfgNode::Return(ret, _) => {
+                    if let Some(value) = self.returned_value(node) {
+                        state_change = format!("result = {}", Self::render_expr(&Self::substitute_state(&value, &symbolic_state)));
+                        self.advance_concrete_state(node, &mut concrete_state);
+                        if let Some(value) = concrete_state.get("result") {
+                            state_change.push_str(&format!(" = {}", value));
+                        }
+                    }
+                    ("return", ret.clone(), None)
+                },
+                CfgNode::MergePoint => continue,
+                CfgNode::Statement(stmt, _) => {
+                    if let Some((var, expr)) = self.parse_assignment(stmt) {
+                        let symbolic = Self::substitute_state(&expr, &symbolic_state);
+                        let value = self.eval_expr_in(&expr, &concrete_state);
+                        state_change = format!("{} = {}", var, Self::render_expr(&symbolic));
+                        match &value {
+                            Some(value) => {
+                                state_change.push_str(&format!(" = {}", value));
+                                concrete_state.insert(var.clone(), value.clone());
+                            },
+                            None => {
+                                concrete_state.remove(&var);
+                            },
+                        }
+                        symbolic_state.insert(var, symbolic);
+                    }
+                    ("exec", stmt.clone(), None)
+                },
+                CfgNode::Condition(cond, conditional) => {
+                    let taken = self.next_edge_kind(path, node).unwrap_or(EdgeKind::Fallthrough);
+                    let condition = match conditional {
+                        Some(ConditionalExpr::If(expr)) | Some(ConditionalExpr::While(expr)) => {
+                            let holds = self.trace_check(Some(&**expr), &concrete_state);
+                            holds.map(|holds| if self.is_false_branch(path, node) { !holds } else { holds })
+                        },
+                        Some(ConditionalExpr::Match(match_condition)) => match_condition.arm_for_edge(&taken)
+                            .and_then(|arm| arm.condition.as_ref())
+                            .and_then(|condition| self.trace_check(Some(condition), &concrete_state)),
+                        _ => None,
+                    };
+                    ("branch", format!("{} -> {}", cond, if taken == EdgeKind::Fallthrough { "next".to_string() } else { taken.label() }), condition)
+                },
+            };
+
+            let verdict = match check {
+                Some(true) => "[holds]".to_string(),
+                Some(false) if !broken => {
+                    broken = true;
+                    "[fails]  <== implication breaks here".to_string()
+                },
+                Some(false) => "[fails]".to_string(),
+                None => state_change,
+            };
+            let line = format!("  #{:<3} {:<7} {:<40} {}", step, kind, text, verdict);
+            trace.push_str(line.trim_end());
+            trace.push('\n');
+        }
+
+        if !broken && !symbolic_only {
+            trace.push_str("  (the counterexample could not be replayed: some values are outside the integer/boolean model)\n");
+        }
+        trace
+    }
+
+    // Source lines a failed path goes through, in path order, with the values the counterexample gives the
+    // variables of each: what a statement assigns, and what a branch, a return or an annotation reads. The
+    // first annotation that doesn't hold is marked. Nodes without a location in the source are left out.
+    pub fn source_lines_of_path(&self, path: &[NodeIndex], counterexample: &HashMap<String, ConstValue>) -> String {
+        let path = &self.expand_regions(path, counterexample);
+        let mut state = counterexample.clone();
+        let mut lines: Vec<(usize, Vec<String>)> = Vec::new();
+        let mut broken = false;
+        for (step, &node) in path.iter().enumerate() {
+            // Field places ('self.len') are read where the tokens spell them out
+            let read = |tokens: proc_macro2::TokenStream, state: &HashMap<String, ConstValue>| -> Vec<String> {
+                let identifiers = Self::identifiers(&tokens);
+                let compact: String = tokens.to_string().chars().filter(|c| !c.is_whitespace()).collect();
+                let mut values: Vec<String> = state.iter()
+                    .filter(|(name, _)| identifiers.contains(*name) || (name.contains('.') && compact.contains(name.as_str())))
+                    .map(|(name, value)| format!("{} = {}", name, value))
+                    .collect();
+                values.sort();
+                values
+            };
+            let mut notes = Vec::new();
+            match &self.graph[node] {
+                CfgNode::Statement(stmt, _) => {
+                    self.advance_concrete_state(node, &mut state);
+                    if let Some(value) = self.parse_assignment(stmt).and_then(|(var, _)| state.get(&var).map(|value| format!("{} = {}", var, value))) {
+                        notes.push(value);
+                    }
+                },
+                CfgNode::Precondition(_, Some(expr)) | CfgNode::Postcondition(_, Some(expr)) | CfgNode::Invariant(_, Some(expr))
+                | CfgNode::Assertion(_, Some(expr)) => {
+                    notes.extend(read(quote::quote!(#expr), &state));
+                    let asserted = step > 0 && !matches!(self.graph[node], CfgNode::Precondition(_, _));
+                    if asserted && !broken && self.trace_check(Some(expr), &state) == Some(false) {
+                        broken = true;
+                        notes.push("<== fails".to_string());
+                    }
+                },
+                CfgNode::Decreases(_, Some(expr)) if step + 1 < path.len() => {
+                    notes.extend(read(quote::quote!(#expr), &state));
+                    self.advance_concrete_state(node, &mut state);
+                },
+                CfgNode::Decreases(_, Some(expr)) => {
+                    notes.extend(read(quote::quote!(#expr), &state));
+                    if !broken && self.trace_check(Self::termination_goal(expr).as_ref(), &state) == Some(false) {
+                        broken = true;
+                        notes.push("<== fails".to_string());
+                    }
+                },
+                CfgNode::Condition(_, Some(conditional)) => notes.extend(read(quote::quote!(#conditional), &state)),
+                CfgNode::Return(_, Some(expr_return)) => {
+                    notes.extend(read(quote::quote!(#expr_return), &state));
+                    self.advance_concrete_state(node, &mut state);
+                    notes.extend(state.get("result").map(|value| format!("result = {}", value)));
+                },
+                _ => {},
+            }
+            // The solver's model breaks the annotation the path ends at, even when its values can't be computed here
+            if step == path.len() - 1 && !broken && matches!(self.graph[node], CfgNode::Postcondition(_, _) | CfgNode::Invariant(_, _) | CfgNode::Decreases(_, _)) {
+                notes.push("<== not proved".to_string());
+            }
+            let Some(line) = self.source_span(node).map(|span| span.line) else { continue };
+            match lines.last_mut() {
+                Some((last, existing)) if *last == line => {
+                    for note in notes {
+                        if !existing.contains(&note) {
+                            existing.push(note);
+                        }
+                    }
+                },
+                _ => lines.push((line, notes)),
+            }
+        }
+        let mut text = String::new();
+        for (line, notes) in lines {
+            let source = self.file.source_lines.get(line - 1).map(|source| source.trim()).unwrap_or_default();
+            let row = format!("  {:>4} | {:<44} {}", line, source, notes.join(", "));
+            text.push_str(row.trim_end());
+            text.push('\n');
+        }
+        text
+    }
+
+    // Replace each summarized region of the path by the path through it that the counterexample takes
+    pub(crate) fn expand_regions(&self, path: &[NodeIndex], counterexample: &HashMap<String, ConstValue>) -> Vec<NodeIndex> {
+        let mut state = counterexample.clone();
+        let mut expanded = Vec::new();
+        for (position, &node) in path.iter().enumerate() {
+            let Some(region) = self.region_at(path, position) else {
+                self.advance_concrete_state(node, &mut state);
+                expanded.push(node);
+                continue;
+            };
+            let taken = region.paths.iter()
+                .find(|region_path| self.region_path_taken(region_path, &state))
+                .unwrap_or(&region.paths[0]);
+            for &region_node in &taken[..taken.len() - 1] {
+                self.advance_concrete_state(region_node, &mut state);
+                expanded.push(region_node);
+            }
+        }
+        expanded
+    }
+
+    // Whether no branch on the path contradicts the concrete values (unknown values don't)
+    fn region_path_taken(&self, region_path: &[NodeIndex], state: &HashMap<String, ConstValue>) -> bool {
+        let mut state = state.clone();
+        for &node in &region_path[..region_path.len() - 1] {
+            let holds = match &self.graph[node] {
+                CfgNode::Condition(_, Some(ConditionalExpr::If(expr))) => self.trace_check(Some(&**expr), &state)
+                    .map(|holds| holds != self.is_false_branch(region_path, node)),
+                CfgNode::Condition(_, Some(ConditionalExpr::Match(match_condition))) => self.next_edge_kind(region_path, node)
+                    .and_then(|kind| match_condition.arm_for_edge(&kind).and_then(|arm| arm.condition.clone()))
+                    .and_then(|condition| self.trace_check(Some(&condition), &state)),
+                _ => None,
+            };
+            if holds == Some(false) {
+                return false;
+            }
+            self.advance_concrete_state(node, &mut state);
+        }
+        true
+    }
+
+    // Concrete effect of a statement on the variables, of a return on 'result' and of the 'decreases!' after
+    // a loop head on the measure at the start of the iteration
+    pub(crate) fn advance_concrete_state(&self, node: NodeIndex, state: &mut HashMap<String, ConstValue>) {
+        let assignment = match &self.graph[node] {
+            CfgNode::Statement(stmt, _) => self.parse_assignment(stmt),
+            CfgNode::Return(_, _) => self.returned_value(node).map(|value| ("result".to_string(), value)),
+            CfgNode::Decreases(_, Some(expr)) => Self::loop_measure(expr).map(|measure| (MEASURE_AT_HEAD.to_string(), measure)),
+            _ => None,
+        };
+        if let Some((var, expr)) = assignment {
+            match self.eval_expr_in(&expr, state) {
+                Some(value) => state.insert(var, value),
+                None => state.remove(&var),
+            };
+        }
+    }
+
+    // Truth value of an annotation or branch condition under concrete values, when it can be computed
+    pub(crate) fn trace_check(&self, expr: Option<&Expr>, state: &HashMap<String, ConstValue>) -> Option<bool> {
+        let expr = match expr? {
+            Expr::Macro(expr_macro) => syn::parse2::<Expr>(expr_macro.mac.tokens.clone()).ok()?,
+            expr => expr.clone(),
+        };
+        match self.eval_expr_in(&expr, state)? {
+            ConstValue::Bool(value) => Some(value),
+            ConstValue::Int(_) => None,
+        }
+    }
+
+    // Replace all state variables at once, so values mentioning other variables aren't rewritten twice
+    fn substitute_state(expr: &Expr, state: &HashMap<String, Expr>) -> Expr {
+        let recurse = |inner: &Expr| Box::new(Self::substitute_state(inner, state));
+        match expr {
+            Expr::Path(expr_path) => expr_path.path.get_ident()
+                .and_then(|ident| state.get(&ident.to_string()).cloned())
+                .unwrap_or_else(|| expr.clone()),
+            Expr::Binary(bin) => Expr::Binary(syn::ExprBinary { left: recurse(&bin.left), right: recurse(&bin.right), ..bin.clone() }),
+            Expr::Unary(unary) => Expr::Unary(syn::ExprUnary { expr: recurse(&unary.expr), ..unary.clone() }),
+            Expr::Paren(paren) => Expr::Paren(syn::ExprParen { expr: recurse(&paren.expr), ..paren.clone() }),
+            Expr::Cast(cast) => Expr::Cast(syn::ExprCast { expr: recurse(&cast.expr), ..cast.clone() }),
+            Expr::Index(index) => Expr::Index(syn::ExprIndex { expr: recurse(&index.expr), index: recurse(&index.index), ..index.clone() }),
+            Expr::Field(field) => Expr::Field(syn::ExprField { base: recurse(&field.base), ..field.clone() }),
+            Expr::Call(call) => Expr::Call(syn::ExprCall {
+                args: call.args.iter().map(|arg| Self::substitute_state(arg, state)).collect(),
+                ..call.clone()
+            }),
+            Expr::MethodCall(method_call) => Expr::MethodCall(syn::ExprMethodCall {
+                receiver: recurse(&method_call.receiver),
+                args: method_call.args.iter().map(|arg| Self::substitute_state(arg, state)).collect(),
+                ..method_call.clone()
+            }),
+            _ => expr.clone(),
+        }
+    }
+}
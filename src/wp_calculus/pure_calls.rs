@@ -0,0 +1,115 @@
+use quote::quote;
+use syn::{Expr, ExprBinary, ExprCall, ExprCast, ExprField, ExprMethodCall, ExprParen, ExprUnary};
+
+use crate::cfg_builder::builder::CfgBuilder;
+
+// Methods of the standard library whose result equals their receiver: copies, conversions that keep the
+// content and views of the same value. They're replaced by the receiver instead of being unknown values.
+const VALUE_PRESERVING_METHODS: [&str; 13] = [
+    "clone", "to_owned", "to_string", "to_vec", "into", "as_str", "as_slice", "as_ref", "as_mut", "borrow", "borrow_mut",
+    "copied", "cloned",
+];
+
+// Functions of the standard library returning their argument, called by path ('Clone::clone(&x)')
+const VALUE_PRESERVING_FUNCTIONS: [&str; 7] = [
+    "Clone::clone", "ToOwned::to_owned", "ToString::to_string", "String::from", "From::from", "Into::into", "identity",
+];
+
+impl CfgBuilder {
+    // Value of a call to a value-preserving method or function: 'x.clone()', 'name.to_string()' and
+    // 'String::from(name)' are 'x' and 'name'
+    pub(crate) fn pure_call_value(expr: &Expr) -> Option<Expr> {
+        let value = match expr {
+            Expr::MethodCall(method_call) if method_call.args.is_empty() && VALUE_PRESERVING_METHODS.contains(&method_call.method.to_string().as_str()) => {
+                &*method_call.receiver
+            },
+            Expr::Call(call) if call.args.len() == 1 => {
+                let Expr::Path(func) = &*call.func else { return None };
+                let segments: Vec<String> = func.path.segments.iter().map(|segment| segment.ident.to_string()).collect();
+                let called = VALUE_PRESERVING_FUNCTIONS.iter().any(|name| {
+                    let name: Vec<&str> = name.split("::").collect();
+                    segments.len() >= name.len() && segments[segments.len() - name.len()..].iter().zip(&name).all(|(segment, part)| segment == part)
+                });
+                if !called {
+                    return None;
+                }
+                call.args.first()?
+            },
+            _ => return None,
+        };
+        // '&x' is passed to 'Clone::clone', the value is 'x'
+        let value = match value {
+            Expr::Reference(reference) => &*reference.expr,
+            value => value,
+        };
+        Some(Self::wrap_with_parens(Self::resolve_pure_calls(value)))
+    }
+
+    // Replace the value-preserving calls of an expression by the values they return, in annotations too
+    pub(crate) fn resolve_pure_calls(expr: &Expr) -> Expr {
+        if let Some(value) = Self::pure_call_value(expr) {
+            return value;
+        }
+        match expr {
+            Expr::Binary(bin) => Expr::Binary(ExprBinary {
+                left: Box::new(Self::resolve_pure_calls(&bin.left)),
+                right: Box::new(Self::resolve_pure_calls(&bin.right)),
+                ..bin.clone()
+            }),
+            Expr::Unary(unary) => Expr::Unary(ExprUnary { expr: Box::new(Self::resolve_pure_calls(&unary.expr)), ..unary.clone() }),
+            Expr::Paren(paren) => Expr::Paren(ExprParen { expr: Box::new(Self::resolve_pure_calls(&paren.expr)), ..paren.clone() }),
+            Expr::Cast(cast) => Expr::Cast(ExprCast { expr: Box::new(Self::resolve_pure_calls(&cast.expr)), ..cast.clone() }),
+            Expr::Field(field) => Expr::Field(ExprField { base: Box::new(Self::resolve_pure_calls(&field.base)), ..field.clone() }),
+            Expr::Call(call) => Expr::Call(ExprCall { args: call.args.iter().map(Self::resolve_pure_calls).collect(), ..call.clone() }),
+            Expr::MethodCall(method_call) => Expr::MethodCall(ExprMethodCall {
+                receiver: Box::new(Self::resolve_pure_calls(&method_call.receiver)),
+                args: method_call.args.iter().map(Self::resolve_pure_calls).collect(),
+                ..method_call.clone()
+            }),
+            // Annotations and tagged assumptions hold their condition as tokens
+            Expr::Macro(expr_macro) => match syn::parse2::<Expr>(expr_macro.mac.tokens.clone()) {
+                Ok(inner) => {
+                    let resolved = Self::resolve_pure_calls(&inner);
+                    if resolved == inner {
+                        return expr.clone();
+                    }
+                    let mut expr_macro = expr_macro.clone();
+                    expr_macro.mac.tokens = quote! { #resolved };
+                    Expr::Macro(expr_macro)
+                },
+                Err(_) => expr.clone(),
+            },
+            _ => expr.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::verdicts;
+    use crate::verifier::VcOutcome;
+
+    fn resolved(src: &str) -> String {
+        CfgBuilder::render_expr(&CfgBuilder::resolve_pure_calls(&syn::parse_str(src).unwrap()))
+    }
+
+    #[test]
+    fn value_preserving_calls_are_their_value() {
+        assert_eq!(resolved("x.clone() + 1"), "x + 1");
+        assert_eq!(resolved("Clone::clone(&x) == std::convert::identity(y)"), "x == y");
+        // Only the last segments are compared, and other calls are kept
+        assert_eq!(resolved("max(a.to_owned(), b.len())"), "max(a, b.len())");
+    }
+
+    #[test]
+    fn a_copy_keeps_the_facts_of_the_original() {
+        let outcomes = verdicts("fn copy(x: i32) -> i32 {
+                pre!(x > 0);
+                post!(y > 0);
+                let y = x.clone();
+                return y;
+            }");
+        assert!(matches!(outcomes.as_slice(), [VcOutcome::Valid]));
+    }
+}
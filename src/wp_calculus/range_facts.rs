@@ -0,0 +1,80 @@
+use quote::quote;
+use syn::visit_mut::{self, VisitMut};
+use syn::{Expr, ExprRange, RangeLimits};
+
+use crate::cfg_builder::builder::CfgBuilder;
+use crate::wp_calculus::ARRAY_STORE;
+
+impl CfgBuilder {
+    // Rewrite range tests and lengths into facts over integers: '(a..b).contains(&x)' becomes
+    // 'x >= a && x < b', and 'v.len()' the length variable 'v_len', as for '--goal no-panic'
+    pub(crate) fn lower_ranges(expr: &Expr) -> Expr {
+        let mut lowered = expr.clone();
+        RangeLowering.visit_expr_mut(&mut lowered);
+        lowered
+    }
+
+    // Bounds of 'value' in a range: 'a..b', 'a..=b', 'a..' and '..b'
+    pub(crate) fn range_bound_facts(range: &ExprRange, value: &Expr) -> Expr {
+        let value = Self::wrap_with_parens(value.clone());
+        let mut facts = Vec::new();
+        if let Some(from) = &range.from {
+            facts.push(quote! { #value >= #from });
+        }
+        if let Some(to) = &range.to {
+            facts.push(match range.limits {
+                RangeLimits::HalfOpen(_) => quote! { #value < #to },
+                RangeLimits::Closed(_) => quote! { #value <= #to },
+            });
+        }
+        if facts.is_empty() {
+            return syn::parse_quote! { true };
+        }
+        syn::parse2(quote! { (#(#facts)&&*) }).expect("Failed to parse range facts")
+    }
+}
+
+struct RangeLowering;
+
+impl VisitMut for RangeLowering {
+    fn visit_expr_mut(&mut self, expr: &mut Expr) {
+        visit_mut::visit_expr_mut(self, expr);
+        let lowered = match &*expr {
+            Expr::MethodCall(call) if call.method == "contains" && call.args.len() == 1 => {
+                let mut receiver = &*call.receiver;
+                while let Expr::Paren(paren) = receiver {
+                    receiver = &paren.expr;
+                }
+                let Expr::Range(range) = receiver else { return };
+                let value = match &call.args[0] {
+                    Expr::Reference(reference) => &*reference.expr,
+                    value => value,
+                };
+                CfgBuilder::range_bound_facts(range, value)
+            },
+            // Storing an element keeps the length of the array
+            Expr::MethodCall(call) if call.method == "len" && call.args.is_empty() => {
+                let mut receiver = &*call.receiver;
+                loop {
+                    match receiver {
+                        Expr::Paren(paren) => receiver = &paren.expr,
+                        Expr::Call(store) if matches!(&*store.func, Expr::Path(func) if func.path.is_ident(ARRAY_STORE)) && store.args.len() == 3 => receiver = &store.args[0],
+                        _ => break,
+                    }
+                }
+                let name = format!("{}_len", CfgBuilder::sanitize_identifier(&CfgBuilder::render_expr(receiver)));
+                syn::parse_str(&name).expect("Failed to parse length variable")
+            },
+            // Annotations and tagged assumptions hold their condition as tokens
+            Expr::Macro(expr_macro) => {
+                let Ok(mut inner) = syn::parse2::<Expr>(expr_macro.mac.tokens.clone()) else { return };
+                self.visit_expr_mut(&mut inner);
+                let mut expr_macro = expr_macro.clone();
+                expr_macro.mac.tokens = quote! { #inner };
+                Expr::Macro(expr_macro)
+            },
+            _ => return,
+        };
+        *expr = lowered;
+    }
+}
@@ -0,0 +1,152 @@
+use petgraph::graph::NodeIndex;
+use quote::quote;
+use syn::visit_mut::{self, VisitMut};
+use syn::{Expr, UnOp};
+
+use crate::cfg_builder::builder::CfgBuilder;
+use crate::cfg_builder::node::CfgNode;
+
+// '*r', '&x' and '&mut x' are the value of the place they reach: a '&T' parameter is a snapshot of its
+// value, and a '&mut T' parameter a location the function writes through ('*out = ..' assigns 'out')
+struct ReferenceLowering;
+
+impl VisitMut for ReferenceLowering {
+    fn visit_expr_mut(&mut self, expr: &mut Expr) {
+        visit_mut::visit_expr_mut(self, expr);
+        let lowered = match &*expr {
+            Expr::Unary(unary) if matches!(unary.op, UnOp::Deref(_)) => (*unary.expr).clone(),
+            Expr::Reference(reference) => (*reference.expr).clone(),
+            Expr::Macro(expr_macro) => {
+                let Ok(mut inner) = syn::parse2::<Expr>(expr_macro.mac.tokens.clone()) else { return };
+                self.visit_expr_mut(&mut inner);
+                let mut expr_macro = expr_macro.clone();
+                expr_macro.mac.tokens = quote! { #inner };
+                Expr::Macro(expr_macro)
+            },
+            _ => return,
+        };
+        *expr = lowered;
+    }
+}
+
+// 'old(e)' is 'e' on the paths starting at the entry of the function, where the current values are the
+// values on entry. Elsewhere (from a loop invariant) it is a value of its own, 'old_e', the same in the
+// assumptions and in the goal.
+struct OldValueLowering {
+    at_entry: bool,
+}
+
+impl VisitMut for OldValueLowering {
+    fn visit_expr_mut(&mut self, expr: &mut Expr) {
+        if let Some(inner) = CfgBuilder::old_argument(expr) {
+            let mut inner = inner.clone();
+            ReferenceLowering.visit_expr_mut(&mut inner);
+            *expr = if self.at_entry {
+                CfgBuilder::wrap_with_parens(inner)
+            } else {
+                let name = format!("old_{}", CfgBuilder::sanitize_identifier(&CfgBuilder::render_expr(&inner)));
+                syn::parse_str(&name).expect("Failed to parse old value")
+            };
+            return;
+        }
+        visit_mut::visit_expr_mut(self, expr);
+        if let Expr::Macro(expr_macro) = &*expr {
+            let Ok(mut inner) = syn::parse2::<Expr>(expr_macro.mac.tokens.clone()) else { return };
+            self.visit_expr_mut(&mut inner);
+            let mut expr_macro = expr_macro.clone();
+            expr_macro.mac.tokens = quote! { #inner };
+            *expr = Expr::Macro(expr_macro);
+        }
+    }
+}
+
+// Substitution inside the arguments of 'old(..)', which the substitutions of the statements leave out
+struct OldArgumentSubstitution<'a> {
+    builder: &'a CfgBuilder,
+    var: &'a str,
+    replacement: &'a Expr,
+}
+
+impl VisitMut for OldArgumentSubstitution<'_> {
+    fn visit_expr_mut(&mut self, expr: &mut Expr) {
+        match expr {
+            Expr::Call(call) if CfgBuilder::old_argument(&Expr::Call(call.clone())).is_some() => {
+                for arg in call.args.iter_mut() {
+                    *arg = self.builder.recursive_substitution(arg, self.var, self.replacement);
+                }
+            },
+            expr => visit_mut::visit_expr_mut(self, expr),
+        }
+    }
+}
+
+impl CfgBuilder {
+    // A contract condition with 'var' replaced everywhere, in 'old(..)' too: the parameters of a callee are
+    // its arguments both before and after the call
+    pub(crate) fn substitute_in_contract(&self, condition: &Expr, var: &str, replacement: &Expr) -> Expr {
+        let mut condition = self.recursive_substitution(condition, var, replacement);
+        OldArgumentSubstitution { builder: self, var, replacement }.visit_expr_mut(&mut condition);
+        condition
+    }
+
+    pub(crate) fn lower_references(expr: &Expr) -> Expr {
+        let mut lowered = expr.clone();
+        ReferenceLowering.visit_expr_mut(&mut lowered);
+        lowered
+    }
+
+    pub(crate) fn lower_old_values(expr: &Expr, at_entry: bool) -> Expr {
+        let mut lowered = expr.clone();
+        OldValueLowering { at_entry }.visit_expr_mut(&mut lowered);
+        lowered
+    }
+
+    // The place of 'old(place)', whose value the substitutions of the statements don't change
+    pub(crate) fn old_argument(expr: &Expr) -> Option<&Expr> {
+        match expr {
+            Expr::Call(call) if call.args.len() == 1 && matches!(&*call.func, Expr::Path(path) if path.path.is_ident("old")) => call.args.first(),
+            _ => None,
+        }
+    }
+
+    // Paths from the 'pre!' of a function (or from its entry when it has none) start with the values on entry
+    pub(crate) fn starts_at_entry(&self, path: &[NodeIndex]) -> bool {
+        path.first().is_some_and(|&start| matches!(self.graph[start], CfgNode::Function(_, _) | CfgNode::Precondition(_, _) | CfgNode::Assumption(_, _)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_support::verdicts;
+    use crate::verifier::VcOutcome;
+
+    #[test]
+    fn old_is_the_value_a_mut_reference_starts_with() {
+        let add_to = |post: &str| verdicts(&format!("fn add_to(total: &mut i32, k: &i32) {{
+                pre!(*k >= 0);
+                post!({});
+                *total += *k;
+            }}", post));
+        assert!(matches!(add_to("*total == old(*total) + *k").as_slice(), [VcOutcome::Valid]));
+        assert!(matches!(add_to("*total > old(*total)").as_slice(), [VcOutcome::Counterexample(_)]));
+    }
+
+    // Around a loop 'old(..)' is carried by the invariant, not by the current value
+    #[test]
+    fn old_in_an_invariant_is_the_value_on_entry() {
+        let count_to = |invariant: &str| verdicts(&format!("fn count_to(total: &mut i32, n: i32) {{
+                pre!(n >= 0);
+                post!(*total == old(*total) + n);
+                let mut i = 0;
+                invariant!({});
+                while i < n {{
+                    *total += 1;
+                    i = i + 1;
+                }}
+            }}", invariant));
+        let outcomes = count_to("i >= 0 && i <= n && *total == old(*total) + i");
+        assert!(!outcomes.is_empty() && outcomes.iter().all(|outcome| matches!(outcome, VcOutcome::Valid)));
+        let outcomes = count_to("i >= 0 && i <= n");
+        assert!(outcomes.iter().any(|outcome| matches!(outcome, VcOutcome::Counterexample(_))));
+    }
+}
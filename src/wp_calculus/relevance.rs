@@ -0,0 +1,132 @@
+use petgraph::graph::NodeIndex;
+use proc_macro2::{TokenStream, TokenTree};
+use quote::quote;
+use std::collections::HashSet;
+use syn::{BinOp, Expr};
+
+use crate::cfg_builder::builder::CfgBuilder;
+use crate::cfg_builder::node::{CfgNode, ConditionalExpr};
+use crate::verifier;
+use crate::wp_calculus::ASSUMPTION_TAG;
+
+impl CfgBuilder {
+    // Nodes of a proved path its proof doesn't need: the assumptions outside the solver's unsat core, and
+    // the statements assigning variables that neither the goal nor a needed assumption depends on.
+    // None when the path has no verification condition or the solver doesn't prove it.
+    pub fn irrelevant_nodes(&self, path: &[NodeIndex]) -> Option<HashSet<NodeIndex>> {
+        let condition = self.tagged_wp(path)?;
+        let mut parts = Vec::new();
+        Self::implication_parts(&condition, &mut parts);
+        let goal = parts.pop()?;
+        let hypotheses: Vec<(Option<String>, Expr)> = parts.into_iter().map(Self::untag_assumption).collect();
+        let core: HashSet<String> = verifier::unsat_core(&hypotheses, &goal, &self.options.solver_settings)?.into_iter().collect();
+        let needed = |node: NodeIndex| core.contains(&format!("{}{}", ASSUMPTION_TAG, node.index()));
+
+        // Backwards from the goal: a statement matters if it assigns a variable used later by the goal or
+        // by a needed assumption
+        let mut live = match path.last().map(|&node| &self.graph[node]) {
+            Some(CfgNode::Postcondition(_, Some(expr))) | Some(CfgNode::Invariant(_, Some(expr))) | Some(CfgNode::Decreases(_, Some(expr))) => Self::identifiers(&quote!(#expr)),
+            _ => Self::identifiers(&quote!(#goal)),
+        };
+        let mut irrelevant = HashSet::new();
+        for (position, &node) in path.iter().enumerate().rev().skip(1) {
+            // Summarized regions use variables their conditions don't show: the statements before them are kept
+            if self.region_at(path, position).is_some() {
+                break;
+            }
+            if let Some(call) = self.results.node_metadata.get(&node).and_then(|metadata| metadata.indirect_call.as_ref()) {
+                if needed(node) {
+                    for post in &call.postconditions {
+                        live.extend(Self::identifiers(&post.parse().unwrap_or_default()));
+                    }
+                } else {
+                    irrelevant.insert(node);
+                }
+                continue;
+            }
+            match &self.graph[node] {
+                CfgNode::Statement(stmt, _) => match self.parse_assignment(stmt) {
+                    Some((var, value)) if live.contains(var.split('.').next().unwrap_or(&var)) => {
+                        if !var.contains('.') {
+                            live.remove(&var);
+                        }
+                        live.extend(Self::identifiers(&quote!(#value)));
+                    },
+                    _ => {
+                        irrelevant.insert(node);
+                    },
+                },
+                CfgNode::Condition(_, Some(conditional)) => {
+                    if needed(node) {
+                        let condition = match conditional {
+                            ConditionalExpr::Match(match_condition) => {
+                                let arm = self.next_edge_kind(path, node).and_then(|kind| match_condition.arm_for_edge(&kind));
+                                let arm_condition = arm.and_then(|arm| arm.condition.clone());
+                                quote!(#arm_condition)
+                            },
+                            ConditionalExpr::ForLoop(expr_for) => {
+                                let (pat, iterated) = (&expr_for.pat, &expr_for.expr);
+                                quote!(#pat #iterated)
+                            },
+                            conditional => {
+                                let expr = conditional.to_syn_expr();
+                                quote!(#expr)
+                            },
+                        };
+                        live.extend(Self::identifiers(&condition));
+                    } else {
+                        irrelevant.insert(node);
+                    }
+                },
+                CfgNode::Precondition(_, Some(_)) | CfgNode::Assumption(_, Some(_)) | CfgNode::Invariant(_, Some(_)) if !needed(node) => {
+                    irrelevant.insert(node);
+                },
+                // An assertion is checked as well as assumed: what it reads is always needed
+                CfgNode::Assertion(_, Some(expr)) => live.extend(Self::identifiers(&quote!(#expr))),
+                _ => {},
+            }
+        }
+        Some(irrelevant)
+    }
+
+    // Elements of the chain 'a >> b >> goal', in order
+    pub(crate) fn implication_parts(condition: &Expr, parts: &mut Vec<Expr>) {
+        match condition {
+            Expr::Binary(bin) if matches!(bin.op, BinOp::Shr(_)) => {
+                Self::implication_parts(&bin.left, parts);
+                Self::implication_parts(&bin.right, parts);
+            },
+            Expr::Paren(paren) if matches!(&*paren.expr, Expr::Binary(bin) if matches!(bin.op, BinOp::Shr(_))) => {
+                Self::implication_parts(&paren.expr, parts);
+            },
+            condition => parts.push(condition.clone()),
+        }
+    }
+
+    // 'assumption_12!(cond)' gives the tracked name 'assumption_12' and 'cond'
+    pub(crate) fn untag_assumption(expr: Expr) -> (Option<String>, Expr) {
+        if let Expr::Macro(expr_macro) = &expr {
+            if let Some(tag) = expr_macro.mac.path.get_ident().map(|ident| ident.to_string()).filter(|name| name.starts_with(ASSUMPTION_TAG)) {
+                if let Ok(inner) = syn::parse2::<Expr>(expr_macro.mac.tokens.clone()) {
+                    return (Some(tag), inner);
+                }
+            }
+        }
+        (None, expr)
+    }
+
+    // Every identifier in the tokens, macro arguments included
+    pub(crate) fn identifiers(tokens: &TokenStream) -> HashSet<String> {
+        let mut identifiers = HashSet::new();
+        for token in tokens.clone() {
+            match token {
+                TokenTree::Ident(ident) => {
+                    identifiers.insert(ident.to_string());
+                },
+                TokenTree::Group(group) => identifiers.extend(Self::identifiers(&group.stream())),
+                _ => {},
+            }
+        }
+        identifiers
+    }
+}
@@ -0,0 +1,118 @@
+use std::collections::HashMap;
+
+use petgraph::graph::NodeIndex;
+
+use crate::cfg_builder::builder::CfgBuilder;
+use crate::cfg_builder::node::{CfgNode, ConditionalExpr, EdgeKind};
+use crate::cfg_builder::ConstValue;
+use crate::wp_calculus::MEASURE_AT_HEAD;
+
+// What running a basic path with concrete inputs shows
+#[derive(Debug, Clone, PartialEq)]
+pub enum SimulationOutcome {
+    Holds, // every annotation checked along the path holds
+    Fails(String), // the first annotation that doesn't
+    NotTaken(String), // the inputs leave the path at this branch, or don't satisfy the annotation it starts from
+    Unknown(String), // the first annotation or branch the evaluator can't compute
+}
+
+#[derive(Debug, Clone)]
+pub struct PathSimulation {
+    pub trace: String,
+    pub outcome: SimulationOutcome,
+}
+
+impl CfgBuilder {
+    // Run a basic path forward with concrete values of its inputs, without the solver ('simulate'): the
+    // statements update the values, each branch must go the way the path does, and each annotation is
+    // evaluated. Values the evaluator can't compute (calls, fields of unknown values) are left unknown.
+    pub fn simulate_path(&self, path: &[NodeIndex], inputs: &HashMap<String, ConstValue>) -> PathSimulation {
+        let path = &self.expand_regions(path, inputs);
+        let mut state = inputs.clone();
+        let mut trace = String::new();
+        let mut outcome = SimulationOutcome::Holds;
+
+        for (step, &node) in path.iter().enumerate() {
+            // 'check' is the value of the annotation or branch condition of the node, when it has one
+            let mut note = String::new();
+            let (kind, text, check) = match &self.graph[node] {
+                CfgNode::Function(name, _) => ("enter", format!("fn {}", name), None),
+                CfgNode::Precondition(pre, expr) => ("assume", format!("pre: {}", pre), Some(self.trace_check(expr.as_ref(), &state))),
+                CfgNode::Assumption(fact, expr) => ("assume", fact.clone(), Some(self.trace_check(expr.as_ref(), &state))),
+                CfgNode::Invariant(inv, expr) if step == 0 => ("assume", format!("invariant: {}", inv), Some(self.trace_check(expr.as_ref(), &state))),
+                CfgNode::Invariant(inv, expr) => ("assert", format!("invariant: {}", inv), Some(self.trace_check(expr.as_ref(), &state))),
+                CfgNode::Postcondition(post, expr) => ("assert", format!("post: {}", post), Some(self.trace_check(expr.as_ref(), &state))),
+                CfgNode::Assertion(condition, expr) => ("assert", format!("assert: {}", condition), Some(self.trace_check(expr.as_ref(), &state))),
+                CfgNode::Decreases(measure, _) if step + 1 < path.len() => {
+                    self.advance_concrete_state(node, &mut state);
+                    if let Some(value) = state.get(MEASURE_AT_HEAD) {
+                        note = format!("{} = {}", MEASURE_AT_HEAD, value);
+                    }
+                    ("measure", format!("decreases: {}", measure), None)
+                },
+                CfgNode::Decreases(measure, expr) => ("assert", format!("decreases: {}", measure), Some(self.trace_check(expr.as_ref().and_then(Self::termination_goal).as_ref(), &state))),
+                CfgNode::Cutoff(inv) => ("cut", inv.clone(), None),
+                CfgNode::Return(ret, _) => {
+                    self.advance_concrete_state(node, &mut state);
+                    if let Some(value) = state.get("result").filter(|_| self.returned_value(node).is_some()) {
+                        note = format!("result = {}", value);
+                    }
+                    ("return", ret.clone(), None)
+                },
+                CfgNode::MergePoint => continue,
+                CfgNode::Statement(stmt, _) => {
+                    self.advance_concrete_state(node, &mut state);
+                    if let Some((var, _)) = self.parse_assignment(stmt) {
+                        note = match state.get(&var) {
+                            Some(value) => format!("{} = {}", var, value),
+                            None => format!("{} = ?", var),
+                        };
+                    }
+                    ("exec", stmt.clone(), None)
+                },
+                CfgNode::Condition(cond, conditional) => {
+                    let taken = self.next_edge_kind(path, node).unwrap_or(EdgeKind::Fallthrough);
+                    let holds = match conditional {
+                        Some(ConditionalExpr::If(expr)) | Some(ConditionalExpr::While(expr)) => self.trace_check(Some(&**expr), &state)
+                            .map(|holds| holds != self.is_false_branch(path, node)),
+                        Some(ConditionalExpr::Match(match_condition)) => match_condition.arm_for_edge(&taken)
+                            .and_then(|arm| arm.condition.as_ref())
+                            .and_then(|condition| self.trace_check(Some(condition), &state)),
+                        _ => Some(true),
+                    };
+                    ("branch", format!("{} -> {}", cond, if taken == EdgeKind::Fallthrough { "next".to_string() } else { taken.label() }), Some(holds))
+                },
+            };
+
+            let verdict = match (kind, check) {
+                (_, None) => note,
+                (_, Some(Some(true))) => "[holds]".to_string(),
+                ("assert", Some(Some(false))) => {
+                    if outcome == SimulationOutcome::Holds {
+                        outcome = SimulationOutcome::Fails(text.clone());
+                    }
+                    "[fails]".to_string()
+                },
+                // The inputs don't follow the path past this node
+                (_, Some(Some(false))) => {
+                    outcome = match kind {
+                        "branch" => SimulationOutcome::NotTaken(format!("{} is not the branch the inputs take", text)),
+                        _ => SimulationOutcome::NotTaken(format!("the inputs don't satisfy {}", text)),
+                    };
+                    trace.push_str(&format!("  #{:<3} {:<7} {:<40} [not taken]  <== the inputs leave the path here\n", step, kind, text));
+                    break;
+                },
+                (_, Some(None)) => {
+                    if outcome == SimulationOutcome::Holds {
+                        outcome = SimulationOutcome::Unknown(text.clone());
+                    }
+                    "[unknown]".to_string()
+                },
+            };
+            let line = format!("  #{:<3} {:<7} {:<40} {}", step, kind, text, verdict);
+            trace.push_str(line.trim_end());
+            trace.push('\n');
+        }
+        PathSimulation { trace, outcome }
+    }
+}
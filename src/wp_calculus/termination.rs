@@ -0,0 +1,25 @@
+use syn::Expr;
+
+use crate::cfg_builder::builder::CfgBuilder;
+
+// Value of the measure of a loop at the start of an iteration, in the goal of the termination paths; the
+// 'decreases!' node after the head of the loop gives it the value of the measure there
+pub const MEASURE_AT_HEAD: &str = "measure_at_head";
+
+impl CfgBuilder {
+    // 'n - i' of 'decreases!(n - i)'
+    pub(crate) fn loop_measure(annotation: &Expr) -> Option<Expr> {
+        match annotation {
+            Expr::Macro(expr_macro) => syn::parse2::<Expr>(expr_macro.mac.tokens.clone()).ok(),
+            _ => None,
+        }
+    }
+
+    // What an iteration does to the measure: non-negative when it starts, smaller when it ends,
+    // '(measure_at_head >= 0 && n - i < measure_at_head)', in parentheses as the goal of an implication
+    pub(crate) fn termination_goal(annotation: &Expr) -> Option<Expr> {
+        let measure = Self::loop_measure(annotation)?;
+        let at_head = syn::Ident::new(MEASURE_AT_HEAD, proc_macro2::Span::call_site());
+        Some(syn::parse_quote! { (#at_head >= 0 && #measure < #at_head) })
+    }
+}
@@ -19,28 +19,78 @@
 /// - Relies on the `syn` crate for Rust syntax parsing.
 /// - Uses `petgraph` for traversing the CFG and maintaining node relationships.
 
-use syn::{Expr, Stmt, ExprAssign, ExprBinary, ExprBlock, ExprIf, ExprCall, ExprUnary, ExprParen, Local, ExprMacro, Macro, Block};
+use syn::{BinOp, Expr, Stmt, ExprAssign, ExprBinary, ExprBlock, ExprIf, ExprCall, ExprUnary, ExprParen, ExprCast, ExprField, ExprIndex, ExprRange, ExprReference, Local, ExprMacro, Macro, Block, Pat};
 use std::collections::HashMap;
 use quote::quote;
 use petgraph::graph::{DiGraph, NodeIndex};
 use petgraph::visit::EdgeRef;
 use crate::cfg_builder::{builder::CfgBuilder, node::ConditionalExpr};
-use crate::cfg_builder::node::{CfgNode};
+use crate::cfg_builder::node::{CfgNode, EdgeKind};
+use crate::cfg_builder::RegionSummary;
+use crate::cfg_builder::node::IndirectCallInfo;
+use crate::wp_calculus::MEASURE_AT_HEAD;
 use proc_macro2::{Span, TokenTree, TokenStream};
 
+// Prefix of the macros tagging the assumptions of a verification condition with their node
+pub const ASSUMPTION_TAG: &str = "assumption_";
+
+// 'array_store(a, i, v)' is the array 'a' with 'v' at index 'i', the value of 'a' after 'a[i] = v'
+pub const ARRAY_STORE: &str = "array_store";
+
+// Value of a target the statement leaves unknown ('x <<= 1'), given a fresh name per node by the wp
+pub const HAVOC: &str = "__havoc";
+
 impl CfgBuilder {
     pub fn apply_wp_calculus(&self, paths: &[Vec<NodeIndex>]) -> Vec<String> {
         let mut updated_postconditions = Vec::new();
 
         for path in paths {
-            let mut variable_state = HashMap::new();
-            let mut working_condition: Option<syn::Expr> = None;
+            if let Some(cond) = self.wp_along_path(path, path.len(), None) {
+                // Constants of the file are known values, not free variables of the condition, and the
+                // logic functions it calls are defined in front of it
+                let cond = self.with_logic_definitions(&self.substitute_constants(&cond));
+                updated_postconditions.push(quote! { #cond }.to_string());
+            }
+        }
+
+        updated_postconditions
+    }
+
+    // Chain the nodes 'path[..end]' in front of 'goal' (e.g. an obligation checked at 'path[end]');
+    // the whole path is still used to tell which branch each condition takes
+    pub fn wp_along_path(&self, path: &[NodeIndex], end: usize, goal: Option<Expr>) -> Option<Expr> {
+        self.wp_chain(path, end, goal, true, false)
+    }
+
+    // Verification condition of a path with each assumption wrapped in 'assumption_<node>!(..)', the
+    // index of the node it comes from, so that the solver can tell which ones a proof needs
+    pub fn tagged_wp(&self, path: &[NodeIndex]) -> Option<Expr> {
+        self.wp_chain(path, path.len(), None, true, true).map(|cond| self.with_logic_definitions(&self.substitute_constants(&cond)))
+    }
 
+    // Same as 'wp_along_path', with 'summarize' telling whether summarized regions are crossed in one step
+    // and 'tagged' whether the assumptions are tagged with their node
+    fn wp_chain(&self, path: &[NodeIndex], end: usize, goal: Option<Expr>, summarize: bool, tagged: bool) -> Option<Expr> {
+        let mut variable_state = HashMap::new();
+        let mut working_condition: Option<syn::Expr> = goal;
+        {
             // Traverse the path in reverse (from postcondition up to precondition)
-            for &node_index in path.iter().rev() {
+            for (position, &node_index) in path[..end].iter().enumerate().rev() {
+                // A summarized region is crossed by all its paths at once
+                if let Some(region) = self.region_at(path, position).filter(|_| summarize) {
+                    working_condition = working_condition.map(|goal| self.wp_through_region(region, goal));
+                    continue;
+                }
+                // The effects of an indirect call are its postconditions, or unknown
+                if let Some(call) = self.results.node_metadata.get(&node_index).and_then(|metadata| metadata.indirect_call.as_ref()) {
+                    working_condition = working_condition.map(|goal| self.wp_through_indirect_call(node_index, call, goal, tagged));
+                    continue;
+                }
                 match &self.graph[node_index] {
                     CfgNode::Statement(stmt_str, stmt_option) => {
                         if let Some((var, expr)) = self.parse_assignment(stmt_str) {
+                            let fresh: Expr = syn::parse_str(&format!("{}_havoc{}", var, node_index.index())).expect("Failed to parse havoc variable");
+                            let expr = self.recursive_substitution(&expr, HAVOC, &fresh);
                             // Check if there is a working condition that needs substitution
                             if let Some(mut cond) = working_condition.take() {
                                 // Substitute once per variable
@@ -53,6 +103,39 @@ impl CfgBuilder {
                             //println!("varState: {:?}", variable_state);
                         }
                     },
+                    CfgNode::Condition(_, Some(ConditionalExpr::Match(match_condition))) => {
+                        // The taken arm is identified by the edge leaving the match
+                        let arm = self.next_edge_kind(path, node_index)
+                            .and_then(|kind| match_condition.arm_for_edge(&kind));
+                        let mut assumptions = Vec::new();
+                        if let Some(fact) = &match_condition.exhaustive_fact {
+                            assumptions.push(Self::wrap_with_parens(fact.clone()));
+                        }
+                        if let Some(arm_condition) = arm.and_then(|arm| arm.condition.clone()) {
+                            assumptions.push(Self::wrap_with_parens(arm_condition));
+                        }
+                        for expr in assumptions.into_iter().rev() {
+                            let expr = Self::tag_assumption(expr, node_index, tagged);
+                            working_condition = Some(if let Some(existing_cond) = working_condition.take() {
+                                syn::parse2(quote! { #expr >> #existing_cond }).expect("Failed to parse condition implication")
+                            } else {
+                                expr
+                            });
+                        }
+                    },
+                    CfgNode::Condition(_, Some(ConditionalExpr::ForLoop(expr_for))) if Self::loop_adapters(&expr_for.expr).is_some() => {
+                        // Adapter loops are assumed by their bound facts rather than by the iterator expression
+                        let adapters = Self::loop_adapters(&expr_for.expr).expect("adapters checked by the guard");
+                        let taken = !self.is_false_branch(path, node_index);
+                        let facts = Self::loop_bound_facts(expr_for, &adapters, taken);
+                        let expr: Expr = syn::parse2(quote! { (#(#facts)&&*) }).expect("Failed to parse loop facts");
+                        let expr = Self::tag_assumption(expr, node_index, tagged);
+                        working_condition = Some(if let Some(existing_cond) = working_condition.take() {
+                            syn::parse2(quote! { #expr >> #existing_cond }).expect("Failed to parse condition implication")
+                        } else {
+                            expr
+                        });
+                    },
                     CfgNode::Condition(_, Some(conditional_expr)) => {
                         // Don't substitute conditions but add them in the implication chain
                         let is_false_branch = self.is_false_branch(&path, node_index);
@@ -79,7 +162,7 @@ impl CfgBuilder {
                             }
                         };
 
-                        let expr = updated_expr.to_syn_expr();
+                        let expr = Self::tag_assumption(updated_expr.to_syn_expr().clone(), node_index, tagged);
                         working_condition = Some(if let Some(existing_cond) = working_condition.take() {
                             syn::parse2(quote! { #expr >> #existing_cond }).expect("Failed to parse condition implication")
                         } else {
@@ -91,14 +174,42 @@ impl CfgBuilder {
                         // Substitute variables in the postcondition/invariant and chain with the current condition
                         let expr = expr.clone();
                         working_condition = Some(if let Some(existing_cond) = working_condition.take() {
+                            let expr = Self::tag_assumption(expr, node_index, tagged);
                             syn::parse2(quote! { #expr >> #existing_cond }).expect("Failed to parse conjunction")
                         } else {
                             expr
                         });
                     },
-                    CfgNode::Precondition(_, Some(expr)) => {
+                    // The copy of a 'decreases!' ending a termination path is its goal, and the node after the loop
+                    // head gives the measure at the start of the iteration its value
+                    CfgNode::Decreases(_, Some(expr)) => {
+                        working_condition = match (working_condition.take(), Self::loop_measure(expr)) {
+                            (Some(cond), Some(measure)) => Some(self.recursive_substitution(&cond, MEASURE_AT_HEAD, &measure)),
+                            (None, _) if position + 1 == path.len() => Self::termination_goal(expr),
+                            (cond, _) => cond,
+                        };
+                    },
+                    // The postconditions after a return read what it returns as 'result'
+                    CfgNode::Return(_, _) => {
+                        if let Some(value) = self.returned_value(node_index) {
+                            working_condition = working_condition.map(|cond| self.recursive_substitution(&cond, "result", &value));
+                        }
+                    },
+                    // An assertion is checked from what the path knows where it is, then assumed by the rest of it.
+                    // The tagged chain only assumes it: the unsat core is about the assumptions of the goal.
+                    CfgNode::Assertion(_, Some(expr)) if !tagged => {
+                        let expr = Self::wrap_with_parens(expr.clone());
+                        working_condition = Some(match working_condition.take() {
+                            Some(existing_cond) => {
+                                let existing_cond = Self::wrap_with_parens(existing_cond);
+                                syn::parse2(quote! { (#expr && #existing_cond) }).expect("Failed to parse assertion")
+                            },
+                            None => expr,
+                        });
+                    },
+                    CfgNode::Precondition(_, Some(expr)) | CfgNode::Assumption(_, Some(expr)) | CfgNode::Assertion(_, Some(expr)) => {
                         // Chain with the current condition
-                        let expr = expr.clone();
+                        let expr = Self::tag_assumption(expr.clone(), node_index, tagged);
                         working_condition = Some(if let Some(existing_cond) = working_condition.take() {
                             syn::parse2(quote! { #expr >> #existing_cond }).expect("Failed to parse conjunction")
                         } else {
@@ -109,28 +220,82 @@ impl CfgBuilder {
                 }
             }
 
-            if let Some(cond) = working_condition {
-                updated_postconditions.push(quote! { #cond }.to_string());
-            }
         }
 
-        updated_postconditions
+        // Calls that return their receiver or argument ('x.clone()') are that value for the solver, and
+        // range tests and lengths are bound facts over integers. The paths of a region are part of the path
+        // crossing it, which gives 'old(..)' its value.
+        let at_entry = self.starts_at_entry(path);
+        working_condition.map(|condition| {
+            let condition = Self::lower_ranges(&Self::resolve_pure_calls(&Self::lower_references(&condition)));
+            if summarize { Self::lower_old_values(&condition, at_entry) } else { condition }
+        })
     }
 
-    fn is_false_branch(&self, path: &[NodeIndex], current_node: NodeIndex) -> bool {
+    // Conjunction of the conditions of every path through the region, each chained in front of 'goal'
+    fn wp_through_region(&self, region: &RegionSummary, goal: Expr) -> Expr {
+        let branches: Vec<Expr> = region.paths.iter()
+            .filter_map(|path| self.wp_chain(path, path.len() - 1, Some(goal.clone()), false, false))
+            .map(Self::wrap_with_parens)
+            .collect();
+        let conjunction: Expr = syn::parse2(quote! { #(#branches)&&* }).expect("Failed to parse region conjunction");
+        Self::wrap_with_parens(conjunction)
+    }
+
+    // Give the variables written by the call fresh values ('y_havoc12'), and assume the postconditions
+    // of the call about them
+    fn wp_through_indirect_call(&self, node: NodeIndex, call: &IndirectCallInfo, goal: Expr, tagged: bool) -> Expr {
+        let fresh = |var: &str| -> Expr { syn::parse_str(&format!("{}_havoc{}", var, node.index())).expect("Failed to parse havoc variable") };
+        let mut goal = goal;
+        for var in &call.havoc {
+            goal = self.recursive_substitution(&goal, var, &fresh(var));
+        }
+        // In the postconditions, 'old(x)' is the value of 'x' before the call
+        let posts: Vec<Expr> = call.postconditions.iter()
+            .filter_map(|post| syn::parse_str::<Expr>(post).ok())
+            .map(|post| {
+                let post = Self::lower_references(&post);
+                let post = self.recursive_substitution(&post, "result", &fresh(call.result.as_deref().unwrap_or("result")));
+                let post = call.havoc.iter().fold(post, |post, var| self.recursive_substitution(&post, var, &fresh(var)));
+                Self::lower_old_values(&post, true)
+            })
+            .map(|post| Self::tag_assumption(Self::wrap_with_parens(post), node, tagged))
+            .collect();
+        posts.into_iter().rev().fold(goal, |goal, post| {
+            syn::parse2(quote! { #post >> #goal }).expect("Failed to parse condition implication")
+        })
+    }
+
+    // 'assumption_12!(cond)' for an assumption made at node 12
+    fn tag_assumption(expr: Expr, node: NodeIndex, tagged: bool) -> Expr {
+        if !tagged {
+            return expr;
+        }
+        let tag = syn::Ident::new(&format!("{}{}", ASSUMPTION_TAG, node.index()), Span::call_site());
+        syn::parse_quote! { #tag!(#expr) }
+    }
+
+    pub(crate) fn is_false_branch(&self, path: &[NodeIndex], current_node: NodeIndex) -> bool {
         // Iterate over edges connecting from the current node in the path
         let current_index = path.iter().position(|&n| n == current_node);
         if let Some(index) = current_index {
             if let Some(next_node) = path.get(index + 1) {
                 if let Some(edge) = self.graph.edges_connecting(current_node, *next_node).next() {
-                    return edge.weight() == "false";
+                    return *edge.weight() == EdgeKind::FalseBranch;
                 }
             }
         }
         false
     }
 
-    fn wrap_with_parens(expr: Expr) -> Expr {
+    // Kind of the path edge leaving 'current_node'
+    pub(crate) fn next_edge_kind(&self, path: &[NodeIndex], current_node: NodeIndex) -> Option<EdgeKind> {
+        let index = path.iter().position(|&n| n == current_node)?;
+        let next_node = path.get(index + 1)?;
+        self.graph.edges_connecting(current_node, *next_node).next().map(|edge| edge.weight().clone())
+    }
+
+    pub(crate) fn wrap_with_parens(expr: Expr) -> Expr {
         Expr::Paren(ExprParen {
             attrs: Vec::new(),
             paren_token: syn::token::Paren(Span::call_site()),
@@ -141,11 +306,12 @@ impl CfgBuilder {
     pub fn recursive_substitution(&self, expr: &Expr, var: &str, replacement_without_paren: &Expr) -> Expr {
         // println!("Substituting in expr: {:?}", quote! {#expr});
         self.print_expr_details(expr);
-        let replacement = &Expr::Paren(ExprParen {
-            attrs: Vec::new(),
-            paren_token: syn::token::Paren(Span::call_site()),
-            expr: Box::new(replacement_without_paren.clone()),
-        });
+        // Parenthesized only when the value could bind to the operators around the variable
+        let replacement = &if Self::binds_tightest(replacement_without_paren) {
+            replacement_without_paren.clone()
+        } else {
+            Self::wrap_with_parens(replacement_without_paren.clone())
+        };
     
         match expr {
             Expr::Path(expr_path) => {
@@ -156,6 +322,31 @@ impl CfgBuilder {
                     expr.clone()
                 }
             },
+            // Field places ('self.len') and constructed values span several tokens, and quantifiers bind names:
+            // substituted in the parsed condition
+            Expr::Macro(expr_macro) if var.contains('.') || Self::constructor_fields(replacement_without_paren).is_some()
+                || crate::spec_parser::mentions_quantifier(&expr_macro.mac.tokens) => {
+                match syn::parse2::<Expr>(expr_macro.mac.tokens.clone()) {
+                    Ok(inner) => {
+                        let inner = self.recursive_substitution(&inner, var, replacement_without_paren);
+                        let mut expr_macro = expr_macro.clone();
+                        expr_macro.mac.tokens = quote! { #inner };
+                        Expr::Macro(expr_macro)
+                    },
+                    Err(_) => expr.clone(),
+                }
+            },
+            Expr::Field(field) => {
+                if Self::field_place_name(expr).as_deref() == Some(var) {
+                    return replacement.clone();
+                }
+                let base = self.recursive_substitution(&field.base, var, replacement_without_paren);
+                // Field of a struct value: the value it was initialized with
+                if let Some(value) = Self::struct_field_value(&base, &field.member) {
+                    return Self::wrap_with_parens(value);
+                }
+                Expr::Field(ExprField { base: Box::new(base), ..field.clone() })
+            },
             Expr::Macro(expr_macro) => {
                 let new_tokens = self.substitute_in_token_stream(&expr_macro.mac.tokens, var, replacement);
                 // println!("new_tokens:{:?}", new_tokens);
@@ -172,40 +363,69 @@ impl CfgBuilder {
             Expr::Assign(assign) => {
                 Expr::Assign(ExprAssign {
                     attrs: assign.attrs.clone(),
-                    left: Box::new(self.recursive_substitution(&assign.left, var, replacement)),
+                    left: Box::new(self.recursive_substitution(&assign.left, var, replacement_without_paren)),
                     eq_token: assign.eq_token,
-                    right: Box::new(self.recursive_substitution(&assign.right, var, replacement)),
+                    right: Box::new(self.recursive_substitution(&assign.right, var, replacement_without_paren)),
                 })
             },
             Expr::Binary(bin) => {
                 // println!("Binary Expression: left = {}, op = {}, right = {}", quote! {#bin.left}, quote! {#bin.op}, quote! {#bin.right});
                 Expr::Binary(ExprBinary {
                     attrs: bin.attrs.clone(),
-                    left: Box::new(self.recursive_substitution(&bin.left, var, replacement)),
-                    op: bin.op.clone(),
-                    right: Box::new(self.recursive_substitution(&bin.right, var, replacement)),
+                    left: Box::new(self.recursive_substitution(&bin.left, var, replacement_without_paren)),
+                    op: bin.op,
+                    right: Box::new(self.recursive_substitution(&bin.right, var, replacement_without_paren)),
                 })
             },
+            // The value on entry doesn't change with the statements
+            Expr::Call(_) if Self::old_argument(expr).is_some() => expr.clone(),
             Expr::Call(call) => {
-                Expr::Call(ExprCall {
+                let call = ExprCall {
                     attrs: call.attrs.clone(),
-                    func: Box::new(self.recursive_substitution(&call.func, var, replacement)),
+                    func: Box::new(self.recursive_substitution(&call.func, var, replacement_without_paren)),
                     paren_token: call.paren_token,
-                    args: call.args.iter().map(|arg| self.recursive_substitution(arg, var, replacement)).collect(),
-                })
+                    args: call.args.iter().map(|arg| self.recursive_substitution(arg, var, replacement_without_paren)).collect(),
+                };
+                // 'is_some(r)' with 'r = Some(v)'
+                let query = match (&*call.func, call.args.first()) {
+                    (Expr::Path(func), Some(value)) => func.path.get_ident()
+                        .and_then(|name| Self::variant_query(&name.to_string(), value, &call.args.iter().skip(1).cloned().collect::<Vec<_>>())),
+                    _ => None,
+                };
+                query.unwrap_or(Expr::Call(call))
+            },
+            Expr::MethodCall(method_call) => {
+                let mut method_call = method_call.clone();
+                method_call.receiver = Box::new(self.recursive_substitution(&method_call.receiver, var, replacement_without_paren));
+                method_call.args = method_call.args.iter().map(|arg| self.recursive_substitution(arg, var, replacement_without_paren)).collect();
+                // 'r.is_some()' and 'r.unwrap()' with 'r = Some(v)'
+                let args: Vec<Expr> = method_call.args.iter().cloned().collect();
+                Self::variant_query(&method_call.method.to_string(), &method_call.receiver, &args).unwrap_or(Expr::MethodCall(method_call))
             },
             Expr::Unary(unary) => {
                 Expr::Unary(ExprUnary {
                     attrs: unary.attrs.clone(),
                     op: unary.op.clone(),
-                    expr: Box::new(self.recursive_substitution(&unary.expr, var, replacement)),
+                    expr: Box::new(self.recursive_substitution(&unary.expr, var, replacement_without_paren)),
                 })
             },
             Expr::Paren(paren) => {
                 Expr::Paren(ExprParen {
                     attrs: paren.attrs.clone(),
                     paren_token: paren.paren_token,
-                    expr: Box::new(self.recursive_substitution(&paren.expr, var, replacement)),
+                    expr: Box::new(self.recursive_substitution(&paren.expr, var, replacement_without_paren)),
+                })
+            },
+            Expr::Reference(reference) => {
+                Expr::Reference(ExprReference {
+                    expr: Box::new(self.recursive_substitution(&reference.expr, var, replacement_without_paren)),
+                    ..reference.clone()
+                })
+            },
+            Expr::Cast(cast) => {
+                Expr::Cast(ExprCast {
+                    expr: Box::new(self.recursive_substitution(&cast.expr, var, replacement_without_paren)),
+                    ..cast.clone()
                 })
             },
             Expr::Block(block) => {
@@ -213,19 +433,60 @@ impl CfgBuilder {
                     attrs: block.attrs.clone(),
                     label: block.label.clone(),
                     block: Block {
-                        stmts: block.block.stmts.iter().map(|stmt| self.recursive_substitute_stmt(stmt, var, replacement)).collect(),
+                        stmts: block.block.stmts.iter().map(|stmt| self.recursive_substitute_stmt(stmt, var, replacement_without_paren)).collect(),
                         ..block.block.clone()
                     },
                 })
             },
+            Expr::Index(index) => {
+                Expr::Index(ExprIndex {
+                    expr: Box::new(self.recursive_substitution(&index.expr, var, replacement_without_paren)),
+                    index: Box::new(self.recursive_substitution(&index.index, var, replacement_without_paren)),
+                    ..index.clone()
+                })
+            },
+            Expr::Range(range) => {
+                Expr::Range(ExprRange {
+                    from: range.from.as_ref().map(|from| Box::new(self.recursive_substitution(from, var, replacement_without_paren))),
+                    to: range.to.as_ref().map(|to| Box::new(self.recursive_substitution(to, var, replacement_without_paren))),
+                    ..range.clone()
+                })
+            },
+            // The variable of a quantifier is not the variable of the same name outside of it, and is renamed
+            // when the replacement names it
+            Expr::Closure(closure) => {
+                let bound: Vec<String> = closure.inputs.iter().flat_map(Self::pattern_bindings).collect();
+                if bound.iter().any(|name| name == var) {
+                    return expr.clone();
+                }
+                let mut closure = closure.clone();
+                let mentioned = Self::identifiers(&quote! { #replacement_without_paren });
+                for name in bound.iter().filter(|name| mentioned.contains(*name)) {
+                    let mut fresh = format!("{}_", name);
+                    while mentioned.contains(&fresh) {
+                        fresh.push('_');
+                    }
+                    let fresh = syn::Ident::new(&fresh, Span::call_site());
+                    for input in closure.inputs.iter_mut() {
+                        if let Pat::Ident(pat_ident) = input {
+                            if pat_ident.ident == name {
+                                pat_ident.ident = fresh.clone();
+                            }
+                        }
+                    }
+                    closure.body = Box::new(self.recursive_substitution(&closure.body, name, &syn::parse_quote!(#fresh)));
+                }
+                closure.body = Box::new(self.recursive_substitution(&closure.body, var, replacement_without_paren));
+                Expr::Closure(closure)
+            },
             Expr::If(expr_if) => {
                 Expr::If(ExprIf {
-                    cond: Box::new(self.recursive_substitution(&expr_if.cond, var, replacement)),
+                    cond: Box::new(self.recursive_substitution(&expr_if.cond, var, replacement_without_paren)),
                     then_branch: Block {
-                        stmts: expr_if.then_branch.stmts.iter().map(|stmt| self.recursive_substitute_stmt(stmt, var, replacement)).collect(),
+                        stmts: expr_if.then_branch.stmts.iter().map(|stmt| self.recursive_substitute_stmt(stmt, var, replacement_without_paren)).collect(),
                         ..expr_if.then_branch.clone()
                     },
-                    else_branch: expr_if.else_branch.as_ref().map(|else_expr| (else_expr.0.clone(), Box::new(self.recursive_substitution(&else_expr.1, var, replacement)))),
+                    else_branch: expr_if.else_branch.as_ref().map(|else_expr| (else_expr.0.clone(), Box::new(self.recursive_substitution(&else_expr.1, var, replacement_without_paren)))),
                     ..expr_if.clone()
                 })
             },
@@ -258,7 +519,7 @@ impl CfgBuilder {
         }
     }
 
-    fn parse_assignment(&self, stmt: &str) -> Option<(String, syn::Expr)> {
+    pub(crate) fn parse_assignment(&self, stmt: &str) -> Option<(String, syn::Expr)> {
         // Debug print the input statement
         // Ensure the statement ends with a semicolon
         let stmt = if stmt.trim_end().ends_with(';') {
@@ -282,36 +543,35 @@ impl CfgBuilder {
         //println!("Parsed syn::Stmt: {:#?}", &stmt);
     
         if let syn::Stmt::Expr(syn::Expr::Assign(assign)) | syn::Stmt::Semi(syn::Expr::Assign(assign), _) = stmt.clone() {
-            // Handle simple assignments like 'count = 0;'
-            if let syn::Expr::Path(path) = *assign.left {
-                if let Some(ident) = path.path.get_ident() {
-                    let var = ident.to_string();
-                    // println!("Found assignment: {} = {:?}", var, *assign.right);
-                    return Some((var, *assign.right));
-                }
+            // Handle simple assignments like 'count = 0;' and field assignments like 'self.len = 0;'
+            if let Some(var) = Self::field_place_name(&assign.left) {
+                // println!("Found assignment: {} = {:?}", var, *assign.right);
+                return Some((var, Self::resolve_pure_calls(&assign.right)));
             }
+            return Self::element_assignment(&assign.left, Self::resolve_pure_calls(&assign.right));
         } else if let syn::Stmt::Expr(syn::Expr::AssignOp(assign_op)) | syn::Stmt::Semi(syn::Expr::AssignOp(assign_op), _) = stmt.clone() {
             // Handle compound assignments like 'count += 1;'
-            if let syn::Expr::Path(path) = *assign_op.left {
-                if let Some(ident) = path.path.get_ident() {
-                    let var = ident.to_string();
-                    let right_expr = syn::Expr::Binary(syn::ExprBinary {
-                        attrs: vec![],
-                        left: Box::new(syn::Expr::Path(path.clone())),
-                        op: assign_op.op.clone(),
-                        right: assign_op.right.clone(),
-                    });
-                    // println!("Found compound assignment: {} = {:?}", var, right_expr);
-                    return Some((var, right_expr));
-                }
+            let right_expr = match Self::binary_op_of_compound(&assign_op.op) {
+                Some(op) => syn::Expr::Binary(syn::ExprBinary {
+                    attrs: vec![],
+                    left: assign_op.left.clone(),
+                    op,
+                    right: assign_op.right.clone(),
+                }),
+                None => syn::parse_str(HAVOC).expect("Failed to parse havoc value"),
+            };
+            if let Some(var) = Self::field_place_name(&assign_op.left) {
+                // println!("Found compound assignment: {} = {:?}", var, right_expr);
+                return Some((var, right_expr));
             }
+            return Self::element_assignment(&assign_op.left, right_expr);
         }
         // Handle 'let' like 'let mut sum = 0;'
         else if let syn::Stmt::Local(local) = stmt.clone() {
             if let syn::Pat::Ident(pat_ident) = &local.pat { // If we have an identifier (sum)
                 let var = pat_ident.ident.to_string(); // Take var identifier (string)
                 if let Some((_, expr)) = &local.init {
-                    return Some((var, *expr.clone())); // Return the id and literal it's initialized to (expr)
+                    return Some((var, Self::resolve_pure_calls(expr))); // Return the id and literal it's initialized to (expr)
                 }   
             }
         }
@@ -320,6 +580,72 @@ impl CfgBuilder {
         None
     }
 
+    // Name of a variable or of a field place reached from one ('self.len'), as used in the verification conditions
+    pub(crate) fn field_place_name(expr: &Expr) -> Option<String> {
+        match expr {
+            Expr::Path(expr_path) => expr_path.path.get_ident().map(|ident| ident.to_string()),
+            Expr::Field(field) => {
+                let member = match &field.member {
+                    syn::Member::Named(ident) => ident.to_string(),
+                    syn::Member::Unnamed(index) => index.index.to_string(),
+                };
+                Some(format!("{}.{}", Self::field_place_name(&field.base)?, member))
+            },
+            Expr::Paren(paren) => Self::field_place_name(&paren.expr),
+            // Writing through a reference ('*out = ..') writes the place it reaches
+            Expr::Unary(unary) if matches!(unary.op, syn::UnOp::Deref(_)) => Self::field_place_name(&unary.expr),
+            _ => None,
+        }
+    }
+
+    // Value given to a field in a struct expression ('Self { len: 0, cap: n }') or to a position in a
+    // tuple-struct or variant constructor ('Pair(a, 2)')
+    fn struct_field_value(expr: &Expr, member: &syn::Member) -> Option<Expr> {
+        let (_, fields) = Self::constructor_fields(expr)?;
+        fields.into_iter().find(|(field, _)| field == member).map(|(_, value)| value)
+    }
+
+    // Variant tests and payload accessors applied to a constructed 'Option' or 'Result': 'is_some(Some(v))'
+    // is 'true', 'Some(v).unwrap()' is 'v', 'None.unwrap_or(d)' is 'd'
+    fn variant_query(query: &str, value: &Expr, args: &[Expr]) -> Option<Expr> {
+        let (constructor, fields) = Self::constructor_fields(value)?;
+        let variant = constructor.rsplit("::").next().unwrap_or(&constructor);
+        let payload = fields.into_iter().next().map(|(_, payload)| Self::wrap_with_parens(payload));
+        match (query, variant) {
+            ("is_some", "Some") | ("is_none", "None") | ("is_ok", "Ok") | ("is_err", "Err") => Some(syn::parse_quote!(true)),
+            ("is_some", "None") | ("is_none", "Some") | ("is_ok", "Err") | ("is_err", "Ok") => Some(syn::parse_quote!(false)),
+            ("unwrap" | "expect" | "unwrap_or", "Some" | "Ok") => payload,
+            ("unwrap_err", "Err") => payload,
+            ("unwrap_or", "None" | "Err") => args.first().cloned().map(Self::wrap_with_parens),
+            _ => None,
+        }
+    }
+
+    // 'a[i] = v' assigns 'a' the array with 'v' at 'i', for the quantified conditions over its elements
+    fn element_assignment(place: &Expr, value: Expr) -> Option<(String, Expr)> {
+        let Expr::Index(index) = place else { return None };
+        let var = Self::field_place_name(&index.expr)?;
+        let store = syn::Ident::new(ARRAY_STORE, Span::call_site());
+        let (array, element) = (&index.expr, &index.index);
+        Some((var, syn::parse_quote!(#store(#array, #element, #value))))
+    }
+
+    // Operator applied by a compound assignment ('+=' applies '+')
+    fn binary_op_of_compound(op: &BinOp) -> Option<BinOp> {
+        match op {
+            BinOp::AddEq(t) => Some(BinOp::Add(syn::token::Add(t.spans[0]))),
+            BinOp::SubEq(t) => Some(BinOp::Sub(syn::token::Sub(t.spans[0]))),
+            BinOp::MulEq(t) => Some(BinOp::Mul(syn::token::Star(t.spans[0]))),
+            BinOp::DivEq(t) => Some(BinOp::Div(syn::token::Div(t.spans[0]))),
+            BinOp::RemEq(t) => Some(BinOp::Rem(syn::token::Rem(t.spans[0]))),
+            BinOp::BitXorEq(t) => Some(BinOp::BitXor(syn::token::Caret(t.spans[0]))),
+            BinOp::BitAndEq(t) => Some(BinOp::BitAnd(syn::token::And(t.spans[0]))),
+            BinOp::BitOrEq(t) => Some(BinOp::BitOr(syn::token::Or(t.spans[0]))),
+            // '>>' stands for implication in the conditions, so a shifted target is havocked instead
+            _ => None,
+        }
+    }
+
     fn print_expr_details(&self, expr: &Expr) {
         // println!("Expr details: {:#?}", expr);
     }
@@ -335,7 +661,13 @@ impl CfgBuilder {
         // Parse the string back into a TokenStream
         let replacement_token_stream: TokenStream = replacement_string.parse().expect("Failed to parse replacement string");
     
+        // The argument of 'old(..)' is kept: it names a value on entry
+        let mut after_old = false;
         tokens.clone().into_iter().flat_map(|tt| {
+            let keep = std::mem::replace(&mut after_old, matches!(&tt, TokenTree::Ident(ident) if ident == "old"));
+            if keep && matches!(&tt, TokenTree::Group(_)) {
+                return vec![tt.clone()].into_iter();
+            }
             // println!("{}", tt.to_string());
             // println!("var {}", var.to_string());
             match &tt {
@@ -366,3 +698,25 @@ impl CfgBuilder {
         }).collect()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::verdicts;
+    use crate::verifier::VcOutcome;
+
+    #[test]
+    fn shift_assignments_leave_the_target_unknown() {
+        let builder = CfgBuilder::new();
+        let (var, value) = builder.parse_assignment("x <<= 1;").unwrap();
+        assert_eq!((var.as_str(), quote!(#value).to_string()), ("x", HAVOC.to_string()));
+        // Kept as 'x >> 1', the shift would read as 'x implies 1' and prove anything
+        let outcomes = verdicts("fn halve(mut x: u32) -> u32 {
+            pre!(x == 8);
+            post!(x == 100);
+            x >>= 1;
+            return x;
+        }");
+        assert!(outcomes.iter().any(|outcome| matches!(outcome, VcOutcome::Counterexample(_))), "{:?}", outcomes);
+    }
+}